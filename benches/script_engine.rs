@@ -0,0 +1,67 @@
+//! Compares running a transformer script with a fresh engine per message
+//! against the prepared engines the simulation uses.
+//!
+//! Run with `cargo bench --bench script_engine`.
+
+use flowchart_tool::script_engine::JavaScriptEngine;
+use flowchart_tool::{execute_transformer_script_with_globals, Message};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+const MESSAGES: u32 = 500;
+
+const SCRIPT: &str = r#"
+function transform(input) {
+    state.count = (state.count || 0) + 1;
+    const total = input.items.reduce((sum, item) => sum + item.price * item.qty, 0);
+    return { id: input.id, total: total, seen: state.count };
+}
+"#;
+
+fn message(i: u32) -> Message {
+    Message::new(json!({
+        "id": i,
+        "items": [{"price": 2.5, "qty": 3}, {"price": 10, "qty": 1}, {"price": 0.75, "qty": 8}]
+    }))
+}
+
+/// What every message cost before engines were prepared: a new context and
+/// a fresh evaluation of the source.
+fn cold(messages: &[Message]) -> Duration {
+    let mut state = serde_json::Map::new();
+    let started = Instant::now();
+    for message in messages {
+        let mut engine = JavaScriptEngine::new().unwrap();
+        engine.set_global_json("state", &json!(state)).unwrap();
+        engine.execute_script(SCRIPT).unwrap();
+        engine.call_function("transform", (*message.data).clone()).unwrap();
+        if let Ok(serde_json::Value::Object(obj)) = engine.get_global_json("state") {
+            state = obj;
+        }
+    }
+    started.elapsed()
+}
+
+fn warm(messages: &[Message]) -> Duration {
+    let mut state = serde_json::Map::new();
+    let started = Instant::now();
+    for message in messages {
+        execute_transformer_script_with_globals(SCRIPT, message, &mut state).unwrap();
+    }
+    started.elapsed()
+}
+
+fn main() {
+    let messages: Vec<Message> = (0..MESSAGES).map(message).collect();
+    // Warm up the allocator and the prepared engine
+    cold(&messages[..10]);
+    warm(&messages[..10]);
+
+    let cold = cold(&messages);
+    let warm = warm(&messages);
+    let per_message = |d: Duration| d.as_secs_f64() * 1e6 / f64::from(MESSAGES);
+    println!("fresh engine per message: {:>9.1} µs/message", per_message(cold));
+    println!("prepared engine:          {:>9.1} µs/message", per_message(warm));
+    println!("speed-up:                 {:>9.1}x", cold.as_secs_f64() / warm.as_secs_f64());
+    assert!(warm < cold, "prepared engines should be faster than fresh ones");
+}
@@ -0,0 +1,19 @@
+//! Feeds arbitrary input to the loaders of files attached to a document:
+//! plugin manifests and exported run summaries used as baselines.
+
+#![no_main]
+
+use flowchart_tool::metrics::BaselineRun;
+use flowchart_tool::plugins::Plugin;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(json) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(plugin) = Plugin::from_manifest(json, None) {
+        let _ = plugin.node_type();
+        let _ = plugin.to_bundle_json();
+    }
+    let _ = BaselineRun::from_json("fuzz", json);
+});
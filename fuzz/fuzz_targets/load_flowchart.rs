@@ -0,0 +1,34 @@
+//! Feeds arbitrary input through every way a flowchart document is loaded:
+//! opening it, merging it into another document, and restoring saved app
+//! state. Malformed or hostile files must be rejected, never panic.
+
+#![no_main]
+
+use flowchart_tool::ui::FlowchartApp;
+use flowchart_tool::Flowchart;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(json) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    // Open (file, browser download or host page)
+    let mut app = FlowchartApp::default();
+    if app.load_document_json(json).is_ok() {
+        let _ = flowchart_tool::analysis::analyze(&app.flowchart);
+        let _ = app.document_json();
+    }
+
+    // Merge into an open document, here a copy of itself so every id clashes
+    if let Ok(other) = Flowchart::from_json(json) {
+        let mut flowchart = other.clone();
+        let _ = flowchart.merge(&other, (40.0, 40.0));
+    }
+
+    // Saved app state, restored on launch
+    if let Ok(mut app) = FlowchartApp::from_json(json) {
+        app.reset_non_ui_fields();
+        let _ = app.to_json();
+    }
+});
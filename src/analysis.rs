@@ -0,0 +1,907 @@
+//! Structural analysis of flowcharts.
+//!
+//! This module computes summary statistics about a flowchart's graph, such as
+//! node counts by type, the longest path through the graph, and its cyclomatic
+//! complexity. The results back the "About this flowchart" dialog and can also
+//! be used programmatically.
+
+use crate::types::*;
+use std::collections::{HashMap, HashSet};
+
+/// Summary statistics describing a flowchart's structure.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlowchartStats {
+    /// Number of producer nodes
+    pub producers: usize,
+    /// Number of consumer nodes
+    pub consumers: usize,
+    /// Number of transformer nodes
+    pub transformers: usize,
+    /// Number of reference nodes linking to other files
+    pub references: usize,
+    /// Number of connections
+    pub connections: usize,
+    /// Number of groups
+    pub groups: usize,
+    /// Number of connections on the longest path through the graph.
+    ///
+    /// Each cycle is collapsed into a single step for this measure.
+    pub longest_path: usize,
+    /// Whether the graph contains at least one cycle
+    pub has_cycles: bool,
+    /// Number of weakly connected components (islands) in the graph
+    pub components: usize,
+    /// Cyclomatic complexity of the graph: `E - N + 2P`
+    pub cyclomatic_complexity: i64,
+    /// Total size of all transformer scripts in bytes
+    pub total_script_bytes: usize,
+    /// Total number of lines across all transformer scripts
+    pub total_script_lines: usize,
+}
+
+impl FlowchartStats {
+    /// Total number of nodes of all types.
+    pub fn total_nodes(&self) -> usize {
+        self.producers + self.consumers + self.transformers + self.references
+    }
+}
+
+/// Computes structural statistics for the given flowchart.
+///
+/// # Arguments
+///
+/// * `flowchart` - The flowchart to analyze
+///
+/// # Returns
+///
+/// A `FlowchartStats` summary of the flowchart
+pub fn analyze(flowchart: &Flowchart) -> FlowchartStats {
+    let mut stats = FlowchartStats {
+        connections: flowchart.connections.len(),
+        groups: flowchart.groups.len(),
+        ..Default::default()
+    };
+
+    for node in flowchart.nodes.values() {
+        match &node.node_type {
+            NodeType::Producer { .. } => stats.producers += 1,
+            NodeType::Consumer { .. } => stats.consumers += 1,
+            NodeType::Transformer { script, .. } => {
+                stats.transformers += 1;
+                stats.total_script_bytes += script.len();
+                stats.total_script_lines += script.lines().count();
+            }
+            NodeType::Reference { .. } => stats.references += 1,
+        }
+    }
+
+    stats.longest_path = flowchart.layers().values().copied().max().unwrap_or(0);
+    stats.has_cycles = flowchart.has_cycle();
+    stats.components = weakly_connected_components(flowchart);
+    stats.cyclomatic_complexity = stats.connections as i64 - stats.total_nodes() as i64
+        + 2 * stats.components as i64;
+
+    stats
+}
+
+/// Which clean-ups a tidy pass should perform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TidyOptions {
+    /// Remove nodes that have no incoming or outgoing connections
+    pub remove_orphans: bool,
+    /// Collapse repeated connections between the same pair of nodes into one
+    pub merge_parallel_edges: bool,
+    /// Remove groups that have no remaining members
+    pub strip_empty_groups: bool,
+}
+
+impl Default for TidyOptions {
+    fn default() -> Self {
+        Self {
+            remove_orphans: true,
+            merge_parallel_edges: true,
+            strip_empty_groups: true,
+        }
+    }
+}
+
+/// The changes a tidy pass would make, computed without modifying the flowchart.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TidyPlan {
+    /// Nodes without any connections, sorted by id
+    pub orphan_nodes: Vec<NodeId>,
+    /// Indices of connections that repeat an earlier connection's endpoints, ascending
+    pub duplicate_connections: Vec<usize>,
+    /// Groups left without members (after orphan removal), sorted by id
+    pub empty_groups: Vec<GroupId>,
+}
+
+impl TidyPlan {
+    /// Returns true if the tidy pass would not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.orphan_nodes.is_empty()
+            && self.duplicate_connections.is_empty()
+            && self.empty_groups.is_empty()
+    }
+}
+
+/// Works out what a tidy pass with the given options would change.
+///
+/// # Arguments
+///
+/// * `flowchart` - The flowchart to inspect
+/// * `options` - Which clean-ups to include
+///
+/// # Returns
+///
+/// A `TidyPlan` listing the affected nodes, connections and groups
+pub fn plan_tidy(flowchart: &Flowchart, options: &TidyOptions) -> TidyPlan {
+    let mut plan = TidyPlan::default();
+
+    if options.remove_orphans {
+        let connected: HashSet<NodeId> = flowchart
+            .connections
+            .iter()
+            .flat_map(|c| [c.from, c.to])
+            .collect();
+        plan.orphan_nodes = flowchart
+            .nodes
+            .keys()
+            .filter(|id| !connected.contains(id))
+            .copied()
+            .collect();
+        plan.orphan_nodes.sort();
+    }
+
+    if options.merge_parallel_edges {
+        let mut seen: HashSet<(NodeId, NodeId)> = HashSet::new();
+        for (idx, conn) in flowchart.connections.iter().enumerate() {
+            if !seen.insert((conn.from, conn.to)) {
+                plan.duplicate_connections.push(idx);
+            }
+        }
+    }
+
+    if options.strip_empty_groups {
+        let removed: HashSet<NodeId> = plan.orphan_nodes.iter().copied().collect();
+        plan.empty_groups = flowchart
+            .groups
+            .values()
+            .filter(|g| {
+                !g.members
+                    .iter()
+                    .any(|m| flowchart.nodes.contains_key(m) && !removed.contains(m))
+            })
+            .map(|g| g.id)
+            .collect();
+        plan.empty_groups.sort();
+    }
+
+    plan
+}
+
+/// Settings for automatic grouping by community detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoGroupOptions {
+    /// Smallest community that is proposed as a group
+    pub min_size: usize,
+    /// Leave nodes that already belong to a group out of the analysis
+    pub skip_grouped: bool,
+}
+
+impl Default for AutoGroupOptions {
+    fn default() -> Self {
+        Self {
+            min_size: 2,
+            skip_grouped: true,
+        }
+    }
+}
+
+/// Proposes groups of densely connected nodes by community detection.
+///
+/// Connections are treated as undirected, with parallel connections adding
+/// weight. Every node starts in its own community and repeatedly moves to the
+/// neighbouring community that most improves modularity (the first phase of
+/// the Louvain method) until nothing moves. Nodes are visited in id order and
+/// ties keep the current community or go to the smallest label, so the result
+/// is deterministic for a given flowchart.
+///
+/// # Arguments
+///
+/// * `flowchart` - The flowchart to inspect
+/// * `options` - Community size and which nodes to consider
+///
+/// # Returns
+///
+/// The proposed communities, each sorted by node id, ordered by their first member
+pub fn plan_auto_groups(flowchart: &Flowchart, options: &AutoGroupOptions) -> Vec<Vec<NodeId>> {
+    let grouped: HashSet<NodeId> = if options.skip_grouped {
+        flowchart
+            .groups
+            .values()
+            .flat_map(|g| g.members.iter().copied())
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    let mut nodes: Vec<NodeId> = flowchart
+        .nodes
+        .keys()
+        .filter(|id| !grouped.contains(id))
+        .copied()
+        .collect();
+    nodes.sort();
+    let index: HashMap<NodeId, usize> = nodes.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    let mut neighbors: Vec<HashMap<usize, usize>> = vec![HashMap::new(); nodes.len()];
+    for conn in &flowchart.connections {
+        let (Some(&a), Some(&b)) = (index.get(&conn.from), index.get(&conn.to)) else {
+            continue;
+        };
+        if a == b {
+            continue;
+        }
+        *neighbors[a].entry(b).or_default() += 1;
+        *neighbors[b].entry(a).or_default() += 1;
+    }
+
+    // Local moving: each node joins the neighbouring community that most
+    // increases modularity, until a full pass moves nothing.
+    let degree: Vec<f64> = neighbors
+        .iter()
+        .map(|n| n.values().sum::<usize>() as f64)
+        .collect();
+    let two_m: f64 = degree.iter().sum();
+    let mut labels: Vec<usize> = (0..nodes.len()).collect();
+    let mut totals: Vec<f64> = degree.clone();
+    if two_m > 0.0 {
+        for _ in 0..crate::constants::AUTO_GROUP_MAX_ROUNDS {
+            let mut changed = false;
+            for node in 0..nodes.len() {
+                if neighbors[node].is_empty() {
+                    continue;
+                }
+                let current = labels[node];
+                totals[current] -= degree[node];
+                let mut links: HashMap<usize, f64> = HashMap::new();
+                for (&other, &weight) in &neighbors[node] {
+                    *links.entry(labels[other]).or_default() += weight as f64;
+                }
+                let gain = |label: usize| {
+                    links.get(&label).copied().unwrap_or(0.0) - totals[label] * degree[node] / two_m
+                };
+                let mut best = current;
+                let mut best_gain = gain(current);
+                let mut candidates: Vec<usize> = links.keys().copied().collect();
+                candidates.sort();
+                for label in candidates {
+                    let g = gain(label);
+                    if g > best_gain + f64::EPSILON {
+                        best = label;
+                        best_gain = g;
+                    }
+                }
+                totals[best] += degree[node];
+                if best != current {
+                    labels[node] = best;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    let mut communities: Vec<Vec<NodeId>> = Vec::new();
+    let mut by_label: HashMap<usize, usize> = HashMap::new();
+    for (node, label) in labels.iter().enumerate() {
+        let slot = *by_label.entry(*label).or_insert_with(|| {
+            communities.push(Vec::new());
+            communities.len() - 1
+        });
+        communities[slot].push(nodes[node]);
+    }
+    communities.retain(|c| c.len() >= options.min_size.max(1));
+    communities
+}
+
+/// Node positions and lane heights computed by the swimlane layout.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SwimlaneLayout {
+    /// New center position for every node
+    pub positions: Vec<(NodeId, (f32, f32))>,
+    /// Height for each lane, in lane order, grown where needed to fit its nodes
+    pub lane_heights: Vec<f32>,
+}
+
+/// Lays nodes out in their swimlanes, ordered left to right by dependency.
+///
+/// Each node's column is the length of the longest chain of connections
+/// leading to it, so every connection points rightwards except those closing
+/// a cycle. Nodes sharing a lane and column are stacked vertically, keeping
+/// their current top-to-bottom order. Unassigned nodes go in a band below
+/// the last lane.
+///
+/// # Arguments
+///
+/// * `flowchart` - The flowchart to lay out; lane assignments are read, not changed
+///
+/// # Returns
+///
+/// A `SwimlaneLayout` with a position for every node
+pub fn plan_swimlane_layout(flowchart: &Flowchart) -> SwimlaneLayout {
+    use crate::constants::{
+        LANE_COLUMN_SPACING, LANE_DEFAULT_HEIGHT, LANE_PADDING, LANE_ROW_SPACING, NODE_HEIGHT,
+        NODE_WIDTH,
+    };
+
+    let ranks = dependency_ranks(flowchart);
+    let lanes = &flowchart.swimlanes.lanes;
+    let unassigned = lanes.len();
+    let lane_index = |node: &FlowchartNode| {
+        node.lane
+            .and_then(|id| lanes.iter().position(|l| l.id == id))
+            .unwrap_or(unassigned)
+    };
+
+    // (lane index, column) -> nodes, in current vertical order
+    let mut cells: HashMap<(usize, usize), Vec<&FlowchartNode>> = HashMap::new();
+    for node in flowchart.nodes.values() {
+        let rank = ranks.get(&node.id).copied().unwrap_or(0);
+        cells.entry((lane_index(node), rank)).or_default().push(node);
+    }
+    for nodes in cells.values_mut() {
+        nodes.sort_by(|a, b| {
+            a.position
+                .1
+                .total_cmp(&b.position.1)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+    }
+
+    let mut rows = vec![0usize; lanes.len() + 1];
+    for ((lane, _), nodes) in &cells {
+        rows[*lane] = rows[*lane].max(nodes.len());
+    }
+    let stack_height = |count: usize| {
+        count as f32 * NODE_HEIGHT + count.saturating_sub(1) as f32 * LANE_ROW_SPACING
+    };
+    let heights: Vec<f32> = rows
+        .iter()
+        .map(|&r| (stack_height(r) + 2.0 * LANE_PADDING).max(LANE_DEFAULT_HEIGHT))
+        .collect();
+    let mut tops = Vec::with_capacity(heights.len());
+    let mut top = 0.0;
+    for (idx, height) in heights.iter().enumerate() {
+        if idx == unassigned && idx > 0 {
+            top += LANE_PADDING;
+        }
+        tops.push(top);
+        top += height;
+    }
+
+    let mut positions = Vec::with_capacity(flowchart.nodes.len());
+    for ((lane, rank), nodes) in &cells {
+        let x = LANE_PADDING + *rank as f32 * (NODE_WIDTH + LANE_COLUMN_SPACING) + NODE_WIDTH / 2.0;
+        let start = tops[*lane] + (heights[*lane] - stack_height(nodes.len())) / 2.0;
+        for (row, node) in nodes.iter().enumerate() {
+            let y = start + row as f32 * (NODE_HEIGHT + LANE_ROW_SPACING) + NODE_HEIGHT / 2.0;
+            positions.push((node.id, (x, y)));
+        }
+    }
+    positions.sort_by_key(|(id, _)| *id);
+
+    SwimlaneLayout {
+        positions,
+        lane_heights: heights[..unassigned].to_vec(),
+    }
+}
+
+/// Nudges nodes apart to make room for newly inserted ones.
+///
+/// Any node overlapping an inserted node, with [`MAKE_ROOM_GAP`] to spare, is
+/// pushed out along the axis that needs the shorter move. Nodes pushed into
+/// others pass the push on, up to [`MAKE_ROOM_MAX_PASSES`] times. Inserted and
+/// pinned nodes never move.
+///
+/// [`MAKE_ROOM_GAP`]: crate::constants::MAKE_ROOM_GAP
+/// [`MAKE_ROOM_MAX_PASSES`]: crate::constants::MAKE_ROOM_MAX_PASSES
+///
+/// # Arguments
+///
+/// * `flowchart` - The flowchart the nodes were inserted into
+/// * `inserted` - The new nodes
+///
+/// # Returns
+///
+/// The new position of every node that moved, sorted by id
+pub fn plan_make_room(flowchart: &Flowchart, inserted: &[NodeId]) -> Vec<(NodeId, (f32, f32))> {
+    use crate::constants::{MAKE_ROOM_GAP, MAKE_ROOM_MAX_PASSES, NODE_HEIGHT, NODE_WIDTH};
+
+    let mut positions: HashMap<NodeId, (f32, f32)> =
+        flowchart.nodes.values().map(|n| (n.id, n.position)).collect();
+    let fixed = |id: &NodeId| {
+        inserted.contains(id) || flowchart.nodes.get(id).is_some_and(|n| n.pinned)
+    };
+    let mut ids: Vec<NodeId> = positions.keys().copied().collect();
+    ids.sort();
+
+    // Nodes whose neighbours may need to move this pass
+    let mut active: Vec<NodeId> = inserted.iter().copied().filter(|id| positions.contains_key(id)).collect();
+    let mut moved: HashSet<NodeId> = HashSet::new();
+    for _ in 0..MAKE_ROOM_MAX_PASSES {
+        let mut next: Vec<NodeId> = Vec::new();
+        for pusher in &active {
+            for other in &ids {
+                if other == pusher || fixed(other) {
+                    continue;
+                }
+                let (a, b) = (positions[pusher], positions[other]);
+                let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                let overlap_x = NODE_WIDTH + MAKE_ROOM_GAP - dx.abs();
+                let overlap_y = NODE_HEIGHT + MAKE_ROOM_GAP - dy.abs();
+                if overlap_x <= 0.0 || overlap_y <= 0.0 {
+                    continue;
+                }
+                let push = if overlap_x <= overlap_y {
+                    (if dx < 0.0 { -overlap_x } else { overlap_x }, 0.0)
+                } else {
+                    (0.0, if dy < 0.0 { -overlap_y } else { overlap_y })
+                };
+                positions.insert(*other, (b.0 + push.0, b.1 + push.1));
+                moved.insert(*other);
+                if !next.contains(other) {
+                    next.push(*other);
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        active = next;
+    }
+
+    let mut result: Vec<(NodeId, (f32, f32))> = moved
+        .into_iter()
+        .map(|id| (id, positions[&id]))
+        .filter(|(id, position)| flowchart.nodes[id].position != *position)
+        .collect();
+    result.sort_by_key(|(id, _)| *id);
+    result
+}
+
+/// Lines up connected nodes so their connections run straight.
+///
+/// Among `ids`, nodes joined by a connection that is closer to horizontal
+/// than vertical, and off by at most [`AXIS_ALIGN_MAX_NUDGE`], end up on one
+/// row; nearly vertical connections put their nodes in one column. Each row
+/// or column takes the position of its first pinned node, or else the
+/// average of its nodes. Pinned nodes never move.
+///
+/// [`AXIS_ALIGN_MAX_NUDGE`]: crate::constants::AXIS_ALIGN_MAX_NUDGE
+///
+/// # Returns
+///
+/// The new position of every node that moved, sorted by id
+pub fn plan_axis_alignment(flowchart: &Flowchart, ids: &[NodeId]) -> Vec<(NodeId, (f32, f32))> {
+    use crate::constants::AXIS_ALIGN_MAX_NUDGE;
+
+    fn find(parent: &mut HashMap<NodeId, NodeId>, id: NodeId) -> NodeId {
+        let up = *parent.get(&id).unwrap_or(&id);
+        if up == id {
+            return id;
+        }
+        let root = find(parent, up);
+        parent.insert(id, root);
+        root
+    }
+
+    let included: HashSet<NodeId> = ids.iter().copied().filter(|id| flowchart.nodes.contains_key(id)).collect();
+    let mut rows: HashMap<NodeId, NodeId> = HashMap::new();
+    let mut columns: HashMap<NodeId, NodeId> = HashMap::new();
+    for conn in &flowchart.connections {
+        if conn.from == conn.to || !included.contains(&conn.from) || !included.contains(&conn.to) {
+            continue;
+        }
+        let (a, b) = (flowchart.nodes[&conn.from].position, flowchart.nodes[&conn.to].position);
+        let (dx, dy) = ((b.0 - a.0).abs(), (b.1 - a.1).abs());
+        let sets = if dy <= dx && dy <= AXIS_ALIGN_MAX_NUDGE {
+            &mut rows
+        } else if dx < dy && dx <= AXIS_ALIGN_MAX_NUDGE {
+            &mut columns
+        } else {
+            continue;
+        };
+        let (ra, rb) = (find(sets, conn.from), find(sets, conn.to));
+        if ra != rb {
+            sets.insert(ra.max(rb), ra.min(rb));
+        }
+    }
+
+    let mut positions: HashMap<NodeId, (f32, f32)> = HashMap::new();
+    for (mut sets, horizontal) in [(rows, true), (columns, false)] {
+        let mut members: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        let mut all: Vec<NodeId> = sets.keys().copied().collect();
+        all.sort();
+        for id in all {
+            let root = find(&mut sets, id);
+            members.entry(root).or_default().push(id);
+        }
+        for (root, mut group) in members {
+            if !group.contains(&root) {
+                group.push(root);
+            }
+            group.sort();
+            let coord = |id: &NodeId| {
+                let p = flowchart.nodes[id].position;
+                if horizontal { p.1 } else { p.0 }
+            };
+            let target = group
+                .iter()
+                .find(|id| flowchart.nodes[*id].pinned)
+                .map(coord)
+                .unwrap_or_else(|| group.iter().map(coord).sum::<f32>() / group.len() as f32);
+            for id in group.iter().filter(|id| !flowchart.nodes[*id].pinned) {
+                let entry = positions.entry(*id).or_insert(flowchart.nodes[id].position);
+                if horizontal {
+                    entry.1 = target;
+                } else {
+                    entry.0 = target;
+                }
+            }
+        }
+    }
+
+    let mut result: Vec<(NodeId, (f32, f32))> = positions
+        .into_iter()
+        .filter(|(id, position)| flowchart.nodes[id].position != *position)
+        .collect();
+    result.sort_by_key(|(id, _)| *id);
+    result
+}
+
+/// Longest-path column for every node.
+///
+/// Cycles are broken at the node with the fewest unresolved inputs, preferring
+/// producers and then the smallest id.
+fn dependency_ranks(flowchart: &Flowchart) -> HashMap<NodeId, usize> {
+    let mut remaining: Vec<NodeId> = flowchart.nodes.keys().copied().collect();
+    remaining.sort();
+    let mut indegree: HashMap<NodeId, usize> = HashMap::new();
+    let mut successors: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+    for conn in &flowchart.connections {
+        if conn.from == conn.to
+            || !flowchart.nodes.contains_key(&conn.from)
+            || !flowchart.nodes.contains_key(&conn.to)
+        {
+            continue;
+        }
+        *indegree.entry(conn.to).or_default() += 1;
+        successors.entry(conn.from).or_default().push(conn.to);
+    }
+
+    let mut ranks: HashMap<NodeId, usize> = HashMap::new();
+    while !remaining.is_empty() {
+        let pick = remaining
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, id)| {
+                let is_producer = matches!(
+                    flowchart.nodes.get(id).map(|n| &n.node_type),
+                    Some(NodeType::Producer { .. })
+                );
+                (indegree.get(id).copied().unwrap_or(0), !is_producer)
+            })
+            .map(|(idx, _)| idx)
+            .unwrap_or(0);
+        let id = remaining.remove(pick);
+        let rank = *ranks.entry(id).or_default();
+        for next in successors.get(&id).into_iter().flatten() {
+            if remaining.contains(next) {
+                let entry = ranks.entry(*next).or_default();
+                *entry = (*entry).max(rank + 1);
+                if let Some(d) = indegree.get_mut(next) {
+                    *d = d.saturating_sub(1);
+                }
+            }
+        }
+    }
+    ranks
+}
+
+/// Counts weakly connected components, treating connections as undirected.
+fn weakly_connected_components(flowchart: &Flowchart) -> usize {
+    let mut neighbors: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+    for conn in &flowchart.connections {
+        neighbors.entry(conn.from).or_default().push(conn.to);
+        neighbors.entry(conn.to).or_default().push(conn.from);
+    }
+
+    let mut seen: HashSet<NodeId> = HashSet::new();
+    let mut components = 0;
+    for id in flowchart.nodes.keys() {
+        if !seen.insert(*id) {
+            continue;
+        }
+        components += 1;
+        let mut stack = vec![*id];
+        while let Some(cur) = stack.pop() {
+            if let Some(next) = neighbors.get(&cur) {
+                for n in next {
+                    if flowchart.nodes.contains_key(n) && seen.insert(*n) {
+                        stack.push(*n);
+                    }
+                }
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn producer() -> NodeType {
+        NodeType::Producer {
+            message_template: json!({}),
+            start_step: 0,
+            messages_per_cycle: 1,
+            steps_between_cycles: 1,
+            messages_produced: 0,
+        }
+    }
+
+    fn consumer() -> NodeType {
+        NodeType::Consumer {
+            consumption_rate: 1,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        }
+    }
+
+    fn transformer(script: &str) -> NodeType {
+        NodeType::Transformer {
+            script: script.to_string(),
+            selected_outputs: None,
+            globals: Default::default(),
+            initial_globals: Default::default(),
+            retry: Default::default(),
+        }
+    }
+
+    fn add(fc: &mut Flowchart, name: &str, node_type: NodeType) -> NodeId {
+        fc.add_node(FlowchartNode::new(name.into(), (0.0, 0.0), node_type))
+    }
+
+    #[test]
+    fn test_analyze_empty_flowchart() {
+        let stats = analyze(&Flowchart::new());
+        assert_eq!(stats, FlowchartStats::default());
+    }
+
+    #[test]
+    fn test_analyze_linear_pipeline() {
+        let mut fc = Flowchart::new();
+        let p = add(&mut fc, "P", producer());
+        let t = add(&mut fc, "T", transformer("function transform(m) {\n  return m;\n}"));
+        let c = add(&mut fc, "C", consumer());
+        fc.add_connection(p, t).unwrap();
+        fc.add_connection(t, c).unwrap();
+
+        let stats = analyze(&fc);
+        assert_eq!(stats.producers, 1);
+        assert_eq!(stats.transformers, 1);
+        assert_eq!(stats.consumers, 1);
+        assert_eq!(stats.total_nodes(), 3);
+        assert_eq!(stats.connections, 2);
+        assert_eq!(stats.longest_path, 2);
+        assert!(!stats.has_cycles);
+        assert_eq!(stats.components, 1);
+        // 2 - 3 + 2 * 1
+        assert_eq!(stats.cyclomatic_complexity, 1);
+        assert_eq!(stats.total_script_lines, 3);
+        assert_eq!(stats.total_script_bytes, 37);
+    }
+
+    #[test]
+    fn test_analyze_detects_cycles_and_islands() {
+        let mut fc = Flowchart::new();
+        let a = add(&mut fc, "A", transformer(""));
+        let b = add(&mut fc, "B", transformer(""));
+        let _lone = add(&mut fc, "Lone", consumer());
+        fc.add_connection(a, b).unwrap();
+        fc.add_connection(b, a).unwrap();
+
+        let stats = analyze(&fc);
+        assert!(stats.has_cycles);
+        assert_eq!(stats.components, 2);
+        // 2 - 3 + 2 * 2
+        assert_eq!(stats.cyclomatic_complexity, 3);
+        assert_eq!(stats.longest_path, 0);
+    }
+
+    #[test]
+    fn test_plan_tidy_finds_orphans_duplicates_and_empty_groups() {
+        let mut fc = Flowchart::new();
+        let p = add(&mut fc, "P", producer());
+        let c = add(&mut fc, "C", consumer());
+        let lone = add(&mut fc, "Lone", consumer());
+        fc.add_connection(p, c).unwrap();
+        fc.add_connection(p, c).unwrap();
+        fc.add_connection(p, c).unwrap();
+
+        let lone_group = Group {
+            id: uuid::Uuid::new_v4(),
+            name: "Only orphan".into(),
+            members: vec![lone],
+            drawing: Default::default(),
+            z_order: 0,
+        };
+        let kept_group = Group {
+            id: uuid::Uuid::new_v4(),
+            name: "Kept".into(),
+            members: vec![p, lone],
+            drawing: Default::default(),
+            z_order: 0,
+        };
+        fc.groups.insert(lone_group.id, lone_group.clone());
+        fc.groups.insert(kept_group.id, kept_group);
+
+        let plan = plan_tidy(&fc, &TidyOptions::default());
+        assert_eq!(plan.orphan_nodes, vec![lone]);
+        assert_eq!(plan.duplicate_connections, vec![1, 2]);
+        assert_eq!(plan.empty_groups, vec![lone_group.id]);
+        assert!(!plan.is_empty());
+
+        let none = TidyOptions {
+            remove_orphans: false,
+            merge_parallel_edges: false,
+            strip_empty_groups: true,
+        };
+        // Without orphan removal the lone node keeps its group alive
+        assert!(plan_tidy(&fc, &none).is_empty());
+    }
+
+    #[test]
+    fn test_plan_auto_groups_splits_loosely_joined_clusters() {
+        let mut fc = Flowchart::new();
+        let left: Vec<NodeId> = (0..3).map(|i| add(&mut fc, &format!("L{}", i), transformer(""))).collect();
+        let right: Vec<NodeId> = (0..3).map(|i| add(&mut fc, &format!("R{}", i), transformer(""))).collect();
+        for side in [&left, &right] {
+            fc.add_connection(side[0], side[1]).unwrap();
+            fc.add_connection(side[1], side[2]).unwrap();
+            fc.add_connection(side[2], side[0]).unwrap();
+        }
+        fc.add_connection(left[0], right[0]).unwrap();
+        let _lone = add(&mut fc, "Lone", consumer());
+
+        let mut groups = plan_auto_groups(&fc, &AutoGroupOptions::default());
+        for group in &mut groups {
+            group.sort();
+        }
+        let mut expected = [left.clone(), right.clone()];
+        for group in &mut expected {
+            group.sort();
+        }
+        assert_eq!(groups.len(), 2);
+        assert!(expected.iter().all(|e| groups.contains(e)));
+        let again = plan_auto_groups(&fc, &AutoGroupOptions::default());
+        assert_eq!(again, groups);
+
+        // Nodes already grouped are left alone
+        let group = Group {
+            id: uuid::Uuid::new_v4(),
+            name: "Left".into(),
+            members: left.clone(),
+            drawing: Default::default(),
+            z_order: 0,
+        };
+        fc.groups.insert(group.id, group);
+        let remaining = plan_auto_groups(&fc, &AutoGroupOptions::default());
+        assert_eq!(remaining, vec![expected[1].clone()]);
+    }
+
+    #[test]
+    fn test_plan_swimlane_layout_keeps_lanes_and_orders_by_dependency() {
+        let mut fc = Flowchart::new();
+        let ops = Lane::new("Ops".into());
+        let billing = Lane::new("Billing".into());
+        fc.swimlanes.lanes = vec![ops.clone(), billing.clone()];
+        let p = add(&mut fc, "P", producer());
+        let t = add(&mut fc, "T", transformer(""));
+        let c1 = add(&mut fc, "C1", consumer());
+        let c2 = add(&mut fc, "C2", consumer());
+        let loose = add(&mut fc, "Loose", consumer());
+        for (id, lane) in [(p, ops.id), (t, billing.id), (c1, billing.id), (c2, billing.id)] {
+            fc.nodes.get_mut(&id).unwrap().lane = Some(lane);
+        }
+        fc.nodes.get_mut(&c2).unwrap().position.1 = 10.0;
+        fc.add_connection(p, t).unwrap();
+        fc.add_connection(t, c1).unwrap();
+        fc.add_connection(t, c2).unwrap();
+        fc.add_connection(c1, p).unwrap();
+
+        let layout = plan_swimlane_layout(&fc);
+        fc.swimlanes.lanes[0].height = layout.lane_heights[0];
+        fc.swimlanes.lanes[1].height = layout.lane_heights[1];
+        let pos: HashMap<NodeId, (f32, f32)> = layout.positions.iter().copied().collect();
+        assert_eq!(pos.len(), 5);
+
+        for (id, lane) in [(p, ops.id), (t, billing.id), (c1, billing.id), (c2, billing.id)] {
+            assert_eq!(fc.swimlanes.lane_at(pos[&id].1), Some(lane));
+        }
+        assert_eq!(fc.swimlanes.lane_at(pos[&loose].1), None);
+        // Dependency order left to right, the cycle broken at the producer
+        assert!(pos[&p].0 < pos[&t].0 && pos[&t].0 < pos[&c1].0);
+        assert_eq!(pos[&c1].0, pos[&c2].0);
+        // Stacked consumers keep their previous vertical order
+        assert!(pos[&c1].1 < pos[&c2].1);
+
+        // Laying out again changes nothing
+        for (id, p) in &layout.positions {
+            fc.nodes.get_mut(id).unwrap().position = *p;
+        }
+        assert_eq!(plan_swimlane_layout(&fc), layout);
+    }
+
+    #[test]
+    fn test_plan_make_room_pushes_neighbours_but_not_pinned_nodes() {
+        use crate::constants::{MAKE_ROOM_GAP, NODE_WIDTH};
+        let mut fc = Flowchart::new();
+        let new = add(&mut fc, "New", consumer());
+        let right = add(&mut fc, "Right", consumer());
+        let further = add(&mut fc, "Further", consumer());
+        let pinned = add(&mut fc, "Pinned", consumer());
+        let far = add(&mut fc, "Far", consumer());
+        fc.nodes.get_mut(&right).unwrap().position = (30.0, 0.0);
+        fc.nodes.get_mut(&further).unwrap().position = (150.0, 0.0);
+        fc.nodes.get_mut(&pinned).unwrap().position = (-20.0, 10.0);
+        fc.nodes.get_mut(&pinned).unwrap().pinned = true;
+        fc.nodes.get_mut(&far).unwrap().position = (0.0, 500.0);
+
+        let moves: HashMap<NodeId, (f32, f32)> = plan_make_room(&fc, &[new]).into_iter().collect();
+        let spacing = NODE_WIDTH + MAKE_ROOM_GAP;
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[&right], (spacing, 0.0));
+        // The push is passed on to the node the first one was pushed into
+        assert_eq!(moves[&further], (2.0 * spacing, 0.0));
+        assert!(!moves.contains_key(&pinned) && !moves.contains_key(&far));
+
+        assert!(plan_make_room(&fc, &[far]).is_empty());
+    }
+
+    #[test]
+    fn test_plan_axis_alignment_straightens_shallow_connections() {
+        let mut fc = Flowchart::new();
+        let a = add(&mut fc, "A", producer());
+        let b = add(&mut fc, "B", transformer(""));
+        let c = add(&mut fc, "C", consumer());
+        let below = add(&mut fc, "Below", consumer());
+        let steep = add(&mut fc, "Steep", consumer());
+        for (id, position) in [(a, (0.0, 0.0)), (b, (200.0, 30.0)), (c, (400.0, 60.0)), (below, (190.0, 250.0)), (steep, (300.0, 300.0))] {
+            fc.nodes.get_mut(&id).unwrap().position = position;
+        }
+        fc.add_connection(a, b).unwrap();
+        fc.add_connection(b, c).unwrap();
+        fc.add_connection(b, below).unwrap();
+        fc.add_connection(a, steep).unwrap();
+        fc.nodes.get_mut(&c).unwrap().pinned = true;
+        let all: Vec<NodeId> = fc.nodes.keys().copied().collect();
+
+        let moves: HashMap<NodeId, (f32, f32)> = plan_axis_alignment(&fc, &all).into_iter().collect();
+        // The row takes the pinned node's height; the column the average x
+        assert_eq!(moves[&a], (0.0, 60.0));
+        assert_eq!(moves[&b], (195.0, 60.0));
+        assert_eq!(moves[&below], (195.0, 250.0));
+        assert!(!moves.contains_key(&c) && !moves.contains_key(&steep));
+
+        // Only the given nodes are aligned
+        assert!(plan_axis_alignment(&fc, &[a, steep]).is_empty());
+    }
+}
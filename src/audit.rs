@@ -0,0 +1,93 @@
+//! Audit trail of document edits.
+//!
+//! Every edit that goes into the undo history is also appended to the
+//! document's edit log as an [`EditRecord`]: when it was made, by whom, and a
+//! one-line description such as "Renamed node Orders to Incoming orders".
+//! Undoing and redoing append records of their own, so the log only ever
+//! grows. It is saved with the document, for teams that need to trace how a
+//! model changed.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One edit in the document's edit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct EditRecord {
+    /// When the edit was made, in seconds since the Unix epoch
+    pub at: f64,
+    /// Who made it, as set in Preferences; empty if unknown
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub author: String,
+    /// What changed
+    pub action: String,
+}
+
+/// The current time in seconds since the Unix epoch.
+pub fn now() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now() / 1000.0
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0.0, |elapsed| elapsed.as_secs_f64())
+    }
+}
+
+/// The name edits are recorded under until one is set: the login name on
+/// the desktop, nothing in the browser.
+pub fn default_author() -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_default()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        String::new()
+    }
+}
+
+/// Formats a time from [`now`] as a UTC date and time, e.g. "2024-03-09 14:05 UTC".
+pub fn format_time(at: f64) -> String {
+    format!("{} UTC", utc_date_time(at.max(0.0) as i64))
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD HH:MM` (UTC).
+pub fn utc_date_time(secs: i64) -> String {
+    let (days, of_day) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_format_as_utc_dates() {
+        assert_eq!(format_time(0.0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_time(951_782_400.0 + 3_661.0), "2000-02-29 01:01 UTC");
+        assert_eq!(format_time(1_709_993_100.0), "2024-03-09 14:05 UTC");
+        assert!(now() > 1_700_000_000.0);
+    }
+}
@@ -0,0 +1,594 @@
+//! Conversion to and from a subset of BPMN 2.0.
+//!
+//! [`to_bpmn`] writes a process model other BPMN tools can open: producers
+//! become start events, consumers end events, transformers script tasks
+//! carrying their JavaScript, and reference nodes call activities naming the
+//! linked file. Connections become sequence flows, and each group becomes a
+//! pool (a participant with a process of its own), with flows between pools
+//! written as message flows. Diagram shapes keep the canvas layout.
+//!
+//! [`from_bpmn`] reads simple process diagrams back. Activities, gateways and
+//! intermediate events all become transformers with the default script
+//! unless they are JavaScript script tasks, named pools become groups, and
+//! nodes without diagram shapes are placed in a row below the rest.
+//! Simulation settings, such as producer templates and consumer rates, are
+//! not part of BPMN and take their defaults.
+
+use crate::constants::{DEFAULT_TRANSFORMER_SCRIPT, GROUP_PADDING, NODE_HEIGHT, NODE_WIDTH, PIPELINE_NODE_SPACING};
+use crate::types::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use uuid::Uuid;
+
+/// Namespace of BPMN process model elements
+const MODEL_NS: &str = "http://www.omg.org/spec/BPMN/20100524/MODEL";
+/// Namespace of BPMN diagram elements
+const BPMNDI_NS: &str = "http://www.omg.org/spec/BPMN/20100524/DI";
+/// Namespace of diagram bounds
+const DC_NS: &str = "http://www.omg.org/spec/DD/20100524/DC";
+/// Namespace of diagram waypoints
+const DI_NS: &str = "http://www.omg.org/spec/DD/20100524/DI";
+
+/// Diameter of start and end event shapes in world units
+const EVENT_SIZE: f32 = 36.0;
+
+/// Writes `flowchart` as a BPMN 2.0 XML document.
+///
+/// Nodes in several groups are placed in the pool of the first group by id.
+pub fn to_bpmn(flowchart: &Flowchart) -> String {
+    let mut nodes: Vec<&FlowchartNode> = flowchart.nodes.values().collect();
+    nodes.sort_by_key(|node| node.id);
+    let mut groups: Vec<&Group> = flowchart.groups.values().collect();
+    groups.sort_by_key(|group| group.id);
+
+    // One process per pool; without groups everything shares a single process
+    let mut pool_of: HashMap<NodeId, usize> = HashMap::new();
+    for (index, group) in groups.iter().enumerate() {
+        for member in &group.members {
+            if flowchart.nodes.contains_key(member) {
+                pool_of.entry(*member).or_insert(index);
+            }
+        }
+    }
+    let ungrouped = groups.len();
+    let process_ids: Vec<String> = groups
+        .iter()
+        .map(|group| format!("Process_{}", group.id))
+        .chain(std::iter::once("Process_1".to_string()))
+        .collect();
+    let process_of = |id: &NodeId| pool_of.get(id).copied().unwrap_or(ungrouped);
+    let has_ungrouped = nodes.iter().any(|node| !pool_of.contains_key(&node.id));
+
+    // Flows between nodes of one process are sequence flows, the rest message flows
+    let flows: Vec<(String, &Connection, bool)> = flowchart
+        .connections
+        .iter()
+        .filter(|conn| flowchart.nodes.contains_key(&conn.from) && flowchart.nodes.contains_key(&conn.to))
+        .enumerate()
+        .map(|(index, conn)| {
+            let same_process = process_of(&conn.from) == process_of(&conn.to);
+            (format!("Flow_{}", index + 1), conn, same_process)
+        })
+        .collect();
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<bpmn:definitions xmlns:bpmn=\"{}\" xmlns:bpmndi=\"{}\" xmlns:dc=\"{}\" xmlns:di=\"{}\" id=\"Definitions_1\" targetNamespace=\"urn:flowchart-tool\">",
+        MODEL_NS, BPMNDI_NS, DC_NS, DI_NS
+    );
+
+    let collaboration = !groups.is_empty();
+    if collaboration {
+        xml.push_str("  <bpmn:collaboration id=\"Collaboration_1\">\n");
+        for (index, group) in groups.iter().enumerate() {
+            let _ = writeln!(
+                xml,
+                "    <bpmn:participant id=\"Participant_{}\" name=\"{}\" processRef=\"{}\" />",
+                group.id,
+                escape(&group.name),
+                process_ids[index]
+            );
+        }
+        if has_ungrouped {
+            let _ = writeln!(
+                xml,
+                "    <bpmn:participant id=\"Participant_1\" processRef=\"{}\" />",
+                process_ids[ungrouped]
+            );
+        }
+        for (flow_id, conn, _) in flows.iter().filter(|(_, _, same)| !same) {
+            let _ = writeln!(
+                xml,
+                "    <bpmn:messageFlow id=\"{}\" sourceRef=\"{}\" targetRef=\"{}\" />",
+                flow_id,
+                element_id(&conn.from),
+                element_id(&conn.to)
+            );
+        }
+        xml.push_str("  </bpmn:collaboration>\n");
+    }
+
+    for (index, process_id) in process_ids.iter().enumerate() {
+        if index == ungrouped && collaboration && !has_ungrouped {
+            continue;
+        }
+        let _ = writeln!(xml, "  <bpmn:process id=\"{}\" isExecutable=\"false\">", process_id);
+        for node in nodes.iter().filter(|node| process_of(&node.id) == index) {
+            write_flow_node(&mut xml, node);
+        }
+        for (flow_id, conn, _) in flows
+            .iter()
+            .filter(|(_, conn, same)| *same && process_of(&conn.from) == index)
+        {
+            let _ = writeln!(
+                xml,
+                "    <bpmn:sequenceFlow id=\"{}\" sourceRef=\"{}\" targetRef=\"{}\" />",
+                flow_id,
+                element_id(&conn.from),
+                element_id(&conn.to)
+            );
+        }
+        xml.push_str("  </bpmn:process>\n");
+    }
+
+    // Diagram interchange: shapes for pools and nodes, edges for flows
+    let plane = if collaboration { "Collaboration_1" } else { "Process_1" };
+    xml.push_str("  <bpmndi:BPMNDiagram id=\"Diagram_1\">\n");
+    let _ = writeln!(xml, "    <bpmndi:BPMNPlane id=\"Plane_1\" bpmnElement=\"{}\">", plane);
+    if collaboration {
+        let pools = groups
+            .iter()
+            .map(|group| format!("Participant_{}", group.id))
+            .chain(has_ungrouped.then(|| "Participant_1".to_string()));
+        for (index, participant) in pools.enumerate() {
+            let members = nodes.iter().filter(|node| process_of(&node.id) == index);
+            let Some((min_x, min_y, max_x, max_y)) = bounds(members) else {
+                continue;
+            };
+            let _ = writeln!(
+                xml,
+                "      <bpmndi:BPMNShape id=\"{0}_di\" bpmnElement=\"{0}\" isHorizontal=\"true\">",
+                participant
+            );
+            write_bounds(
+                &mut xml,
+                min_x - GROUP_PADDING,
+                min_y - GROUP_PADDING,
+                max_x - min_x + 2.0 * GROUP_PADDING,
+                max_y - min_y + 2.0 * GROUP_PADDING,
+            );
+            xml.push_str("      </bpmndi:BPMNShape>\n");
+        }
+    }
+    for node in &nodes {
+        let (width, height) = shape_size(&node.node_type);
+        let _ = writeln!(
+            xml,
+            "      <bpmndi:BPMNShape id=\"{0}_di\" bpmnElement=\"{0}\">",
+            element_id(&node.id)
+        );
+        write_bounds(
+            &mut xml,
+            node.position.0 - width / 2.0,
+            node.position.1 - height / 2.0,
+            width,
+            height,
+        );
+        xml.push_str("      </bpmndi:BPMNShape>\n");
+    }
+    for (flow_id, conn, _) in &flows {
+        let from = flowchart.nodes[&conn.from].position;
+        let to = flowchart.nodes[&conn.to].position;
+        let _ = writeln!(xml, "      <bpmndi:BPMNEdge id=\"{0}_di\" bpmnElement=\"{0}\">", flow_id);
+        let _ = writeln!(xml, "        <di:waypoint x=\"{}\" y=\"{}\" />", from.0, from.1);
+        let _ = writeln!(xml, "        <di:waypoint x=\"{}\" y=\"{}\" />", to.0, to.1);
+        xml.push_str("      </bpmndi:BPMNEdge>\n");
+    }
+    xml.push_str("    </bpmndi:BPMNPlane>\n");
+    xml.push_str("  </bpmndi:BPMNDiagram>\n");
+    xml.push_str("</bpmn:definitions>\n");
+    xml
+}
+
+/// Reads the processes of a BPMN 2.0 XML document into a new flowchart.
+pub fn from_bpmn(xml: &str) -> Result<Flowchart, String> {
+    let document = roxmltree::Document::parse(xml).map_err(|e| format!("Failed to parse BPMN: {}", e))?;
+    let root = document.root_element();
+    if !root.has_tag_name((MODEL_NS, "definitions")) {
+        return Err("Not a BPMN 2.0 file: the root element is not bpmn:definitions".to_string());
+    }
+
+    // Diagram shape centers, keyed by the id of the element they show
+    let mut centers: HashMap<&str, (f32, f32)> = HashMap::new();
+    for shape in root
+        .descendants()
+        .filter(|n| n.has_tag_name((BPMNDI_NS, "BPMNShape")))
+    {
+        let element = shape.attribute("bpmnElement");
+        let bounds = shape.children().find(|n| n.has_tag_name((DC_NS, "Bounds")));
+        if let (Some(element), Some(bounds)) = (element, bounds) {
+            let number = |name| bounds.attribute(name).and_then(|v: &str| v.parse::<f32>().ok());
+            if let (Some(x), Some(y), Some(w), Some(h)) =
+                (number("x"), number("y"), number("width"), number("height"))
+            {
+                centers.insert(element, (x + w / 2.0, y + h / 2.0));
+            }
+        }
+    }
+
+    let mut flowchart = Flowchart::new();
+    let mut ids: HashMap<&str, NodeId> = HashMap::new();
+    let mut process_nodes: HashMap<&str, Vec<NodeId>> = HashMap::new();
+    let mut flows: Vec<(&str, &str)> = Vec::new();
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut unplaced: Vec<NodeId> = Vec::new();
+
+    for process in root.children().filter(|n| n.has_tag_name((MODEL_NS, "process"))) {
+        let process_id = process.attribute("id").unwrap_or_default();
+        let elements: Vec<_> = process
+            .children()
+            .filter(|n| n.tag_name().namespace() == Some(MODEL_NS))
+            .collect();
+        let connected: HashSet<&str> = elements
+            .iter()
+            .filter(|n| n.tag_name().name() == "sequenceFlow")
+            .flat_map(|n| [n.attribute("sourceRef"), n.attribute("targetRef")])
+            .flatten()
+            .collect();
+
+        for element in elements {
+            let tag = element.tag_name().name();
+            if tag == "sequenceFlow" {
+                if let (Some(from), Some(to)) = (element.attribute("sourceRef"), element.attribute("targetRef")) {
+                    flows.push((from, to));
+                }
+                continue;
+            }
+            let Some(element_id) = element.attribute("id") else {
+                continue;
+            };
+            let node_type = match tag {
+                "startEvent" => default_producer(),
+                "endEvent" => default_consumer(),
+                "callActivity" => match element.attribute("calledElement") {
+                    Some(path) if !connected.contains(element_id) => NodeType::Reference { path: path.to_string() },
+                    _ => default_transformer(DEFAULT_TRANSFORMER_SCRIPT.to_string()),
+                },
+                "scriptTask" => default_transformer(javascript_of(&element).unwrap_or(DEFAULT_TRANSFORMER_SCRIPT.to_string())),
+                "task" | "userTask" | "serviceTask" | "sendTask" | "receiveTask" | "manualTask"
+                | "businessRuleTask" | "subProcess" | "transaction" | "adHocSubProcess" | "exclusiveGateway"
+                | "parallelGateway" | "inclusiveGateway" | "eventBasedGateway" | "complexGateway"
+                | "intermediateCatchEvent" | "intermediateThrowEvent" => {
+                    default_transformer(DEFAULT_TRANSFORMER_SCRIPT.to_string())
+                }
+                _ => continue,
+            };
+
+            let base = element
+                .attribute("name")
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .unwrap_or(element_id);
+            let name = unique_name(base, &mut taken);
+            let position = centers.get(element_id).copied().unwrap_or((0.0, 0.0));
+            let id = flowchart.add_node(FlowchartNode::new(name, position, node_type));
+            if !centers.contains_key(element_id) {
+                unplaced.push(id);
+            }
+            ids.insert(element_id, id);
+            process_nodes.entry(process_id).or_default().push(id);
+        }
+    }
+
+    // Pools with a name become groups; message flows join nodes across pools
+    for collaboration in root.children().filter(|n| n.has_tag_name((MODEL_NS, "collaboration"))) {
+        for element in collaboration.children() {
+            if element.has_tag_name((MODEL_NS, "participant")) {
+                let name = element.attribute("name").map(str::trim).unwrap_or_default();
+                let members = element
+                    .attribute("processRef")
+                    .and_then(|process| process_nodes.get(process))
+                    .cloned()
+                    .unwrap_or_default();
+                if !name.is_empty() && !members.is_empty() {
+                    let group = Group {
+                        id: Uuid::new_v4(),
+                        name: name.to_string(),
+                        members,
+                        drawing: GroupDrawingMode::default(),
+                        z_order: 0,
+                    };
+                    flowchart.groups.insert(group.id, group);
+                }
+            } else if element.has_tag_name((MODEL_NS, "messageFlow")) {
+                if let (Some(from), Some(to)) = (element.attribute("sourceRef"), element.attribute("targetRef")) {
+                    flows.push((from, to));
+                }
+            }
+        }
+    }
+
+    for (from, to) in flows {
+        let (Some(from), Some(to)) = (ids.get(from), ids.get(to)) else {
+            continue;
+        };
+        let joins_reference = [from, to]
+            .iter()
+            .any(|id| matches!(flowchart.nodes[*id].node_type, NodeType::Reference { .. }));
+        if !joins_reference {
+            let _ = flowchart.add_connection(*from, *to);
+        }
+    }
+
+    // Nodes without a diagram shape go in a row below the placed ones
+    let below = flowchart
+        .nodes
+        .values()
+        .filter(|node| !unplaced.contains(&node.id))
+        .map(|node| node.position.1 + PIPELINE_NODE_SPACING)
+        .fold(0.0_f32, f32::max);
+    for (index, id) in unplaced.iter().enumerate() {
+        if let Some(node) = flowchart.nodes.get_mut(id) {
+            node.position = (index as f32 * PIPELINE_NODE_SPACING, below);
+        }
+    }
+
+    Ok(flowchart)
+}
+
+/// The BPMN element id of a node
+fn element_id(id: &NodeId) -> String {
+    format!("Node_{}", id)
+}
+
+/// Writes the process element standing for `node`.
+fn write_flow_node(xml: &mut String, node: &FlowchartNode) {
+    let id = element_id(&node.id);
+    let name = escape(&node.name);
+    match &node.node_type {
+        NodeType::Producer { .. } => {
+            let _ = writeln!(xml, "    <bpmn:startEvent id=\"{}\" name=\"{}\" />", id, name);
+        }
+        NodeType::Consumer { .. } => {
+            let _ = writeln!(xml, "    <bpmn:endEvent id=\"{}\" name=\"{}\" />", id, name);
+        }
+        NodeType::Transformer { script, .. } => {
+            let _ = writeln!(
+                xml,
+                "    <bpmn:scriptTask id=\"{}\" name=\"{}\" scriptFormat=\"javascript\">",
+                id, name
+            );
+            let _ = writeln!(xml, "      <bpmn:script>{}</bpmn:script>", escape(script));
+            xml.push_str("    </bpmn:scriptTask>\n");
+        }
+        NodeType::Reference { path } => {
+            let _ = writeln!(
+                xml,
+                "    <bpmn:callActivity id=\"{}\" name=\"{}\" calledElement=\"{}\" />",
+                id,
+                name,
+                escape(path)
+            );
+        }
+    }
+}
+
+/// Width and height of the diagram shape for a node of this type
+fn shape_size(node_type: &NodeType) -> (f32, f32) {
+    match node_type {
+        NodeType::Producer { .. } | NodeType::Consumer { .. } => (EVENT_SIZE, EVENT_SIZE),
+        _ => (NODE_WIDTH, NODE_HEIGHT),
+    }
+}
+
+/// Smallest box around the node rects of `nodes`, as (min x, min y, max x, max y)
+fn bounds<'a>(nodes: impl Iterator<Item = &'a &'a FlowchartNode>) -> Option<(f32, f32, f32, f32)> {
+    nodes.fold(None, |acc, node| {
+        let (x, y) = node.position;
+        let rect = (
+            x - NODE_WIDTH / 2.0,
+            y - NODE_HEIGHT / 2.0,
+            x + NODE_WIDTH / 2.0,
+            y + NODE_HEIGHT / 2.0,
+        );
+        Some(match acc {
+            None => rect,
+            Some((a, b, c, d)) => (a.min(rect.0), b.min(rect.1), c.max(rect.2), d.max(rect.3)),
+        })
+    })
+}
+
+fn write_bounds(xml: &mut String, x: f32, y: f32, width: f32, height: f32) {
+    let _ = writeln!(
+        xml,
+        "        <dc:Bounds x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" />",
+        x, y, width, height
+    );
+}
+
+/// The script of a script task written in JavaScript
+fn javascript_of(task: &roxmltree::Node) -> Option<String> {
+    let format = task.attribute("scriptFormat")?.to_ascii_lowercase();
+    if !matches!(
+        format.as_str(),
+        "javascript" | "js" | "ecmascript" | "text/javascript" | "application/javascript"
+    ) {
+        return None;
+    }
+    let script = task.children().find(|n| n.has_tag_name((MODEL_NS, "script")))?;
+    let text: String = script.children().filter(|n| n.is_text()).filter_map(|n| n.text()).collect();
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// `base`, or `base (2)`, `base (3)`, ... if that name is already taken
+fn unique_name(base: &str, taken: &mut HashSet<String>) -> String {
+    let name = if taken.contains(base) {
+        (2..)
+            .map(|n| format!("{} ({})", base, n))
+            .find(|candidate| !taken.contains(candidate))
+            .expect("unbounded range always yields a free name")
+    } else {
+        base.to_string()
+    };
+    taken.insert(name.clone());
+    name
+}
+
+fn default_producer() -> NodeType {
+    NodeType::Producer {
+        message_template: serde_json::json!({"value": 0}),
+        start_step: 0,
+        messages_per_cycle: 1,
+        steps_between_cycles: 1,
+        messages_produced: 0,
+    }
+}
+
+fn default_consumer() -> NodeType {
+    NodeType::Consumer {
+        consumption_rate: 1,
+        processing_time: 0,
+        max_concurrency: 1,
+        in_flight: Vec::new(),
+        accept_filter: String::new(),
+    }
+}
+
+fn default_transformer(script: String) -> NodeType {
+    NodeType::Transformer {
+        script,
+        selected_outputs: None,
+        globals: serde_json::Map::new(),
+        initial_globals: serde_json::Map::new(),
+        retry: RetryPolicy::default(),
+    }
+}
+
+/// Escapes text for use in XML content and attribute values
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::{build_example, ExampleKind};
+
+    fn by_name(flowchart: &Flowchart, name: &str) -> FlowchartNode {
+        flowchart
+            .nodes
+            .values()
+            .find(|n| n.name == name)
+            .unwrap_or_else(|| panic!("no node named {}", name))
+            .clone()
+    }
+
+    #[test]
+    fn test_bpmn_round_trip_keeps_structure_and_layout() {
+        let mut original = build_example(ExampleKind::DecisionBranch);
+        let mut members: Vec<NodeId> = original.nodes.keys().copied().collect();
+        members.sort();
+        members.truncate(2);
+        let group = Group {
+            id: Uuid::new_v4(),
+            name: "Intake & <review>".to_string(),
+            members: members.clone(),
+            drawing: GroupDrawingMode::Rectangle,
+            z_order: 0,
+        };
+        original.groups.insert(group.id, group);
+
+        let xml = to_bpmn(&original);
+        assert!(roxmltree::Document::parse(&xml).is_ok());
+        let restored = from_bpmn(&xml).unwrap();
+
+        assert_eq!(restored.nodes.len(), original.nodes.len());
+        assert_eq!(restored.connections.len(), original.connections.len());
+        for node in original.nodes.values() {
+            let copy = by_name(&restored, &node.name);
+            assert_eq!(copy.position, node.position);
+            assert_eq!(
+                std::mem::discriminant(&copy.node_type),
+                std::mem::discriminant(&node.node_type)
+            );
+            if let (NodeType::Transformer { script: a, .. }, NodeType::Transformer { script: b, .. }) =
+                (&node.node_type, &copy.node_type)
+            {
+                assert_eq!(a, b);
+            }
+        }
+        for conn in &original.connections {
+            let from = &restored.nodes.values().find(|n| n.name == original.nodes[&conn.from].name).unwrap().id;
+            let to = &restored.nodes.values().find(|n| n.name == original.nodes[&conn.to].name).unwrap().id;
+            assert!(restored.connections.iter().any(|c| c.from == *from && c.to == *to));
+        }
+
+        let group = restored.groups.values().next().expect("the pool becomes a group");
+        assert_eq!(group.name, "Intake & <review>");
+        let mut names: Vec<_> = group.members.iter().map(|id| restored.nodes[id].name.clone()).collect();
+        let mut expected: Vec<_> = members.iter().map(|id| original.nodes[id].name.clone()).collect();
+        names.sort();
+        expected.sort();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_from_bpmn_reads_a_foreign_diagram() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<definitions xmlns="http://www.omg.org/spec/BPMN/20100524/MODEL"
+             xmlns:bpmndi="http://www.omg.org/spec/BPMN/20100524/DI"
+             xmlns:dc="http://www.omg.org/spec/DD/20100524/DC" id="d">
+  <collaboration id="c">
+    <participant id="p" name="Shop" processRef="orders" />
+  </collaboration>
+  <process id="orders">
+    <startEvent id="start" name="Order placed" />
+    <exclusiveGateway id="gw" name="In stock?" />
+    <scriptTask id="ship" name="Ship" scriptFormat="JavaScript">
+      <script>function transform(input) { return input; }</script>
+    </scriptTask>
+    <userTask id="refund" />
+    <endEvent id="end" name="Done" />
+    <sequenceFlow id="f1" sourceRef="start" targetRef="gw" />
+    <sequenceFlow id="f2" sourceRef="gw" targetRef="ship" />
+    <sequenceFlow id="f3" sourceRef="gw" targetRef="refund" />
+    <sequenceFlow id="f4" sourceRef="ship" targetRef="end" />
+    <sequenceFlow id="f5" sourceRef="refund" targetRef="missing" />
+  </process>
+  <bpmndi:BPMNDiagram id="di">
+    <bpmndi:BPMNPlane id="plane" bpmnElement="c">
+      <bpmndi:BPMNShape id="start_di" bpmnElement="start">
+        <dc:Bounds x="100" y="100" width="36" height="36" />
+      </bpmndi:BPMNShape>
+    </bpmndi:BPMNPlane>
+  </bpmndi:BPMNDiagram>
+</definitions>"#;
+        let flowchart = from_bpmn(xml).unwrap();
+        assert_eq!(flowchart.nodes.len(), 5);
+        assert_eq!(flowchart.connections.len(), 4);
+
+        let start = by_name(&flowchart, "Order placed");
+        assert!(matches!(start.node_type, NodeType::Producer { .. }));
+        assert_eq!(start.position, (118.0, 118.0));
+        assert!(matches!(by_name(&flowchart, "Done").node_type, NodeType::Consumer { .. }));
+        match by_name(&flowchart, "Ship").node_type {
+            NodeType::Transformer { script, .. } => assert_eq!(script, "function transform(input) { return input; }"),
+            other => panic!("expected transformer, got {:?}", other),
+        }
+        // Unnamed elements use their id, and unplaced ones go below the rest
+        let refund = by_name(&flowchart, "refund");
+        assert!(refund.position.1 > start.position.1);
+
+        let group = flowchart.groups.values().next().unwrap();
+        assert_eq!(group.name, "Shop");
+        assert_eq!(group.members.len(), 5);
+
+        assert!(from_bpmn("<svg xmlns=\"http://www.w3.org/2000/svg\" />").is_err());
+        assert!(from_bpmn("not xml").is_err());
+    }
+}
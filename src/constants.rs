@@ -17,6 +17,25 @@ pub const GROUP_LABEL_PADDING_BASE: f32 = 6.0;
 pub const NODE_WIDTH: f32 = 100.0;
 /// Default node height in world units.
 pub const NODE_HEIGHT: f32 = 70.0;
+/// Distance between the centers of the nodes the pipeline wizard creates.
+pub const PIPELINE_NODE_SPACING: f32 = NODE_WIDTH * 2.0;
+/// Vertical gap between the copies the Replicate command stacks.
+pub const REPLICATE_ROW_GAP: f32 = 40.0;
+/// Most copies the Replicate command makes at once.
+pub const REPLICATE_MAX_COPIES: u32 = 100;
+/// Gap kept between a newly inserted node and the nodes nudged out of its way.
+pub const MAKE_ROOM_GAP: f32 = 20.0;
+/// Most passes the make-room layout takes to resolve overlaps.
+pub const MAKE_ROOM_MAX_PASSES: usize = 50;
+
+// Node defaults
+/// Script given to newly created transformer nodes.
+pub const DEFAULT_TRANSFORMER_SCRIPT: &str = "// Transform the input message with optional routing via __targets\nfunction transform(input) {\n    // To target specific outputs by node name, include __targets as an array.\n    // For example, send only to node named \"NextNode\":\n    // return { value: input.value, __targets: [\"NextNode\"] };\n    // If __targets is omitted or null, the message is broadcast to all outputs.\n    // Add __priority (a number) to deliver the message ahead of lower-priority ones.\n    return input;\n}";
+
+/// Spec shown when the producer test-data generator is first opened.
+pub const DEFAULT_GENERATOR_SPEC: &str = "# One field per line: name: type\n# Types: int MIN..MAX, float MIN..MAX, bool, choice a | b, text LEN, uuid, const JSON\nid: int 1..1000\nstatus: choice pending | shipped | delivered\namount: float 1..500\n";
+/// Number of sample messages previewed in the test-data generator.
+pub const GENERATOR_PREVIEW_SAMPLES: usize = 3;
 
 // Grid/drawing
 /// Grid cell size in world units.
@@ -27,11 +46,169 @@ pub const GRID_WIDTH: usize = 5;
 pub const DOT_SPACING: f32 = 8.0;
 /// Radius of minor grid dots (in screen pixels).
 pub const DOT_RADIUS: f32 = 3.0;
+/// Half-length of the origin crosshair arms (in screen pixels).
+pub const ORIGIN_MARKER_SIZE: f32 = 12.0;
+/// Radius of the error badge on nodes that failed during the run (in world units).
+pub const ERROR_BADGE_RADIUS: f32 = 8.0;
+/// Number of recent steps shown by the sparkline inside consumer nodes.
+pub const CONSUMER_SPARKLINE_STEPS: usize = 50;
 
 // Canvas interactions
 /// Click threshold in world units used for distinguishing click vs drag.
 pub const CLICK_THRESHOLD: f32 = 10.0;
+/// Maximum center-to-center distance (world units) at which a newly created
+/// node is offered an auto-connection to an existing node.
+pub const AUTO_CONNECT_DISTANCE: f32 = 160.0;
+/// Maximum distance (world units) from a connection line at which a newly
+/// created node is offered to be inserted into that connection.
+pub const AUTO_CONNECT_EDGE_DISTANCE: f32 = NODE_HEIGHT * 0.5;
+/// Largest offset (world units) across a connection at which it is drawn as
+/// a straight horizontal or vertical line.
+pub const ORTHOGONAL_SNAP_TOLERANCE: f32 = 10.0;
+/// Largest offset (world units) across a connection that "Align Connected
+/// Nodes" removes; steeper connections are left alone.
+pub const AXIS_ALIGN_MAX_NUDGE: f32 = NODE_HEIGHT;
+/// Default smallest canvas zoom factor.
+pub const ZOOM_DEFAULT_MIN: f32 = 0.25;
+/// Default largest canvas zoom factor.
+pub const ZOOM_DEFAULT_MAX: f32 = 5.0;
+/// Range the zoom limits can be set to in Preferences.
+pub const ZOOM_LIMIT_RANGE: std::ops::RangeInclusive<f32> = 0.05..=20.0;
+/// Factor one Ctrl+= / Ctrl+- press zooms in or out by.
+pub const ZOOM_KEY_STEP: f32 = 1.25;
+/// Default speed (screen points per second) of panning with the arrow keys or WASD.
+pub const KEYBOARD_PAN_DEFAULT_SPEED: f32 = 600.0;
+/// Fastest keyboard panning speed offered in Preferences.
+pub const KEYBOARD_PAN_MAX_SPEED: f32 = 3000.0;
+/// Earlier views kept by the view history.
+pub const VIEW_HISTORY_LIMIT: usize = 50;
+/// Seconds the view must stay still before a pan or zoom becomes a step of
+/// the view history, so one gesture is recorded once.
+pub const VIEW_HISTORY_SETTLE_SECS: f64 = 0.5;
+
+// Properties panel
+/// In-transit messages drawn per connection, and listed per page, unless changed in Preferences.
+pub const MESSAGE_RENDER_DEFAULT_LIMIT: usize = 200;
+/// Largest message render limit that can be set in Preferences.
+pub const MESSAGE_RENDER_MAX_LIMIT: usize = 100_000;
+/// Height (in points) of the producer schedule timeline.
+pub const PRODUCER_TIMELINE_HEIGHT: f32 = 28.0;
+/// Maximum number of steps shown on the producer schedule timeline.
+pub const PRODUCER_TIMELINE_MAX_STEPS: u64 = 100;
+/// Latest start step that can be set on a producer.
+pub const PRODUCER_MAX_START_STEP: u64 = 1_000_000_000;
+/// Most messages a producer can be set to emit.
+pub const PRODUCER_MAX_MESSAGES: u32 = 100_000_000;
+/// Longest gap between a producer's emissions, in steps.
+pub const PRODUCER_MAX_STEPS_BETWEEN: u32 = 1_000_000;
+/// Range a connection's line thickness can be set to, in points.
+pub const CONNECTION_WIDTH_RANGE: std::ops::RangeInclusive<f32> = 0.5..=8.0;
+
+// Simulation
+/// Maximum number of message trace hops kept for the trace view.
+pub const TRACE_LOG_LIMIT: usize = 10_000;
+/// Maximum number of script failures kept for the errors list.
+pub const ERROR_LOG_LIMIT: usize = 1_000;
+/// Delivered messages kept in memory in each node's history unless changed in Preferences.
+pub const NODE_HISTORY_LIMIT: usize = 200;
+/// Largest number of delivered messages per node that can be kept in memory.
+pub const NODE_HISTORY_MAX_LIMIT: usize = 100_000;
+/// Most bytes of older node history spilled to a temporary file.
+pub const HISTORY_SPILL_MAX_BYTES: u64 = 1 << 30;
+/// Spilled history entries read back at a time in the node inspector.
+pub const HISTORY_SPILL_PAGE: usize = 200;
+/// Maximum number of per-step samples kept for run statistics.
+pub const RUN_STATS_SAMPLE_LIMIT: usize = 100_000;
+/// Simulation events kept in the events log unless changed in Preferences.
+pub const EVENT_LOG_DEFAULT_CAPACITY: usize = 10_000;
+/// Largest events log size that can be set in Preferences.
+pub const EVENT_LOG_MAX_CAPACITY: usize = 1_000_000;
+/// Most transformers whose prepared scripts an engine or thread keeps ready to run.
+pub const PREPARED_SCRIPT_LIMIT: usize = 64;
+/// Steps between sweeps of the shared payload pool for payloads no message uses.
+pub const PAYLOAD_PRUNE_INTERVAL_STEPS: u64 = 64;
+/// Most combinations a parameter sweep may run.
+pub const SWEEP_MAX_RUNS: usize = 400;
+/// Steps each sweep run lasts when the measurement window is open-ended.
+pub const SWEEP_DEFAULT_STEPS: u64 = 200;
+/// Steps Verify Determinism compares when the run has no configured end.
+pub const DETERMINISM_CHECK_STEPS: u64 = 500;
+/// Keys that can be chosen in Preferences to step the simulation.
+pub const STEP_KEY_CHOICES: [egui::Key; 5] =
+    [egui::Key::Space, egui::Key::Period, egui::Key::N, egui::Key::F8, egui::Key::F10];
+/// Steps Shift plus the step key advances unless changed in Preferences.
+pub const MULTI_STEP_DEFAULT_COUNT: u32 = 10;
+/// Most steps Shift plus the step key can be set to advance.
+pub const MULTI_STEP_MAX_COUNT: u32 = 10_000;
+/// Most steps a running simulation can be set to advance per frame.
+pub const MAX_STEPS_PER_FRAME: u32 = 1_000;
+/// Deepest nesting followed when inferring the shape of messages on a connection.
+pub const MESSAGE_SHAPE_MAX_DEPTH: usize = 8;
+/// Most fields tracked per object when inferring message shapes; later new fields are skipped.
+pub const MESSAGE_SHAPE_MAX_FIELDS: usize = 64;
+/// Array elements of each message looked at when inferring the shape of its items.
+pub const MESSAGE_SHAPE_MAX_ITEMS: usize = 32;
+
+/// Characters of a script or message template kept in the node table export.
+pub const NODE_TABLE_CODE_CHARS: usize = 60;
+
+// Analysis
+/// Upper bound on community detection passes when proposing automatic groups.
+pub const AUTO_GROUP_MAX_ROUNDS: usize = 100;
+
+// Swimlanes
+/// Height of a newly created lane in world units.
+pub const LANE_DEFAULT_HEIGHT: f32 = 160.0;
+/// Space kept between a lane's edges and the nodes laid out inside it.
+pub const LANE_PADDING: f32 = 30.0;
+/// Horizontal gap between dependency columns in the swimlane layout.
+pub const LANE_COLUMN_SPACING: f32 = 60.0;
+/// Vertical gap between nodes sharing a column within one lane.
+pub const LANE_ROW_SPACING: f32 = 30.0;
+
+// Animation
+/// Time each phase of the flashing error border lasts, in seconds.
+pub const ERROR_FLASH_HALF_PERIOD_SECS: f64 = 0.25;
+/// Duration of one pulse of the traced-node highlight, in seconds.
+pub const TRACE_PULSE_PERIOD_SECS: f64 = 1.5;
+/// Delay between repaints while only a decorative animation is running.
+pub const ANIMATION_FRAME_SECS: f64 = 1.0 / 30.0;
+/// Time nodes take to glide to the positions chosen by an auto-layout, in seconds.
+pub const LAYOUT_ANIMATION_SECS: f64 = 0.3;
+
+// Files
+/// Prefix of the document path shown for flowcharts kept in the browser's document store.
+pub const BROWSER_DOCUMENT_PREFIX: &str = "Browser: ";
+/// Quiet period (in seconds) after the last change event on the open file
+/// before it is re-read, so multi-step writes are seen as one change.
+pub const FILE_WATCH_DEBOUNCE_SECS: f64 = 0.5;
+/// File extension registered with the OS for flowchart documents.
+pub const FLOWCHART_FILE_EXTENSION: &str = "flowchart";
+/// How long a second launch waits for the running instance to take its file.
+pub const INSTANCE_HANDOFF_TIMEOUT_MS: u64 = 1000;
+/// Files at least this large (in bytes) show a progress dialog while they load.
+pub const LOAD_PROGRESS_MIN_BYTES: u64 = 1024 * 1024;
+/// Size of each read (in bytes) when loading a file in the background.
+pub const LOAD_CHUNK_BYTES: usize = 256 * 1024;
+/// Number of recent app events kept for crash reports.
+pub const CRASH_LOG_CAPACITY: usize = 50;
+
+// Desktop
+/// Name of the app, and the window title when nothing is running.
+pub const APP_TITLE: &str = "Flowchart Tool";
+
+// Notifications
+/// How long a notification without a button stays up, in seconds.
+pub const NOTIFICATION_TIMEOUT_SECS: f64 = 6.0;
+
+// Web app
+/// Name of the service worker cache holding this release's files. It changes
+/// with every version, so an update never serves files of an older one.
+pub const PWA_CACHE_NAME: &str = concat!("flowchart-tool-v", env!("CARGO_PKG_VERSION"));
 
 // Undo/redo
 /// Maximum number of undo history entries to retain.
 pub const MAX_UNDO_HISTORY: usize = 100;
+/// Window (in seconds) within which consecutive edits of the same property
+/// are coalesced into a single undo entry.
+pub const UNDO_COALESCE_WINDOW_SECS: f64 = 1.0;
@@ -0,0 +1,175 @@
+//! Crash recovery and bug report bundles.
+//!
+//! A panic hook records the panic message next to a short ring buffer of
+//! recent app events. When the UI panics, the app writes a bundle with an
+//! emergency copy of the document and a plain-text report (app version,
+//! platform, panic, recent events and an anonymized state summary) to the
+//! recovery directory; the next launch finds it and offers to restore.
+
+use crate::constants::CRASH_LOG_CAPACITY;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// File names inside the recovery directory.
+const DOCUMENT_FILE: &str = "document.json";
+const DOCUMENT_PATH_FILE: &str = "document_path.txt";
+const REPORT_FILE: &str = "report.txt";
+
+/// Most recent app events, oldest first.
+static RECENT_EVENTS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+/// Description of the last panic seen by the hook.
+static LAST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+/// Process start, for event timestamps.
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// What a crashed session left behind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashBundle {
+    /// The document as it was when the app crashed, as flowchart JSON
+    pub document: String,
+    /// Where the document was saved, if it had a path
+    pub document_path: Option<String>,
+    /// Plain-text bug report
+    pub report: String,
+}
+
+/// Adds an event to the log included in bug reports.
+///
+/// Events should describe what happened, not the user's content: no file
+/// names, node names or payloads.
+pub fn record_event(event: impl Into<String>) {
+    let elapsed = STARTED.get_or_init(Instant::now).elapsed().as_secs_f64();
+    let mut events = RECENT_EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    if events.len() == CRASH_LOG_CAPACITY {
+        events.pop_front();
+    }
+    events.push_back(format!("[{:>9.2}s] {}", elapsed, event.into()));
+}
+
+/// Installs the panic hook that records panics for the bug report.
+///
+/// The previous hook still runs, so panics are printed as usual.
+pub fn install_panic_hook() {
+    STARTED.get_or_init(Instant::now);
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "(non-string panic payload)".to_string());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_else(|| "unknown location".to_string());
+        let thread = std::thread::current().name().unwrap_or("unnamed").to_string();
+        let description = format!("'{}' at {} (thread {})", message, location, thread);
+        record_event(format!("Panic: {}", description));
+        *LAST_PANIC.lock().unwrap_or_else(|e| e.into_inner()) = Some(description);
+        previous(info);
+    }));
+}
+
+/// Builds the bug report text around an anonymized `summary` of app state.
+pub fn build_report(summary: &str) -> String {
+    let panic = LAST_PANIC
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| "(not recorded)".to_string());
+    let events = RECENT_EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut report = format!(
+        "Flowchart Tool crash report\n\nVersion: {}\nPlatform: {} ({})\nPanic: {}\n\nState summary:\n{}\n\nRecent events:\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        panic,
+        summary.trim_end(),
+    );
+    if events.is_empty() {
+        report.push_str("(none)\n");
+    }
+    for event in events.iter() {
+        report.push_str(event);
+        report.push('\n');
+    }
+    report
+}
+
+/// Directory holding the bundle of a crashed session (desktop only).
+///
+/// Tests get `None` so they never touch a real user's recovery data.
+pub fn recovery_dir() -> Option<PathBuf> {
+    #[cfg(not(any(target_arch = "wasm32", test)))]
+    {
+        eframe::storage_dir("flowchart_tool").map(|dir| dir.join("recovery"))
+    }
+    #[cfg(any(target_arch = "wasm32", test))]
+    {
+        None
+    }
+}
+
+/// Writes `bundle` to `dir`, replacing any earlier one.
+pub fn write_bundle(dir: &Path, bundle: &CrashBundle) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join(REPORT_FILE), &bundle.report)?;
+    match &bundle.document_path {
+        Some(path) => std::fs::write(dir.join(DOCUMENT_PATH_FILE), path)?,
+        None => {
+            let _ = std::fs::remove_file(dir.join(DOCUMENT_PATH_FILE));
+        }
+    }
+    // Written last: its presence marks a complete bundle
+    std::fs::write(dir.join(DOCUMENT_FILE), &bundle.document)
+}
+
+/// Reads the bundle left in `dir`, if any.
+pub fn read_bundle(dir: &Path) -> Option<CrashBundle> {
+    let document = std::fs::read_to_string(dir.join(DOCUMENT_FILE)).ok()?;
+    Some(CrashBundle {
+        document,
+        document_path: std::fs::read_to_string(dir.join(DOCUMENT_PATH_FILE)).ok(),
+        report: std::fs::read_to_string(dir.join(REPORT_FILE)).unwrap_or_default(),
+    })
+}
+
+/// Removes the bundle in `dir` once the user has dealt with it.
+pub fn clear_bundle(dir: &Path) {
+    for file in [DOCUMENT_FILE, DOCUMENT_PATH_FILE, REPORT_FILE] {
+        let _ = std::fs::remove_file(dir.join(file));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip_and_report_contents() {
+        for i in 0..CRASH_LOG_CAPACITY + 5 {
+            record_event(format!("event {}", i));
+        }
+        let report = build_report("nodes: 3");
+        assert!(report.contains(env!("CARGO_PKG_VERSION")));
+        assert!(report.contains("nodes: 3"));
+        assert!(report.contains(&format!("event {}", CRASH_LOG_CAPACITY + 4)));
+        assert!(!report.contains("event 0\n"), "oldest events are dropped");
+
+        let dir = std::env::temp_dir().join(format!("flowchart_crash_{}", uuid::Uuid::new_v4()));
+        assert!(read_bundle(&dir).is_none());
+        let bundle = CrashBundle {
+            document: "{}".to_string(),
+            document_path: Some("/tmp/chart.json".to_string()),
+            report,
+        };
+        write_bundle(&dir, &bundle).unwrap();
+        assert_eq!(read_bundle(&dir), Some(bundle));
+        clear_bundle(&dir);
+        assert!(read_bundle(&dir).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
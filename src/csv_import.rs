@@ -0,0 +1,352 @@
+//! Nodes from a CSV table.
+//!
+//! [`read_node_csv`] turns a table with one node per row into node specs to
+//! review before they are added, so a model can be bootstrapped from an
+//! inventory of services kept in a spreadsheet. The first row names the
+//! columns, in any order and case:
+//!
+//! - `name` and `type` (`producer`, `consumer`, `transformer` or
+//!   `reference`) are required;
+//! - `start_step`, `messages`, `steps_between`, `phase`, `jitter` and
+//!   `template` (JSON) set up producers;
+//! - `consumption_rate`, `processing_time`, `max_concurrency` and
+//!   `accept_filter` set up consumers;
+//! - `script`, `retry_attempts` and `retry_backoff` set up transformers, and
+//!   `reference` is the file of a reference node;
+//! - `failure_probability` and `failure_mode` make transformers and
+//!   consumers fail at random;
+//! - `x` and `y` place the node, `group` (or `groups`) names the groups it
+//!   joins and `downstream` the nodes it sends to, separated by `;`.
+//!
+//! Empty cells take the values a node created from the canvas has. A table
+//! exported with [`crate::node_table`] reads back, except for scripts and
+//! templates, which the export shortens; its other columns are ignored.
+
+use crate::constants::DEFAULT_TRANSFORMER_SCRIPT;
+use crate::node_table::NODE_TABLE_COLUMNS;
+use crate::types::*;
+use std::collections::HashMap;
+
+/// Columns [`read_node_csv`] reads.
+pub const NODE_CSV_COLUMNS: [&str; 24] = [
+    "name",
+    "type",
+    "start_step",
+    "messages",
+    "steps_between",
+    "phase",
+    "jitter",
+    "template",
+    "consumption_rate",
+    "processing_time",
+    "max_concurrency",
+    "accept_filter",
+    "script",
+    "retry_attempts",
+    "retry_backoff",
+    "reference",
+    "failure_probability",
+    "failure_mode",
+    "x",
+    "y",
+    "group",
+    "groups",
+    "downstream",
+    "downstreams",
+];
+
+/// Splits CSV text into rows of fields.
+///
+/// Fields may be quoted with `"`, holding separators, line breaks and
+/// doubled quotes. Both `\n` and `\r\n` end a row.
+pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+                line += 1;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if quoted {
+        return Err(format!("Quoted field opened before line {} is never closed", line));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// A node read from a row of the table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeSpec {
+    /// Number of the row in the table, the header being row 1
+    pub row: usize,
+    /// Name of the node
+    pub name: String,
+    /// Type and parameters of the node
+    pub node_type: NodeType,
+    /// Phase and jitter, for producers
+    pub timing: ProducerTiming,
+    /// Random failures, for transformers and consumers
+    pub failure: FailureSettings,
+    /// Position on the canvas, if the row gave one
+    pub position: Option<(f32, f32)>,
+    /// Names of the groups the node joins
+    pub groups: Vec<String>,
+    /// Names of the nodes the node sends to
+    pub downstream: Vec<String>,
+}
+
+impl NodeSpec {
+    /// The node to add at `position`.
+    pub fn to_node(&self, position: (f32, f32)) -> FlowchartNode {
+        let mut node = FlowchartNode::new(self.name.clone(), position, self.node_type.clone());
+        node.timing = self.timing;
+        node.failure = self.failure;
+        node
+    }
+}
+
+/// The nodes of a table, and what was skipped in it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeCsv {
+    /// The rows that could be read, in file order
+    pub nodes: Vec<NodeSpec>,
+    /// Rows and columns that were skipped, and why
+    pub warnings: Vec<String>,
+}
+
+/// The cells of one row, looked up by column name.
+struct Row<'a> {
+    row: usize,
+    cells: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Row<'a> {
+    fn text(&self, column: &str) -> Option<&'a str> {
+        self.cells.get(column).map(|cell| cell.trim()).filter(|cell| !cell.is_empty())
+    }
+
+    fn number<T: std::str::FromStr>(&self, column: &str, default: T) -> Result<T, String> {
+        match self.text(column) {
+            None => Ok(default),
+            Some(cell) => cell
+                .parse()
+                .map_err(|_| format!("row {}: '{}' is not a valid {}", self.row, cell, column)),
+        }
+    }
+
+    fn list(&self, columns: [&str; 2]) -> Vec<String> {
+        columns
+            .iter()
+            .filter_map(|column| self.text(column))
+            .flat_map(|cell| cell.split(';'))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn spec(&self) -> Result<NodeSpec, String> {
+        let name = self.text("name").ok_or_else(|| format!("row {}: no name", self.row))?;
+        let kind = self.text("type").unwrap_or_default().to_ascii_lowercase();
+        let node_type = match kind.as_str() {
+            "producer" => NodeType::Producer {
+                message_template: match self.text("template") {
+                    Some(template) => serde_json::from_str(template)
+                        .map_err(|e| format!("row {}: template isn't JSON: {}", self.row, e))?,
+                    None => serde_json::json!({"value": 0}),
+                },
+                start_step: self.number("start_step", 0)?,
+                messages_per_cycle: self.number("messages", 1)?,
+                steps_between_cycles: self.number("steps_between", 1)?,
+                messages_produced: 0,
+            },
+            "consumer" => NodeType::Consumer {
+                consumption_rate: self.number("consumption_rate", 1)?,
+                processing_time: self.number("processing_time", 0)?,
+                max_concurrency: self.number("max_concurrency", 1)?,
+                in_flight: Vec::new(),
+                accept_filter: self.text("accept_filter").unwrap_or_default().to_string(),
+            },
+            "transformer" => NodeType::Transformer {
+                script: self.text("script").unwrap_or(DEFAULT_TRANSFORMER_SCRIPT).to_string(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: RetryPolicy {
+                    max_attempts: self.number("retry_attempts", 1)?.max(1),
+                    backoff_steps: self.number("retry_backoff", 0)?,
+                },
+            },
+            "reference" => NodeType::Reference {
+                path: self.text("reference").unwrap_or_default().to_string(),
+            },
+            "" => return Err(format!("row {}: no type for {}", self.row, name)),
+            other => return Err(format!("row {}: unknown type '{}' for {}", self.row, other, name)),
+        };
+
+        let timing = ProducerTiming {
+            phase: self.number("phase", 0)?,
+            jitter: self.number("jitter", 0)?,
+        };
+        let probability: f64 = self.number("failure_probability", 0.0)?;
+        if !(0.0..=1.0).contains(&probability) {
+            return Err(format!("row {}: failure_probability must be between 0 and 1", self.row));
+        }
+        let mode = match self.text("failure_mode") {
+            None => FailureMode::default(),
+            Some(mode) => FailureMode::ALL
+                .into_iter()
+                .find(|m| m.label().eq_ignore_ascii_case(mode))
+                .ok_or_else(|| format!("row {}: unknown failure_mode '{}'", self.row, mode))?,
+        };
+        let position = match (self.text("x"), self.text("y")) {
+            (None, None) => None,
+            _ => Some((self.number("x", 0.0)?, self.number("y", 0.0)?)),
+        };
+        Ok(NodeSpec {
+            row: self.row,
+            name: name.to_string(),
+            node_type,
+            timing,
+            failure: FailureSettings { probability, mode },
+            position,
+            groups: self.list(["group", "groups"]),
+            downstream: self.list(["downstream", "downstreams"]),
+        })
+    }
+}
+
+/// Reads the nodes of a CSV table.
+///
+/// Rows that can't be read are skipped with a warning, as are rows repeating
+/// an earlier name, since `downstream` refers to nodes by name.
+///
+/// # Returns
+///
+/// The nodes, or an error if the text isn't CSV or lacks the `name` and
+/// `type` columns.
+pub fn read_node_csv(text: &str) -> Result<NodeCsv, String> {
+    let rows = parse_csv(text)?;
+    let mut rows = rows.iter().enumerate().filter(|(_, row)| row.iter().any(|cell| !cell.trim().is_empty()));
+    let (_, header) = rows.next().ok_or_else(|| "The file has no rows".to_string())?;
+    let header: Vec<String> = header.iter().map(|column| column.trim().to_ascii_lowercase()).collect();
+    for required in ["name", "type"] {
+        if !header.iter().any(|column| column == required) {
+            return Err(format!("The first row has no '{}' column", required));
+        }
+    }
+
+    let mut table = NodeCsv::default();
+    for column in &header {
+        if !NODE_CSV_COLUMNS.contains(&column.as_str()) && !NODE_TABLE_COLUMNS.contains(&column.as_str()) {
+            table.warnings.push(format!("Column '{}' is ignored", column));
+        }
+    }
+    for (index, row) in rows {
+        let row = Row {
+            row: index + 1,
+            cells: header.iter().map(String::as_str).zip(row.iter().map(String::as_str)).collect(),
+        };
+        match row.spec() {
+            Ok(spec) if table.nodes.iter().any(|n| n.name == spec.name) => table
+                .warnings
+                .push(format!("row {}: {} is named on an earlier row", spec.row, spec.name)),
+            Ok(spec) => table.nodes.push(spec),
+            Err(e) => table.warnings.push(e),
+        }
+    }
+    Ok(table)
+}
+
+/// Column of each node when laid out from left to right: how many nodes of
+/// the table lie upstream of it on its longest `downstream` path. Cycles are
+/// cut where they close.
+pub fn layout_columns(nodes: &[NodeSpec]) -> Vec<usize> {
+    let index: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, n)| (n.name.as_str(), i)).collect();
+    let mut columns = vec![0; nodes.len()];
+    // A column can only grow up to the number of nodes, so this settles
+    for _ in 0..nodes.len() {
+        let mut changed = false;
+        for (from, node) in nodes.iter().enumerate() {
+            for name in &node.downstream {
+                if let Some(&to) = index.get(name.as_str()) {
+                    if columns[to] < columns[from] + 1 && columns[from] + 1 < nodes.len() {
+                        columns[to] = columns[from] + 1;
+                        changed = true;
+                    }
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_fields_keep_separators_and_quotes() {
+        let rows = parse_csv("a,\"b, \"\"c\"\"\"\r\n\"multi\nline\",\n").unwrap();
+        assert_eq!(rows, vec![vec!["a", "b, \"c\""], vec!["multi\nline", ""]]);
+        assert!(parse_csv("a,\"open\n").is_err());
+    }
+
+    #[test]
+    fn rows_become_nodes_and_bad_rows_are_reported() {
+        let csv = "Name,Type,messages,steps_between,consumption_rate,x,y,downstream,group,owner\n\
+                   Orders,producer,20,3,,10,20,Enrich,Intake,team-a\n\
+                   Enrich,Transformer,,,,,,Billing; Audit,,\n\
+                   Billing,consumer,,,4,,,,Intake,\n\
+                   Audit,consumer,,,lots,,,\n\
+                   Orders,consumer,,,,,,\n\
+                   Ghost,queue,,,,,,\n";
+        let table = read_node_csv(csv).unwrap();
+        let names: Vec<&str> = table.nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["Orders", "Enrich", "Billing"]);
+        assert_eq!(table.warnings.len(), 4, "{:?}", table.warnings);
+        assert!(table.warnings[0].contains("owner"));
+        assert!(table.warnings[1].contains("row 5"));
+
+        let orders = &table.nodes[0];
+        assert!(matches!(
+            orders.node_type,
+            NodeType::Producer { messages_per_cycle: 20, steps_between_cycles: 3, .. }
+        ));
+        assert_eq!(orders.position, Some((10.0, 20.0)));
+        assert_eq!(orders.groups, vec!["Intake"]);
+        assert_eq!(table.nodes[1].downstream, vec!["Billing", "Audit"]);
+        assert!(matches!(table.nodes[2].node_type, NodeType::Consumer { consumption_rate: 4, .. }));
+        assert_eq!(layout_columns(&table.nodes), vec![0, 1, 2]);
+
+        assert!(read_node_csv("name,kind\nA,producer\n").is_err());
+    }
+}
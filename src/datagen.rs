@@ -0,0 +1,261 @@
+//! Test-data generation for producer message templates.
+//!
+//! A spec lists one field per line as `name: type`, for example:
+//!
+//! ```text
+//! id: int 1..1000
+//! price: float 0.5..99.5
+//! status: choice pending | shipped | delivered
+//! customer.name: text 8
+//! express: bool
+//! ```
+//!
+//! Dotted names build nested objects. Generation is driven by a seed so the
+//! same spec and seed always produce the same samples.
+
+use serde_json::{Map, Value};
+
+/// The kind of value generated for a field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldKind {
+    /// Whole number in an inclusive range
+    Integer {
+        /// Smallest value
+        min: i64,
+        /// Largest value
+        max: i64,
+    },
+    /// Decimal number in a half-open range, rounded to two places
+    Float {
+        /// Smallest value
+        min: f64,
+        /// Upper bound
+        max: f64,
+    },
+    /// `true` or `false`
+    Boolean,
+    /// One of the listed strings
+    Choice(Vec<String>),
+    /// Random lowercase letters of the given length
+    Text(usize),
+    /// A random UUID string
+    Uuid,
+    /// The same JSON value in every sample
+    Constant(Value),
+}
+
+/// One field of a generator spec.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSpec {
+    /// Field name; dots separate nested object keys
+    pub name: String,
+    /// What to generate for the field
+    pub kind: FieldKind,
+}
+
+/// Parses a generator spec, one `name: type` field per line.
+///
+/// Blank lines and lines starting with `#` are ignored. Supported types are
+/// `int MIN..MAX`, `float MIN..MAX`, `bool`, `choice a | b | c`, `text LEN`,
+/// `uuid` and `const JSON`.
+///
+/// # Returns
+///
+/// The parsed fields, or an error naming the offending line.
+pub fn parse_spec(spec: &str) -> Result<Vec<FieldSpec>, String> {
+    let mut fields = Vec::new();
+    for (line_no, line) in spec.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let err = |msg: &str| format!("Line {}: {}", line_no + 1, msg);
+        let (name, ty) = line
+            .split_once(':')
+            .ok_or_else(|| err("expected \"name: type\""))?;
+        let name = name.trim();
+        if name.is_empty() || name.split('.').any(|part| part.trim().is_empty()) {
+            return Err(err("field name is empty"));
+        }
+        let ty = ty.trim();
+        let (keyword, args) = ty.split_once(char::is_whitespace).unwrap_or((ty, ""));
+        let args = args.trim();
+
+        let kind = match keyword {
+            "int" => {
+                let (min, max) = parse_range(args).ok_or_else(|| err("expected \"int MIN..MAX\""))?;
+                let min: i64 = min.parse().map_err(|_| err("invalid int minimum"))?;
+                let max: i64 = max.parse().map_err(|_| err("invalid int maximum"))?;
+                if min > max {
+                    return Err(err("int minimum is larger than maximum"));
+                }
+                FieldKind::Integer { min, max }
+            }
+            "float" => {
+                let (min, max) =
+                    parse_range(args).ok_or_else(|| err("expected \"float MIN..MAX\""))?;
+                let min: f64 = min.parse().map_err(|_| err("invalid float minimum"))?;
+                let max: f64 = max.parse().map_err(|_| err("invalid float maximum"))?;
+                if !(min.is_finite() && max.is_finite()) || min > max {
+                    return Err(err("float range is invalid"));
+                }
+                FieldKind::Float { min, max }
+            }
+            "bool" => FieldKind::Boolean,
+            "choice" => {
+                let choices: Vec<String> = args
+                    .split('|')
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect();
+                if choices.is_empty() {
+                    return Err(err("expected \"choice a | b | c\""));
+                }
+                FieldKind::Choice(choices)
+            }
+            "text" => {
+                let len = if args.is_empty() {
+                    8
+                } else {
+                    args.parse().map_err(|_| err("invalid text length"))?
+                };
+                FieldKind::Text(len)
+            }
+            "uuid" => FieldKind::Uuid,
+            "const" => FieldKind::Constant(
+                serde_json::from_str(args).map_err(|_| err("const value must be JSON"))?,
+            ),
+            other => return Err(err(&format!("unknown type \"{}\"", other))),
+        };
+        fields.push(FieldSpec {
+            name: name.to_string(),
+            kind,
+        });
+    }
+    Ok(fields)
+}
+
+/// Generates one sample object for the given fields.
+///
+/// # Arguments
+///
+/// * `fields` - The parsed spec
+/// * `seed` - Seed for the pseudo-random values; equal seeds give equal samples
+pub fn generate_sample(fields: &[FieldSpec], seed: u64) -> Value {
+    let mut rng = SplitMix64(seed);
+    let mut root = Map::new();
+    for field in fields {
+        let value = match &field.kind {
+            FieldKind::Integer { min, max } => {
+                let span = (*max as i128 - *min as i128 + 1) as u128;
+                let offset = (rng.next() as u128 % span) as i128;
+                Value::from((*min as i128 + offset) as i64)
+            }
+            FieldKind::Float { min, max } => {
+                let v = min + rng.next_f64() * (max - min);
+                Value::from((v * 100.0).round() / 100.0)
+            }
+            FieldKind::Boolean => Value::Bool(rng.next() & 1 == 1),
+            FieldKind::Choice(choices) => {
+                Value::String(choices[(rng.next() % choices.len() as u64) as usize].clone())
+            }
+            FieldKind::Text(len) => Value::String(
+                (0..*len)
+                    .map(|_| (b'a' + (rng.next() % 26) as u8) as char)
+                    .collect(),
+            ),
+            FieldKind::Uuid => {
+                let bytes = ((rng.next() as u128) << 64 | rng.next() as u128).to_be_bytes();
+                Value::String(uuid::Builder::from_random_bytes(bytes).into_uuid().to_string())
+            }
+            FieldKind::Constant(value) => value.clone(),
+        };
+        insert_dotted(&mut root, &field.name, value);
+    }
+    Value::Object(root)
+}
+
+/// Splits `MIN..MAX` into its two bounds.
+fn parse_range(args: &str) -> Option<(&str, &str)> {
+    let (min, max) = args.split_once("..")?;
+    Some((min.trim(), max.trim()))
+}
+
+/// Inserts `value` at a dotted path, creating (or replacing non-object) parents.
+fn insert_dotted(root: &mut Map<String, Value>, name: &str, value: Value) {
+    let mut parts: Vec<&str> = name.split('.').map(str::trim).collect();
+    let last = parts.pop().unwrap_or_default();
+    let mut map = root;
+    for part in parts {
+        let entry = map
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !entry.is_object() {
+            *entry = Value::Object(Map::new());
+        }
+        map = entry.as_object_mut().expect("just made an object");
+    }
+    map.insert(last.to_string(), value);
+}
+
+/// Small deterministic generator; quality is plenty for sample data and
+/// random node failures.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_spec_and_generate_nested_sample() {
+        let fields = parse_spec(
+            "# order data\nid: int 1..3\nprice: float 1..2\nstatus: choice a | b\ncustomer.name: text 4\ncustomer.vip: bool\nid2: uuid\nsource: const \"test\"\n",
+        )
+        .unwrap();
+        assert_eq!(fields.len(), 7);
+        assert_eq!(fields[0].kind, FieldKind::Integer { min: 1, max: 3 });
+
+        for seed in 0..20 {
+            let sample = generate_sample(&fields, seed);
+            let id = sample["id"].as_i64().unwrap();
+            assert!((1..=3).contains(&id));
+            let price = sample["price"].as_f64().unwrap();
+            assert!((1.0..=2.0).contains(&price));
+            assert!(["a", "b"].contains(&sample["status"].as_str().unwrap()));
+            assert_eq!(sample["customer"]["name"].as_str().unwrap().len(), 4);
+            assert!(sample["customer"]["vip"].is_boolean());
+            assert!(uuid::Uuid::parse_str(sample["id2"].as_str().unwrap()).is_ok());
+            assert_eq!(sample["source"], json!("test"));
+        }
+        assert_eq!(generate_sample(&fields, 7), generate_sample(&fields, 7));
+        assert_ne!(generate_sample(&fields, 7), generate_sample(&fields, 8));
+    }
+
+    #[test]
+    fn test_parse_spec_reports_line_numbers() {
+        assert_eq!(
+            parse_spec("a: int 1..2\n\nb: int 5..1").unwrap_err(),
+            "Line 3: int minimum is larger than maximum"
+        );
+        assert!(parse_spec("nocolon").unwrap_err().starts_with("Line 1"));
+        assert!(parse_spec("x: colour").unwrap_err().contains("unknown type"));
+        assert!(parse_spec("x: choice").is_err());
+        assert!(parse_spec("a..b: bool").is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Checks that a simulation run can be repeated exactly.
+//!
+//! A document run twice with the same seed should go through the same states
+//! step after step. [`check_determinism`] runs two copies of a flowchart side
+//! by side, each on a thread of its own with a freshly built node map, so
+//! anything that depends on `HashMap` iteration order or on script state left
+//! over from another run makes them drift apart. After every step it compares
+//! a hash of each run's simulation state and stops at the first step where
+//! they differ, naming the nodes and connections that did.
+//!
+//! Message and trace IDs are random by design and are left out of the hash;
+//! everything else a step can change is in it. [`assert_deterministic`] is
+//! the same check for tests.
+
+use crate::simulation::SimulationEngine;
+use crate::types::*;
+use serde_json::json;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::mpsc;
+
+/// The first step on which two runs of the same flowchart differed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Step after which the states differed
+    pub step: u64,
+    /// Nodes and connections whose state differed, e.g. `node Sink`
+    pub differences: Vec<String>,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The runs differed after step {}", self.step)?;
+        if !self.differences.is_empty() {
+            write!(f, ": {}", self.differences.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Two runs that matched on every step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeterminismReport {
+    /// Steps compared
+    pub steps: u64,
+    /// State hash both runs ended with
+    pub final_hash: u64,
+}
+
+/// Hash of `value`'s JSON.
+fn json_hash(value: &serde_json::Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The simulation state of each node, ordered by ID, and of each connection,
+/// in order, as a label and a hash.
+pub fn state_parts(flowchart: &Flowchart) -> Vec<(String, u64)> {
+    let mut nodes: Vec<&FlowchartNode> = flowchart.nodes.values().collect();
+    nodes.sort_by_key(|node| node.id);
+    let name = |id: &NodeId| flowchart.nodes.get(id).map_or_else(|| id.to_string(), |node| node.name.clone());
+
+    let mut parts: Vec<(String, u64)> = nodes
+        .iter()
+        .map(|node| {
+            let state = json!({ "state": node.state, "type": node.node_type });
+            (format!("node {}", node.name), json_hash(&state))
+        })
+        .collect();
+    parts.extend(flowchart.connections.iter().map(|connection| {
+        let messages: Vec<serde_json::Value> = connection
+            .messages
+            .iter()
+            .map(|message| {
+                json!({
+                    "data": message.data,
+                    "attempts": message.attempts,
+                    "retry_at": message.retry_at,
+                    "priority": message.priority,
+                    "traced": message.trace_id.is_some(),
+                    "created_step": message.created_step,
+                })
+            })
+            .collect();
+        let label = format!("connection {} → {}", name(&connection.from), name(&connection.to));
+        (label, json_hash(&json!(messages)))
+    }));
+    parts
+}
+
+/// Hash of the whole simulation state of `flowchart`: its step and
+/// [`state_parts`].
+pub fn state_hash(flowchart: &Flowchart) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    flowchart.current_step.hash(&mut hasher);
+    for (_, hash) in state_parts(flowchart) {
+        hash.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// State hash and [`state_parts`] after a step.
+type StepState = (u64, Vec<(String, u64)>);
+
+/// Runs a copy of `flowchart` from the start on a new thread, sending the
+/// state after each of `steps` steps until the receiver hangs up.
+fn spawn_run(flowchart: &Flowchart, steps: u64) -> (mpsc::Receiver<StepState>, std::thread::JoinHandle<()>) {
+    let mut flowchart = flowchart.clone();
+    let (sender, receiver) = mpsc::sync_channel(1);
+    let handle = std::thread::spawn(move || {
+        // A map built on this thread iterates in an order of its own
+        flowchart.nodes = flowchart.nodes.drain().collect();
+        flowchart.reset_simulation();
+        flowchart.simulation_state = SimulationState::Running;
+        let mut engine = SimulationEngine::new();
+        for _ in 0..steps {
+            let delivered = engine.step(&mut flowchart);
+            engine.deliver_messages(delivered, &mut flowchart);
+            if sender.send((state_hash(&flowchart), state_parts(&flowchart))).is_err() {
+                break;
+            }
+        }
+    });
+    (receiver, handle)
+}
+
+/// Runs `flowchart` twice from the start with its run settings for `steps`
+/// steps and compares their states after every step.
+///
+/// # Panics
+///
+/// If either run panics.
+pub fn check_determinism(flowchart: &Flowchart, steps: u64) -> Result<DeterminismReport, Divergence> {
+    let (first, first_handle) = spawn_run(flowchart, steps);
+    let (second, second_handle) = spawn_run(flowchart, steps);
+    let mut result = Ok(DeterminismReport { steps: 0, final_hash: state_hash(flowchart) });
+    for step in 1..=steps {
+        let (Ok((hash, parts)), Ok((other_hash, other_parts))) = (first.recv(), second.recv()) else {
+            break;
+        };
+        if hash != other_hash {
+            let mut differences: Vec<String> = parts
+                .iter()
+                .zip(&other_parts)
+                .filter(|(a, b)| a.1 != b.1)
+                .map(|(a, _)| a.0.clone())
+                .collect();
+            differences.dedup();
+            result = Err(Divergence { step, differences });
+            break;
+        }
+        result = Ok(DeterminismReport { steps: step, final_hash: hash });
+    }
+    drop((first, second));
+    for handle in [first_handle, second_handle] {
+        if let Err(panic) = handle.join() {
+            std::panic::resume_unwind(panic);
+        }
+    }
+    result
+}
+
+/// Asserts that two runs of `flowchart` go through the same states for
+/// `steps` steps.
+///
+/// # Panics
+///
+/// Naming the first step and the nodes and connections that differed.
+pub fn assert_deterministic(flowchart: &Flowchart, steps: u64) {
+    if let Err(divergence) = check_determinism(flowchart, steps) {
+        panic!("{}", divergence);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::{all_examples, build_example, ExampleKind};
+
+    #[test]
+    fn examples_run_the_same_twice() {
+        // The ETL example stamps its records with the current time
+        for example in all_examples().iter().filter(|e| e.kind != ExampleKind::EtlPipeline) {
+            assert_deterministic(&build_example(example.kind), 60);
+        }
+    }
+
+    #[test]
+    fn scripts_using_randomness_are_reported() {
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "Source".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 1,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        let transformer = flowchart.add_node(FlowchartNode::new(
+            "Dice".into(),
+            (0.0, 0.0),
+            NodeType::Transformer {
+                script: "function transform(input) { return { roll: Math.random() }; }".into(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: RetryPolicy::default(),
+            },
+        ));
+        let consumer = flowchart.add_node(FlowchartNode::new(
+            "Sink".into(),
+            (0.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: "$.never".into(),
+            },
+        ));
+        flowchart.add_connection(producer, transformer).unwrap();
+        flowchart.add_connection(transformer, consumer).unwrap();
+
+        let divergence = check_determinism(&flowchart, 10).unwrap_err();
+        assert_eq!(divergence.differences, vec!["connection Dice → Sink".to_string()]);
+        assert!(divergence.to_string().starts_with(&format!("The runs differed after step {}", divergence.step)));
+    }
+}
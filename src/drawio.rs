@@ -0,0 +1,198 @@
+//! Export to the draw.io (diagrams.net) file format.
+//!
+//! [`to_drawio`] writes an uncompressed `.drawio` file: one vertex per node
+//! with its canvas geometry, label and the fill color it has in SVG export,
+//! one edge per connection joined to its nodes, and a rectangle behind the
+//! members of each group. The result opens in diagrams.net for further
+//! annotation; the simulation settings of the nodes are not included.
+
+use crate::bpmn::escape;
+use crate::constants::{GRID_SIZE, GROUP_CORNER_RADIUS, GROUP_PADDING, NODE_HEIGHT, NODE_WIDTH};
+use crate::types::*;
+use std::fmt::Write;
+
+/// Writes `flowchart` as a draw.io diagram.
+pub fn to_drawio(flowchart: &Flowchart) -> String {
+    let mut nodes: Vec<&FlowchartNode> = flowchart.nodes.values().collect();
+    nodes.sort_by_key(|node| node.id);
+    let mut groups: Vec<&Group> = flowchart.groups.values().collect();
+    groups.sort_by_key(|group| group.id);
+
+    let mut xml = String::new();
+    xml.push_str("<mxfile host=\"flowchart-tool\">\n");
+    xml.push_str("  <diagram id=\"flowchart\" name=\"Flowchart\">\n");
+    let _ = writeln!(
+        xml,
+        "    <mxGraphModel grid=\"1\" gridSize=\"{}\" guides=\"1\" arrows=\"1\" connect=\"1\" page=\"0\">",
+        GRID_SIZE
+    );
+    xml.push_str("      <root>\n");
+    xml.push_str("        <mxCell id=\"0\" />\n");
+    xml.push_str("        <mxCell id=\"1\" parent=\"0\" />\n");
+
+    // Groups first so they are drawn behind the nodes
+    for group in groups {
+        let members = group.members.iter().filter_map(|id| flowchart.nodes.get(id));
+        let Some((min_x, min_y, max_x, max_y)) = node_bounds(members) else {
+            continue;
+        };
+        let style = format!(
+            "rounded=1;arcSize={};absoluteArcSize=1;whiteSpace=wrap;html=1;fillColor=#808080;fillOpacity=8;strokeColor=#808080;strokeOpacity=50;verticalAlign=bottom;align=left;spacingLeft=6;fontColor=#444444;",
+            GROUP_CORNER_RADIUS * 2.0
+        );
+        write_vertex(
+            &mut xml,
+            &format!("group-{}", group.id),
+            &group.name,
+            &style,
+            (
+                min_x - GROUP_PADDING,
+                min_y - GROUP_PADDING,
+                max_x - min_x + 2.0 * GROUP_PADDING,
+                max_y - min_y + 2.0 * GROUP_PADDING,
+            ),
+        );
+    }
+
+    for node in &nodes {
+        let fill = match node.node_type {
+            NodeType::Producer { .. } => "#90EE90",
+            NodeType::Consumer { .. } => "#FF9999",
+            NodeType::Transformer { .. } => "#ADD8E6",
+            NodeType::Reference { .. } => "#E6D28C",
+        };
+        let style = format!(
+            "rounded=1;whiteSpace=wrap;html=1;fillColor={};strokeColor=#000000;fontColor=#000000;",
+            fill
+        );
+        write_vertex(
+            &mut xml,
+            &cell_id(&node.id),
+            &node.name,
+            &style,
+            (
+                node.position.0 - NODE_WIDTH / 2.0,
+                node.position.1 - NODE_HEIGHT / 2.0,
+                NODE_WIDTH,
+                NODE_HEIGHT,
+            ),
+        );
+    }
+
+    let connections = flowchart
+        .connections
+        .iter()
+        .filter(|conn| flowchart.nodes.contains_key(&conn.from) && flowchart.nodes.contains_key(&conn.to));
+    for (index, conn) in connections.enumerate() {
+        let _ = writeln!(
+            xml,
+            "        <mxCell id=\"edge-{}\" style=\"endArrow=classic;html=1;strokeColor=#404040;\" edge=\"1\" parent=\"1\" source=\"{}\" target=\"{}\">",
+            index + 1,
+            cell_id(&conn.from),
+            cell_id(&conn.to)
+        );
+        xml.push_str("          <mxGeometry relative=\"1\" as=\"geometry\" />\n");
+        xml.push_str("        </mxCell>\n");
+    }
+
+    xml.push_str("      </root>\n");
+    xml.push_str("    </mxGraphModel>\n");
+    xml.push_str("  </diagram>\n");
+    xml.push_str("</mxfile>\n");
+    xml
+}
+
+/// The draw.io cell id of a node
+fn cell_id(id: &NodeId) -> String {
+    format!("node-{}", id)
+}
+
+/// Writes a vertex cell; `geometry` is (x, y, width, height) of its top-left corner and size.
+fn write_vertex(xml: &mut String, id: &str, label: &str, style: &str, geometry: (f32, f32, f32, f32)) {
+    // Labels are HTML (for wrapping), so the name is escaped once for HTML and once for the attribute
+    let _ = writeln!(
+        xml,
+        "        <mxCell id=\"{}\" value=\"{}\" style=\"{}\" vertex=\"1\" parent=\"1\">",
+        id,
+        escape(&escape(label)),
+        style
+    );
+    let (x, y, width, height) = geometry;
+    let _ = writeln!(
+        xml,
+        "          <mxGeometry x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" as=\"geometry\" />",
+        x, y, width, height
+    );
+    xml.push_str("        </mxCell>\n");
+}
+
+/// Smallest box around the node rects of `nodes`, as (min x, min y, max x, max y)
+fn node_bounds<'a>(nodes: impl Iterator<Item = &'a FlowchartNode>) -> Option<(f32, f32, f32, f32)> {
+    nodes.fold(None, |acc, node| {
+        let (x, y) = node.position;
+        let rect = (
+            x - NODE_WIDTH / 2.0,
+            y - NODE_HEIGHT / 2.0,
+            x + NODE_WIDTH / 2.0,
+            y + NODE_HEIGHT / 2.0,
+        );
+        Some(match acc {
+            None => rect,
+            Some((a, b, c, d)) => (a.min(rect.0), b.min(rect.1), c.max(rect.2), d.max(rect.3)),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::{build_example, ExampleKind};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_drawio_export_has_geometry_labels_and_edges() {
+        let mut flowchart = build_example(ExampleKind::DecisionBranch);
+        let first = *flowchart.nodes.keys().min().unwrap();
+        flowchart.nodes.get_mut(&first).unwrap().name = "Check <stock> & ship".to_string();
+        let group = Group {
+            id: Uuid::new_v4(),
+            name: "Warehouse".to_string(),
+            members: vec![first],
+            drawing: GroupDrawingMode::Polygon,
+            z_order: 0,
+        };
+        flowchart.groups.insert(group.id, group);
+
+        let xml = to_drawio(&flowchart);
+        let document = roxmltree::Document::parse(&xml).unwrap();
+        let cells: Vec<_> = document.descendants().filter(|n| n.has_tag_name("mxCell")).collect();
+        let vertices: HashMap<&str, roxmltree::Node> = cells
+            .iter()
+            .filter(|c| c.attribute("vertex") == Some("1"))
+            .map(|c| (c.attribute("id").unwrap(), *c))
+            .collect();
+        assert_eq!(vertices.len(), flowchart.nodes.len() + 1);
+
+        let node = &flowchart.nodes[&first];
+        let cell = vertices[cell_id(&first).as_str()];
+        assert_eq!(cell.attribute("value"), Some("Check &lt;stock&gt; &amp; ship"));
+        let geometry = cell.children().find(|n| n.has_tag_name("mxGeometry")).unwrap();
+        let number = |name| geometry.attribute(name).unwrap().parse::<f32>().unwrap();
+        assert_eq!(number("x"), node.position.0 - NODE_WIDTH / 2.0);
+        assert_eq!(number("y"), node.position.1 - NODE_HEIGHT / 2.0);
+        assert_eq!(number("width"), NODE_WIDTH);
+
+        // The group is listed before the nodes so it stays behind them
+        let group_index = cells.iter().position(|c| c.attribute("value") == Some("Warehouse")).unwrap();
+        let node_index = cells.iter().position(|c| c.attribute("id") == Some(cell_id(&first).as_str())).unwrap();
+        assert!(group_index < node_index);
+
+        let edges: Vec<_> = cells.iter().filter(|c| c.attribute("edge") == Some("1")).collect();
+        assert_eq!(edges.len(), flowchart.connections.len());
+        for (edge, conn) in edges.iter().zip(&flowchart.connections) {
+            assert_eq!(edge.attribute("source"), Some(cell_id(&conn.from).as_str()));
+            assert_eq!(edge.attribute("target"), Some(cell_id(&conn.to).as_str()));
+        }
+    }
+}
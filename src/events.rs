@@ -0,0 +1,229 @@
+//! Structured log of what happens during a simulation.
+//!
+//! The simulation engine records a [`SimEvent`] whenever a message is
+//! produced, delivered to a transformer, consumed, dropped, or a script
+//! fails. Events are kept in an [`EventLog`] ring buffer whose size is set in
+//! Preferences; the events panel narrows it down with an [`EventFilter`].
+//! Script failures are also kept as [`SimError`]s, together with the payload
+//! that caused them, for the errors list.
+
+use crate::constants::EVENT_LOG_DEFAULT_CAPACITY;
+use crate::types::*;
+use std::collections::VecDeque;
+
+/// What kind of thing happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EventKind {
+    /// A producer created a message
+    Produced,
+    /// A message was taken off a connection by a transformer
+    Delivered,
+    /// A consumer received a message
+    Consumed,
+    /// A transformer script failed
+    Error,
+    /// A message was lost: nowhere to send it, or its script failed for good
+    Dropped,
+}
+
+impl EventKind {
+    /// Every kind, in the order the events panel lists them.
+    pub const ALL: [EventKind; 5] = [
+        EventKind::Produced,
+        EventKind::Delivered,
+        EventKind::Consumed,
+        EventKind::Error,
+        EventKind::Dropped,
+    ];
+
+    /// Name shown in the events panel.
+    pub fn label(self) -> &'static str {
+        match self {
+            EventKind::Produced => "Produced",
+            EventKind::Delivered => "Delivered",
+            EventKind::Consumed => "Consumed",
+            EventKind::Error => "Error",
+            EventKind::Dropped => "Dropped",
+        }
+    }
+}
+
+/// One recorded simulation event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimEvent {
+    /// Step the event happened on
+    pub step: u64,
+    /// What happened
+    pub kind: EventKind,
+    /// Node the event happened at
+    pub node: NodeId,
+    /// Node the message came from, for deliveries and consumption
+    pub from: Option<NodeId>,
+    /// Trace of the message involved
+    pub trace_id: Option<TraceId>,
+    /// Error message or reason for a drop; empty otherwise
+    pub detail: String,
+}
+
+/// A script failure, with the message that caused it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimError {
+    /// Step the script failed on
+    pub step: u64,
+    /// Transformer whose script failed
+    pub node: NodeId,
+    /// Error raised by the script, with the retry attempt if it is retried
+    pub message: String,
+    /// Payload of the message being processed
+    pub payload: serde_json::Value,
+    /// Trace of the message being processed
+    pub trace_id: Option<TraceId>,
+}
+
+/// The most recent events, oldest first, up to a set capacity.
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    events: VecDeque<SimEvent>,
+    capacity: usize,
+    recorded: u64,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::with_capacity(EVENT_LOG_DEFAULT_CAPACITY)
+    }
+}
+
+impl EventLog {
+    /// Creates an empty log keeping at most `capacity` events.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity: capacity.max(1),
+            recorded: 0,
+        }
+    }
+
+    /// Most events kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes how many events are kept, dropping the oldest if needed.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.events.len() > self.capacity {
+            self.events.pop_front();
+        }
+    }
+
+    /// Appends `event`, dropping the oldest once the log is full.
+    pub fn push(&mut self, event: SimEvent) {
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+        self.recorded += 1;
+    }
+
+    /// The kept events, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &SimEvent> {
+        self.events.iter()
+    }
+
+    /// Number of kept events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true if no events are kept.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Events recorded since the log was last cleared, including dropped ones.
+    pub fn recorded(&self) -> u64 {
+        self.recorded
+    }
+
+    /// Forgets all events, e.g. when the simulation is reset. The capacity is kept.
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.recorded = 0;
+    }
+}
+
+/// Which events the events panel shows. The default shows everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// Kinds to hide
+    pub hidden_kinds: Vec<EventKind>,
+    /// Only events at or coming from this node
+    pub node: Option<NodeId>,
+}
+
+impl EventFilter {
+    /// Returns true if events of `kind` are shown.
+    pub fn shows_kind(&self, kind: EventKind) -> bool {
+        !self.hidden_kinds.contains(&kind)
+    }
+
+    /// Shows or hides events of `kind`.
+    pub fn set_kind_shown(&mut self, kind: EventKind, shown: bool) {
+        self.hidden_kinds.retain(|k| *k != kind);
+        if !shown {
+            self.hidden_kinds.push(kind);
+        }
+    }
+
+    /// Returns true if `event` passes the filter.
+    pub fn matches(&self, event: &SimEvent) -> bool {
+        self.shows_kind(event.kind)
+            && self
+                .node
+                .is_none_or(|node| event.node == node || event.from == Some(node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(step: u64, kind: EventKind, node: NodeId) -> SimEvent {
+        SimEvent {
+            step,
+            kind,
+            node,
+            from: None,
+            trace_id: None,
+            detail: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_ring_buffer_and_filter() {
+        let (a, b) = (NodeId::new_v4(), NodeId::new_v4());
+        let mut log = EventLog::with_capacity(3);
+        for step in 0..5 {
+            log.push(event(step, EventKind::Produced, a));
+        }
+        assert_eq!((log.len(), log.recorded()), (3, 5));
+        assert_eq!(log.iter().next().unwrap().step, 2);
+
+        log.push(SimEvent { from: Some(a), ..event(5, EventKind::Consumed, b) });
+        log.set_capacity(2);
+        assert_eq!(log.iter().map(|e| e.step).collect::<Vec<_>>(), vec![4, 5]);
+
+        let mut filter = EventFilter { node: Some(a), ..Default::default() };
+        assert_eq!(log.iter().filter(|e| filter.matches(e)).count(), 2);
+        filter.set_kind_shown(EventKind::Produced, false);
+        assert_eq!(log.iter().filter(|e| filter.matches(e)).count(), 1);
+        filter.node = Some(b);
+        filter.set_kind_shown(EventKind::Consumed, false);
+        assert_eq!(log.iter().filter(|e| filter.matches(e)).count(), 0);
+
+        log.clear();
+        assert!(log.is_empty());
+        assert_eq!((log.recorded(), log.capacity()), (0, 2));
+    }
+}
@@ -135,6 +135,7 @@ fn build_basic_linear() -> Flowchart {
             selected_outputs: None,
             globals: Default::default(),
             initial_globals: Default::default(),
+            retry: Default::default(),
         },
     );
     let trans_id = fc.add_node(trans);
@@ -143,7 +144,13 @@ fn build_basic_linear() -> Flowchart {
     let cons = FlowchartNode::new(
         "Consumer".into(),
         (600.0, 200.0),
-        NodeType::Consumer { consumption_rate: 1 },
+        NodeType::Consumer {
+            consumption_rate: 1,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let cons_id = fc.add_node(cons);
 
@@ -199,6 +206,7 @@ fn build_decision_branch() -> Flowchart {
             selected_outputs: None,
             globals: globals.clone(),
             initial_globals: globals,
+            retry: Default::default(),
         },
     );
     let branch_id = fc.add_node(branch);
@@ -206,14 +214,26 @@ fn build_decision_branch() -> Flowchart {
     let even = FlowchartNode::new(
         "Even Bin".into(),
         (550.0, 100.0),
-        NodeType::Consumer { consumption_rate: 4 },
+        NodeType::Consumer {
+            consumption_rate: 4,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let even_id = fc.add_node(even);
 
     let odd = FlowchartNode::new(
         "Odd Bin".into(),
         (550.0, 260.0),
-        NodeType::Consumer { consumption_rate: 4 },
+        NodeType::Consumer {
+            consumption_rate: 4,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let odd_id = fc.add_node(odd);
 
@@ -252,6 +272,7 @@ fn build_etl_pipeline() -> Flowchart {
             selected_outputs: None,
             globals: Default::default(),
             initial_globals: Default::default(),
+            retry: Default::default(),
         },
     );
     let extract_id = fc.add_node(extract);
@@ -276,6 +297,7 @@ fn build_etl_pipeline() -> Flowchart {
             selected_outputs: None,
             globals: Default::default(),
             initial_globals: Default::default(),
+            retry: Default::default(),
         },
     );
     let transform_id = fc.add_node(transform);
@@ -306,6 +328,7 @@ fn build_etl_pipeline() -> Flowchart {
             selected_outputs: None,
             globals: globals.clone(),
             initial_globals: globals,
+            retry: Default::default(),
         },
     );
     let load_id = fc.add_node(load);
@@ -313,14 +336,26 @@ fn build_etl_pipeline() -> Flowchart {
     let success = FlowchartNode::new(
         "Warehouse".into(),
         (900.0, 200.0),
-        NodeType::Consumer { consumption_rate: 8 },
+        NodeType::Consumer {
+            consumption_rate: 8,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let success_id = fc.add_node(success);
 
     let retry = FlowchartNode::new(
         "Retry Queue".into(),
         (900.0, 320.0),
-        NodeType::Consumer { consumption_rate: 2 },
+        NodeType::Consumer {
+            consumption_rate: 2,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let retry_id = fc.add_node(retry);
 
@@ -387,6 +422,7 @@ fn build_socket_client_server() -> Flowchart {
             selected_outputs: None,
             globals: Default::default(),
             initial_globals: Default::default(),
+            retry: Default::default(),
         },
     );
     let router_id = fc.add_node(router);
@@ -424,6 +460,7 @@ fn build_socket_client_server() -> Flowchart {
             selected_outputs: None,
             globals: Default::default(),
             initial_globals: Default::default(),
+            retry: Default::default(),
         },
     );
     let server_id = fc.add_node(server);
@@ -457,6 +494,7 @@ fn build_socket_client_server() -> Flowchart {
             selected_outputs: None,
             globals: Default::default(),
             initial_globals: Default::default(),
+            retry: Default::default(),
         },
     );
     let client_handler_id = fc.add_node(client_handler);
@@ -465,7 +503,13 @@ fn build_socket_client_server() -> Flowchart {
     let client_sink = FlowchartNode::new(
         "Client Sink".into(),
         (800.0, 240.0),
-        NodeType::Consumer { consumption_rate: 4 },
+        NodeType::Consumer {
+            consumption_rate: 4,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let client_sink_id = fc.add_node(client_sink);
 
@@ -525,6 +569,7 @@ fn build_network_packet_traversal() -> Flowchart {
             selected_outputs: None,
             globals: Default::default(),
             initial_globals: Default::default(),
+            retry: Default::default(),
         },
     );
     let core_id = fc.add_node(core);
@@ -551,6 +596,7 @@ fn build_network_packet_traversal() -> Flowchart {
             selected_outputs: None,
             globals: Default::default(),
             initial_globals: Default::default(),
+            retry: Default::default(),
         },
     );
     let switch_a_id = fc.add_node(switch_a);
@@ -577,6 +623,7 @@ fn build_network_packet_traversal() -> Flowchart {
             selected_outputs: None,
             globals: Default::default(),
             initial_globals: Default::default(),
+            retry: Default::default(),
         },
     );
     let switch_b_id = fc.add_node(switch_b);
@@ -604,6 +651,7 @@ fn build_network_packet_traversal() -> Flowchart {
             selected_outputs: None,
             globals: Default::default(),
             initial_globals: Default::default(),
+            retry: Default::default(),
         },
     );
     let switch_c_id = fc.add_node(switch_c);
@@ -629,14 +677,26 @@ fn build_network_packet_traversal() -> Flowchart {
     let a2 = FlowchartNode::new(
         "A2".into(),
         (-400.0, 240.0),
-        NodeType::Consumer { consumption_rate: 6 },
+        NodeType::Consumer {
+            consumption_rate: 6,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let a2_id = fc.add_node(a2);
 
     let a3 = FlowchartNode::new(
         "A3".into(),
         (-400.0, 300.0),
-        NodeType::Consumer { consumption_rate: 6 },
+        NodeType::Consumer {
+            consumption_rate: 6,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let a3_id = fc.add_node(a3);
 
@@ -661,14 +721,26 @@ fn build_network_packet_traversal() -> Flowchart {
     let b2 = FlowchartNode::new(
         "B2".into(),
         (0.0, 270.0),
-        NodeType::Consumer { consumption_rate: 6 },
+        NodeType::Consumer {
+            consumption_rate: 6,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let b2_id = fc.add_node(b2);
 
     let b3 = FlowchartNode::new(
         "B3".into(),
         (0.0, 330.0),
-        NodeType::Consumer { consumption_rate: 6 },
+        NodeType::Consumer {
+            consumption_rate: 6,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let b3_id = fc.add_node(b3);
 
@@ -676,21 +748,39 @@ fn build_network_packet_traversal() -> Flowchart {
     let c1 = FlowchartNode::new(
         "C1".into(),
         (400.0, 220.0),
-        NodeType::Consumer { consumption_rate: 6 },
+        NodeType::Consumer {
+            consumption_rate: 6,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let c1_id = fc.add_node(c1);
 
     let c2 = FlowchartNode::new(
         "C2".into(),
         (400.0, 280.0),
-        NodeType::Consumer { consumption_rate: 6 },
+        NodeType::Consumer {
+            consumption_rate: 6,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let c2_id = fc.add_node(c2);
 
     let c3 = FlowchartNode::new(
         "C3".into(),
         (400.0, 340.0),
-        NodeType::Consumer { consumption_rate: 6 },
+        NodeType::Consumer {
+            consumption_rate: 6,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        },
     );
     let c3_id = fc.add_node(c3);
 
@@ -726,24 +816,28 @@ fn build_network_packet_traversal() -> Flowchart {
         name: "Subnet A".into(),
         members: vec![switch_a_id, a1_id, a2_id, a3_id],
         drawing: GroupDrawingMode::Rectangle,
+        z_order: 0,
     };
     let subnet_b = Group {
         id: Uuid::new_v4(),
         name: "Subnet B".into(),
         members: vec![switch_b_id, b1_id, b2_id, b3_id],
         drawing: GroupDrawingMode::Rectangle,
+        z_order: 0,
     };
     let subnet_c = Group {
         id: Uuid::new_v4(),
         name: "Subnet C".into(),
         members: vec![switch_c_id, c1_id, c2_id, c3_id],
         drawing: GroupDrawingMode::Rectangle,
+        z_order: 0,
     };
     let core_grp = Group {
         id: Uuid::new_v4(),
         name: "Core".into(),
         members: vec![core_id],
         drawing: GroupDrawingMode::Rectangle,
+        z_order: 0,
     };
 
     fc.groups.insert(subnet_a.id, subnet_a);
@@ -765,7 +859,7 @@ mod example_quiescence_tests {
         for _ in 0..cycles {
             let deliveries = engine.step(flowchart);
             let count = deliveries.len();
-            for (node_id, msg) in deliveries {
+            for (node_id, _, msg) in deliveries {
                 // Ignore individual delivery errors for this helper; surface via panic on Err
                 engine
                     .deliver_message(node_id, msg, flowchart)
@@ -0,0 +1,411 @@
+//! Simulation runs from the command line, without a window.
+//!
+//! `flowchart_tool --headless model.json` runs the document's simulation with
+//! its saved [`RunConfig`] and prints the run summary as JSON, the same JSON
+//! the Run Statistics dashboard exports, so it can be loaded as a baseline.
+//! Flags override single settings for that run without changing the file:
+//!
+//! - `--seed N` - seed of random failures and producer jitter
+//! - `--max-steps N` - end the run after `N` steps
+//! - `--pause-at N` - end the run on step `N`
+//! - `--warm-up N` - steps whose statistics are discarded
+//! - `--measure N` - length of the measurement window, 0 for open-ended
+//! - `--continue-on-error` / `--stop-on-error` - whether script errors end the run
+//!
+//! `flowchart_tool sim model.json --steps N` is the same run for CI pipelines
+//! and scripts, printing a table of per-node message counts instead of the
+//! summary (see [`format_counts`]). `--steps` is short for `--max-steps`, and
+//! `--counts` or `--json` pick either output in both forms. `--csv` prints the
+//! per-step metrics instead, the CSV the toolbar's Export CSV button saves.
+//!
+//! The run goes through the same stepping as the app's Start button, so a
+//! document behaves the same in both; only the steps per frame don't matter
+//! here.
+
+use crate::types::{Flowchart, NodeType, RunConfig, RunHalt};
+use crate::ui::FlowchartApp;
+
+/// Run settings given on the command line, each replacing the document's.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunOverrides {
+    /// Replaces [`RunConfig::seed`]
+    pub seed: Option<u64>,
+    /// Replaces [`RunConfig::max_steps`]
+    pub max_steps: Option<u64>,
+    /// Replaces [`RunConfig::pause_at_step`]
+    pub pause_at_step: Option<u64>,
+    /// Replaces [`RunConfig::warm_up_steps`]
+    pub warm_up_steps: Option<u64>,
+    /// Replaces [`RunConfig::measurement_steps`]
+    pub measurement_steps: Option<u64>,
+    /// Replaces [`RunConfig::continue_on_error`]
+    pub continue_on_error: Option<bool>,
+}
+
+impl RunOverrides {
+    /// Applies the overrides to `config`.
+    pub fn apply(&self, config: &mut RunConfig) {
+        if let Some(seed) = self.seed {
+            config.seed = seed;
+        }
+        if let Some(steps) = self.max_steps {
+            config.max_steps = Some(steps);
+        }
+        if let Some(step) = self.pause_at_step {
+            config.pause_at_step = Some(step);
+        }
+        if let Some(steps) = self.warm_up_steps {
+            config.warm_up_steps = steps;
+        }
+        if let Some(steps) = self.measurement_steps {
+            config.measurement_steps = steps;
+        }
+        if let Some(carry_on) = self.continue_on_error {
+            config.continue_on_error = carry_on;
+        }
+    }
+}
+
+/// What a command-line run prints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeadlessOutput {
+    /// The run summary as JSON
+    #[default]
+    Summary,
+    /// A table of per-node message counts
+    Counts,
+    /// The per-step metrics as CSV
+    Steps,
+}
+
+/// A command-line run: the document to run and the settings to override.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadlessArgs {
+    /// Path of the flowchart file
+    pub path: String,
+    /// Settings replacing the document's
+    pub overrides: RunOverrides,
+    /// What to print
+    pub output: HeadlessOutput,
+}
+
+impl HeadlessArgs {
+    /// Parses the arguments after the program name. `--headless` itself is
+    /// skipped, and a leading `sim` subcommand switches to the counts table.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut path = None;
+        let mut overrides = RunOverrides::default();
+        let mut output = HeadlessOutput::Summary;
+        let mut args = args.into_iter().peekable();
+        if args.peek().is_some_and(|arg| arg == "sim") {
+            args.next();
+            output = HeadlessOutput::Counts;
+        }
+        while let Some(arg) = args.next() {
+            let mut number = |name: &str| -> Result<u64, String> {
+                let value = args.next().ok_or_else(|| format!("{} needs a number", name))?;
+                value.parse().map_err(|_| format!("{} needs a number, got '{}'", name, value))
+            };
+            match arg.as_str() {
+                "--headless" => {}
+                "--seed" => overrides.seed = Some(number("--seed")?),
+                "--max-steps" | "--steps" => match number(&arg)? {
+                    0 => return Err(format!("{} must be at least 1", arg)),
+                    steps => overrides.max_steps = Some(steps),
+                },
+                "--pause-at" => match number("--pause-at")? {
+                    0 => return Err("--pause-at must be at least 1".to_string()),
+                    step => overrides.pause_at_step = Some(step),
+                },
+                "--warm-up" => overrides.warm_up_steps = Some(number("--warm-up")?),
+                "--measure" => overrides.measurement_steps = Some(number("--measure")?),
+                "--continue-on-error" => overrides.continue_on_error = Some(true),
+                "--stop-on-error" => overrides.continue_on_error = Some(false),
+                "--counts" => output = HeadlessOutput::Counts,
+                "--json" => output = HeadlessOutput::Summary,
+                "--csv" => output = HeadlessOutput::Steps,
+                flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+                _ if path.is_some() => return Err(format!("Only one flowchart file can be run, got '{}' too", arg)),
+                _ => path = Some(arg),
+            }
+        }
+        let path = path.ok_or_else(|| "No flowchart file given".to_string())?;
+        Ok(Self { path, overrides, output })
+    }
+}
+
+/// Outcome of a command-line run.
+#[derive(Debug, Clone)]
+pub struct HeadlessRun {
+    /// Steps run
+    pub steps: u64,
+    /// What ended the run
+    pub halt: RunHalt,
+    /// The run summary as exported from the Run Statistics dashboard
+    pub summary_json: String,
+    /// Message counts of every node, by name
+    pub counts: Vec<NodeCount>,
+    /// The per-step metrics as CSV
+    pub steps_csv: String,
+}
+
+/// Message counts of one node at the end of a command-line run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeCount {
+    /// Name of the node
+    pub name: String,
+    /// "Producer", "Transformer", "Consumer" or "Reference"
+    pub kind: &'static str,
+    /// Messages the node produced; zero for all but producers
+    pub produced: u64,
+    /// Messages delivered to the node over the whole run, warm-up included
+    pub received: u64,
+    /// Messages still waiting on the connections into the node
+    pub waiting: usize,
+}
+
+/// The counts of every node of the app's flowchart after a run, sorted by name.
+fn node_counts(app: &FlowchartApp) -> Vec<NodeCount> {
+    let mut counts: Vec<NodeCount> = app
+        .flowchart
+        .nodes
+        .values()
+        .map(|node| {
+            let (kind, produced) = match &node.node_type {
+                NodeType::Producer { messages_produced, .. } => ("Producer", u64::from(*messages_produced)),
+                NodeType::Transformer { .. } => ("Transformer", 0),
+                NodeType::Consumer { .. } => ("Consumer", 0),
+                NodeType::Reference { .. } => ("Reference", 0),
+            };
+            NodeCount {
+                name: node.name.clone(),
+                kind,
+                produced,
+                received: app.simulation_engine.delivered_to(node.id),
+                waiting: app
+                    .flowchart
+                    .connections
+                    .iter()
+                    .filter(|c| c.to == node.id)
+                    .map(|c| c.messages.len())
+                    .sum(),
+            }
+        })
+        .collect();
+    counts.sort_by(|a, b| a.name.cmp(&b.name).then(a.kind.cmp(b.kind)));
+    counts
+}
+
+/// Formats `counts` as a table with a header row and aligned columns.
+pub fn format_counts(counts: &[NodeCount]) -> String {
+    let rows: Vec<[String; 5]> = std::iter::once(["Node", "Type", "Produced", "Received", "Waiting"].map(str::to_string))
+        .chain(counts.iter().map(|count| {
+            [
+                count.name.clone(),
+                count.kind.to_string(),
+                count.produced.to_string(),
+                count.received.to_string(),
+                count.waiting.to_string(),
+            ]
+        }))
+        .collect();
+    let mut widths = [0; 5];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            // Names and types line up on the left, numbers on the right
+            let [name, kind, produced, received, waiting] = row;
+            format!(
+                "{:<w0$}  {:<w1$}  {:>w2$}  {:>w3$}  {:>w4$}",
+                name,
+                kind,
+                produced,
+                received,
+                waiting,
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+                w3 = widths[3],
+                w4 = widths[4],
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Runs `flowchart` from the start until its run configuration ends it.
+///
+/// `path` is where the flowchart was read from; linked source files are
+/// resolved against it as in the app.
+pub fn run_flowchart(flowchart: Flowchart, path: Option<String>) -> Result<HeadlessRun, String> {
+    if !flowchart.run_config.ends() {
+        return Err("The run has no end: pass --steps, or set End after in Simulation → Run Statistics".to_string());
+    }
+    let mut app = FlowchartApp::default();
+    app.apply_loaded_flowchart_from_source(flowchart, path);
+    app.start_simulation();
+    let halt = loop {
+        if let Some(halt) = app.advance_running_simulation() {
+            break halt;
+        }
+    };
+    let summary_json = app.run_summary().to_json(app.simulation_engine.stats(), &app.flowchart)?;
+    Ok(HeadlessRun {
+        steps: app.flowchart.current_step,
+        halt,
+        summary_json,
+        counts: node_counts(&app),
+        steps_csv: app.simulation_engine.steps_csv(&app.flowchart),
+    })
+}
+
+/// Reads the flowchart named in `args`, applies the overrides and runs it.
+pub fn run(args: &HeadlessArgs) -> Result<HeadlessRun, String> {
+    let content =
+        std::fs::read_to_string(&args.path).map_err(|e| format!("Failed to read {}: {}", args.path, e))?;
+    let mut flowchart = Flowchart::from_json(&content).map_err(|e| format!("Failed to parse flowchart: {}", e))?;
+    args.overrides.apply(&mut flowchart.run_config);
+    run_flowchart(flowchart, Some(args.path.clone()))
+}
+
+/// Runs the command line's flowchart, printing the summary, the counts
+/// table or the per-step CSV to stdout and what ended the run to stderr.
+///
+/// Returns the exit code: 0 when the run ended as configured, 1 when it
+/// couldn't run and 2 when it stopped on a script error.
+pub fn run_from_args(args: impl IntoIterator<Item = String>) -> i32 {
+    match HeadlessArgs::parse(args).and_then(|args| Ok((run(&args)?, args.output))) {
+        Ok((run, output)) => {
+            match output {
+                HeadlessOutput::Summary => println!("{}", run.summary_json),
+                HeadlessOutput::Counts => println!("{}", format_counts(&run.counts)),
+                HeadlessOutput::Steps => print!("{}", run.steps_csv),
+            }
+            eprintln!("Ran {} steps: {}", run.steps, run.halt.describe());
+            if run.halt == RunHalt::Error {
+                2
+            } else {
+                0
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FlowchartNode, NodeType};
+    use serde_json::json;
+
+    fn args(list: &[&str]) -> Result<HeadlessArgs, String> {
+        HeadlessArgs::parse(list.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn flags_override_the_document_settings() {
+        let parsed = args(&["--headless", "model.json", "--seed", "9", "--max-steps", "40", "--continue-on-error"]).unwrap();
+        assert_eq!(parsed.path, "model.json");
+        let mut config = RunConfig { seed: 1, warm_up_steps: 5, ..Default::default() };
+        parsed.overrides.apply(&mut config);
+        assert_eq!(
+            config,
+            RunConfig {
+                seed: 9,
+                warm_up_steps: 5,
+                max_steps: Some(40),
+                continue_on_error: true,
+                ..Default::default()
+            }
+        );
+
+        assert!(args(&["--max-steps", "0", "a.json"]).is_err());
+        assert!(args(&["--seed", "x", "a.json"]).is_err());
+        assert!(args(&["--speed", "2", "a.json"]).is_err());
+        assert!(args(&["a.json", "b.json"]).is_err());
+        assert!(args(&["--seed", "3"]).is_err());
+        assert_eq!(parsed.output, HeadlessOutput::Summary);
+
+        let sim = args(&["sim", "model.json", "--steps", "25"]).unwrap();
+        assert_eq!((sim.output, sim.overrides.max_steps), (HeadlessOutput::Counts, Some(25)));
+        assert_eq!(args(&["sim", "model.json", "--json"]).unwrap().output, HeadlessOutput::Summary);
+        assert!(args(&["model.json", "sim"]).is_err());
+        assert_eq!(args(&["sim", "model.json", "--csv"]).unwrap().output, HeadlessOutput::Steps);
+    }
+
+    #[test]
+    fn runs_until_the_configured_end() {
+        let mut flowchart = Flowchart::new();
+        flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 5,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        assert!(run_flowchart(flowchart.clone(), None).is_err());
+
+        flowchart.run_config.max_steps = Some(12);
+        flowchart.run_config.steps_per_frame = 5;
+        let run = run_flowchart(flowchart.clone(), None).unwrap();
+        assert_eq!((run.steps, run.halt), (12, RunHalt::MaxSteps));
+        let summary: serde_json::Value = serde_json::from_str(&run.summary_json).unwrap();
+        assert_eq!(summary["produced"], 5);
+        assert_eq!(format_counts(&run.counts), "Node  Type      Produced  Received  Waiting\nP     Producer         5         0        0");
+
+        flowchart.run_config.pause_at_step = Some(3);
+        let run = run_flowchart(flowchart, None).unwrap();
+        assert_eq!((run.steps, run.halt), (3, RunHalt::PauseStep));
+    }
+
+
+    #[test]
+    fn counts_cover_the_warm_up_too() {
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 1,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        let consumer = flowchart.add_node(FlowchartNode::new(
+            "C".into(),
+            (200.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.add_connection(producer, consumer).unwrap();
+        flowchart.run_config.max_steps = Some(12);
+        flowchart.run_config.warm_up_steps = 8;
+
+        let run = run_flowchart(flowchart, None).unwrap();
+        let summary: serde_json::Value = serde_json::from_str(&run.summary_json).unwrap();
+        let (c, p) = (&run.counts[0], &run.counts[1]);
+        assert!(c.received > summary["delivered"].as_u64().unwrap(), "warm-up deliveries are counted");
+        assert_eq!(c.received + c.waiting as u64, p.produced);
+    }
+}
@@ -0,0 +1,646 @@
+//! Filtering and sampling of messages shown in the inspectors.
+//!
+//! The connection inspector and the node history list both narrow down what
+//! they show with a [`MessageFilter`]. Node histories are recorded by the
+//! simulation engine into a [`MessageHistory`], which can sample every k-th
+//! message per node and spill older ones to disk to keep memory bounded on
+//! long runs.
+
+use crate::constants::NODE_HISTORY_LIMIT;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::constants::HISTORY_SPILL_MAX_BYTES;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::spill::{SpillFile, SpillRef};
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+
+/// Criteria for narrowing down a list of messages. Empty criteria match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageFilter {
+    /// JSONPath expression that must select a value, optionally followed by a
+    /// comparison such as `$.status == "ok"` or `$.count > 3`; set it with
+    /// [`MessageFilter::set_json_path`] so it is compiled once
+    json_path: String,
+    /// `json_path` compiled, or why it doesn't parse; `None` while it is empty
+    condition: Option<Result<Condition, String>>,
+    /// Case-insensitive text that must appear in the serialized payload
+    pub text: String,
+    /// Prefix of the trace ID, as shown in the inspector (hyphens are ignored)
+    pub trace_id: String,
+    /// First step to include, inclusive
+    pub step_from: Option<u64>,
+    /// Last step to include, inclusive
+    pub step_to: Option<u64>,
+}
+
+impl MessageFilter {
+    /// Returns true if no criteria are set.
+    pub fn is_empty(&self) -> bool {
+        self.json_path.trim().is_empty()
+            && self.text.is_empty()
+            && self.trace_id.trim().is_empty()
+            && self.step_from.is_none()
+            && self.step_to.is_none()
+    }
+
+    /// The JSONPath expression, as typed.
+    pub fn json_path(&self) -> &str {
+        &self.json_path
+    }
+
+    /// Replaces the JSONPath expression and compiles it for [`MessageFilter::matches`].
+    pub fn set_json_path(&mut self, expression: impl Into<String>) {
+        self.json_path = expression.into();
+        self.condition = (!self.json_path.trim().is_empty()).then(|| parse_condition(&self.json_path));
+    }
+
+    /// Checks that the JSONPath expression can be parsed.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` for an empty or valid expression, or a description of the problem.
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.condition {
+            Some(Err(err)) => Err(err.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns true if `message` meets every criterion.
+    ///
+    /// An invalid JSONPath expression matches nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message to test
+    /// * `step` - Step the message was recorded on; `None` skips the step range
+    pub fn matches(&self, message: &Message, step: Option<u64>) -> bool {
+        if let Some(step) = step {
+            if self.step_from.is_some_and(|from| step < from)
+                || self.step_to.is_some_and(|to| step > to)
+            {
+                return false;
+            }
+        }
+
+        let trace = self.trace_id.trim().replace('-', "").to_ascii_lowercase();
+        if !trace.is_empty() {
+            let Some(trace_id) = message.trace_id else {
+                return false;
+            };
+            if !trace_id.simple().to_string().starts_with(&trace) {
+                return false;
+            }
+        }
+
+        if !self.text.is_empty() {
+            let payload = message.data.to_string().to_lowercase();
+            if !payload.contains(&self.text.to_lowercase()) {
+                return false;
+            }
+        }
+
+        match &self.condition {
+            Some(Ok(condition)) if !condition.holds(&message.data) => return false,
+            Some(Err(_)) => return false,
+            _ => {}
+        }
+
+        true
+    }
+}
+
+/// One message received by a node, as kept in its history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Step on which the message was delivered
+    pub step: u64,
+    /// Node the message arrived from
+    pub from: NodeId,
+    /// The delivered message
+    pub message: Message,
+}
+
+/// Per-node record of delivered messages.
+///
+/// Each node keeps its most recent messages in memory, `NODE_HISTORY_LIMIT`
+/// unless changed with [`MessageHistory::set_limit`]. On native builds the
+/// older ones can be spilled to a temporary file instead of dropped, up to
+/// `HISTORY_SPILL_MAX_BYTES`, and read back with
+/// [`MessageHistory::spilled_entries`].
+#[derive(Debug)]
+pub struct MessageHistory {
+    entries: HashMap<NodeId, VecDeque<HistoryEntry>>,
+    received: HashMap<NodeId, u64>,
+    sample_every: u32,
+    limit: usize,
+    /// File older entries are moved to, while spilling is on
+    #[cfg(not(target_arch = "wasm32"))]
+    spill: Option<SpillFile>,
+    /// Where each node's spilled entries are, oldest first
+    #[cfg(not(target_arch = "wasm32"))]
+    spilled: HashMap<NodeId, Vec<SpillRef>>,
+}
+
+impl Default for MessageHistory {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            received: HashMap::new(),
+            sample_every: 1,
+            limit: NODE_HISTORY_LIMIT,
+            #[cfg(not(target_arch = "wasm32"))]
+            spill: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            spilled: HashMap::new(),
+        }
+    }
+}
+
+impl MessageHistory {
+    /// Keeps only every k-th message delivered to each node (1 keeps all).
+    pub fn set_sample_every(&mut self, k: u32) {
+        self.sample_every = k.max(1);
+    }
+
+    /// How many delivered messages each kept entry stands for.
+    pub fn sample_every(&self) -> u32 {
+        self.sample_every
+    }
+
+    /// Most entries kept in memory per node.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Changes how many entries are kept in memory per node, moving the
+    /// oldest out if needed.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit.max(1);
+        let ids: Vec<NodeId> = self.entries.keys().copied().collect();
+        for node_id in ids {
+            self.trim(node_id);
+        }
+    }
+
+    /// Moves a node's entries beyond the limit out of memory.
+    fn trim(&mut self, node_id: NodeId) {
+        let Some(entries) = self.entries.get_mut(&node_id) else {
+            return;
+        };
+        while entries.len() > self.limit {
+            let Some(_oldest) = entries.pop_front() else {
+                break;
+            };
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(spill) = self.spill.as_mut().filter(|spill| spill.len() < HISTORY_SPILL_MAX_BYTES) {
+                // A history that can't be written keeps working in memory only
+                match spill.append(&_oldest) {
+                    Ok(at) => self.spilled.entry(node_id).or_default().push(at),
+                    Err(_) => self.spill = None,
+                }
+            }
+        }
+    }
+
+    /// Records a delivery, subject to sampling and the per-node limit.
+    pub fn record(&mut self, node_id: NodeId, from: NodeId, step: u64, message: &Message) {
+        let received = self.received.entry(node_id).or_insert(0);
+        let index = *received;
+        *received += 1;
+        if !index.is_multiple_of(u64::from(self.sample_every)) {
+            return;
+        }
+
+        self.entries.entry(node_id).or_default().push_back(HistoryEntry {
+            step,
+            from,
+            message: message.clone(),
+        });
+        self.trim(node_id);
+    }
+
+    /// Kept entries for a node, oldest first.
+    pub fn entries(&self, node_id: NodeId) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.get(&node_id).into_iter().flatten()
+    }
+
+    /// Total number of messages delivered to a node, including ones not kept.
+    pub fn received(&self, node_id: NodeId) -> u64 {
+        self.received.get(&node_id).copied().unwrap_or(0)
+    }
+
+    /// Forgets all recorded messages. The sampling and retention settings are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.received.clear();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.spilled.clear();
+            if self.spill.as_mut().is_some_and(|spill| spill.clear().is_err()) {
+                self.spill = None;
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MessageHistory {
+    /// Whether entries beyond the limit are moved to a temporary file.
+    pub fn spills_to_disk(&self) -> bool {
+        self.spill.is_some()
+    }
+
+    /// Turns spilling older entries to a temporary file on or off. Turning
+    /// it off deletes the file and the entries in it.
+    pub fn set_spill_to_disk(&mut self, spill: bool) -> Result<(), String> {
+        if !spill {
+            self.spill = None;
+            self.spilled.clear();
+        } else if self.spill.is_none() {
+            self.spill = Some(SpillFile::create("flowchart_history")?);
+        }
+        Ok(())
+    }
+
+    /// Number of a node's entries on disk.
+    pub fn spilled(&self, node_id: NodeId) -> usize {
+        self.spilled.get(&node_id).map_or(0, Vec::len)
+    }
+
+    /// Reads back a node's spilled entries in `range`, counted from the
+    /// oldest. Indices past the end are ignored.
+    pub fn spilled_entries(&mut self, node_id: NodeId, range: std::ops::Range<usize>) -> Result<Vec<HistoryEntry>, String> {
+        let (Some(spill), Some(refs)) = (self.spill.as_mut(), self.spilled.get(&node_id)) else {
+            return Ok(Vec::new());
+        };
+        let end = range.end.min(refs.len());
+        refs[range.start.min(end)..end].iter().map(|&at| spill.read(at)).collect()
+    }
+}
+
+/// Selects the values addressed by a JSONPath expression.
+///
+/// Supports the common subset: the root `$`, child access with `.name` or
+/// `['name']`, array indices `[n]` (negative counts from the end), wildcards
+/// `.*` and `[*]`, and recursive descent with `..name`.
+///
+/// # Returns
+///
+/// The selected values, or a description of why the expression is invalid.
+pub fn select_json_path<'a>(value: &'a Value, path: &str) -> Result<Vec<&'a Value>, String> {
+    let segments = parse_path(path)?;
+    let mut current = vec![value];
+    for segment in &segments {
+        let mut next = Vec::new();
+        for v in current {
+            segment.apply(v, &mut next);
+        }
+        current = next;
+    }
+    Ok(current)
+}
+
+/// Checks a JSONPath condition such as `$.status == "ok"` against `data`.
+///
+/// Without a comparison the condition holds when the path selects anything.
+///
+/// # Returns
+///
+/// Whether the condition holds, or a description of why it is invalid.
+pub fn json_path_condition_holds(condition: &str, data: &Value) -> Result<bool, String> {
+    parse_condition(condition).map(|c| c.holds(data))
+}
+
+/// A single step of a parsed JSONPath expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+    Descendant(String),
+}
+
+impl Segment {
+    fn apply<'a>(&self, value: &'a Value, out: &mut Vec<&'a Value>) {
+        match self {
+            Segment::Key(key) => {
+                if let Some(v) = value.get(key) {
+                    out.push(v);
+                }
+            }
+            Segment::Index(index) => {
+                if let Value::Array(items) = value {
+                    let len = items.len() as i64;
+                    let i = if *index < 0 { len + index } else { *index };
+                    if (0..len).contains(&i) {
+                        out.push(&items[i as usize]);
+                    }
+                }
+            }
+            Segment::Wildcard => match value {
+                Value::Array(items) => out.extend(items.iter()),
+                Value::Object(map) => out.extend(map.values()),
+                _ => {}
+            },
+            Segment::Descendant(key) => {
+                let mut stack = vec![value];
+                while let Some(v) = stack.pop() {
+                    match v {
+                        Value::Object(map) => {
+                            if let Some(found) = map.get(key) {
+                                out.push(found);
+                            }
+                            stack.extend(map.values().rev());
+                        }
+                        Value::Array(items) => stack.extend(items.iter().rev()),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>, String> {
+    let path = path.trim();
+    let mut rest = path
+        .strip_prefix('$')
+        .ok_or_else(|| "JSONPath must start with '$'".to_string())?;
+    let mut segments = Vec::new();
+
+    let take_name = |s: &str| -> (String, usize) {
+        let end = s.find(['.', '[']).unwrap_or(s.len());
+        (s[..end].trim().to_string(), end)
+    };
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("..") {
+            let (name, len) = take_name(after);
+            if name.is_empty() {
+                return Err("Expected a field name after '..'".to_string());
+            }
+            segments.push(Segment::Descendant(name));
+            rest = &after[len..];
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let (name, len) = take_name(after);
+            match name.as_str() {
+                "" => return Err("Expected a field name after '.'".to_string()),
+                "*" => segments.push(Segment::Wildcard),
+                _ => segments.push(Segment::Key(name)),
+            }
+            rest = &after[len..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let close = after
+                .find(']')
+                .ok_or_else(|| "Missing ']' in JSONPath".to_string())?;
+            let inner = after[..close].trim();
+            let quoted = inner
+                .strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+            if let Some(key) = quoted {
+                segments.push(Segment::Key(key.to_string()));
+            } else if inner == "*" {
+                segments.push(Segment::Wildcard);
+            } else {
+                let index = inner
+                    .parse::<i64>()
+                    .map_err(|_| format!("Invalid index '{}' in JSONPath", inner))?;
+                segments.push(Segment::Index(index));
+            }
+            rest = &after[close + 1..];
+        } else {
+            return Err(format!("Unexpected '{}' in JSONPath", rest));
+        }
+    }
+    Ok(segments)
+}
+
+/// A JSONPath with an optional comparison against a JSON literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Condition {
+    path: Vec<Segment>,
+    comparison: Option<(Comparison, Value)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Condition {
+    /// True if any selected value passes the comparison (or any value is selected at all).
+    fn holds(&self, data: &Value) -> bool {
+        let mut current = vec![data];
+        for segment in &self.path {
+            let mut next = Vec::new();
+            for v in current {
+                segment.apply(v, &mut next);
+            }
+            current = next;
+        }
+        match &self.comparison {
+            None => !current.is_empty(),
+            Some((op, literal)) => current.iter().any(|v| compare(v, *op, literal)),
+        }
+    }
+}
+
+fn parse_condition(expression: &str) -> Result<Condition, String> {
+    // Find the first comparison operator outside quotes and brackets
+    let bytes = expression.as_bytes();
+    let mut quote: Option<u8> = None;
+    let mut depth = 0usize;
+    let mut split: Option<(usize, usize, Comparison)> = None;
+    for (i, &b) in bytes.iter().enumerate() {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None => match b {
+                b'\'' | b'"' => quote = Some(b),
+                b'[' => depth += 1,
+                b']' => depth = depth.saturating_sub(1),
+                b'=' | b'!' | b'<' | b'>' if depth == 0 => {
+                    let next = bytes.get(i + 1).copied();
+                    split = match (b, next) {
+                        (b'=', Some(b'=')) => Some((i, 2, Comparison::Eq)),
+                        (b'!', Some(b'=')) => Some((i, 2, Comparison::Ne)),
+                        (b'<', Some(b'=')) => Some((i, 2, Comparison::Le)),
+                        (b'>', Some(b'=')) => Some((i, 2, Comparison::Ge)),
+                        (b'<', _) => Some((i, 1, Comparison::Lt)),
+                        (b'>', _) => Some((i, 1, Comparison::Gt)),
+                        _ => return Err(format!("Unexpected '{}' in JSONPath", b as char)),
+                    };
+                    break;
+                }
+                _ => {}
+            },
+        }
+    }
+
+    match split {
+        None => Ok(Condition {
+            path: parse_path(expression)?,
+            comparison: None,
+        }),
+        Some((at, len, op)) => {
+            let literal = expression[at + len..].trim();
+            let literal: Value = serde_json::from_str(literal)
+                .map_err(|_| format!("Expected a JSON value after the comparison, got '{}'", literal))?;
+            Ok(Condition {
+                path: parse_path(&expression[..at])?,
+                comparison: Some((op, literal)),
+            })
+        }
+    }
+}
+
+fn compare(value: &Value, op: Comparison, literal: &Value) -> bool {
+    let ordering = match (value, literal) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .zip(b.as_f64())
+            .and_then(|(a, b)| a.partial_cmp(&b)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    match op {
+        Comparison::Eq => ordering.map_or(value == literal, |o| o.is_eq()),
+        Comparison::Ne => ordering.map_or(value != literal, |o| o.is_ne()),
+        Comparison::Lt => ordering.is_some_and(|o| o.is_lt()),
+        Comparison::Le => ordering.is_some_and(|o| o.is_le()),
+        Comparison::Gt => ordering.is_some_and(|o| o.is_gt()),
+        Comparison::Ge => ordering.is_some_and(|o| o.is_ge()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_select_json_path_subset() {
+        let data = json!({
+            "order": {"id": 7, "items": [{"sku": "a"}, {"sku": "b"}]},
+            "tags": ["x", "y"],
+            "odd key": true
+        });
+        assert_eq!(select_json_path(&data, "$.order.id").unwrap(), vec![&json!(7)]);
+        assert_eq!(select_json_path(&data, "$.tags[-1]").unwrap(), vec![&json!("y")]);
+        assert_eq!(
+            select_json_path(&data, "$.order.items[*].sku").unwrap(),
+            vec![&json!("a"), &json!("b")]
+        );
+        assert_eq!(select_json_path(&data, "$..sku").unwrap().len(), 2);
+        assert_eq!(select_json_path(&data, "$['odd key']").unwrap(), vec![&json!(true)]);
+        assert!(select_json_path(&data, "$.missing").unwrap().is_empty());
+        assert!(select_json_path(&data, "order.id").is_err());
+        assert!(select_json_path(&data, "$.tags[x]").is_err());
+    }
+
+    #[test]
+    fn test_message_filter_criteria() {
+        let mut message = Message::new(json!({"status": "ok", "count": 5}));
+        message.trace_id = Some(uuid::Uuid::new_v4());
+        let trace_prefix = message.trace_id.unwrap().simple().to_string()[..6].to_string();
+
+        let filter = MessageFilter::default();
+        assert!(filter.is_empty());
+        assert!(filter.matches(&message, Some(3)));
+
+        let by_path = |expr: &str| {
+            let mut filter = MessageFilter::default();
+            filter.set_json_path(expr);
+            filter
+        };
+        assert!(by_path("$.status").matches(&message, None));
+        assert!(by_path("$.status == \"ok\"").matches(&message, None));
+        assert!(by_path("$.count >= 5").matches(&message, None));
+        assert!(!by_path("$.count < 5").matches(&message, None));
+        assert!(!by_path("$.missing").matches(&message, None));
+        assert!(by_path("$.count >").validate().is_err());
+        assert!(!by_path("$.count >").matches(&message, None));
+
+        let by_text = MessageFilter {
+            text: "OK".into(),
+            ..Default::default()
+        };
+        assert!(by_text.matches(&message, None));
+
+        let by_trace = MessageFilter {
+            trace_id: trace_prefix.to_uppercase(),
+            ..Default::default()
+        };
+        assert!(by_trace.matches(&message, None));
+        assert!(!by_trace.matches(&Message::new(json!({})), None));
+
+        let by_steps = MessageFilter {
+            step_from: Some(2),
+            step_to: Some(4),
+            ..Default::default()
+        };
+        assert!(by_steps.matches(&message, Some(4)));
+        assert!(!by_steps.matches(&message, Some(5)));
+        // Without a step (connection contents) the range does not apply
+        assert!(by_steps.matches(&message, None));
+    }
+
+    #[test]
+    fn test_history_samples_every_kth_message() {
+        let node = uuid::Uuid::new_v4();
+        let from = uuid::Uuid::new_v4();
+        let mut history = MessageHistory::default();
+        history.set_sample_every(3);
+        for step in 0..10 {
+            history.record(node, from, step, &Message::new(json!(step)));
+        }
+        let steps: Vec<u64> = history.entries(node).map(|e| e.step).collect();
+        assert_eq!(steps, vec![0, 3, 6, 9]);
+        assert_eq!(history.received(node), 10);
+
+        history.clear();
+        assert_eq!(history.entries(node).count(), 0);
+        assert_eq!(history.sample_every(), 3);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn history_beyond_the_limit_spills_to_disk_and_reads_back() {
+        let node = NodeId::new_v4();
+        let from = NodeId::new_v4();
+        let mut history = MessageHistory::default();
+        history.set_limit(3);
+        for step in 0..4 {
+            history.record(node, from, step, &Message::new(json!(step)));
+        }
+        history.set_spill_to_disk(true).unwrap();
+        for step in 4..10 {
+            history.record(node, from, step, &Message::new(json!({ "step": step })));
+        }
+        let kept: Vec<u64> = history.entries(node).map(|e| e.step).collect();
+        assert_eq!(kept, vec![7, 8, 9]);
+        // Step 0 was dropped before spilling was turned on
+        assert_eq!(history.spilled(node), 6);
+        let older = history.spilled_entries(node, 4..10).unwrap();
+        assert_eq!(older.iter().map(|e| e.step).collect::<Vec<_>>(), vec![5, 6]);
+        assert_eq!(older[0].message.data, json!({ "step": 5 }).into());
+        assert_eq!(older[0].from, from);
+
+        history.set_limit(2);
+        assert_eq!(history.spilled(node), 7);
+        history.clear();
+        assert_eq!(history.spilled(node), 0);
+        assert!(history.spills_to_disk());
+        history.set_spill_to_disk(false).unwrap();
+        assert!(!history.spills_to_disk());
+    }
+}
@@ -0,0 +1,114 @@
+//! Single-instance handoff for the desktop app.
+//!
+//! The first instance listens on a loopback TCP port and records the port,
+//! with a random token, in a small file in the temp directory. A later launch
+//! with a file argument (for example from a double-click in the file manager)
+//! sends the path to that instance and exits, so the file opens in the
+//! existing window and goes through its unsaved-changes prompt.
+
+use crate::constants::INSTANCE_HANDOFF_TIMEOUT_MS;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Reply sent once a handed-off path has been accepted.
+const ACK: &str = "ok";
+
+/// File recording how to reach the running instance for the current user.
+fn instance_file() -> PathBuf {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    std::env::temp_dir().join(format!("flowchart_tool-{}.instance", user))
+}
+
+/// Sends `path` to the running instance. Returns true if it took the file.
+pub fn hand_off(path: &str) -> bool {
+    hand_off_at(&instance_file(), path)
+}
+
+/// Starts accepting paths from later launches; `on_open` runs for each one.
+pub fn listen(on_open: impl Fn(String) + Send + 'static) {
+    if let Err(e) = listen_at(&instance_file(), on_open) {
+        eprintln!("Single-instance handoff unavailable: {}", e);
+    }
+}
+
+/// [`hand_off`] against an explicit instance file.
+fn hand_off_at(instance_file: &Path, path: &str) -> bool {
+    let Ok(record) = std::fs::read_to_string(instance_file) else {
+        return false;
+    };
+    let Some((port, token)) = record.trim().split_once(' ') else {
+        return false;
+    };
+    let Ok(port) = port.parse::<u16>() else {
+        return false;
+    };
+
+    let timeout = Duration::from_millis(INSTANCE_HANDOFF_TIMEOUT_MS);
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let Ok(mut stream) = TcpStream::connect_timeout(&addr, timeout) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(timeout));
+    if writeln!(stream, "{}\n{}", token, path).is_err() {
+        return false;
+    }
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).is_ok() && reply.trim() == ACK
+}
+
+/// [`listen`] with an explicit instance file.
+fn listen_at(instance_file: &Path, on_open: impl Fn(String) + Send + 'static) -> std::io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let port = listener.local_addr()?.port();
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    std::fs::write(instance_file, format!("{} {}", port, token))?;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let timeout = Some(Duration::from_millis(INSTANCE_HANDOFF_TIMEOUT_MS));
+            let _ = stream.set_read_timeout(timeout);
+            let mut reader = BufReader::new(&stream);
+            let (mut sent_token, mut path) = (String::new(), String::new());
+            if reader.read_line(&mut sent_token).is_err() || reader.read_line(&mut path).is_err() {
+                continue;
+            }
+            // Ignore anything that doesn't know this instance's token
+            let path = path.trim_end_matches(['\r', '\n']);
+            if sent_token.trim() != token || path.is_empty() {
+                continue;
+            }
+            on_open(path.to_string());
+            let _ = writeln!(&stream, "{}", ACK);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_second_launch_hands_path_to_running_instance() {
+        let file = std::env::temp_dir().join(format!("flowchart_tool-test-{}.instance", uuid::Uuid::new_v4()));
+        assert!(!hand_off_at(&file, "/tmp/a.json"), "no instance is running yet");
+
+        let (tx, rx) = channel();
+        listen_at(&file, move |path| tx.send(path).unwrap()).unwrap();
+        assert!(hand_off_at(&file, "/tmp/a b.json"));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), "/tmp/a b.json");
+
+        // A stale record with the wrong token is rejected
+        let record = std::fs::read_to_string(&file).unwrap();
+        let port = record.split(' ').next().unwrap();
+        std::fs::write(&file, format!("{} wrong", port)).unwrap();
+        assert!(!hand_off_at(&file, "/tmp/c.json"));
+
+        let _ = std::fs::remove_file(&file);
+    }
+}
@@ -17,12 +17,44 @@
 #![warn(missing_docs)]
 #![deny(unsafe_code)]
 
+pub mod analysis;
+pub mod audit;
+pub mod bpmn;
+pub mod crash;
+pub mod csv_import;
+pub mod datagen;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod determinism;
+pub mod drawio;
+pub mod events;
+pub mod inspect;
+pub mod metrics;
+pub mod node_table;
+pub mod parameters;
+pub mod payloads;
+pub mod plugins;
+pub mod pwa;
+pub mod replicate;
+pub mod scheduler;
+pub mod schema;
+pub mod script_api;
 pub mod script_engine;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod script_workers;
+pub mod shapes;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod spill;
 pub mod simulation;
+pub mod sweep;
+pub mod traffic;
 pub mod types;
 pub mod ui;
 pub mod constants;
 pub mod examples;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod instance;
 
 // Re-export public types and functions
 pub use simulation::*;
@@ -57,6 +89,24 @@ use {
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub async fn run_app(canvas_id: &str) -> Result<(), eframe::wasm_bindgen::JsValue> {
+    start_app(canvas_id).await?;
+    Ok(())
+}
+
+/// Starts the app on the canvas with id `canvas_id` and returns a handle the
+/// host page can drive it with.
+///
+/// # Example
+///
+/// ```js
+/// const app = await start_app("flowchart_canvas");
+/// app.load_document(json);
+/// app.on_step((sample) => console.log(sample.step, sample.consumed));
+/// app.start();
+/// ```
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub async fn start_app(canvas_id: &str) -> Result<FlowchartHandle, eframe::wasm_bindgen::JsValue> {
     let window = web_sys::window().unwrap();
     let document = window.document().unwrap();
     let canvas = document.get_element_by_id(canvas_id).unwrap();
@@ -67,30 +117,33 @@ pub async fn run_app(canvas_id: &str) -> Result<(), eframe::wasm_bindgen::JsValu
         let link = document.create_element("link").unwrap();
         link.set_attribute("rel", "icon").ok();
         link.set_attribute("type", "image/svg+xml").ok();
-        // Simple inlined SVG circle icon
-        let svg = r#"<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 64 64'>
-<circle cx='32' cy='32' r='26' fill='#4287F5' stroke='#2a5db0' stroke-width='4'/>
-<path d='M20 28h24v8H20z' fill='white'/>
-</svg>"#;
         let data_url = format!(
             "data:image/svg+xml;utf8,{}",
-            js_sys::encode_uri_component(svg)
+            js_sys::encode_uri_component(pwa::APP_ICON_SVG)
         );
         link.set_attribute("href", &data_url).ok();
         head.append_child(&link).ok();
     }
 
     let options = eframe::WebOptions::default();
-    eframe::WebRunner::new()
+    let runner = eframe::WebRunner::new();
+    let egui_ctx = std::rc::Rc::new(std::cell::RefCell::new(None));
+    let created_ctx = egui_ctx.clone();
+    runner
         .start(
             canvas,
             options,
-            Box::new(|cc| {
+            Box::new(move |cc| {
+                *created_ctx.borrow_mut() = Some(cc.egui_ctx.clone());
+                if safe_mode_requested() {
+                    eprintln!("Starting in safe mode (web)");
+                    return Ok(Box::new(FlowchartApp::safe_mode()));
+                }
                 if let Some(storage) = cc.storage {
                     if let Some(json) = storage.get_string("app_state") {
                         match FlowchartApp::from_json(&json) {
                             Ok(mut app) => {
-                                app.reset_non_ui_fields();
+                                app.restore_browser_session();
                                 eprintln!("Loaded app_state from storage (web)");
                                 return Ok(Box::new(app));
                             }
@@ -108,7 +161,214 @@ pub async fn run_app(canvas_id: &str) -> Result<(), eframe::wasm_bindgen::JsValu
             }),
         )
         .await?;
-    Ok(())
+    let ctx = egui_ctx.borrow_mut().take();
+    Ok(FlowchartHandle { runner, ctx })
+}
+
+/// Name of the service worker cache for this release's files (see [`pwa`]).
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn pwa_cache_name() -> String {
+    constants::PWA_CACHE_NAME.to_string()
+}
+
+/// The web app manifest as JSON, for the host page to serve.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn pwa_manifest() -> String {
+    pwa::web_app_manifest()
+}
+
+/// The app icon as SVG, for the host page to serve next to the manifest as
+/// `icon.svg`.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn pwa_icon_svg() -> String {
+    pwa::APP_ICON_SVG.to_string()
+}
+
+/// Asks the browser to keep the app's storage when space runs low, resolving
+/// to whether it agreed.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub async fn persist_storage() -> Result<bool, JsValue> {
+    pwa::persist_storage().await.map_err(|e: String| JsValue::from_str(&e))
+}
+
+/// Bytes of browser storage the app uses and may use, as `{ usage, quota }`.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub async fn storage_estimate() -> Result<js_sys::Object, JsValue> {
+    let (usage, quota) = pwa::storage_estimate().await.map_err(|e: String| JsValue::from_str(&e))?;
+    let estimate = js_sys::Object::new();
+    js_sys::Reflect::set(&estimate, &"usage".into(), &usage.into())?;
+    js_sys::Reflect::set(&estimate, &"quota".into(), &quota.into())?;
+    Ok(estimate)
+}
+
+/// Returns true if the page URL asks for safe mode (`?safe_mode`).
+#[cfg(target_arch = "wasm32")]
+fn safe_mode_requested() -> bool {
+    web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .and_then(|search| web_sys::UrlSearchParams::new_with_str(&search).ok())
+        .is_some_and(|params| params.has("safe_mode"))
+}
+
+/// Parses the JSON data of a message from the host page.
+#[cfg(target_arch = "wasm32")]
+fn parse_message_data(data: &str) -> Result<serde_json::Value, JsValue> {
+    serde_json::from_str(data).map_err(|e| JsValue::from_str(&format!("Invalid message JSON: {}", e)))
+}
+
+/// Converts messages to an array of JavaScript objects.
+#[cfg(target_arch = "wasm32")]
+fn messages_to_js(messages: Vec<types::Message>) -> Result<JsValue, JsValue> {
+    let json = serde_json::to_string(&messages).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    js_sys::JSON::parse(&json)
+}
+
+/// Handle to a running web app, returned by `start_app`.
+///
+/// Methods taking effect on the next frame request a repaint themselves.
+/// They fail with an error if the app has panicked.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct FlowchartHandle {
+    runner: eframe::WebRunner,
+    ctx: Option<egui::Context>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl FlowchartHandle {
+    /// Runs `f` on the app and repaints the canvas.
+    fn with_app<R>(&self, f: impl FnOnce(&mut FlowchartApp) -> R) -> Result<R, JsValue> {
+        let mut app = self
+            .runner
+            .app_mut::<FlowchartApp>()
+            .ok_or_else(|| JsValue::from_str("The flowchart app is not running"))?;
+        let result = f(&mut app);
+        if let Some(ctx) = &self.ctx {
+            ctx.request_repaint();
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl FlowchartHandle {
+    /// Replaces the open document with the flowchart JSON in `json`.
+    pub fn load_document(&self, json: &str) -> Result<(), JsValue> {
+        self.with_app(|app| app.load_document_json(json))?
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// The open document as flowchart JSON.
+    pub fn document(&self) -> Result<String, JsValue> {
+        self.with_app(|app| app.document_json())?
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Starts or resumes the simulation.
+    pub fn start(&self) -> Result<(), JsValue> {
+        self.with_app(|app| app.start_simulation())
+    }
+
+    /// Pauses the simulation.
+    pub fn pause(&self) -> Result<(), JsValue> {
+        self.with_app(|app| app.pause_simulation())
+    }
+
+    /// Stops the simulation and clears the run.
+    pub fn stop(&self) -> Result<(), JsValue> {
+        self.with_app(|app| app.stop_simulation())
+    }
+
+    /// Advances the simulation by one step, returning the node errors as an
+    /// array of `{ node, error }` objects.
+    pub fn step(&self) -> Result<js_sys::Array, JsValue> {
+        let errors = self.with_app(|app| app.step_simulation())?;
+        let array = js_sys::Array::new();
+        for (node_id, error) in errors {
+            let entry = js_sys::Object::new();
+            js_sys::Reflect::set(&entry, &"node".into(), &node_id.to_string().into())?;
+            js_sys::Reflect::set(&entry, &"error".into(), &error.into())?;
+            array.push(&entry);
+        }
+        Ok(array)
+    }
+
+    /// Whether the simulation is running.
+    pub fn is_running(&self) -> Result<bool, JsValue> {
+        self.with_app(|app| app.is_simulation_running())
+    }
+
+    /// Calls `callback` with the statistics of every step as an object, or
+    /// stops calling it if `callback` is null or undefined.
+    pub fn on_step(&self, callback: Option<js_sys::Function>) -> Result<(), JsValue> {
+        let listener = callback.map(|callback| {
+            Box::new(move |sample: &metrics::StepSample| {
+                let Ok(json) = serde_json::to_string(sample) else {
+                    return;
+                };
+                if let Ok(value) = js_sys::JSON::parse(&json) {
+                    // The host's exceptions are its own; keep simulating
+                    let _ = callback.call1(&JsValue::NULL, &value);
+                }
+            }) as ui::StepListener
+        });
+        self.with_app(|app| app.set_step_listener(listener))
+    }
+
+    /// Hands a message with the JSON `data` straight to the consumer or
+    /// transformer with id or name `node`.
+    pub fn inject_into_node(&self, node: &str, data: &str) -> Result<(), JsValue> {
+        let data = parse_message_data(data)?;
+        self.with_app(|app| app.inject_into_node(node, data))?
+            .map_err(|e: String| JsValue::from_str(&e))
+    }
+
+    /// Puts a message with the JSON `data` on the connection at `index`, for
+    /// the next step to deliver.
+    pub fn inject_on_connection(&self, index: usize, data: &str) -> Result<(), JsValue> {
+        let data = parse_message_data(data)?;
+        self.with_app(|app| app.inject_on_connection(index, data))?
+            .map_err(|e: String| JsValue::from_str(&e))
+    }
+
+    /// The messages waiting to be delivered to the node with id or name
+    /// `node`, as an array of message objects.
+    pub fn peek_messages(&self, node: &str) -> Result<JsValue, JsValue> {
+        let messages = self.with_app(|app| app.peek_messages(node))?;
+        messages_to_js(messages.map_err(|e: String| JsValue::from_str(&e))?)
+    }
+
+    /// Takes the messages waiting to be delivered to the node with id or name
+    /// `node` out of the run, returning them like `peek_messages`.
+    pub fn drain_messages(&self, node: &str) -> Result<JsValue, JsValue> {
+        let messages = self.with_app(|app| app.drain_messages(node))?;
+        messages_to_js(messages.map_err(|e: String| JsValue::from_str(&e))?)
+    }
+
+    /// Shows a notification that `version` is ready and offers to reload.
+    /// Returns false, showing nothing, unless `version` is newer than the
+    /// running one.
+    pub fn notify_update_available(&self, version: &str) -> Result<bool, JsValue> {
+        self.with_app(|app| app.notify_update_available(version))
+    }
+
+    /// Calls `callback` when the user accepts an update, so the page can
+    /// activate the waiting service worker and reload. Without one, the app
+    /// reloads the page itself.
+    pub fn on_update_accepted(&self, callback: Option<js_sys::Function>) -> Result<(), JsValue> {
+        let listener = callback.map(|callback| {
+            Box::new(move || {
+                let _ = callback.call0(&JsValue::NULL);
+            }) as ui::UpdateListener
+        });
+        self.with_app(|app| app.set_update_listener(listener))
+    }
 }
 
 /// Runs the flowchart application with default settings.
@@ -160,9 +420,47 @@ fn generate_app_icon() -> egui::IconData {
     }
 }
 
+/// Returns the flowchart file passed on the command line, made absolute.
+///
+/// Options (anything starting with `-`, such as the process serial number
+/// macOS adds) are skipped.
+#[cfg(not(target_arch = "wasm32"))]
+fn file_argument() -> Option<String> {
+    let arg = std::env::args_os()
+        .skip(1)
+        .find(|a| !a.to_string_lossy().starts_with('-'))?;
+    let path = std::path::PathBuf::from(arg);
+    let path = std::fs::canonicalize(&path).unwrap_or(path);
+    Some(path.display().to_string())
+}
+
 /// Entrypoint for the desktop app
+///
+/// A file path argument (`flowchart_tool mychart.json`) is opened on start.
+/// If another instance is already running it receives the file instead and
+/// this launch exits without opening a window. `--safe-mode` starts without
+/// the stored app state, plugins or crash recovery. `--headless`, or the
+/// `sim` subcommand, runs the file's simulation without a window instead
+/// (see [`headless`]).
 #[cfg(not(target_arch = "wasm32"))]
 pub fn run_app() -> Result<(), eframe::Error> {
+    if std::env::args().skip(1).any(|a| a == "--print-schema") {
+        println!("{}", schema::flowchart_schema_json());
+        return Ok(());
+    }
+    let subcommand = std::env::args().nth(1);
+    if subcommand.as_deref() == Some("sim") || std::env::args().skip(1).any(|a| a == "--headless") {
+        std::process::exit(headless::run_from_args(std::env::args().skip(1)));
+    }
+    crash::install_panic_hook();
+    let safe_mode = std::env::args().skip(1).any(|a| a == "--safe-mode");
+    let open_path = file_argument();
+    if let Some(path) = &open_path {
+        if instance::hand_off(path) {
+            return Ok(());
+        }
+    }
+
     let icon = generate_app_icon();
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -173,32 +471,51 @@ pub fn run_app() -> Result<(), eframe::Error> {
         ..Default::default()
     };
     eframe::run_native(
-        "Flowchart Tool",
+        constants::APP_TITLE,
         options,
-        Box::new(|cc| {
-            if let Some(storage) = cc.storage {
-                if let Some(json) = storage.get_string("app_state") {
-                    match FlowchartApp::from_json(&json) {
-                        Ok(mut app) => {
-                            app.reset_non_ui_fields();
-                            eprintln!("Loaded app_state from storage (native)");
-                            return Ok(Box::new(app));
-                        }
-                        Err(err) => {
-                            eprintln!("Failed to parse app_state (native): {err}");
-                        }
-                    }
-                } else {
-                    eprintln!("No app_state found in storage (native)");
-                }
+        Box::new(move |cc| {
+            let mut app = if safe_mode {
+                eprintln!("Starting in safe mode (native)");
+                FlowchartApp::safe_mode()
             } else {
-                eprintln!("No storage available (native)");
+                let mut app = restore_native_app(cc);
+                app.check_for_crash_recovery();
+                app.reload_plugins();
+                app
+            };
+            if let Some(path) = open_path {
+                app.open_path_on_launch(path);
             }
-            Ok(Box::new(FlowchartApp::default()))
+            app.listen_for_other_launches(&cc.egui_ctx);
+            Ok(Box::new(app))
         }),
     )
 }
 
+/// Restores the app from eframe storage, or starts fresh.
+#[cfg(not(target_arch = "wasm32"))]
+fn restore_native_app(cc: &eframe::CreationContext<'_>) -> FlowchartApp {
+    if let Some(storage) = cc.storage {
+        if let Some(json) = storage.get_string("app_state") {
+            match FlowchartApp::from_json(&json) {
+                Ok(mut app) => {
+                    app.reset_non_ui_fields();
+                    eprintln!("Loaded app_state from storage (native)");
+                    return app;
+                }
+                Err(err) => {
+                    eprintln!("Failed to parse app_state (native): {err}");
+                }
+            }
+        } else {
+            eprintln!("No app_state found in storage (native)");
+        }
+    } else {
+        eprintln!("No storage available (native)");
+    }
+    FlowchartApp::default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,535 @@
+//! Run statistics collected while the simulation runs.
+//!
+//! The simulation engine records a [`StepSample`] for every step. Samples are
+//! kept for the whole run (up to `RUN_STATS_SAMPLE_LIMIT`), so the warm-up and
+//! measurement window in [`RunConfig`] can be changed afterwards and the
+//! [`RunSummary`] recomputed without re-running. An exported summary can be
+//! loaded back as a [`BaselineRun`] to compare a later run against it.
+
+use crate::constants::RUN_STATS_SAMPLE_LIMIT;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// What happened during one simulation step.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StepSample {
+    /// The step number
+    pub step: u64,
+    /// Messages created by producers
+    pub produced: u64,
+    /// Messages taken off connections by their destination
+    pub delivered: u64,
+    /// Messages delivered to consumers
+    pub consumed: u64,
+    /// Transformer script failures
+    pub errors: u64,
+    /// Messages on connections at the end of the step
+    pub in_transit: u64,
+    /// Messages delivered to each node
+    pub deliveries: BTreeMap<NodeId, u64>,
+    /// End-to-end latency in steps of each message consumed, by consumer
+    #[serde(default)]
+    pub latencies: Vec<(NodeId, u64)>,
+    /// Messages on each connection at the end of the step, as `(from, to,
+    /// count)`; connections without messages are left out
+    #[serde(default)]
+    pub connections: Vec<(NodeId, NodeId, u64)>,
+}
+
+/// Per-step samples of the current run, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct RunStats {
+    samples: VecDeque<StepSample>,
+}
+
+impl RunStats {
+    /// Forgets all samples, e.g. when the simulation is reset.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Returns true if no step has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The recorded samples, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = &StepSample> {
+        self.samples.iter()
+    }
+
+    /// Appends `sample`, dropping the oldest once the limit is reached.
+    pub fn push(&mut self, sample: StepSample) {
+        if self.samples.len() >= RUN_STATS_SAMPLE_LIMIT {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// The samples of the last `count` steps, oldest first.
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &StepSample> {
+        self.samples.iter().skip(self.samples.len().saturating_sub(count))
+    }
+
+    /// The sample of the most recent step.
+    pub fn last(&self) -> Option<&StepSample> {
+        self.samples.back()
+    }
+
+    /// The sample of the most recent step, to add what happens after it.
+    pub fn last_mut(&mut self) -> Option<&mut StepSample> {
+        self.samples.back_mut()
+    }
+
+    /// Aggregates the samples inside the measurement window of `settings`.
+    pub fn summarize(&self, settings: &RunConfig) -> RunSummary {
+        let mut summary = RunSummary {
+            settings: *settings,
+            ..Default::default()
+        };
+        let mut in_transit_total = 0u64;
+        for sample in self.samples.iter().filter(|s| settings.measures(s.step)) {
+            summary.first_step.get_or_insert(sample.step);
+            summary.last_step = Some(sample.step);
+            summary.measured_steps += 1;
+            summary.produced += sample.produced;
+            summary.delivered += sample.delivered;
+            summary.consumed += sample.consumed;
+            summary.errors += sample.errors;
+            summary.max_in_transit = summary.max_in_transit.max(sample.in_transit);
+            in_transit_total += sample.in_transit;
+            for (node, count) in &sample.deliveries {
+                *summary.node_deliveries.entry(*node).or_default() += count;
+            }
+            for (node, latency) in &sample.latencies {
+                summary.latencies.entry(*node).or_default().push(*latency);
+            }
+        }
+        if summary.measured_steps > 0 {
+            summary.mean_in_transit = in_transit_total as f64 / summary.measured_steps as f64;
+        }
+        for latencies in summary.latencies.values_mut() {
+            latencies.sort_unstable();
+        }
+        summary
+    }
+
+    /// Raw latency samples as CSV: one row per consumed message.
+    pub fn latency_csv(&self, flowchart: &Flowchart) -> String {
+        let mut csv = String::from("step,measured,consumer,latency\n");
+        for sample in &self.samples {
+            for (node, latency) in &sample.latencies {
+                let name = flowchart.nodes.get(node).map_or_else(|| node.to_string(), |n| n.name.clone());
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    sample.step,
+                    flowchart.run_config.measures(sample.step),
+                    csv_field(&name),
+                    latency
+                ));
+            }
+        }
+        csv
+    }
+
+    /// Per-step samples as CSV, with a column marking the measured steps.
+    ///
+    /// Each node gets a `delivered:<name>` column, ordered by name, and each
+    /// connection an `in_transit:<from> → <to>` column with the messages on it
+    /// at the end of the step, ordered by the names of its ends.
+    pub fn to_csv(&self, flowchart: &Flowchart) -> String {
+        let mut nodes: Vec<(&String, NodeId)> = flowchart.nodes.values().map(|n| (&n.name, n.id)).collect();
+        nodes.sort();
+        let name = |id: &NodeId| flowchart.nodes.get(id).map_or_else(|| id.to_string(), |n| n.name.clone());
+        let mut connections: Vec<(String, String, NodeId, NodeId)> = flowchart
+            .connections
+            .iter()
+            .map(|c| (name(&c.from), name(&c.to), c.from, c.to))
+            .collect();
+        connections.sort();
+        connections.dedup_by(|a, b| (a.2, a.3) == (b.2, b.3));
+
+        let mut csv = String::from("step,measured,produced,delivered,consumed,errors,in_transit");
+        for (name, _) in &nodes {
+            csv.push_str(&format!(",{}", csv_field(&format!("delivered:{}", name))));
+        }
+        for (from, to, _, _) in &connections {
+            csv.push_str(&format!(",{}", csv_field(&format!("in_transit:{} → {}", from, to))));
+        }
+        csv.push('\n');
+        for sample in &self.samples {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}",
+                sample.step,
+                flowchart.run_config.measures(sample.step),
+                sample.produced,
+                sample.delivered,
+                sample.consumed,
+                sample.errors,
+                sample.in_transit,
+            ));
+            for (_, id) in &nodes {
+                csv.push_str(&format!(",{}", sample.deliveries.get(id).copied().unwrap_or(0)));
+            }
+            for (_, _, from, to) in &connections {
+                let count: u64 = sample
+                    .connections
+                    .iter()
+                    .filter(|(f, t, _)| (f, t) == (from, to))
+                    .map(|(_, _, count)| count)
+                    .sum();
+                csv.push_str(&format!(",{}", count));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Quotes a CSV field when it contains a separator, quote or line break.
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Totals and averages over the measured steps of a run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    /// The window the summary covers
+    pub settings: RunConfig,
+    /// Number of steps inside the window that have run
+    pub measured_steps: u64,
+    /// First measured step
+    pub first_step: Option<u64>,
+    /// Last measured step so far
+    pub last_step: Option<u64>,
+    /// Messages created by producers
+    pub produced: u64,
+    /// Messages taken off connections
+    pub delivered: u64,
+    /// Messages delivered to consumers
+    pub consumed: u64,
+    /// Transformer script failures
+    pub errors: u64,
+    /// Average number of messages on connections per step
+    pub mean_in_transit: f64,
+    /// Highest number of messages on connections at the end of a step
+    pub max_in_transit: u64,
+    /// Messages delivered to each node
+    pub node_deliveries: BTreeMap<NodeId, u64>,
+    /// Sorted end-to-end latencies of the messages each consumer consumed
+    #[serde(default)]
+    pub latencies: BTreeMap<NodeId, Vec<u64>>,
+}
+
+impl RunSummary {
+    /// Messages consumed per measured step.
+    pub fn throughput(&self) -> f64 {
+        per_step(self.consumed, self.measured_steps)
+    }
+
+    /// Messages delivered to `node` per measured step.
+    pub fn node_rate(&self, node: NodeId) -> f64 {
+        per_step(self.node_deliveries.get(&node).copied().unwrap_or(0), self.measured_steps)
+    }
+
+    /// Latency percentiles of the messages `consumer` consumed.
+    pub fn latency_stats(&self, consumer: NodeId) -> Option<LatencyStats> {
+        self.latencies.get(&consumer).and_then(|l| LatencyStats::from_sorted(l))
+    }
+
+    /// Returns true once every step of a bounded window has run.
+    pub fn is_complete(&self) -> bool {
+        self.settings.last_measured_step().is_some_and(|last| self.last_step == Some(last))
+    }
+
+    /// The summary as JSON, with node ids replaced by node names where known.
+    ///
+    /// `steps` lists `[step, consumed, in_transit]` for every recorded step of
+    /// `stats`, so the run can be charted again as a [`BaselineRun`].
+    pub fn to_json(&self, stats: &RunStats, flowchart: &Flowchart) -> Result<String, String> {
+        let nodes: serde_json::Map<String, serde_json::Value> = self
+            .node_deliveries
+            .iter()
+            .map(|(id, count)| {
+                let name = flowchart.nodes.get(id).map_or_else(|| id.to_string(), |n| n.name.clone());
+                let mut entry = serde_json::json!({ "delivered": count, "per_step": self.node_rate(*id) });
+                if let Some(latency) = self.latency_stats(*id) {
+                    entry["latency"] = serde_json::to_value(latency).unwrap_or_default();
+                }
+                (name, entry)
+            })
+            .collect();
+        let value = serde_json::json!({
+            "warm_up_steps": self.settings.warm_up_steps,
+            "measurement_steps": self.settings.measurement_steps,
+            "measured_steps": self.measured_steps,
+            "first_step": self.first_step,
+            "last_step": self.last_step,
+            "produced": self.produced,
+            "delivered": self.delivered,
+            "consumed": self.consumed,
+            "errors": self.errors,
+            "throughput": self.throughput(),
+            "mean_in_transit": self.mean_in_transit,
+            "max_in_transit": self.max_in_transit,
+            "nodes": nodes,
+            "steps": stats
+                .samples()
+                .map(|s| [s.step, s.consumed, s.in_transit])
+                .collect::<Vec<_>>(),
+        });
+        serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to serialize run summary: {}", e))
+    }
+}
+
+/// A node's figures in an exported summary.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BaselineNode {
+    /// Messages delivered to the node
+    #[serde(default)]
+    pub delivered: u64,
+    /// Messages delivered per measured step
+    #[serde(default)]
+    pub per_step: f64,
+    /// Latency distribution, for consumers
+    #[serde(default)]
+    pub latency: Option<LatencyStats>,
+}
+
+/// An earlier run loaded from an exported summary, to compare against.
+///
+/// Nodes are matched to the current flowchart by name, steps by number.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BaselineRun {
+    /// File the run was loaded from
+    #[serde(skip)]
+    pub source: String,
+    /// Warm-up the run was measured with
+    #[serde(default)]
+    pub warm_up_steps: u64,
+    /// Measurement window the run was measured with
+    #[serde(default)]
+    pub measurement_steps: u64,
+    /// Number of measured steps
+    pub measured_steps: u64,
+    /// Messages created by producers
+    #[serde(default)]
+    pub produced: u64,
+    /// Messages taken off connections
+    #[serde(default)]
+    pub delivered: u64,
+    /// Messages delivered to consumers
+    #[serde(default)]
+    pub consumed: u64,
+    /// Transformer script failures
+    #[serde(default)]
+    pub errors: u64,
+    /// Messages consumed per measured step
+    #[serde(default)]
+    pub throughput: f64,
+    /// Average number of messages on connections per step
+    #[serde(default)]
+    pub mean_in_transit: f64,
+    /// Highest number of messages on connections at the end of a step
+    #[serde(default)]
+    pub max_in_transit: u64,
+    /// Per-node figures by node name
+    #[serde(default)]
+    pub nodes: BTreeMap<String, BaselineNode>,
+    /// `[step, consumed, in_transit]` per recorded step; empty for summaries
+    /// exported before steps were included
+    #[serde(default)]
+    pub steps: Vec<[u64; 3]>,
+}
+
+impl BaselineRun {
+    /// Parses a summary written by [`RunSummary::to_json`].
+    pub fn from_json(source: &str, json: &str) -> Result<Self, String> {
+        let mut run: Self = serde_json::from_str(json)
+            .map_err(|e| format!("{} is not an exported run summary: {}", source, e))?;
+        run.source = source.to_string();
+        Ok(run)
+    }
+}
+
+/// Distribution of end-to-end latencies, in steps.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// Number of samples
+    pub count: usize,
+    /// Shortest latency
+    pub min: u64,
+    /// Longest latency
+    pub max: u64,
+    /// Average latency
+    pub mean: f64,
+    /// Median
+    pub p50: u64,
+    /// 95th percentile
+    pub p95: u64,
+    /// 99th percentile
+    pub p99: u64,
+}
+
+impl LatencyStats {
+    /// Computes the distribution of `sorted` latencies; `None` if there are none.
+    pub fn from_sorted(sorted: &[u64]) -> Option<Self> {
+        let (&min, &max) = (sorted.first()?, sorted.last()?);
+        Some(Self {
+            count: sorted.len(),
+            min,
+            max,
+            mean: sorted.iter().sum::<u64>() as f64 / sorted.len() as f64,
+            p50: percentile(sorted, 50.0),
+            p95: percentile(sorted, 95.0),
+            p99: percentile(sorted, 99.0),
+        })
+    }
+}
+
+/// Nearest-rank percentile of a non-empty sorted slice.
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Counts `sorted` latencies into at most `max_bins` equal-width bins.
+///
+/// Returns `(first, last, count)` per bin, where the bin covers latencies
+/// `first..=last`. Bins are at least one step wide.
+pub fn latency_histogram(sorted: &[u64], max_bins: usize) -> Vec<(u64, u64, usize)> {
+    let (Some(&min), Some(&max)) = (sorted.first(), sorted.last()) else {
+        return Vec::new();
+    };
+    let span = max - min + 1;
+    let width = span.div_ceil(max_bins.max(1) as u64);
+    let mut bins: Vec<(u64, u64, usize)> = (0..span.div_ceil(width))
+        .map(|i| (min + i * width, min + (i + 1) * width - 1, 0))
+        .collect();
+    for latency in sorted {
+        bins[((latency - min) / width) as usize].2 += 1;
+    }
+    bins
+}
+
+fn per_step(count: u64, steps: u64) -> f64 {
+    if steps == 0 {
+        0.0
+    } else {
+        count as f64 / steps as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(step: u64, consumed: u64, in_transit: u64) -> StepSample {
+        StepSample {
+            step,
+            consumed,
+            delivered: consumed,
+            in_transit,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_summary_only_covers_measurement_window() {
+        let mut stats = RunStats::default();
+        // A startup burst in the first steps, then a steady state
+        for step in 0..10 {
+            let consumed = if step < 3 { 10 } else { 2 };
+            stats.push(sample(step, consumed, step));
+        }
+
+        let all = stats.summarize(&RunConfig::default());
+        assert_eq!(all.measured_steps, 10);
+        assert_eq!(all.consumed, 44);
+
+        let settings = RunConfig {
+            warm_up_steps: 3,
+            measurement_steps: 5,
+            ..Default::default()
+        };
+        let window = stats.summarize(&settings);
+        assert_eq!(window.measured_steps, 5);
+        assert_eq!((window.first_step, window.last_step), (Some(3), Some(7)));
+        assert_eq!(window.throughput(), 2.0);
+        assert_eq!(window.max_in_transit, 7);
+        assert_eq!(window.mean_in_transit, 5.0);
+        assert!(window.is_complete());
+        assert!(!stats.summarize(&RunConfig { warm_up_steps: 3, measurement_steps: 50, ..Default::default() }).is_complete());
+
+        let flowchart = Flowchart {
+            run_config: settings,
+            ..Default::default()
+        };
+        let csv = stats.to_csv(&flowchart);
+        assert!(stats.latency_csv(&flowchart).lines().eq(["step,measured,consumer,latency"]));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "step,measured,produced,delivered,consumed,errors,in_transit");
+        assert!(lines[1].starts_with("0,false,"));
+        assert!(lines[4].starts_with("3,true,"));
+        assert!(lines[9].starts_with("8,false,"));
+    }
+
+    #[test]
+    fn test_latency_percentiles_and_histogram() {
+        let latencies: Vec<u64> = (1..=100).collect();
+        let stats = LatencyStats::from_sorted(&latencies).unwrap();
+        assert_eq!((stats.min, stats.max, stats.count), (1, 100, 100));
+        assert_eq!((stats.p50, stats.p95, stats.p99), (50, 95, 99));
+        assert_eq!(stats.mean, 50.5);
+        assert!(LatencyStats::from_sorted(&[]).is_none());
+
+        let bins = latency_histogram(&latencies, 10);
+        assert_eq!(bins.len(), 10);
+        assert_eq!(bins[0], (1, 10, 10));
+        assert_eq!(bins[9], (91, 100, 10));
+        // Narrow ranges get one bin per step
+        assert_eq!(latency_histogram(&[3, 3, 4], 10), vec![(3, 3, 2), (4, 4, 1)]);
+    }
+
+    #[test]
+    fn test_exported_summary_loads_as_baseline() {
+        let mut flowchart = Flowchart::default();
+        let consumer = flowchart.add_node(FlowchartNode::new(
+            "Sink".into(),
+            (0.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        let mut stats = RunStats::default();
+        for step in 0..4 {
+            let mut s = sample(step, 2, 1);
+            s.deliveries.insert(consumer, 2);
+            s.latencies = vec![(consumer, step + 1); 2];
+            stats.push(s);
+        }
+        let summary = stats.summarize(&RunConfig { warm_up_steps: 1, measurement_steps: 0, ..Default::default() });
+        let json = summary.to_json(&stats, &flowchart).unwrap();
+
+        let baseline = BaselineRun::from_json("run_summary.json", &json).unwrap();
+        assert_eq!(baseline.source, "run_summary.json");
+        assert_eq!((baseline.warm_up_steps, baseline.measured_steps), (1, 3));
+        assert_eq!(baseline.throughput, summary.throughput());
+        assert_eq!(baseline.steps, vec![[0, 2, 1], [1, 2, 1], [2, 2, 1], [3, 2, 1]]);
+        let sink = &baseline.nodes["Sink"];
+        assert_eq!((sink.delivered, sink.per_step), (6, 2.0));
+        assert_eq!(sink.latency, summary.latency_stats(consumer));
+
+        // A flowchart document is not a summary
+        assert!(BaselineRun::from_json("chart.json", &flowchart.to_json().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+//! Per-node configuration as a table.
+//!
+//! [`node_table_csv`] lists every node with its type and the parameters that
+//! shape the simulation, one row per node, for reviewing large models in a
+//! spreadsheet. Columns that don't apply to a node's type are left empty.
+//! Scripts and message templates are shortened to their first characters,
+//! with a hash of the full text so an audit can tell whether two nodes, or
+//! two versions of a model, run the same code.
+
+use crate::constants::NODE_TABLE_CODE_CHARS;
+use crate::metrics::csv_field;
+use crate::types::*;
+
+/// Column headers of [`node_table_csv`], in order.
+pub const NODE_TABLE_COLUMNS: [&str; 26] = [
+    "name",
+    "id",
+    "type",
+    "groups",
+    "lane",
+    "start_step",
+    "messages",
+    "steps_between",
+    "phase",
+    "jitter",
+    "consumption_rate",
+    "processing_time",
+    "max_concurrency",
+    "accept_filter",
+    "outputs",
+    "retry_attempts",
+    "retry_backoff",
+    "failure_probability",
+    "failure_mode",
+    "code",
+    "code_hash",
+    "code_length",
+    "source_file",
+    "reference",
+    "plugin",
+    "pinned",
+];
+
+/// FNV-1a hash of `text`, as 16 hex digits.
+pub fn code_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// `text` on one line, cut to `NODE_TABLE_CODE_CHARS` characters.
+fn shorten(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= NODE_TABLE_CODE_CHARS {
+        line
+    } else {
+        let mut short: String = line.chars().take(NODE_TABLE_CODE_CHARS).collect();
+        short.push('…');
+        short
+    }
+}
+
+/// One row of the table, a cell per entry of [`NODE_TABLE_COLUMNS`].
+pub fn node_row(flowchart: &Flowchart, node: &FlowchartNode) -> Vec<String> {
+    let mut groups: Vec<&str> = flowchart
+        .groups
+        .values()
+        .filter(|group| group.members.contains(&node.id))
+        .map(|group| group.name.as_str())
+        .collect();
+    groups.sort_unstable();
+    let lane = node
+        .lane
+        .and_then(|id| flowchart.swimlanes.lane(id))
+        .map_or_else(String::new, |lane| lane.name.clone());
+
+    let mut row = vec![String::new(); NODE_TABLE_COLUMNS.len()];
+    let mut set = |column: &str, value: String| {
+        if let Some(index) = NODE_TABLE_COLUMNS.iter().position(|c| *c == column) {
+            row[index] = value;
+        }
+    };
+    set("name", node.name.clone());
+    set("id", node.id.to_string());
+    set("groups", groups.join("; "));
+    set("lane", lane);
+    let code = match &node.node_type {
+        NodeType::Producer {
+            message_template,
+            start_step,
+            messages_per_cycle,
+            steps_between_cycles,
+            ..
+        } => {
+            set("type", "Producer".into());
+            set("start_step", start_step.to_string());
+            set("messages", messages_per_cycle.to_string());
+            set("steps_between", steps_between_cycles.to_string());
+            set("phase", node.timing.phase.to_string());
+            set("jitter", node.timing.jitter.to_string());
+            Some(serde_json::to_string(message_template).unwrap_or_default())
+        }
+        NodeType::Consumer {
+            consumption_rate,
+            processing_time,
+            max_concurrency,
+            accept_filter,
+            ..
+        } => {
+            set("type", "Consumer".into());
+            set("consumption_rate", consumption_rate.to_string());
+            set("processing_time", processing_time.to_string());
+            set("max_concurrency", max_concurrency.to_string());
+            set("accept_filter", accept_filter.clone());
+            None
+        }
+        NodeType::Transformer {
+            script,
+            selected_outputs,
+            retry,
+            ..
+        } => {
+            set("type", "Transformer".into());
+            set("outputs", selected_outputs.as_ref().map_or_else(|| "all".to_string(), |names| names.join("; ")));
+            set("retry_attempts", retry.max_attempts.to_string());
+            set("retry_backoff", retry.backoff_steps.to_string());
+            Some(script.clone())
+        }
+        NodeType::Reference { path } => {
+            set("type", "Reference".into());
+            set("reference", path.clone());
+            None
+        }
+    };
+    if matches!(node.node_type, NodeType::Transformer { .. } | NodeType::Consumer { .. }) {
+        set("failure_probability", node.failure.probability.to_string());
+        set("failure_mode", node.failure.mode.label().to_string());
+    }
+    if let Some(code) = code {
+        set("code", shorten(&code));
+        set("code_hash", code_hash(&code));
+        set("code_length", code.chars().count().to_string());
+    }
+    set("source_file", node.source_file.clone().unwrap_or_default());
+    set("plugin", node.plugin.as_ref().map_or_else(String::new, |plugin| plugin.id.clone()));
+    set("pinned", node.pinned.to_string());
+    row
+}
+
+/// Every node of `flowchart` as CSV, ordered by name.
+pub fn node_table_csv(flowchart: &Flowchart) -> String {
+    let mut nodes: Vec<&FlowchartNode> = flowchart.nodes.values().collect();
+    nodes.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+
+    let mut csv = NODE_TABLE_COLUMNS.join(",");
+    csv.push('\n');
+    for node in nodes {
+        let row: Vec<String> = node_row(flowchart, node).iter().map(|cell| csv_field(cell)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rows_fill_the_columns_of_each_type() {
+        let mut flowchart = Flowchart::new();
+        let script = format!("function transform(input) {{\n    return input; // {}\n}}", "x".repeat(80));
+        let transformer = flowchart.add_node(FlowchartNode::new(
+            "Enrich, then route".into(),
+            (0.0, 0.0),
+            NodeType::Transformer {
+                script: script.clone(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: RetryPolicy { max_attempts: 3, backoff_steps: 2 },
+            },
+        ));
+        flowchart.add_node(FlowchartNode::new(
+            "Orders".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({"id": 1}),
+                start_step: 4,
+                messages_per_cycle: 10,
+                steps_between_cycles: 2,
+                messages_produced: 0,
+            },
+        ));
+        let group = Group {
+            id: uuid::Uuid::new_v4(),
+            name: "Billing".into(),
+            members: vec![transformer],
+            drawing: GroupDrawingMode::default(),
+            z_order: 0,
+        };
+        flowchart.groups.insert(group.id, group);
+
+        let csv = node_table_csv(&flowchart);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], NODE_TABLE_COLUMNS.join(","));
+        assert!(lines[1].starts_with("\"Enrich, then route\","), "{}", lines[1]);
+        assert!(lines[2].starts_with("Orders,"), "{}", lines[2]);
+
+        let cell = |row: &[String], column: &str| row[NODE_TABLE_COLUMNS.iter().position(|c| *c == column).unwrap()].clone();
+        let row = node_row(&flowchart, &flowchart.nodes[&transformer]);
+        assert_eq!(cell(&row, "groups"), "Billing");
+        assert_eq!(cell(&row, "retry_attempts"), "3");
+        assert_eq!(cell(&row, "start_step"), "");
+        assert_eq!(cell(&row, "code").chars().count(), NODE_TABLE_CODE_CHARS + 1);
+        assert!(cell(&row, "code").starts_with("function transform(input) { return input;"));
+        assert_eq!(cell(&row, "code_hash"), code_hash(&script));
+        assert_ne!(code_hash(&script), code_hash("function transform(input) { return input; }"));
+    }
+}
@@ -0,0 +1,308 @@
+//! Document-level parameters.
+//!
+//! A flowchart can define named [`Parameters`], such as `NUM_WORKERS` or
+//! `BASE_RATE`, so one model can be reused across environments by changing a
+//! few values in one place. Parameters reach the model in three ways:
+//!
+//! - A [`Binding`] ties a numeric node property to a parameter; after each
+//!   change [`apply_parameters`] writes the parameter's value into every
+//!   property bound to it, so the simulation reads plain node values.
+//! - Producer message templates may use `"{{NAME}}"`: a string that is only
+//!   the reference becomes the parameter's value, and references inside
+//!   longer strings are replaced by its text (see [`substitute`]).
+//! - Transformer scripts read them as `context.params.NAME`.
+
+use crate::types::{Flowchart, NodeId, NodeType};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A numeric node property a parameter can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BoundProperty {
+    /// Producer step of the first message
+    StartStep,
+    /// Producer total messages
+    MessagesPerCycle,
+    /// Producer steps between cycles
+    StepsBetweenCycles,
+    /// Consumer messages taken per step
+    ConsumptionRate,
+    /// Consumer processing time in steps
+    ProcessingTime,
+    /// Consumer messages processed at once
+    MaxConcurrency,
+}
+
+impl BoundProperty {
+    /// Every property, producers' first.
+    pub const ALL: [BoundProperty; 6] = [
+        BoundProperty::StartStep,
+        BoundProperty::MessagesPerCycle,
+        BoundProperty::StepsBetweenCycles,
+        BoundProperty::ConsumptionRate,
+        BoundProperty::ProcessingTime,
+        BoundProperty::MaxConcurrency,
+    ];
+
+    /// Name shown in the parameters window.
+    pub fn label(self) -> &'static str {
+        match self {
+            BoundProperty::StartStep => "start step",
+            BoundProperty::MessagesPerCycle => "total messages",
+            BoundProperty::StepsBetweenCycles => "steps between cycles",
+            BoundProperty::ConsumptionRate => "consumption rate",
+            BoundProperty::ProcessingTime => "processing time",
+            BoundProperty::MaxConcurrency => "max concurrency",
+        }
+    }
+
+    /// Returns true if nodes of `node_type` have this property.
+    pub fn applies_to(self, node_type: &NodeType) -> bool {
+        match self {
+            BoundProperty::StartStep | BoundProperty::MessagesPerCycle | BoundProperty::StepsBetweenCycles => {
+                matches!(node_type, NodeType::Producer { .. })
+            }
+            _ => matches!(node_type, NodeType::Consumer { .. }),
+        }
+    }
+
+    /// Sets the property on `node_type`, returning false if it doesn't have
+    /// it or the value doesn't fit.
+    pub fn set(self, node_type: &mut NodeType, value: u64) -> bool {
+        match (self, node_type) {
+            (BoundProperty::StartStep, NodeType::Producer { start_step, .. }) => *start_step = value,
+            (BoundProperty::MessagesPerCycle, NodeType::Producer { messages_per_cycle: field, .. })
+            | (BoundProperty::StepsBetweenCycles, NodeType::Producer { steps_between_cycles: field, .. })
+            | (BoundProperty::ConsumptionRate, NodeType::Consumer { consumption_rate: field, .. })
+            | (BoundProperty::ProcessingTime, NodeType::Consumer { processing_time: field, .. })
+            | (BoundProperty::MaxConcurrency, NodeType::Consumer { max_concurrency: field, .. }) => {
+                let Ok(value) = u32::try_from(value) else {
+                    return false;
+                };
+                *field = value;
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// A node property that takes its value from a parameter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Binding {
+    /// The node
+    pub node: NodeId,
+    /// Its property
+    pub property: BoundProperty,
+    /// Name of the parameter
+    pub parameter: String,
+}
+
+/// Named values of a document and the node properties bound to them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Parameters {
+    /// Values by name
+    #[serde(default)]
+    pub values: BTreeMap<String, Value>,
+    /// Node properties set from the values
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bindings: Vec<Binding>,
+}
+
+impl Parameters {
+    /// Returns true if there are no values and no bindings.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.bindings.is_empty()
+    }
+
+    /// The values as the JSON object scripts see as `context.params`.
+    pub fn to_json(&self) -> Value {
+        Value::Object(self.values.clone().into_iter().collect())
+    }
+
+    /// Binds `property` of `node` to `parameter`, replacing an earlier binding
+    /// of the same property.
+    pub fn bind(&mut self, node: NodeId, property: BoundProperty, parameter: &str) {
+        self.unbind(node, property);
+        self.bindings.push(Binding {
+            node,
+            property,
+            parameter: parameter.to_string(),
+        });
+    }
+
+    /// Removes the binding of `property` of `node`, if any.
+    pub fn unbind(&mut self, node: NodeId, property: BoundProperty) {
+        self.bindings
+            .retain(|binding| !(binding.node == node && binding.property == property));
+    }
+
+    /// Renames a parameter along with the bindings that use it.
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(value) = self.values.remove(from) {
+            self.values.insert(to.to_string(), value);
+        }
+        for binding in &mut self.bindings {
+            if binding.parameter == from {
+                binding.parameter = to.to_string();
+            }
+        }
+    }
+}
+
+/// Returns true if `name` can name a parameter: a letter or underscore
+/// followed by letters, digits and underscores, so scripts can write
+/// `context.params.NAME`.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Writes the value of each bound parameter into its node property.
+///
+/// # Returns
+///
+/// A description of each binding that couldn't be applied: its parameter is
+/// missing or not a whole number the property can hold. Bindings of deleted
+/// nodes are skipped.
+pub fn apply_parameters(flowchart: &mut Flowchart) -> Vec<String> {
+    let mut problems = Vec::new();
+    let Flowchart { nodes, parameters, .. } = flowchart;
+    for binding in &parameters.bindings {
+        let Some(node) = nodes.get_mut(&binding.node) else {
+            continue;
+        };
+        if !binding.property.applies_to(&node.node_type) {
+            problems.push(format!("{} has no {}", node.name, binding.property.label()));
+            continue;
+        }
+        let value = parameters.values.get(&binding.parameter);
+        let applied = value
+            .and_then(whole_number)
+            .is_some_and(|number| binding.property.set(&mut node.node_type, number));
+        if !applied {
+            let reason = match value {
+                None => "doesn't exist".to_string(),
+                Some(value) => format!("is {}, not a whole number {} can be", value, binding.property.label()),
+            };
+            problems.push(format!("{}: parameter {} {}", node.name, binding.parameter, reason));
+        }
+    }
+    problems
+}
+
+/// A non-negative whole number, also from a number written with a zero fraction.
+fn whole_number(value: &Value) -> Option<u64> {
+    value
+        .as_u64()
+        .or_else(|| value.as_f64().filter(|f| *f >= 0.0 && f.fract() == 0.0).map(|f| f as u64))
+}
+
+/// `template` with its `{{NAME}}` references to `values` replaced.
+///
+/// A string that is nothing but one reference becomes the value itself, so
+/// `{"workers": "{{NUM_WORKERS}}"}` gives a number. References inside longer
+/// strings are replaced by the value's text. Unknown names are left as written.
+pub fn substitute(template: &Value, values: &BTreeMap<String, Value>) -> Value {
+    match template {
+        Value::String(text) => {
+            let whole = text
+                .strip_prefix("{{")
+                .and_then(|rest| rest.strip_suffix("}}"))
+                .filter(|name| !name.contains("{{"))
+                .and_then(|name| values.get(name.trim()));
+            if let Some(value) = whole {
+                return value.clone();
+            }
+            let mut out = text.clone();
+            for (name, value) in values {
+                let reference = format!("{{{{{}}}}}", name);
+                if out.contains(&reference) {
+                    let text = value.as_str().map_or_else(|| value.to_string(), str::to_string);
+                    out = out.replace(&reference, &text);
+                }
+            }
+            Value::String(out)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|item| substitute(item, values)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), substitute(value, values)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FlowchartNode;
+    use serde_json::json;
+
+    #[test]
+    fn bound_properties_follow_their_parameters() {
+        let mut flowchart = Flowchart::new();
+        let sink = flowchart.add_node(FlowchartNode::new(
+            "Sink".into(),
+            (0.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 0,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.parameters.values.insert("NUM_WORKERS".into(), json!(4));
+        flowchart.parameters.bind(sink, BoundProperty::MaxConcurrency, "NUM_WORKERS");
+        flowchart.parameters.bind(sink, BoundProperty::ProcessingTime, "DELAY");
+        flowchart.parameters.bind(sink, BoundProperty::StartStep, "NUM_WORKERS");
+
+        let problems = apply_parameters(&mut flowchart);
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[0].contains("DELAY doesn't exist"));
+        let NodeType::Consumer { max_concurrency, .. } = flowchart.nodes[&sink].node_type else {
+            unreachable!()
+        };
+        assert_eq!(max_concurrency, 4);
+
+        flowchart.parameters.rename("NUM_WORKERS", "WORKERS");
+        flowchart.parameters.values.insert("WORKERS".into(), json!(2.0));
+        flowchart.parameters.unbind(sink, BoundProperty::StartStep);
+        flowchart.parameters.values.insert("DELAY".into(), json!(-1));
+        assert_eq!(apply_parameters(&mut flowchart).len(), 1);
+        let NodeType::Consumer { max_concurrency, .. } = flowchart.nodes[&sink].node_type else {
+            unreachable!()
+        };
+        assert_eq!(max_concurrency, 2);
+
+        assert!(is_valid_name("BASE_RATE") && is_valid_name("_x1"));
+        assert!(!is_valid_name("2FAST") && !is_valid_name("a-b") && !is_valid_name(""));
+    }
+
+    #[test]
+    fn template_references_become_values_or_text() {
+        let values = BTreeMap::from([("RATE".to_string(), json!(2.5)), ("ENV".to_string(), json!("staging"))]);
+        let template = json!({
+            "rate": "{{RATE}}",
+            "url": "https://{{ENV}}.example.com/?r={{RATE}}",
+            "tags": ["{{ENV}}", "{{MISSING}}"],
+            "n": 3
+        });
+        assert_eq!(
+            substitute(&template, &values),
+            json!({
+                "rate": 2.5,
+                "url": "https://staging.example.com/?r=2.5",
+                "tags": ["staging", "{{MISSING}}"],
+                "n": 3
+            })
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Shared storage for identical message payloads.
+//!
+//! Producers usually emit the same template over and over, so a long run can
+//! hold thousands of messages carrying equal payloads. The simulation engine
+//! passes every new message through a [`PayloadPool`], which swaps its payload
+//! for a shared copy of an equal one already in use. [`PayloadStats`] reports
+//! how much memory that saves.
+
+use crate::types::Message;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Memory saved by sharing payloads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PayloadStats {
+    /// Distinct payloads currently in use
+    pub distinct: usize,
+    /// Messages (including those kept in node histories) using a pooled payload
+    pub references: usize,
+    /// Approximate bytes of payload held once instead of per message
+    pub bytes_saved: usize,
+}
+
+/// Deduplicates message payloads by value.
+#[derive(Debug, Default)]
+pub struct PayloadPool {
+    /// Pooled payloads with their approximate size in bytes
+    payloads: HashMap<Arc<Value>, usize>,
+}
+
+impl PayloadPool {
+    /// Replaces `message`'s payload with the pooled copy of an equal payload,
+    /// or adds it to the pool if it is the first of its kind.
+    pub fn intern(&mut self, message: &mut Message) {
+        if let Some((shared, _)) = self.payloads.get_key_value(&*message.data) {
+            if !Arc::ptr_eq(shared, &message.data) {
+                message.data = shared.clone();
+            }
+            return;
+        }
+        let size = serde_json::to_string(&*message.data).map_or(0, |json| json.len());
+        self.payloads.insert(message.data.clone(), size);
+    }
+
+    /// Forgets payloads no message uses any more.
+    pub fn prune(&mut self) {
+        self.payloads.retain(|payload, _| Arc::strong_count(payload) > 1);
+    }
+
+    /// Forgets every payload.
+    pub fn clear(&mut self) {
+        self.payloads.clear();
+    }
+
+    /// How many payloads are shared and the memory that saves.
+    pub fn stats(&self) -> PayloadStats {
+        let mut stats = PayloadStats::default();
+        for (payload, size) in &self.payloads {
+            // The pool holds one reference of its own
+            let users = Arc::strong_count(payload) - 1;
+            if users == 0 {
+                continue;
+            }
+            stats.distinct += 1;
+            stats.references += users;
+            stats.bytes_saved += (users - 1) * size;
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_equal_payloads_share_one_copy() {
+        let mut pool = PayloadPool::default();
+        let mut a = Message::new(json!({"order": 1}));
+        let mut b = Message::new(json!({"order": 1}));
+        let mut c = Message::new(json!({"order": 2}));
+        pool.intern(&mut a);
+        pool.intern(&mut b);
+        pool.intern(&mut c);
+        assert!(Arc::ptr_eq(&a.data, &b.data));
+        assert!(!Arc::ptr_eq(&a.data, &c.data));
+
+        let stats = pool.stats();
+        assert_eq!(stats.distinct, 2);
+        assert_eq!(stats.references, 3);
+        assert_eq!(stats.bytes_saved, r#"{"order":1}"#.len());
+
+        // Editing a shared payload leaves the others alone
+        Arc::make_mut(&mut b.data)["order"] = json!(3);
+        assert_eq!(a.data["order"], 1);
+
+        drop(c);
+        pool.prune();
+        assert_eq!(pool.stats().distinct, 1);
+    }
+}
@@ -0,0 +1,337 @@
+//! Plugin behavior packs for custom node types.
+//!
+//! A plugin is a JSON manifest plus a JavaScript behavior script. Nodes
+//! created from a plugin are transformer nodes: the script is copied into the
+//! node and runs in the script engine like any transformer script, and the
+//! declared properties become the node's initial global state
+//! (`globalThis.state`). The node also keeps a [`PluginRef`] with the
+//! plugin's id, icon and ports, so documents still open and run on machines
+//! without the plugin installed.
+//!
+//! Manifest example:
+//!
+//! ```json
+//! {
+//!   "id": "rate-limiter",
+//!   "name": "Rate Limiter",
+//!   "version": "1.0.0",
+//!   "description": "Passes at most `limit` messages per step",
+//!   "icon": "⏱",
+//!   "script": "behavior.js",
+//!   "properties": [{ "name": "limit", "label": "Limit", "default": 5 }],
+//!   "ports": { "inputs": true, "outputs": true }
+//! }
+//! ```
+//!
+//! On the desktop, plugins are loaded from the `plugins` folder in the app's
+//! data directory: either `<folder>/plugin.json` with the script file next to
+//! it, or a single `.json` file whose manifest has the script inline in
+//! `source`. The web build installs single-file plugins by upload.
+
+use crate::types::{NodeType, PluginRef};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Manifest file name inside a plugin folder.
+const MANIFEST_FILE: &str = "plugin.json";
+
+/// A property a plugin node exposes to its script.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PluginProperty {
+    /// Key under `globalThis.state`; must be a JavaScript identifier
+    pub name: String,
+    /// Name shown in the properties panel; defaults to `name`
+    #[serde(default)]
+    pub label: String,
+    /// Value new nodes start with
+    #[serde(default)]
+    pub default: serde_json::Value,
+    /// Help text shown on hover
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Which sides of a plugin node can be connected.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PluginPorts {
+    /// Whether the node receives messages
+    #[serde(default = "default_true")]
+    pub inputs: bool,
+    /// Whether the node sends messages on
+    #[serde(default = "default_true")]
+    pub outputs: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for PluginPorts {
+    fn default() -> Self {
+        Self {
+            inputs: true,
+            outputs: true,
+        }
+    }
+}
+
+/// The manifest describing a plugin.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PluginManifest {
+    /// Stable identifier, e.g. `rate-limiter`
+    pub id: String,
+    /// Name shown in menus and given to new nodes
+    pub name: String,
+    /// Free-form version string
+    #[serde(default)]
+    pub version: String,
+    /// One-line description
+    #[serde(default)]
+    pub description: String,
+    /// Short icon text (usually one emoji or symbol) drawn on nodes
+    #[serde(default)]
+    pub icon: String,
+    /// Script file, relative to the manifest (desktop folders only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    /// Inline script source; used instead of `script` when present
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Properties exposed to the script
+    #[serde(default)]
+    pub properties: Vec<PluginProperty>,
+    /// Connectable sides
+    #[serde(default)]
+    pub ports: PluginPorts,
+}
+
+/// A loaded plugin: its manifest and behavior script.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Plugin {
+    /// The manifest
+    pub manifest: PluginManifest,
+    /// JavaScript behavior, written like a transformer script
+    pub script: String,
+}
+
+/// Returns true if `name` can be used as a JavaScript property identifier.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+impl Plugin {
+    /// Parses a manifest, taking the script from `source` or, failing that,
+    /// from the `script` file resolved against `base_dir`.
+    pub fn from_manifest(manifest_json: &str, base_dir: Option<&Path>) -> Result<Self, String> {
+        let manifest: PluginManifest = serde_json::from_str(manifest_json)
+            .map_err(|e| format!("Invalid plugin manifest: {}", e))?;
+        let label = if manifest.name.is_empty() { manifest.id.clone() } else { manifest.name.clone() };
+
+        if manifest.id.is_empty()
+            || !manifest
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(format!(
+                "Plugin \"{}\": id must be non-empty and use only letters, digits, '-', '_' or '.'",
+                label
+            ));
+        }
+        if manifest.name.trim().is_empty() {
+            return Err(format!("Plugin \"{}\": name is required", manifest.id));
+        }
+        if !manifest.ports.inputs {
+            // Behaviors run when a message arrives, so a node without inputs would never run
+            return Err(format!("Plugin \"{}\": nodes must accept inputs", label));
+        }
+        let mut seen = std::collections::HashSet::new();
+        for property in &manifest.properties {
+            if !is_identifier(&property.name) {
+                return Err(format!(
+                    "Plugin \"{}\": property \"{}\" is not a valid identifier",
+                    label, property.name
+                ));
+            }
+            if !seen.insert(property.name.as_str()) {
+                return Err(format!("Plugin \"{}\": property \"{}\" is declared twice", label, property.name));
+            }
+        }
+
+        let script = match (&manifest.source, &manifest.script, base_dir) {
+            (Some(source), _, _) => source.clone(),
+            (None, Some(file), Some(dir)) => std::fs::read_to_string(dir.join(file))
+                .map_err(|e| format!("Plugin \"{}\": failed to read {}: {}", label, file, e))?,
+            (None, Some(_), None) => {
+                return Err(format!(
+                    "Plugin \"{}\": uploaded plugins must include the script inline as \"source\"",
+                    label
+                ))
+            }
+            (None, None, _) => return Err(format!("Plugin \"{}\": no script given", label)),
+        };
+        Ok(Self { manifest, script })
+    }
+
+    /// Single-file form of the plugin, with the script inline.
+    pub fn to_bundle_json(&self) -> Result<String, String> {
+        let mut manifest = self.manifest.clone();
+        manifest.script = None;
+        manifest.source = Some(self.script.clone());
+        serde_json::to_string_pretty(&manifest).map_err(|e| format!("Failed to serialize plugin: {}", e))
+    }
+
+    /// Node type for a new node of this plugin.
+    pub fn node_type(&self) -> NodeType {
+        let globals: serde_json::Map<String, serde_json::Value> = self
+            .manifest
+            .properties
+            .iter()
+            .map(|p| (p.name.clone(), p.default.clone()))
+            .collect();
+        NodeType::Transformer {
+            script: self.script.clone(),
+            selected_outputs: None,
+            globals: globals.clone(),
+            initial_globals: globals,
+            retry: Default::default(),
+        }
+    }
+
+    /// Link stored on nodes created from this plugin.
+    pub fn node_ref(&self) -> PluginRef {
+        PluginRef {
+            id: self.manifest.id.clone(),
+            icon: self.manifest.icon.clone(),
+            outputs: self.manifest.ports.outputs,
+        }
+    }
+}
+
+/// The installed plugins, by id.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PluginRegistry {
+    plugins: BTreeMap<String, Plugin>,
+}
+
+impl PluginRegistry {
+    /// Looks up a plugin by id.
+    pub fn get(&self, id: &str) -> Option<&Plugin> {
+        self.plugins.get(id)
+    }
+
+    /// Iterates over the plugins ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = &Plugin> {
+        self.plugins.values()
+    }
+
+    /// Returns true when no plugins are installed.
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Adds a plugin, replacing any with the same id.
+    pub fn insert(&mut self, plugin: Plugin) {
+        self.plugins.insert(plugin.manifest.id.clone(), plugin);
+    }
+
+    /// Removes a plugin by id.
+    pub fn remove(&mut self, id: &str) -> Option<Plugin> {
+        self.plugins.remove(id)
+    }
+
+    /// Loads every plugin in `dir`.
+    ///
+    /// Returns the registry and one message per plugin that failed to load.
+    pub fn load_dir(dir: &Path) -> (Self, Vec<String>) {
+        let mut registry = Self::default();
+        let mut errors = Vec::new();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return (registry, errors);
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        for path in paths {
+            let (manifest_path, base_dir) = if path.is_dir() {
+                (path.join(MANIFEST_FILE), path.clone())
+            } else if path.extension().is_some_and(|e| e == "json") {
+                (path.clone(), dir.to_path_buf())
+            } else {
+                continue;
+            };
+            let Ok(manifest) = std::fs::read_to_string(&manifest_path) else {
+                continue;
+            };
+            match Plugin::from_manifest(&manifest, Some(&base_dir)) {
+                Ok(plugin) => registry.insert(plugin),
+                Err(e) => errors.push(format!("{}: {}", manifest_path.display(), e)),
+            }
+        }
+        (registry, errors)
+    }
+}
+
+/// Folder the desktop app loads plugins from.
+///
+/// Tests get `None` so they never read a real user's plugins.
+pub fn plugins_dir() -> Option<PathBuf> {
+    #[cfg(not(any(target_arch = "wasm32", test)))]
+    {
+        eframe::storage_dir("flowchart_tool").map(|dir| dir.join("plugins"))
+    }
+    #[cfg(any(target_arch = "wasm32", test))]
+    {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_dir_reads_folders_and_bundles_and_reports_errors() {
+        let dir = std::env::temp_dir().join(format!("flowchart_plugins_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("limiter")).unwrap();
+        std::fs::write(
+            dir.join("limiter").join(MANIFEST_FILE),
+            r#"{"id": "limiter", "name": "Limiter", "icon": "⏱", "script": "behavior.js",
+                "properties": [{"name": "limit", "default": 5}]}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("limiter").join("behavior.js"), "return input;").unwrap();
+        std::fs::write(
+            dir.join("sink.json"),
+            r#"{"id": "sink", "name": "Sink", "source": "return null;", "ports": {"outputs": false}}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("broken.json"), r#"{"id": "bad id", "name": "Bad", "source": ""}"#).unwrap();
+
+        let (registry, errors) = PluginRegistry::load_dir(&dir);
+        assert_eq!(registry.iter().count(), 2);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("broken.json"));
+
+        let limiter = registry.get("limiter").unwrap();
+        assert_eq!(limiter.script, "return input;");
+        match limiter.node_type() {
+            NodeType::Transformer { initial_globals, .. } => {
+                assert_eq!(initial_globals["limit"], serde_json::json!(5))
+            }
+            other => panic!("unexpected node type {:?}", other),
+        }
+        assert!(!registry.get("sink").unwrap().node_ref().outputs);
+
+        // The single-file form loads back without the folder
+        let bundle = limiter.to_bundle_json().unwrap();
+        let reloaded = Plugin::from_manifest(&bundle, None).unwrap();
+        assert_eq!(reloaded.script, limiter.script);
+        assert_eq!(reloaded.manifest.properties, limiter.manifest.properties);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
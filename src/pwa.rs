@@ -0,0 +1,110 @@
+//! Packaging the web build as an installable app that works offline.
+//!
+//! The page hosting the wasm build registers a service worker that keeps the
+//! app's files in a cache named [`PWA_CACHE_NAME`], serves the manifest from
+//! [`web_app_manifest`] with [`APP_ICON_SVG`] next to it as `icon.svg`, and
+//! can ask the browser to keep its storage with `persist_storage` so saved
+//! documents and the offline copy survive when space runs low.
+//!
+//! When the worker finds a newer release waiting, the page calls
+//! `FlowchartHandle::notify_update_available`. The app shows a notification
+//! offering to reload; once the user accepts, after the unsaved-changes
+//! prompt if needed, it calls the page's `on_update_accepted` listener so the
+//! page can activate the new worker and reload, or reloads the page itself if
+//! there is none.
+
+use crate::constants::PWA_CACHE_NAME;
+use serde_json::json;
+
+/// The app icon, used as the favicon and the installed app's icon.
+pub const APP_ICON_SVG: &str = r#"<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 64 64'>
+<circle cx='32' cy='32' r='26' fill='#4287F5' stroke='#2a5db0' stroke-width='4'/>
+<path d='M20 28h24v8H20z' fill='white'/>
+</svg>"#;
+
+/// The web app manifest, as JSON.
+pub fn web_app_manifest() -> String {
+    json!({
+        "name": "Flowchart Tool",
+        "short_name": "Flowchart",
+        "description": "Visual flowchart editor and simulator",
+        "start_url": ".",
+        "scope": ".",
+        "display": "standalone",
+        "background_color": "#1b1b1b",
+        "theme_color": "#4287F5",
+        "icons": [{ "src": "icon.svg", "sizes": "any", "type": "image/svg+xml", "purpose": "any" }],
+        "version": env!("CARGO_PKG_VERSION"),
+        "cache": PWA_CACHE_NAME,
+    })
+    .to_string()
+}
+
+/// Whether `offered` is a later release than the one running.
+///
+/// Versions are compared number by number; anything that isn't a dotted
+/// list of numbers counts as newer unless it is the running version.
+pub fn is_newer_version(offered: &str) -> bool {
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        version.trim().trim_start_matches('v').split('.').map(|part| part.parse().ok()).collect()
+    };
+    let current = env!("CARGO_PKG_VERSION");
+    match (parse(offered), parse(current)) {
+        (Some(offered), Some(current)) => offered > current,
+        _ => offered.trim() != current,
+    }
+}
+
+/// Describes a JavaScript error for the user.
+#[cfg(target_arch = "wasm32")]
+fn describe_js_error(error: crate::wasm_bindgen::JsValue) -> String {
+    error.as_string().unwrap_or_else(|| format!("Browser storage error: {:?}", error))
+}
+
+/// Asks the browser not to evict the app's storage when space runs low.
+///
+/// # Returns
+///
+/// Whether the browser agreed, or a description of the error.
+#[cfg(target_arch = "wasm32")]
+pub async fn persist_storage() -> Result<bool, String> {
+    let window = web_sys::window().ok_or("No browser window")?;
+    let promise = window.navigator().storage().persist().map_err(describe_js_error)?;
+    let granted = wasm_bindgen_futures::JsFuture::from(promise).await.map_err(describe_js_error)?;
+    Ok(granted.as_bool().unwrap_or(false))
+}
+
+/// Bytes of browser storage the app uses and may use, as (usage, quota).
+#[cfg(target_arch = "wasm32")]
+pub async fn storage_estimate() -> Result<(f64, f64), String> {
+    let window = web_sys::window().ok_or("No browser window")?;
+    let promise = window.navigator().storage().estimate().map_err(describe_js_error)?;
+    let estimate = wasm_bindgen_futures::JsFuture::from(promise).await.map_err(describe_js_error)?;
+    let field = |name: &str| {
+        js_sys::Reflect::get(&estimate, &name.into())
+            .ok()
+            .and_then(|value| value.as_f64())
+            .unwrap_or(0.0)
+    };
+    Ok((field("usage"), field("quota")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_names_this_release_and_versions_compare_by_number() {
+        let manifest: serde_json::Value = serde_json::from_str(&web_app_manifest()).unwrap();
+        assert_eq!(manifest["display"], "standalone");
+        assert_eq!(manifest["cache"], PWA_CACHE_NAME);
+        assert!(PWA_CACHE_NAME.ends_with(env!("CARGO_PKG_VERSION")));
+
+        let current = env!("CARGO_PKG_VERSION");
+        assert!(!is_newer_version(current));
+        assert!(!is_newer_version("0.0.1"));
+        assert!(is_newer_version("999.0.0"));
+        assert!(is_newer_version(&format!("v{}.1", current)));
+        assert!(is_newer_version("nightly"));
+    }
+}
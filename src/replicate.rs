@@ -0,0 +1,444 @@
+//! Replicating a subgraph.
+//!
+//! [`replicate`] turns a selection of nodes into a pool of identical copies,
+//! for modelling scaled-out workers without duplicating them by hand. The
+//! selection becomes the first copy and the others are stacked below it, each
+//! with its nodes' names suffixed by the copy's number ("Worker 1",
+//! "Worker 2", ...) and wired to the same nodes outside the selection.
+//!
+//! Optionally a fan-out transformer is inserted in front of the copies, handing
+//! each incoming message to the next copy in turn, and a fan-in transformer
+//! behind them that merges their output again. Scripts that route with
+//! `__targets` by name are left as written.
+
+use crate::constants::{DEFAULT_TRANSFORMER_SCRIPT, NODE_HEIGHT, PIPELINE_NODE_SPACING, REPLICATE_ROW_GAP};
+use crate::parameters::Binding;
+use crate::types::*;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// How [`replicate`] builds the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplicateOptions {
+    /// Number of copies, including the selection itself
+    pub count: usize,
+    /// Insert a transformer that spreads incoming messages over the copies
+    pub fan_out: bool,
+    /// Insert a transformer that merges the copies' output
+    pub fan_in: bool,
+}
+
+/// Everything [`replicate`] changed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Replication {
+    /// The nodes of each copy, in selection order; the first copy is the selection
+    pub copies: Vec<Vec<NodeId>>,
+    /// The fan-out transformer, if one was inserted
+    pub fan_out: Option<NodeId>,
+    /// The fan-in transformer, if one was inserted
+    pub fan_in: Option<NodeId>,
+    /// Selected nodes renamed to carry the number 1, as `(node, old, new)`
+    pub renamed: Vec<(NodeId, String, String)>,
+    /// Connections removed in favour of the fan nodes, with the index each
+    /// had when removed, in removal order
+    pub removed: Vec<(Connection, usize)>,
+    /// Connections added, as `(from, to)` pairs
+    pub connections: Vec<(NodeId, NodeId)>,
+}
+
+impl Replication {
+    /// The nodes added: the new copies followed by the fan nodes.
+    pub fn created(&self) -> Vec<NodeId> {
+        self.copies
+            .iter()
+            .skip(1)
+            .flatten()
+            .copied()
+            .chain(self.fan_out)
+            .chain(self.fan_in)
+            .collect()
+    }
+}
+
+/// Script of a fan-out transformer sending each message to the next of `copies`.
+fn fan_out_script(copies: &[Vec<String>]) -> String {
+    let targets = serde_json::to_string(copies).expect("names always serialize");
+    format!(
+        "// Hands each message to the next copy in turn\n\
+         const copies = {};\n\
+         function transform(input) {{\n    \
+             const next = (state.next || 0) % copies.length;\n    \
+             state.next = next + 1;\n    \
+             input.__targets = copies[next];\n    \
+             return input;\n\
+         }}",
+        targets
+    )
+}
+
+/// Connects `from` to `to`, noting the connection in `added`.
+fn connect(flowchart: &mut Flowchart, from: NodeId, to: NodeId, added: &mut Vec<(NodeId, NodeId)>) {
+    if flowchart.add_connection(from, to).is_ok() {
+        added.push((from, to));
+    }
+}
+
+/// A transformer for the fan nodes.
+fn fan_node(name: String, position: (f32, f32), script: String) -> FlowchartNode {
+    FlowchartNode::new(
+        name,
+        position,
+        NodeType::Transformer {
+            script,
+            selected_outputs: None,
+            globals: Default::default(),
+            initial_globals: Default::default(),
+            retry: Default::default(),
+        },
+    )
+}
+
+/// Turns the selected nodes into the first of `count` numbered copies.
+///
+/// # Arguments
+///
+/// * `flowchart` - The flowchart to change
+/// * `node_ids` - The selected nodes; unknown IDs are ignored
+/// * `options` - Number of copies and which fan nodes to insert
+///
+/// # Returns
+///
+/// What was changed, or an error if the selection is empty or fewer than two
+/// copies are asked for. Connections between the selected nodes are copied
+/// into every copy; connections to and from other nodes are repeated for
+/// every copy, or go through the fan nodes instead. Parameter bindings of the
+/// selected nodes are repeated for their copies, and transformers choosing
+/// outputs by name are pointed at the copies or fan nodes.
+pub fn replicate(
+    flowchart: &mut Flowchart,
+    node_ids: &[NodeId],
+    options: ReplicateOptions,
+) -> Result<Replication, String> {
+    let mut originals: Vec<NodeId> = Vec::new();
+    for id in node_ids {
+        if flowchart.nodes.contains_key(id) && !originals.contains(id) {
+            originals.push(*id);
+        }
+    }
+    if originals.is_empty() {
+        return Err("Select the nodes to replicate".to_string());
+    }
+    if options.count < 2 {
+        return Err("Replicating needs at least two copies".to_string());
+    }
+    let selected: HashSet<NodeId> = originals.iter().copied().collect();
+    let old_names: Vec<String> = originals.iter().map(|id| flowchart.nodes[id].name.clone()).collect();
+
+    // Names: every copy numbered, none clashing with the nodes left alone
+    let mut taken: HashSet<String> = flowchart
+        .nodes
+        .values()
+        .filter(|node| !selected.contains(&node.id))
+        .map(|node| node.name.clone())
+        .collect();
+    let mut unique = |base: String| {
+        let name = if taken.contains(&base) {
+            (2..)
+                .map(|n| format!("{} ({})", base, n))
+                .find(|candidate| !taken.contains(candidate))
+                .expect("unbounded range always yields a free name")
+        } else {
+            base
+        };
+        taken.insert(name.clone());
+        name
+    };
+    let names: Vec<Vec<String>> = (1..=options.count)
+        .map(|i| old_names.iter().map(|name| unique(format!("{} {}", name, i))).collect())
+        .collect();
+
+    let internal: Vec<(NodeId, NodeId)> = flowchart
+        .connections
+        .iter()
+        .filter(|c| selected.contains(&c.from) && selected.contains(&c.to))
+        .map(|c| (c.from, c.to))
+        .collect();
+    let incoming: Vec<(usize, NodeId, NodeId)> = flowchart
+        .connections
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !selected.contains(&c.from) && selected.contains(&c.to))
+        .map(|(index, c)| (index, c.from, c.to))
+        .collect();
+    let outgoing: Vec<(usize, NodeId, NodeId)> = flowchart
+        .connections
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| selected.contains(&c.from) && !selected.contains(&c.to))
+        .map(|(index, c)| (index, c.from, c.to))
+        .collect();
+
+    // Where messages enter and leave a copy: the ends of the outside
+    // connections, or else the selected nodes with no connections inside
+    type Outside = (usize, NodeId, NodeId);
+    let ends = |outside: &[Outside], end: fn(&Outside) -> NodeId, open: &dyn Fn(&NodeId) -> bool| {
+        let mut ids: Vec<NodeId> = Vec::new();
+        for id in outside.iter().map(end) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        if ids.is_empty() {
+            ids = originals.iter().copied().filter(|id| open(id)).collect();
+        }
+        ids
+    };
+    let entries: Vec<NodeId> = ends(&incoming, |c| c.2, &|id| {
+        !matches!(flowchart.nodes[id].node_type, NodeType::Producer { .. })
+            && !internal.iter().any(|(_, to)| to == id)
+    });
+    let exits: Vec<NodeId> = ends(&outgoing, |c| c.1, &|id| {
+        let node = &flowchart.nodes[id];
+        (node.tap || !matches!(node.node_type, NodeType::Consumer { .. }))
+            && !internal.iter().any(|(from, _)| from == id)
+    });
+    let fan_out = options.fan_out && !entries.is_empty();
+    let fan_in = options.fan_in && !exits.is_empty();
+    let fan_out_name = fan_out.then(|| unique("Fan-out".to_string()));
+    let fan_in_name = fan_in.then(|| unique("Fan-in".to_string()));
+
+    let mut result = Replication::default();
+    let mut removed: Vec<usize> = Vec::new();
+    if fan_out {
+        removed.extend(incoming.iter().map(|c| c.0));
+    }
+    if fan_in {
+        removed.extend(outgoing.iter().map(|c| c.0));
+    }
+    removed.sort_unstable_by(|a, b| b.cmp(a));
+    for index in removed {
+        result.removed.push((flowchart.connections.remove(index), index));
+    }
+
+    // Transformers outside the selection choosing it by name now choose
+    // every copy, or the fan-out
+    let mut original_names: HashMap<String, usize> = HashMap::new();
+    for (k, name) in old_names.iter().enumerate() {
+        original_names.entry(name.clone()).or_insert(k);
+    }
+    for node in flowchart.nodes.values_mut().filter(|node| !selected.contains(&node.id)) {
+        let NodeType::Transformer {
+            selected_outputs: Some(outputs),
+            ..
+        } = &mut node.node_type
+        else {
+            continue;
+        };
+        let mut next: Vec<String> = Vec::new();
+        for name in outputs.iter() {
+            let replacements = match (original_names.get(name), &fan_out_name) {
+                (Some(_), Some(fan)) => vec![fan.clone()],
+                (Some(&k), None) => names.iter().map(|copy| copy[k].clone()).collect(),
+                (None, _) => vec![name.clone()],
+            };
+            for replacement in replacements {
+                if !next.contains(&replacement) {
+                    next.push(replacement);
+                }
+            }
+        }
+        *outputs = next;
+    }
+
+    let (min_x, max_x, min_y, max_y) = originals.iter().fold(
+        (f32::INFINITY, f32::NEG_INFINITY, f32::INFINITY, f32::NEG_INFINITY),
+        |(min_x, max_x, min_y, max_y), id| {
+            let (x, y) = flowchart.nodes[id].position;
+            (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+        },
+    );
+    let row = max_y - min_y + NODE_HEIGHT + REPLICATE_ROW_GAP;
+    let outside_names: HashSet<String> = outgoing
+        .iter()
+        .map(|(_, _, to)| flowchart.nodes[to].name.clone())
+        .collect();
+
+    for (i, copy_names) in names.iter().enumerate() {
+        let rename = |name: &String| match original_names.get(name) {
+            Some(&k) => copy_names[k].clone(),
+            None => match &fan_in_name {
+                Some(fan) if outside_names.contains(name) => fan.clone(),
+                _ => name.clone(),
+            },
+        };
+        let mut ids = Vec::with_capacity(originals.len());
+        for (k, id) in originals.iter().enumerate() {
+            let mut node = flowchart.nodes[id].clone();
+            if let NodeType::Transformer {
+                selected_outputs: Some(outputs),
+                ..
+            } = &mut node.node_type
+            {
+                let mut next: Vec<String> = Vec::new();
+                for name in outputs.iter().map(rename) {
+                    if !next.contains(&name) {
+                        next.push(name);
+                    }
+                }
+                *outputs = next;
+            }
+            node.name = copy_names[k].clone();
+            if i == 0 {
+                result.renamed.push((*id, old_names[k].clone(), node.name.clone()));
+                flowchart.nodes.insert(*id, node);
+                ids.push(*id);
+            } else {
+                node.id = Uuid::new_v4();
+                node.state = NodeState::Idle;
+                node.position.1 += row * i as f32;
+                ids.push(flowchart.add_node(node));
+            }
+        }
+        result.copies.push(ids);
+    }
+
+    let index_of = |id: &NodeId| originals.iter().position(|o| o == id).expect("selected node");
+    let copy_of = |i: usize, id: &NodeId| result.copies[i][index_of(id)];
+    let mut added = Vec::new();
+    for i in 1..options.count {
+        for (from, to) in &internal {
+            connect(flowchart, copy_of(i, from), copy_of(i, to), &mut added);
+        }
+        if !fan_out {
+            for (_, from, to) in &incoming {
+                connect(flowchart, *from, copy_of(i, to), &mut added);
+            }
+        }
+        if !fan_in {
+            for (_, from, to) in &outgoing {
+                connect(flowchart, copy_of(i, from), *to, &mut added);
+            }
+        }
+    }
+
+    let middle = (min_y + max_y) / 2.0 + row * (options.count - 1) as f32 / 2.0;
+    if let Some(name) = fan_out_name {
+        let targets: Vec<Vec<String>> = (0..options.count)
+            .map(|i| entries.iter().map(|id| names[i][index_of(id)].clone()).collect())
+            .collect();
+        let position = (min_x - PIPELINE_NODE_SPACING, middle);
+        let fan = flowchart.add_node(fan_node(name, position, fan_out_script(&targets)));
+        let mut sources: Vec<NodeId> = Vec::new();
+        for (_, from, _) in &incoming {
+            if !sources.contains(from) {
+                sources.push(*from);
+                connect(flowchart, *from, fan, &mut added);
+            }
+        }
+        for i in 0..options.count {
+            for id in &entries {
+                connect(flowchart, fan, copy_of(i, id), &mut added);
+            }
+        }
+        result.fan_out = Some(fan);
+    }
+    if let Some(name) = fan_in_name {
+        let fan = flowchart.add_node(fan_node(
+            name,
+            (max_x + PIPELINE_NODE_SPACING, middle),
+            DEFAULT_TRANSFORMER_SCRIPT.to_string(),
+        ));
+        for i in 0..options.count {
+            for id in &exits {
+                connect(flowchart, copy_of(i, id), fan, &mut added);
+            }
+        }
+        let mut targets: Vec<NodeId> = Vec::new();
+        for (_, _, to) in &outgoing {
+            if !targets.contains(to) {
+                targets.push(*to);
+                connect(flowchart, fan, *to, &mut added);
+            }
+        }
+        result.fan_in = Some(fan);
+    }
+    result.connections = added;
+
+    let copied: Vec<Binding> = flowchart
+        .parameters
+        .bindings
+        .iter()
+        .filter_map(|binding| originals.iter().position(|id| *id == binding.node).map(|k| (k, binding)))
+        .flat_map(|(k, binding)| {
+            result.copies.iter().skip(1).map(move |copy| Binding {
+                node: copy[k],
+                ..binding.clone()
+            })
+        })
+        .collect();
+    flowchart.parameters.bindings.extend(copied);
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parameters::BoundProperty;
+
+    fn transformer(name: &str) -> FlowchartNode {
+        fan_node(name.to_string(), (0.0, 0.0), DEFAULT_TRANSFORMER_SCRIPT.to_string())
+    }
+
+    #[test]
+    fn copies_are_numbered_and_wired_through_the_fan_nodes() {
+        let mut flowchart = Flowchart::new();
+        let source = flowchart.add_node(transformer("Source"));
+        let parse = flowchart.add_node(transformer("Parse"));
+        let store = flowchart.add_node(transformer("Store"));
+        let sink = flowchart.add_node(transformer("Sink"));
+        for (from, to) in [(source, parse), (parse, store), (store, sink)] {
+            flowchart.add_connection(from, to).unwrap();
+        }
+        if let NodeType::Transformer { selected_outputs, .. } = &mut flowchart.nodes.get_mut(&source).unwrap().node_type {
+            *selected_outputs = Some(vec!["Parse".to_string()]);
+        }
+        flowchart.parameters.bind(store, BoundProperty::ProcessingTime, "DELAY");
+
+        let options = ReplicateOptions { count: 3, fan_out: false, fan_in: false };
+        assert!(replicate(&mut flowchart, &[], options).is_err());
+        let mut plain = flowchart.clone();
+        let result = replicate(&mut plain, &[parse, store], options).unwrap();
+        assert_eq!(result.created().len(), 4);
+        assert_eq!(plain.nodes[&parse].name, "Parse 1");
+        assert_eq!(plain.nodes[&result.copies[2][1]].name, "Store 3");
+        // Each copy: its own Parse → Store, fed by Source and feeding Sink
+        assert_eq!(plain.connections.len(), 9);
+        let NodeType::Transformer { selected_outputs, .. } = &plain.nodes[&source].node_type else {
+            unreachable!()
+        };
+        assert_eq!(selected_outputs.as_deref().unwrap(), ["Parse 1", "Parse 2", "Parse 3"]);
+        assert_eq!(plain.parameters.bindings.len(), 3);
+
+        let options = ReplicateOptions { fan_out: true, fan_in: true, ..options };
+        let result = replicate(&mut flowchart, &[parse, store], options).unwrap();
+        let (fan_out, fan_in) = (result.fan_out.unwrap(), result.fan_in.unwrap());
+        assert_eq!(result.removed.len(), 2);
+        let mut expected = vec![(source, fan_out), (fan_in, sink)];
+        for copy in &result.copies {
+            expected.extend([(copy[0], copy[1]), (fan_out, copy[0]), (copy[1], fan_in)]);
+        }
+        let mut actual: Vec<(NodeId, NodeId)> = flowchart.connections.iter().map(|c| (c.from, c.to)).collect();
+        expected.sort();
+        actual.sort();
+        assert_eq!(actual, expected);
+        let NodeType::Transformer { script, .. } = &flowchart.nodes[&fan_out].node_type else {
+            unreachable!()
+        };
+        assert!(script.contains(r#"[["Parse 1"],["Parse 2"],["Parse 3"]]"#), "{}", script);
+        let NodeType::Transformer { selected_outputs, .. } = &flowchart.nodes[&source].node_type else {
+            unreachable!()
+        };
+        assert_eq!(selected_outputs.as_deref().unwrap(), ["Fan-out"]);
+    }
+}
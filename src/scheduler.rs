@@ -0,0 +1,194 @@
+//! Agenda of the simulation engine.
+//!
+//! Rather than visiting every node and connection on every step, the
+//! [`SimulationEngine`](crate::simulation::SimulationEngine) keeps a priority
+//! queue of [`Wakeup`]s keyed by the step they are due on: a connection with
+//! messages that may be deliverable, a producer whose next message is due, or
+//! a node whose state has to settle back to idle. A step only handles the
+//! wakeups due on it and the consumers still busy with earlier messages, so
+//! quiet parts of a large flowchart cost next to nothing.
+//!
+//! Waking something up early is harmless: it does what the step would have
+//! done anyway. Missing a wakeup is not, so the agenda is rebuilt from the
+//! whole flowchart whenever it can't be trusted: when a run starts, when
+//! nodes, connections or messages in transit were added or removed behind the
+//! engine's back, when the step counter moved, and after
+//! [`Agenda::invalidate`]. Edits that change how a node schedules its work,
+//! such as a producer's timing, must be followed by
+//! [`SimulationEngine::reschedule`](crate::simulation::SimulationEngine::reschedule).
+
+use crate::types::*;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap};
+
+/// Something the engine has to look at on a given step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wakeup {
+    /// Try to deliver the messages on a connection, by index
+    Deliver(usize),
+    /// Let a producer emit its next message
+    Produce(NodeId),
+    /// Update the state of a consumer or transformer that handled messages
+    Settle(NodeId),
+}
+
+/// The wakeups due on one step, each listed once and in a fixed order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DueWork {
+    /// Connections, by index
+    pub connections: BTreeSet<usize>,
+    /// Producers
+    pub producers: BTreeSet<NodeId>,
+    /// Consumers and transformers
+    pub settle: BTreeSet<NodeId>,
+}
+
+/// What the flowchart looked like when the agenda last matched it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fingerprint {
+    step: u64,
+    nodes: usize,
+    connections: usize,
+    in_transit: usize,
+}
+
+impl Fingerprint {
+    fn of(flowchart: &Flowchart) -> Self {
+        Self {
+            step: flowchart.current_step,
+            nodes: flowchart.nodes.len(),
+            connections: flowchart.connections.len(),
+            in_transit: flowchart.connections.iter().map(|c| c.messages.len()).sum(),
+        }
+    }
+}
+
+/// Wakeups by step, and the consumers with messages in progress.
+#[derive(Debug, Clone, Default)]
+pub struct Agenda {
+    queue: BinaryHeap<Reverse<(u64, Wakeup)>>,
+    /// Consumers whose `in_flight` isn't empty
+    busy: BTreeSet<NodeId>,
+    /// The flowchart as of the end of the last step, if the agenda matches it
+    synced: Option<Fingerprint>,
+}
+
+impl Agenda {
+    /// Makes the next step rebuild the agenda from the whole flowchart.
+    pub fn invalidate(&mut self) {
+        self.synced = None;
+    }
+
+    /// Schedules `wakeup` for `step`.
+    pub fn wake(&mut self, step: u64, wakeup: Wakeup) {
+        self.queue.push(Reverse((step, wakeup)));
+    }
+
+    /// Notes that the engine put `count` messages on connections between
+    /// steps, so they aren't mistaken for changes made behind its back.
+    pub fn note_sent(&mut self, count: usize) {
+        if let Some(synced) = &mut self.synced {
+            synced.in_transit += count;
+        }
+    }
+
+    /// Notes that messages were taken off connections between steps.
+    pub fn note_taken(&mut self, count: usize) {
+        if let Some(synced) = &mut self.synced {
+            synced.in_transit = synced.in_transit.saturating_sub(count);
+        }
+    }
+
+    /// Notes the flowchart as it is at the end of a step.
+    pub fn mark_synced(&mut self, flowchart: &Flowchart) {
+        self.synced = Some(Fingerprint::of(flowchart));
+    }
+
+    /// Rebuilds the agenda if `flowchart` changed in a way it didn't see, or
+    /// a run is starting.
+    pub fn sync(&mut self, flowchart: &Flowchart) {
+        if flowchart.current_step != 0 && self.synced == Some(Fingerprint::of(flowchart)) {
+            return;
+        }
+        let step = flowchart.current_step;
+        self.queue.clear();
+        self.busy.clear();
+        for (index, connection) in flowchart.connections.iter().enumerate() {
+            if !connection.messages.is_empty() {
+                self.wake(step, Wakeup::Deliver(index));
+            }
+        }
+        for node in flowchart.nodes.values() {
+            match &node.node_type {
+                NodeType::Producer { .. } => self.wake(step, Wakeup::Produce(node.id)),
+                NodeType::Consumer { in_flight, .. } => {
+                    if !in_flight.is_empty() {
+                        self.busy.insert(node.id);
+                    }
+                    self.wake(step, Wakeup::Settle(node.id));
+                }
+                NodeType::Transformer { .. } => self.wake(step, Wakeup::Settle(node.id)),
+                NodeType::Reference { .. } => {}
+            }
+        }
+    }
+
+    /// Removes and returns the wakeups due on or before `step`.
+    pub fn take_due(&mut self, step: u64) -> DueWork {
+        let mut due = DueWork::default();
+        while let Some(&Reverse((at, wakeup))) = self.queue.peek() {
+            if at > step {
+                break;
+            }
+            self.queue.pop();
+            match wakeup {
+                Wakeup::Deliver(index) => due.connections.insert(index),
+                Wakeup::Produce(id) => due.producers.insert(id),
+                Wakeup::Settle(id) => due.settle.insert(id),
+            };
+        }
+        due
+    }
+
+    /// Consumers with messages in progress.
+    pub fn busy(&self) -> &BTreeSet<NodeId> {
+        &self.busy
+    }
+
+    /// Marks a consumer as busy or not.
+    pub fn set_busy(&mut self, id: NodeId, busy: bool) {
+        if busy {
+            self.busy.insert(id);
+        } else {
+            self.busy.remove(&id);
+        }
+    }
+
+    /// Steps the agenda has work on, in order.
+    pub fn scheduled_steps(&self) -> BTreeSet<u64> {
+        self.queue.iter().map(|Reverse((step, _))| *step).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn due_wakeups_come_out_once_and_in_order() {
+        let mut agenda = Agenda::default();
+        let node = NodeId::from_u128(1);
+        agenda.wake(5, Wakeup::Deliver(2));
+        agenda.wake(3, Wakeup::Deliver(0));
+        agenda.wake(3, Wakeup::Deliver(0));
+        agenda.wake(4, Wakeup::Produce(node));
+        agenda.wake(9, Wakeup::Settle(node));
+        assert_eq!(agenda.scheduled_steps(), BTreeSet::from([3, 4, 5, 9]));
+
+        let due = agenda.take_due(5);
+        assert_eq!(due.connections, BTreeSet::from([0, 2]));
+        assert_eq!(due.producers, BTreeSet::from([node]));
+        assert!(due.settle.is_empty());
+        assert_eq!(agenda.scheduled_steps(), BTreeSet::from([9]));
+    }
+}
@@ -0,0 +1,50 @@
+//! JSON Schema of the flowchart file format.
+//!
+//! The schema is generated from the same serde types that read and write
+//! documents, so it always describes the format of this version. External
+//! tools can use it to validate flowchart files or generate new ones; the
+//! desktop app prints it with `flowchart_tool --print-schema`.
+
+use crate::types::Flowchart;
+
+/// Schema of a flowchart document.
+pub fn flowchart_schema() -> schemars::Schema {
+    let mut schema = schemars::schema_for!(Flowchart);
+    schema.insert("title".into(), "Flowchart document".into());
+    schema
+}
+
+/// [`flowchart_schema`] as pretty-printed JSON.
+pub fn flowchart_schema_json() -> String {
+    serde_json::to_string_pretty(&flowchart_schema()).expect("schemas serialize to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::{all_examples, build_example};
+    use serde_json::Value;
+
+    #[test]
+    fn test_schema_describes_saved_documents() {
+        let schema: Value = serde_json::from_str(&flowchart_schema_json()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        let required: Vec<&str> = schema["required"].as_array().unwrap().iter().filter_map(Value::as_str).collect();
+        assert!(required.contains(&"nodes"));
+        // Fields with defaults may be left out of a file
+        assert!(!required.contains(&"groups"));
+
+        for example in all_examples() {
+            let document: Value = serde_json::from_str(&build_example(example.kind).to_json().unwrap()).unwrap();
+            for key in document.as_object().unwrap().keys() {
+                assert!(properties.contains_key(key), "{} missing from the schema", key);
+            }
+        }
+
+        // Every node type is described
+        let node_type = serde_json::to_string(&schema["$defs"]["NodeType"]).unwrap();
+        for variant in ["Producer", "Consumer", "Transformer", "Reference"] {
+            assert!(node_type.contains(variant), "{} missing from the schema", variant);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! The script environment of transformer nodes.
+//!
+//! [`SCRIPT_API`] describes everything a transformer script can use, each
+//! with an example. The Transformer editor's Script API panel is drawn from
+//! it, and the tests below run every example against its documented input
+//! and output, so the panel can't drift from what the engine does.
+
+use crate::types::NodeId;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// One part of the script environment.
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptApiItem {
+    /// Name as written in a script
+    pub name: &'static str,
+    /// What it is and how the engine treats it
+    pub summary: &'static str,
+    /// A complete transformer script using it
+    pub example: &'static str,
+    /// Message the example is given, as JSON
+    pub input: &'static str,
+    /// Message the example returns for `input`, as JSON
+    pub output: &'static str,
+}
+
+/// Everything a transformer script can use.
+pub const SCRIPT_API: &[ScriptApiItem] = &[
+    ScriptApiItem {
+        name: "transform(input, context)",
+        summary: "Called once for each message the node receives, with the message payload as `input`. \
+                  Return the message to send on, an array to send one message per element, or null to drop it. \
+                  Top-level code runs once, so variables declared outside transform keep their values between calls.",
+        example: "function transform(input) {\n    return { value: input.value * 2 };\n}",
+        input: r#"{"value": 21}"#,
+        output: r#"{"value": 42}"#,
+    },
+    ScriptApiItem {
+        name: "context",
+        summary: "Second argument of transform: `node` is this node's name, `step` the simulation step the message \
+                  arrived on, `attempt` 1 for the first try and higher on retries, and `params` the document's \
+                  parameters by name.",
+        example: "function transform(input, context) {\n    input.seenBy = context.node;\n    input.retried = context.attempt > 1;\n    return input;\n}",
+        input: r#"{"id": 7}"#,
+        output: r#"{"id": 7, "seenBy": "Transformer", "retried": false}"#,
+    },
+    ScriptApiItem {
+        name: "globalThis.state",
+        summary: "The node's Global State, as an object. Changes persist from one message to the next, \
+                  and Stop resets it to the initial values.",
+        example: "function transform(input) {\n    state.count = (state.count || 0) + 1;\n    input.count = state.count;\n    return input;\n}",
+        input: r#"{}"#,
+        output: r#"{"count": 1}"#,
+    },
+    ScriptApiItem {
+        name: "__targets",
+        summary: "Array of node names on a returned message. The message goes only to connected nodes with those names; \
+                  leave it out or set it to null to send to every output. It is removed before delivery.",
+        example: "function transform(input) {\n    input.__targets = [input.value % 2 === 0 ? \"Even\" : \"Odd\"];\n    return input;\n}",
+        input: r#"{"value": 4}"#,
+        output: r#"{"value": 4, "__targets": ["Even"]}"#,
+    },
+    ScriptApiItem {
+        name: "__priority",
+        summary: "Number on a returned message; higher priorities are delivered ahead of lower ones on the same \
+                  connection. Without it a message keeps the priority of its input. It is removed before delivery.",
+        example: "function transform(input) {\n    if (input.urgent) {\n        input.__priority = 10;\n    }\n    return input;\n}",
+        input: r#"{"urgent": true}"#,
+        output: r#"{"urgent": true, "__priority": 10}"#,
+    },
+    ScriptApiItem {
+        name: "console",
+        summary: "console.log, info, warn, error and debug accept any arguments and discard them, so scripts that log \
+                  don't fail. Use the Inspector and Global State to look at values instead.",
+        example: "function transform(input) {\n    console.log(\"received\", input);\n    return input;\n}",
+        input: r#"{"ok": true}"#,
+        output: r#"{"ok": true}"#,
+    },
+];
+
+/// Defines the `console` object on a fresh engine.
+pub(crate) const CONSOLE_SHIM: &str = "globalThis.console = (function () {\n\
+    const discard = function () {};\n\
+    return Object.freeze({ log: discard, info: discard, warn: discard, error: discard, debug: discard });\n\
+})();";
+
+/// The `context` argument passed to `transform` with each message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptContext {
+    /// Id of the transformer node; scripts don't see it, it picks the
+    /// node's prepared engine
+    pub node_id: NodeId,
+    /// Name of the transformer node
+    pub node: String,
+    /// Simulation step the message arrived on
+    pub step: u64,
+    /// 1 for the first try, higher on retries
+    pub attempt: u32,
+    /// The document's parameters, as an object; built once per step and
+    /// shared by the contexts of all its messages
+    pub params: Arc<Value>,
+}
+
+impl Default for ScriptContext {
+    fn default() -> Self {
+        Self {
+            node_id: NodeId::nil(),
+            node: "Transformer".to_string(),
+            step: 0,
+            attempt: 1,
+            params: Arc::new(json!({})),
+        }
+    }
+}
+
+impl ScriptContext {
+    /// The context as the JSON object the script receives.
+    pub fn to_json(&self) -> Value {
+        json!({ "node": self.node, "step": self.step, "attempt": self.attempt, "params": self.params })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::execute_transformer_script_in_context;
+    use crate::types::Message;
+
+    #[test]
+    fn every_example_returns_its_documented_output() {
+        for item in SCRIPT_API {
+            let input: Value = serde_json::from_str(item.input).unwrap();
+            let expected: Value = serde_json::from_str(item.output).unwrap();
+            let mut globals = Default::default();
+            let outputs = execute_transformer_script_in_context(
+                item.example,
+                &Message::new(input),
+                &mut globals,
+                &ScriptContext::default(),
+            )
+            .unwrap_or_else(|e| panic!("example for {} failed: {}", item.name, e));
+            assert_eq!(outputs.len(), 1, "{}", item.name);
+            assert_eq!(*outputs[0].data, expected, "{}", item.name);
+        }
+    }
+
+    #[test]
+    fn context_reports_the_node_step_attempt_and_params() {
+        let script = "function transform(input, context) { return context; }";
+        let context = ScriptContext {
+            node: "Router".into(),
+            step: 12,
+            attempt: 3,
+            params: Arc::new(json!({"NUM_WORKERS": 4})),
+            ..Default::default()
+        };
+        let mut globals = Default::default();
+        let outputs =
+            execute_transformer_script_in_context(script, &Message::new(json!({})), &mut globals, &context).unwrap();
+        assert_eq!(*outputs[0].data, context.to_json());
+    }
+}
@@ -16,13 +16,27 @@
 //!
 //! This makes it safe to execute scripts from untrusted sources, as they can only
 //! perform data transformations on the provided input without side effects.
+//!
+//! # Prepared Scripts
+//!
+//! Creating a context and evaluating a transformer's source costs far more than
+//! calling its `transform` function, so [`PreparedScripts`] keeps one engine
+//! per transformer and reuses it for every message. The simulation engine owns
+//! the ones of a run and drops them when the run is reset; the free functions
+//! such as `execute_transformer_script` use a set kept per thread (see
+//! [`with_prepared_scripts`]).
 
 use boa_engine::property::NonMaxU32;
 use boa_engine::{
     js_string, object::builtins::JsArray, property::PropertyKey, Context, JsObject, JsResult,
     JsString, JsValue, Source,
 };
+use crate::constants::PREPARED_SCRIPT_LIMIT;
+use crate::types::NodeId;
 use serde_json::Value;
+use std::cell::RefCell;
+use std::mem::ManuallyDrop;
+use std::collections::{HashMap, VecDeque};
 
 /// JavaScript script execution engine that works on all platforms
 pub struct JavaScriptEngine {
@@ -85,6 +99,9 @@ impl JavaScriptEngine {
         // Optional: You can also disable other potentially dangerous features
         // like import() if boa supports it in future versions
 
+        // A console that discards its output, so scripts that log still run
+        context.eval(Source::from_bytes(crate::script_api::CONSOLE_SHIM))?;
+
         Ok(())
     }
 
@@ -131,6 +148,13 @@ impl JavaScriptEngine {
     ///
     /// The function must be defined in the global scope.
     pub fn call_function(&mut self, function_name: &str, arg: Value) -> Result<Value, String> {
+        self.call_function_with_args(function_name, &[arg])
+    }
+
+    /// Call a JavaScript function by name with the given arguments
+    ///
+    /// The function must be defined in the global scope.
+    pub fn call_function_with_args(&mut self, function_name: &str, args: &[Value]) -> Result<Value, String> {
         // Get the function from the global object
         let global = self.context.global_object().clone();
         let function_key = PropertyKey::String(JsString::from(function_name));
@@ -143,14 +167,17 @@ impl JavaScriptEngine {
             return Err(format!("'{}' is not a function", function_name));
         }
 
-        // Convert the argument to JsValue
-        let js_arg = self.json_to_js_value(&arg)?;
+        // Convert the arguments to JsValues
+        let js_args = args
+            .iter()
+            .map(|arg| self.json_to_js_value(arg))
+            .collect::<Result<Vec<_>, _>>()?;
 
         // Call the function
         let result = function_value
             .as_callable()
             .ok_or_else(|| format!("'{}' is not callable", function_name))?
-            .call(&JsValue::undefined(), &[js_arg], &mut self.context)
+            .call(&JsValue::undefined(), &js_args, &mut self.context)
             .map_err(|e| format!("Function call failed: {}", e))?;
 
         // Convert the result back to JSON
@@ -289,6 +316,105 @@ pub fn create_script_engine() -> Result<JavaScriptEngine, String> {
     JavaScriptEngine::new()
 }
 
+/// An engine on which a transformer's script has been evaluated.
+struct PreparedScript {
+    /// The evaluated source; a different one needs a new engine
+    source: String,
+    engine: JavaScriptEngine,
+}
+
+/// Engines on which transformer scripts have been evaluated, one per
+/// transformer, so each keeps a global scope of its own.
+///
+/// Boa contexts cannot move between threads, so the engines stay on the
+/// thread that prepared them.
+#[derive(Default)]
+pub struct PreparedScripts {
+    engines: HashMap<NodeId, PreparedScript>,
+    /// Transformers in the order their engines were prepared, oldest first
+    order: VecDeque<NodeId>,
+}
+
+impl PreparedScripts {
+    /// Drops every engine, e.g. when the simulation is reset.
+    pub fn clear(&mut self) {
+        self.engines.clear();
+        self.order.clear();
+    }
+
+    /// Runs `f` on the engine of the transformer `owner` after it has
+    /// evaluated `script`, so functions the script defines can be called
+    /// without evaluating its source again.
+    ///
+    /// `state` is bound as the global `state` before the script is first
+    /// evaluated, so top-level code sees it, and again before every call. The
+    /// engine is kept for later calls until `owner`'s script changes, up to
+    /// `PREPARED_SCRIPT_LIMIT` transformers. Its global scope therefore carries
+    /// over between calls: top-level code runs once, and variables a function
+    /// changes keep their values. A script whose evaluation fails is not kept.
+    pub fn run<R>(
+        &mut self,
+        owner: NodeId,
+        script: &str,
+        state: &Value,
+        f: impl FnOnce(&mut JavaScriptEngine) -> Result<R, String>,
+    ) -> Result<R, String> {
+        if self.engines.get(&owner).is_none_or(|prepared| prepared.source != script) {
+            let mut engine =
+                create_script_engine().map_err(|e| format!("Failed to create script engine: {}", e))?;
+            engine
+                .set_global_json("state", state)
+                .map_err(|e| format!("Failed to set state: {}", e))?;
+            engine
+                .execute_script(script)
+                .map_err(|e| format!("Failed to execute script: {}", e))?;
+            self.order.retain(|id| *id != owner);
+            if self.order.len() >= PREPARED_SCRIPT_LIMIT {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.engines.remove(&oldest);
+                }
+            }
+            self.order.push_back(owner);
+            self.engines.insert(
+                owner,
+                PreparedScript {
+                    source: script.to_string(),
+                    engine,
+                },
+            );
+        }
+        let engine = &mut self
+            .engines
+            .get_mut(&owner)
+            .expect("prepared script was just inserted")
+            .engine;
+        engine
+            .set_global_json("state", state)
+            .map_err(|e| format!("Failed to set state: {}", e))?;
+        f(engine)
+    }
+}
+
+thread_local! {
+    // Never dropped: boa's garbage collector is itself thread-local and may
+    // already be gone when the thread exits (see `clear_prepared_scripts`)
+    static PREPARED_SCRIPTS: ManuallyDrop<RefCell<PreparedScripts>> = ManuallyDrop::default();
+}
+
+/// Drops the engines [`with_prepared_scripts`] prepared on this thread.
+///
+/// Short-lived threads that run scripts through it must call this before they
+/// finish, or their engines leak.
+pub fn clear_prepared_scripts() {
+    PREPARED_SCRIPTS.with(|prepared| prepared.borrow_mut().clear());
+}
+
+/// Runs `f` on the engines prepared on this thread, for scripts run outside a
+/// simulation engine.
+pub fn with_prepared_scripts<R>(f: impl FnOnce(&mut PreparedScripts) -> R) -> R {
+    PREPARED_SCRIPTS.with(|prepared| f(&mut prepared.borrow_mut()))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::simulation::{execute_transformer_script, execute_transformer_script_with_globals};
@@ -558,4 +684,33 @@ mod tests {
         // Original text should be preserved
         assert!(output["text"].is_string());
     }
+
+    #[test]
+    fn test_prepared_script_is_evaluated_once_and_reused() {
+        let script = r#"
+            var calls = 0;
+            function transform(input) {
+                calls += 1;
+                state.seen = (state.seen || 0) + 1;
+                return { calls: calls, seen: state.seen };
+            }
+        "#;
+        let mut node_a = serde_json::Map::new();
+        let mut node_b = serde_json::Map::new();
+        let input = Message::new(json!({}));
+        let first = execute_transformer_script_with_globals(script, &input, &mut node_a).unwrap();
+        let second = execute_transformer_script_with_globals(script, &input, &mut node_a).unwrap();
+        let other = execute_transformer_script_with_globals(script, &input, &mut node_b).unwrap();
+
+        // Top-level code ran once; `state` is still each node's own
+        assert_eq!(*first[0].data, json!({"calls": 1, "seen": 1}));
+        assert_eq!(*second[0].data, json!({"calls": 2, "seen": 2}));
+        assert_eq!(*other[0].data, json!({"calls": 3, "seen": 1}));
+
+        // A script that fails to evaluate is not kept
+        let broken = "function transform(input) {";
+        assert!(execute_transformer_script(broken, &input).is_err());
+        assert!(execute_transformer_script(broken, &input).is_err());
+        super::clear_prepared_scripts();
+    }
 }
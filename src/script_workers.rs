@@ -0,0 +1,174 @@
+//! Worker threads that run transformer scripts for a simulation engine.
+//!
+//! Each transformer is handed to one worker the first time one of its
+//! scripts runs and stays there for the rest of the run, so its prepared
+//! engine and the global scope its script builds up live on a single thread.
+//! Scripts of transformers on different workers run in parallel; those of one
+//! transformer run in the order they were sent. The workers live as long as
+//! the engine that started them.
+
+use crate::script_api::ScriptContext;
+use crate::script_engine::PreparedScripts;
+use crate::simulation::{run_transformer_script, ScriptOutcome};
+use crate::types::{Message, NodeId};
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+/// Messages for one transformer to run its script on, in order.
+pub(crate) struct ScriptJob {
+    /// The transformer
+    pub node_id: NodeId,
+    /// Its script
+    pub script: String,
+    /// Its `state` before the first message
+    pub globals: serde_json::Map<String, serde_json::Value>,
+    /// Each message with the context it runs in
+    pub messages: Vec<(Message, ScriptContext)>,
+}
+
+/// A [`ScriptJob`] that has run: the transformer's `state` afterwards and the
+/// outcome for each of its messages.
+pub(crate) struct FinishedJob {
+    /// Index of the job among those passed to [`ScriptWorkers::run`]
+    pub job: usize,
+    /// The transformer's `state` after the last message
+    pub globals: serde_json::Map<String, serde_json::Value>,
+    /// Outcome for each message, in order
+    pub outcomes: Vec<ScriptOutcome>,
+}
+
+/// What a worker is asked to do.
+enum Request {
+    /// Run a job and send the result back
+    Run {
+        job: usize,
+        script_job: ScriptJob,
+        reply: mpsc::Sender<FinishedJob>,
+    },
+    /// Drop every prepared engine
+    Clear,
+}
+
+/// One worker thread and the channel it takes requests from.
+struct Worker {
+    sender: mpsc::Sender<Request>,
+    handle: JoinHandle<()>,
+}
+
+impl Worker {
+    fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel::<Request>();
+        let handle = std::thread::spawn(move || {
+            let mut prepared = PreparedScripts::default();
+            while let Ok(request) = receiver.recv() {
+                match request {
+                    Request::Run { job, script_job, reply } => {
+                        let ScriptJob { node_id: _, script, mut globals, messages } = script_job;
+                        let outcomes = messages
+                            .iter()
+                            .map(|(message, context)| {
+                                run_transformer_script(&mut prepared, &script, message, &mut globals, context)
+                            })
+                            .collect();
+                        let _ = reply.send(FinishedJob { job, globals, outcomes });
+                    }
+                    Request::Clear => prepared.clear(),
+                }
+            }
+            // Dropped here, while the thread's garbage collector is still alive
+            drop(prepared);
+        });
+        Self { sender, handle }
+    }
+}
+
+/// A fixed set of worker threads and the transformers assigned to each.
+pub(crate) struct ScriptWorkers {
+    workers: Vec<Worker>,
+    /// Worker of each transformer that has run a script this run
+    assigned: HashMap<NodeId, usize>,
+}
+
+impl ScriptWorkers {
+    /// Starts `count` worker threads.
+    pub fn new(count: usize) -> Self {
+        Self {
+            workers: (0..count.max(1)).map(|_| Worker::spawn()).collect(),
+            assigned: HashMap::new(),
+        }
+    }
+
+    /// The worker of `node_id`, assigning the next one in turn to a
+    /// transformer that has none yet. A worker that died is replaced, losing
+    /// the engines it held.
+    fn worker_of(&mut self, node_id: NodeId) -> &Worker {
+        let next = self.assigned.len() % self.workers.len();
+        let index = *self.assigned.entry(node_id).or_insert(next);
+        if self.workers[index].handle.is_finished() {
+            self.workers[index] = Worker::spawn();
+        }
+        &self.workers[index]
+    }
+
+    /// Runs `jobs` on the workers of their transformers and waits for them.
+    ///
+    /// Returns the jobs that finished, in no particular order; a job whose
+    /// worker panicked is left out.
+    pub fn run(&mut self, jobs: Vec<ScriptJob>) -> Vec<FinishedJob> {
+        let (reply, results) = mpsc::channel();
+        for (job, script_job) in jobs.into_iter().enumerate() {
+            let worker = self.worker_of(script_job.node_id);
+            let _ = worker.sender.send(Request::Run {
+                job,
+                script_job,
+                reply: reply.clone(),
+            });
+        }
+        drop(reply);
+        results.into_iter().collect()
+    }
+
+    /// Runs a single message through its transformer's worker.
+    pub fn run_one(
+        &mut self,
+        script: &str,
+        message: &Message,
+        globals: &mut serde_json::Map<String, serde_json::Value>,
+        context: &ScriptContext,
+    ) -> ScriptOutcome {
+        let job = ScriptJob {
+            node_id: context.node_id,
+            script: script.to_string(),
+            globals: globals.clone(),
+            messages: vec![(message.clone(), context.clone())],
+        };
+        let finished = self.run(vec![job]).pop();
+        match finished {
+            Some(FinishedJob { globals: after, mut outcomes, .. }) if outcomes.len() == 1 => {
+                *globals = after;
+                outcomes.remove(0)
+            }
+            _ => Err("The script worker stopped unexpectedly".to_string()),
+        }
+    }
+
+    /// Drops every prepared engine and forgets which worker each transformer
+    /// had, e.g. when the simulation is reset.
+    pub fn clear(&mut self) {
+        for worker in &self.workers {
+            let _ = worker.sender.send(Request::Clear);
+        }
+        self.assigned.clear();
+    }
+}
+
+impl Drop for ScriptWorkers {
+    fn drop(&mut self) {
+        // Closing the channels ends the workers' loops
+        let handles: Vec<JoinHandle<()>> = self.workers.drain(..).map(|worker| worker.handle).collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Shapes of the messages seen on a connection.
+//!
+//! During a run every delivered payload is merged into a [`ValueShape`] for
+//! its connection: the JSON types each field had, and whether every object
+//! carried it. Larger models pass data through many nodes, and the shape
+//! shows what a connection carries without reading individual messages.
+//!
+//! Inference is bounded so long runs stay cheap: nesting deeper than
+//! `MESSAGE_SHAPE_MAX_DEPTH` only records the type, objects contribute at
+//! most `MESSAGE_SHAPE_MAX_FIELDS` fields, and only the first
+//! `MESSAGE_SHAPE_MAX_ITEMS` elements of each array are looked at.
+
+use crate::constants::{MESSAGE_SHAPE_MAX_DEPTH, MESSAGE_SHAPE_MAX_FIELDS, MESSAGE_SHAPE_MAX_ITEMS};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The type of a JSON value, with integers told apart from other numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum JsonType {
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Boolean,
+    /// A number without a fractional part
+    Integer,
+    /// Any other number
+    Number,
+    /// A string
+    String,
+    /// An array
+    Array,
+    /// An object
+    Object,
+}
+
+impl JsonType {
+    /// The type of `value`.
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => JsonType::Null,
+            Value::Bool(_) => JsonType::Boolean,
+            Value::Number(n) if n.is_i64() || n.is_u64() => JsonType::Integer,
+            Value::Number(_) => JsonType::Number,
+            Value::String(_) => JsonType::String,
+            Value::Array(_) => JsonType::Array,
+            Value::Object(_) => JsonType::Object,
+        }
+    }
+
+    /// Name shown in the connection properties.
+    pub fn label(self) -> &'static str {
+        match self {
+            JsonType::Null => "null",
+            JsonType::Boolean => "boolean",
+            JsonType::Integer => "integer",
+            JsonType::Number => "number",
+            JsonType::String => "string",
+            JsonType::Array => "array",
+            JsonType::Object => "object",
+        }
+    }
+}
+
+/// What the values merged into it had in common.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValueShape {
+    seen: u64,
+    types: BTreeSet<JsonType>,
+    /// How many of the values were objects
+    objects: u64,
+    /// Fields of those objects; a field's `seen` counts the objects that had it
+    fields: BTreeMap<String, ValueShape>,
+    /// Whether some fields were skipped for exceeding `MESSAGE_SHAPE_MAX_FIELDS`
+    truncated: bool,
+    /// Shape of the elements of the values that were arrays
+    items: Option<Box<ValueShape>>,
+}
+
+/// One row of a shape as listed in the connection properties.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShapeLine {
+    /// Nesting level, 0 for fields of the message itself
+    pub depth: usize,
+    /// Field name, `[]` for array elements, `…` for skipped fields
+    pub name: String,
+    /// Whether some objects didn't carry the field
+    pub optional: bool,
+    /// The types seen, such as `string | null`
+    pub types: String,
+}
+
+impl ValueShape {
+    /// Merges `value` into the shape.
+    pub fn observe(&mut self, value: &Value) {
+        self.observe_at(value, 0);
+    }
+
+    fn observe_at(&mut self, value: &Value, depth: usize) {
+        self.seen += 1;
+        self.types.insert(JsonType::of(value));
+        if depth >= MESSAGE_SHAPE_MAX_DEPTH {
+            return;
+        }
+        match value {
+            Value::Object(map) => {
+                self.objects += 1;
+                for (name, field) in map {
+                    if !self.fields.contains_key(name) && self.fields.len() >= MESSAGE_SHAPE_MAX_FIELDS {
+                        self.truncated = true;
+                        continue;
+                    }
+                    self.fields.entry(name.clone()).or_default().observe_at(field, depth + 1);
+                }
+            }
+            Value::Array(items) => {
+                let shape = self.items.get_or_insert_with(Default::default);
+                for item in items.iter().take(MESSAGE_SHAPE_MAX_ITEMS) {
+                    shape.observe_at(item, depth + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Number of values merged in.
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// The types seen, joined with `|`. Integers are folded into numbers
+    /// when both were seen.
+    pub fn type_label(&self) -> String {
+        let both_numbers = self.types.contains(&JsonType::Integer) && self.types.contains(&JsonType::Number);
+        self.types
+            .iter()
+            .filter(|t| !(both_numbers && **t == JsonType::Integer))
+            .map(|t| t.label())
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// The fields and array elements below this shape, depth first, fields
+    /// in name order.
+    pub fn describe(&self) -> Vec<ShapeLine> {
+        let mut lines = Vec::new();
+        self.describe_into(0, &mut lines);
+        lines
+    }
+
+    fn describe_into(&self, depth: usize, lines: &mut Vec<ShapeLine>) {
+        for (name, field) in &self.fields {
+            lines.push(ShapeLine {
+                depth,
+                name: name.clone(),
+                optional: field.seen < self.objects,
+                types: field.type_label(),
+            });
+            field.describe_into(depth + 1, lines);
+        }
+        if self.truncated {
+            lines.push(ShapeLine {
+                depth,
+                name: "…".to_string(),
+                optional: true,
+                types: "more fields not tracked".to_string(),
+            });
+        }
+        if let Some(items) = self.items.as_deref().filter(|items| items.seen > 0) {
+            lines.push(ShapeLine {
+                depth,
+                name: "[]".to_string(),
+                optional: false,
+                types: items.type_label(),
+            });
+            items.describe_into(depth + 1, lines);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn shape_of(values: &[Value]) -> ValueShape {
+        let mut shape = ValueShape::default();
+        for value in values {
+            shape.observe(value);
+        }
+        shape
+    }
+
+    #[test]
+    fn fields_missing_from_some_messages_are_optional() {
+        let shape = shape_of(&[
+            json!({"id": 1, "user": {"name": "a"}, "note": null}),
+            json!({"id": 2.5, "user": {"name": "b", "age": 30}}),
+        ]);
+        assert_eq!(shape.seen(), 2);
+        assert_eq!(shape.type_label(), "object");
+        let described = shape.describe();
+        let lines: Vec<(usize, &str, bool, &str)> = described
+            .iter()
+            .map(|l| (l.depth, l.name.as_str(), l.optional, l.types.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (0, "id", false, "number"),
+                (0, "note", true, "null"),
+                (0, "user", false, "object"),
+                (1, "age", true, "integer"),
+                (1, "name", false, "string"),
+            ]
+        );
+    }
+
+    #[test]
+    fn array_items_and_limits() {
+        let shape = shape_of(&[json!([{"k": "x"}, 3]), json!(7)]);
+        assert_eq!(shape.type_label(), "integer | array");
+        let lines = shape.describe();
+        assert_eq!(lines[0].name, "[]");
+        assert_eq!(lines[0].types, "integer | object");
+        assert_eq!((lines[1].depth, lines[1].name.as_str(), lines[1].optional), (1, "k", false));
+
+        let wide: serde_json::Map<String, Value> =
+            (0..MESSAGE_SHAPE_MAX_FIELDS + 5).map(|i| (format!("f{:03}", i), json!(i))).collect();
+        let shape = shape_of(&[Value::Object(wide)]);
+        let lines = shape.describe();
+        assert_eq!(lines.len(), MESSAGE_SHAPE_MAX_FIELDS + 1);
+        assert_eq!(lines.last().unwrap().name, "…");
+    }
+}
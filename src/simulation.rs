@@ -4,20 +4,119 @@
 //! generation, consumption, transformation via Lua scripts, and message routing
 //! between nodes.
 
-use crate::script_engine::{create_script_engine, JavaScriptEngine};
+use crate::constants::{ERROR_LOG_LIMIT, PAYLOAD_PRUNE_INTERVAL_STEPS, TRACE_LOG_LIMIT};
+use crate::datagen::SplitMix64;
+use crate::events::{EventKind, EventLog, SimError, SimEvent};
+use crate::inspect::{json_path_condition_holds, MessageHistory};
+use crate::metrics::{RunStats, StepSample};
+use crate::payloads::PayloadPool;
+use crate::scheduler::{Agenda, Wakeup};
+use crate::script_api::ScriptContext;
+use crate::shapes::ValueShape;
+use crate::script_engine::{create_script_engine, with_prepared_scripts, JavaScriptEngine, PreparedScripts};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::script_workers::{ScriptJob, ScriptWorkers};
 use crate::types::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
+
+/// Global function a JavaScript accept filter is compiled into.
+const ACCEPT_FILTER_FUNCTION: &str = "__acceptFilter";
+
+/// Wraps a JavaScript accept filter expression in a function taking `msg`.
+fn accept_filter_script(expression: &str) -> String {
+    // The newline keeps a trailing `//` comment from swallowing the closing paren
+    format!(
+        "globalThis.{} = function (msg) {{ return !!({}\n); }};",
+        ACCEPT_FILTER_FUNCTION, expression
+    )
+}
+
+/// Checks that a consumer accept filter can be used.
+///
+/// Filters starting with `$` are JSONPath conditions; anything else is a
+/// JavaScript expression over the message payload `msg`.
+///
+/// # Returns
+///
+/// `Ok(())` for an empty or valid filter, or a description of the problem.
+pub fn validate_accept_filter(filter: &str) -> Result<(), String> {
+    let filter = filter.trim();
+    if filter.is_empty() {
+        return Ok(());
+    }
+    if filter.starts_with('$') {
+        return json_path_condition_holds(filter, &serde_json::Value::Null).map(|_| ());
+    }
+    create_script_engine()?.execute_script(&accept_filter_script(filter))
+}
+
+/// One recorded stop of a traced message on its way through the flowchart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceHop {
+    /// Trace the hop belongs to
+    pub trace_id: TraceId,
+    /// Node that produced or received the message
+    pub node_id: NodeId,
+    /// Node the message arrived from, or `None` where it was produced
+    pub from: Option<NodeId>,
+    /// Simulation step on which the hop happened
+    pub step: u64,
+}
 
 /// Engine responsible for running flowchart simulations.
 ///
-/// The simulation engine processes nodes in sequence, handles message flow,
-/// and executes transformation scripts.
+/// The simulation engine handles message flow and executes transformation
+/// scripts. Each step only visits the connections, producers and nodes whose
+/// work is due on it, as kept in its [`Agenda`].
 #[derive(Serialize, Deserialize)]
 pub struct SimulationEngine {
-    /// The JavaScript runtime environment for script execution
+    /// JavaScript runtime used to evaluate consumer accept filters
     #[serde(skip)]
-    #[allow(dead_code)]
     script_engine: Option<JavaScriptEngine>,
+    /// Transformer scripts evaluated during the current run, one engine per transformer
+    #[serde(skip)]
+    scripts: PreparedScripts,
+    /// Threads the transformer scripts run on instead, once started
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    script_workers: Option<ScriptWorkers>,
+    /// Number of script threads to start; below two, scripts run on the calling thread
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    script_threads: usize,
+    /// Most recent trace hops, oldest first, capped at `TRACE_LOG_LIMIT`
+    #[serde(skip)]
+    trace_log: VecDeque<TraceHop>,
+    /// Messages delivered to each node, shown in the node history inspector
+    #[serde(skip)]
+    history: MessageHistory,
+    /// Per-step samples for the run statistics dashboard
+    #[serde(skip)]
+    stats: RunStats,
+    /// Recent simulation events for the events panel
+    #[serde(skip)]
+    events: EventLog,
+    /// Most recent script failures, oldest first, capped at `ERROR_LOG_LIMIT`
+    #[serde(skip)]
+    errors: VecDeque<SimError>,
+    /// Payloads shared between identical messages
+    #[serde(skip)]
+    payloads: PayloadPool,
+    /// Draws random node failures; reseeded from the run settings when a run starts
+    #[serde(skip)]
+    failure_rng: SplitMix64,
+    /// Shape of the messages delivered over each connection, by source and destination
+    #[serde(skip)]
+    shapes: HashMap<(NodeId, NodeId), ValueShape>,
+    /// Messages delivered to each node since the run started; unlike the
+    /// statistics samples these cover every step
+    #[serde(skip)]
+    delivered: HashMap<NodeId, u64>,
+    /// Work due on coming steps
+    #[serde(skip)]
+    agenda: Agenda,
 }
 
 impl Default for SimulationEngine {
@@ -28,17 +127,421 @@ impl Default for SimulationEngine {
 
 impl SimulationEngine {
     /// Creates a new simulation engine with a fresh Lua environment.
+    ///
+    /// On native builds transformer scripts run on one worker thread per core.
     pub fn new() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            Self::with_script_threads(std::thread::available_parallelism().map_or(1, |n| n.get()))
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            Self::create()
+        }
+    }
+
+    /// Creates a new simulation engine running transformer scripts on
+    /// `threads` worker threads, or on the calling thread for fewer than two.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_script_threads(threads: usize) -> Self {
+        Self {
+            script_threads: threads,
+            ..Self::create()
+        }
+    }
+
+    fn create() -> Self {
         let script_engine = create_script_engine().ok();
-        Self { script_engine }
+        Self {
+            script_engine,
+            scripts: PreparedScripts::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            script_workers: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            script_threads: 1,
+            trace_log: VecDeque::new(),
+            history: MessageHistory::default(),
+            stats: RunStats::default(),
+            events: EventLog::default(),
+            errors: VecDeque::new(),
+            payloads: PayloadPool::default(),
+            failure_rng: SplitMix64::default(),
+            shapes: HashMap::new(),
+            delivered: HashMap::new(),
+            agenda: Agenda::default(),
+        }
+    }
+
+    /// Messages delivered to each node so far.
+    pub fn history(&self) -> &MessageHistory {
+        &self.history
+    }
+
+    /// Mutable access to the node history, e.g. to change its sampling.
+    pub fn history_mut(&mut self) -> &mut MessageHistory {
+        &mut self.history
+    }
+
+    /// Statistics of the current run.
+    pub fn stats(&self) -> &RunStats {
+        &self.stats
+    }
+
+    /// The per-step statistics of the current run as CSV (see [`RunStats::to_csv`]).
+    pub fn steps_csv(&self, flowchart: &Flowchart) -> String {
+        self.stats.to_csv(flowchart)
+    }
+
+    /// Drops the transformers' prepared engines, so their scripts start from
+    /// a fresh global scope. A run starting from step 0 does this itself.
+    pub fn clear_scripts(&mut self) {
+        self.scripts.clear();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(workers) = &mut self.script_workers {
+            workers.clear();
+        }
+    }
+
+    /// The script worker threads, started with the first script if there are to be any.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn script_workers(&mut self) -> Option<&mut ScriptWorkers> {
+        if self.script_workers.is_none() && self.script_threads >= 2 {
+            self.script_workers = Some(ScriptWorkers::new(self.script_threads));
+        }
+        self.script_workers.as_mut()
+    }
+
+    /// Runs a transformer's `script` on `message`, on the transformer's worker
+    /// thread if scripts run on workers.
+    fn run_script(
+        &mut self,
+        script: &str,
+        message: &Message,
+        globals: &mut serde_json::Map<String, serde_json::Value>,
+        context: &ScriptContext,
+    ) -> ScriptOutcome {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(workers) = self.script_workers() {
+            return workers.run_one(script, message, globals, context);
+        }
+        run_transformer_script(&mut self.scripts, script, message, globals, context)
+    }
+
+    /// Runs the scripts for the `delivered` messages ahead of their delivery,
+    /// each transformer's on its worker thread, and stores each transformer's
+    /// resulting globals.
+    ///
+    /// Returns the outcome for each delivered message index that was run.
+    /// Nothing runs when scripts don't run on workers, e.g. on the web build,
+    /// which has no threads; those scripts run when the message is delivered.
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    fn run_transformer_scripts(
+        &mut self,
+        delivered: &[(NodeId, usize, Message)],
+        flowchart: &mut Flowchart,
+        params: &Arc<serde_json::Value>,
+    ) -> HashMap<usize, ScriptOutcome> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            HashMap::new()
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if self.script_threads < 2 {
+                return HashMap::new();
+            }
+            // Delivered messages are handled after the step that moved them
+            let step = flowchart.current_step.saturating_sub(1);
+            let mut jobs: Vec<ScriptJob> = Vec::new();
+            // Index among the delivered messages of each message of each job
+            let mut indices: Vec<Vec<usize>> = Vec::new();
+            let mut job_of: HashMap<NodeId, usize> = HashMap::new();
+            for (index, (node_id, _, message)) in delivered.iter().enumerate() {
+                let Some(node) = flowchart.nodes.get(node_id) else {
+                    continue;
+                };
+                let NodeType::Transformer { script, globals, .. } = &node.node_type else {
+                    continue;
+                };
+                let job = *job_of.entry(*node_id).or_insert_with(|| {
+                    jobs.push(ScriptJob {
+                        node_id: *node_id,
+                        script: script.clone(),
+                        globals: globals.clone(),
+                        messages: Vec::new(),
+                    });
+                    indices.push(Vec::new());
+                    jobs.len() - 1
+                });
+                let context = ScriptContext {
+                    node_id: *node_id,
+                    node: node.name.clone(),
+                    step,
+                    attempt: message.attempts + 1,
+                    params: Arc::clone(params),
+                };
+                jobs[job].messages.push((message.clone(), context));
+                indices[job].push(index);
+            }
+            if jobs.is_empty() {
+                return HashMap::new();
+            }
+            let node_ids: Vec<NodeId> = jobs.iter().map(|job| job.node_id).collect();
+            let Some(workers) = self.script_workers() else {
+                return HashMap::new();
+            };
+
+            // A job whose worker panicked leaves its messages to run on delivery
+            let mut outcomes = HashMap::new();
+            for finished in workers.run(jobs) {
+                if let Some(NodeType::Transformer { globals, .. }) =
+                    flowchart.nodes.get_mut(&node_ids[finished.job]).map(|node| &mut node.node_type)
+                {
+                    *globals = finished.globals;
+                }
+                outcomes.extend(indices[finished.job].iter().copied().zip(finished.outcomes));
+            }
+            outcomes
+        }
+    }
+
+    /// Mutable access to the run statistics, e.g. to clear them on reset.
+    pub fn stats_mut(&mut self) -> &mut RunStats {
+        &mut self.stats
+    }
+
+    /// Recent simulation events.
+    pub fn events(&self) -> &EventLog {
+        &self.events
+    }
+
+    /// Mutable access to the event log, e.g. to resize or clear it.
+    pub fn events_mut(&mut self) -> &mut EventLog {
+        &mut self.events
+    }
+
+    /// Script failures of the run, oldest first.
+    ///
+    /// Only the most recent `ERROR_LOG_LIMIT` failures are kept.
+    pub fn errors(&self) -> &VecDeque<SimError> {
+        &self.errors
+    }
+
+    /// Forgets all recorded script failures.
+    pub fn clear_errors(&mut self) {
+        self.errors.clear();
+    }
+
+    /// Shape of the messages delivered from `from` to `to` since the run
+    /// started, or `None` if none were.
+    pub fn connection_shape(&self, from: NodeId, to: NodeId) -> Option<&ValueShape> {
+        self.shapes.get(&(from, to))
+    }
+
+    /// Forgets the message shapes of all connections.
+    pub fn clear_shapes(&mut self) {
+        self.shapes.clear();
+    }
+
+    /// Messages delivered to `node` since the run started.
+    pub fn delivered_to(&self, node: NodeId) -> u64 {
+        self.delivered.get(&node).copied().unwrap_or(0)
+    }
+
+    /// Payloads shared between identical messages, for the memory statistics.
+    pub fn payloads(&self) -> &PayloadPool {
+        &self.payloads
+    }
+
+    /// Appends an event to the event log.
+    fn log_event(
+        &mut self,
+        step: u64,
+        kind: EventKind,
+        node: NodeId,
+        from: Option<NodeId>,
+        trace_id: Option<TraceId>,
+        detail: impl Into<String>,
+    ) {
+        self.events.push(SimEvent {
+            step,
+            kind,
+            node,
+            from,
+            trace_id,
+            detail: detail.into(),
+        });
+    }
+
+    /// Appends a failure to the error log, dropping the oldest beyond `ERROR_LOG_LIMIT`.
+    fn record_error(&mut self, error: SimError) {
+        if self.errors.len() >= ERROR_LOG_LIMIT {
+            self.errors.pop_front();
+        }
+        self.errors.push_back(error);
+    }
+
+    /// Returns the recorded hops of a trace in the order they happened.
+    ///
+    /// Only the most recent `TRACE_LOG_LIMIT` hops across all traces are kept,
+    /// so the start of long-running traces may be missing.
+    pub fn trace_hops(&self, trace_id: TraceId) -> Vec<TraceHop> {
+        let mut hops: Vec<TraceHop> = Vec::new();
+        for hop in self.trace_log.iter().filter(|h| h.trace_id == trace_id) {
+            // Parallel connections deliver the same message twice
+            if !hops.contains(hop) {
+                hops.push(*hop);
+            }
+        }
+        hops
+    }
+
+    /// Forgets all recorded trace hops, e.g. when the simulation is reset.
+    pub fn clear_traces(&mut self) {
+        self.trace_log.clear();
+    }
+
+    /// Returns whether a consumer's accept filter lets `message` through.
+    ///
+    /// Invalid filters, and expressions that throw, match nothing.
+    fn accepts(&mut self, filter: &str, message: &Message) -> bool {
+        let filter = filter.trim();
+        if filter.starts_with('$') {
+            return json_path_condition_holds(filter, &message.data).unwrap_or(false);
+        }
+        if self.script_engine.is_none() {
+            self.script_engine = create_script_engine().ok();
+        }
+        let Some(engine) = self.script_engine.as_mut() else {
+            return false;
+        };
+        let result = engine
+            .execute_script(&accept_filter_script(filter))
+            .and_then(|_| engine.call_function(ACCEPT_FILTER_FUNCTION, (*message.data).clone()));
+        matches!(result, Ok(serde_json::Value::Bool(true)))
+    }
+
+    /// Appends a hop for `message` if it carries a trace ID.
+    fn record_hop(&mut self, message: &Message, node_id: NodeId, from: Option<NodeId>, step: u64) {
+        let Some(trace_id) = message.trace_id else {
+            return;
+        };
+        if self.trace_log.len() >= TRACE_LOG_LIMIT {
+            self.trace_log.pop_front();
+        }
+        self.trace_log.push_back(TraceHop {
+            trace_id,
+            node_id,
+            from,
+            step,
+        });
+    }
+
+    /// Makes the next step look at the whole flowchart again.
+    ///
+    /// Call this after editing the flowchart between steps in a way that
+    /// changes when its nodes have work: producer schedules and timing, a
+    /// consumer's concurrency or messages in progress, or the run seed.
+    /// Adding or removing nodes, connections or messages in transit, and
+    /// resetting the run, are noticed without it.
+    pub fn reschedule(&mut self) {
+        self.agenda.invalidate();
+    }
+
+    /// Puts `message` on the connection at `index`, to be moved to its
+    /// destination by the next step like a message its source sent.
+    ///
+    /// A message without a creation step gets the current one, so its
+    /// latency counts from the injection.
+    pub fn inject_on_connection(
+        &mut self,
+        index: usize,
+        mut message: Message,
+        flowchart: &mut Flowchart,
+    ) -> Result<(), String> {
+        let step = flowchart.current_step;
+        let connection = flowchart
+            .connections
+            .get_mut(index)
+            .ok_or_else(|| format!("Connection {} not found", index))?;
+        message.created_step.get_or_insert(step);
+        self.payloads.intern(&mut message);
+        connection.messages.push(message);
+        self.agenda.note_sent(1);
+        self.agenda.wake(step, Wakeup::Deliver(index));
+        Ok(())
+    }
+
+    /// Hands `message` to a consumer or transformer right away, as if it had
+    /// just arrived: a consumer takes it, a transformer runs its script on it
+    /// and sends the results on. The node's accept filter, concurrency and
+    /// random failures don't apply; put the message on a connection with
+    /// [`SimulationEngine::inject_on_connection`] for those.
+    ///
+    /// # Returns
+    ///
+    /// An error if the node doesn't exist or take messages, or its script failed.
+    pub fn inject_into_node(
+        &mut self,
+        node_id: NodeId,
+        mut message: Message,
+        flowchart: &mut Flowchart,
+    ) -> Result<(), String> {
+        let node = flowchart
+            .nodes
+            .get(&node_id)
+            .ok_or_else(|| format!("Node {} not found", node_id))?;
+        let kind = match node.node_type {
+            NodeType::Consumer { .. } => EventKind::Consumed,
+            NodeType::Transformer { .. } => EventKind::Delivered,
+            NodeType::Producer { .. } | NodeType::Reference { .. } => {
+                return Err(format!("{} doesn't take messages", node.name));
+            }
+        };
+        let step = flowchart.current_step;
+        message.created_step.get_or_insert(step);
+        self.payloads.intern(&mut message);
+        self.log_event(step, kind, node_id, None, message.trace_id, "injected");
+        let params = Arc::new(flowchart.parameters.to_json());
+        self.deliver_with_outcome(node_id, None, message, flowchart, &params, None)
+    }
+
+    /// The messages waiting on the connections into `node_id`, connection by
+    /// connection, each in delivery order.
+    pub fn peek_messages<'a>(&self, node_id: NodeId, flowchart: &'a Flowchart) -> Vec<&'a Message> {
+        flowchart
+            .connections
+            .iter()
+            .filter(|connection| connection.to == node_id)
+            .flat_map(|connection| &connection.messages)
+            .collect()
+    }
+
+    /// Takes the messages waiting on the connections into `node_id` out of
+    /// the run, in the order of [`SimulationEngine::peek_messages`].
+    pub fn drain_messages(&mut self, node_id: NodeId, flowchart: &mut Flowchart) -> Vec<Message> {
+        let drained: Vec<Message> = flowchart
+            .connections
+            .iter_mut()
+            .filter(|connection| connection.to == node_id)
+            .flat_map(|connection| std::mem::take(&mut connection.messages))
+            .collect();
+        self.agenda.note_taken(drained.len());
+        drained
     }
 
     /// Executes a single simulation step on the given flowchart.
     ///
     /// This method:
-    /// 1. Moves messages along connections
-    /// 2. Delivers messages that have reached their destinations
-    /// 3. Processes each node according to its type
+    /// 1. Frees consumer slots whose processing time has elapsed
+    /// 2. Moves messages along connections, holding back messages for busy consumers
+    ///    and messages a consumer's accept filter rejects
+    /// 3. Fails messages at random on nodes with a failure probability
+    /// 4. Delivers messages that have reached their destinations
+    /// 5. Lets producers emit and settles the state of nodes that handled messages
+    ///
+    /// Only the connections and nodes with work due on this step are visited;
+    /// each visit schedules the next one (see [`crate::scheduler`]).
     ///
     /// # Arguments
     ///
@@ -46,67 +549,283 @@ impl SimulationEngine {
     ///
     /// # Returns
     ///
-    /// A vector of messages that were delivered during this step.
-    pub fn step(&mut self, flowchart: &mut Flowchart) -> Vec<(NodeId, Message)> {
+    /// The messages delivered during this step, each with its destination and
+    /// the index of the connection it arrived on.
+    pub fn step(&mut self, flowchart: &mut Flowchart) -> Vec<(NodeId, usize, Message)> {
         let mut delivered_messages = Vec::new();
 
-        // Collect all messages for delivery and clear connections
-        for connection in &mut flowchart.connections {
-            // All messages are delivered immediately in one step
+        // Catch up with edits the agenda didn't see, then take this step's work
+        self.agenda.sync(flowchart);
+        let current_step = flowchart.current_step;
+        let due = self.agenda.take_due(current_step);
+
+        // Advance consumers that are still busy with earlier messages
+        let was_busy: Vec<NodeId> = self.agenda.busy().iter().copied().collect();
+        for node_id in &was_busy {
+            let busy = match flowchart.nodes.get_mut(node_id).map(|node| &mut node.node_type) {
+                Some(NodeType::Consumer { in_flight, .. }) => {
+                    for remaining in in_flight.iter_mut() {
+                        *remaining = remaining.saturating_sub(1);
+                    }
+                    in_flight.retain(|remaining| *remaining > 0);
+                    !in_flight.is_empty()
+                }
+                _ => false,
+            };
+            self.agenda.set_busy(*node_id, busy);
+        }
+
+        // Collect messages for delivery and clear connections
+        let mut sample = StepSample {
+            step: current_step,
+            ..Default::default()
+        };
+        if current_step == 0 {
+            self.failure_rng = SplitMix64(flowchart.run_config.seed);
+            self.shapes.clear();
+            self.delivered.clear();
+            self.clear_scripts();
+        }
+        for &index in &due.connections {
+            let Some(connection) = flowchart.connections.get_mut(index) else {
+                continue;
+            };
+            let to_consumer = matches!(
+                flowchart.nodes.get(&connection.to).map(|node| &node.node_type),
+                Some(NodeType::Consumer { .. })
+            );
+            // Filtered consumers leave non-matching messages on the connection
+            let accept_filter = match flowchart.nodes.get(&connection.to).map(|node| &node.node_type) {
+                Some(NodeType::Consumer { accept_filter, .. }) if !accept_filter.trim().is_empty() => {
+                    Some(accept_filter.clone())
+                }
+                _ => None,
+            };
+            // Transformers and consumers may fail messages at random
+            let (failure, retry) = match flowchart.nodes.get(&connection.to) {
+                Some(node) if !node.failure.is_off() => match &node.node_type {
+                    NodeType::Consumer { .. } => (Some(node.failure), RetryPolicy::default()),
+                    NodeType::Transformer { retry, .. } => (Some(node.failure), *retry),
+                    _ => (None, RetryPolicy::default()),
+                },
+                _ => (None, RetryPolicy::default()),
+            };
+
+            // Consumers with a processing time only accept messages into free
+            // slots; the rest wait on the connection until a slot frees up
+            let consumer_slots = match flowchart
+                .nodes
+                .get_mut(&connection.to)
+                .map(|node| &mut node.node_type)
+            {
+                Some(NodeType::Consumer {
+                    processing_time,
+                    max_concurrency,
+                    in_flight,
+                    ..
+                }) if *processing_time > 0 => Some((*processing_time, *max_concurrency, in_flight)),
+                _ => None,
+            };
+            let mut capacity = match &consumer_slots {
+                Some((_, 0, _)) | None => usize::MAX,
+                Some((_, limit, in_flight)) => (*limit as usize).saturating_sub(in_flight.len()),
+            };
+
+            // Highest priority first; the stable sort keeps arrival order among equals
+            connection
+                .messages
+                .sort_by_key(|message| std::cmp::Reverse(message.priority));
+
+            // Messages waiting for a retry stay put until their backoff has elapsed
+            let mut waiting = Vec::new();
+            let mut accepted = 0;
             for message in connection.messages.drain(..) {
-                delivered_messages.push((connection.to, message));
+                let due = message.retry_at.is_none_or(|step| step <= current_step);
+                if due
+                    && capacity > 0
+                    && accept_filter.as_deref().is_none_or(|filter| self.accepts(filter, &message))
+                {
+                    capacity -= 1;
+                    self.record_hop(&message, connection.to, Some(connection.from), current_step);
+                    if let Some(failure) = failure.filter(|f| self.failure_rng.next_f64() < f.probability) {
+                        let (to, from) = (connection.to, connection.from);
+                        if failure.mode == FailureMode::Drop {
+                            self.log_event(current_step, EventKind::Dropped, to, Some(from), message.trace_id, "random drop");
+                            continue;
+                        }
+                        sample.errors += 1;
+                        let attempts = message.attempts + 1;
+                        let retrying = attempts < retry.max_attempts;
+                        let detail = if retrying {
+                            format!("random failure (attempt {}/{}, retrying)", attempts, retry.max_attempts)
+                        } else {
+                            "random failure".to_string()
+                        };
+                        self.log_event(current_step, EventKind::Error, to, Some(from), message.trace_id, detail.clone());
+                        self.record_error(SimError {
+                            step: current_step,
+                            node: to,
+                            message: detail,
+                            payload: (*message.data).clone(),
+                            trace_id: message.trace_id,
+                        });
+                        if retrying {
+                            let mut retried = message;
+                            retried.attempts = attempts;
+                            retried.retry_at = Some(current_step + 1 + u64::from(retry.backoff_steps));
+                            waiting.push(retried);
+                        } else {
+                            self.log_event(current_step, EventKind::Dropped, to, Some(from), message.trace_id, "random failure");
+                        }
+                        continue;
+                    }
+                    accepted += 1;
+                    self.history
+                        .record(connection.to, connection.from, current_step, &message);
+                    self.shapes
+                        .entry((connection.from, connection.to))
+                        .or_default()
+                        .observe(&message.data);
+                    sample.delivered += 1;
+                    let kind = if to_consumer { EventKind::Consumed } else { EventKind::Delivered };
+                    self.log_event(current_step, kind, connection.to, Some(connection.from), message.trace_id, "");
+                    if to_consumer {
+                        sample.consumed += 1;
+                        if let Some(created) = message.created_step {
+                            sample.latencies.push((connection.to, current_step.saturating_sub(created)));
+                        }
+                    }
+                    *sample.deliveries.entry(connection.to).or_default() += 1;
+                    *self.delivered.entry(connection.to).or_default() += 1;
+                    delivered_messages.push((connection.to, index, message));
+                } else {
+                    waiting.push(message);
+                }
+            }
+            connection.messages = waiting;
+
+            // A full consumer takes more once its earliest message is done
+            let mut slot_free = current_step + 1;
+            if let Some((processing_time, limit, in_flight)) = consumer_slots {
+                in_flight.extend(std::iter::repeat_n(processing_time, accepted));
+                if !in_flight.is_empty() {
+                    self.agenda.set_busy(connection.to, true);
+                }
+                if limit > 0 && in_flight.len() >= limit as usize {
+                    slot_free = current_step + u64::from(in_flight.iter().copied().min().unwrap_or(1));
+                }
+            }
+            // Come back when a waiting message may go through
+            if let Some(ready) = connection
+                .messages
+                .iter()
+                .map(|message| message.retry_at.unwrap_or(0).max(current_step + 1))
+                .min()
+            {
+                self.agenda.wake(ready.max(slot_free), Wakeup::Deliver(index));
             }
         }
 
-        // Process producer nodes and generate messages
-        // Collect node IDs first to avoid borrow conflicts
-        let node_ids: Vec<_> = flowchart.nodes.keys().cloned().collect();
-        let current_step = flowchart.current_step;
+        // Deliver across connections in priority order as well
+        delivered_messages.sort_by_key(|(_, _, message)| std::cmp::Reverse(message.priority));
 
-        for node_id in node_ids {
+        // Producers whose next message may be due
+        for node_id in due.producers {
             if let Some(node) = flowchart.nodes.get_mut(&node_id) {
-                match node.node_type.clone() {
-                    NodeType::Producer {
-                        message_template,
+                if let NodeType::Producer {
+                    message_template,
+                    start_step,
+                    messages_per_cycle,
+                    steps_between_cycles,
+                    messages_produced: _,
+                } = node.node_type.clone()
+                {
+                    let message_template = if flowchart.parameters.values.is_empty() {
+                        message_template
+                    } else {
+                        crate::parameters::substitute(&message_template, &flowchart.parameters.values)
+                    };
+                    let mut generated_messages = self.process_producer_node(
+                        node,
+                        &message_template,
                         start_step,
                         messages_per_cycle,
                         steps_between_cycles,
-                        messages_produced: _,
-                    } => {
-                        let generated_messages = self.process_producer_node(
-                            node,
-                            &message_template,
-                            start_step,
-                            messages_per_cycle,
-                            steps_between_cycles,
-                            current_step,
-                        );
+                        current_step,
+                        flowchart.run_config.seed,
+                    );
+                    if let Some(next) = next_production_step(node, current_step, flowchart.run_config.seed) {
+                        self.agenda.wake(next, Wakeup::Produce(node_id));
+                    }
 
-                        // Add generated messages to all outgoing connections
-                        if !generated_messages.is_empty() {
-                            for connection in &mut flowchart.connections {
-                                if connection.from == node_id {
-                                    for message in &generated_messages {
-                                        connection.messages.push(message.clone());
-                                    }
+                    for message in &mut generated_messages {
+                        self.payloads.intern(message);
+                    }
+                    let has_outputs = flowchart.connections.iter().any(|c| c.from == node_id);
+                    for message in &generated_messages {
+                        self.record_hop(message, node_id, None, current_step);
+                        self.log_event(current_step, EventKind::Produced, node_id, None, message.trace_id, "");
+                        if !has_outputs {
+                            self.log_event(
+                                current_step,
+                                EventKind::Dropped,
+                                node_id,
+                                None,
+                                message.trace_id,
+                                "no outgoing connections",
+                            );
+                        }
+                    }
+                    sample.produced += generated_messages.len() as u64;
+
+                    // Add generated messages to all outgoing connections
+                    if !generated_messages.is_empty() {
+                        for (index, connection) in flowchart.connections.iter_mut().enumerate() {
+                            if connection.from == node_id {
+                                for message in &generated_messages {
+                                    connection.messages.push(message.clone());
                                 }
+                                self.agenda.wake(current_step + 1, Wakeup::Deliver(index));
                             }
                         }
                     }
-                    NodeType::Consumer {
-                        consumption_rate: _,
-                    } => {
-                        self.process_consumer_node(node);
-                    }
-                    NodeType::Transformer { script, .. } => {
-                        self.process_transformer_node(node, &script);
-                    }
                 }
             }
         }
 
+        // Consumers and transformers that handled messages, or are still
+        // busy, settle; references are placeholders and do not simulate
+        let settle: BTreeSet<NodeId> = due
+            .settle
+            .into_iter()
+            .chain(was_busy)
+            .chain(self.agenda.busy().iter().copied())
+            .collect();
+        for node_id in settle {
+            if let Some(node) = flowchart.nodes.get_mut(&node_id) {
+                if matches!(node.node_type, NodeType::Consumer { .. }) {
+                    self.process_consumer_node(node);
+                } else if matches!(node.node_type, NodeType::Transformer { .. }) {
+                    self.process_transformer_node(node);
+                }
+            }
+        }
+
+        sample.in_transit = flowchart.connections.iter().map(|c| c.messages.len() as u64).sum();
+        sample.connections = flowchart
+            .connections
+            .iter()
+            .filter(|c| !c.messages.is_empty())
+            .map(|c| (c.from, c.to, c.messages.len() as u64))
+            .collect();
+        self.stats.push(sample);
+        if current_step.is_multiple_of(PAYLOAD_PRUNE_INTERVAL_STEPS) {
+            self.payloads.prune();
+        }
+
         // Increment step counter
         flowchart.current_step += 1;
+        self.agenda.mark_synced(flowchart);
 
         delivered_messages
     }
@@ -121,10 +840,12 @@ impl SimulationEngine {
     /// * `messages_per_cycle` - Total number of messages to generate (not per cycle, but in total)
     /// * `steps_between_cycles` - Number of steps to wait between production cycles
     /// * `current_step` - The current simulation step
+    /// * `seed` - Seed of the run, which the node's jitter is drawn from
     ///
     /// # Returns
     ///
     /// A vector of messages that were generated during this step
+    #[allow(clippy::too_many_arguments)]
     fn process_producer_node(
         &self,
         node: &mut FlowchartNode,
@@ -133,6 +854,7 @@ impl SimulationEngine {
         messages_per_cycle: u32,
         steps_between_cycles: u32,
         current_step: u64,
+        seed: u64,
     ) -> Vec<Message> {
         let mut generated_messages = Vec::new();
 
@@ -158,16 +880,17 @@ impl SimulationEngine {
             return generated_messages;
         }
 
-        let steps_since_start = current_step - start_step;
-
-        // Check if this is a production cycle step
-        let should_produce = if steps_between_cycles == 0 {
-            // Produce every step after start_step
-            true
-        } else {
-            // Produce on start_step, then every steps_between_cycles steps
-            steps_since_start.is_multiple_of(steps_between_cycles as u64)
-        };
+        // The next message is due on its scheduled step; one that is late
+        // because an earlier one was delayed goes out as soon as possible
+        let should_produce = producer_due_step(
+            start_step,
+            steps_between_cycles,
+            &node.timing,
+            seed,
+            node.id,
+            u64::from(messages_produced),
+        )
+        .is_some_and(|due| current_step >= due);
 
         if should_produce {
             node.state = NodeState::Processing;
@@ -177,7 +900,12 @@ impl SimulationEngine {
             let to_generate = remaining.min(1); // Generate 1 message per cycle
 
             for _ in 0..to_generate {
-                let message = Message::new(message_template.clone());
+                let mut message = Message::new(message_template.clone());
+                message.trace_id = Some(uuid::Uuid::new_v4());
+                message.created_step = Some(current_step);
+                if let Some(priority) = take_priority(Arc::make_mut(&mut message.data)) {
+                    message.priority = priority;
+                }
                 generated_messages.push(message);
             }
 
@@ -202,8 +930,13 @@ impl SimulationEngine {
     ///
     /// * `node` - The consumer node to process
     fn process_consumer_node(&self, node: &mut FlowchartNode) {
-        // TODO: Implement message consumption logic
-        node.state = NodeState::Idle;
+        // A consumer stays busy while messages are still being processed
+        let busy = matches!(&node.node_type, NodeType::Consumer { in_flight, .. } if !in_flight.is_empty());
+        node.state = if busy {
+            NodeState::Processing
+        } else {
+            NodeState::Idle
+        };
     }
 
     /// Processes a transformer node by potentially executing its script.
@@ -211,8 +944,7 @@ impl SimulationEngine {
     /// # Arguments
     ///
     /// * `node` - The transformer node to process
-    /// * `script` - The JavaScript script to execute
-    fn process_transformer_node(&self, node: &mut FlowchartNode, _script: &String) {
+    fn process_transformer_node(&self, node: &mut FlowchartNode) {
         // TODO: Implement script execution for transformation
         node.state = NodeState::Idle;
     }
@@ -224,6 +956,11 @@ impl SimulationEngine {
     /// - Transformers execute JavaScript and forward the result
     /// - Producers ignore incoming messages
     ///
+    /// If a transformer script throws and the node's `RetryPolicy` allows more
+    /// attempts, the message is put back on the node's first incoming
+    /// connection with its attempt count increased and held there for the
+    /// backoff period.
+    ///
     /// # Arguments
     ///
     /// * `node_id` - The ID of the destination node
@@ -238,29 +975,155 @@ impl SimulationEngine {
         node_id: NodeId,
         message: Message,
         flowchart: &mut Flowchart,
+    ) -> Result<(), String> {
+        let params = Arc::new(flowchart.parameters.to_json());
+        self.deliver_with_outcome(node_id, None, message, flowchart, &params, None)
+    }
+
+    /// Delivers every message returned by [`SimulationEngine::step`], in order.
+    ///
+    /// On native builds the scripts of different transformers run in parallel
+    /// on worker threads; each transformer keeps to one thread and sees its
+    /// own messages one after another, so its `state` and script globals
+    /// evolve as they would sequentially. The results are then applied in
+    /// delivery order, giving the same outcome as calling
+    /// [`SimulationEngine::deliver_message`] for each message, except that a
+    /// message to retry goes back on the connection it arrived on.
+    ///
+    /// # Returns
+    ///
+    /// The destination and error of each message whose script failed.
+    pub fn deliver_messages(
+        &mut self,
+        delivered: Vec<(NodeId, usize, Message)>,
+        flowchart: &mut Flowchart,
+    ) -> Vec<(NodeId, String)> {
+        // One copy of the parameters for every script run of the step
+        let params = Arc::new(flowchart.parameters.to_json());
+        let mut outcomes = self.run_transformer_scripts(&delivered, flowchart, &params);
+        let mut errors = Vec::new();
+        for (index, (node_id, source, message)) in delivered.into_iter().enumerate() {
+            let outcome = outcomes.remove(&index);
+            if let Err(error) = self.deliver_with_outcome(node_id, Some(source), message, flowchart, &params, outcome) {
+                errors.push((node_id, error));
+            }
+        }
+        errors
+    }
+
+    /// Puts `receipt` on every outgoing connection of the tapped consumer `node_id`.
+    fn send_receipt(&mut self, node_id: NodeId, mut receipt: Message, flowchart: &mut Flowchart) {
+        self.payloads.intern(&mut receipt);
+        for (index, connection) in flowchart.connections.iter_mut().enumerate() {
+            if connection.from == node_id {
+                connection.messages.push(receipt.clone());
+                self.agenda.note_sent(1);
+                self.agenda.wake(flowchart.current_step, Wakeup::Deliver(index));
+            }
+        }
+    }
+
+    /// Delivers a message, using `outcome` as the result of a transformer's
+    /// script if it was already run; otherwise the script runs here.
+    ///
+    /// A message to retry goes back on the connection at `source`, or on the
+    /// node's first incoming connection if that isn't known. Scripts see
+    /// `params` as `context.params`.
+    fn deliver_with_outcome(
+        &mut self,
+        node_id: NodeId,
+        source: Option<usize>,
+        message: Message,
+        flowchart: &mut Flowchart,
+        params: &Arc<serde_json::Value>,
+        outcome: Option<ScriptOutcome>,
     ) -> Result<(), String> {
         if let Some(node) = flowchart.nodes.get_mut(&node_id) {
             match &mut node.node_type {
                 NodeType::Consumer { .. } => {
-                    // Message is consumed and destroyed
+                    // Message is consumed and destroyed; a tap sends a receipt on in its place
                     node.state = NodeState::Processing;
+                    self.agenda.wake(flowchart.current_step, Wakeup::Settle(node_id));
+                    if node.tap {
+                        let step = flowchart.current_step.saturating_sub(1);
+                        let receipt = consumption_receipt(&node.name, &message, step);
+                        self.send_receipt(node_id, receipt, flowchart);
+                    }
                     Ok(())
                 }
                 NodeType::Transformer {
                     script,
                     selected_outputs: _,
                     globals,
+                    retry,
                     ..
                 } => {
                     // Execute JavaScript to transform the message
                     node.state = NodeState::Processing;
+                    self.agenda.wake(flowchart.current_step, Wakeup::Settle(node_id));
                     let script = script.clone();
+                    let retry = *retry;
+                    // Delivered messages are handled after the step that moved them
+                    let step = flowchart.current_step.saturating_sub(1);
+                    let context = ScriptContext {
+                        node_id,
+                        node: node.name.clone(),
+                        step,
+                        attempt: message.attempts + 1,
+                        params: Arc::clone(params),
+                    };
 
                     // Execute the transformation script
                     let mut transformed_messages =
-                        match execute_transformer_script_with_globals(&script, &message, globals) {
+                        match outcome.unwrap_or_else(|| self.run_script(&script, &message, globals, &context)) {
                             Ok(msgs) => msgs,
                             Err(err) => {
+                                if let Some(sample) = self.stats.last_mut() {
+                                    sample.errors += 1;
+                                }
+                                let attempts = message.attempts + 1;
+                                let incoming = if attempts < retry.max_attempts {
+                                    source
+                                        .filter(|&index| flowchart.connections.get(index).is_some_and(|c| c.to == node_id))
+                                        .or_else(|| flowchart.connections.iter().position(|c| c.to == node_id))
+                                } else {
+                                    None
+                                };
+                                let retrying = incoming.is_some();
+                                let detail = if retrying {
+                                    format!("{} (attempt {}/{}, retrying)", err, attempts, retry.max_attempts)
+                                } else {
+                                    err.clone()
+                                };
+                                self.log_event(step, EventKind::Error, node_id, None, message.trace_id, detail.clone());
+                                self.record_error(SimError {
+                                    step,
+                                    node: node_id,
+                                    message: detail,
+                                    payload: (*message.data).clone(),
+                                    trace_id: message.trace_id,
+                                });
+                                if !retrying {
+                                    self.log_event(step, EventKind::Dropped, node_id, None, message.trace_id, "script failed");
+                                }
+                                if let Some(index) = incoming {
+                                    // Re-queue the message on its way in and try again after the backoff
+                                    let mut retried = message;
+                                    retried.attempts = attempts;
+                                    let retry_at = flowchart.current_step + u64::from(retry.backoff_steps);
+                                    retried.retry_at = Some(retry_at);
+                                    flowchart.connections[index].messages.insert(0, retried);
+                                    self.agenda.note_sent(1);
+                                    self.agenda.wake(retry_at, Wakeup::Deliver(index));
+                                    if let Some(n) = flowchart.nodes.get_mut(&node_id) {
+                                        n.state = NodeState::Error(format!(
+                                            "{} (attempt {}/{}, retrying)",
+                                            err, attempts, retry.max_attempts
+                                        ));
+                                    }
+                                    return Ok(());
+                                }
+
                                 // Record error state on the node and propagate the error
                                 if let Some(n) = flowchart.nodes.get_mut(&node_id) {
                                     n.state = NodeState::Error(err.clone());
@@ -269,12 +1132,30 @@ impl SimulationEngine {
                             }
                         };
 
+                    if transformed_messages.is_empty() {
+                        self.log_event(
+                            step,
+                            EventKind::Dropped,
+                            node_id,
+                            None,
+                            message.trace_id,
+                            "script returned no messages",
+                        );
+                    }
+
                     // Programmatic routing: each output message may include a special "__targets" field
                     // which is an array of destination node names. If absent or null, broadcast to all.
                     for mut transformed_message in transformed_messages.drain(..) {
+                        // Outputs keep the input's priority unless the script sets "__priority"
+                        transformed_message.priority = take_priority(Arc::make_mut(&mut transformed_message.data))
+                            .unwrap_or(message.priority);
+                        // Fan-out copies all stay on the input's trace and keep its creation step
+                        transformed_message.trace_id = message.trace_id;
+                        transformed_message.created_step = message.created_step;
+
                         // Extract routing targets from message control field and strip it from payload
                         let mut routing_targets: Option<Vec<String>> = None; // None = broadcast
-                        if let serde_json::Value::Object(ref mut map) = transformed_message.data {
+                        if let serde_json::Value::Object(map) = Arc::make_mut(&mut transformed_message.data) {
                             if map.contains_key("__targets") {
                                 let raw =
                                     map.remove("__targets").unwrap_or(serde_json::Value::Null);
@@ -299,7 +1180,9 @@ impl SimulationEngine {
                             }
                         }
 
-                        for connection in flowchart.connections.iter_mut() {
+                        self.payloads.intern(&mut transformed_message);
+                        let mut sent = false;
+                        for (index, connection) in flowchart.connections.iter_mut().enumerate() {
                             if connection.from != node_id {
                                 continue;
                             }
@@ -315,14 +1198,25 @@ impl SimulationEngine {
                             };
                             if send {
                                 connection.messages.push(transformed_message.clone());
+                                self.agenda.note_sent(1);
+                                self.agenda.wake(flowchart.current_step, Wakeup::Deliver(index));
+                                sent = true;
                             }
                         }
+                        if !sent {
+                            let reason = if routing_targets.is_some() {
+                                "no connection to the requested targets"
+                            } else {
+                                "no outgoing connections"
+                            };
+                            self.log_event(step, EventKind::Dropped, node_id, None, message.trace_id, reason);
+                        }
                     }
 
                     Ok(())
                 }
-                NodeType::Producer { .. } => {
-                    // Producers don't accept incoming messages
+                NodeType::Producer { .. } | NodeType::Reference { .. } => {
+                    // Producers and references don't accept incoming messages
                     Ok(())
                 }
             }
@@ -332,66 +1226,100 @@ impl SimulationEngine {
     }
 }
 
+/// The message a tapped consumer sends on after taking `message` on `step`.
+///
+/// The receipt names the consumer, the step, the consumed message's id and
+/// how many steps it took to arrive, but not its payload. It stays on the
+/// consumed message's trace and keeps its priority.
+pub fn consumption_receipt(consumer: &str, message: &Message, step: u64) -> Message {
+    let mut receipt = Message::new(serde_json::json!({
+        "consumer": consumer,
+        "step": step,
+        "message": message.id.to_string(),
+        "latency": message.created_step.map(|created| step.saturating_sub(created)),
+    }));
+    receipt.trace_id = message.trace_id;
+    receipt.priority = message.priority;
+    receipt.created_step = Some(step);
+    receipt
+}
+
+/// Removes the `__priority` control field from a message payload.
+///
+/// # Returns
+///
+/// The requested priority, or `None` if the payload does not set one or the
+/// value is not an integer.
+fn take_priority(data: &mut serde_json::Value) -> Option<i32> {
+    let serde_json::Value::Object(map) = data else {
+        return None;
+    };
+    let raw = map.remove("__priority")?;
+    raw.as_i64()
+        .map(|p| p.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+}
+
 /// Executes a JavaScript transformation script on an input message.
 ///
 /// This function uses the cross-platform script engine to execute JavaScript code,
-/// providing the input message and returning the transformed messages.
+/// providing the input message and returning the transformed messages. The
+/// script is evaluated once per thread (see
+/// [`crate::script_engine::with_prepared_scripts`]); each message then only
+/// calls its `transform` function, with `globals` exposed as `state`.
 ///
 /// # Arguments
 ///
 /// * `script` - The JavaScript script code to execute
 /// * `input_message` - The message to transform
+/// * `globals` - The node's persistent state, updated with the script's changes
 ///
 /// # Returns
 ///
 /// A vector of output messages or an error string if script execution fails.
-///
-/// # Example JavaScript Script
-///
-/// ```javascript
-/// // Access input message via global 'input'
-/// const new_data = {
-///     original: input.data,
-///     transformed: true,
-///     timestamp: Date.now()
-/// };
-///
-/// // Return transformed message(s)
-/// new_data;
-/// ```
 pub fn execute_transformer_script_with_globals(
     script: &str,
     input_message: &Message,
     globals: &mut serde_json::Map<String, serde_json::Value>,
 ) -> Result<Vec<Message>, String> {
-    let mut script_engine =
-        create_script_engine().map_err(|e| format!("Failed to create script engine: {}", e))?;
+    execute_transformer_script_in_context(script, input_message, globals, &ScriptContext::default())
+}
 
-    // Provide per-node persistent globals as `globalThis.state`
+/// [`execute_transformer_script_with_globals`] passing `context` as the
+/// second argument of `transform` (see [`crate::script_api`]).
+pub fn execute_transformer_script_in_context(
+    script: &str,
+    input_message: &Message,
+    globals: &mut serde_json::Map<String, serde_json::Value>,
+    context: &ScriptContext,
+) -> Result<Vec<Message>, String> {
+    with_prepared_scripts(|prepared| run_transformer_script(prepared, script, input_message, globals, context))
+}
+
+/// Runs `script` on the engine `prepared` keeps for the transformer of
+/// `context`, evaluating the script there first if needed.
+///
+/// The transformer's `globals` are its `state`, top-level code included, and
+/// are updated with the script's changes.
+pub(crate) fn run_transformer_script(
+    prepared: &mut PreparedScripts,
+    script: &str,
+    input_message: &Message,
+    globals: &mut serde_json::Map<String, serde_json::Value>,
+    context: &ScriptContext,
+) -> Result<Vec<Message>, String> {
     let state_value = serde_json::Value::Object(globals.clone());
-    script_engine
-        .set_global_json("state", &state_value)
-        .map_err(|e| format!("Failed to set state: {}", e))?;
-
-    // Create input JSON for the script
-    let input_json = serde_json::json!(input_message.data);
-
-    // Execute the script to define the transform function
-    script_engine
-        .execute_script(script)
-        .map_err(|e| format!("Failed to execute script: {}", e))?;
-
-    // Call the transform function with the input
-    let result = script_engine
-        .call_function("transform", input_json)
-        .map_err(|e| format!("Failed to call transform function: {}", e))?;
-
-    // Read back potentially mutated state and persist it for the node
-    if let Ok(new_state) = script_engine.get_global_json("state") {
-        if let serde_json::Value::Object(obj) = new_state {
+    let result = prepared.run(context.node_id, script, &state_value, |script_engine| {
+        // Call the transform function with the input
+        let result = script_engine
+            .call_function_with_args("transform", &[(*input_message.data).clone(), context.to_json()])
+            .map_err(|e| format!("Failed to call transform function: {}", e))?;
+
+        // Read back potentially mutated state and persist it for the node
+        if let Ok(serde_json::Value::Object(obj)) = script_engine.get_global_json("state") {
             *globals = obj;
         }
-    }
+        Ok(result)
+    })?;
 
     // Interpret the script result:
     // - null => no output messages
@@ -402,32 +1330,537 @@ pub fn execute_transformer_script_with_globals(
         serde_json::Value::Array(arr) => {
             let msgs = arr
                 .into_iter()
-                .map(|elem| Message {
-                    id: uuid::Uuid::new_v4(),
-                    data: elem,
-                })
+                .map(Message::new)
                 .collect();
             Ok(msgs)
         }
-        other => Ok(vec![Message {
-            id: uuid::Uuid::new_v4(),
-            data: other,
-        }]),
+        other => Ok(vec![Message::new(other)]),
+    }
+}
+
+// Helper functions removed - now handled by the script engine
+
+/// Output messages of a transformer script, or why it failed.
+pub(crate) type ScriptOutcome = Result<Vec<Message>, String>;
+
+/// Step after `step` on which a producer that was just processed has to be
+/// looked at again: the next one while it shows as producing, so it can go
+/// back to idle, otherwise the one its next message is due on.
+fn next_production_step(node: &FlowchartNode, step: u64, seed: u64) -> Option<u64> {
+    let NodeType::Producer {
+        start_step,
+        messages_per_cycle,
+        steps_between_cycles,
+        messages_produced,
+        ..
+    } = &node.node_type
+    else {
+        return None;
+    };
+    if node.state != NodeState::Idle {
+        return Some(step + 1);
+    }
+    if messages_produced >= messages_per_cycle {
+        return None;
+    }
+    producer_due_step(*start_step, *steps_between_cycles, &node.timing, seed, node.id, u64::from(*messages_produced))
+        .map(|due| due.max(step + 1))
+}
+
+/// Step on which message number `emission` of a producer is due: `start_step`
+/// plus the producer's phase, `emission` cycles of `steps_between_cycles` (one
+/// step when it is zero) and the emission's random delay.
+///
+/// Returns `None` if the step doesn't fit in a `u64`.
+fn producer_due_step(
+    start_step: u64,
+    steps_between_cycles: u32,
+    timing: &ProducerTiming,
+    seed: u64,
+    node: NodeId,
+    emission: u64,
+) -> Option<u64> {
+    let interval = u64::from(steps_between_cycles.max(1));
+    emission
+        .checked_mul(interval)
+        .and_then(|d| start_step.checked_add(d))
+        .and_then(|s| s.checked_add(u64::from(timing.phase)))
+        .and_then(|s| s.checked_add(timing.delay(seed, node, emission)))
+}
+
+/// Computes the steps on which a producer emits messages, without running a simulation.
+///
+/// This mirrors the schedule used by [`SimulationEngine::step`]: one message on
+/// `start_step` and then one every `steps_between_cycles` steps (every step when
+/// it is zero) until `messages_per_cycle` messages have been produced, each
+/// shifted by the producer's [`ProducerTiming`]. A producer emits at most one
+/// message per step, so a message due no later than the previous one goes out
+/// on the step after it.
+///
+/// # Arguments
+///
+/// * `start_step` - First step on which the producer may emit
+/// * `messages_per_cycle` - Total number of messages the producer emits
+/// * `steps_between_cycles` - Gap between emissions in steps
+/// * `timing` - Phase and jitter of the producer
+/// * `seed` - Seed of the run, which the jitter is drawn from
+/// * `node` - The producer, whose id the jitter is drawn from
+/// * `limit` - Maximum number of steps to return
+///
+/// # Returns
+///
+/// The emission steps in ascending order, truncated to `limit` entries.
+pub fn producer_emission_steps(
+    start_step: u64,
+    messages_per_cycle: u32,
+    steps_between_cycles: u32,
+    timing: &ProducerTiming,
+    seed: u64,
+    node: NodeId,
+    limit: usize,
+) -> Vec<u64> {
+    let mut previous: Option<u64> = None;
+    (0..u64::from(messages_per_cycle))
+        .take(limit)
+        .map_while(|k| {
+            let due = producer_due_step(start_step, steps_between_cycles, timing, seed, node, k)?;
+            let step = match previous {
+                Some(p) => due.max(p.checked_add(1)?),
+                None => due,
+            };
+            previous = Some(step);
+            Some(step)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_simulation_engine_creation() {
+        let engine = SimulationEngine::new();
+        // Engine should be created successfully
+        assert!(engine.script_engine.is_some()); // Basic creation test
+    }
+
+    #[test]
+    fn test_producer_emission_steps_match_simulation() {
+        for (start, total, between) in [(0, 3, 0), (2, 4, 3), (5, 1, 10), (1, 0, 2)] {
+            let mut engine = SimulationEngine::new();
+            let mut flowchart = Flowchart::new();
+            let producer = flowchart.add_node(FlowchartNode::new(
+                "P".into(),
+                (0.0, 0.0),
+                NodeType::Producer {
+                    message_template: json!({}),
+                    start_step: start,
+                    messages_per_cycle: total,
+                    steps_between_cycles: between,
+                    messages_produced: 0,
+                },
+            ));
+            let sink = flowchart.add_node(FlowchartNode::new(
+                "C".into(),
+                (0.0, 0.0),
+                NodeType::Consumer {
+                    consumption_rate: 1,
+                    processing_time: 0,
+                    max_concurrency: 1,
+                    in_flight: Vec::new(),
+                    accept_filter: String::new(),
+                },
+            ));
+            flowchart.add_connection(producer, sink).unwrap();
+
+            let mut observed = Vec::new();
+            for _ in 0..40 {
+                let step = flowchart.current_step;
+                engine.step(&mut flowchart);
+                if !flowchart.connections[0].messages.is_empty() {
+                    observed.push(step);
+                }
+            }
+            let off = ProducerTiming::default();
+            assert_eq!(producer_emission_steps(start, total, between, &off, 0, producer, 100), observed);
+        }
+        let off = ProducerTiming::default();
+        assert_eq!(producer_emission_steps(0, 1000, 1, &off, 0, uuid::Uuid::nil(), 3), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_producer_phase_and_jitter_are_seeded() {
+        let run = |seed: u64, timing: ProducerTiming| {
+            let mut engine = SimulationEngine::new();
+            let mut flowchart = Flowchart::new();
+            flowchart.run_config.seed = seed;
+            let mut node = FlowchartNode::new(
+                "P".into(),
+                (0.0, 0.0),
+                NodeType::Producer {
+                    message_template: json!({}),
+                    start_step: 2,
+                    messages_per_cycle: 8,
+                    steps_between_cycles: 5,
+                    messages_produced: 0,
+                },
+            );
+            node.timing = timing;
+            let producer = flowchart.add_node(node);
+            let sink = flowchart.add_node(FlowchartNode::new(
+                "C".into(),
+                (0.0, 0.0),
+                NodeType::Consumer {
+                    consumption_rate: 1,
+                    processing_time: 0,
+                    max_concurrency: 1,
+                    in_flight: Vec::new(),
+                    accept_filter: String::new(),
+                },
+            ));
+            flowchart.add_connection(producer, sink).unwrap();
+
+            let mut observed = Vec::new();
+            for _ in 0..80 {
+                let step = flowchart.current_step;
+                engine.step(&mut flowchart);
+                if !flowchart.connections[0].messages.is_empty() {
+                    observed.push(step);
+                }
+            }
+            let predicted = producer_emission_steps(2, 8, 5, &timing, seed, producer, 100);
+            assert_eq!(predicted, observed);
+            observed
+        };
+
+        let phased = run(1, ProducerTiming { phase: 3, jitter: 0 });
+        assert_eq!(phased, vec![5, 10, 15, 20, 25, 30, 35, 40]);
+
+        let jittered = ProducerTiming { phase: 1, jitter: 4 };
+        let steps = run(7, jittered);
+        assert_eq!(steps.len(), 8);
+        assert!(steps.windows(2).all(|w| w[0] < w[1]));
+        for (k, step) in steps.iter().enumerate() {
+            let planned = 3 + 5 * k as u64;
+            assert!((planned..=planned + 4).contains(step), "{:?}", steps);
+        }
+        assert_ne!(steps, run(1, ProducerTiming { phase: 1, jitter: 0 }));
+    }
+
+    #[test]
+    fn test_consumer_processing_time_holds_excess_messages_upstream() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 3,
+                steps_between_cycles: 0,
+                messages_produced: 0,
+            },
+        ));
+        let consumer = flowchart.add_node(FlowchartNode::new(
+            "C".into(),
+            (0.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 2,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.add_connection(producer, consumer).unwrap();
+
+        // Steps 0-2 emit one message each; the consumer accepts one every 2 steps
+        let delivered: Vec<usize> = (0..8).map(|_| engine.step(&mut flowchart).len()).collect();
+        assert_eq!(delivered, vec![0, 1, 0, 1, 0, 1, 0, 0]);
+        assert!(flowchart.connections[0].messages.is_empty());
+
+        // While waiting, excess messages stay on the connection
+        let mut flowchart_waiting = flowchart.clone();
+        if let Some(node) = flowchart_waiting.nodes.get_mut(&consumer) {
+            if let NodeType::Consumer { in_flight, .. } = &mut node.node_type {
+                in_flight.push(5);
+            }
+        }
+        flowchart_waiting.connections[0].messages.push(Message::new(json!({})));
+        assert!(engine.step(&mut flowchart_waiting).is_empty());
+        assert_eq!(flowchart_waiting.connections[0].messages.len(), 1);
+        assert_eq!(flowchart_waiting.nodes[&consumer].state, NodeState::Processing);
+    }
+
+    #[test]
+    fn test_consumer_accept_filter_leaves_other_messages_waiting() {
+        for filter in ["$.kind == \"order\"", "msg.kind === 'order' // orders only"] {
+            let mut engine = SimulationEngine::new();
+            let mut flowchart = Flowchart::new();
+            let producer = flowchart.add_node(FlowchartNode::new(
+                "P".into(),
+                (0.0, 0.0),
+                NodeType::Producer {
+                    message_template: json!({}),
+                    start_step: 100,
+                    messages_per_cycle: 0,
+                    steps_between_cycles: 0,
+                    messages_produced: 0,
+                },
+            ));
+            let consumer = flowchart.add_node(FlowchartNode::new(
+                "C".into(),
+                (0.0, 0.0),
+                NodeType::Consumer {
+                    consumption_rate: 1,
+                    processing_time: 0,
+                    max_concurrency: 1,
+                    in_flight: Vec::new(),
+                    accept_filter: filter.to_string(),
+                },
+            ));
+            flowchart.add_connection(producer, consumer).unwrap();
+            for kind in ["order", "ping", "order"] {
+                flowchart.connections[0].messages.push(Message::new(json!({"kind": kind})));
+            }
+
+            let delivered = engine.step(&mut flowchart);
+            assert_eq!(delivered.len(), 2, "filter {}", filter);
+            assert!(delivered.iter().all(|(_, _, m)| m.data["kind"] == "order"));
+            assert_eq!(flowchart.connections[0].messages.len(), 1);
+            assert_eq!(flowchart.connections[0].messages[0].data["kind"], "ping");
+            assert!(validate_accept_filter(filter).is_ok());
+        }
+        assert!(validate_accept_filter("$.kind ==").is_err());
+        assert!(validate_accept_filter("msg.kind ===").is_err());
+        assert!(validate_accept_filter("").is_ok());
+    }
+
+    #[test]
+    fn test_consumer_latency_spans_transformers() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 3,
+                steps_between_cycles: 0,
+                messages_produced: 0,
+            },
+        ));
+        let transformer = flowchart.add_node(FlowchartNode::new(
+            "T".into(),
+            (0.0, 0.0),
+            NodeType::Transformer {
+                script: "function transform(input) { return input; }".into(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: Default::default(),
+            },
+        ));
+        let consumer = flowchart.add_node(FlowchartNode::new(
+            "C".into(),
+            (0.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.add_connection(producer, transformer).unwrap();
+        flowchart.add_connection(transformer, consumer).unwrap();
+        for _ in 0..6 {
+            for (node_id, _, message) in engine.step(&mut flowchart) {
+                engine.deliver_message(node_id, message, &mut flowchart).unwrap();
+            }
+        }
+
+        let summary = engine.stats().summarize(&RunConfig::default());
+        assert_eq!(summary.consumed, 3);
+        // One step to reach the transformer, one more to reach the consumer
+        assert_eq!(summary.latencies[&consumer], vec![2, 2, 2]);
+        assert!(!summary.latencies.contains_key(&transformer));
+    }
+
+    #[test]
+    fn tapped_consumers_send_receipts_instead_of_messages() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let consumer = |name: &str| {
+            FlowchartNode::new(
+                name.into(),
+                (100.0, 0.0),
+                NodeType::Consumer {
+                    consumption_rate: 1,
+                    processing_time: 0,
+                    max_concurrency: 0,
+                    in_flight: Vec::new(),
+                    accept_filter: String::new(),
+                },
+            )
+        };
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({"secret": 1}),
+                start_step: 0,
+                messages_per_cycle: 1,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        let mut tapped = consumer("Orders");
+        tapped.tap = true;
+        let tapped = flowchart.add_node(tapped);
+        let ledger = flowchart.add_node(consumer("Ledger"));
+        flowchart.add_connection(producer, tapped).unwrap();
+        flowchart.add_connection(tapped, ledger).unwrap();
+
+        let mut receipt = None;
+        for _ in 0..4 {
+            for (node_id, _, message) in engine.step(&mut flowchart) {
+                if node_id == ledger {
+                    receipt = Some(message.clone());
+                }
+                engine.deliver_message(node_id, message, &mut flowchart).unwrap();
+            }
+        }
+        let receipt = receipt.expect("the ledger got a receipt");
+        assert_eq!(*receipt.data, json!({"consumer": "Orders", "step": 1, "message": receipt.data["message"], "latency": 1}));
+        let consumed: Vec<_> = engine.events().iter().filter(|e| e.kind == EventKind::Consumed).collect();
+        assert_eq!(consumed.len(), 2);
+        assert_eq!(consumed[1].trace_id, consumed[0].trace_id);
     }
-}
 
-// Helper functions removed - now handled by the script engine
+    #[test]
+    fn steps_csv_counts_the_messages_left_on_each_connection() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 3,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        let sink = flowchart.add_node(FlowchartNode::new(
+            "Sink".into(),
+            (100.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 0,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.add_connection(producer, sink).unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
+        for _ in 0..5 {
+            for (node_id, _, message) in engine.step(&mut flowchart) {
+                engine.deliver_message(node_id, message, &mut flowchart).unwrap();
+            }
+        }
+        let csv = engine.steps_csv(&flowchart);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "step,measured,produced,delivered,consumed,errors,in_transit,delivered:P,delivered:Sink,in_transit:P → Sink"
+        );
+        // One message waits on the connection each step until the producer's three are consumed
+        assert_eq!(lines[2], "1,true,1,1,1,0,1,0,1,1");
+        assert_eq!(lines[4], "3,true,0,1,1,0,0,0,1,0");
+    }
 
     #[test]
-    fn test_simulation_engine_creation() {
-        let engine = SimulationEngine::new();
-        // Engine should be created successfully
-        assert!(engine.script_engine.is_some()); // Basic creation test
+    fn test_events_log_records_message_lifecycle() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let transformer = |name: &str, script: &str| {
+            FlowchartNode::new(
+                name.into(),
+                (100.0, 0.0),
+                NodeType::Transformer {
+                    script: script.into(),
+                    selected_outputs: None,
+                    globals: Default::default(),
+                    initial_globals: Default::default(),
+                    retry: Default::default(),
+                },
+            )
+        };
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 1,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        let failing = flowchart.add_node(transformer("Failing", "function transform(input) { throw new Error(\"boom\"); }"));
+        let dead_end = flowchart.add_node(transformer("Dead end", "function transform(input) { return input; }"));
+        let consumer = flowchart.add_node(FlowchartNode::new(
+            "C".into(),
+            (200.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        for to in [failing, dead_end, consumer] {
+            flowchart.add_connection(producer, to).unwrap();
+        }
+        for _ in 0..2 {
+            for (node_id, _, message) in engine.step(&mut flowchart) {
+                let _ = engine.deliver_message(node_id, message, &mut flowchart);
+            }
+        }
+
+        let events: Vec<_> = engine.events().iter().collect();
+        let trace = events[0].trace_id;
+        assert!(trace.is_some());
+        assert!(events.iter().all(|e| e.trace_id == trace));
+        let count = |kind: EventKind, node: NodeId| events.iter().filter(|e| e.kind == kind && e.node == node).count();
+        assert_eq!(count(EventKind::Produced, producer), 1);
+        assert_eq!(count(EventKind::Delivered, failing), 1);
+        assert_eq!(count(EventKind::Delivered, dead_end), 1);
+        assert_eq!(count(EventKind::Consumed, consumer), 1);
+        assert_eq!(count(EventKind::Error, failing), 1);
+        assert_eq!(count(EventKind::Dropped, failing), 1);
+        assert_eq!(count(EventKind::Dropped, dead_end), 1);
+        let consumed = events.iter().find(|e| e.kind == EventKind::Consumed).unwrap();
+        assert_eq!((consumed.step, consumed.from), (1, Some(producer)));
+        let error = events.iter().find(|e| e.kind == EventKind::Error).unwrap();
+        assert!(error.detail.contains("boom"));
+        assert_eq!(error.step, 1);
+
+        // The failure is also kept with the payload that caused it
+        let errors: Vec<_> = engine.errors().iter().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].step, errors[0].node), (1, failing));
+        assert_eq!(errors[0].message, error.detail);
+        assert_eq!(errors[0].payload, json!({}));
+        assert_eq!(errors[0].trace_id, trace);
     }
 
     #[test]
@@ -529,9 +1962,9 @@ mod tests {
 
         // Now arrays are treated as multiple output messages
         assert_eq!(result.len(), 3);
-        assert_eq!(result[0].data, json!(1));
-        assert_eq!(result[1].data, json!(2));
-        assert_eq!(result[2].data, json!(3));
+        assert_eq!(*result[0].data, json!(1));
+        assert_eq!(*result[1].data, json!(2));
+        assert_eq!(*result[2].data, json!(3));
     }
 
     #[test]
@@ -549,7 +1982,7 @@ mod tests {
         assert_eq!(result.len(), 1);
         let msg = &result[0];
 
-        assert_eq!(msg.data, json!("hello world"));
+        assert_eq!(*msg.data, json!("hello world"));
     }
 
     #[test]
@@ -567,7 +2000,7 @@ mod tests {
         assert_eq!(result.len(), 1);
         let msg = &result[0];
 
-        assert_eq!(msg.data, json!(42));
+        assert_eq!(*msg.data, json!(42));
     }
 
     #[test]
@@ -626,6 +2059,691 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_failed_transformer_message_is_retried_after_backoff() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let source = flowchart.add_node(FlowchartNode::new(
+            "Source".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 0,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        let failing = flowchart.add_node(FlowchartNode::new(
+            "Failing".into(),
+            (100.0, 0.0),
+            NodeType::Transformer {
+                script: "function transform(input) { throw new Error(\"boom\"); }".into(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: RetryPolicy {
+                    max_attempts: 2,
+                    backoff_steps: 3,
+                },
+            },
+        ));
+        flowchart.add_connection(source, failing).unwrap();
+        flowchart.current_step = 5;
+
+        // First failure re-queues the message instead of erroring
+        let result = engine.deliver_message(failing, Message::new(json!({"n": 1})), &mut flowchart);
+        assert!(result.is_ok());
+        let queued = &flowchart.connections[0].messages;
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].attempts, 1);
+        assert_eq!(queued[0].retry_at, Some(8));
+        assert!(matches!(flowchart.nodes[&failing].state, NodeState::Error(_)));
+
+        // The message waits on the connection until the backoff has elapsed
+        for _ in 5..8 {
+            assert!(engine.step(&mut flowchart).is_empty());
+        }
+        let mut due = engine.step(&mut flowchart);
+        assert_eq!(due.len(), 1);
+
+        // The last allowed attempt fails for real
+        let (node_id, _, message) = due.remove(0);
+        assert!(engine.deliver_message(node_id, message, &mut flowchart).is_err());
+        assert!(flowchart.connections[0].messages.is_empty());
+    }
+
+    #[test]
+    fn test_script_retries_go_back_on_the_connection_they_arrived_on() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let producer = |name: &str| {
+            FlowchartNode::new(
+                name.into(),
+                (0.0, 0.0),
+                NodeType::Producer {
+                    message_template: json!({}),
+                    start_step: 0,
+                    messages_per_cycle: 0,
+                    steps_between_cycles: 1,
+                    messages_produced: 0,
+                },
+            )
+        };
+        let first = flowchart.add_node(producer("First"));
+        let second = flowchart.add_node(producer("Second"));
+        let failing = flowchart.add_node(FlowchartNode::new(
+            "Failing".into(),
+            (100.0, 0.0),
+            NodeType::Transformer {
+                script: "function transform(input) { throw new Error(\"boom\"); }".into(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: RetryPolicy {
+                    max_attempts: 2,
+                    backoff_steps: 1,
+                },
+            },
+        ));
+        flowchart.add_connection(first, failing).unwrap();
+        flowchart.add_connection(second, failing).unwrap();
+        flowchart.connections[1].messages.push(Message::new(json!({"n": 1})));
+
+        let delivered = engine.step(&mut flowchart);
+        assert_eq!(delivered.iter().map(|(_, source, _)| *source).collect::<Vec<_>>(), [1]);
+        assert!(engine.deliver_messages(delivered, &mut flowchart).is_empty());
+
+        // The retry waits on the second input, not the first
+        assert!(flowchart.connections[0].messages.is_empty());
+        let queued = &flowchart.connections[1].messages;
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].attempts, 1);
+    }
+
+    #[test]
+    fn test_random_failures_follow_the_run_seed() {
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 40,
+                steps_between_cycles: 0,
+                messages_produced: 0,
+            },
+        ));
+        let mut node = FlowchartNode::new(
+            "C".into(),
+            (0.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        );
+        node.failure = FailureSettings {
+            probability: 0.5,
+            mode: FailureMode::Drop,
+        };
+        let consumer = flowchart.add_node(node);
+        flowchart.add_connection(producer, consumer).unwrap();
+        flowchart.run_config.seed = 7;
+
+        let run = |flowchart: &Flowchart| {
+            let mut engine = SimulationEngine::new();
+            let mut flowchart = flowchart.clone();
+            (0..45).map(|_| engine.step(&mut flowchart).len()).collect::<Vec<_>>()
+        };
+        let first = run(&flowchart);
+        assert_eq!(first, run(&flowchart), "the same seed fails the same messages");
+        let consumed: usize = first.iter().sum();
+        assert!(consumed > 0 && consumed < 40, "about half are dropped, got {}", consumed);
+
+        flowchart.nodes.get_mut(&consumer).unwrap().failure.probability = 0.0;
+        assert_eq!(run(&flowchart).iter().sum::<usize>(), 40);
+        flowchart.nodes.get_mut(&consumer).unwrap().failure.probability = 1.0;
+        assert_eq!(run(&flowchart).iter().sum::<usize>(), 0);
+    }
+
+    #[test]
+    fn test_connection_shapes_are_inferred_per_run() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({"id": 1}),
+                start_step: 0,
+                messages_per_cycle: 2,
+                steps_between_cycles: 0,
+                messages_produced: 0,
+            },
+        ));
+        let transformer = flowchart.add_node(FlowchartNode::new(
+            "T".into(),
+            (100.0, 0.0),
+            NodeType::Transformer {
+                script: "function transform(input, context) { input.by = context.node; return input; }".into(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: RetryPolicy::default(),
+            },
+        ));
+        let consumer = flowchart.add_node(FlowchartNode::new(
+            "C".into(),
+            (200.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.add_connection(producer, transformer).unwrap();
+        flowchart.add_connection(transformer, consumer).unwrap();
+        for _ in 0..5 {
+            let delivered = engine.step(&mut flowchart);
+            assert!(engine.deliver_messages(delivered, &mut flowchart).is_empty());
+        }
+
+        let produced = engine.connection_shape(producer, transformer).unwrap();
+        assert_eq!(produced.seen(), 2);
+        assert_eq!(produced.describe().iter().map(|l| l.name.as_str()).collect::<Vec<_>>(), ["id"]);
+        let transformed = engine.connection_shape(transformer, consumer).unwrap().describe();
+        assert!(transformed.iter().any(|l| l.name == "by" && l.types == "string" && !l.optional));
+
+        // A new run starts from nothing
+        flowchart.reset_simulation();
+        engine.step(&mut flowchart);
+        assert!(engine.connection_shape(transformer, consumer).is_none());
+    }
+
+    #[test]
+    fn test_random_errors_are_retried_and_counted() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 1,
+                steps_between_cycles: 0,
+                messages_produced: 0,
+            },
+        ));
+        let mut node = FlowchartNode::new(
+            "T".into(),
+            (100.0, 0.0),
+            NodeType::Transformer {
+                script: "function transform(input) { return input; }".into(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: RetryPolicy {
+                    max_attempts: 2,
+                    backoff_steps: 2,
+                },
+            },
+        );
+        node.failure.probability = 1.0;
+        let transformer = flowchart.add_node(node);
+        flowchart.add_connection(producer, transformer).unwrap();
+
+        // Produced on step 0, fails on step 1 and waits out the backoff
+        for _ in 0..2 {
+            assert!(engine.step(&mut flowchart).is_empty());
+        }
+        assert_eq!(flowchart.connections[0].messages[0].retry_at, Some(4));
+
+        // The retry fails for good without stopping the run
+        for _ in 2..6 {
+            assert!(engine.step(&mut flowchart).is_empty());
+        }
+        assert!(flowchart.connections[0].messages.is_empty());
+        assert_eq!(engine.errors().len(), 2);
+        assert_eq!(engine.stats().summarize(&RunConfig::default()).errors, 2);
+        assert!(engine
+            .events()
+            .iter()
+            .any(|e| e.kind == EventKind::Dropped && e.detail == "random failure"));
+    }
+
+    #[test]
+    fn test_identical_produced_payloads_are_shared() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({"value": 1}),
+                start_step: 0,
+                messages_per_cycle: 3,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        // A filter nothing passes keeps the messages on the connection
+        let sink = flowchart.add_node(FlowchartNode::new(
+            "C".into(),
+            (100.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: "$.missing".into(),
+            },
+        ));
+        flowchart.add_connection(producer, sink).unwrap();
+        for _ in 0..3 {
+            engine.step(&mut flowchart);
+        }
+
+        let messages = &flowchart.connections[0].messages;
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().all(|m| Arc::ptr_eq(&m.data, &messages[0].data)));
+        let stats = engine.payloads().stats();
+        assert_eq!(stats.distinct, 1);
+        assert_eq!(stats.bytes_saved, 2 * r#"{"value":1}"#.len());
+    }
+
+    #[test]
+    fn transformers_with_the_same_script_keep_their_own_globals_until_reset() {
+        let mut flowchart = Flowchart::new();
+        let script = "var seen = state.offset; function transform(input) { seen += 1; return { seen: seen }; }";
+        let mut transformer = |name: &str, offset: u64| {
+            let initial_globals: serde_json::Map<String, serde_json::Value> =
+                [("offset".to_string(), json!(offset))].into_iter().collect();
+            let node = flowchart.add_node(FlowchartNode::new(
+                name.into(),
+                (0.0, 0.0),
+                NodeType::Transformer {
+                    script: script.into(),
+                    selected_outputs: None,
+                    globals: initial_globals.clone(),
+                    initial_globals,
+                    retry: Default::default(),
+                },
+            ));
+            let sink = flowchart.add_node(FlowchartNode::new(
+                format!("{} sink", name),
+                (0.0, 0.0),
+                NodeType::Consumer {
+                    consumption_rate: 1,
+                    processing_time: 0,
+                    max_concurrency: 0,
+                    in_flight: Vec::new(),
+                    accept_filter: String::new(),
+                },
+            ));
+            flowchart.add_connection(node, sink).unwrap();
+            node
+        };
+        let first = transformer("T1", 0);
+        let second = transformer("T2", 10);
+        let seen = |flowchart: &Flowchart, node: NodeId| -> Vec<serde_json::Value> {
+            let connection = flowchart.connections.iter().find(|c| c.from == node).unwrap();
+            connection.messages.iter().map(|m| m.data["seen"].clone()).collect()
+        };
+
+        let mut engine = SimulationEngine::new();
+        for node in [first, second, first, first] {
+            engine.deliver_message(node, Message::new(json!({})), &mut flowchart).unwrap();
+        }
+        // Top-level code saw each node's state, and the counters stay apart
+        assert_eq!(seen(&flowchart, first), [json!(1), json!(2), json!(3)]);
+        assert_eq!(seen(&flowchart, second), [json!(11)]);
+
+        flowchart.reset_simulation();
+        engine.clear_scripts();
+        engine.deliver_message(first, Message::new(json!({})), &mut flowchart).unwrap();
+        assert_eq!(seen(&flowchart, first), [json!(1)]);
+    }
+
+    #[test]
+    fn runs_on_worker_threads_match_runs_on_the_calling_thread() {
+        let mut flowchart = Flowchart::new();
+        // Top-level `calls` and `state.n` only agree while the engine is kept
+        let script = "var calls = 0; function transform(input) { calls += 1; state.n = (state.n || 0) + 1; \
+                      return { calls: calls, n: state.n, v: input.v }; }";
+        let mut pipeline = |name: &str, steps_between_cycles: u32| {
+            let producer = flowchart.add_node(FlowchartNode::new(
+                format!("{} source", name),
+                (0.0, 0.0),
+                NodeType::Producer {
+                    message_template: json!({"v": 1}),
+                    start_step: 0,
+                    messages_per_cycle: 100,
+                    steps_between_cycles,
+                    messages_produced: 0,
+                },
+            ));
+            let transformer = flowchart.add_node(FlowchartNode::new(
+                name.into(),
+                (100.0, 0.0),
+                NodeType::Transformer {
+                    script: script.into(),
+                    selected_outputs: None,
+                    globals: Default::default(),
+                    initial_globals: Default::default(),
+                    retry: Default::default(),
+                },
+            ));
+            let sink = flowchart.add_node(FlowchartNode::new(
+                format!("{} sink", name),
+                (200.0, 0.0),
+                NodeType::Consumer {
+                    consumption_rate: 100,
+                    processing_time: 0,
+                    max_concurrency: 0,
+                    in_flight: Vec::new(),
+                    accept_filter: String::new(),
+                },
+            ));
+            flowchart.add_connection(producer, transformer).unwrap();
+            flowchart.add_connection(transformer, sink).unwrap();
+        };
+        // Some steps give both transformers messages, some only the first
+        pipeline("Every step", 1);
+        pipeline("Every third step", 3);
+
+        let run = |threads: usize| {
+            let mut flowchart = flowchart.clone();
+            let mut engine = SimulationEngine::with_script_threads(threads);
+            let mut outputs = Vec::new();
+            for _ in 0..2 {
+                flowchart.reset_simulation();
+                for _ in 0..12 {
+                    let delivered = engine.step(&mut flowchart);
+                    for (node_id, _, message) in &delivered {
+                        if matches!(flowchart.nodes[node_id].node_type, NodeType::Consumer { .. }) {
+                            outputs.push((*node_id, (*message.data).clone()));
+                        }
+                    }
+                    assert!(engine.deliver_messages(delivered, &mut flowchart).is_empty());
+                }
+            }
+            outputs
+        };
+        let sequential = run(1);
+        assert!(sequential.iter().all(|(_, data)| data["calls"] == data["n"]));
+        // The second run starts from fresh scripts and repeats the first
+        let (first, second) = sequential.split_at(sequential.len() / 2);
+        assert_eq!(first, second);
+        assert_eq!(first.iter().filter(|(_, data)| data["n"] == 10).count(), 1);
+        assert_eq!(run(4), sequential);
+    }
+
+    #[test]
+    fn test_parallel_delivery_matches_sequential_delivery() {
+        let build = || {
+            let mut flowchart = Flowchart::new();
+            let mut transformer = |name: &str, script: &str| {
+                flowchart.add_node(FlowchartNode::new(
+                    name.into(),
+                    (0.0, 0.0),
+                    NodeType::Transformer {
+                        script: script.into(),
+                        selected_outputs: None,
+                        globals: Default::default(),
+                        initial_globals: Default::default(),
+                        retry: Default::default(),
+                    },
+                ))
+            };
+            let counter = transformer(
+                "Counter",
+                "function transform(input) { state.n = (state.n || 0) + 1; return { n: state.n, v: input.v }; }",
+            );
+            let picky = transformer(
+                "Picky",
+                "function transform(input) { if (input.v % 2) { throw new Error('odd'); } return input; }",
+            );
+            let sink = flowchart.add_node(FlowchartNode::new(
+                "Sink".into(),
+                (0.0, 0.0),
+                NodeType::Consumer {
+                    consumption_rate: 1,
+                    processing_time: 0,
+                    max_concurrency: 1,
+                    in_flight: Vec::new(),
+                    accept_filter: "$.missing".into(),
+                },
+            ));
+            flowchart.add_connection(counter, sink).unwrap();
+            flowchart.add_connection(picky, sink).unwrap();
+            let delivered: Vec<(NodeId, usize, Message)> = (0..6)
+                .map(|v| (if v % 2 == 0 { counter } else { picky }, 0, Message::new(json!({ "v": v / 2 }))))
+                .collect();
+            (flowchart, delivered)
+        };
+
+        let (mut sequential, delivered) = build();
+        let mut engine = SimulationEngine::with_script_threads(1);
+        let mut sequential_errors = Vec::new();
+        for (node_id, _, message) in delivered {
+            if let Err(error) = engine.deliver_message(node_id, message, &mut sequential) {
+                sequential_errors.push((node_id, error));
+            }
+        }
+
+        let (mut parallel, delivered) = build();
+        let mut engine = SimulationEngine::with_script_threads(4);
+        let parallel_errors = engine.deliver_messages(delivered, &mut parallel);
+
+        let payloads = |flowchart: &Flowchart| -> Vec<Vec<serde_json::Value>> {
+            flowchart
+                .connections
+                .iter()
+                .map(|c| c.messages.iter().map(|m| (*m.data).clone()).collect())
+                .collect()
+        };
+        // Node IDs differ between the two copies, so compare the errors alone
+        let messages = |errors: &[(NodeId, String)]| errors.iter().map(|(_, e)| e.clone()).collect::<Vec<_>>();
+        assert_eq!(parallel_errors.len(), 1);
+        assert_eq!(messages(&parallel_errors), messages(&sequential_errors));
+        assert_eq!(payloads(&parallel), payloads(&sequential));
+        let globals = |flowchart: &Flowchart| {
+            flowchart
+                .nodes
+                .values()
+                .find_map(|n| match &n.node_type {
+                    NodeType::Transformer { globals, .. } if n.name == "Counter" => Some(globals.clone()),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert_eq!(globals(&parallel)["n"], json!(3));
+        assert_eq!(globals(&parallel), globals(&sequential));
+    }
+
+    #[test]
+    fn test_messages_are_delivered_by_priority_then_arrival() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({"value": 1, "__priority": 5}),
+                start_step: 0,
+                messages_per_cycle: 1,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        let sink = flowchart.add_node(FlowchartNode::new(
+            "C".into(),
+            (100.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.add_connection(producer, sink).unwrap();
+
+        // Producer strips the control field and applies the priority
+        engine.step(&mut flowchart);
+        let produced = &flowchart.connections[0].messages[0];
+        assert_eq!(produced.priority, 5);
+        assert_eq!(*produced.data, json!({"value": 1}));
+
+        let mut low_a = Message::new(json!("low-a"));
+        low_a.priority = -1;
+        let mut high = Message::new(json!("high"));
+        high.priority = 10;
+        let normal = Message::new(json!("normal"));
+        let mut low_b = Message::new(json!("low-b"));
+        low_b.priority = -1;
+        flowchart.connections[0]
+            .messages
+            .extend([low_a, high, normal, low_b]);
+
+        let order: Vec<serde_json::Value> = engine
+            .step(&mut flowchart)
+            .into_iter()
+            .map(|(_, _, m)| (*m.data).clone())
+            .collect();
+        assert_eq!(
+            order,
+            vec![json!("high"), json!({"value": 1}), json!("normal"), json!("low-a"), json!("low-b")]
+        );
+    }
+
+    #[test]
+    fn test_transformer_outputs_inherit_or_override_priority() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let transformer = flowchart.add_node(FlowchartNode::new(
+            "T".into(),
+            (0.0, 0.0),
+            NodeType::Transformer {
+                script: "function transform(input) { return input.boost ? { v: 1, __priority: 9 } : { v: 0 }; }".into(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: Default::default(),
+            },
+        ));
+        let sink = flowchart.add_node(FlowchartNode::new(
+            "C".into(),
+            (100.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.add_connection(transformer, sink).unwrap();
+
+        let mut input = Message::new(json!({"boost": false}));
+        input.priority = 3;
+        engine.deliver_message(transformer, input, &mut flowchart).unwrap();
+        engine
+            .deliver_message(transformer, Message::new(json!({"boost": true})), &mut flowchart)
+            .unwrap();
+
+        let messages = &flowchart.connections[0].messages;
+        assert_eq!(messages[0].priority, 3);
+        assert_eq!(messages[1].priority, 9);
+        assert_eq!(*messages[1].data, json!({"v": 1}));
+    }
+
+    #[test]
+    fn test_trace_id_follows_message_through_transform_and_fan_out() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({"value": 1}),
+                start_step: 0,
+                messages_per_cycle: 2,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        let transformer = flowchart.add_node(FlowchartNode::new(
+            "T".into(),
+            (100.0, 0.0),
+            NodeType::Transformer {
+                script: "function transform(input) { return [input, { copy: true }]; }".into(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: Default::default(),
+            },
+        ));
+        let consumer = |name: &str| {
+            FlowchartNode::new(
+                name.into(),
+                (200.0, 0.0),
+                NodeType::Consumer {
+                    consumption_rate: 1,
+                    processing_time: 0,
+                    max_concurrency: 1,
+                    in_flight: Vec::new(),
+                    accept_filter: String::new(),
+                },
+            )
+        };
+        let a = flowchart.add_node(consumer("A"));
+        let b = flowchart.add_node(consumer("B"));
+        flowchart.add_connection(producer, transformer).unwrap();
+        flowchart.add_connection(transformer, a).unwrap();
+        flowchart.add_connection(transformer, b).unwrap();
+
+        let run_step = |flowchart: &mut Flowchart, engine: &mut SimulationEngine| {
+            for (node_id, _, message) in engine.step(flowchart) {
+                engine.deliver_message(node_id, message, flowchart).unwrap();
+            }
+        };
+
+        run_step(&mut flowchart, &mut engine);
+        let first = flowchart.connections[0].messages[0].trace_id.expect("produced messages are traced");
+        run_step(&mut flowchart, &mut engine);
+        let second = flowchart.connections[0].messages[0].trace_id.unwrap();
+        assert_ne!(first, second, "each produced message starts its own trace");
+
+        // Both outputs of the fan-out stay on the input's trace
+        for connection in &flowchart.connections[1..] {
+            assert!(connection.messages.iter().all(|m| m.trace_id == Some(first)));
+        }
+        run_step(&mut flowchart, &mut engine);
+
+        let hops: Vec<(NodeId, Option<NodeId>, u64)> = engine
+            .trace_hops(first)
+            .into_iter()
+            .map(|h| (h.node_id, h.from, h.step))
+            .collect();
+        // Both fan-out copies reach A and B on the same step, so each is one hop
+        assert_eq!(hops.len(), 4, "{:?}", hops);
+        assert_eq!(hops[0], (producer, None, 0));
+        assert_eq!(hops[1], (transformer, Some(producer), 1));
+        assert!(hops.contains(&(a, Some(transformer), 2)));
+        assert!(hops.contains(&(b, Some(transformer), 2)));
+
+        engine.clear_traces();
+        assert!(engine.trace_hops(first).is_empty());
+    }
+
     #[test]
     fn test_transformer_missing_function() {
         // Test that a missing transform function is caught
@@ -664,6 +2782,7 @@ mod tests {
                 selected_outputs: None,
                 globals: Default::default(),
                 initial_globals: Default::default(),
+                retry: Default::default(),
             },
         );
         let transformer_id = transformer.id;
@@ -675,6 +2794,10 @@ mod tests {
             (100.0, 0.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
         let consumer_id = consumer.id;
@@ -725,6 +2848,7 @@ mod tests {
                 selected_outputs: None,
                 globals: Default::default(),
                 initial_globals: Default::default(),
+                retry: Default::default(),
             },
         );
         let transformer_id = transformer.id;
@@ -736,6 +2860,119 @@ mod tests {
         // Should succeed, producing a message with null data
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn steps_only_visit_scheduled_work_and_reschedule_after_edits() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 3,
+                steps_between_cycles: 10,
+                messages_produced: 0,
+            },
+        ));
+        let consumer = flowchart.add_node(FlowchartNode::new(
+            "C".into(),
+            (0.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.add_connection(producer, consumer).unwrap();
+
+        // The producer settles on step 1 while its message is delivered, the
+        // consumer settles on step 2, and nothing else happens until step 10
+        for _ in 0..2 {
+            let delivered = engine.step(&mut flowchart);
+            engine.deliver_messages(delivered, &mut flowchart);
+        }
+        assert_eq!(engine.agenda.scheduled_steps(), BTreeSet::from([2, 10]));
+        engine.step(&mut flowchart);
+        assert_eq!(engine.agenda.scheduled_steps(), BTreeSet::from([10]));
+        assert_eq!(flowchart.nodes[&consumer].state, NodeState::Idle);
+
+        // A shorter interval takes effect once the engine is told
+        if let NodeType::Producer { steps_between_cycles, .. } = &mut flowchart.nodes.get_mut(&producer).unwrap().node_type {
+            *steps_between_cycles = 2;
+        }
+        engine.reschedule();
+        engine.step(&mut flowchart);
+        assert_eq!(flowchart.connections[0].messages.len(), 1);
+        assert_eq!(flowchart.connections[0].messages[0].created_step, Some(3));
+    }
+
+    #[test]
+    fn injected_messages_run_through_the_model_and_waiting_ones_can_be_drained() {
+        let mut engine = SimulationEngine::new();
+        let mut flowchart = Flowchart::new();
+        let transformer = flowchart.add_node(FlowchartNode::new(
+            "Double".into(),
+            (0.0, 0.0),
+            NodeType::Transformer {
+                script: "function transform(input) { return { value: input.value * 2 }; }".into(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: RetryPolicy::default(),
+            },
+        ));
+        let consumer = flowchart.add_node(FlowchartNode::new(
+            "Sink".into(),
+            (0.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 0,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.add_connection(transformer, consumer).unwrap();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "Idle source".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: json!({}),
+                start_step: 0,
+                messages_per_cycle: 0,
+                steps_between_cycles: 1,
+                messages_produced: 0,
+            },
+        ));
+        flowchart.add_connection(producer, transformer).unwrap();
+        flowchart.current_step = 5;
+
+        engine.inject_into_node(transformer, Message::new(json!({"value": 2})), &mut flowchart).unwrap();
+        let waiting = engine.peek_messages(consumer, &flowchart);
+        assert_eq!(waiting.len(), 1);
+        assert_eq!(*waiting[0].data, json!({"value": 4}));
+        assert_eq!(waiting[0].created_step, Some(5));
+        assert!(engine.inject_into_node(producer, Message::new(json!({})), &mut flowchart).is_err());
+
+        // Messages put on a connection are moved by the next step
+        engine.inject_on_connection(1, Message::new(json!({"value": 10})), &mut flowchart).unwrap();
+        assert!(engine.inject_on_connection(7, Message::new(json!({})), &mut flowchart).is_err());
+        let delivered = engine.step(&mut flowchart);
+        let destinations: Vec<NodeId> = delivered.iter().map(|(to, _, _)| *to).collect();
+        assert_eq!(destinations, vec![consumer, transformer]);
+        engine.deliver_messages(delivered, &mut flowchart);
+
+        let drained = engine.drain_messages(consumer, &mut flowchart);
+        assert_eq!(drained.len(), 1);
+        assert_eq!(*drained[0].data, json!({"value": 20}));
+        assert!(engine.peek_messages(consumer, &flowchart).is_empty());
+        let delivered = engine.step(&mut flowchart);
+        assert!(delivered.is_empty());
+    }
 }
 
 #[cfg(test)]
@@ -763,6 +3000,7 @@ mod programmatic_routing_tests {
                 selected_outputs: None,
                 globals: Default::default(),
                 initial_globals: Default::default(),
+                retry: Default::default(),
             },
         );
         let transformer_id = transformer.id;
@@ -774,6 +3012,10 @@ mod programmatic_routing_tests {
             (150.0, 0.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
         let c1_id = c1.id;
@@ -784,6 +3026,10 @@ mod programmatic_routing_tests {
             (150.0, 100.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
         let c2_id = c2.id;
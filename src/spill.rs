@@ -0,0 +1,122 @@
+//! Records moved out of memory into a temporary file.
+//!
+//! A [`SpillFile`] appends records as JSON lines to a file of its own in the
+//! system's temporary directory and hands back a [`SpillRef`] for each, from
+//! which the record can be read again later. The file is removed when the
+//! `SpillFile` is dropped. It is used by the node message history to keep
+//! older messages of long runs without holding them in memory.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Where a record was written in a [`SpillFile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpillRef {
+    offset: u64,
+    len: u32,
+}
+
+/// Temporary file of JSON records, removed on drop.
+#[derive(Debug)]
+pub struct SpillFile {
+    file: File,
+    path: PathBuf,
+    len: u64,
+}
+
+impl SpillFile {
+    /// Creates an empty file in the temporary directory, named after `prefix`.
+    pub fn create(prefix: &str) -> Result<Self, String> {
+        let path = std::env::temp_dir().join(format!("{}_{}.jsonl", prefix, uuid::Uuid::new_v4()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        Ok(Self { file, path, len: 0 })
+    }
+
+    /// Path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Bytes written so far.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether nothing has been written since the file was created or cleared.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends `record` to the end of the file.
+    pub fn append<T: Serialize>(&mut self, record: &T) -> Result<SpillRef, String> {
+        let mut line = serde_json::to_vec(record).map_err(|e| format!("Failed to serialize record: {}", e))?;
+        line.push(b'\n');
+        let len = u32::try_from(line.len()).map_err(|_| "Record too large to spill".to_string())?;
+        self.file
+            .seek(SeekFrom::Start(self.len))
+            .and_then(|_| self.file.write_all(&line))
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        let at = SpillRef { offset: self.len, len };
+        self.len += u64::from(len);
+        Ok(at)
+    }
+
+    /// Reads back the record written at `at`.
+    pub fn read<T: DeserializeOwned>(&mut self, at: SpillRef) -> Result<T, String> {
+        let mut line = vec![0; at.len as usize];
+        self.file
+            .seek(SeekFrom::Start(at.offset))
+            .and_then(|_| self.file.read_exact(&mut line))
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+        serde_json::from_slice(&line).map_err(|e| format!("Failed to parse spilled record: {}", e))
+    }
+
+    /// Drops everything written so far. Earlier [`SpillRef`]s become invalid.
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.file
+            .set_len(0)
+            .map_err(|e| format!("Failed to truncate {}: {}", self.path.display(), e))?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn records_read_back_until_cleared_and_the_file_goes_on_drop() {
+        let mut spill = SpillFile::create("flowchart_spill_test").unwrap();
+        let first = spill.append(&json!({"n": 1})).unwrap();
+        let second = spill.append(&json!(["two", 2])).unwrap();
+        assert_eq!(spill.read::<Value>(second).unwrap(), json!(["two", 2]));
+        assert_eq!(spill.read::<Value>(first).unwrap(), json!({"n": 1}));
+
+        spill.clear().unwrap();
+        assert!(spill.is_empty());
+        let again = spill.append(&json!("three")).unwrap();
+        assert_eq!(again.offset, first.offset);
+        assert_eq!(spill.read::<Value>(again).unwrap(), json!("three"));
+
+        let path = spill.path().to_path_buf();
+        assert!(path.exists());
+        drop(spill);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,418 @@
+//! Parameter sweeps for capacity planning.
+//!
+//! A sweep varies one or two node parameters across a grid of values, runs a
+//! headless copy of the flowchart for every combination and keeps each run's
+//! [`RunSummary`], so any [`SweepMetric`] can be shown afterwards without
+//! re-running. Runs use the document's warm-up and measurement window, and a
+//! copy of the document taken when the sweep was planned.
+
+use crate::metrics::RunSummary;
+use crate::simulation::SimulationEngine;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+
+/// A numeric node property a sweep can vary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SweepParameter {
+    /// Producer total messages
+    #[default]
+    ProducerTotal,
+    /// Producer steps between cycles
+    ProducerInterval,
+    /// Consumer processing time in steps
+    ConsumerProcessingTime,
+    /// Consumer messages processed at once
+    ConsumerConcurrency,
+}
+
+impl SweepParameter {
+    /// Every parameter, in menu order.
+    pub const ALL: [SweepParameter; 4] = [
+        SweepParameter::ProducerTotal,
+        SweepParameter::ProducerInterval,
+        SweepParameter::ConsumerProcessingTime,
+        SweepParameter::ConsumerConcurrency,
+    ];
+
+    /// Name shown in the sweep dialog.
+    pub fn label(self) -> &'static str {
+        match self {
+            SweepParameter::ProducerTotal => "Producer: total messages",
+            SweepParameter::ProducerInterval => "Producer: steps between cycles",
+            SweepParameter::ConsumerProcessingTime => "Consumer: processing time",
+            SweepParameter::ConsumerConcurrency => "Consumer: max concurrency",
+        }
+    }
+
+    /// Returns true if nodes of `node_type` have this parameter.
+    pub fn applies_to(self, node_type: &NodeType) -> bool {
+        match self {
+            SweepParameter::ProducerTotal | SweepParameter::ProducerInterval => {
+                matches!(node_type, NodeType::Producer { .. })
+            }
+            _ => matches!(node_type, NodeType::Consumer { .. }),
+        }
+    }
+
+    /// Current value of the parameter on `node_type`, if it has it.
+    pub fn get(self, node_type: &NodeType) -> Option<u32> {
+        match (self, node_type) {
+            (SweepParameter::ProducerTotal, NodeType::Producer { messages_per_cycle, .. }) => Some(*messages_per_cycle),
+            (SweepParameter::ProducerInterval, NodeType::Producer { steps_between_cycles, .. }) => {
+                Some(*steps_between_cycles)
+            }
+            (SweepParameter::ConsumerProcessingTime, NodeType::Consumer { processing_time, .. }) => {
+                Some(*processing_time)
+            }
+            (SweepParameter::ConsumerConcurrency, NodeType::Consumer { max_concurrency, .. }) => {
+                Some(*max_concurrency)
+            }
+            _ => None,
+        }
+    }
+
+    /// Sets the parameter on `node_type`; does nothing if it doesn't have it.
+    pub fn set(self, node_type: &mut NodeType, value: u32) {
+        let field = match (self, node_type) {
+            (SweepParameter::ProducerTotal, NodeType::Producer { messages_per_cycle, .. }) => messages_per_cycle,
+            (SweepParameter::ProducerInterval, NodeType::Producer { steps_between_cycles, .. }) => {
+                steps_between_cycles
+            }
+            (SweepParameter::ConsumerProcessingTime, NodeType::Consumer { processing_time, .. }) => processing_time,
+            (SweepParameter::ConsumerConcurrency, NodeType::Consumer { max_concurrency, .. }) => max_concurrency,
+            _ => return,
+        };
+        *field = value;
+    }
+}
+
+/// One swept parameter and the values it takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SweepAxis {
+    /// Node whose parameter is varied
+    pub node: NodeId,
+    /// The parameter
+    pub parameter: SweepParameter,
+    /// First value
+    pub start: u32,
+    /// Last value, inclusive
+    pub end: u32,
+    /// Distance between values; at least 1
+    pub increment: u32,
+}
+
+impl Default for SweepAxis {
+    fn default() -> Self {
+        Self {
+            node: NodeId::nil(),
+            parameter: SweepParameter::default(),
+            start: 1,
+            end: 10,
+            increment: 1,
+        }
+    }
+}
+
+impl SweepAxis {
+    /// The values from `start` to `end` (in either direction) by `increment`.
+    pub fn values(&self) -> Vec<u32> {
+        let increment = self.increment.max(1) as usize;
+        if self.start <= self.end {
+            (self.start..=self.end).step_by(increment).collect()
+        } else {
+            (self.end..=self.start).rev().step_by(increment).collect()
+        }
+    }
+
+    /// Axis label, e.g. `Consumer: processing time (Sink)`.
+    pub fn label(&self, flowchart: &Flowchart) -> String {
+        match flowchart.nodes.get(&self.node) {
+            Some(node) => format!("{} ({})", self.parameter.label(), node.name),
+            None => self.parameter.label().to_string(),
+        }
+    }
+}
+
+/// The output a sweep's heatmap shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SweepMetric {
+    /// Messages consumed per measured step
+    #[default]
+    Throughput,
+    /// Average messages on connections per step
+    MeanInTransit,
+    /// Most messages on connections at the end of a step
+    MaxInTransit,
+    /// Transformer script failures
+    Errors,
+    /// Average end-to-end latency over all consumers
+    MeanLatency,
+    /// Worst 95th percentile latency of any consumer
+    P95Latency,
+}
+
+impl SweepMetric {
+    /// Every metric, in menu order.
+    pub const ALL: [SweepMetric; 6] = [
+        SweepMetric::Throughput,
+        SweepMetric::MeanInTransit,
+        SweepMetric::MaxInTransit,
+        SweepMetric::Errors,
+        SweepMetric::MeanLatency,
+        SweepMetric::P95Latency,
+    ];
+
+    /// Name shown in the sweep dialog.
+    pub fn label(self) -> &'static str {
+        match self {
+            SweepMetric::Throughput => "Throughput (msg/step)",
+            SweepMetric::MeanInTransit => "In transit (mean)",
+            SweepMetric::MaxInTransit => "In transit (max)",
+            SweepMetric::Errors => "Script errors",
+            SweepMetric::MeanLatency => "Latency (mean steps)",
+            SweepMetric::P95Latency => "Latency (worst p95 steps)",
+        }
+    }
+
+    /// The metric's value for a run; `None` when it has no data (e.g. latency
+    /// of a run where nothing was consumed).
+    pub fn value(self, summary: &RunSummary) -> Option<f64> {
+        match self {
+            SweepMetric::Throughput => Some(summary.throughput()),
+            SweepMetric::MeanInTransit => Some(summary.mean_in_transit),
+            SweepMetric::MaxInTransit => Some(summary.max_in_transit as f64),
+            SweepMetric::Errors => Some(summary.errors as f64),
+            SweepMetric::MeanLatency => {
+                let (total, count) = summary
+                    .latencies
+                    .values()
+                    .flatten()
+                    .fold((0u64, 0u64), |(total, count), l| (total + l, count + 1));
+                (count > 0).then(|| total as f64 / count as f64)
+            }
+            SweepMetric::P95Latency => summary
+                .latencies
+                .keys()
+                .filter_map(|id| summary.latency_stats(*id))
+                .map(|stats| stats.p95 as f64)
+                .reduce(f64::max),
+        }
+    }
+}
+
+/// Runs a copy of `flowchart` from the start for `steps` steps and
+/// summarizes it over the flowchart's measurement window.
+///
+/// Script errors are counted in the summary; the run carries on past them.
+pub fn run_headless(flowchart: &Flowchart, steps: u64) -> RunSummary {
+    let mut flowchart = flowchart.clone();
+    flowchart.reset_simulation();
+    flowchart.simulation_state = SimulationState::Running;
+    let mut engine = SimulationEngine::new();
+    for _ in 0..steps {
+        let delivered = engine.step(&mut flowchart);
+        engine.deliver_messages(delivered, &mut flowchart);
+    }
+    engine.stats().summarize(&flowchart.run_config)
+}
+
+/// A sweep in progress or finished: the grid and the runs done so far.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    /// Columns of the grid
+    pub x: SweepAxis,
+    /// Rows of the grid; a single row when only one parameter is swept
+    pub y: Option<SweepAxis>,
+    /// Steps each run lasts
+    pub steps: u64,
+    base: Flowchart,
+    x_values: Vec<u32>,
+    y_values: Vec<u32>,
+    /// Row-major results; `None` until the combination has run
+    results: Vec<Option<RunSummary>>,
+}
+
+impl Sweep {
+    /// Plans a sweep of a copy of `flowchart` over `x` and optionally `y`.
+    ///
+    /// Fails when an axis names a node without its parameter, both axes vary
+    /// the same parameter, or the grid has more than `max_runs` combinations.
+    pub fn new(
+        flowchart: &Flowchart,
+        x: SweepAxis,
+        y: Option<SweepAxis>,
+        steps: u64,
+        max_runs: usize,
+    ) -> Result<Self, String> {
+        for axis in std::iter::once(&x).chain(&y) {
+            match flowchart.nodes.get(&axis.node) {
+                Some(node) if axis.parameter.applies_to(&node.node_type) => {}
+                Some(node) => {
+                    return Err(format!("\"{}\" has no parameter \"{}\"", node.name, axis.parameter.label()))
+                }
+                None => return Err("Choose a node for each swept parameter".to_string()),
+            }
+        }
+        if y.is_some_and(|y| (y.node, y.parameter) == (x.node, x.parameter)) {
+            return Err("The two axes vary the same parameter".to_string());
+        }
+        if steps == 0 {
+            return Err("Each run needs at least one step".to_string());
+        }
+        let x_values = x.values();
+        let y_values = y.map_or_else(|| vec![0], |y| y.values());
+        let runs = x_values.len() * y_values.len();
+        if runs > max_runs {
+            return Err(format!("{} combinations is more than the limit of {}", runs, max_runs));
+        }
+        Ok(Self {
+            x,
+            y,
+            steps,
+            base: flowchart.clone(),
+            x_values,
+            y_values,
+            results: vec![None; runs],
+        })
+    }
+
+    /// Values of the columns.
+    pub fn x_values(&self) -> &[u32] {
+        &self.x_values
+    }
+
+    /// Values of the rows; a single placeholder row for one-parameter sweeps.
+    pub fn y_values(&self) -> &[u32] {
+        &self.y_values
+    }
+
+    /// Number of combinations that have run.
+    pub fn completed(&self) -> usize {
+        self.results.iter().filter(|r| r.is_some()).count()
+    }
+
+    /// Total number of combinations.
+    pub fn total(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Returns true once every combination has run.
+    pub fn is_finished(&self) -> bool {
+        self.completed() == self.total()
+    }
+
+    /// Summary of the run in `column` and `row`, once it has run.
+    pub fn result(&self, column: usize, row: usize) -> Option<&RunSummary> {
+        self.results.get(row * self.x_values.len() + column)?.as_ref()
+    }
+
+    /// Runs the next combination that hasn't run.
+    ///
+    /// Returns false when there was nothing left to run.
+    pub fn run_next(&mut self) -> bool {
+        let Some(index) = self.results.iter().position(|r| r.is_none()) else {
+            return false;
+        };
+        let (column, row) = (index % self.x_values.len(), index / self.x_values.len());
+        let mut variant = self.base.clone();
+        let mut settings = vec![(self.x, self.x_values[column])];
+        settings.extend(self.y.map(|y| (y, self.y_values[row])));
+        for (axis, value) in settings {
+            if let Some(node) = variant.nodes.get_mut(&axis.node) {
+                axis.parameter.set(&mut node.node_type, value);
+            }
+        }
+        self.results[index] = Some(run_headless(&variant, self.steps));
+        true
+    }
+
+    /// Lowest and highest value of `metric` over the runs so far.
+    pub fn metric_range(&self, metric: SweepMetric) -> Option<(f64, f64)> {
+        self.results
+            .iter()
+            .flatten()
+            .filter_map(|summary| metric.value(summary))
+            .fold(None, |range, v| match range {
+                None => Some((v, v)),
+                Some((low, high)) => Some((f64::min(low, v), f64::max(high, v))),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline() -> (Flowchart, NodeId, NodeId) {
+        let mut flowchart = Flowchart::default();
+        let producer = flowchart.add_node(FlowchartNode::new(
+            "P".into(),
+            (0.0, 0.0),
+            NodeType::Producer {
+                message_template: serde_json::json!({}),
+                start_step: 0,
+                messages_per_cycle: 1000,
+                steps_between_cycles: 0,
+                messages_produced: 0,
+            },
+        ));
+        let consumer = flowchart.add_node(FlowchartNode::new(
+            "C".into(),
+            (200.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 1,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ));
+        flowchart.add_connection(producer, consumer).unwrap();
+        (flowchart, producer, consumer)
+    }
+
+    #[test]
+    fn test_sweep_grid_runs_every_combination() {
+        let (mut flowchart, producer, consumer) = pipeline();
+        flowchart.run_config = RunConfig { warm_up_steps: 6, measurement_steps: 12, ..Default::default() };
+        let x = SweepAxis {
+            node: consumer,
+            parameter: SweepParameter::ConsumerProcessingTime,
+            start: 1,
+            end: 3,
+            increment: 1,
+        };
+        let y = SweepAxis {
+            node: consumer,
+            parameter: SweepParameter::ConsumerConcurrency,
+            start: 3,
+            end: 1,
+            increment: 2,
+        };
+        assert_eq!(y.values(), vec![3, 1]);
+        assert!(Sweep::new(&flowchart, x, Some(x), 18, 100).is_err());
+        assert!(Sweep::new(&flowchart, x, Some(y), 18, 5).is_err());
+        let wrong_node = SweepAxis { node: producer, ..x };
+        assert!(Sweep::new(&flowchart, wrong_node, None, 18, 100).is_err());
+
+        let mut sweep = Sweep::new(&flowchart, x, Some(y), 18, 100).unwrap();
+        assert_eq!(sweep.total(), 6);
+        while sweep.run_next() {}
+        assert!(sweep.is_finished());
+
+        // The producer sends one message per step; the consumer keeps up
+        // only while concurrency / processing time is at least 1
+        for (column, time) in sweep.x_values().to_vec().into_iter().enumerate() {
+            for (row, slots) in sweep.y_values().to_vec().into_iter().enumerate() {
+                let throughput = SweepMetric::Throughput.value(sweep.result(column, row).unwrap()).unwrap();
+                let expected = (slots as f64 / time as f64).min(1.0);
+                assert!((throughput - expected).abs() < 1e-9, "time {} slots {}: {}", time, slots, throughput);
+            }
+        }
+        let (low, high) = sweep.metric_range(SweepMetric::Throughput).unwrap();
+        assert!((low - 1.0 / 3.0).abs() < 1e-9 && high == 1.0);
+        // The document itself is untouched
+        assert_eq!(SweepParameter::ConsumerProcessingTime.get(&flowchart.nodes[&consumer].node_type), Some(1));
+        assert_eq!(flowchart.current_step, 0);
+    }
+}
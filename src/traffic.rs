@@ -0,0 +1,349 @@
+//! Producer proposals from recorded traffic.
+//!
+//! A trace is a JSON Lines file with one recorded message per line. A record
+//! is an object that may carry:
+//!
+//! - when it was sent, in `timestamp`, `ts` or `time`: seconds since the
+//!   epoch, milliseconds since the epoch (numbers above 10^11), or an
+//!   RFC 3339 string such as `2024-05-01T12:00:00.250Z`;
+//! - the stream it belongs to, in `source`, `topic` or `stream`;
+//! - the message itself, in `payload`, `data` or `body`. Without one, the
+//!   record minus the fields above is the message.
+//!
+//! Each stream becomes one proposed producer that emits as many messages as
+//! were recorded, at their average rate, with a template made of the fields
+//! most messages share. Records without a timestamp count as one step apart
+//! in file order.
+
+use crate::types::NodeType;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Keys holding a record's timestamp, in lookup order.
+const TIME_KEYS: [&str; 3] = ["timestamp", "ts", "time"];
+
+/// Keys holding a record's stream, in lookup order.
+const STREAM_KEYS: [&str; 3] = ["source", "topic", "stream"];
+
+/// Keys holding a record's message, in lookup order.
+const PAYLOAD_KEYS: [&str; 3] = ["payload", "data", "body"];
+
+/// Share of a stream's messages a field must appear in to be in the template.
+const COMMON_FIELD_SHARE: f64 = 0.5;
+
+/// Name of the stream of records that don't name one.
+const DEFAULT_STREAM: &str = "Recorded traffic";
+
+/// One line of a traffic trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrafficRecord {
+    /// When the message was sent, in seconds since the epoch
+    pub time: Option<f64>,
+    /// The stream the message belongs to
+    pub stream: String,
+    /// The recorded message
+    pub payload: Value,
+}
+
+/// Parses a JSON Lines trace. Blank lines are skipped.
+///
+/// # Returns
+///
+/// The records in file order, or an error naming the first line that isn't
+/// valid JSON.
+pub fn parse_trace(text: &str) -> Result<Vec<TrafficRecord>, String> {
+    let mut records = Vec::new();
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(line).map_err(|e| format!("Line {}: {}", line_no + 1, e))?;
+        records.push(parse_record(value));
+    }
+    if records.is_empty() {
+        return Err("The trace has no records".to_string());
+    }
+    Ok(records)
+}
+
+/// Splits a record into its timestamp, stream and message.
+fn parse_record(value: Value) -> TrafficRecord {
+    let Value::Object(mut fields) = value else {
+        return TrafficRecord {
+            time: None,
+            stream: DEFAULT_STREAM.to_string(),
+            payload: value,
+        };
+    };
+    let time = take_first(&mut fields, &TIME_KEYS).and_then(|v| parse_time(&v));
+    let stream = match take_first(&mut fields, &STREAM_KEYS) {
+        Some(Value::String(s)) if !s.trim().is_empty() => s,
+        Some(Value::String(_)) | Some(Value::Null) | None => DEFAULT_STREAM.to_string(),
+        Some(other) => other.to_string(),
+    };
+    let payload = take_first(&mut fields, &PAYLOAD_KEYS).unwrap_or(Value::Object(fields));
+    TrafficRecord { time, stream, payload }
+}
+
+/// Removes the first of `keys` present in `fields` and returns its value.
+fn take_first(fields: &mut Map<String, Value>, keys: &[&str]) -> Option<Value> {
+    keys.iter().find_map(|key| fields.remove(*key))
+}
+
+/// Reads a timestamp as seconds since the epoch.
+fn parse_time(value: &Value) -> Option<f64> {
+    let time = match value {
+        Value::Number(n) => n.as_f64()?,
+        Value::String(s) => s.trim().parse::<f64>().ok().or_else(|| parse_rfc3339(s.trim()))?,
+        _ => return None,
+    };
+    // Epoch seconds stay below 10^11 until the year 5138
+    Some(if time > 1e11 { time / 1000.0 } else { time })
+}
+
+/// Parses an RFC 3339 date-time, e.g. `2024-05-01T12:00:00.250+02:00`, as
+/// seconds since the epoch.
+fn parse_rfc3339(s: &str) -> Option<f64> {
+    let number = |range: std::ops::Range<usize>| s.get(range)?.parse::<i64>().ok();
+    let bytes = s.as_bytes();
+    if bytes.len() < 20 || bytes[4] != b'-' || bytes[7] != b'-' || bytes[13] != b':' || bytes[16] != b':' {
+        return None;
+    }
+    if !matches!(bytes[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    let mut fraction = 0.0;
+    if let Some(digits) = rest.strip_prefix('.') {
+        let len = digits.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return None;
+        }
+        fraction = format!("0.{}", &digits[..len]).parse().ok()?;
+        rest = &digits[len..];
+    }
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.as_bytes().first()? {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let (hours, minutes) = rest[1..].split_once(':')?;
+            if hours.len() != 2 || minutes.len() != 2 {
+                return None;
+            }
+            sign * (hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60)
+        }
+    };
+
+    // Days since the epoch of a proleptic Gregorian date (Howard Hinnant's algorithm)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    Some(seconds as f64 + fraction)
+}
+
+/// Builds a template from the fields most of `values` share.
+///
+/// Objects keep the keys present in at least half of them, each inferred
+/// from the values it has; anything else becomes its most common value.
+pub fn infer_template(values: &[&Value]) -> Value {
+    if values.is_empty() {
+        return Value::Object(Map::new());
+    }
+    if values.iter().all(|v| v.is_object()) {
+        let mut keys: Vec<&String> = Vec::new();
+        let mut present: HashMap<&String, Vec<&Value>> = HashMap::new();
+        for value in values {
+            for (key, field) in value.as_object().into_iter().flatten() {
+                let entry = present.entry(key).or_insert_with(|| {
+                    keys.push(key);
+                    Vec::new()
+                });
+                entry.push(field);
+            }
+        }
+        let threshold = values.len() as f64 * COMMON_FIELD_SHARE;
+        let template = keys
+            .into_iter()
+            .filter(|key| present[key].len() as f64 >= threshold)
+            .map(|key| (key.clone(), infer_template(&present[key])))
+            .collect();
+        return Value::Object(template);
+    }
+
+    // The most common value; ties go to the one seen first
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+    for (index, value) in values.iter().enumerate() {
+        counts.entry(value.to_string()).or_insert((0, index)).0 += 1;
+    }
+    let (_, first) = counts
+        .values()
+        .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)))
+        .copied()
+        .unwrap_or_default();
+    values[first].clone()
+}
+
+/// A producer approximating one stream of a trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProducerProposal {
+    /// The stream's name, used as the producer's name
+    pub name: String,
+    /// Number of recorded messages, which the producer emits in total
+    pub messages: usize,
+    /// Recorded messages per second, if the stream has timestamps
+    pub rate_per_second: Option<f64>,
+    /// Step of the first message, relative to the first record of the trace
+    pub start_step: u64,
+    /// Steps between messages
+    pub steps_between_cycles: u32,
+    /// Template inferred from the recorded messages
+    pub message_template: Value,
+    /// Whether the recorded rate is above one message per step, which a
+    /// producer can't match; a shorter step would
+    pub rate_capped: bool,
+}
+
+impl ProducerProposal {
+    /// The producer node configuration of the proposal.
+    pub fn node_type(&self) -> NodeType {
+        NodeType::Producer {
+            message_template: self.message_template.clone(),
+            start_step: self.start_step,
+            messages_per_cycle: u32::try_from(self.messages).unwrap_or(u32::MAX),
+            steps_between_cycles: self.steps_between_cycles,
+            messages_produced: 0,
+        }
+    }
+}
+
+/// Proposes one producer per stream of `records`, in order of each
+/// stream's first record, with `seconds_per_step` of recorded time per
+/// simulation step.
+pub fn propose_producers(records: &[TrafficRecord], seconds_per_step: f64) -> Vec<ProducerProposal> {
+    let seconds_per_step = if seconds_per_step > 0.0 { seconds_per_step } else { 1.0 };
+    let mut streams: Vec<(&str, Vec<(usize, &TrafficRecord)>)> = Vec::new();
+    for (index, record) in records.iter().enumerate() {
+        match streams.iter_mut().find(|(name, _)| *name == record.stream) {
+            Some((_, members)) => members.push((index, record)),
+            None => streams.push((&record.stream, vec![(index, record)])),
+        }
+    }
+    let origin = records.iter().filter_map(|r| r.time).reduce(f64::min);
+
+    streams
+        .into_iter()
+        .map(|(name, members)| {
+            let times: Option<Vec<f64>> = members.iter().map(|(_, r)| r.time).collect();
+            // Steps of each message: from timestamps, else one step per line
+            let steps: Vec<f64> = match (&times, origin) {
+                (Some(times), Some(origin)) => times.iter().map(|t| (t - origin) / seconds_per_step).collect(),
+                _ => members.iter().map(|(index, _)| *index as f64).collect(),
+            };
+            let first = steps.iter().copied().fold(f64::INFINITY, f64::min);
+            let last = steps.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let mean_gap = if steps.len() > 1 { (last - first) / (steps.len() - 1) as f64 } else { 1.0 };
+            let rate_per_second = times.as_ref().filter(|_| steps.len() > 1 && last > first).map(|_| {
+                (steps.len() - 1) as f64 / ((last - first) * seconds_per_step)
+            });
+            let payloads: Vec<&Value> = members.iter().map(|(_, r)| &r.payload).collect();
+            ProducerProposal {
+                name: name.to_string(),
+                messages: members.len(),
+                rate_per_second,
+                start_step: first.max(0.0).round() as u64,
+                steps_between_cycles: mean_gap.round().clamp(1.0, u32::MAX as f64) as u32,
+                message_template: infer_template(&payloads),
+                rate_capped: mean_gap < 0.5,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_trace_splits_records_and_reads_timestamps() {
+        let trace = r#"{"ts": 1714564800, "topic": "orders", "payload": {"id": 1}}
+
+{"timestamp": "2024-05-01T12:00:01.500Z", "topic": "orders", "id": 2, "status": "new"}
+{"time": 1714564802000, "value": 7}
+42
+"#;
+        let records = parse_trace(trace).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].time, Some(1_714_564_800.0));
+        assert_eq!(records[0].payload, json!({"id": 1}));
+        assert_eq!(records[1].time, Some(1_714_564_801.5));
+        assert_eq!(records[1].stream, "orders");
+        assert_eq!(records[1].payload, json!({"id": 2, "status": "new"}));
+        // Milliseconds and streams that aren't named
+        assert_eq!(records[2].time, Some(1_714_564_802.0));
+        assert_eq!(records[2].stream, DEFAULT_STREAM);
+        assert_eq!(records[3].payload, json!(42));
+
+        assert_eq!(parse_rfc3339("2024-05-01T14:00:00+02:00"), Some(1_714_564_800.0));
+        assert_eq!(parse_rfc3339("2024-13-01T00:00:00Z"), None);
+        assert!(parse_trace("{\"a\": 1}\n{oops").unwrap_err().starts_with("Line 2"));
+        assert!(parse_trace("\n\n").is_err());
+    }
+
+    #[test]
+    fn test_infer_template_keeps_common_fields_and_values() {
+        let values = [
+            json!({"status": "new", "id": 1, "meta": {"region": "eu"}}),
+            json!({"status": "new", "id": 2, "meta": {"region": "us"}, "rare": true}),
+            json!({"status": "paid", "id": 3, "meta": {"region": "eu"}}),
+        ];
+        let refs: Vec<&Value> = values.iter().collect();
+        assert_eq!(
+            infer_template(&refs),
+            json!({"status": "new", "id": 1, "meta": {"region": "eu"}})
+        );
+    }
+
+    #[test]
+    fn test_propose_producers_matches_stream_rates() {
+        let mut trace = String::new();
+        for i in 0..5 {
+            trace.push_str(&format!("{{\"ts\": {}, \"topic\": \"orders\", \"id\": {}}}\n", 100 + i * 4, i));
+        }
+        for i in 0..3 {
+            trace.push_str(&format!("{{\"ts\": {}, \"topic\": \"clicks\"}}\n", 110.0 + i as f64 * 0.1));
+        }
+        let records = parse_trace(&trace).unwrap();
+        let proposals = propose_producers(&records, 2.0);
+        assert_eq!(proposals.len(), 2);
+
+        let orders = &proposals[0];
+        assert_eq!(orders.name, "orders");
+        assert_eq!(orders.messages, 5);
+        assert_eq!((orders.start_step, orders.steps_between_cycles), (0, 2));
+        assert_eq!(orders.rate_per_second, Some(0.25));
+        assert!(!orders.rate_capped);
+        assert!(matches!(orders.node_type(), NodeType::Producer { messages_per_cycle: 5, .. }));
+
+        // Faster than a step allows: one message per step, flagged
+        let clicks = &proposals[1];
+        assert_eq!((clicks.start_step, clicks.steps_between_cycles), (5, 1));
+        assert!(clicks.rate_capped);
+    }
+}
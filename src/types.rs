@@ -3,8 +3,10 @@
 //! This module defines all the fundamental data structures used throughout the application,
 //! including nodes, connections, messages, and the main flowchart structure.
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Unique identifier for flowchart nodes.
@@ -16,8 +18,14 @@ pub type MessageId = Uuid;
 /// Unique identifier for groups.
 pub type GroupId = Uuid;
 
+/// Unique identifier for swimlanes.
+pub type LaneId = Uuid;
+
+/// Correlation identifier shared by a produced message and everything derived from it.
+pub type TraceId = Uuid;
+
 /// Represents the current state of a flowchart node during simulation.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum NodeState {
     /// Node is idle and not processing anything
     Idle,
@@ -28,7 +36,7 @@ pub enum NodeState {
 }
 
 /// Represents the overall state of the flowchart simulation.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum SimulationState {
     /// Simulation is stopped
     Stopped,
@@ -39,7 +47,7 @@ pub enum SimulationState {
 }
 
 /// Defines the different types of nodes available in the flowchart.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum NodeType {
     /// A node that generates messages at a specified rate
     Producer {
@@ -59,6 +67,20 @@ pub enum NodeType {
     Consumer {
         /// Maximum number of messages to consume per simulation step
         consumption_rate: u32,
+        /// Number of steps each message occupies the consumer; 0 consumes instantly
+        #[serde(default)]
+        processing_time: u32,
+        /// Maximum number of messages processed at once; 0 means unlimited
+        #[serde(default)]
+        max_concurrency: u32,
+        /// Remaining processing steps for each message currently being processed
+        #[serde(default)]
+        in_flight: Vec<u32>,
+        /// Only messages matching this filter are consumed; the rest wait on the
+        /// connection. A JSONPath condition such as `$.status == "ok"`, or a
+        /// JavaScript expression over `msg`. Empty accepts everything.
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        accept_filter: String,
     },
     /// A node that transforms messages using JavaScript
     Transformer {
@@ -73,11 +95,22 @@ pub enum NodeType {
         /// Initial values for globals; used to reset on Stop
         #[serde(default)]
         initial_globals: serde_json::Map<String, serde_json::Value>,
+        /// How messages are retried when the script throws
+        #[serde(default)]
+        retry: RetryPolicy,
+    },
+    /// A node standing in for another flowchart file.
+    ///
+    /// Reference nodes take no part in the simulation and cannot be connected;
+    /// they let large models be organized across several documents.
+    Reference {
+        /// Path of the linked flowchart file, relative to the referencing file's directory
+        path: String,
     },
 }
 
 /// A named group of nodes.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Group {
     /// Unique identifier for this group
     pub id: GroupId,
@@ -89,23 +122,388 @@ pub struct Group {
     /// How this group should be visualized on the canvas
     #[serde(default)]
     pub drawing: GroupDrawingMode,
+    /// Stacking order among groups: higher groups are drawn over lower ones
+    /// and are hit first, ties in id order
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub z_order: i32,
 }
 
-/// Drawing mode for a group background shape.
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+/// A named set of nodes that can be selected again in one go.
+///
+/// Unlike a [`Group`] it isn't drawn and doesn't take part in layouts; it
+/// only remembers a selection for recurring bulk edits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SavedSelection {
+    /// Name shown in the selections menu
+    pub name: String,
+    /// The selected nodes, in selection order
+    #[serde(default)]
+    pub nodes: Vec<NodeId>,
+}
+
+/// A named horizontal band (team, service, ...) that nodes can be assigned to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Lane {
+    /// Unique identifier for this lane
+    pub id: LaneId,
+    /// Display name of the lane
+    pub name: String,
+    /// Height of the band in world units
+    #[serde(default = "default_lane_height")]
+    pub height: f32,
+}
+
+fn default_lane_height() -> f32 {
+    crate::constants::LANE_DEFAULT_HEIGHT
+}
+
+impl Lane {
+    /// Creates a lane with the default height.
+    pub fn new(name: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            height: default_lane_height(),
+        }
+    }
+}
+
+/// Swimlane mode settings and the lanes it shows.
+///
+/// Lanes are stacked top to bottom in order, starting at world y = 0. Nodes
+/// referring to a lane that no longer exists are treated as unassigned.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default, JsonSchema)]
+pub struct Swimlanes {
+    /// Whether lanes are drawn on the canvas
+    #[serde(default)]
+    pub enabled: bool,
+    /// Lanes in display order, top first
+    #[serde(default)]
+    pub lanes: Vec<Lane>,
+}
+
+impl Swimlanes {
+    /// World-space vertical extent `(top, bottom)` of each lane, in order.
+    pub fn bands(&self) -> impl Iterator<Item = (&Lane, f32, f32)> {
+        self.lanes.iter().scan(0.0_f32, |top, lane| {
+            let start = *top;
+            *top += lane.height;
+            Some((lane, start, *top))
+        })
+    }
+
+    /// The lane whose band contains world y coordinate `y`, if any.
+    pub fn lane_at(&self, y: f32) -> Option<LaneId> {
+        self.bands()
+            .find(|(_, top, bottom)| y >= *top && y < *bottom)
+            .map(|(lane, _, _)| lane.id)
+    }
+
+    /// Looks up a lane by id.
+    pub fn lane(&self, id: LaneId) -> Option<&Lane> {
+        self.lanes.iter().find(|l| l.id == id)
+    }
+}
+
+/// How a run is carried out, saved with the document so every run of it,
+/// in the app or from the command line, behaves the same.
+///
+/// The first `warm_up_steps` steps are discarded so startup transients don't
+/// skew steady-state results; measurement then covers `measurement_steps`
+/// steps, or the rest of the run when that is 0. A run pauses by itself on
+/// `pause_at_step`, ends after `max_steps`, and stops on a script error
+/// unless `continue_on_error` is set.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(default)]
+pub struct RunConfig {
+    /// Steps at the start of a run whose statistics are discarded
+    pub warm_up_steps: u64,
+    /// Length of the measurement window in steps; 0 measures until the run stops
+    pub measurement_steps: u64,
+    /// Seed of the random node failures and producer jitter, so reruns repeat them
+    pub seed: u64,
+    /// Step on which a running simulation pauses by itself, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_at_step: Option<u64>,
+    /// Steps after which a run ends, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_steps: Option<u64>,
+    /// Steps a running simulation advances per frame
+    pub steps_per_frame: u32,
+    /// Whether a run carries on past script errors instead of stopping
+    pub continue_on_error: bool,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            warm_up_steps: 0,
+            measurement_steps: 0,
+            seed: 0,
+            pause_at_step: None,
+            max_steps: None,
+            steps_per_frame: 1,
+            continue_on_error: false,
+        }
+    }
+}
+
+/// Why a run stopped or paused by itself.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunHalt {
+    /// A node raised a script error
+    Error,
+    /// The run reached its maximum number of steps
+    MaxSteps,
+    /// The run reached its pause-at step
+    PauseStep,
+}
+
+impl RunHalt {
+    /// Describes the halt for the status line and the command line.
+    pub fn describe(self) -> &'static str {
+        match self {
+            RunHalt::Error => "stopped on a script error",
+            RunHalt::MaxSteps => "reached the maximum number of steps",
+            RunHalt::PauseStep => "reached the pause-at step",
+        }
+    }
+}
+
+impl RunConfig {
+    /// Returns true if statistics of `step` are part of the measurement.
+    pub fn measures(&self, step: u64) -> bool {
+        step >= self.warm_up_steps
+            && (self.measurement_steps == 0 || step - self.warm_up_steps < self.measurement_steps)
+    }
+
+    /// Last measured step, or `None` when measurement runs until the run stops.
+    pub fn last_measured_step(&self) -> Option<u64> {
+        (self.measurement_steps > 0).then(|| self.warm_up_steps + self.measurement_steps - 1)
+    }
+
+    /// Returns true if a run ends by itself, on its maximum number of steps
+    /// or its pause-at step.
+    pub fn ends(&self) -> bool {
+        self.max_steps.is_some() || self.pause_at_step.is_some_and(|step| step > 0)
+    }
+
+    /// What halts a run that has just completed `steps` steps, if anything.
+    /// `errored` tells whether the last step raised script errors.
+    pub fn halt_after(&self, steps: u64, errored: bool) -> Option<RunHalt> {
+        if errored && !self.continue_on_error {
+            Some(RunHalt::Error)
+        } else if self.max_steps.is_some_and(|max| steps >= max) {
+            Some(RunHalt::MaxSteps)
+        } else if self.pause_at_step == Some(steps) {
+            Some(RunHalt::PauseStep)
+        } else {
+            None
+        }
+    }
+}
+
+/// A node value tested by a [`FormatRule`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+pub enum RuleMetric {
+    /// Messages waiting on the node's incoming connections
+    #[default]
+    Backlog,
+    /// Messages a consumer is processing
+    InFlight,
+    /// Messages delivered to the node during the run
+    Delivered,
+    /// Script failures of the node during the run
+    Errors,
+    /// Messages a producer has emitted
+    Produced,
+    /// A consumer's processing time in steps
+    ProcessingTime,
+}
+
+impl RuleMetric {
+    /// Every metric, in the order shown in the rules panel.
+    pub const ALL: [RuleMetric; 6] = [
+        RuleMetric::Backlog,
+        RuleMetric::InFlight,
+        RuleMetric::Delivered,
+        RuleMetric::Errors,
+        RuleMetric::Produced,
+        RuleMetric::ProcessingTime,
+    ];
+
+    /// Name shown in the rules panel.
+    pub fn label(self) -> &'static str {
+        match self {
+            RuleMetric::Backlog => "backlog",
+            RuleMetric::InFlight => "in flight",
+            RuleMetric::Delivered => "delivered",
+            RuleMetric::Errors => "errors",
+            RuleMetric::Produced => "produced",
+            RuleMetric::ProcessingTime => "processing time",
+        }
+    }
+}
+
+/// How a [`FormatRule`] compares its metric with the threshold.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+pub enum RuleComparison {
+    /// Greater than the threshold
+    #[default]
+    Above,
+    /// Greater than or equal to the threshold
+    AtLeast,
+    /// Less than the threshold
+    Below,
+    /// Less than or equal to the threshold
+    AtMost,
+    /// Equal to the threshold
+    Equal,
+}
+
+impl RuleComparison {
+    /// Every comparison, in the order shown in the rules panel.
+    pub const ALL: [RuleComparison; 5] = [
+        RuleComparison::Above,
+        RuleComparison::AtLeast,
+        RuleComparison::Below,
+        RuleComparison::AtMost,
+        RuleComparison::Equal,
+    ];
+
+    /// Operator shown in the rules panel.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            RuleComparison::Above => ">",
+            RuleComparison::AtLeast => "≥",
+            RuleComparison::Below => "<",
+            RuleComparison::AtMost => "≤",
+            RuleComparison::Equal => "=",
+        }
+    }
+
+    /// Returns true if `value` compares with `threshold` this way.
+    pub fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            RuleComparison::Above => value > threshold,
+            RuleComparison::AtLeast => value >= threshold,
+            RuleComparison::Below => value < threshold,
+            RuleComparison::AtMost => value <= threshold,
+            RuleComparison::Equal => value == threshold,
+        }
+    }
+}
+
+/// Which nodes a [`FormatRule`] applies to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+pub enum RuleTarget {
+    /// Every node
+    #[default]
+    AllNodes,
+    /// Producer nodes
+    Producers,
+    /// Transformer nodes
+    Transformers,
+    /// Consumer nodes
+    Consumers,
+}
+
+impl RuleTarget {
+    /// Every target, in the order shown in the rules panel.
+    pub const ALL: [RuleTarget; 4] = [
+        RuleTarget::AllNodes,
+        RuleTarget::Producers,
+        RuleTarget::Transformers,
+        RuleTarget::Consumers,
+    ];
+
+    /// Name shown in the rules panel.
+    pub fn label(self) -> &'static str {
+        match self {
+            RuleTarget::AllNodes => "All nodes",
+            RuleTarget::Producers => "Producers",
+            RuleTarget::Transformers => "Transformers",
+            RuleTarget::Consumers => "Consumers",
+        }
+    }
+
+    /// Returns true if nodes of `node_type` are targeted.
+    pub fn matches(self, node_type: &NodeType) -> bool {
+        matches!(
+            (self, node_type),
+            (RuleTarget::AllNodes, _)
+                | (RuleTarget::Producers, NodeType::Producer { .. })
+                | (RuleTarget::Transformers, NodeType::Transformer { .. })
+                | (RuleTarget::Consumers, NodeType::Consumer { .. })
+        )
+    }
+}
+
+/// A conditional formatting rule: nodes whose metric passes the comparison
+/// are drawn with the rule's colors, e.g. consumers turn red while their
+/// backlog is above 50.
+///
+/// Rules are evaluated in order while the canvas is drawn; the fill and the
+/// border each come from the first matching rule that sets them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct FormatRule {
+    /// Name shown in the rules panel
+    pub name: String,
+    /// Whether the rule is evaluated
+    pub enabled: bool,
+    /// Which nodes the rule applies to
+    pub target: RuleTarget,
+    /// The value compared with the threshold
+    pub metric: RuleMetric,
+    /// How the metric is compared with the threshold
+    pub comparison: RuleComparison,
+    /// The value the metric is compared with
+    pub threshold: f64,
+    /// Fill color (RGB) of matching nodes, if the rule changes it
+    pub fill: Option<[u8; 3]>,
+    /// Border color (RGB) of matching nodes, if the rule changes it
+    pub border: Option<[u8; 3]>,
+}
+
+impl Default for FormatRule {
+    /// A new rule: consumers with a backlog above 50 turn red.
+    fn default() -> Self {
+        Self {
+            name: "High backlog".to_string(),
+            enabled: true,
+            target: RuleTarget::Consumers,
+            metric: RuleMetric::Backlog,
+            comparison: RuleComparison::Above,
+            threshold: 50.0,
+            fill: Some([230, 70, 70]),
+            border: None,
+        }
+    }
+}
+
+impl FormatRule {
+    /// Returns true if the rule is on, targets `node_type` and `value` of
+    /// its metric passes the comparison.
+    pub fn applies(&self, node_type: &NodeType, value: f64) -> bool {
+        self.enabled && self.target.matches(node_type) && self.comparison.holds(value, self.threshold)
+    }
+}
+
+/// Drawing mode for a group background shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum GroupDrawingMode {
     /// Draw an axis-aligned rounded rectangle around the members' bounding box.
+    #[default]
     Rectangle,
     /// Draw a shrink-wrapped polygon (convex hull) around members with padding.
     Polygon,
 }
 
-impl Default for GroupDrawingMode {
-    fn default() -> Self {
-        GroupDrawingMode::Rectangle
-    }
-}
-
 // --- Custom serde to map legacy values ---
 impl Serialize for GroupDrawingMode {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -148,8 +546,122 @@ impl<'de> Deserialize<'de> for GroupDrawingMode {
     }
 }
 
+impl JsonSchema for GroupDrawingMode {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "GroupDrawingMode".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "How a group is drawn around its members.",
+            "type": "string",
+            "enum": ["Rectangle", "Polygon"]
+        })
+    }
+}
+
+/// Retry behavior for a transformer whose script fails on a message.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total number of processing attempts per message, including the first one
+    pub max_attempts: u32,
+    /// Number of steps to wait before a failed message is retried
+    pub backoff_steps: u32,
+}
+
+impl Default for RetryPolicy {
+    /// A single attempt: failures stop the simulation as before.
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_steps: 0,
+        }
+    }
+}
+
+/// What happens to a message a node randomly fails on.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+pub enum FailureMode {
+    /// The message fails like a script error: it is counted as an error and
+    /// retried if the transformer retries
+    #[default]
+    Error,
+    /// The message is silently lost
+    Drop,
+}
+
+impl FailureMode {
+    /// All modes, in the order they are offered in the properties panel.
+    pub const ALL: [FailureMode; 2] = [FailureMode::Error, FailureMode::Drop];
+
+    /// Name shown in the properties panel.
+    pub fn label(self) -> &'static str {
+        match self {
+            FailureMode::Error => "Error",
+            FailureMode::Drop => "Drop",
+        }
+    }
+}
+
+/// Random failures of a transformer or consumer, for modeling unreliable
+/// steps without scripting the failures.
+///
+/// Each message delivered to the node fails with `probability`, drawn from
+/// the run's seeded generator (see [`RunConfig::seed`]).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default, JsonSchema)]
+#[serde(default)]
+pub struct FailureSettings {
+    /// Chance that a message fails, from 0 (never) to 1 (always)
+    pub probability: f64,
+    /// What happens to a failed message
+    pub mode: FailureMode,
+}
+
+impl FailureSettings {
+    /// Returns true if the node never fails.
+    pub fn is_off(&self) -> bool {
+        self.probability <= 0.0
+    }
+}
+
+/// Phase offset and random jitter of a producer's emissions, so producers on
+/// the same cycle don't all fire on the same steps.
+///
+/// Every emission is shifted by `phase` steps and then delayed by a random
+/// 0 to `jitter` steps, drawn from the run's seed (see [`RunConfig::seed`])
+/// so every run with the same seed emits on the same steps.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+#[serde(default)]
+pub struct ProducerTiming {
+    /// Steps every emission is shifted by
+    pub phase: u32,
+    /// Most steps an emission is delayed by at random
+    pub jitter: u32,
+}
+
+impl ProducerTiming {
+    /// Returns true if emissions follow the plain schedule.
+    pub fn is_off(&self) -> bool {
+        self.phase == 0 && self.jitter == 0
+    }
+
+    /// Random delay of emission number `emission` of `node`, from 0 to
+    /// `jitter` steps, the same in every run with `seed`.
+    pub fn delay(&self, seed: u64, node: NodeId, emission: u64) -> u64 {
+        if self.jitter == 0 {
+            return 0;
+        }
+        let (high, low) = node.as_u64_pair();
+        let mut rng = crate::datagen::SplitMix64(
+            seed ^ high ^ low.rotate_left(32) ^ emission.wrapping_mul(0xD6E8_FEB8_6659_FD93),
+        );
+        rng.next() % (u64::from(self.jitter) + 1)
+    }
+}
+
 /// Represents a single node in the flowchart.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct FlowchartNode {
     /// Unique identifier for this node
     pub id: NodeId,
@@ -161,6 +673,54 @@ pub struct FlowchartNode {
     pub node_type: NodeType,
     /// Current processing state of the node
     pub state: NodeState,
+    /// Swimlane the node is assigned to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lane: Option<LaneId>,
+    /// Plugin the node was created from, if any (see [`crate::plugins`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<PluginRef>,
+    /// Whether the force-directed layout keeps this node where it is
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// File the script (transformers) or message template (producers) is
+    /// read from when a run starts, relative to the document's folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<String>,
+    /// Random failures of messages delivered to the node (transformers and
+    /// consumers only)
+    #[serde(default, skip_serializing_if = "FailureSettings::is_off")]
+    pub failure: FailureSettings,
+    /// Phase and jitter of the node's emissions (producers only)
+    #[serde(default, skip_serializing_if = "ProducerTiming::is_off")]
+    pub timing: ProducerTiming,
+    /// Stacking order among nodes: higher nodes are drawn over lower ones
+    /// and are hit first, ties in id order
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub z_order: i32,
+    /// Whether the node sends a receipt on its outgoing connections for each
+    /// message it takes, instead of being the end of the line (consumers only)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tap: bool,
+}
+
+/// Links a node to the plugin behavior pack it was created from.
+///
+/// The node itself carries the plugin's script, so only what is needed to
+/// draw and connect it without the plugin installed is kept here.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct PluginRef {
+    /// Id of the plugin
+    pub id: String,
+    /// Icon text drawn on the node
+    #[serde(default)]
+    pub icon: String,
+    /// Whether the node can send messages on
+    #[serde(default = "default_plugin_outputs")]
+    pub outputs: bool,
+}
+
+fn default_plugin_outputs() -> bool {
+    true
 }
 
 impl FlowchartNode {
@@ -182,12 +742,20 @@ impl FlowchartNode {
             position,
             node_type,
             state: NodeState::Idle,
+            lane: None,
+            plugin: None,
+            pinned: false,
+            source_file: None,
+            failure: FailureSettings::default(),
+            timing: ProducerTiming::default(),
+            z_order: 0,
+            tap: false,
         }
     }
 }
 
 /// Represents a directional connection between two nodes.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Connection {
     /// ID of the source node
     pub from: NodeId,
@@ -195,6 +763,9 @@ pub struct Connection {
     pub to: NodeId,
     /// Messages currently traveling along this connection
     pub messages: Vec<Message>,
+    /// How the connection is drawn
+    #[serde(default, skip_serializing_if = "LineStyle::is_default")]
+    pub style: LineStyle,
 }
 
 impl Connection {
@@ -213,17 +784,122 @@ impl Connection {
             from,
             to,
             messages: Vec::new(),
+            style: LineStyle::default(),
         }
     }
 }
 
+/// Shape drawn where a connection meets its target.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+pub enum ArrowHead {
+    /// A filled triangle
+    #[default]
+    Filled,
+    /// Two strokes forming an open chevron
+    Open,
+    /// No arrowhead
+    None,
+}
+
+impl ArrowHead {
+    /// All arrowheads, in the order they are offered in the properties panel.
+    pub const ALL: [ArrowHead; 3] = [ArrowHead::Filled, ArrowHead::Open, ArrowHead::None];
+
+    /// Name shown in the properties panel.
+    pub fn label(self) -> &'static str {
+        match self {
+            ArrowHead::Filled => "Filled",
+            ArrowHead::Open => "Open",
+            ArrowHead::None => "None",
+        }
+    }
+}
+
+/// Dash pattern of a connection line.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+pub enum LineDash {
+    /// A continuous line
+    #[default]
+    Solid,
+    /// Long dashes
+    Dashed,
+    /// Dots
+    Dotted,
+}
+
+impl LineDash {
+    /// All patterns, in the order they are offered in the properties panel.
+    pub const ALL: [LineDash; 3] = [LineDash::Solid, LineDash::Dashed, LineDash::Dotted];
+
+    /// Name shown in the properties panel.
+    pub fn label(self) -> &'static str {
+        match self {
+            LineDash::Solid => "Solid",
+            LineDash::Dashed => "Dashed",
+            LineDash::Dotted => "Dotted",
+        }
+    }
+}
+
+/// How a connection is drawn, so different kinds of edges, such as
+/// retries or control flow, can be told apart at a glance.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct LineStyle {
+    /// Shape at the target end
+    pub arrow: ArrowHead,
+    /// Line thickness in points at 100% zoom
+    pub width: f32,
+    /// Dash pattern
+    pub dash: LineDash,
+    /// Line and arrowhead color (RGB), the theme's connection color if unset
+    pub color: Option<[u8; 3]>,
+}
+
+impl Default for LineStyle {
+    /// A solid dark gray line with a filled arrowhead, as connections were
+    /// always drawn.
+    fn default() -> Self {
+        Self {
+            arrow: ArrowHead::Filled,
+            width: 2.0,
+            dash: LineDash::Solid,
+            color: None,
+        }
+    }
+}
+
+impl LineStyle {
+    /// Returns true if the connection is drawn the default way.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// Represents a message flowing through the flowchart system.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Message {
     /// Unique identifier for this message
     pub id: MessageId,
-    /// The data payload of the message
-    pub data: serde_json::Value,
+    /// The data payload of the message, shared between identical messages
+    /// (see [`crate::payloads::PayloadPool`]); use `Arc::make_mut` to edit it
+    pub data: Arc<serde_json::Value>,
+    /// Number of failed processing attempts so far
+    #[serde(default)]
+    pub attempts: u32,
+    /// Step before which the message is held on its connection, if waiting to be retried
+    #[serde(default)]
+    pub retry_at: Option<u64>,
+    /// Delivery priority; higher values are delivered first, equal values in arrival order
+    #[serde(default)]
+    pub priority: i32,
+    /// Trace the message belongs to, assigned when it is produced and kept by transformers
+    #[serde(default)]
+    pub trace_id: Option<TraceId>,
+    /// Step on which a producer created the message (or the message it was
+    /// transformed from), used for end-to-end latency
+    #[serde(default)]
+    pub created_step: Option<u64>,
 }
 
 impl Message {
@@ -239,13 +915,18 @@ impl Message {
     pub fn new(data: serde_json::Value) -> Self {
         Self {
             id: Uuid::new_v4(),
-            data,
+            data: Arc::new(data),
+            attempts: 0,
+            retry_at: None,
+            priority: 0,
+            trace_id: None,
+            created_step: None,
         }
     }
 }
 
 /// The main flowchart structure containing all nodes, connections, and simulation state.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Flowchart {
     /// Map of all nodes in the flowchart, indexed by their ID
     pub nodes: HashMap<NodeId, FlowchartNode>,
@@ -254,6 +935,24 @@ pub struct Flowchart {
     /// Map of groups by id
     #[serde(default)]
     pub groups: HashMap<GroupId, Group>,
+    /// Swimlane mode and lane definitions
+    #[serde(default)]
+    pub swimlanes: Swimlanes,
+    /// Seed, stop conditions and measurement window of runs
+    #[serde(default, alias = "run_settings")]
+    pub run_config: RunConfig,
+    /// Conditional formatting rules, in priority order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub format_rules: Vec<FormatRule>,
+    /// Named selections, for re-selecting the same nodes later
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub saved_selections: Vec<SavedSelection>,
+    /// Named values node properties, templates and scripts refer to
+    #[serde(default, skip_serializing_if = "crate::parameters::Parameters::is_empty")]
+    pub parameters: crate::parameters::Parameters,
+    /// Edits made to the document, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edit_log: Vec<crate::audit::EditRecord>,
     /// Current state of the simulation
     pub simulation_state: SimulationState,
     /// Current simulation step counter
@@ -267,6 +966,12 @@ impl Default for Flowchart {
             nodes: HashMap::new(),
             connections: Vec::new(),
             groups: HashMap::new(),
+            swimlanes: Swimlanes::default(),
+            run_config: RunConfig::default(),
+            format_rules: Vec::new(),
+            saved_selections: Vec::new(),
+            parameters: Default::default(),
+            edit_log: Vec::new(),
             simulation_state: SimulationState::Stopped,
             current_step: 0,
         }
@@ -289,6 +994,32 @@ impl Flowchart {
         serde_json::from_str(json)
     }
 
+    /// Returns the simulation to its starting point: step 0, no messages on
+    /// connections, producer counters and consumer work cleared, and
+    /// transformer globals back at their initial values.
+    pub fn reset_simulation(&mut self) {
+        self.simulation_state = SimulationState::Stopped;
+        self.current_step = 0;
+        for connection in &mut self.connections {
+            connection.messages.clear();
+        }
+        for node in self.nodes.values_mut() {
+            node.state = NodeState::Idle;
+            match &mut node.node_type {
+                NodeType::Producer { messages_produced, .. } => {
+                    *messages_produced = 0;
+                }
+                NodeType::Consumer { in_flight, .. } => {
+                    in_flight.clear();
+                }
+                NodeType::Transformer { globals, initial_globals, .. } => {
+                    *globals = initial_globals.clone();
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Adds a node to the flowchart.
     ///
     /// # Arguments
@@ -304,6 +1035,22 @@ impl Flowchart {
         id
     }
 
+    /// The nodes from the bottom of the stack to the top, the order they
+    /// are drawn in.
+    pub fn nodes_by_z_order(&self) -> Vec<&FlowchartNode> {
+        let mut nodes: Vec<&FlowchartNode> = self.nodes.values().collect();
+        nodes.sort_by_key(|node| (node.z_order, node.id));
+        nodes
+    }
+
+    /// The groups from the bottom of the stack to the top, the order they
+    /// are drawn in.
+    pub fn groups_by_z_order(&self) -> Vec<&Group> {
+        let mut groups: Vec<&Group> = self.groups.values().collect();
+        groups.sort_by_key(|group| (group.z_order, group.id));
+        groups
+    }
+
     /// Adds a connection between two existing nodes.
     ///
     /// # Arguments
@@ -356,6 +1103,343 @@ impl Flowchart {
         }
         removed
     }
+
+    /// Returns the distinct nodes that `node_id` has outgoing connections to,
+    /// in connection order.
+    pub fn successors(&self, node_id: &NodeId) -> Vec<NodeId> {
+        let mut out: Vec<NodeId> = Vec::new();
+        for conn in &self.connections {
+            if conn.from == *node_id && !out.contains(&conn.to) {
+                out.push(conn.to);
+            }
+        }
+        out
+    }
+
+    /// Returns the distinct nodes that have outgoing connections to `node_id`,
+    /// in connection order.
+    pub fn predecessors(&self, node_id: &NodeId) -> Vec<NodeId> {
+        let mut out: Vec<NodeId> = Vec::new();
+        for conn in &self.connections {
+            if conn.to == *node_id && !out.contains(&conn.from) {
+                out.push(conn.from);
+            }
+        }
+        out
+    }
+
+    /// Builds a successor list for every node, ignoring connections whose
+    /// endpoints are missing.
+    fn adjacency(&self) -> HashMap<NodeId, Vec<NodeId>> {
+        let mut adj: HashMap<NodeId, Vec<NodeId>> =
+            self.nodes.keys().map(|id| (*id, Vec::new())).collect();
+        for conn in &self.connections {
+            if !self.nodes.contains_key(&conn.to) {
+                continue;
+            }
+            if let Some(list) = adj.get_mut(&conn.from) {
+                if !list.contains(&conn.to) {
+                    list.push(conn.to);
+                }
+            }
+        }
+        adj
+    }
+
+    /// Orders all nodes so that every connection points from an earlier node to a later one.
+    ///
+    /// Ties are broken by node id so the result is deterministic.
+    ///
+    /// # Returns
+    ///
+    /// The ordered node ids, or an error message if the graph contains a cycle.
+    pub fn topological_order(&self) -> Result<Vec<NodeId>, String> {
+        let adj = self.adjacency();
+        let mut in_degree: HashMap<NodeId, usize> = adj.keys().map(|id| (*id, 0)).collect();
+        for targets in adj.values() {
+            for to in targets {
+                *in_degree.entry(*to).or_insert(0) += 1;
+            }
+        }
+
+        let mut ready: BTreeSet<NodeId> = in_degree
+            .iter()
+            .filter(|(_, d)| **d == 0)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(id) = ready.pop_first() {
+            order.push(id);
+            for to in &adj[&id] {
+                let d = in_degree.get_mut(to).expect("in-degree tracked for every node");
+                *d -= 1;
+                if *d == 0 {
+                    ready.insert(*to);
+                }
+            }
+        }
+
+        if order.len() == self.nodes.len() {
+            Ok(order)
+        } else {
+            Err("Flowchart contains a cycle".to_string())
+        }
+    }
+
+    /// Finds the strongly connected components of the graph.
+    ///
+    /// Each component is sorted by node id, and components are returned in
+    /// topological order of the condensed graph (sources first). A node that is
+    /// not part of any cycle forms a component on its own.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        // Iterative Tarjan's algorithm
+        let adj = self.adjacency();
+        let mut ids: Vec<NodeId> = adj.keys().copied().collect();
+        ids.sort();
+
+        let mut index_of: HashMap<NodeId, usize> = HashMap::new();
+        let mut low: HashMap<NodeId, usize> = HashMap::new();
+        let mut on_stack: HashMap<NodeId, bool> = HashMap::new();
+        let mut stack: Vec<NodeId> = Vec::new();
+        let mut components: Vec<Vec<NodeId>> = Vec::new();
+        let mut next_index = 0usize;
+
+        for root in ids {
+            if index_of.contains_key(&root) {
+                continue;
+            }
+            // (node, position of the next successor to visit)
+            let mut work: Vec<(NodeId, usize)> = vec![(root, 0)];
+            while let Some((node, pos)) = work.pop() {
+                if pos == 0 {
+                    index_of.insert(node, next_index);
+                    low.insert(node, next_index);
+                    next_index += 1;
+                    stack.push(node);
+                    on_stack.insert(node, true);
+                }
+                let succ = &adj[&node];
+                if pos < succ.len() {
+                    work.push((node, pos + 1));
+                    let to = succ[pos];
+                    if !index_of.contains_key(&to) {
+                        work.push((to, 0));
+                    } else if on_stack.get(&to).copied().unwrap_or(false) {
+                        let l = low[&node].min(index_of[&to]);
+                        low.insert(node, l);
+                    }
+                    continue;
+                }
+                // All successors visited; propagate low-link to the parent
+                if let Some((parent, _)) = work.last() {
+                    let l = low[parent].min(low[&node]);
+                    low.insert(*parent, l);
+                }
+                if low[&node] == index_of[&node] {
+                    let mut comp = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack.insert(member, false);
+                        comp.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    comp.sort();
+                    components.push(comp);
+                }
+            }
+        }
+
+        // Tarjan emits components in reverse topological order
+        components.reverse();
+        components
+    }
+
+    /// Assigns each node to a layer such that every connection goes from a lower
+    /// layer to a higher one, using longest-path layering.
+    ///
+    /// Source nodes are on layer 0. Nodes in the same cycle share a layer, and
+    /// connections within a cycle are ignored.
+    pub fn layers(&self) -> HashMap<NodeId, usize> {
+        let components = self.strongly_connected_components();
+        let mut component_of: HashMap<NodeId, usize> = HashMap::new();
+        for (i, comp) in components.iter().enumerate() {
+            for id in comp {
+                component_of.insert(*id, i);
+            }
+        }
+
+        // Components are already topologically ordered, so one forward pass suffices
+        let adj = self.adjacency();
+        let mut comp_layer = vec![0usize; components.len()];
+        for (i, comp) in components.iter().enumerate() {
+            for id in comp {
+                for to in &adj[id] {
+                    let j = component_of[to];
+                    if j != i {
+                        comp_layer[j] = comp_layer[j].max(comp_layer[i] + 1);
+                    }
+                }
+            }
+        }
+
+        component_of
+            .into_iter()
+            .map(|(id, comp)| (id, comp_layer[comp]))
+            .collect()
+    }
+
+    /// Returns true if any connection path leads from a node back to itself.
+    pub fn has_cycle(&self) -> bool {
+        self.topological_order().is_err()
+    }
+
+    /// Copies the given nodes into a new standalone flowchart.
+    ///
+    /// Connections are kept only where both ends are part of the subset, and
+    /// groups containing any of the nodes are kept with their membership
+    /// trimmed to the subset. Every node and group receives a fresh ID so the
+    /// result can be opened or merged alongside the original. Runtime state is
+    /// dropped: nodes start idle and connections carry no messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_ids` - The nodes to copy; unknown IDs are ignored
+    ///
+    /// # Returns
+    ///
+    /// A new stopped `Flowchart` containing copies of the selected elements.
+    pub fn extract_subset(&self, node_ids: &[NodeId]) -> Flowchart {
+        let mut result = Flowchart::new();
+        let mut id_map: HashMap<NodeId, NodeId> = HashMap::new();
+
+        for id in node_ids {
+            if id_map.contains_key(id) {
+                continue;
+            }
+            if let Some(node) = self.nodes.get(id) {
+                let mut copy = node.clone();
+                copy.id = Uuid::new_v4();
+                copy.state = NodeState::Idle;
+                id_map.insert(*id, copy.id);
+                result.nodes.insert(copy.id, copy);
+            }
+        }
+
+        for conn in &self.connections {
+            if let (Some(from), Some(to)) = (id_map.get(&conn.from), id_map.get(&conn.to)) {
+                result.connections.push(Connection::new(*from, *to));
+            }
+        }
+
+        for group in self.groups.values() {
+            let members: Vec<NodeId> = group
+                .members
+                .iter()
+                .filter_map(|m| id_map.get(m).copied())
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            let copy = Group {
+                id: Uuid::new_v4(),
+                name: group.name.clone(),
+                members,
+                drawing: group.drawing,
+                z_order: group.z_order,
+            };
+            result.groups.insert(copy.id, copy);
+        }
+
+        result
+    }
+
+    /// Inserts a copy of another flowchart's contents into this one.
+    ///
+    /// All nodes and groups receive fresh IDs and the nodes are shifted by
+    /// `offset`. Nodes whose names collide with existing ones are renamed by
+    /// appending a number, e.g. `"Producer (2)"`, and transformer output
+    /// selections are updated to follow the renames.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The flowchart to copy from
+    /// * `offset` - World-space offset applied to every inserted node
+    ///
+    /// # Returns
+    ///
+    /// A `MergeResult` listing everything that was added.
+    pub fn merge(&mut self, other: &Flowchart, offset: (f32, f32)) -> MergeResult {
+        let all_ids: Vec<NodeId> = other.nodes.keys().copied().collect();
+        let incoming = other.extract_subset(&all_ids);
+        let mut result = MergeResult::default();
+
+        let mut nodes: Vec<FlowchartNode> = incoming.nodes.into_values().collect();
+        nodes.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+
+        let mut taken: HashSet<String> = self.nodes.values().map(|n| n.name.clone()).collect();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut renames: HashMap<String, String> = HashMap::new();
+        for node in &mut nodes {
+            let first_of_name = seen.insert(node.name.clone());
+            if taken.contains(&node.name) {
+                let new_name = (2..)
+                    .map(|n| format!("{} ({})", node.name, n))
+                    .find(|candidate| !taken.contains(candidate))
+                    .expect("unbounded range always yields a free name");
+                if first_of_name {
+                    renames.insert(node.name.clone(), new_name.clone());
+                }
+                result.renamed.push((node.name.clone(), new_name.clone()));
+                node.name = new_name;
+            }
+            taken.insert(node.name.clone());
+            node.position.0 += offset.0;
+            node.position.1 += offset.1;
+        }
+
+        for mut node in nodes {
+            if let NodeType::Transformer {
+                selected_outputs: Some(outputs),
+                ..
+            } = &mut node.node_type
+            {
+                for name in outputs.iter_mut() {
+                    if let Some(new_name) = renames.get(name) {
+                        *name = new_name.clone();
+                    }
+                }
+            }
+            result.node_ids.push(self.add_node(node));
+        }
+
+        for conn in incoming.connections {
+            result.connections.push((conn.from, conn.to));
+            self.connections.push(conn);
+        }
+
+        let mut groups: Vec<Group> = incoming.groups.into_values().collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        for group in groups {
+            result.group_ids.push(group.id);
+            self.groups.insert(group.id, group);
+        }
+
+        result
+    }
+}
+
+/// Everything added to a flowchart by [`Flowchart::merge`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeResult {
+    /// IDs of the inserted nodes, in insertion order
+    pub node_ids: Vec<NodeId>,
+    /// Inserted connections as `(from, to)` pairs
+    pub connections: Vec<(NodeId, NodeId)>,
+    /// IDs of the inserted groups
+    pub group_ids: Vec<GroupId>,
+    /// Nodes renamed to avoid collisions, as `(original, new)` name pairs
+    pub renamed: Vec<(String, String)>,
 }
 
 #[cfg(test)]
@@ -370,6 +1454,10 @@ mod tests {
             (100.0, 200.0),
             NodeType::Consumer {
                 consumption_rate: 5,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
 
@@ -420,6 +1508,7 @@ mod tests {
                 selected_outputs: None,
                 globals: Default::default(),
                 initial_globals: Default::default(),
+                retry: Default::default(),
             },
         );
 
@@ -439,7 +1528,7 @@ mod tests {
         let data = json!({"key": "value", "number": 123});
         let message = Message::new(data.clone());
 
-        assert_eq!(message.data, data);
+        assert_eq!(*message.data, data);
         assert!(!message.id.is_nil());
     }
 
@@ -472,6 +1561,10 @@ mod tests {
             (0.0, 0.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
         let node_id = node.id;
@@ -503,6 +1596,10 @@ mod tests {
             (100.0, 0.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
 
@@ -532,6 +1629,10 @@ mod tests {
             (100.0, 0.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
 
@@ -555,6 +1656,10 @@ mod tests {
             (0.0, 0.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
         let id = flowchart.add_node(node);
@@ -601,6 +1706,10 @@ mod tests {
             (0.0, 0.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
         let node_id = flowchart.add_node(node);
@@ -641,6 +1750,10 @@ mod tests {
             (100.0, 0.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
         let node3 = FlowchartNode::new(
@@ -648,6 +1761,10 @@ mod tests {
             (200.0, 0.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
 
@@ -677,6 +1794,10 @@ mod tests {
             (50.0, 100.0),
             NodeType::Consumer {
                 consumption_rate: 5,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
         flowchart.add_node(node);
@@ -698,6 +1819,10 @@ mod tests {
             (50.0, 100.0),
             NodeType::Consumer {
                 consumption_rate: 5,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
         let node_id = original.add_node(node);
@@ -733,6 +1858,10 @@ mod tests {
             (200.0, 100.0),
             NodeType::Consumer {
                 consumption_rate: 2,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
 
@@ -756,6 +1885,10 @@ mod tests {
             (0.0, 0.0),
             NodeType::Consumer {
                 consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
 
@@ -804,4 +1937,540 @@ mod tests {
 
         assert_eq!(connection.messages.len(), 2);
     }
+
+    fn graph_node(flowchart: &mut Flowchart, name: &str) -> NodeId {
+        flowchart.add_node(FlowchartNode::new(
+            name.to_string(),
+            (0.0, 0.0),
+            NodeType::Consumer {
+                consumption_rate: 1,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
+            },
+        ))
+    }
+
+    #[test]
+    fn test_successors_and_predecessors() {
+        let mut flowchart = Flowchart::new();
+        let a = graph_node(&mut flowchart, "A");
+        let b = graph_node(&mut flowchart, "B");
+        let c = graph_node(&mut flowchart, "C");
+        flowchart.add_connection(a, b).unwrap();
+        flowchart.add_connection(a, c).unwrap();
+        flowchart.add_connection(a, b).unwrap();
+        flowchart.add_connection(c, b).unwrap();
+
+        assert_eq!(flowchart.successors(&a), vec![b, c]);
+        assert_eq!(flowchart.predecessors(&b), vec![a, c]);
+        assert!(flowchart.successors(&b).is_empty());
+        assert!(flowchart.predecessors(&a).is_empty());
+    }
+
+    #[test]
+    fn test_topological_order_respects_connections() {
+        let mut flowchart = Flowchart::new();
+        let a = graph_node(&mut flowchart, "A");
+        let b = graph_node(&mut flowchart, "B");
+        let c = graph_node(&mut flowchart, "C");
+        let d = graph_node(&mut flowchart, "D");
+        flowchart.add_connection(a, b).unwrap();
+        flowchart.add_connection(b, d).unwrap();
+        flowchart.add_connection(a, c).unwrap();
+        flowchart.add_connection(c, d).unwrap();
+
+        let order = flowchart.topological_order().unwrap();
+        assert_eq!(order.len(), 4);
+        let pos = |id: NodeId| order.iter().position(|n| *n == id).unwrap();
+        for conn in &flowchart.connections {
+            assert!(pos(conn.from) < pos(conn.to));
+        }
+        assert!(!flowchart.has_cycle());
+    }
+
+    #[test]
+    fn test_topological_order_rejects_cycles() {
+        let mut flowchart = Flowchart::new();
+        let a = graph_node(&mut flowchart, "A");
+        let b = graph_node(&mut flowchart, "B");
+        flowchart.add_connection(a, b).unwrap();
+        flowchart.add_connection(b, a).unwrap();
+
+        assert!(flowchart.topological_order().is_err());
+        assert!(flowchart.has_cycle());
+    }
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let mut flowchart = Flowchart::new();
+        let src = graph_node(&mut flowchart, "Source");
+        let a = graph_node(&mut flowchart, "A");
+        let b = graph_node(&mut flowchart, "B");
+        let c = graph_node(&mut flowchart, "C");
+        let sink = graph_node(&mut flowchart, "Sink");
+        flowchart.add_connection(src, a).unwrap();
+        flowchart.add_connection(a, b).unwrap();
+        flowchart.add_connection(b, c).unwrap();
+        flowchart.add_connection(c, a).unwrap();
+        flowchart.add_connection(c, sink).unwrap();
+
+        let components = flowchart.strongly_connected_components();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0], vec![src]);
+        let mut cycle = vec![a, b, c];
+        cycle.sort();
+        assert_eq!(components[1], cycle);
+        assert_eq!(components[2], vec![sink]);
+    }
+
+    #[test]
+    fn test_extract_subset_remaps_ids_and_keeps_internal_structure() {
+        let mut flowchart = Flowchart::new();
+        let a = graph_node(&mut flowchart, "A");
+        let b = graph_node(&mut flowchart, "B");
+        let c = graph_node(&mut flowchart, "C");
+        flowchart.add_connection(a, b).unwrap();
+        flowchart.add_connection(b, c).unwrap();
+        flowchart.connections[0].messages.push(Message::new(json!({})));
+        let group = Group {
+            id: Uuid::new_v4(),
+            name: "Pair".into(),
+            members: vec![b, c],
+            drawing: GroupDrawingMode::Polygon,
+            z_order: 0,
+        };
+        flowchart.groups.insert(group.id, group.clone());
+
+        let subset = flowchart.extract_subset(&[a, b]);
+        assert_eq!(subset.nodes.len(), 2);
+        assert!(subset.nodes.keys().all(|id| !flowchart.nodes.contains_key(id)));
+
+        let find = |name: &str| subset.nodes.values().find(|n| n.name == name).unwrap().id;
+        let (new_a, new_b) = (find("A"), find("B"));
+        assert_eq!(subset.connections.len(), 1);
+        assert_eq!((subset.connections[0].from, subset.connections[0].to), (new_a, new_b));
+        assert!(subset.connections[0].messages.is_empty());
+
+        assert_eq!(subset.groups.len(), 1);
+        let copied = subset.groups.values().next().unwrap();
+        assert_ne!(copied.id, group.id);
+        assert_eq!(copied.members, vec![new_b]);
+        assert_eq!(copied.drawing, GroupDrawingMode::Polygon);
+    }
+
+    #[test]
+    fn test_merge_offsets_renames_and_regenerates_ids() {
+        let mut target = Flowchart::new();
+        graph_node(&mut target, "A");
+
+        let mut other = Flowchart::new();
+        let a = graph_node(&mut other, "A");
+        let t = other.add_node(FlowchartNode::new(
+            "T".into(),
+            (10.0, 20.0),
+            NodeType::Transformer {
+                script: String::new(),
+                selected_outputs: Some(vec!["A".into()]),
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: Default::default(),
+            },
+        ));
+        other.add_connection(t, a).unwrap();
+
+        let result = target.merge(&other, (100.0, -50.0));
+        assert_eq!(target.nodes.len(), 3);
+        assert_eq!(result.node_ids.len(), 2);
+        assert!(!target.nodes.contains_key(&a) && !target.nodes.contains_key(&t));
+        assert_eq!(result.renamed, vec![("A".to_string(), "A (2)".to_string())]);
+
+        let merged_t = target.nodes.values().find(|n| n.name == "T").unwrap();
+        assert_eq!(merged_t.position, (110.0, -30.0));
+        match &merged_t.node_type {
+            NodeType::Transformer { selected_outputs, .. } => {
+                assert_eq!(selected_outputs.as_deref(), Some(&["A (2)".to_string()][..]));
+            }
+            _ => panic!("expected transformer"),
+        }
+
+        let merged_a = target.nodes.values().find(|n| n.name == "A (2)").unwrap();
+        assert_eq!(result.connections, vec![(merged_t.id, merged_a.id)]);
+        assert_eq!(target.connections.len(), 1);
+    }
+
+    #[test]
+    fn test_reference_node_roundtrip() {
+        let mut flowchart = Flowchart::new();
+        let id = flowchart.add_node(FlowchartNode::new(
+            "Billing".into(),
+            (0.0, 0.0),
+            NodeType::Reference {
+                path: "billing.json".into(),
+            },
+        ));
+
+        let restored = Flowchart::from_json(&flowchart.to_json().unwrap()).unwrap();
+        match &restored.nodes[&id].node_type {
+            NodeType::Reference { path } => assert_eq!(path, "billing.json"),
+            other => panic!("expected reference node, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_format_rule_applies_to_targeted_nodes_past_the_threshold() {
+        let consumer = NodeType::Consumer {
+            consumption_rate: 1,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        };
+        let producer = NodeType::Producer {
+            message_template: serde_json::json!({}),
+            start_step: 0,
+            messages_per_cycle: 1,
+            steps_between_cycles: 1,
+            messages_produced: 0,
+        };
+        let mut rule = FormatRule::default();
+        assert!(rule.applies(&consumer, 51.0));
+        assert!(!rule.applies(&consumer, 50.0));
+        assert!(!rule.applies(&producer, 51.0));
+
+        rule.comparison = RuleComparison::AtLeast;
+        rule.target = RuleTarget::AllNodes;
+        assert!(rule.applies(&producer, 50.0));
+        rule.enabled = false;
+        assert!(!rule.applies(&producer, 50.0));
+
+        // Rules saved by older versions miss fields and get the defaults
+        let partial: FormatRule = serde_json::from_str(r#"{"metric": "Errors", "threshold": 0}"#).unwrap();
+        assert_eq!(partial.metric, RuleMetric::Errors);
+        assert!(partial.enabled);
+    }
+
+    #[test]
+    fn test_layers_use_longest_path_and_collapse_cycles() {
+        let mut flowchart = Flowchart::new();
+        let a = graph_node(&mut flowchart, "A");
+        let b = graph_node(&mut flowchart, "B");
+        let c = graph_node(&mut flowchart, "C");
+        let d = graph_node(&mut flowchart, "D");
+        let e = graph_node(&mut flowchart, "E");
+        flowchart.add_connection(a, b).unwrap();
+        flowchart.add_connection(b, c).unwrap();
+        flowchart.add_connection(a, c).unwrap();
+        // C <-> D form a cycle feeding E
+        flowchart.add_connection(c, d).unwrap();
+        flowchart.add_connection(d, c).unwrap();
+        flowchart.add_connection(d, e).unwrap();
+
+        let layers = flowchart.layers();
+        assert_eq!(layers[&a], 0);
+        assert_eq!(layers[&b], 1);
+        assert_eq!(layers[&c], 2);
+        assert_eq!(layers[&d], 2);
+        assert_eq!(layers[&e], 3);
+    }
+}
+
+/// Generators of arbitrary documents, and round-trip tests guarding the file
+/// format against changes that lose or alter data.
+#[cfg(test)]
+mod roundtrip_tests {
+    use super::*;
+    use proptest::collection::{btree_map, vec};
+    use proptest::option;
+    use proptest::prelude::*;
+    use serde_json::Value;
+
+    fn uuid() -> impl Strategy<Value = Uuid> {
+        any::<u128>().prop_map(Uuid::from_u128)
+    }
+
+    fn finite_f32() -> impl Strategy<Value = f32> {
+        prop::num::f32::NORMAL | prop::num::f32::SUBNORMAL | prop::num::f32::ZERO
+    }
+
+    fn json_value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            any::<u64>().prop_map(Value::from),
+            (prop::num::f64::NORMAL | prop::num::f64::SUBNORMAL | prop::num::f64::ZERO).prop_map(Value::from),
+            ".*".prop_map(Value::from),
+        ];
+        leaf.prop_recursive(3, 24, 4, |inner| {
+            prop_oneof![
+                vec(inner.clone(), 0..4).prop_map(Value::Array),
+                json_map(inner).prop_map(Value::Object),
+            ]
+        })
+    }
+
+    fn json_map(values: impl Strategy<Value = Value>) -> impl Strategy<Value = serde_json::Map<String, Value>> {
+        btree_map(".*", values, 0..4).prop_map(|entries| entries.into_iter().collect())
+    }
+
+    fn node_type() -> impl Strategy<Value = NodeType> {
+        prop_oneof![
+            (json_value(), any::<u64>(), any::<u32>(), any::<u32>(), any::<u32>()).prop_map(
+                |(message_template, start_step, messages_per_cycle, steps_between_cycles, messages_produced)| {
+                    NodeType::Producer {
+                        message_template,
+                        start_step,
+                        messages_per_cycle,
+                        steps_between_cycles,
+                        messages_produced,
+                    }
+                }
+            ),
+            (any::<u32>(), any::<u32>(), any::<u32>(), vec(any::<u32>(), 0..4), ".*").prop_map(
+                |(consumption_rate, processing_time, max_concurrency, in_flight, accept_filter)| {
+                    NodeType::Consumer {
+                        consumption_rate,
+                        processing_time,
+                        max_concurrency,
+                        in_flight,
+                        accept_filter,
+                    }
+                }
+            ),
+            (
+                ".*",
+                option::of(vec(".*", 0..3)),
+                json_map(json_value()),
+                json_map(json_value()),
+                any::<(u32, u32)>(),
+            )
+                .prop_map(|(script, selected_outputs, globals, initial_globals, (max_attempts, backoff_steps))| {
+                    NodeType::Transformer {
+                        script,
+                        selected_outputs,
+                        globals,
+                        initial_globals,
+                        retry: RetryPolicy { max_attempts, backoff_steps },
+                    }
+                }),
+            ".*".prop_map(|path| NodeType::Reference { path }),
+        ]
+    }
+
+    fn node(lanes: Vec<LaneId>) -> impl Strategy<Value = FlowchartNode> {
+        let lane = if lanes.is_empty() {
+            Just(None).boxed()
+        } else {
+            option::of(prop::sample::select(lanes)).boxed()
+        };
+        let plugin = option::of((".*", ".*", any::<bool>()).prop_map(|(id, icon, outputs)| PluginRef { id, icon, outputs }));
+        (
+            uuid(),
+            ".*",
+            (finite_f32(), finite_f32()),
+            node_type(),
+            prop_oneof![Just(NodeState::Idle), Just(NodeState::Processing)],
+            lane,
+            plugin,
+            any::<(bool, bool)>(),
+            option::of("[a-z/]{1,12}\\.js"),
+            failure(),
+            any::<(u32, u32)>().prop_map(|(phase, jitter)| ProducerTiming { phase, jitter }),
+            -3i32..3,
+        )
+            .prop_map(|(id, name, position, node_type, state, lane, plugin, (pinned, tap), source_file, failure, timing, z_order)| FlowchartNode {
+                id,
+                name,
+                position,
+                node_type,
+                state,
+                lane,
+                plugin,
+                pinned,
+                source_file,
+                failure,
+                timing,
+                z_order,
+                tap,
+            })
+    }
+
+    fn failure() -> impl Strategy<Value = FailureSettings> {
+        // Whole percentages, so the JSON text round-trips exactly; settings
+        // that are off aren't saved, so they keep the default mode
+        let probability = (0u32..=100).prop_map(|p| f64::from(p) / 100.0);
+        (probability, prop::sample::select(FailureMode::ALL.to_vec())).prop_map(|(probability, mode)| FailureSettings {
+            probability,
+            mode: if probability > 0.0 { mode } else { FailureMode::default() },
+        })
+    }
+
+    fn message() -> impl Strategy<Value = Message> {
+        (
+            uuid(),
+            json_value(),
+            any::<u32>(),
+            option::of(any::<u64>()),
+            any::<i32>(),
+            option::of(uuid()),
+            option::of(any::<u64>()),
+        )
+            .prop_map(|(id, data, attempts, retry_at, priority, trace_id, created_step)| Message {
+                id,
+                data: Arc::new(data),
+                attempts,
+                retry_at,
+                priority,
+                trace_id,
+                created_step,
+            })
+    }
+
+    fn connection(nodes: Vec<NodeId>) -> impl Strategy<Value = Connection> {
+        let endpoint = prop::sample::select(nodes);
+        let arrow = prop::sample::select(ArrowHead::ALL.to_vec());
+        let dash = prop::sample::select(LineDash::ALL.to_vec());
+        let style = (arrow, 1u8..8, dash, option::of(any::<[u8; 3]>()))
+            .prop_map(|(arrow, width, dash, color)| LineStyle { arrow, width: f32::from(width), dash, color });
+        (endpoint.clone(), endpoint, vec(message(), 0..3), style)
+            .prop_map(|(from, to, messages, style)| Connection { from, to, messages, style })
+    }
+
+    fn group(nodes: Vec<NodeId>) -> impl Strategy<Value = Group> {
+        let drawing = prop_oneof![Just(GroupDrawingMode::Rectangle), Just(GroupDrawingMode::Polygon)];
+        (uuid(), ".*", prop::sample::subsequence(nodes.clone(), 0..=nodes.len()), drawing, -3i32..3)
+            .prop_map(|(id, name, members, drawing, z_order)| Group { id, name, members, drawing, z_order })
+    }
+
+    fn format_rule() -> impl Strategy<Value = FormatRule> {
+        let target = prop::sample::select(RuleTarget::ALL.to_vec());
+        let metric = prop::sample::select(RuleMetric::ALL.to_vec());
+        let comparison = prop::sample::select(RuleComparison::ALL.to_vec());
+        // Whole thresholds, so the JSON text round-trips exactly
+        let threshold = (-1000i32..1000).prop_map(f64::from);
+        (".*", any::<bool>(), target, metric, comparison, threshold, option::of(any::<[u8; 3]>()), option::of(any::<[u8; 3]>()))
+            .prop_map(|(name, enabled, target, metric, comparison, threshold, fill, border)| FormatRule {
+                name,
+                enabled,
+                target,
+                metric,
+                comparison,
+                threshold,
+                fill,
+                border,
+            })
+    }
+
+    fn flowchart() -> impl Strategy<Value = Flowchart> {
+        let lanes = vec((uuid(), ".*", finite_f32()).prop_map(|(id, name, height)| Lane { id, name, height }), 0..3);
+        (lanes, any::<bool>())
+            .prop_flat_map(|(lanes, enabled)| {
+                let ids = lanes.iter().map(|l| l.id).collect::<Vec<_>>();
+                (Just(Swimlanes { enabled, lanes }), vec(node(ids), 0..6))
+            })
+            .prop_flat_map(|(swimlanes, nodes)| {
+                let ids: Vec<NodeId> = nodes.iter().map(|n| n.id).collect();
+                let (connections, groups) = if ids.is_empty() {
+                    (Just(Vec::new()).boxed(), Just(Vec::new()).boxed())
+                } else {
+                    (vec(connection(ids.clone()), 0..6).boxed(), vec(group(ids.clone()), 0..3).boxed())
+                };
+                let selections = vec(
+                    (".*", prop::sample::subsequence(ids.clone(), 0..=ids.len()))
+                        .prop_map(|(name, nodes)| SavedSelection { name, nodes }),
+                    0..2,
+                );
+                (
+                    Just(swimlanes),
+                    Just(nodes),
+                    connections,
+                    groups,
+                    (
+                        any::<(u64, u64, u64, Option<u64>)>(),
+                        any::<(Option<u64>, u32, bool)>(),
+                    ),
+                    prop_oneof![
+                        Just(SimulationState::Stopped),
+                        Just(SimulationState::Running),
+                        Just(SimulationState::Paused),
+                    ],
+                    any::<u64>(),
+                    vec(format_rule(), 0..3),
+                    selections,
+                )
+            })
+            .prop_map(
+                |(
+                    swimlanes,
+                    nodes,
+                    connections,
+                    groups,
+                    (
+                        (warm_up_steps, measurement_steps, seed, pause_at_step),
+                        (max_steps, steps_per_frame, continue_on_error),
+                    ),
+                    simulation_state,
+                    current_step,
+                    format_rules,
+                    saved_selections,
+                )| {
+                    Flowchart {
+                        nodes: nodes.into_iter().map(|n| (n.id, n)).collect(),
+                        connections,
+                        groups: groups.into_iter().map(|g| (g.id, g)).collect(),
+                        swimlanes,
+                        run_config: RunConfig {
+                            warm_up_steps,
+                            measurement_steps,
+                            seed,
+                            pause_at_step,
+                            max_steps,
+                            steps_per_frame,
+                            continue_on_error,
+                        },
+                        format_rules,
+                        saved_selections,
+                        parameters: Default::default(),
+                        edit_log: Vec::new(),
+                        simulation_state,
+                        current_step,
+                    }
+                },
+            )
+    }
+
+    /// Saves `flowchart` in every file format and loads it back.
+    ///
+    /// New formats get a round trip here, so all the tests below cover them.
+    fn round_trips(flowchart: &Flowchart) -> Vec<(&'static str, Flowchart)> {
+        let json = flowchart.to_json().expect("documents serialize to JSON");
+        vec![("JSON", Flowchart::from_json(&json).expect("saved JSON loads"))]
+    }
+
+    proptest! {
+        #[test]
+        fn test_documents_survive_save_and_load(flowchart in flowchart()) {
+            for (format, loaded) in round_trips(&flowchart) {
+                prop_assert_eq!(&loaded, &flowchart, "{} round trip changed the document", format);
+            }
+        }
+
+        #[test]
+        fn test_node_types_survive_save_and_load(node_type in node_type()) {
+            let json = serde_json::to_string(&node_type).unwrap();
+            prop_assert_eq!(serde_json::from_str::<NodeType>(&json).unwrap(), node_type);
+        }
+
+        #[test]
+        fn test_connections_and_groups_survive_save_and_load(
+            (connection, group) in vec(uuid(), 1..5).prop_flat_map(|ids| (connection(ids.clone()), group(ids)))
+        ) {
+            let json = serde_json::to_string(&connection).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Connection>(&json).unwrap(), connection);
+            let json = serde_json::to_string(&group).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Group>(&json).unwrap(), group);
+        }
+    }
 }
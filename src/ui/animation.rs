@@ -0,0 +1,84 @@
+//! Time-based animation helpers.
+//!
+//! Canvas effects are driven by egui's input time rather than by counting
+//! frames, so they run at the same speed whatever the frame rate is. With the
+//! "Reduce motion" preference they hold a steady state and stop scheduling
+//! repaints, which also saves battery on the web build.
+
+use super::state::FlowchartApp;
+use eframe::egui;
+
+/// Reads the browser's `prefers-reduced-motion` setting; false elsewhere.
+pub(crate) fn prefers_reduced_motion() -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        if let Some(win) = web_sys::window() {
+            if let Ok(Some(query)) = win.match_media("(prefers-reduced-motion: reduce)") {
+                return query.matches();
+            }
+        }
+    }
+    false
+}
+
+/// Eases `t` in 0..=1 so motion starts fast and settles gently.
+pub(crate) fn ease_out(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    1.0 - (1.0 - t).powi(3)
+}
+
+impl FlowchartApp {
+    /// Square wave that flips every `half_period` seconds; steady on with reduced motion.
+    pub(crate) fn blink(&self, half_period: f64) -> bool {
+        if self.reduce_motion {
+            return true;
+        }
+        ((self.frame_time / half_period).floor() as u64).is_multiple_of(2)
+    }
+
+    /// Smooth value cycling between 0 and 1 every `period` seconds; 1 with reduced motion.
+    pub(crate) fn pulse(&self, period: f64) -> f32 {
+        if self.reduce_motion {
+            return 1.0;
+        }
+        let phase = (self.frame_time / period).fract();
+        (0.5 - 0.5 * (phase * std::f64::consts::TAU).cos()) as f32
+    }
+
+    /// Schedules the frame that continues a running animation.
+    ///
+    /// Does nothing with reduced motion, so static effects cost no repaints.
+    pub(crate) fn request_animation_frame(&self, ctx: &egui::Context) {
+        if !self.reduce_motion {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(
+                crate::constants::ANIMATION_FRAME_SECS,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effects_follow_time_and_respect_reduced_motion() {
+        let mut app = FlowchartApp {
+            reduce_motion: false,
+            frame_time: 0.1,
+            ..Default::default()
+        };
+        assert!(app.blink(0.25));
+        app.frame_time = 0.3;
+        assert!(!app.blink(0.25));
+        app.frame_time = 0.0;
+        assert!(app.pulse(1.0) < 0.01);
+        app.frame_time = 2.5;
+        assert!(app.pulse(1.0) > 0.99);
+
+        app.reduce_motion = true;
+        app.frame_time = 0.3;
+        assert!(app.blink(0.25));
+        assert_eq!(app.pulse(1.0), 1.0);
+    }
+}
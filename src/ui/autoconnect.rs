@@ -0,0 +1,235 @@
+//! Connection splitting and auto-connect suggestions for new nodes.
+//!
+//! When a node is created on top of an existing connection or close to another
+//! node, the user is offered to wire it in automatically. Connections can also
+//! be split explicitly from their context menu. Each of these is recorded as a
+//! single undoable step.
+
+use super::state::{AutoConnectSuggestion, FlowchartApp};
+use crate::constants::{AUTO_CONNECT_DISTANCE, AUTO_CONNECT_EDGE_DISTANCE, DEFAULT_TRANSFORMER_SCRIPT};
+use crate::types::*;
+use crate::ui::UndoAction;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Works out whether a freshly created node should be offered an automatic connection.
+    ///
+    /// Nodes that can both receive and send (transformers) dropped onto a
+    /// connection are offered to split it. Otherwise the nearest node within
+    /// `AUTO_CONNECT_DISTANCE` is offered as a source or target, following the
+    /// usual connection rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - The newly created node
+    pub(crate) fn suggest_auto_connect(&self, node_id: NodeId) -> Option<AutoConnectSuggestion> {
+        let node = self.flowchart.nodes.get(&node_id)?;
+        let pos = egui::pos2(node.position.0, node.position.1);
+
+        if matches!(node.node_type, NodeType::Transformer { .. }) {
+            let mut best: Option<(f32, NodeId, NodeId)> = None;
+            for conn in &self.flowchart.connections {
+                if conn.from == node_id || conn.to == node_id {
+                    continue;
+                }
+                if let (Some(from), Some(to)) = (
+                    self.flowchart.nodes.get(&conn.from),
+                    self.flowchart.nodes.get(&conn.to),
+                ) {
+                    let start = egui::pos2(from.position.0, from.position.1);
+                    let end = egui::pos2(to.position.0, to.position.1);
+                    let d = self.point_to_line_distance(pos, start, end);
+                    if d <= AUTO_CONNECT_EDGE_DISTANCE && best.is_none_or(|(bd, _, _)| d < bd) {
+                        best = Some((d, conn.from, conn.to));
+                    }
+                }
+            }
+            if let Some((_, from, to)) = best {
+                return Some(AutoConnectSuggestion::SplitConnection { node_id, from, to });
+            }
+        }
+
+        // Nearest other node within range
+        let (_, other) = self
+            .flowchart
+            .nodes
+            .values()
+            .filter(|n| n.id != node_id)
+            .map(|n| {
+                let d = (egui::pos2(n.position.0, n.position.1) - pos).length();
+                (d, n)
+            })
+            .filter(|(d, _)| *d <= AUTO_CONNECT_DISTANCE)
+            .min_by(|a, b| a.0.total_cmp(&b.0))?;
+
+        // Prefer feeding into a consumer, otherwise receive from the neighbor
+        let (from, to) = if matches!(other.node_type, NodeType::Consumer { .. })
+            || matches!(node.node_type, NodeType::Producer { .. })
+        {
+            (node, other)
+        } else {
+            (other, node)
+        };
+        let exists = self
+            .flowchart
+            .connections
+            .iter()
+            .any(|c| c.from == from.id && c.to == to.id);
+        if exists || !Self::is_valid_node_connection(from, to) {
+            return None;
+        }
+        Some(AutoConnectSuggestion::Connect {
+            from: from.id,
+            to: to.id,
+        })
+    }
+
+    /// Splits the connection `from -> to` so that it runs through `node_id`.
+    ///
+    /// Messages in flight on the original connection are moved onto the
+    /// first half (`from -> node_id`), so they still pass through the new node.
+    ///
+    /// # Returns
+    ///
+    /// The undo actions describing the change in the order they were applied,
+    /// or `None` if the connection does not exist.
+    pub(crate) fn split_connection(
+        &mut self,
+        from: NodeId,
+        to: NodeId,
+        node_id: NodeId,
+    ) -> Option<Vec<UndoAction>> {
+        let index = self
+            .flowchart
+            .connections
+            .iter()
+            .position(|c| c.from == from && c.to == to)?;
+        let original = self.flowchart.connections.remove(index);
+
+        let mut first = Connection::new(from, node_id);
+        first.messages = original.messages.clone();
+        self.flowchart.connections.push(first);
+        self.flowchart.connections.push(Connection::new(node_id, to));
+
+        Some(vec![
+            UndoAction::ConnectionDeleted {
+                connection: original,
+                index,
+            },
+            UndoAction::ConnectionCreated { from, to: node_id },
+            UndoAction::ConnectionCreated { from: node_id, to },
+        ])
+    }
+
+    /// Creates a transformer at the context menu position and splices it into
+    /// the connection `from -> to`, as one undo step.
+    ///
+    /// The new node is selected with its name ready for editing, like nodes
+    /// created from the regular context menu entries.
+    pub(crate) fn insert_transformer_into_connection(&mut self, from: NodeId, to: NodeId) {
+        let node = FlowchartNode::new(
+            format!("node{}", self.node_counter + 1),
+            self.context_menu.world_pos,
+            NodeType::Transformer {
+                script: DEFAULT_TRANSFORMER_SCRIPT.to_string(),
+                selected_outputs: None,
+                globals: Default::default(),
+                initial_globals: Default::default(),
+                retry: Default::default(),
+            },
+        );
+        let node_id = self.flowchart.add_node(node);
+        let Some(mut actions) = self.split_connection(from, to, node_id) else {
+            // Connection vanished since the menu opened; leave the graph untouched
+            self.flowchart.nodes.remove(&node_id);
+            return;
+        };
+        self.node_counter += 1;
+        actions.insert(0, UndoAction::NodeCreated { node_id });
+
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+
+        self.interaction.selected_nodes.clear();
+        self.interaction.selected_connection = None;
+        self.interaction.selected_node = Some(node_id);
+        self.start_editing_node_name(node_id, &format!("node{}", self.node_counter));
+    }
+
+    /// Applies an accepted auto-connect suggestion and records it as one undo step.
+    pub(crate) fn apply_auto_connect(&mut self, suggestion: AutoConnectSuggestion) {
+        let actions = match suggestion {
+            AutoConnectSuggestion::SplitConnection { node_id, from, to } => {
+                match self.split_connection(from, to, node_id) {
+                    Some(actions) => actions,
+                    None => return,
+                }
+            }
+            AutoConnectSuggestion::Connect { from, to } => match self.flowchart.add_connection(from, to) {
+                Ok(()) => vec![UndoAction::ConnectionCreated { from, to }],
+                Err(_) => return,
+            },
+        };
+
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+    }
+
+    /// Returns a short description of the suggestion for display in the popup.
+    fn describe_auto_connect(&self, suggestion: &AutoConnectSuggestion) -> Option<String> {
+        let name = |id: &NodeId| self.flowchart.nodes.get(id).map(|n| n.name.clone());
+        match suggestion {
+            AutoConnectSuggestion::SplitConnection { node_id, from, to } => Some(format!(
+                "Insert \"{}\" between \"{}\" and \"{}\"?",
+                name(node_id)?,
+                name(from)?,
+                name(to)?
+            )),
+            AutoConnectSuggestion::Connect { from, to } => Some(format!(
+                "Connect \"{}\" → \"{}\"?",
+                name(from)?,
+                name(to)?
+            )),
+        }
+    }
+
+    /// Draws the auto-connect confirmation popup when a suggestion is pending.
+    pub(crate) fn draw_auto_connect_popup(&mut self, ctx: &egui::Context) {
+        let Some(suggestion) = self.interaction.auto_connect_suggestion else {
+            return;
+        };
+        // Drop stale suggestions (e.g. a node involved was deleted or undone)
+        let Some(text) = self.describe_auto_connect(&suggestion) else {
+            self.interaction.auto_connect_suggestion = None;
+            return;
+        };
+
+        let mut decision: Option<bool> = None;
+        egui::Window::new("Auto-connect")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -40.0))
+            .show(ctx, |ui| {
+                ui.label(text);
+                ui.horizontal(|ui| {
+                    if ui.button("Connect").clicked() {
+                        decision = Some(true);
+                    }
+                    if ui.button("Dismiss").clicked() {
+                        decision = Some(false);
+                    }
+                });
+            });
+
+        match decision {
+            Some(true) => {
+                self.interaction.auto_connect_suggestion = None;
+                self.apply_auto_connect(suggestion);
+            }
+            Some(false) => self.interaction.auto_connect_suggestion = None,
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Auto-group command.
+//!
+//! Proposes groups of densely connected nodes computed by
+//! [`crate::analysis::plan_auto_groups`]. The user picks which proposals to
+//! keep and they are created as groups in a single undo entry.
+
+use super::state::FlowchartApp;
+use crate::analysis::plan_auto_groups;
+use crate::types::*;
+use crate::ui::UndoAction;
+use eframe::egui;
+
+/// Largest minimum community size offered in the dialog.
+const MAX_MIN_GROUP_SIZE: usize = 50;
+
+impl FlowchartApp {
+    /// Draws the auto-group dialog. Opens when `show_auto_group_dialog` is true.
+    pub(crate) fn draw_auto_group_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let mut apply = false;
+        let proposals = plan_auto_groups(&self.flowchart, &self.auto_group_options);
+
+        egui::Window::new("Auto-group")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Minimum group size:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.auto_group_options.min_size)
+                            .range(2..=MAX_MIN_GROUP_SIZE),
+                    );
+                });
+                ui.checkbox(
+                    &mut self.auto_group_options.skip_grouped,
+                    "Ignore nodes that are already grouped",
+                );
+                ui.separator();
+
+                if proposals.is_empty() {
+                    ui.label("No densely connected nodes found.");
+                } else {
+                    ui.label(format!("{} proposed group(s):", proposals.len()));
+                    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                        for members in &proposals {
+                            let names = members
+                                .iter()
+                                .filter_map(|id| self.flowchart.nodes.get(id).map(|n| n.name.clone()))
+                                .collect::<Vec<_>>()
+                                .join(", ");
+                            let declined = self.auto_group_declined.contains(members);
+                            let mut keep = !declined;
+                            if ui
+                                .checkbox(&mut keep, format!("{} node(s): {}", members.len(), names))
+                                .changed()
+                            {
+                                if keep {
+                                    self.auto_group_declined.retain(|d| d != members);
+                                } else {
+                                    self.auto_group_declined.push(members.clone());
+                                }
+                            }
+                        }
+                    });
+                }
+
+                let accepted = proposals
+                    .iter()
+                    .filter(|p| !self.auto_group_declined.contains(p))
+                    .count();
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(accepted > 0, |ui| {
+                        if ui.button(format!("Create {} Group(s)", accepted)).clicked() {
+                            apply = true;
+                        }
+                    });
+                    if ui.button("Cancel").clicked() {
+                        self.show_auto_group_dialog = false;
+                    }
+                });
+            });
+
+        if apply {
+            let accepted: Vec<Vec<NodeId>> = proposals
+                .into_iter()
+                .filter(|p| !self.auto_group_declined.contains(p))
+                .collect();
+            self.create_auto_groups(accepted);
+            self.show_auto_group_dialog = false;
+        }
+        if !keep_open {
+            self.show_auto_group_dialog = false;
+        }
+    }
+
+    /// Creates one group per member list and records them as one undo entry.
+    pub(crate) fn create_auto_groups(&mut self, proposals: Vec<Vec<NodeId>>) {
+        let mut actions: Vec<UndoAction> = Vec::new();
+        for members in proposals {
+            if members.is_empty() {
+                continue;
+            }
+            let gid = uuid::Uuid::new_v4();
+            let name = format!("Group {}", self.group_counter + 1);
+            self.group_counter += 1;
+            self.flowchart.groups.insert(
+                gid,
+                Group {
+                    id: gid,
+                    name,
+                    members,
+                    drawing: GroupDrawingMode::Rectangle,
+                    z_order: 0,
+                },
+            );
+            actions.push(UndoAction::GroupCreated { group_id: gid });
+        }
+        if actions.is_empty() {
+            return;
+        }
+
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+    }
+}
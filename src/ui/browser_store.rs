@@ -0,0 +1,198 @@
+//! IndexedDB-backed document store for the web build.
+//!
+//! Each flowchart is kept as one record keyed by its name, so several named
+//! documents can live side by side in the browser. Writes that exceed the
+//! browser's storage quota are reported with a message that suggests freeing
+//! space or downloading a copy instead.
+
+use super::state::StoredDocumentInfo;
+use crate::wasm_bindgen::closure::Closure;
+use crate::wasm_bindgen::{JsCast, JsValue};
+use serde::{Deserialize, Serialize};
+use web_sys::{DomException, IdbDatabase, IdbOpenDbRequest, IdbRequest, IdbTransaction, IdbTransactionMode};
+
+/// IndexedDB database holding the documents.
+const DB_NAME: &str = "flowchart_tool";
+/// Schema version; bump when the object stores change.
+const DB_VERSION: u32 = 1;
+/// Object store with one record per document, keyed by name.
+const STORE_NAME: &str = "documents";
+/// Shown when the browser refuses a write because storage is full.
+const QUOTA_EXCEEDED_MESSAGE: &str = "Browser storage is full. Delete saved flowcharts you no longer need, or use Save As… to download a copy.";
+
+/// One stored document as written to IndexedDB.
+#[derive(Serialize, Deserialize)]
+struct StoredDocument {
+    name: String,
+    saved_at: f64,
+    content: String,
+}
+
+/// Describes a DOM exception for the user, calling out quota errors.
+fn describe_exception(error: Option<DomException>) -> String {
+    match error {
+        Some(e) if e.name() == "QuotaExceededError" => QUOTA_EXCEEDED_MESSAGE.to_string(),
+        Some(e) => format!("Browser storage error: {}", e.message()),
+        None => "Browser storage error".to_string(),
+    }
+}
+
+/// Describes an exception thrown synchronously by an IndexedDB call.
+fn describe_js_error(error: JsValue) -> String {
+    describe_exception(error.dyn_into::<DomException>().ok())
+}
+
+/// Waits for an IndexedDB request to finish and returns its result.
+async fn await_request(request: &IdbRequest) -> Result<JsValue, String> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let req = request.clone();
+        let onsuccess = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+            let result = req.result().unwrap_or(JsValue::UNDEFINED);
+            let _ = resolve.call1(&JsValue::NULL, &result);
+        }) as Box<dyn FnMut(_)>);
+        request.set_onsuccess(Some(onsuccess.as_ref().unchecked_ref()));
+        onsuccess.forget();
+
+        let req = request.clone();
+        let onerror = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+            let message = describe_exception(req.error().ok().flatten());
+            let _ = reject.call1(&JsValue::NULL, &JsValue::from_str(&message));
+        }) as Box<dyn FnMut(_)>);
+        request.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+        onerror.forget();
+    });
+    wasm_bindgen_futures::JsFuture::from(promise)
+        .await
+        .map_err(|e| e.as_string().unwrap_or_else(|| "Browser storage error".to_string()))
+}
+
+/// Waits for a transaction to commit. Quota errors surface here as an abort.
+async fn await_transaction(transaction: &IdbTransaction) -> Result<(), String> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let oncomplete = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+            let _ = resolve.call0(&JsValue::NULL);
+        }) as Box<dyn FnMut(_)>);
+        transaction.set_oncomplete(Some(oncomplete.as_ref().unchecked_ref()));
+        oncomplete.forget();
+
+        let tx = transaction.clone();
+        let onabort = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+            let message = describe_exception(tx.error());
+            let _ = reject.call1(&JsValue::NULL, &JsValue::from_str(&message));
+        }) as Box<dyn FnMut(_)>);
+        transaction.set_onabort(Some(onabort.as_ref().unchecked_ref()));
+        transaction.set_onerror(Some(onabort.as_ref().unchecked_ref()));
+        onabort.forget();
+    });
+    wasm_bindgen_futures::JsFuture::from(promise)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.as_string().unwrap_or_else(|| "Browser storage error".to_string()))
+}
+
+/// Opens the database, creating the document store on first use.
+async fn open_db() -> Result<IdbDatabase, String> {
+    let factory = web_sys::window()
+        .ok_or("No window found")?
+        .indexed_db()
+        .ok()
+        .flatten()
+        .ok_or("This browser does not provide IndexedDB storage")?;
+    let request: IdbOpenDbRequest = factory
+        .open_with_u32(DB_NAME, DB_VERSION)
+        .map_err(describe_js_error)?;
+
+    let onupgradeneeded = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        let db = event
+            .target()
+            .and_then(|t| t.dyn_into::<IdbOpenDbRequest>().ok())
+            .and_then(|r| r.result().ok())
+            .and_then(|r| r.dyn_into::<IdbDatabase>().ok());
+        if let Some(db) = db {
+            if !db.object_store_names().contains(STORE_NAME) {
+                let _ = db.create_object_store(STORE_NAME);
+            }
+        }
+    }) as Box<dyn FnMut(_)>);
+    request.set_onupgradeneeded(Some(onupgradeneeded.as_ref().unchecked_ref()));
+    onupgradeneeded.forget();
+
+    await_request(&request)
+        .await?
+        .dyn_into::<IdbDatabase>()
+        .map_err(|_| "Failed to open browser storage".to_string())
+}
+
+/// Starts a transaction on the document store.
+fn transaction(db: &IdbDatabase, mode: IdbTransactionMode) -> Result<IdbTransaction, String> {
+    db.transaction_with_str_and_mode(STORE_NAME, mode)
+        .map_err(describe_js_error)
+}
+
+/// Stores `content` under `name`, replacing any document with the same name.
+pub(crate) async fn put_document(name: &str, content: &str) -> Result<(), String> {
+    let record = serde_json::to_string(&StoredDocument {
+        name: name.to_string(),
+        saved_at: js_sys::Date::now(),
+        content: content.to_string(),
+    })
+    .map_err(|e| format!("Failed to serialize document: {}", e))?;
+
+    let db = open_db().await?;
+    let tx = transaction(&db, IdbTransactionMode::Readwrite)?;
+    let store = tx.object_store(STORE_NAME).map_err(describe_js_error)?;
+    let request = store
+        .put_with_key(&JsValue::from_str(&record), &JsValue::from_str(name))
+        .map_err(describe_js_error)?;
+    await_request(&request).await?;
+    await_transaction(&tx).await
+}
+
+/// Reads the document stored under `name`, if there is one.
+pub(crate) async fn get_document(name: &str) -> Result<Option<String>, String> {
+    let db = open_db().await?;
+    let tx = transaction(&db, IdbTransactionMode::Readonly)?;
+    let store = tx.object_store(STORE_NAME).map_err(describe_js_error)?;
+    let request = store.get(&JsValue::from_str(name)).map_err(describe_js_error)?;
+    let Some(record) = await_request(&request).await?.as_string() else {
+        return Ok(None);
+    };
+    serde_json::from_str::<StoredDocument>(&record)
+        .map(|doc| Some(doc.content))
+        .map_err(|e| format!("Stored document is damaged: {}", e))
+}
+
+/// Lists every stored document, newest first.
+pub(crate) async fn list_documents() -> Result<Vec<StoredDocumentInfo>, String> {
+    let db = open_db().await?;
+    let tx = transaction(&db, IdbTransactionMode::Readonly)?;
+    let store = tx.object_store(STORE_NAME).map_err(describe_js_error)?;
+    let request = store.get_all().map_err(describe_js_error)?;
+    let records: js_sys::Array = await_request(&request)
+        .await?
+        .dyn_into()
+        .map_err(|_| "Unexpected browser storage contents".to_string())?;
+
+    let mut documents: Vec<StoredDocumentInfo> = records
+        .iter()
+        .filter_map(|r| r.as_string())
+        .filter_map(|r| serde_json::from_str::<StoredDocument>(&r).ok())
+        .map(|doc| StoredDocumentInfo {
+            name: doc.name,
+            saved_at: doc.saved_at,
+            size: doc.content.len(),
+        })
+        .collect();
+    documents.sort_by(|a, b| b.saved_at.total_cmp(&a.saved_at));
+    Ok(documents)
+}
+
+/// Removes the document stored under `name`.
+pub(crate) async fn delete_document(name: &str) -> Result<(), String> {
+    let db = open_db().await?;
+    let tx = transaction(&db, IdbTransactionMode::Readwrite)?;
+    let store = tx.object_store(STORE_NAME).map_err(describe_js_error)?;
+    let request = store.delete(&JsValue::from_str(name)).map_err(describe_js_error)?;
+    await_request(&request).await?;
+    await_transaction(&tx).await
+}
@@ -3,7 +3,7 @@
 //! This module handles canvas panning, zooming, node dragging, connection drawing,
 //! and coordinate transformations between screen and world space.
 
-use super::state::FlowchartApp;
+use super::state::{ChangeKind, FlowchartApp};
 use crate::types::*;
 use crate::ui::UndoAction;
 use eframe::egui;
@@ -84,12 +84,56 @@ impl FlowchartApp {
             self.interaction.is_panning = false;
             self.interaction.last_pan_pos = None;
         }
+
+        // Horizontal scrolling (Shift+wheel, or sideways on a touchpad) pans sideways
+        let horizontal = ui.input(|i| i.smooth_scroll_delta.x);
+        if horizontal != 0.0 && ui.input(|i| i.pointer.hover_pos()).is_some_and(|p| response.rect.contains(p)) {
+            self.canvas.offset.x += horizontal;
+        }
+    }
+
+    /// Pans the canvas while the arrow keys or W/A/S/D are held, at
+    /// `keyboard_pan_speed`.
+    ///
+    /// Keys go to text fields instead while one has focus, and are left
+    /// alone with Ctrl/Cmd or Alt held so shortcuts keep working.
+    pub fn handle_keyboard_panning(&mut self, ui: &egui::Ui) {
+        if ui.ctx().wants_keyboard_input() {
+            return;
+        }
+        let (direction, dt) = ui.input(|i| {
+            if i.modifiers.command || i.modifiers.alt {
+                return (egui::Vec2::ZERO, 0.0);
+            }
+            let held = |keys: [egui::Key; 2]| keys.iter().any(|key| i.key_down(*key));
+            let mut direction = egui::Vec2::ZERO;
+            if held([egui::Key::ArrowLeft, egui::Key::A]) {
+                direction.x -= 1.0;
+            }
+            if held([egui::Key::ArrowRight, egui::Key::D]) {
+                direction.x += 1.0;
+            }
+            if held([egui::Key::ArrowUp, egui::Key::W]) {
+                direction.y -= 1.0;
+            }
+            if held([egui::Key::ArrowDown, egui::Key::S]) {
+                direction.y += 1.0;
+            }
+            (direction, i.stable_dt.min(0.1))
+        });
+        if direction == egui::Vec2::ZERO {
+            return;
+        }
+        // Moving the view right moves the content left
+        self.canvas.offset -= direction.normalized() * self.keyboard_pan_speed * dt;
+        ui.ctx().request_repaint();
     }
 
     /// Handles scroll wheel zooming functionality.
     ///
     /// Zooms in/out while keeping the mouse cursor position fixed in world space.
-    /// Zoom range is clamped between 0.25x and 5.0x.
+    /// Horizontal scrolling pans instead (see [`Self::handle_canvas_panning`]).
+    /// The zoom stays within `zoom_limits` (see [`Self::zoom_around`]).
     /// Only zooms if the cursor is over the canvas.
     ///
     /// # Arguments
@@ -111,25 +155,53 @@ impl FlowchartApp {
                     return;
                 }
 
-                // Calculate the world position under the mouse cursor before zoom
-                let world_pos_before_zoom = self.screen_to_world(mouse_pos);
-
                 // Apply zoom change with smaller, more precise steps
                 let zoom_delta = if scroll_delta > 0.0 { 0.025 } else { -0.025 };
-                let old_zoom = self.canvas.zoom_factor;
-                self.canvas.zoom_factor = (self.canvas.zoom_factor + zoom_delta).clamp(0.25, 5.0);
+                self.zoom_around(self.canvas.zoom_factor + zoom_delta, mouse_pos);
+            }
+        }
+    }
 
-                // Only adjust offset if zoom actually changed
-                if (self.canvas.zoom_factor - old_zoom).abs() > f32::EPSILON {
-                    // Calculate where that world position should appear on screen after zoom
-                    let world_pos_after_zoom = self.world_to_screen(world_pos_before_zoom);
+    /// Handles Ctrl/Cmd+= and Ctrl/Cmd+- (zoom in and out around the cursor,
+    /// or the middle of the canvas when the cursor is elsewhere) and
+    /// Ctrl/Cmd+0 (back to 100%).
+    pub fn handle_keyboard_zoom(&mut self, ui: &egui::Ui, response: &egui::Response) {
+        if ui.ctx().wants_keyboard_input() {
+            return;
+        }
+        let (zoom_in, zoom_out, reset) = ui.input_mut(|i| {
+            let zoom_in = i.consume_key(egui::Modifiers::COMMAND, egui::Key::Equals)
+                | i.consume_key(egui::Modifiers::COMMAND, egui::Key::Plus);
+            let zoom_out = i.consume_key(egui::Modifiers::COMMAND, egui::Key::Minus);
+            let reset = i.consume_key(egui::Modifiers::COMMAND, egui::Key::Num0);
+            (zoom_in, zoom_out, reset)
+        });
+        let anchor = ui
+            .input(|i| i.pointer.hover_pos())
+            .filter(|pos| response.rect.contains(*pos))
+            .unwrap_or_else(|| response.rect.center());
+        let zoom = self.canvas.zoom_factor;
+        if reset {
+            self.zoom_around(1.0, anchor);
+        } else if zoom_in {
+            self.zoom_around(zoom * crate::constants::ZOOM_KEY_STEP, anchor);
+        } else if zoom_out {
+            self.zoom_around(zoom / crate::constants::ZOOM_KEY_STEP, anchor);
+        }
+    }
 
-                    // Adjust canvas offset to keep the world position under the mouse cursor
-                    let offset_adjustment = mouse_pos - world_pos_after_zoom;
-                    self.canvas.offset += offset_adjustment;
-                }
-            }
+    /// Sets the zoom to `zoom`, clamped to `zoom_limits`, keeping the world
+    /// position under `anchor` (screen space) where it is.
+    pub fn zoom_around(&mut self, zoom: f32, anchor: egui::Pos2) {
+        let (min, max) = self.zoom_limits;
+        let zoom = zoom.clamp(min, max.max(min));
+        if (zoom - self.canvas.zoom_factor).abs() <= f32::EPSILON {
+            return;
         }
+        let world_anchor = self.screen_to_world(anchor);
+        self.canvas.zoom_factor = zoom;
+        // Shift the view so the anchored world position appears under the anchor again
+        self.canvas.offset += anchor - self.world_to_screen(world_anchor);
     }
 
     /// Handles node dragging functionality with left mouse button.
@@ -150,15 +222,25 @@ impl FlowchartApp {
             if let Some(current_pos) = response.interact_pointer_pos() {
                 let world_pos = self.screen_to_world(current_pos);
                 let shift_held = ui.input(|i| i.modifiers.shift);
+                let alt_held = ui.input(|i| i.modifiers.alt);
 
                 // Check if we're starting a new interaction
                 if self.interaction.dragging_node.is_none()
                     && self.interaction.drawing_connection_from.is_none()
                     && self.interaction.pending_shift_connection_from.is_none()
+                    && self.interaction.duplicating_from.is_none()
                 {
                     // Check if clicking on a node
                     if let Some(node_id) = self.find_node_at_position(world_pos) {
-                        if shift_held {
+                        if alt_held && !shift_held {
+                            // Alt-press on node: drag out a copy, placed and connected on release
+                            self.interaction.duplicating_from = Some(node_id);
+                            self.interaction.duplicate_drop_pos = Some(current_pos);
+                            if let Some(node) = self.flowchart.nodes.get(&node_id) {
+                                self.interaction.node_drag_offset =
+                                    egui::pos2(node.position.0, node.position.1) - world_pos;
+                            }
+                        } else if shift_held {
                             // Shift-press on node: defer connection start until drag threshold is exceeded.
                             // If no drag happens and mouse is released, treat as additive selection.
                             if self.interaction.pending_shift_connection_from.is_none() {
@@ -174,6 +256,9 @@ impl FlowchartApp {
                 } else if let Some(dragging_id) = self.interaction.dragging_node {
                     // Continue dragging node - check shift for grid snapping
                     self.update_dragged_node_position(dragging_id, world_pos, ui);
+                } else if self.interaction.duplicating_from.is_some() {
+                    // Continue dragging out a copy - update the preview position
+                    self.interaction.duplicate_drop_pos = Some(current_pos);
                 } else if self.interaction.drawing_connection_from.is_some() {
                     // Continue drawing connection - update preview position
                     self.interaction.connection_draw_pos = Some(current_pos);
@@ -204,6 +289,17 @@ impl FlowchartApp {
                 }
             }
 
+            // Place the copy of an Alt-dragged node unless it was dropped back on the original
+            if let (Some(from_id), Some(drop_pos)) = (
+                self.interaction.duplicating_from.take(),
+                self.interaction.duplicate_drop_pos.take(),
+            ) {
+                let world_pos = self.duplicate_drop_world_pos(drop_pos, ui.input(|i| i.modifiers.shift));
+                if self.find_node_at_position(self.screen_to_world(drop_pos)) != Some(from_id) {
+                    self.duplicate_and_connect(from_id, (world_pos.x, world_pos.y));
+                }
+            }
+
             // If there was a pending shift-click on a node and we never started drawing a connection,
             // interpret this as a toggle selection of that node.
             if let Some(node_id) = self.interaction.pending_shift_connection_from.take() {
@@ -242,12 +338,12 @@ impl FlowchartApp {
                         }
                     }
                     if !old_positions.is_empty() && old_positions != new_positions {
-                        self.undo_history
-                            .push_action(UndoAction::MultipleNodesMoved {
-                                old_positions,
-                                new_positions,
-                            });
-                        self.file.has_unsaved_changes = true;
+                        let action = UndoAction::MultipleNodesMoved {
+                            old_positions,
+                            new_positions,
+                        };
+                        self.file.mark_action(&action);
+                        self.undo_history.push_action(action);
                     }
                 } else if let Some(old_pos) = self.interaction.drag_original_position {
                     self.record_node_movement(dragging_id, old_pos);
@@ -366,7 +462,7 @@ impl FlowchartApp {
                     old_position,
                     new_position,
                 });
-                self.file.has_unsaved_changes = true;
+                self.file.mark_changed(ChangeKind::NodeMoved(node_id));
             }
         }
     }
@@ -390,16 +486,7 @@ impl FlowchartApp {
 
                     if let (Some(from), Some(to)) = (from_node, to_node) {
                         // Check if connection is allowed based on node types
-                        let is_valid_connection = match (&from.node_type, &to.node_type) {
-                            // Consumer cannot send (cannot be source)
-                            (NodeType::Consumer { .. }, _) => false,
-                            // Producer cannot receive (cannot be target)
-                            (_, NodeType::Producer { .. }) => false,
-                            // All other combinations are valid
-                            _ => true,
-                        };
-
-                        if !is_valid_connection {
+                        if !Self::is_valid_node_connection(from, to) {
                             return;
                         }
 
@@ -422,7 +509,7 @@ impl FlowchartApp {
                                     to: to_node_id,
                                 });
 
-                            self.file.has_unsaved_changes = true;
+                            self.file.mark_changed(ChangeKind::ConnectionAdded);
                         }
                     }
                 }
@@ -430,6 +517,142 @@ impl FlowchartApp {
         }
     }
 
+    /// World position of the copy dropped at `drop_pos` (screen space),
+    /// keeping the grab offset like a dragged node and snapped with Shift.
+    pub(crate) fn duplicate_drop_world_pos(&self, drop_pos: egui::Pos2, snap: bool) -> egui::Pos2 {
+        let world_pos = self.screen_to_world(drop_pos) + self.interaction.node_drag_offset;
+        if snap {
+            self.snap_to_grid(world_pos)
+        } else {
+            world_pos
+        }
+    }
+
+    /// Adds a copy of `node_id` at `position` and connects the original to
+    /// it when the type rules allow, as one undo step.
+    ///
+    /// The copy starts with a fresh run state and a numbered name, and is
+    /// selected. Returns its id, or `None` if the node doesn't exist.
+    pub(crate) fn duplicate_and_connect(&mut self, node_id: NodeId, position: (f32, f32)) -> Option<NodeId> {
+        let original = self.flowchart.nodes.get(&node_id)?;
+        let mut copy = original.clone();
+        copy.id = uuid::Uuid::new_v4();
+        copy.name = self.next_copy_name(&original.name);
+        copy.position = position;
+        copy.pinned = false;
+        copy.state = NodeState::Idle;
+        match &mut copy.node_type {
+            NodeType::Producer { messages_produced, .. } => *messages_produced = 0,
+            NodeType::Consumer { in_flight, .. } => in_flight.clear(),
+            NodeType::Transformer { globals, initial_globals, .. } => *globals = initial_globals.clone(),
+            NodeType::Reference { .. } => {}
+        }
+        let connect = Self::is_valid_node_connection(original, &copy);
+        let copy_id = self.flowchart.add_node(copy);
+        self.node_counter += 1;
+
+        let mut actions = vec![UndoAction::NodeCreated { node_id: copy_id }];
+        if connect {
+            self.flowchart.connections.push(Connection::new(node_id, copy_id));
+            actions.push(UndoAction::ConnectionCreated { from: node_id, to: copy_id });
+        }
+        actions.extend(self.make_room_for(&[copy_id]));
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+        crate::crash::record_event("Duplicated a node");
+
+        self.interaction.selected_nodes = vec![copy_id];
+        self.interaction.selected_node = Some(copy_id);
+        self.interaction.selected_group = None;
+        self.interaction.selected_connection = None;
+        self.interaction.editing_node_name = None;
+        self.clear_temp_editing_values();
+        Some(copy_id)
+    }
+
+    /// Connects each selected node to the next one in selection order, as one
+    /// undo step.
+    ///
+    /// Shift-clicks add nodes in click order and a marquee adds them left to
+    /// right. Pairs the type rules don't allow, and connections that already
+    /// exist, are skipped. Returns the number of connections created.
+    pub(crate) fn connect_selection_in_order(&mut self) -> usize {
+        let selection = self.selected_node_ids();
+        let mut actions = Vec::new();
+        for pair in selection.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let (Some(from_node), Some(to_node)) = (self.flowchart.nodes.get(&from), self.flowchart.nodes.get(&to))
+            else {
+                continue;
+            };
+            if from == to
+                || !Self::is_valid_node_connection(from_node, to_node)
+                || self.flowchart.connections.iter().any(|c| c.from == from && c.to == to)
+            {
+                continue;
+            }
+            self.flowchart.connections.push(Connection::new(from, to));
+            actions.push(UndoAction::ConnectionCreated { from, to });
+        }
+        let created = actions.len();
+        if created == 0 {
+            return 0;
+        }
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+        crate::crash::record_event("Connected the selection in order");
+        created
+    }
+
+    /// `name` with the next number no other node uses: "parse" becomes
+    /// "parse 2", and "parse 2" becomes "parse 3".
+    pub(crate) fn next_copy_name(&self, name: &str) -> String {
+        let (base, number) = match name.rsplit_once(' ') {
+            Some((base, n)) if !base.is_empty() => match n.parse::<u32>() {
+                Ok(n) => (base, n),
+                Err(_) => (name, 1),
+            },
+            _ => (name, 1),
+        };
+        (number.saturating_add(1)..)
+            .map(|n| format!("{} {}", base, n))
+            .find(|candidate| !self.flowchart.nodes.values().any(|node| &node.name == candidate))
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Returns whether a connection from a node of type `from` to one of type `to`
+    /// is allowed.
+    ///
+    /// Consumers cannot be a source, producers cannot be a target, and
+    /// reference nodes cannot be connected at all.
+    pub(crate) fn is_valid_connection(from: &NodeType, to: &NodeType) -> bool {
+        match (from, to) {
+            // References only stand in for other files
+            (NodeType::Reference { .. }, _) | (_, NodeType::Reference { .. }) => false,
+            // Consumer cannot send (cannot be source)
+            (NodeType::Consumer { .. }, _) => false,
+            // Producer cannot receive (cannot be target)
+            (_, NodeType::Producer { .. }) => false,
+            // All other combinations are valid
+            _ => true,
+        }
+    }
+
+    /// Returns whether `from` may be connected to `to`: the type rules of
+    /// [`Self::is_valid_connection`], except that consumers with a tap can be
+    /// a source, plus plugin nodes without outputs cannot be a source.
+    pub(crate) fn is_valid_node_connection(from: &FlowchartNode, to: &FlowchartNode) -> bool {
+        if from.plugin.as_ref().is_some_and(|p| !p.outputs) {
+            return false;
+        }
+        if from.tap && matches!(from.node_type, NodeType::Consumer { .. }) {
+            return !matches!(to.node_type, NodeType::Producer { .. } | NodeType::Reference { .. });
+        }
+        Self::is_valid_connection(&from.node_type, &to.node_type)
+    }
+
     /// Finds the node at the given canvas position, if any.
     ///
     /// # Arguments
@@ -442,12 +665,13 @@ impl FlowchartApp {
     pub fn find_node_at_position(&self, pos: egui::Pos2) -> Option<NodeId> {
         let node_size = egui::vec2(crate::constants::NODE_WIDTH, crate::constants::NODE_HEIGHT);
 
-        for (id, node) in &self.flowchart.nodes {
+        // Topmost first, so the node drawn over the others is the one hit
+        for node in self.flowchart.nodes_by_z_order().into_iter().rev() {
             let node_pos = egui::pos2(node.position.0, node.position.1);
             let rect = egui::Rect::from_center_size(node_pos, node_size);
 
             if rect.contains(pos) {
-                return Some(*id);
+                return Some(node.id);
             }
         }
         None
@@ -477,12 +701,7 @@ impl FlowchartApp {
         let click_threshold = crate::constants::CLICK_THRESHOLD; // pixels in world space
         let mut hits: Vec<usize> = Vec::new();
         for (idx, connection) in self.flowchart.connections.iter().enumerate() {
-            if let (Some(from_node), Some(to_node)) = (
-                self.flowchart.nodes.get(&connection.from),
-                self.flowchart.nodes.get(&connection.to),
-            ) {
-                let start = egui::pos2(from_node.position.0, from_node.position.1);
-                let end = egui::pos2(to_node.position.0, to_node.position.1);
+            if let Some((start, end)) = self.connection_endpoints(connection.from, connection.to) {
                 let distance = self.point_to_line_distance(pos, start, end);
                 if distance < click_threshold {
                     hits.push(idx);
@@ -505,7 +724,7 @@ impl FlowchartApp {
     /// # Returns
     ///
     /// The minimum distance from the point to the line segment
-    fn point_to_line_distance(
+    pub(crate) fn point_to_line_distance(
         &self,
         point: egui::Pos2,
         line_start: egui::Pos2,
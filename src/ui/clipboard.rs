@@ -0,0 +1,111 @@
+//! "Copy as JSON" for nodes, connections and messages, and "Copy Canvas as
+//! Image".
+//!
+//! Objects are serialized exactly as they appear in saved files and placed on
+//! the system clipboard through egui, which works on native and web builds.
+//! Images can only be placed on the clipboard by the native build; the web
+//! build downloads them as a PNG instead.
+
+use super::state::{ExportOptions, ExportScope, FlowchartApp};
+use crate::types::*;
+use eframe::egui;
+use serde::Serialize;
+
+/// Serializes `value` as pretty JSON and places it on the clipboard.
+pub(crate) fn copy_as_json(ctx: &egui::Context, value: &impl Serialize) {
+    if let Ok(json) = serde_json::to_string_pretty(value) {
+        ctx.copy_text(json);
+    }
+}
+
+/// Draws a "Copy as JSON" button that copies `value` when clicked.
+///
+/// # Returns
+///
+/// True if the button was clicked.
+pub(crate) fn copy_as_json_button(ui: &mut egui::Ui, value: &impl Serialize) -> bool {
+    let clicked = ui
+        .button("Copy as JSON")
+        .on_hover_text("Copy to the clipboard for bug reports and scripts")
+        .clicked();
+    if clicked {
+        copy_as_json(ui.ctx(), value);
+    }
+    clicked
+}
+
+impl FlowchartApp {
+    /// JSON text for a node, as it would appear in a saved file.
+    pub(crate) fn node_as_json(&self, node_id: NodeId) -> Option<String> {
+        let node = self.flowchart.nodes.get(&node_id)?;
+        serde_json::to_string_pretty(node).ok()
+    }
+
+    /// JSON text for the connection `from -> to`, including messages in transit.
+    pub(crate) fn connection_as_json(&self, from: NodeId, to: NodeId) -> Option<String> {
+        let connection = self
+            .flowchart
+            .connections
+            .iter()
+            .find(|c| c.from == from && c.to == to)?;
+        serde_json::to_string_pretty(connection).ok()
+    }
+
+    /// Copies the selected nodes, or the visible part of the canvas when
+    /// nothing is selected, to the clipboard as a PNG image.
+    pub fn copy_canvas_as_image(&mut self, ctx: &egui::Context) {
+        let options = self.canvas_image_options(ctx);
+        crate::crash::record_event("Copied canvas as image");
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let (svg, width, height) = self.build_svg_with_options(ctx, &options);
+            let mut fonts = fontdb::Database::new();
+            fonts.load_system_fonts();
+            match super::export::rasterize_svg(&svg, width, height, &options, fonts) {
+                Ok(pixmap) => {
+                    let image = super::snapshot::Snapshot::from_pixmap(&pixmap);
+                    ctx.copy_image(egui::ColorImage::from_rgba_unmultiplied(
+                        [image.width as usize, image.height as usize],
+                        &image.pixels,
+                    ));
+                }
+                Err(e) => eprintln!("Failed to copy canvas as image: {}", e),
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        self.export_png_with_options(ctx, &options);
+    }
+
+    /// Export options drawing the selection, or the visible canvas, the way
+    /// it looks on screen: same zoom, pixel density, background and grid.
+    pub(crate) fn canvas_image_options(&self, ctx: &egui::Context) -> ExportOptions {
+        let screen = self.canvas.screen_rect;
+        let scope = if !self.selected_node_ids().is_empty() {
+            ExportScope::SelectionOnly
+        } else if screen.is_positive() {
+            ExportScope::Area(egui::Rect::from_min_max(
+                self.screen_to_world(screen.min),
+                self.screen_to_world(screen.max),
+            ))
+        } else {
+            ExportScope::WholeGraph
+        };
+        let background_color = match self.canvas.background_color {
+            Some((r, g, b)) => egui::Color32::from_rgb(r, g, b),
+            None if self.dark_mode => egui::Color32::from_gray(20),
+            None => egui::Color32::WHITE,
+        };
+        let defaults = ExportOptions::default();
+        ExportOptions {
+            scope,
+            include_grid: self.canvas.show_grid,
+            include_background: true,
+            background_color,
+            margin_px: if matches!(scope, ExportScope::Area(_)) { 0.0 } else { defaults.margin_px },
+            png_scale: self.canvas.zoom_factor * ctx.pixels_per_point(),
+            ..defaults
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Confirmation of destructive actions.
+//!
+//! Deleting several nodes, stopping a run that has made progress and saving
+//! over a file that changed elsewhere go through
+//! [`confirm_then`](FlowchartApp::confirm_then), which asks first unless
+//! that kind of confirmation is off in [`ConfirmationSettings`]. The dialog's
+//! "Don't ask again" checkbox turns it off; Preferences turns it back on.
+//!
+//! [`ConfirmationSettings`]: super::state::ConfirmationSettings
+
+use super::state::{FlowchartApp, Modal, PendingConfirmation, PendingSaveOperation};
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Runs `action` now if its kind doesn't ask for confirmation, and
+    /// otherwise shows the confirmation dialog for it.
+    pub(crate) fn confirm_then(&mut self, action: PendingConfirmation) {
+        if self.confirmations.asks(action.kind()) {
+            self.dialogs.open(Modal::Confirm(action));
+        } else {
+            self.perform_confirmed(action);
+        }
+    }
+
+    /// Stops the simulation from the toolbar or menu, asking first if the
+    /// run has progress that stopping would clear.
+    pub fn request_stop_simulation(&mut self) {
+        if self.flowchart.current_step > 0 {
+            self.confirm_then(PendingConfirmation::ResetSimulation);
+        } else {
+            self.stop_simulation();
+        }
+    }
+
+    /// Carries out a confirmed action.
+    fn perform_confirmed(&mut self, action: PendingConfirmation) {
+        match action {
+            PendingConfirmation::DeleteNodes(ids) => self.delete_nodes(&ids),
+            PendingConfirmation::ResetSimulation => self.stop_simulation(),
+            PendingConfirmation::Overwrite(save) => self.file.pending_save_operation = Some(save),
+        }
+    }
+
+    /// Title, explanation and confirm button label for `action`.
+    fn confirmation_text(&self, action: &PendingConfirmation) -> (&'static str, String, &'static str) {
+        match action {
+            PendingConfirmation::DeleteNodes(ids) => (
+                "Delete Nodes?",
+                format!("Delete {} nodes and their connections? You can undo this.", ids.len()),
+                "Delete",
+            ),
+            PendingConfirmation::ResetSimulation => (
+                "Reset Simulation?",
+                format!(
+                    "Stopping clears the run after {} steps: messages in flight, statistics, events and errors. \
+                     Its final messages and statistics stay in Simulation → Last Run until the next run starts.",
+                    self.flowchart.current_step
+                ),
+                "Stop and Reset",
+            ),
+            PendingConfirmation::Overwrite(PendingSaveOperation::BrowserDocument(name)) => (
+                "Overwrite Document?",
+                format!("A flowchart named \"{}\" is already stored in this browser. Saving replaces it.", name),
+                "Replace",
+            ),
+            PendingConfirmation::Overwrite(_) => {
+                let name = self
+                    .file
+                    .current_path
+                    .as_deref()
+                    .and_then(|path| std::path::Path::new(path).file_name())
+                    .map_or_else(|| "The file".to_string(), |n| n.to_string_lossy().into_owned());
+                (
+                    "Overwrite File?",
+                    format!(
+                        "{} was changed by another program since it was opened or saved. Saving replaces those changes.",
+                        name
+                    ),
+                    "Overwrite",
+                )
+            }
+        }
+    }
+
+    /// Draws the confirmation at the front of the dialog queue.
+    pub(crate) fn draw_confirmation_dialog(&mut self, ctx: &egui::Context) {
+        let Some(Modal::Confirm(action)) = self.dialogs.current() else {
+            return;
+        };
+        let (title, message, confirm_label) = self.confirmation_text(action);
+        let mut confirmed = false;
+        let mut cancelled = false;
+        let mut dont_ask_again = self.dialogs.dont_ask_again;
+
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(message);
+                ui.checkbox(&mut dont_ask_again, "Don't ask again")
+                    .on_hover_text("Turn this confirmation back on in Preferences");
+                ui.horizontal(|ui| {
+                    if ui.button(confirm_label).clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            cancelled = true;
+        }
+
+        self.dialogs.dont_ask_again = dont_ask_again;
+        if confirmed {
+            if let Some(Modal::Confirm(action)) = self.dialogs.close() {
+                if dont_ask_again {
+                    self.confirmations.set_asks(action.kind(), false);
+                }
+                self.perform_confirmed(action);
+            }
+        } else if cancelled {
+            self.dialogs.close();
+        }
+    }
+}
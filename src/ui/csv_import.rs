@@ -0,0 +1,263 @@
+//! Nodes from a CSV table.
+//!
+//! Reviews the nodes read from a CSV table (see [`crate::csv_import`]): the
+//! rows that will become nodes, the connections their `downstream` column
+//! asks for and anything that was skipped. The nodes are added as one undo
+//! step. Rows without a position are laid out from left to right along
+//! their downstream connections, around the centre of the canvas.
+
+use super::state::{FlowchartApp, NodeCsvImportState};
+use super::UndoAction;
+use crate::constants::{NODE_HEIGHT, NODE_WIDTH};
+use crate::csv_import::{layout_columns, read_node_csv, NodeSpec};
+use crate::types::*;
+use eframe::egui;
+use std::collections::{BTreeMap, HashMap};
+
+/// Distance between the columns of nodes laid out without a position.
+const COLUMN_SPACING: f32 = NODE_WIDTH * 1.8;
+
+/// Distance between the nodes of a column.
+const ROW_SPACING: f32 = NODE_HEIGHT * 1.5;
+
+/// The node a `downstream` name refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Downstream {
+    /// Another row of the table, by index
+    Row(usize),
+    /// A node already in the flowchart
+    Existing(NodeId),
+}
+
+impl FlowchartApp {
+    /// Opens the review dialog for the table `content` read from `path`.
+    pub(crate) fn open_node_csv_import(&mut self, path: &str, content: &str) {
+        let source = std::path::Path::new(path)
+            .file_name()
+            .map_or_else(|| path.to_string(), |n| n.to_string_lossy().into_owned());
+        let (table, error) = match read_node_csv(content) {
+            Ok(table) => (table, None),
+            Err(e) => (Default::default(), Some(e)),
+        };
+        self.node_csv_import = Some(NodeCsvImportState {
+            source,
+            table,
+            error,
+            connect: true,
+        });
+    }
+
+    /// The connections the `downstream` column asks for, by row, and why
+    /// the others can't be made. Names are looked up in the table first,
+    /// then among the nodes already in the flowchart.
+    fn csv_connections(&self, nodes: &[NodeSpec]) -> (Vec<(usize, Downstream)>, Vec<String>) {
+        let rows: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, n)| (n.name.as_str(), i)).collect();
+        let mut existing: HashMap<&str, Vec<&FlowchartNode>> = HashMap::new();
+        for node in self.flowchart.nodes.values() {
+            existing.entry(node.name.as_str()).or_default().push(node);
+        }
+
+        let mut connections = Vec::new();
+        let mut problems = Vec::new();
+        for (from, spec) in nodes.iter().enumerate() {
+            let source = spec.to_node((0.0, 0.0));
+            for name in &spec.downstream {
+                let (target, node) = if let Some(&to) = rows.get(name.as_str()) {
+                    (Downstream::Row(to), nodes[to].to_node((0.0, 0.0)))
+                } else {
+                    match existing.get(name.as_str()).map(Vec::as_slice) {
+                        Some([node]) => (Downstream::Existing(node.id), (*node).clone()),
+                        Some(_) => {
+                            problems.push(format!("{} → {}: several nodes have that name", spec.name, name));
+                            continue;
+                        }
+                        None => {
+                            problems.push(format!("{} → {}: no node has that name", spec.name, name));
+                            continue;
+                        }
+                    }
+                };
+                if !Self::is_valid_node_connection(&source, &node) {
+                    problems.push(format!("{} → {}: these node types can't be connected", spec.name, name));
+                } else if !connections.contains(&(from, target)) {
+                    connections.push((from, target));
+                }
+            }
+        }
+        (connections, problems)
+    }
+
+    /// Draws the review dialog while a table is open.
+    pub(crate) fn draw_node_csv_import_dialog(&mut self, ctx: &egui::Context) {
+        let Some(state) = self.node_csv_import.as_ref() else {
+            return;
+        };
+        let (connections, problems) = self.csv_connections(&state.table.nodes);
+        let mut connect = state.connect;
+        let mut keep_open = true;
+        let mut add = false;
+        let mut cancel = false;
+
+        egui::Window::new("Nodes from CSV")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                if let Some(error) = &state.error {
+                    ui.colored_label(egui::Color32::RED, format!("Couldn't read {}: {}", state.source, error));
+                    return;
+                }
+                ui.label(format!("{} node(s) in {}", state.table.nodes.len(), state.source));
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    egui::Grid::new("node_csv_grid").num_columns(3).striped(true).show(ui, |ui| {
+                        ui.strong("Name");
+                        ui.strong("Type");
+                        ui.strong("Downstream");
+                        ui.end_row();
+                        for spec in &state.table.nodes {
+                            ui.label(&spec.name);
+                            ui.label(match spec.node_type {
+                                NodeType::Producer { .. } => "Producer",
+                                NodeType::Consumer { .. } => "Consumer",
+                                NodeType::Transformer { .. } => "Transformer",
+                                NodeType::Reference { .. } => "Reference",
+                            });
+                            ui.label(spec.downstream.join(", "));
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                let unconnected: &[String] = if connect { &problems } else { &[] };
+                let skipped: Vec<&String> = state.table.warnings.iter().chain(unconnected).collect();
+                if !skipped.is_empty() {
+                    egui::CollapsingHeader::new(format!("Skipped ({})", skipped.len()))
+                        .id_salt("node_csv_skipped")
+                        .show(ui, |ui| {
+                            for warning in skipped {
+                                ui.colored_label(egui::Color32::from_rgb(220, 140, 0), warning);
+                            }
+                        });
+                }
+                ui.checkbox(&mut connect, format!("Connect by the downstream column ({} connections)", connections.len()));
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let count = state.table.nodes.len();
+                    if ui
+                        .add_enabled(count > 0, egui::Button::new(format!("Add {} Node(s)", count)))
+                        .clicked()
+                    {
+                        add = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if let Some(state) = self.node_csv_import.as_mut() {
+            state.connect = connect;
+        }
+        if add {
+            if let Some(state) = self.node_csv_import.take() {
+                let center = self.screen_to_world(self.canvas.screen_rect.center());
+                self.add_csv_nodes(&state, (center.x, center.y));
+            }
+        } else if cancel || !keep_open {
+            self.node_csv_import = None;
+        }
+    }
+
+    /// Adds the nodes of `state` around `center`, with their connections and
+    /// groups, as one undo step, and selects them.
+    pub(crate) fn add_csv_nodes(&mut self, state: &NodeCsvImportState, center: (f32, f32)) -> Vec<NodeId> {
+        let nodes = &state.table.nodes;
+        if nodes.is_empty() {
+            return Vec::new();
+        }
+        let connections = if state.connect {
+            self.csv_connections(nodes).0
+        } else {
+            Vec::new()
+        };
+
+        // Rows without a position go in columns by how far downstream they are
+        let columns = layout_columns(nodes);
+        let unplaced: Vec<usize> = (0..nodes.len()).filter(|&i| nodes[i].position.is_none()).collect();
+        let mut column_sizes: BTreeMap<usize, usize> = BTreeMap::new();
+        for &i in &unplaced {
+            *column_sizes.entry(columns[i]).or_default() += 1;
+        }
+        let widest = column_sizes.keys().last().copied().unwrap_or_default();
+        let mut filled: HashMap<usize, usize> = HashMap::new();
+        let ids: Vec<NodeId> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| {
+                let position = spec.position.unwrap_or_else(|| {
+                    let column = columns[i];
+                    let row = filled.entry(column).or_default();
+                    let size = column_sizes[&column];
+                    let x = center.0 + COLUMN_SPACING * (column as f32 - widest as f32 / 2.0);
+                    let y = center.1 + ROW_SPACING * (*row as f32 - (size - 1) as f32 / 2.0);
+                    *row += 1;
+                    (x, y)
+                });
+                self.node_counter += 1;
+                self.flowchart.add_node(spec.to_node(position))
+            })
+            .collect();
+
+        let mut actions: Vec<UndoAction> = ids.iter().map(|&node_id| UndoAction::NodeCreated { node_id }).collect();
+        for (from, target) in connections {
+            let to = match target {
+                Downstream::Row(row) => ids[row],
+                Downstream::Existing(id) => id,
+            };
+            if self.flowchart.add_connection(ids[from], to).is_ok() {
+                actions.push(UndoAction::ConnectionCreated { from: ids[from], to });
+            }
+        }
+
+        let mut groups: Vec<(&str, Vec<NodeId>)> = Vec::new();
+        for (spec, id) in nodes.iter().zip(&ids) {
+            for name in &spec.groups {
+                match groups.iter_mut().find(|(group, _)| group == name) {
+                    Some((_, members)) => members.push(*id),
+                    None => groups.push((name, vec![*id])),
+                }
+            }
+        }
+        for (name, members) in groups {
+            let group_id = uuid::Uuid::new_v4();
+            self.group_counter += 1;
+            self.flowchart.groups.insert(
+                group_id,
+                Group {
+                    id: group_id,
+                    name: name.to_string(),
+                    members,
+                    drawing: GroupDrawingMode::Rectangle,
+                    z_order: 0,
+                },
+            );
+            actions.push(UndoAction::GroupCreated { group_id });
+        }
+
+        actions.extend(self.make_room_for(&ids));
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+        crate::crash::record_event("Added nodes from CSV");
+
+        self.interaction.selected_node = None;
+        self.interaction.selected_group = None;
+        self.interaction.selected_connection = None;
+        self.interaction.selected_nodes = ids.clone();
+        ids
+    }
+}
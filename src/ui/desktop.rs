@@ -0,0 +1,67 @@
+//! Desktop conventions: run progress outside the window and the macOS app menu.
+//!
+//! eframe has no API for the taskbar progress bar or the dock badge, so a run
+//! heading for a known step (Run To, the pause-at step or the maximum steps)
+//! shows its progress in the window title, which the Windows taskbar, the
+//! macOS Dock and Mission Control all display. When such a run finishes while
+//! the window is in the background, the app asks for attention, bouncing the
+//! Dock icon or flashing the taskbar button.
+//!
+//! eframe draws its menus inside the window and can't fill the macOS menu
+//! bar, so on macOS the in-window menu bar follows the layout of a native one
+//! instead: an application menu comes first, holding About, Preferences and
+//! Quit, which the File, Edit and Help menus then leave out.
+
+use super::state::FlowchartApp;
+use crate::constants::APP_TITLE;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Step the running simulation is heading for and stops on by itself, if any.
+    fn run_target_step(&self) -> Option<u64> {
+        if !self.is_simulation_running {
+            return None;
+        }
+        let config = &self.flowchart.run_config;
+        let upcoming = |step: &u64| *step > self.flowchart.current_step;
+        self.run_to_step
+            .into_iter()
+            .chain(config.pause_at_step.filter(upcoming))
+            .chain(config.max_steps)
+            .min()
+    }
+
+    /// How far the running simulation is towards its target step, from 0 to 1.
+    pub(crate) fn run_progress(&self) -> Option<f32> {
+        let target = self.run_target_step()?;
+        Some((self.flowchart.current_step as f64 / target.max(1) as f64).min(1.0) as f32)
+    }
+
+    /// Title of the window, with the progress of a running simulation.
+    pub(crate) fn window_title(&self) -> String {
+        match self.run_progress() {
+            Some(progress) => format!("{} — {:.0}%", APP_TITLE, (progress * 100.0).floor()),
+            None if self.is_simulation_running => format!("{} — Running", APP_TITLE),
+            None => APP_TITLE.to_string(),
+        }
+    }
+
+    /// Shows run progress in the window title and asks for attention when a
+    /// run with a target finishes in the background. Called once per frame.
+    pub(crate) fn update_window_progress(&mut self, ctx: &egui::Context) {
+        let tracking = self.run_progress().is_some();
+        if self.window_tracks_run && !tracking && !self.is_simulation_running && !ctx.input(|i| i.focused) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
+                egui::UserAttentionType::Informational,
+            ));
+        }
+        self.window_tracks_run = tracking;
+
+        // Only on changes; setting the title is a call into the OS
+        let title = self.window_title();
+        if title != self.window_title_shown {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title_shown = title;
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! The Verify Determinism command.
+//!
+//! Runs two copies of the document from the start with its run settings (see
+//! [`crate::determinism`]) and reports whether they went through the same
+//! states on every step, or where they first differed. The current run isn't
+//! touched. Runs last until the document's configured end, or
+//! `DETERMINISM_CHECK_STEPS` steps when it has none.
+
+use super::state::FlowchartApp;
+use crate::constants::DETERMINISM_CHECK_STEPS;
+use crate::determinism::check_determinism;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Runs the check and opens the window with its outcome.
+    pub(crate) fn verify_determinism(&mut self) {
+        let config = &self.flowchart.run_config;
+        let steps = [config.max_steps, config.pause_at_step.filter(|step| *step > 0)]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(DETERMINISM_CHECK_STEPS);
+        crate::crash::record_event("Verified determinism");
+        self.determinism_check = Some(match check_determinism(&self.flowchart, steps) {
+            Ok(report) => Ok(format!(
+                "Both runs went through the same states for all {} steps (state {:016x}).",
+                report.steps, report.final_hash
+            )),
+            Err(divergence) => Err(divergence.to_string()),
+        });
+    }
+
+    /// Draws the outcome of the last check while there is one.
+    pub(crate) fn draw_determinism_dialog(&mut self, ctx: &egui::Context) {
+        let Some(outcome) = &self.determinism_check else {
+            return;
+        };
+        let mut keep_open = true;
+        let mut again = false;
+        egui::Window::new("Verify Determinism")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                match outcome {
+                    Ok(message) => {
+                        ui.colored_label(egui::Color32::from_rgb(40, 160, 60), message);
+                    }
+                    Err(message) => {
+                        ui.colored_label(egui::Color32::RED, message);
+                        ui.label(
+                            "Scripts that use Math.random() or the current time, or that keep \
+                             state outside the globals API, are the usual causes.",
+                        );
+                    }
+                }
+                ui.separator();
+                if ui.button("Run Again").clicked() {
+                    again = true;
+                }
+            });
+        if again {
+            self.verify_determinism();
+        } else if !keep_open {
+            self.determinism_check = None;
+        }
+    }
+}
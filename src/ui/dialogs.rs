@@ -0,0 +1,129 @@
+//! Modal dialogs.
+//!
+//! Dialogs that need an answer — unsaved changes, confirmations of
+//! destructive actions and errors — are queued in the app's
+//! [`DialogManager`] instead of each keeping its own flag. Only the oldest is
+//! shown, centred over the window; answering it shows the next. They don't
+//! block anything: a running simulation keeps stepping behind them.
+//!
+//! [`DialogManager`]: super::state::DialogManager
+
+use super::state::{FlowchartApp, Modal, PendingConfirmAction, PendingLoadOperation};
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Runs `action` now if nothing is unsaved, and otherwise asks whether to
+    /// discard the changes first.
+    pub(crate) fn unless_unsaved(&mut self, action: PendingConfirmAction, run: impl FnOnce(&mut Self)) {
+        if self.file.has_unsaved_changes_effective() {
+            self.dialogs.open(Modal::UnsavedChanges(action));
+        } else {
+            run(self);
+        }
+    }
+
+    /// Draws the dialog at the front of the queue.
+    pub(crate) fn draw_dialogs(&mut self, ctx: &egui::Context) {
+        match self.dialogs.current() {
+            Some(Modal::UnsavedChanges(action)) => {
+                let action = *action;
+                self.draw_unsaved_changes_dialog(ctx, action);
+            }
+            Some(Modal::Confirm(_)) => self.draw_confirmation_dialog(ctx),
+            Some(Modal::Error { .. }) => self.draw_error_dialog(ctx),
+            None => {}
+        }
+    }
+
+    /// Asks whether to discard unsaved changes to carry on with `action`.
+    fn draw_unsaved_changes_dialog(&mut self, ctx: &egui::Context, action: PendingConfirmAction) {
+        let (title, confirm_label) = match action {
+            PendingConfirmAction::Quit => ("Unsaved changes — Quit?", "Discard and Quit"),
+            PendingConfirmAction::New => ("Unsaved changes — Create New?", "Discard and Create New"),
+            PendingConfirmAction::Open => ("Unsaved changes — Load File?", "Discard and Load"),
+            PendingConfirmAction::LoadExample => ("Unsaved changes — Load Example?", "Discard and Load Example"),
+            PendingConfirmAction::OpenReference => ("Unsaved changes — Open Referenced File?", "Discard and Open"),
+            PendingConfirmAction::OpenFile => ("Unsaved changes — Open File?", "Discard and Open"),
+            PendingConfirmAction::ReloadForUpdate => ("Unsaved changes — Reload to Update?", "Discard and Reload"),
+        };
+        let mut confirmed = false;
+        let mut cancelled = false;
+
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label("You have unsaved changes. Are you sure you want to continue?");
+                if let Some(details) = self.file.unsaved_changes_description() {
+                    ui.weak(details);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button(confirm_label).clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            cancelled = true;
+        }
+
+        if confirmed {
+            self.dialogs.close();
+            match action {
+                PendingConfirmAction::New => self.new_flowchart(),
+                PendingConfirmAction::Open => self.load_flowchart(),
+                PendingConfirmAction::LoadExample => {
+                    if let Some(kind) = self.file.pending_example.take() {
+                        self.load_example(kind);
+                    }
+                }
+                PendingConfirmAction::OpenReference | PendingConfirmAction::OpenFile => {
+                    if let Some(path) = self.file.pending_reference_path.take() {
+                        self.file.pending_load_operation = Some(PendingLoadOperation::OpenPath(path));
+                    }
+                }
+                PendingConfirmAction::ReloadForUpdate => self.reload_for_update(),
+                PendingConfirmAction::Quit => {
+                    // Allow one close request to pass without interception
+                    self.file.allow_close_on_next_request = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+        } else if cancelled {
+            self.dialogs.close();
+            if matches!(action, PendingConfirmAction::OpenReference | PendingConfirmAction::OpenFile) {
+                self.file.pending_reference_path = None;
+            }
+        }
+    }
+
+    /// Shows the error at the front of the queue until it is dismissed.
+    fn draw_error_dialog(&mut self, ctx: &egui::Context) {
+        let Some(Modal::Error { title, message }) = self.dialogs.current() else {
+            return;
+        };
+        let mut dismissed = false;
+
+        egui::Window::new(title.as_str())
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.colored_label(egui::Color32::RED, message.as_str());
+                if ui.button("OK").clicked() {
+                    dismissed = true;
+                }
+            });
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            dismissed = true;
+        }
+
+        if dismissed {
+            self.dialogs.close();
+        }
+    }
+}
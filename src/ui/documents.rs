@@ -0,0 +1,214 @@
+//! Browser documents dialog (web build).
+//!
+//! Lists the flowcharts kept in the browser's document store (see
+//! `browser_store`) so one can be opened, deleted, or picked as the name to
+//! save the current document under.
+
+use super::state::{
+    BrowserDocumentsMode, FlowchartApp, PendingConfirmation, PendingSaveOperation, StoreOperation,
+    StoredDocumentInfo,
+};
+use crate::audit::utc_date_time;
+use eframe::egui;
+
+/// Formats milliseconds since the Unix epoch as `YYYY-MM-DD HH:MM` (UTC).
+pub(crate) fn format_timestamp(millis: f64) -> String {
+    utc_date_time((millis / 1000.0).floor() as i64)
+}
+
+/// Formats a byte count as B, KB or MB.
+fn format_size(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+impl FlowchartApp {
+    /// Draws the browser documents dialog while it is open.
+    pub(crate) fn draw_browser_documents_dialog(&mut self, ctx: &egui::Context) {
+        let unsaved = self.file.has_unsaved_changes_effective();
+        let Some(state) = self.browser_documents.as_mut() else {
+            return;
+        };
+        let title = match state.mode {
+            BrowserDocumentsMode::Open => "Open from Browser",
+            BrowserDocumentsMode::Save => "Save to Browser",
+        };
+
+        let mut keep_open = true;
+        let mut open: Option<String> = None;
+        let mut save: Option<String> = None;
+        let mut delete: Option<String> = None;
+        let mut cancel = false;
+        egui::Window::new(title)
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                if state.mode == BrowserDocumentsMode::Save {
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut state.name);
+                    });
+                    let name = state.name.trim();
+                    let exists = state
+                        .documents
+                        .iter()
+                        .flatten()
+                        .any(|d| d.name == name);
+                    if exists {
+                        ui.weak("Replaces the saved flowchart with this name");
+                    }
+                } else if unsaved {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(220, 160, 40),
+                        "Opening a document discards unsaved changes",
+                    );
+                }
+                ui.separator();
+
+                match &state.documents {
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Loading…");
+                        });
+                    }
+                    Some(documents) if documents.is_empty() => {
+                        ui.label("No flowcharts are stored in this browser yet.");
+                    }
+                    Some(documents) => {
+                        egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+                            egui::Grid::new("browser_documents_grid")
+                                .num_columns(4)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for doc in documents {
+                                        document_row(ui, state.mode, doc, &mut state.name, &mut open, &mut state.confirm_delete);
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                    }
+                }
+
+                if let Some(name) = state.confirm_delete.clone() {
+                    ui.separator();
+                    ui.label(format!("Delete \"{}\" from this browser? This cannot be undone.", name));
+                    ui.horizontal(|ui| {
+                        if ui.button("Delete").clicked() {
+                            delete = Some(name);
+                            state.confirm_delete = None;
+                        }
+                        if ui.button("Keep").clicked() {
+                            state.confirm_delete = None;
+                        }
+                    });
+                }
+
+                if let Some(error) = &state.error {
+                    ui.separator();
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+
+                ui.separator();
+                ui.weak("Documents are kept in this browser only. Use Save As… to download a copy.");
+                if state.mode == BrowserDocumentsMode::Save {
+                    ui.horizontal(|ui| {
+                        let name = state.name.trim();
+                        if ui
+                            .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                            .clicked()
+                        {
+                            save = Some(name.to_string());
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel = true;
+                        }
+                    });
+                }
+            });
+
+        if let Some(name) = delete {
+            self.file.pending_store_operation = Some(StoreOperation::Delete(name));
+        }
+        if let Some(name) = open {
+            self.browser_documents = None;
+            self.open_browser_document(&name);
+        } else if let Some(name) = save {
+            let exists = self
+                .browser_documents
+                .as_ref()
+                .and_then(|state| state.documents.as_ref())
+                .is_some_and(|documents| documents.iter().any(|d| d.name == name));
+            self.browser_documents = None;
+            if exists && !self.file.is_example_readonly {
+                self.confirm_then(PendingConfirmation::Overwrite(PendingSaveOperation::BrowserDocument(name)));
+            } else {
+                self.save_browser_document(&name);
+            }
+        } else if cancel || !keep_open {
+            self.browser_documents = None;
+        }
+    }
+}
+
+/// One row of the document list: name, save time, size and actions.
+fn document_row(
+    ui: &mut egui::Ui,
+    mode: BrowserDocumentsMode,
+    doc: &StoredDocumentInfo,
+    save_name: &mut String,
+    open: &mut Option<String>,
+    confirm_delete: &mut Option<String>,
+) {
+    match mode {
+        BrowserDocumentsMode::Open => {
+            ui.label(&doc.name);
+        }
+        BrowserDocumentsMode::Save => {
+            if ui
+                .selectable_label(*save_name == doc.name, &doc.name)
+                .on_hover_text("Use this name")
+                .clicked()
+            {
+                *save_name = doc.name.clone();
+            }
+        }
+    }
+    ui.weak(format_timestamp(doc.saved_at))
+        .on_hover_text("Last saved (UTC)");
+    ui.weak(format_size(doc.size));
+    ui.horizontal(|ui| {
+        if mode == BrowserDocumentsMode::Open && ui.button("Open").clicked() {
+            *open = Some(doc.name.clone());
+        }
+        if ui
+            .button("✖")
+            .on_hover_text("Delete from this browser")
+            .clicked()
+        {
+            *confirm_delete = Some(doc.name.clone());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp_and_size() {
+        assert_eq!(format_timestamp(0.0), "1970-01-01 00:00");
+        // 2024-02-29 13:45:30 UTC
+        assert_eq!(format_timestamp(1_709_214_330_000.0), "2024-02-29 13:45");
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(2048), "2.0 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
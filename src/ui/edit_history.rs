@@ -0,0 +1,181 @@
+//! Edit History window and the recording of the document's edit log.
+//!
+//! Edits pushed onto the undo history are described and appended to the
+//! document's [`edit_log`](crate::types::Flowchart::edit_log) at the start of
+//! the next frame, once the nodes they name exist. Undo and redo are
+//! described as they happen instead, since they may remove what they name.
+//! Recording can be turned off and the author set in Preferences; the window
+//! lists the log newest first and filters it by author or description.
+
+use super::state::FlowchartApp;
+use super::undo::UndoAction;
+use crate::audit::{format_time, EditRecord};
+use crate::types::*;
+use eframe::egui;
+
+/// Descriptions of a batch listed before the rest are counted.
+const BATCH_DESCRIPTIONS_SHOWN: usize = 3;
+
+/// One-line description of `action` as an edit of `flowchart`, e.g. "Renamed Orders to Incoming".
+pub(crate) fn describe_action(action: &UndoAction, flowchart: &Flowchart) -> String {
+    let name = |id: &NodeId| {
+        flowchart
+            .nodes
+            .get(id)
+            .map_or_else(|| "a node".to_string(), |node| node.name.clone())
+    };
+    let nodes = |count: usize, first: Option<&NodeId>| match (count, first) {
+        (1, Some(id)) => name(id),
+        _ => format!("{} nodes", count),
+    };
+    match action {
+        UndoAction::NodeMoved { node_id, .. } => format!("Moved {}", name(node_id)),
+        UndoAction::MultipleNodesMoved { new_positions, .. } => {
+            format!("Moved {}", nodes(new_positions.len(), new_positions.first().map(|(id, _)| id)))
+        }
+        UndoAction::PropertyChanged { node_id, .. } => format!("Edited the properties of {}", name(node_id)),
+        UndoAction::NodeDeleted { node, .. } => format!("Deleted {}", node.name),
+        UndoAction::MultipleNodesDeleted { nodes, .. } => match nodes.as_slice() {
+            [node] => format!("Deleted {}", node.name),
+            _ => format!("Deleted {} nodes", nodes.len()),
+        },
+        UndoAction::ConnectionDeleted { connection, .. } => {
+            format!("Removed the connection {} → {}", name(&connection.from), name(&connection.to))
+        }
+        UndoAction::NodeCreated { node_id } => format!("Added {}", name(node_id)),
+        UndoAction::ConnectionCreated { from, to } => format!("Connected {} → {}", name(from), name(to)),
+        UndoAction::ConnectionMessagesChanged { from, to, .. } => {
+            format!("Changed the messages on the connection {} → {}", name(from), name(to))
+        }
+        UndoAction::ConnectionRestored { from, to, .. } => format!("Connected {} → {}", name(from), name(to)),
+        UndoAction::NodeRenamed { old_name, new_name, .. } => format!("Renamed {} to {}", old_name, new_name),
+        UndoAction::GroupDeleted { group } => format!("Deleted the group {}", group.name),
+        UndoAction::GroupCreated { group_id } => match flowchart.groups.get(group_id) {
+            Some(group) => format!("Created the group {}", group.name),
+            None => "Created a group".to_string(),
+        },
+        UndoAction::LaneAssigned { node_id, new_lane, .. } => {
+            match new_lane.and_then(|id| flowchart.swimlanes.lanes.iter().find(|lane| lane.id == id)) {
+                Some(lane) => format!("Moved {} to the lane {}", name(node_id), lane.name),
+                None => format!("Took {} out of its lane", name(node_id)),
+            }
+        }
+        UndoAction::SourceFileChanged { node_id, new_file, .. } => match new_file {
+            Some(file) => format!("Linked {} to {}", name(node_id), file),
+            None => format!("Unlinked the source file of {}", name(node_id)),
+        },
+        UndoAction::FailureChanged { node_id, .. } => format!("Changed the failures of {}", name(node_id)),
+        UndoAction::TapChanged { node_id, tap } => {
+            format!("Turned {} the tap of {}", if *tap { "on" } else { "off" }, name(node_id))
+        }
+        UndoAction::ConnectionStyleChanged { from, to, .. } => {
+            format!("Restyled the connection {} → {}", name(from), name(to))
+        }
+        UndoAction::TimingChanged { node_id, .. } => format!("Changed the timing of {}", name(node_id)),
+        UndoAction::ZOrderChanged { nodes: moved, groups } => {
+            if groups.is_empty() {
+                format!("Restacked {}", nodes(moved.len(), moved.first().map(|(id, _, _)| id)))
+            } else {
+                format!("Restacked {} items", moved.len() + groups.len())
+            }
+        }
+        UndoAction::NodesPinned { node_ids, pinned } => {
+            let verb = if *pinned { "Pinned" } else { "Unpinned" };
+            format!("{} {}", verb, nodes(node_ids.len(), node_ids.first()))
+        }
+        UndoAction::ParametersChanged { .. } => "Changed the parameters".to_string(),
+        UndoAction::SwimlanesChanged { .. } => "Changed the swimlanes".to_string(),
+        UndoAction::RunConfigChanged { .. } => "Changed the run settings".to_string(),
+        UndoAction::FormatRulesChanged { .. } => "Changed the formatting rules".to_string(),
+        UndoAction::SavedSelectionsChanged { .. } => "Changed the saved selections".to_string(),
+        UndoAction::Batch { actions } => {
+            let mut parts: Vec<String> = actions.iter().map(|a| describe_action(a, flowchart)).collect();
+            parts.dedup();
+            if parts.len() > BATCH_DESCRIPTIONS_SHOWN {
+                let more = parts.len() - BATCH_DESCRIPTIONS_SHOWN;
+                parts.truncate(BATCH_DESCRIPTIONS_SHOWN);
+                format!("{} and {} more", parts.join("; "), more)
+            } else if parts.is_empty() {
+                "Changed nothing".to_string()
+            } else {
+                parts.join("; ")
+            }
+        }
+    }
+}
+
+impl FlowchartApp {
+    /// Appends a record of `action` to the document's edit log, if recording.
+    pub(crate) fn append_edit_record(&mut self, action: String) {
+        if !self.record_edit_log {
+            return;
+        }
+        self.flowchart.edit_log.push(EditRecord {
+            at: crate::audit::now(),
+            author: self.edit_log_author.trim().to_string(),
+            action,
+        });
+    }
+
+    /// Describes the edits pushed onto the undo history since the last call
+    /// and appends them to the edit log.
+    pub(crate) fn log_edits(&mut self) {
+        for action in self.undo_history.take_unlogged() {
+            let description = describe_action(&action, &self.flowchart);
+            self.append_edit_record(description);
+        }
+    }
+
+    /// Draws the Edit History window. Opens when `show_edit_history_dialog` is true.
+    pub(crate) fn draw_edit_history_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let log = &self.flowchart.edit_log;
+        let filter = &mut self.edit_history_filter;
+
+        egui::Window::new("Edit History")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                if !self.record_edit_log {
+                    ui.weak("Recording is off; turn it on under Edit History in Preferences.");
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+                    ui.add(egui::TextEdit::singleline(filter).hint_text("author or change"));
+                });
+                let needle = filter.trim().to_lowercase();
+                let shown: Vec<&EditRecord> = log
+                    .iter()
+                    .rev()
+                    .filter(|record| {
+                        needle.is_empty()
+                            || record.action.to_lowercase().contains(&needle)
+                            || record.author.to_lowercase().contains(&needle)
+                    })
+                    .collect();
+                ui.label(format!("{} of {} edits, newest first", shown.len(), log.len()));
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    egui::Grid::new("edit_history_grid").num_columns(3).striped(true).show(ui, |ui| {
+                        for record in shown {
+                            ui.monospace(format_time(record.at));
+                            if record.author.is_empty() {
+                                ui.weak("unknown");
+                            } else {
+                                ui.label(&record.author);
+                            }
+                            ui.label(&record.action);
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+
+        if !keep_open {
+            self.show_edit_history_dialog = false;
+        }
+    }
+}
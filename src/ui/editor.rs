@@ -59,25 +59,17 @@ pub fn simple_js_format(src: &str, indent: &str) -> String {
                 continue;
             }
             match ch {
-                '\\' => {
-                    if in_single || in_double || in_back {
-                        escape = true;
-                    }
+                '\\' if in_single || in_double || in_back => {
+                    escape = true;
                 }
-                '\'' => {
-                    if !in_double && !in_back {
-                        in_single = !in_single;
-                    }
+                '\'' if !in_double && !in_back => {
+                    in_single = !in_single;
                 }
-                '"' => {
-                    if !in_single && !in_back {
-                        in_double = !in_double;
-                    }
+                '"' if !in_single && !in_back => {
+                    in_double = !in_double;
                 }
-                '`' => {
-                    if !in_single && !in_double {
-                        in_back = !in_back;
-                    }
+                '`' if !in_single && !in_double => {
+                    in_back = !in_back;
                 }
                 _ => {}
             }
@@ -0,0 +1,193 @@
+//! Simulation errors list.
+//!
+//! Lists every script failure of the run kept by the simulation engine, not
+//! just the node with the error border: the step, the node, the error and the
+//! payload of the message that caused it. Clicking a node selects it and
+//! moves the error border there.
+//!
+//! The failing nodes also carry an error badge on the canvas for as long as
+//! their errors are kept. Hovering it shows the latest error and clicking it
+//! opens the node's script in the properties panel.
+
+use super::json_tree::show_json_tree;
+use super::state::FlowchartApp;
+use super::trace::short_trace_id;
+use crate::constants::{ERROR_BADGE_RADIUS, ERROR_LOG_LIMIT, NODE_HEIGHT, NODE_WIDTH};
+use crate::events::SimError;
+use crate::types::*;
+use eframe::egui;
+
+/// Fill color of error badges.
+const BADGE_COLOR: egui::Color32 = egui::Color32::from_rgb(220, 40, 40);
+
+impl FlowchartApp {
+    /// Kept errors of `node_id`, oldest first.
+    pub(crate) fn node_errors(&self, node_id: NodeId) -> impl Iterator<Item = &SimError> {
+        self.simulation_engine.errors().iter().filter(move |e| e.node == node_id)
+    }
+
+    /// Screen position of the error badge of `node`, on its top-right corner.
+    pub(crate) fn error_badge_center(&self, node: &FlowchartNode) -> egui::Pos2 {
+        self.world_to_screen(egui::pos2(
+            node.position.0 + NODE_WIDTH / 2.0,
+            node.position.1 - NODE_HEIGHT / 2.0,
+        ))
+    }
+
+    /// Draws the error badge of `node` if it has errors in the run.
+    pub(crate) fn draw_error_badge(&self, painter: &egui::Painter, node: &FlowchartNode) {
+        if self.node_errors(node.id).next().is_none() {
+            return;
+        }
+        let center = self.error_badge_center(node);
+        let radius = ERROR_BADGE_RADIUS * self.canvas.zoom_factor;
+        painter.circle(center, radius, BADGE_COLOR, egui::Stroke::new(1.5, egui::Color32::WHITE));
+        painter.text(
+            center,
+            egui::Align2::CENTER_CENTER,
+            "!",
+            egui::FontId::proportional((radius * 1.5).clamp(6.0, 48.0)),
+            egui::Color32::WHITE,
+        );
+    }
+
+    /// The node whose error badge is under `screen_pos`, if any.
+    pub(crate) fn error_badge_at(&self, screen_pos: egui::Pos2) -> Option<NodeId> {
+        let radius = ERROR_BADGE_RADIUS * self.canvas.zoom_factor;
+        self.flowchart
+            .nodes
+            .values()
+            .filter(|node| self.node_errors(node.id).next().is_some())
+            .find(|node| self.error_badge_center(node).distance(screen_pos) <= radius)
+            .map(|node| node.id)
+    }
+
+    /// Shows the errors of a hovered badge and opens the script editor of a
+    /// clicked one.
+    pub(crate) fn handle_error_badges(&mut self, ui: &egui::Ui, response: &egui::Response) {
+        let Some(node_id) = response.hover_pos().and_then(|pos| self.error_badge_at(pos)) else {
+            return;
+        };
+        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+        if response.clicked() {
+            self.open_script_editor(node_id);
+        }
+
+        let errors: Vec<&SimError> = self.node_errors(node_id).collect();
+        let Some(latest) = errors.last() else {
+            return;
+        };
+        response.clone().on_hover_ui_at_pointer(|ui| {
+            ui.colored_label(BADGE_COLOR, &latest.message);
+            let summary = match errors.len() {
+                1 => format!("Failed on step {}", latest.step),
+                n => format!("Failed {} times, last on step {}", n, latest.step),
+            };
+            ui.weak(summary);
+            ui.weak("Click to edit the script");
+        });
+    }
+
+    /// Selects `node_id` and focuses its script in the properties panel.
+    pub(crate) fn open_script_editor(&mut self, node_id: NodeId) {
+        if !self.flowchart.nodes.contains_key(&node_id) {
+            return;
+        }
+        self.interaction.selected_nodes = vec![node_id];
+        self.interaction.selected_connection = None;
+        self.interaction.selected_group = None;
+        self.interaction.selected_node = Some(node_id);
+        self.interaction.editing_node_name = None;
+        self.clear_temp_editing_values();
+        self.interaction.focus_script_editor = true;
+    }
+
+    /// Draws the errors list. Opens when `show_errors_dialog` is true.
+    pub(crate) fn draw_errors_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let mut select: Option<NodeId> = None;
+        let mut trace: Option<TraceId> = None;
+        let mut clear = false;
+
+        let errors = self.simulation_engine.errors();
+        let flowchart = &self.flowchart;
+        let palette = self.syntax_theme.palette(ctx.style().visuals.dark_mode);
+
+        egui::Window::new("Simulation Errors")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .default_height(320.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(match errors.len() {
+                        0 => "No errors in this run.".to_string(),
+                        1 => "1 error".to_string(),
+                        n => format!("{} errors", n),
+                    });
+                    if errors.len() >= ERROR_LOG_LIMIT {
+                        ui.weak(format!("(keeps the last {})", ERROR_LOG_LIMIT));
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui
+                            .add_enabled(!errors.is_empty(), egui::Button::new("Clear errors"))
+                            .clicked()
+                        {
+                            clear = true;
+                        }
+                    });
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                    for (index, error) in errors.iter().enumerate() {
+                        ui.push_id(index, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.monospace(format!("Step {}", error.step));
+                                let name = flowchart
+                                    .nodes
+                                    .get(&error.node)
+                                    .map_or_else(|| "(deleted node)".to_string(), |n| n.name.clone());
+                                if ui.link(name).on_hover_text("Select this node").clicked() {
+                                    select = Some(error.node);
+                                }
+                                if let Some(trace_id) = error.trace_id {
+                                    if ui
+                                        .link(egui::RichText::new(short_trace_id(trace_id)).monospace())
+                                        .on_hover_text("Trace this message")
+                                        .clicked()
+                                    {
+                                        trace = Some(trace_id);
+                                    }
+                                }
+                            });
+                            ui.colored_label(egui::Color32::from_rgb(220, 80, 80), &error.message);
+                            egui::CollapsingHeader::new("Payload")
+                                .id_salt("error_payload")
+                                .show(ui, |ui| show_json_tree(ui, &error.payload, palette));
+                            ui.separator();
+                        });
+                    }
+                });
+            });
+
+        if let Some(node_id) = select.filter(|id| self.flowchart.nodes.contains_key(id)) {
+            self.interaction.selected_nodes.clear();
+            self.interaction.selected_connection = None;
+            self.interaction.selected_group = None;
+            self.interaction.selected_node = Some(node_id);
+            self.error_node = Some(node_id);
+        }
+        if let Some(trace_id) = trace {
+            self.start_trace(trace_id);
+        }
+        if clear {
+            self.simulation_engine.clear_errors();
+            self.error_node = None;
+        }
+        if !keep_open {
+            self.show_errors_dialog = false;
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Events panel.
+//!
+//! Lists the simulation events recorded in [`crate::events::EventLog`] below
+//! the canvas, filtered by kind and node. In follow mode the newest events
+//! stay in view as the simulation runs.
+
+use super::state::FlowchartApp;
+use super::trace::short_trace_id;
+use crate::events::{EventKind, SimEvent};
+use crate::types::*;
+use eframe::egui;
+
+/// Height of one row in the events list.
+const EVENT_ROW_HEIGHT: f32 = 18.0;
+
+/// Color an event kind is labelled with.
+fn kind_color(kind: EventKind) -> egui::Color32 {
+    match kind {
+        EventKind::Produced => egui::Color32::from_rgb(110, 190, 110),
+        EventKind::Delivered => egui::Color32::from_rgb(90, 160, 230),
+        EventKind::Consumed => egui::Color32::from_rgb(150, 150, 200),
+        EventKind::Error => egui::Color32::from_rgb(220, 80, 80),
+        EventKind::Dropped => egui::Color32::from_rgb(230, 150, 60),
+    }
+}
+
+impl FlowchartApp {
+    /// Draws the events panel at the bottom of the window. Shown when
+    /// `show_events_panel` is true; must be drawn before the canvas.
+    pub(crate) fn draw_events_panel(&mut self, ctx: &egui::Context) {
+        let mut select: Option<NodeId> = None;
+        let mut trace: Option<TraceId> = None;
+        let mut clear = false;
+        let mut close = false;
+
+        let log = self.simulation_engine.events();
+        let flowchart = &self.flowchart;
+        let panel = &mut self.events_panel;
+        let name = |id: &NodeId| {
+            flowchart
+                .nodes
+                .get(id)
+                .map_or_else(|| "(deleted node)".to_string(), |n| n.name.clone())
+        };
+
+        egui::TopBottomPanel::bottom("events_panel")
+            .resizable(true)
+            .default_height(180.0)
+            .min_height(80.0)
+            .show(ctx, |ui| {
+                let was_following = panel.follow;
+                ui.horizontal(|ui| {
+                    ui.strong("Events");
+                    for kind in EventKind::ALL {
+                        let mut shown = panel.filter.shows_kind(kind);
+                        let label = egui::RichText::new(kind.label()).color(kind_color(kind));
+                        if ui.checkbox(&mut shown, label).changed() {
+                            panel.filter.set_kind_shown(kind, shown);
+                        }
+                    }
+                    ui.separator();
+                    let mut nodes: Vec<&FlowchartNode> = flowchart.nodes.values().collect();
+                    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+                    let selected = panel.filter.node.map_or_else(|| "All nodes".to_string(), |id| name(&id));
+                    egui::ComboBox::from_id_salt("events_node_filter")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut panel.filter.node, None, "All nodes");
+                            for node in nodes {
+                                ui.selectable_value(&mut panel.filter.node, Some(node.id), &node.name);
+                            }
+                        });
+                    ui.separator();
+                    ui.checkbox(&mut panel.follow, "Follow")
+                        .on_hover_text("Keep the newest events in view");
+                    if ui.button("Clear").clicked() {
+                        clear = true;
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("✖").on_hover_text("Hide the events panel").clicked() {
+                            close = true;
+                        }
+                    });
+                });
+
+                let events: Vec<&SimEvent> = log.iter().filter(|e| panel.filter.matches(e)).collect();
+                ui.weak(format!(
+                    "{} shown · {} kept of {} recorded (keeps the last {}, see Preferences)",
+                    events.len(),
+                    log.len(),
+                    log.recorded(),
+                    log.capacity()
+                ));
+                ui.separator();
+
+                let mut area = egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(panel.follow);
+                if panel.follow && !was_following {
+                    area = area.vertical_scroll_offset(f32::MAX);
+                }
+                area.show_rows(ui, EVENT_ROW_HEIGHT, events.len(), |ui, rows| {
+                    for event in &events[rows] {
+                        ui.horizontal(|ui| {
+                            ui.monospace(format!("{:>6}", event.step));
+                            ui.colored_label(kind_color(event.kind), format!("{:<9}", event.kind.label()));
+                            if ui.link(name(&event.node)).clicked() {
+                                select = Some(event.node);
+                            }
+                            if let Some(from) = &event.from {
+                                ui.weak("←");
+                                if ui.link(name(from)).clicked() {
+                                    select = Some(*from);
+                                }
+                            }
+                            if let Some(trace_id) = event.trace_id {
+                                if ui
+                                    .link(egui::RichText::new(short_trace_id(trace_id)).monospace())
+                                    .on_hover_text("Trace this message")
+                                    .clicked()
+                                {
+                                    trace = Some(trace_id);
+                                }
+                            }
+                            if !event.detail.is_empty() {
+                                ui.weak(&event.detail);
+                            }
+                        });
+                    }
+                });
+            });
+
+        if let Some(node_id) = select.filter(|id| self.flowchart.nodes.contains_key(id)) {
+            self.interaction.selected_nodes.clear();
+            self.interaction.selected_connection = None;
+            self.interaction.selected_group = None;
+            self.interaction.selected_node = Some(node_id);
+        }
+        if let Some(trace_id) = trace {
+            self.start_trace(trace_id);
+        }
+        if clear {
+            self.simulation_engine.events_mut().clear();
+        }
+        if close {
+            self.show_events_panel = false;
+        }
+    }
+}
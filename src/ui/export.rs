@@ -50,48 +50,16 @@ impl FlowchartApp {
         {
             let (svg, width, height) = self.build_svg_with_options(ctx, options);
 
-            use tiny_skia::Pixmap;
-            use usvg;
-
-            // Parse SVG
-            let mut opt = usvg::Options::default();
             let mut db = fontdb::Database::new();
             db.load_system_fonts();
-            opt.fontdb = Arc::new(db);
-
-            let tree = match usvg::Tree::from_data(svg.as_bytes(), &opt) {
-                Ok(t) => t,
+            let pixmap = match rasterize_svg(&svg, width, height, options, db) {
+                Ok(p) => p,
                 Err(e) => {
-                    eprintln!("Failed to parse SVG for PNG export: {}", e);
+                    eprintln!("{}", e);
                     return;
                 }
             };
 
-            // Scale handling
-            let scale = options.png_scale.clamp(0.25, 8.0);
-            let out_w = ((width as f32) * scale).round().max(1.0) as u32;
-            let out_h = ((height as f32) * scale).round().max(1.0) as u32;
-
-            // Render into a raster pixmap
-            let mut pixmap = match Pixmap::new(out_w, out_h) {
-                Some(p) => p,
-                None => {
-                    eprintln!("Failed to create pixmap {}x{}", out_w, out_h);
-                    return;
-                }
-            };
-
-            // Optional background fill
-            if options.include_background {
-                let c = options.background_color;
-                pixmap.fill(tiny_skia::Color::from_rgba8(c.r(), c.g(), c.b(), c.a()));
-            }
-
-            // Render using resvg's default renderer with scaling
-            let mut pmut = pixmap.as_mut();
-            let transform = tiny_skia::Transform::from_scale(scale, scale);
-            let _ = resvg::render(&tree, transform, &mut pmut);
-
             // Save via a dialog
             tokio::spawn(async move {
                 if let Some(handle) = rfd::AsyncFileDialog::new()
@@ -232,7 +200,10 @@ impl FlowchartApp {
     }
 
     /// Build an SVG string for the given options. Returns (svg, width, height).
-    fn build_svg_with_options(
+    ///
+    /// Nodes and groups are drawn in their stacking order, ties in id order,
+    /// so a document always gives the same SVG.
+    pub(crate) fn build_svg_with_options(
         &self,
         ctx: &eframe::egui::Context,
         options: &ExportOptions,
@@ -250,7 +221,7 @@ impl FlowchartApp {
         // Determine filtered elements per scope
         let mut included_node_ids: std::collections::HashSet<NodeId> = std::collections::HashSet::new();
         match options.scope {
-            ExportScope::WholeGraph => {
+            ExportScope::WholeGraph | ExportScope::Area(_) => {
                 for id in self.flowchart.nodes.keys() {
                     included_node_ids.insert(*id);
                 }
@@ -265,8 +236,11 @@ impl FlowchartApp {
             }
         }
 
+        let nodes: Vec<_> = self.flowchart.nodes_by_z_order().into_iter().map(|node| (&node.id, node)).collect();
+        let groups: Vec<_> = self.flowchart.groups_by_z_order().into_iter().map(|group| (&group.id, group)).collect();
+
         // Include nodes
-        for (id, node) in &self.flowchart.nodes {
+        for (id, node) in nodes.iter().copied() {
             if !included_node_ids.is_empty() && !included_node_ids.contains(id) {
                 continue;
             }
@@ -279,7 +253,7 @@ impl FlowchartApp {
         }
 
         // Include groups
-        for (gid, group) in &self.flowchart.groups {
+        for (gid, group) in groups.iter().copied() {
             // Skip groups with no included members when exporting selection only
             if !included_node_ids.is_empty() {
                 let has_member = group.members.iter().any(|m| included_node_ids.contains(m));
@@ -317,12 +291,20 @@ impl FlowchartApp {
             max_y = node_h;
         }
 
+        // An area is drawn as a whole, cutting through whatever crosses its edges
+        if let ExportScope::Area(area) = options.scope {
+            min_x = area.min.x;
+            min_y = area.min.y;
+            max_x = area.max.x;
+            max_y = area.max.y;
+        }
+
         let width = ((max_x - min_x) + 2.0 * margin).ceil().max(1.0) as u32;
         let height = ((max_y - min_y) + 2.0 * margin).ceil().max(1.0) as u32;
 
         // Helper closures to map world->svg coordinates
-        let map_x = |x: f32| (x - min_x + margin);
-        let map_y = |y: f32| (y - min_y + margin);
+        let map_x = |x: f32| x - min_x + margin;
+        let map_y = |y: f32| y - min_y + margin;
 
         let mut out = String::new();
         use std::fmt::Write as _;
@@ -391,7 +373,7 @@ impl FlowchartApp {
         }
 
         // Groups (background)
-        for (gid, group) in &self.flowchart.groups {
+        for (gid, group) in groups.iter().copied() {
             if !included_node_ids.is_empty() {
                 let has_member = group.members.iter().any(|m| included_node_ids.contains(m));
                 if !has_member { continue; }
@@ -469,28 +451,29 @@ impl FlowchartApp {
             sc.r(), sc.g(), sc.b(), options.stroke_width
         );
         for conn in &self.flowchart.connections {
-            if !included_node_ids.is_empty() {
-                if !(included_node_ids.contains(&conn.from) && included_node_ids.contains(&conn.to)) {
-                    continue;
-                }
+            if !included_node_ids.is_empty()
+                && (!included_node_ids.contains(&conn.from) || !included_node_ids.contains(&conn.to))
+            {
+                continue;
             }
-            if let (Some(from), Some(to)) = (
-                self.flowchart.nodes.get(&conn.from),
-                self.flowchart.nodes.get(&conn.to),
-            ) {
-                let (sx, sy) = (map_x(from.position.0), map_y(from.position.1));
-                let (tx, ty) = (map_x(to.position.0), map_y(to.position.1));
+            if let Some((from, to)) = self.connection_endpoints(conn.from, conn.to) {
+                let (sx, sy) = (map_x(from.x), map_y(from.y));
+                let (tx, ty) = (map_x(to.x), map_y(to.y));
+                // The connection's own style, relative to the export stroke
+                let width = options.stroke_width * conn.style.width / LineStyle::default().width;
+                let color = conn.style.color.unwrap_or([sc.r(), sc.g(), sc.b()]);
+                let attributes = svg_line_attributes(&conn.style, width);
                 // Arrow shape parameters derived from stroke width
-                let arrow_len = 6.0 + options.stroke_width * 2.0;
+                let arrow_len = 6.0 + width * 2.0;
                 let arrow_half_w = arrow_len * 0.6;
                 match options.connection_style {
                     ConnectionStyle::Straight => {
                         let _ = writeln!(
                             out,
-                            "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" />",
-                            sx, sy, tx, ty
+                            "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"{} />",
+                            sx, sy, tx, ty, attributes
                         );
-                        // Mid-point arrow polygon oriented along the line
+                        // Arrow polygon oriented along the line
                         let dx = tx - sx;
                         let dy = ty - sy;
                         let dist = (dx * dx + dy * dy).sqrt().max(1e-6);
@@ -498,18 +481,21 @@ impl FlowchartApp {
                         let uy = dy / dist;
                         let px = -uy;
                         let py = ux;
-                        let cx = sx + dx * 0.5;
-                        let cy = sy + dy * 0.5;
+                        // Tip on the target's border
+                        let cx = tx - ux * arrow_len;
+                        let cy = ty - uy * arrow_len;
                         let tipx = cx + ux * arrow_len;
                         let tipy = cy + uy * arrow_len;
                         let leftx = cx - ux * arrow_len + px * arrow_half_w;
                         let lefty = cy - uy * arrow_len + py * arrow_half_w;
                         let rightx = cx - ux * arrow_len - px * arrow_half_w;
                         let righty = cy - uy * arrow_len - py * arrow_half_w;
-                        let _ = writeln!(
-                            out,
-                            "  <polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" fill=\"#{:02x}{:02x}{:02x}\" />",
-                            tipx, tipy, leftx, lefty, rightx, righty, sc.r(), sc.g(), sc.b()
+                        write_svg_arrowhead(
+                            &mut out,
+                            conn.style.arrow,
+                            [(tipx, tipy), (leftx, lefty), (rightx, righty)],
+                            color,
+                            width,
                         );
                     }
                     ConnectionStyle::Curved => {
@@ -526,11 +512,11 @@ impl FlowchartApp {
                         let c2y = sy + dy * 0.75 - ny * offset;
                         let _ = writeln!(
                             out,
-                            "  <path d=\"M{:.1},{:.1} C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" />",
-                            sx, sy, c1x, c1y, c2x, c2y, tx, ty
+                            "  <path d=\"M{:.1},{:.1} C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\"{} />",
+                            sx, sy, c1x, c1y, c2x, c2y, tx, ty, attributes
                         );
-                        // Arrow at t=0.5 along the bezier using tangent for orientation
-                        let t = 0.5_f32;
+                        // Arrow at the end of the bezier using tangent for orientation
+                        let t = 1.0_f32;
                         let omt = 1.0 - t;
                         // Position on curve B(t)
                         let bx = omt * omt * omt * sx
@@ -553,16 +539,18 @@ impl FlowchartApp {
                         let uy = dyb / dlen;
                         let px = -uy;
                         let py = ux;
-                        let tipx = bx + ux * arrow_len;
-                        let tipy = by + uy * arrow_len;
-                        let leftx = bx - ux * arrow_len + px * arrow_half_w;
-                        let lefty = by - uy * arrow_len + py * arrow_half_w;
-                        let rightx = bx - ux * arrow_len - px * arrow_half_w;
-                        let righty = by - uy * arrow_len - py * arrow_half_w;
-                        let _ = writeln!(
-                            out,
-                            "  <polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" fill=\"#{:02x}{:02x}{:02x}\" />",
-                            tipx, tipy, leftx, lefty, rightx, righty, sc.r(), sc.g(), sc.b()
+                        let tipx = bx;
+                        let tipy = by;
+                        let leftx = bx - ux * arrow_len * 2.0 + px * arrow_half_w;
+                        let lefty = by - uy * arrow_len * 2.0 + py * arrow_half_w;
+                        let rightx = bx - ux * arrow_len * 2.0 - px * arrow_half_w;
+                        let righty = by - uy * arrow_len * 2.0 - py * arrow_half_w;
+                        write_svg_arrowhead(
+                            &mut out,
+                            conn.style.arrow,
+                            [(tipx, tipy), (leftx, lefty), (rightx, righty)],
+                            color,
+                            width,
                         );
                     }
                 }
@@ -571,7 +559,7 @@ impl FlowchartApp {
         let _ = writeln!(out, "</g>");
 
         // Nodes
-        for (id, node) in &self.flowchart.nodes {
+        for (id, node) in nodes.iter().copied() {
             if !included_node_ids.is_empty() && !included_node_ids.contains(id) {
                 continue;
             }
@@ -583,6 +571,7 @@ impl FlowchartApp {
                 NodeType::Producer { .. } => ("#90EE90", "#000000"), // lightgreen
                 NodeType::Consumer { .. } => ("#FF9999", "#000000"), // light red approx
                 NodeType::Transformer { .. } => ("#ADD8E6", "#000000"), // lightblue
+                NodeType::Reference { .. } => ("#E6D28C", "#000000"), // sand
             };
             let _ = writeln!(
                 out,
@@ -635,6 +624,89 @@ impl FlowchartApp {
     }
 }
 
+/// Renders `svg` (of size `width` x `height`) at `options.png_scale`, with
+/// text set in the fonts of `fonts`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn rasterize_svg(
+    svg: &str,
+    width: u32,
+    height: u32,
+    options: &ExportOptions,
+    fonts: fontdb::Database,
+) -> Result<tiny_skia::Pixmap, String> {
+    // Parse SVG
+    let opt = usvg::Options {
+        fontdb: Arc::new(fonts),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_data(svg.as_bytes(), &opt)
+        .map_err(|e| format!("Failed to parse SVG for PNG export: {}", e))?;
+
+    // Scale handling
+    let scale = options.png_scale.clamp(0.25, 8.0);
+    let out_w = ((width as f32) * scale).round().max(1.0) as u32;
+    let out_h = ((height as f32) * scale).round().max(1.0) as u32;
+
+    // Render into a raster pixmap
+    let mut pixmap = tiny_skia::Pixmap::new(out_w, out_h)
+        .ok_or_else(|| format!("Failed to create pixmap {}x{}", out_w, out_h))?;
+
+    // Optional background fill
+    if options.include_background {
+        let c = options.background_color;
+        pixmap.fill(tiny_skia::Color::from_rgba8(c.r(), c.g(), c.b(), c.a()));
+    }
+
+    // Render using resvg's default renderer with scaling
+    let transform = tiny_skia::Transform::from_scale(scale, scale);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+/// Attributes a connection line's `style` adds to the shared connection
+/// stroke, each starting with a space; empty for the default style.
+fn svg_line_attributes(style: &LineStyle, width: f32) -> String {
+    let mut attributes = String::new();
+    if let Some([r, g, b]) = style.color {
+        attributes.push_str(&format!(" stroke=\"#{:02x}{:02x}{:02x}\"", r, g, b));
+    }
+    if style.width != LineStyle::default().width {
+        attributes.push_str(&format!(" stroke-width=\"{:.1}\"", width));
+    }
+    match style.dash {
+        LineDash::Solid => {}
+        LineDash::Dashed => attributes.push_str(&format!(" stroke-dasharray=\"{:.1},{:.1}\"", width * 4.0, width * 2.4)),
+        LineDash::Dotted => {
+            attributes.push_str(&format!(" stroke-dasharray=\"0,{:.1}\" stroke-linecap=\"round\"", width * 2.5))
+        }
+    }
+    attributes
+}
+
+/// Writes the arrowhead `head` with its tip and two wing points.
+fn write_svg_arrowhead(out: &mut String, head: ArrowHead, points: [(f32, f32); 3], color: [u8; 3], width: f32) {
+    use std::fmt::Write as _;
+    let [(tipx, tipy), (leftx, lefty), (rightx, righty)] = points;
+    let [r, g, b] = color;
+    match head {
+        ArrowHead::Filled => {
+            let _ = writeln!(
+                out,
+                "  <polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" fill=\"#{:02x}{:02x}{:02x}\" />",
+                tipx, tipy, leftx, lefty, rightx, righty, r, g, b
+            );
+        }
+        ArrowHead::Open => {
+            let _ = writeln!(
+                out,
+                "  <polyline points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" stroke=\"#{:02x}{:02x}{:02x}\" stroke-width=\"{:.1}\" />",
+                leftx, lefty, tipx, tipy, rightx, righty, r, g, b, width
+            );
+        }
+        ArrowHead::None => {}
+    }
+}
+
 fn escape_xml(input: &str) -> String {
     let mut s = String::with_capacity(input.len());
     for ch in input.chars() {
@@ -738,12 +810,6 @@ fn round_polygon_points_svg(points: &[(f32, f32)], radius_px: f32, segments_per_
     #[inline]
     fn perp_left(vx: f32, vy: f32) -> (f32, f32) { (-vy, vx) }
 
-    #[inline]
-    fn normalize(vx: f32, vy: f32) -> (f32, f32) {
-        let len = (vx * vx + vy * vy).sqrt().max(1e-6);
-        (vx / len, vy / len)
-    }
-
     #[inline]
     fn project_point_on_line(ax: f32, ay: f32, dx: f32, dy: f32, px: f32, py: f32) -> (f32, f32) {
         let dir2 = (dx * dx + dy * dy).max(1e-12);
@@ -754,6 +820,7 @@ fn round_polygon_points_svg(points: &[(f32, f32)], radius_px: f32, segments_per_
     }
 
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     fn intersect_lines(ax: f32, ay: f32, d1x: f32, d1y: f32, bx: f32, by: f32, d2x: f32, d2y: f32) -> Option<(f32, f32)> {
         let denom = d1x * d2y - d1y * d2x;
         if denom.abs() < 1e-6 { return None; }
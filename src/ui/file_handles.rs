@@ -0,0 +1,159 @@
+//! File System Access API support for the web build.
+//!
+//! Where the browser offers `showOpenFilePicker`/`showSaveFilePicker`, files
+//! are opened and saved through handles so that Save can write back to the
+//! same file. The handle of the open document is remembered here; browsers
+//! without the API keep using the download/upload flow in `file_ops`.
+
+use crate::wasm_bindgen::{JsCast, JsValue};
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use std::cell::RefCell;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::DomException;
+
+thread_local! {
+    /// Handle of the currently open document, with the path it was opened as.
+    static CURRENT_HANDLE: RefCell<Option<(String, FileHandle)>> = const { RefCell::new(None) };
+}
+
+/// A `FileSystemFileHandle` picked by the user.
+#[derive(Clone)]
+pub(crate) struct FileHandle(JsValue);
+
+/// Returns true when the browser provides the file picker APIs.
+pub(crate) fn is_supported() -> bool {
+    let Some(window) = web_sys::window() else {
+        return false;
+    };
+    ["showOpenFilePicker", "showSaveFilePicker"]
+        .iter()
+        .all(|name| {
+            Reflect::get(&window, &JsValue::from_str(name))
+                .map(|f| f.is_function())
+                .unwrap_or(false)
+        })
+}
+
+/// Calls `target[method](...args)` and awaits the returned promise.
+async fn call_async(target: &JsValue, method: &str, args: &[JsValue]) -> Result<JsValue, JsValue> {
+    let function: Function = Reflect::get(target, &JsValue::from_str(method))?.dyn_into()?;
+    let args: Array = args.iter().collect();
+    let promise: Promise = function.apply(target, &args)?.dyn_into()?;
+    JsFuture::from(promise).await
+}
+
+/// True when the user dismissed a picker rather than something failing.
+fn is_abort(error: &JsValue) -> bool {
+    error
+        .dyn_ref::<DomException>()
+        .is_some_and(|e| e.name() == "AbortError")
+}
+
+/// Describes a rejected file system call for the user.
+fn describe_error(action: &str, error: &JsValue) -> String {
+    match error.dyn_ref::<DomException>() {
+        Some(e) if e.name() == "NotAllowedError" => {
+            format!("Failed to {}: permission to the file was denied", action)
+        }
+        Some(e) => format!("Failed to {}: {}", action, e.message()),
+        None => format!("Failed to {}", action),
+    }
+}
+
+/// Picker options accepting flowchart JSON files.
+fn picker_options(suggested_name: Option<&str>) -> Result<Object, JsValue> {
+    let extensions: Array = [
+        JsValue::from_str(".json"),
+        JsValue::from_str(&format!(".{}", crate::constants::FLOWCHART_FILE_EXTENSION)),
+    ]
+    .iter()
+    .collect();
+    let accept = Object::new();
+    Reflect::set(&accept, &JsValue::from_str("application/json"), &extensions)?;
+    let file_type = Object::new();
+    Reflect::set(&file_type, &JsValue::from_str("description"), &JsValue::from_str("Flowchart JSON"))?;
+    Reflect::set(&file_type, &JsValue::from_str("accept"), &accept)?;
+
+    let options = Object::new();
+    let types: Array = [JsValue::from(file_type)].iter().collect();
+    Reflect::set(&options, &JsValue::from_str("types"), &types)?;
+    if let Some(name) = suggested_name {
+        Reflect::set(&options, &JsValue::from_str("suggestedName"), &JsValue::from_str(name))?;
+    }
+    Ok(options)
+}
+
+/// Asks the user for a file to open. `Ok(None)` means the picker was dismissed.
+pub(crate) async fn pick_file_to_open() -> Result<Option<FileHandle>, String> {
+    let window: JsValue = web_sys::window().ok_or("No window found")?.into();
+    let options = picker_options(None).map_err(|e| describe_error("open file", &e))?;
+    match call_async(&window, "showOpenFilePicker", &[options.into()]).await {
+        Ok(handles) => Ok(Array::from(&handles).iter().next().map(FileHandle)),
+        Err(e) if is_abort(&e) => Ok(None),
+        Err(e) => Err(describe_error("open file", &e)),
+    }
+}
+
+/// Asks the user where to save. `Ok(None)` means the picker was dismissed.
+pub(crate) async fn pick_file_to_save(suggested_name: &str) -> Result<Option<FileHandle>, String> {
+    let window: JsValue = web_sys::window().ok_or("No window found")?.into();
+    let options = picker_options(Some(suggested_name)).map_err(|e| describe_error("save file", &e))?;
+    match call_async(&window, "showSaveFilePicker", &[options.into()]).await {
+        Ok(handle) => Ok(Some(FileHandle(handle))),
+        Err(e) if is_abort(&e) => Ok(None),
+        Err(e) => Err(describe_error("save file", &e)),
+    }
+}
+
+impl FileHandle {
+    /// The file's name, which the app uses as the document path.
+    pub(crate) fn name(&self) -> String {
+        Reflect::get(&self.0, &JsValue::from_str("name"))
+            .ok()
+            .and_then(|n| n.as_string())
+            .unwrap_or_else(|| "flowchart.json".to_string())
+    }
+
+    /// Reads the whole file as text.
+    pub(crate) async fn read(&self) -> Result<String, String> {
+        let file = call_async(&self.0, "getFile", &[])
+            .await
+            .map_err(|e| describe_error("read file", &e))?;
+        call_async(&file, "text", &[])
+            .await
+            .map_err(|e| describe_error("read file", &e))?
+            .as_string()
+            .ok_or_else(|| "File content is not a string".to_string())
+    }
+
+    /// Replaces the file's contents with `content`.
+    ///
+    /// The browser may ask the user for write permission the first time.
+    pub(crate) async fn write(&self, content: &str) -> Result<(), String> {
+        let writable = call_async(&self.0, "createWritable", &[])
+            .await
+            .map_err(|e| describe_error("save file", &e))?;
+        call_async(&writable, "write", &[JsValue::from_str(content)])
+            .await
+            .map_err(|e| describe_error("save file", &e))?;
+        call_async(&writable, "close", &[])
+            .await
+            .map(|_| ())
+            .map_err(|e| describe_error("save file", &e))
+    }
+}
+
+/// Remembers `handle` as the file behind the document opened as `path`.
+pub(crate) fn remember(path: &str, handle: FileHandle) {
+    CURRENT_HANDLE.with(|h| *h.borrow_mut() = Some((path.to_string(), handle)));
+}
+
+/// The remembered handle, if it belongs to the document at `path`.
+pub(crate) fn handle_for(path: &str) -> Option<FileHandle> {
+    CURRENT_HANDLE.with(|h| {
+        h.borrow()
+            .as_ref()
+            .filter(|(p, _)| p == path)
+            .map(|(_, handle)| handle.clone())
+    })
+}
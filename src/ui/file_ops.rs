@@ -1,13 +1,52 @@
 //! File operations for saving and loading flowcharts.
 //!
 //! This module handles all file I/O operations including native file dialogs
-//! and WASM-compatible browser-based file operations.
+//! and WASM-compatible browser-based file operations. On the web, browsers with
+//! the File System Access API open and save through file handles (see
+//! `file_handles`); others fall back to upload and download.
 
-use super::state::{FileOperationResult, FlowchartApp, PendingLoadOperation, PendingSaveOperation};
-use crate::types::Flowchart;
+use super::state::{
+    BrowserDocumentsMode, BrowserDocumentsState, FileOperationResult, FlowchartApp,
+    PendingConfirmAction, PendingConfirmation, PendingLoadOperation, PendingSaveOperation,
+    StoreOperation,
+};
+use crate::constants::{BROWSER_DOCUMENT_PREFIX, FLOWCHART_FILE_EXTENSION};
+use crate::types::{Flowchart, NodeId, NodeType};
 use crate::ui::UndoHistory;
 use eframe::egui;
 use crate::examples::{build_example, all_examples, ExampleKind};
+use std::sync::mpsc::Sender;
+
+#[cfg(target_arch = "wasm32")]
+use super::file_handles;
+#[cfg(not(target_arch = "wasm32"))]
+use super::load_progress::spawn_load;
+
+/// A background request to the browser's document store.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+enum StoreTask {
+    /// Store a document under a name
+    Save { name: String, content: String },
+    /// Read a document by name
+    Load(String),
+    /// List all documents
+    List,
+    /// Delete a document by name
+    Delete(String),
+}
+
+/// Parses the contents of a picked document, so it can happen off the UI thread.
+fn parse_document(path: String, content: String) -> FileOperationResult {
+    match Flowchart::from_json(&content) {
+        Ok(flowchart) => FileOperationResult::DocumentParsed(path, Box::new(flowchart), content),
+        Err(e) => FileOperationResult::OperationFailed(format!("Failed to parse flowchart: {}", e)),
+    }
+}
+
+/// Returns the document store name encoded in a browser document path.
+pub(crate) fn browser_document_name(path: &str) -> Option<&str> {
+    path.strip_prefix(BROWSER_DOCUMENT_PREFIX)
+}
 
 impl FlowchartApp {
     /// Handles pending file operations for both native and WASM platforms.
@@ -28,44 +67,173 @@ impl FlowchartApp {
             }
         }
         for result in op_results {
+            if self.load_result_cancelled(&result) {
+                continue;
+            }
             match result {
                 FileOperationResult::SaveCompleted(path) => {
+                    self.last_browser_document = browser_document_name(&path).map(str::to_string);
                     self.file.current_path = Some(path);
-                    self.file.has_unsaved_changes = false;
+                    self.file.mark_saved();
+                    crate::crash::record_event("Saved document");
                     println!("File saved successfully");
                 }
                 FileOperationResult::LoadCompleted(path, content) => match Flowchart::from_json(&content) {
-                    Ok(flowchart) => {
-                        self.apply_loaded_flowchart_from_source(flowchart, Some(path));
-                        println!("File loaded successfully");
+                    Ok(flowchart) => self.open_loaded_document(path, flowchart, content),
+                    Err(e) => {
+                        eprintln!("Failed to parse flowchart: {}", e);
                     }
+                },
+                FileOperationResult::DocumentParsed(path, flowchart, content) => {
+                    self.open_loaded_document(path, *flowchart, content);
+                }
+                FileOperationResult::LoadStarted(progress) => {
+                    self.file.load_progress = Some(progress);
+                }
+                FileOperationResult::LoadProgressed(bytes_read) => {
+                    if let Some(progress) = self.file.load_progress.as_mut() {
+                        progress.bytes_read = bytes_read;
+                    }
+                }
+                FileOperationResult::LoadCancelled => {
+                    println!("Loading cancelled");
+                }
+                FileOperationResult::ExportCompleted(path) => {
+                    println!("Selection exported to {}", path);
+                }
+                FileOperationResult::MergeLoaded(path, content) => match Flowchart::from_json(&content) {
+                    Ok(flowchart) => self.begin_merge(path, flowchart),
                     Err(e) => {
                         eprintln!("Failed to parse flowchart: {}", e);
                     }
                 },
+                FileOperationResult::BrowserDocumentsListed(documents) => {
+                    if let Some(dialog) = self.browser_documents.as_mut() {
+                        dialog.documents = Some(documents);
+                        dialog.error = None;
+                    }
+                }
+                FileOperationResult::BrowserDocumentDeleted(name) => {
+                    if let Some(documents) = self
+                        .browser_documents
+                        .as_mut()
+                        .and_then(|d| d.documents.as_mut())
+                    {
+                        documents.retain(|d| d.name != name);
+                    }
+                    if self.last_browser_document.as_deref() == Some(name.as_str()) {
+                        self.last_browser_document = None;
+                    }
+                    // The document stays open but no longer has a place to save to
+                    if self.file.current_path.as_deref().and_then(browser_document_name) == Some(name.as_str()) {
+                        self.file.current_path = None;
+                        self.file.has_unsaved_changes = true;
+                    }
+                }
+                FileOperationResult::FileChangedOnDisk(path) => {
+                    self.note_file_changed_on_disk(&path);
+                }
+                FileOperationResult::PluginLoaded(_path, content) => {
+                    self.install_plugin(&content);
+                }
+                FileOperationResult::RunBaselineLoaded(path, content) => {
+                    self.set_run_baseline(&path, &content);
+                }
+                FileOperationResult::BpmnLoaded(path, content) => match crate::bpmn::from_bpmn(&content) {
+                    Ok(flowchart) => self.begin_merge(path, flowchart),
+                    Err(e) => {
+                        eprintln!("Failed to import BPMN: {}", e);
+                    }
+                },
+                FileOperationResult::TrafficTraceLoaded(path, content) => {
+                    self.open_traffic_import(&path, &content);
+                }
+                FileOperationResult::NodeCsvLoaded(path, content) => {
+                    self.open_node_csv_import(&path, &content);
+                }
+                FileOperationResult::OpenRequested(path) => {
+                    self.request_open_path(path, PendingConfirmAction::OpenFile);
+                }
                 FileOperationResult::OperationFailed(error) => {
                     eprintln!("File operation failed: {}", error);
+                    crate::crash::record_event("File operation failed");
+                    // The browser documents dialog shows its own errors
+                    match self.browser_documents.as_mut() {
+                        Some(dialog) => dialog.error = Some(error),
+                        None => self.dialogs.error("File Operation Failed", error),
+                    }
                 }
             }
         }
 
+        // Follow the open file on disk
+        self.sync_file_watcher(ctx);
+        self.check_file_changed_on_disk(ctx);
+
         // Handle pending save operations
         if let Some(save_op) = self.file.pending_save_operation.take() {
             let ctx = ctx.clone();
-            let flowchart_json = self.flowchart.to_json().unwrap_or_default();
+            let flowchart_json = match save_op {
+                PendingSaveOperation::ExportSelection => self
+                    .flowchart
+                    .extract_subset(&self.selected_node_ids())
+                    .to_json(),
+                _ => self.flowchart.to_json(),
+            }
+            .unwrap_or_default();
             let sender = self.file.file_operation_sender.clone();
+            if matches!(save_op, PendingSaveOperation::Save | PendingSaveOperation::SaveAs) {
+                // Our own write must not be mistaken for an external change
+                self.file.disk_snapshot = Some(flowchart_json.clone());
+            }
 
             match save_op {
-                PendingSaveOperation::SaveAs => {
+                PendingSaveOperation::BrowserDocument(name) => {
+                    Self::spawn_store_task(
+                        &ctx,
+                        sender,
+                        StoreTask::Save {
+                            name,
+                            content: flowchart_json,
+                        },
+                    );
+                }
+                PendingSaveOperation::SaveAs | PendingSaveOperation::ExportSelection => {
+                    // Exports go to a new file and leave the document's own path alone
+                    let (file_name, completed): (&str, fn(String) -> FileOperationResult) =
+                        if matches!(save_op, PendingSaveOperation::ExportSelection) {
+                            ("selection.json", FileOperationResult::ExportCompleted)
+                        } else {
+                            ("flowchart.json", FileOperationResult::SaveCompleted)
+                        };
+
                     #[cfg(target_arch = "wasm32")]
-                    {
+                    if file_handles::is_supported() {
+                        let keep_handle = matches!(save_op, PendingSaveOperation::SaveAs);
+                        wasm_bindgen_futures::spawn_local(async move {
+                            let result = match file_handles::pick_file_to_save(file_name).await {
+                                Ok(Some(handle)) => {
+                                    let path = handle.name();
+                                    let written = handle.write(&flowchart_json).await;
+                                    if written.is_ok() && keep_handle {
+                                        file_handles::remember(&path, handle);
+                                    }
+                                    Some(written.map(|_| completed(path)))
+                                }
+                                Ok(None) => None,
+                                Err(e) => Some(Err(e)),
+                            };
+                            if let (Some(result), Some(tx)) = (result, sender) {
+                                let _ = tx.send(result.unwrap_or_else(FileOperationResult::OperationFailed));
+                            }
+                            ctx.request_repaint();
+                        });
+                    } else {
                         // Use synchronous download for Firefox compatibility
-                        match Self::trigger_download("flowchart.json", &flowchart_json) {
+                        match Self::trigger_download(file_name, &flowchart_json) {
                             Ok(_) => {
                                 if let Some(tx) = sender {
-                                    let _ = tx.send(FileOperationResult::SaveCompleted(
-                                        "flowchart.json".to_string(),
-                                    ));
+                                    let _ = tx.send(completed(file_name.to_string()));
                                 }
                             }
                             Err(e) => {
@@ -82,7 +250,7 @@ impl FlowchartApp {
                         tokio::spawn(async move {
                             if let Some(handle) = rfd::AsyncFileDialog::new()
                                 .add_filter("JSON", &["json"])
-                                .set_file_name("flowchart.json")
+                                .set_file_name(file_name)
                                 .save_file()
                                 .await
                             {
@@ -90,9 +258,7 @@ impl FlowchartApp {
                                 match std::fs::write(path, flowchart_json) {
                                     Ok(_) => {
                                         if let Some(tx) = sender {
-                                            let _ = tx.send(FileOperationResult::SaveCompleted(
-                                                path.display().to_string(),
-                                            ));
+                                            let _ = tx.send(completed(path.display().to_string()));
                                         }
                                     }
                                     Err(e) => {
@@ -134,10 +300,32 @@ impl FlowchartApp {
 
                         #[cfg(target_arch = "wasm32")]
                         {
-                            println!("Would have safed to {path}");
-                            // For WASM, we can't "save" to a previous path without user interaction
-                            // Fall back to Save As
-                            self.file.pending_save_operation = Some(PendingSaveOperation::SaveAs);
+                            if let Some(name) = browser_document_name(&path) {
+                                Self::spawn_store_task(
+                                    &ctx,
+                                    sender,
+                                    StoreTask::Save {
+                                        name: name.to_string(),
+                                        content: flowchart_json,
+                                    },
+                                );
+                            } else if let Some(handle) = file_handles::handle_for(&path) {
+                                // Write back to the file the document was opened from
+                                wasm_bindgen_futures::spawn_local(async move {
+                                    let result = match handle.write(&flowchart_json).await {
+                                        Ok(_) => FileOperationResult::SaveCompleted(path),
+                                        Err(e) => FileOperationResult::OperationFailed(e),
+                                    };
+                                    if let Some(tx) = sender {
+                                        let _ = tx.send(result);
+                                    }
+                                    ctx.request_repaint();
+                                });
+                            } else {
+                                // Without a file handle the browser can't write to a previous path
+                                // Fall back to Save As
+                                self.file.pending_save_operation = Some(PendingSaveOperation::SaveAs);
+                            }
                         }
                     } else {
                         self.file.pending_save_operation = Some(PendingSaveOperation::SaveAs);
@@ -146,74 +334,242 @@ impl FlowchartApp {
             }
         }
 
+        // Handle pending document store requests
+        if let Some(store_op) = self.file.pending_store_operation.take() {
+            let task = match store_op {
+                StoreOperation::List => StoreTask::List,
+                StoreOperation::Delete(name) => StoreTask::Delete(name),
+            };
+            Self::spawn_store_task(ctx, self.file.file_operation_sender.clone(), task);
+        }
+
         // Handle pending load operations
-        if let Some(_load_op) = self.file.pending_load_operation.take() {
+        if let Some(load_op) = self.file.pending_load_operation.take() {
             let ctx = ctx.clone();
             let sender = self.file.file_operation_sender.clone();
+            #[cfg(target_arch = "wasm32")]
+            let keep_handle = matches!(load_op, PendingLoadOperation::Load);
+            // Imports of other formats pick files of their own type
+            let import_filter: Option<(&str, &[&str])> = match load_op {
+                PendingLoadOperation::ImportBpmn => Some(("BPMN", &["bpmn", "xml"])),
+                PendingLoadOperation::TrafficTrace => Some(("JSON Lines", &["jsonl", "ndjson", "json"])),
+                PendingLoadOperation::NodeCsv => Some(("CSV", &["csv"])),
+                _ => None,
+            };
+            let (completed, direct_path): (fn(String, String) -> FileOperationResult, _) =
+                match load_op {
+                    PendingLoadOperation::Load => (parse_document, None),
+                    PendingLoadOperation::Merge => (FileOperationResult::MergeLoaded, None),
+                    PendingLoadOperation::Plugin => (FileOperationResult::PluginLoaded, None),
+                    PendingLoadOperation::RunBaseline => (FileOperationResult::RunBaselineLoaded, None),
+                    PendingLoadOperation::ImportBpmn => (FileOperationResult::BpmnLoaded, None),
+                    PendingLoadOperation::TrafficTrace => (FileOperationResult::TrafficTraceLoaded, None),
+                    PendingLoadOperation::NodeCsv => (FileOperationResult::NodeCsvLoaded, None),
+                    PendingLoadOperation::OpenPath(path) => (parse_document, Some(path)),
+                    PendingLoadOperation::BrowserDocument(name) => {
+                        // Read from the document store instead of a file
+                        Self::spawn_store_task(&ctx, sender, StoreTask::Load(name));
+                        return;
+                    }
+                };
 
             #[cfg(target_arch = "wasm32")]
             {
-                wasm_bindgen_futures::spawn_local(async move {
-                    match Self::show_open_file_picker().await {
-                        Some(file) => {
-                            let filename = file.name();
-                            match Self::read_file(file).await {
-                                Ok(content) => {
-                                    if let Some(tx) = sender {
-                                        let _ = tx.send(FileOperationResult::LoadCompleted(
-                                            filename, content,
-                                        ));
-                                    }
+                if let Some(path) = direct_path {
+                    // The browser cannot read arbitrary paths without a file picker
+                    if let Some(tx) = sender {
+                        let _ = tx.send(FileOperationResult::OperationFailed(format!(
+                            "Cannot open {} directly in the browser; use File → Load instead",
+                            path
+                        )));
+                    }
+                } else if file_handles::is_supported() && import_filter.is_none() {
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let result = match file_handles::pick_file_to_open().await {
+                            Ok(Some(handle)) => {
+                                let path = handle.name();
+                                let content = handle.read().await;
+                                if content.is_ok() && keep_handle {
+                                    file_handles::remember(&path, handle);
                                 }
-                                Err(e) => {
-                                    if let Some(tx) = sender {
-                                        let _ = tx.send(FileOperationResult::OperationFailed(e));
+                                Some(content.map(|content| completed(path, content)))
+                            }
+                            Ok(None) => None,
+                            Err(e) => Some(Err(e)),
+                        };
+                        if let (Some(result), Some(tx)) = (result, sender) {
+                            let _ = tx.send(result.unwrap_or_else(FileOperationResult::OperationFailed));
+                        }
+                        ctx.request_repaint();
+                    });
+                } else {
+                    let accept = match import_filter {
+                        Some((_, extensions)) => extensions.iter().map(|e| format!(".{}", e)).collect::<Vec<_>>().join(","),
+                        None => format!(".json,.{},application/json", FLOWCHART_FILE_EXTENSION),
+                    };
+                    wasm_bindgen_futures::spawn_local(async move {
+                        match Self::show_open_file_picker(&accept).await {
+                            Some(file) => {
+                                let filename = file.name();
+                                match Self::read_file(file).await {
+                                    Ok(content) => {
+                                        if let Some(tx) = sender {
+                                            let _ = tx.send(completed(filename, content));
+                                        }
+                                    }
+                                    Err(e) => {
+                                        if let Some(tx) = sender {
+                                            let _ = tx.send(FileOperationResult::OperationFailed(e));
+                                        }
                                     }
                                 }
                             }
+                            None => {
+                                eprintln!("Open dialog cancelled or API not supported");
+                            }
                         }
-                        None => {
-                            eprintln!("Open dialog cancelled or API not supported");
-                        }
-                    }
-                    ctx.request_repaint();
-                });
+                        ctx.request_repaint();
+                    });
+                }
             }
 
             #[cfg(not(target_arch = "wasm32"))]
             {
                 tokio::spawn(async move {
-                    if let Some(handle) = rfd::AsyncFileDialog::new()
-                        .add_filter("JSON", &["json"])
-                        .pick_file()
-                        .await
-                    {
-                        let path = handle.path();
-                        match std::fs::read_to_string(path) {
-                            Ok(json) => {
-                                if let Some(tx) = sender {
-                                    let _ = tx.send(FileOperationResult::LoadCompleted(
-                                        path.display().to_string(),
-                                        json,
-                                    ));
-                                }
-                            }
-                            Err(e) => {
-                                if let Some(tx) = sender {
-                                    let _ = tx.send(FileOperationResult::OperationFailed(format!(
-                                        "Failed to read file: {}",
-                                        e
-                                    )));
-                                }
-                            }
-                        }
+                    let (filter, extensions) = import_filter.unwrap_or(("Flowchart", &["json", FLOWCHART_FILE_EXTENSION]));
+                    let path = match direct_path {
+                        Some(path) => Some(std::path::PathBuf::from(path)),
+                        None => rfd::AsyncFileDialog::new()
+                            .add_filter(filter, extensions)
+                            .pick_file()
+                            .await
+                            .map(|handle| handle.path().to_path_buf()),
+                    };
+                    // Read on a thread of its own so large files don't hold up the runtime
+                    match path {
+                        Some(path) => spawn_load(path, completed, sender, ctx),
+                        None => ctx.request_repaint(),
                     }
-                    ctx.request_repaint();
                 });
             }
         }
     }
 
+    /// Runs a document store request in the background and reports its outcome
+    /// on the file operation channel.
+    ///
+    /// The document store is IndexedDB, so outside the web build every request fails.
+    fn spawn_store_task(
+        ctx: &egui::Context,
+        sender: Option<Sender<FileOperationResult>>,
+        task: StoreTask,
+    ) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use super::browser_store;
+            let ctx = ctx.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = match task {
+                    StoreTask::Save { name, content } => {
+                        browser_store::put_document(&name, &content)
+                            .await
+                            .map(|_| FileOperationResult::SaveCompleted(format!("{}{}", BROWSER_DOCUMENT_PREFIX, name)))
+                    }
+                    StoreTask::Load(name) => match browser_store::get_document(&name).await {
+                        Ok(Some(content)) => Ok(FileOperationResult::LoadCompleted(
+                            format!("{}{}", BROWSER_DOCUMENT_PREFIX, name),
+                            content,
+                        )),
+                        Ok(None) => Err(format!("No flowchart named \"{}\" is stored in this browser", name)),
+                        Err(e) => Err(e),
+                    },
+                    StoreTask::List => browser_store::list_documents()
+                        .await
+                        .map(FileOperationResult::BrowserDocumentsListed),
+                    StoreTask::Delete(name) => browser_store::delete_document(&name)
+                        .await
+                        .map(|_| FileOperationResult::BrowserDocumentDeleted(name)),
+                };
+                if let Some(tx) = sender {
+                    let _ = tx.send(result.unwrap_or_else(FileOperationResult::OperationFailed));
+                }
+                ctx.request_repaint();
+            });
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            drop(task);
+            if let Some(tx) = sender {
+                let _ = tx.send(FileOperationResult::OperationFailed(
+                    "Browser storage is only available in the web build".to_string(),
+                ));
+            }
+            ctx.request_repaint();
+        }
+    }
+
+    /// Opens the browser documents dialog and starts listing the stored documents.
+    pub fn open_browser_documents(&mut self, mode: BrowserDocumentsMode) {
+        let name = self
+            .file
+            .current_path
+            .as_deref()
+            .and_then(browser_document_name)
+            .unwrap_or("Untitled")
+            .to_string();
+        self.browser_documents = Some(BrowserDocumentsState {
+            mode,
+            documents: None,
+            error: None,
+            name,
+            confirm_delete: None,
+        });
+        self.file.pending_store_operation = Some(StoreOperation::List);
+    }
+
+    /// Requests the document stored in the browser under `name` to be opened.
+    pub fn open_browser_document(&mut self, name: &str) {
+        self.file.pending_load_operation = Some(PendingLoadOperation::BrowserDocument(name.to_string()));
+    }
+
+    /// Requests the current document to be stored in the browser under `name`.
+    pub fn save_browser_document(&mut self, name: &str) {
+        if self.file.is_example_readonly {
+            eprintln!(
+                "Saving is disabled while an example is loaded. Use File → New or File → Load to enable saving."
+            );
+            return;
+        }
+        self.file.pending_save_operation = Some(PendingSaveOperation::BrowserDocument(name.to_string()));
+    }
+
+    /// Reopens the browser document that was open when the app was last closed.
+    pub fn reopen_last_browser_document(&mut self) {
+        if let Some(name) = self.last_browser_document.clone() {
+            self.open_browser_document(&name);
+        }
+    }
+
+    /// Sets up app state saved on an earlier visit (web only): keeps the UI
+    /// settings and reopens the last browser document.
+    ///
+    /// State saved before documents were stored in the browser holds the
+    /// flowchart itself and names no document. That flowchart stays open,
+    /// marked unsaved, so it isn't lost and closing it asks to save it first.
+    pub fn restore_browser_session(&mut self) {
+        let legacy = self.last_browser_document.is_none() && !self.flowchart.nodes.is_empty();
+        let flowchart = legacy.then(|| std::mem::take(&mut self.flowchart));
+        self.reset_non_ui_fields();
+        match flowchart {
+            Some(flowchart) => {
+                self.apply_loaded_flowchart_from_source(flowchart, None);
+                self.file.has_unsaved_changes = true;
+            }
+            None => self.reopen_last_browser_document(),
+        }
+    }
+
     /// Triggers a file download in the browser (WASM only, Firefox-compatible).
     ///
     /// Creates a temporary anchor element with a blob URL and triggers a download.
@@ -228,27 +584,45 @@ impl FlowchartApp {
     /// `Ok(())` if successful, or an error message if the operation fails.
     #[cfg(target_arch = "wasm32")]
     pub(crate) fn trigger_download(filename: &str, content: &str) -> Result<(), String> {
+        let blob_parts = js_sys::Array::new();
+        blob_parts.push(&crate::wasm_bindgen::JsValue::from_str(content));
+        Self::download_blob_parts(filename, &blob_parts)
+    }
+
+    /// Triggers a browser download of binary `content`, such as a recorded run.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn trigger_download_bytes(filename: &str, content: &[u8]) -> Result<(), String> {
+        let blob_parts = js_sys::Array::new();
+        blob_parts.push(&js_sys::Uint8Array::from(content));
+        Self::download_blob_parts(filename, &blob_parts)
+    }
+
+    /// Downloads a Blob made of `blob_parts` under `filename`.
+    #[cfg(target_arch = "wasm32")]
+    fn download_blob_parts(filename: &str, blob_parts: &js_sys::Array) -> Result<(), String> {
         use crate::wasm_bindgen::JsCast;
 
         let window = web_sys::window().ok_or("No window found")?;
         let document = window.document().ok_or("No document found")?;
 
-        // Create a Blob containing the file content
-        let blob_parts = js_sys::Array::new();
-        blob_parts.push(&crate::wasm_bindgen::JsValue::from_str(content));
-
         let blob_options = web_sys::BlobPropertyBag::new();
         // Set content type based on file extension for better browser handling
         let content_type = if filename.ends_with(".svg") {
             "image/svg+xml"
         } else if filename.ends_with(".json") {
             "application/json"
+        } else if filename.ends_with(".bpmn") || filename.ends_with(".drawio") {
+            "application/xml"
+        } else if filename.ends_with(".gif") {
+            "image/gif"
+        } else if filename.ends_with(".zip") {
+            "application/zip"
         } else {
             "application/octet-stream"
         };
         blob_options.set_type(content_type);
 
-        let blob = web_sys::Blob::new_with_str_sequence_and_options(&blob_parts, &blob_options)
+        let blob = web_sys::Blob::new_with_buffer_source_sequence_and_options(blob_parts, &blob_options)
             .map_err(|_| "Failed to create blob")?;
 
         // Create object URL for the blob
@@ -290,11 +664,15 @@ impl FlowchartApp {
     ///
     /// Creates a temporary file input element and waits for the user to select a file.
     ///
+    /// # Arguments
+    ///
+    /// * `accept` - The file types offered, as in the input's `accept` attribute
+    ///
     /// # Returns
     ///
     /// The selected `File` object, or `None` if the user cancelled or the operation failed.
     #[cfg(target_arch = "wasm32")]
-    async fn show_open_file_picker() -> Option<web_sys::File> {
+    async fn show_open_file_picker(accept: &str) -> Option<web_sys::File> {
         use crate::wasm_bindgen::closure::Closure;
         use crate::wasm_bindgen::JsCast;
 
@@ -309,7 +687,7 @@ impl FlowchartApp {
             .ok()?;
 
         input.set_type("file");
-        input.set_accept(".json,application/json");
+        input.set_accept(accept);
         input.style().set_property("display", "none").ok()?;
 
         // Create a promise to wait for file selection
@@ -424,23 +802,158 @@ impl FlowchartApp {
             return;
         }
         if self.file.current_path.is_some() {
-            self.file.pending_save_operation = Some(PendingSaveOperation::Save);
+            if self.disk_file_changed() {
+                // Saving would replace what another program wrote
+                self.confirm_then(PendingConfirmation::Overwrite(PendingSaveOperation::Save));
+            } else {
+                self.file.pending_save_operation = Some(PendingSaveOperation::Save);
+            }
         } else {
             self.save_as_flowchart();
         }
     }
 
+    /// Opens a file dialog to write the selected nodes to a standalone flowchart file.
+    ///
+    /// The exported file contains copies of the selected nodes, the connections
+    /// between them and the groups they belong to, all with fresh IDs. The
+    /// current document and its save state are not affected.
+    pub fn export_selection(&mut self) {
+        if self.selected_node_ids().is_empty() {
+            eprintln!("Nothing selected to export");
+            return;
+        }
+        self.file.pending_save_operation = Some(PendingSaveOperation::ExportSelection);
+    }
+
+    /// Returns the IDs of all currently selected nodes, including the primary selection.
+    pub(crate) fn selected_node_ids(&self) -> Vec<NodeId> {
+        let mut ids = self.interaction.selected_nodes.clone();
+        if let Some(id) = self.interaction.selected_node {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+
     /// Opens a file dialog to load a flowchart from disk or browser storage.
     pub fn load_flowchart(&mut self) {
         self.file.pending_load_operation = Some(PendingLoadOperation::Load);
     }
 
+    /// Opens a file dialog to pick a flowchart to merge into the current one.
+    ///
+    /// Once the file is read, the "Import & Merge" dialog asks where to place it.
+    pub fn import_and_merge(&mut self) {
+        self.file.pending_load_operation = Some(PendingLoadOperation::Merge);
+    }
+
+    /// Opens a file dialog to pick a BPMN 2.0 file to merge into the current flowchart.
+    ///
+    /// Its processes are converted with [`crate::bpmn::from_bpmn`] and placed
+    /// through the "Import & Merge" dialog, so the import is one undo step.
+    pub fn import_bpmn(&mut self) {
+        self.file.pending_load_operation = Some(PendingLoadOperation::ImportBpmn);
+    }
+
+    /// Opens a file dialog to pick a recorded traffic trace (JSON Lines).
+    ///
+    /// Producers approximating its streams are proposed with
+    /// [`crate::traffic::propose_producers`] for review before adding them.
+    pub fn import_traffic_trace(&mut self) {
+        self.file.pending_load_operation = Some(PendingLoadOperation::TrafficTrace);
+    }
+
+    /// Opens a file dialog to pick a CSV table of nodes, which is shown for
+    /// review before the nodes are added (see [`crate::csv_import`]).
+    pub fn import_node_csv(&mut self) {
+        self.file.pending_load_operation = Some(PendingLoadOperation::NodeCsv);
+    }
+
+    /// Resolves a reference node's path against the directory of the current file.
+    ///
+    /// Absolute paths, and any path while the document has not been saved yet,
+    /// are returned unchanged.
+    pub(crate) fn resolve_reference_path(&self, path: &str) -> String {
+        let target = std::path::Path::new(path);
+        if target.is_absolute() || self.file.is_example_readonly {
+            return path.to_string();
+        }
+        match self
+            .file
+            .current_path
+            .as_deref()
+            .and_then(|current| std::path::Path::new(current).parent())
+        {
+            Some(dir) => dir.join(target).display().to_string(),
+            None => path.to_string(),
+        }
+    }
+
+    /// Navigates to the flowchart file linked by a reference node.
+    ///
+    /// Asks for confirmation first if the current document has unsaved changes.
+    pub fn open_reference(&mut self, node_id: NodeId) {
+        let Some(NodeType::Reference { path }) =
+            self.flowchart.nodes.get(&node_id).map(|n| &n.node_type)
+        else {
+            return;
+        };
+        if path.trim().is_empty() {
+            eprintln!("Reference node has no file path");
+            return;
+        }
+        let resolved = self.resolve_reference_path(path);
+        self.request_open_path(resolved, PendingConfirmAction::OpenReference);
+    }
+
+    /// Opens a file given on the command line once the app starts.
+    pub fn open_path_on_launch(&mut self, path: String) {
+        self.file.pending_load_operation = Some(PendingLoadOperation::OpenPath(path));
+    }
+
+    /// Accepts files handed off by later launches of the app (see
+    /// [`crate::instance`]) and brings this window to the front for them.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn listen_for_other_launches(&self, ctx: &egui::Context) {
+        let sender = self.file.file_operation_sender.clone();
+        let ctx = ctx.clone();
+        crate::instance::listen(move |path| {
+            if let Some(tx) = &sender {
+                let _ = tx.send(FileOperationResult::OpenRequested(path));
+            }
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Opens the file at `path`, going through the unsaved-changes dialog
+    /// (titled for `action`) if the current document has unsaved changes.
+    pub fn request_open_path(&mut self, path: String, action: PendingConfirmAction) {
+        if self.file.has_unsaved_changes_effective() {
+            self.file.pending_reference_path = Some(path);
+            self.dialogs.open(super::state::Modal::UnsavedChanges(action));
+        } else {
+            self.file.pending_load_operation = Some(PendingLoadOperation::OpenPath(path));
+        }
+    }
+
     /// Creates a new empty flowchart, resetting all state.
     pub fn new_flowchart(&mut self) {
         self.flowchart = Flowchart::new();
         self.flowchart.current_step = 0;
+        self.simulation_engine.clear_traces();
+        self.simulation_engine.history_mut().clear();
+        self.simulation_engine.stats_mut().clear();
+        self.simulation_engine.events_mut().clear();
+        self.simulation_engine.clear_errors();
+        self.simulation_engine.clear_shapes();
+        self.traced_message = None;
+        self.layout_animation = None;
+        self.layout_preview = None;
         self.file.current_path = None;
-        self.file.has_unsaved_changes = false;
+        self.file.mark_saved();
         self.file.is_example_readonly = false;
         self.interaction.selected_node = None;
         self.interaction.editing_node_name = None;
@@ -458,8 +971,17 @@ impl FlowchartApp {
     ) {
         self.flowchart = flowchart;
         self.flowchart.current_step = 0;
+        self.simulation_engine.clear_traces();
+        self.simulation_engine.history_mut().clear();
+        self.simulation_engine.stats_mut().clear();
+        self.simulation_engine.events_mut().clear();
+        self.simulation_engine.clear_errors();
+        self.simulation_engine.clear_shapes();
+        self.traced_message = None;
+        self.layout_animation = None;
+        self.layout_preview = None;
         self.file.current_path = source_path;
-        self.file.has_unsaved_changes = false;
+        self.file.mark_saved();
         // Mark as read-only if this came from an example source path
         self.file.is_example_readonly = self
             .file
@@ -471,12 +993,22 @@ impl FlowchartApp {
         self.interaction.editing_node_name = None;
         self.error_node = None;
         self.is_simulation_running = false;
+        self.run_to_step = None;
         // Update node counter to avoid ID conflicts
         self.node_counter = self.flowchart.nodes.len() as u32;
         // Clear undo/redo history when opening a file/example
         self.undo_history = UndoHistory::new();
     }
 
+    /// Opens a document loaded from `path` and remembers its contents on disk.
+    fn open_loaded_document(&mut self, path: String, flowchart: Flowchart, content: String) {
+        self.last_browser_document = browser_document_name(&path).map(str::to_string);
+        self.apply_loaded_flowchart_from_source(flowchart, Some(path));
+        self.file.disk_snapshot = Some(content);
+        crate::crash::record_event("Loaded document");
+        println!("File loaded successfully");
+    }
+
     /// Loads a built-in example into the editor immediately.
     pub fn load_example(&mut self, kind: ExampleKind) {
         let flowchart = build_example(kind);
@@ -493,8 +1025,8 @@ impl FlowchartApp {
     pub fn request_load_example(&mut self, kind: ExampleKind) {
         if self.file.has_unsaved_changes_effective() {
             self.file.pending_example = Some(kind);
-            self.file.pending_confirm_action = Some(super::state::PendingConfirmAction::LoadExample);
-            self.file.show_unsaved_dialog = true;
+            self.dialogs
+                .open(super::state::Modal::UnsavedChanges(super::state::PendingConfirmAction::LoadExample));
         } else {
             self.load_example(kind);
         }
@@ -0,0 +1,241 @@
+//! External change detection for the open file.
+//!
+//! On native builds the directory holding the open file is watched with the
+//! `notify` crate. Change events are debounced, the file is re-read and
+//! compared with what the app last loaded or saved, and a real difference
+//! opens a prompt to reload the file or keep the local version.
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::state::FileOperationResult;
+use super::state::{ExternalChange, FlowchartApp};
+use crate::constants::{BROWSER_DOCUMENT_PREFIX, FILE_WATCH_DEBOUNCE_SECS};
+use crate::types::Flowchart;
+use eframe::egui;
+
+/// Watches one file for changes made outside the app.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub struct FileWatcher {
+    /// Path of the watched file
+    path: String,
+    /// Live watcher; `None` if watching failed, so it isn't retried every frame
+    #[cfg(not(target_arch = "wasm32"))]
+    _watcher: Option<notify::RecommendedWatcher>,
+}
+
+impl FileWatcher {
+    /// Starts watching `path`, reporting changes as [`FileOperationResult::FileChangedOnDisk`].
+    ///
+    /// The parent directory is watched rather than the file so that editors
+    /// which save by replacing the file are still noticed.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start(
+        path: &str,
+        sender: Option<std::sync::mpsc::Sender<FileOperationResult>>,
+        ctx: &egui::Context,
+    ) -> Self {
+        use notify::{EventKind, RecursiveMode, Watcher};
+
+        let target = std::path::PathBuf::from(path);
+        let file_name = target.file_name().map(|n| n.to_os_string());
+        let dir = match target.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => std::path::PathBuf::from("."),
+        };
+        let reported = path.to_string();
+        let ctx = ctx.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if !matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                return;
+            }
+            if event.paths.iter().any(|p| p.file_name() == file_name.as_deref()) {
+                if let Some(tx) = &sender {
+                    let _ = tx.send(FileOperationResult::FileChangedOnDisk(reported.clone()));
+                }
+                ctx.request_repaint();
+            }
+        })
+        .and_then(|mut watcher| watcher.watch(&dir, RecursiveMode::NonRecursive).map(|_| watcher));
+
+        let watcher = match watcher {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                eprintln!("Failed to watch {} for changes: {}", path, e);
+                None
+            }
+        };
+        Self {
+            path: path.to_string(),
+            _watcher: watcher,
+        }
+    }
+}
+
+/// Returns true for document paths that name a file on disk.
+fn is_disk_path(path: &str) -> bool {
+    !path.starts_with("Example: ") && !path.starts_with(BROWSER_DOCUMENT_PREFIX)
+}
+
+impl FlowchartApp {
+    /// Keeps the watcher pointed at the open file. Called every frame.
+    pub(crate) fn sync_file_watcher(&mut self, ctx: &egui::Context) {
+        let wanted = self.file.current_path.as_deref().filter(|p| is_disk_path(p));
+        if self.file.watcher.as_ref().map(|w| w.path.as_str()) == wanted {
+            return;
+        }
+        self.file.watcher = None;
+        self.file.disk_change_pending_since = None;
+        self.file.external_change = None;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = wanted {
+            self.file.watcher = Some(FileWatcher::start(
+                path,
+                self.file.file_operation_sender.clone(),
+                ctx,
+            ));
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = ctx;
+    }
+
+    /// Notes a change event on `path`; it is re-read once events stop arriving.
+    pub(crate) fn note_file_changed_on_disk(&mut self, path: &str) {
+        if self.file.current_path.as_deref() == Some(path) {
+            self.file.disk_change_pending_since = Some(self.frame_time);
+        }
+    }
+
+    /// Re-reads the open file after the debounce period and raises the
+    /// reload prompt if it no longer matches what the app last saw.
+    pub(crate) fn check_file_changed_on_disk(&mut self, ctx: &egui::Context) {
+        let Some(since) = self.file.disk_change_pending_since else {
+            return;
+        };
+        let waited = self.frame_time - since;
+        if waited < FILE_WATCH_DEBOUNCE_SECS {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(
+                FILE_WATCH_DEBOUNCE_SECS - waited,
+            ));
+            return;
+        }
+        self.file.disk_change_pending_since = None;
+        let Some(path) = self.file.current_path.clone() else {
+            return;
+        };
+
+        let change = match std::fs::read_to_string(&path) {
+            Ok(content) if self.file.disk_snapshot.as_deref() == Some(content.as_str()) => None,
+            Ok(content) => Some(ExternalChange::Modified(content)),
+            Err(_) if !std::path::Path::new(&path).exists() => Some(ExternalChange::Removed),
+            Err(e) => {
+                eprintln!("Failed to re-read {}: {}", path, e);
+                None
+            }
+        };
+        if change.is_some() {
+            self.file.external_change = change;
+        }
+    }
+
+    /// Returns true if the open file no longer holds what the app last
+    /// loaded or saved, so saving would overwrite someone else's changes.
+    pub(crate) fn disk_file_changed(&self) -> bool {
+        let Some(path) = self.file.current_path.as_deref().filter(|p| is_disk_path(p)) else {
+            return false;
+        };
+        let Some(snapshot) = self.file.disk_snapshot.as_deref() else {
+            return false;
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            std::fs::read_to_string(path).is_ok_and(|content| content != snapshot)
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = (path, snapshot);
+            false
+        }
+    }
+
+    /// Replaces the document with the version on disk.
+    pub(crate) fn reload_from_disk(&mut self) {
+        let Some(ExternalChange::Modified(content)) = self.file.external_change.take() else {
+            return;
+        };
+        match Flowchart::from_json(&content) {
+            Ok(flowchart) => {
+                let path = self.file.current_path.clone();
+                self.apply_loaded_flowchart_from_source(flowchart, path);
+                self.file.disk_snapshot = Some(content);
+            }
+            Err(e) => {
+                eprintln!("Failed to parse flowchart: {}", e);
+            }
+        }
+    }
+
+    /// Keeps the document as it is; it now differs from the file on disk.
+    pub(crate) fn keep_local_version(&mut self) {
+        match self.file.external_change.take() {
+            Some(ExternalChange::Modified(content)) => self.file.disk_snapshot = Some(content),
+            Some(ExternalChange::Removed) => self.file.disk_snapshot = None,
+            None => return,
+        }
+        self.file.has_unsaved_changes = true;
+    }
+
+    /// Draws the reload prompt while an external change is waiting.
+    pub(crate) fn draw_external_change_dialog(&mut self, ctx: &egui::Context) {
+        let Some(change) = &self.file.external_change else {
+            return;
+        };
+        let removed = *change == ExternalChange::Removed;
+        let name = self
+            .file
+            .current_path
+            .as_deref()
+            .and_then(|p| std::path::Path::new(p).file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let unsaved = self.file.has_unsaved_changes_effective();
+
+        let mut reload = false;
+        let mut keep = false;
+        egui::Window::new("File Changed on Disk")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                if removed {
+                    ui.label(format!("{} was deleted or moved by another program.", name));
+                    ui.weak("Save to write your version back to disk.");
+                } else {
+                    ui.label(format!("{} was changed by another program.", name));
+                    if unsaved {
+                        ui.weak("Reloading discards your unsaved changes.");
+                    }
+                }
+                ui.horizontal(|ui| {
+                    if !removed && ui.button("Reload").clicked() {
+                        reload = true;
+                    }
+                    let label = if removed { "Keep Editing" } else { "Keep My Version" };
+                    if ui.button(label).clicked() {
+                        keep = true;
+                    }
+                });
+            });
+
+        if reload {
+            self.reload_from_disk();
+        } else if keep {
+            self.keep_local_version();
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Conditional formatting.
+//!
+//! The document's [`FormatRule`]s are evaluated against the live values of
+//! each node whenever the canvas is drawn, so a rule such as "consumers turn
+//! red while their backlog is above 50" tracks the simulation as it runs.
+//! The rules panel adds, orders and edits them; edits are undoable and saved
+//! with the document.
+
+use super::state::{ChangeKind, FlowchartApp};
+use crate::types::*;
+use crate::ui::UndoAction;
+use eframe::egui;
+use std::collections::HashMap;
+
+/// Undo coalescing key for typing in rule names and thresholds.
+const RULE_EDIT_KEY: &str = "format_rules:edit";
+
+/// Colors the formatting rules give a node, where they change them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct NodeFormat {
+    /// Fill color replacing the node type's color
+    pub fill: Option<egui::Color32>,
+    /// Border color replacing the normal black border
+    pub border: Option<egui::Color32>,
+}
+
+fn rgb(color: [u8; 3]) -> egui::Color32 {
+    egui::Color32::from_rgb(color[0], color[1], color[2])
+}
+
+impl FlowchartApp {
+    /// Messages delivered to each node during the run.
+    fn delivered_per_node(&self) -> HashMap<NodeId, u64> {
+        let mut delivered = HashMap::new();
+        for sample in self.simulation_engine.stats().samples() {
+            for (node, count) in &sample.deliveries {
+                *delivered.entry(*node).or_default() += count;
+            }
+        }
+        delivered
+    }
+
+    /// Current value of `metric` for `node`; metrics that don't apply to
+    /// the node's type are 0.
+    fn node_metric(&self, node: &FlowchartNode, metric: RuleMetric, delivered: &HashMap<NodeId, u64>) -> f64 {
+        let value = match (metric, &node.node_type) {
+            (RuleMetric::Backlog, _) => self
+                .flowchart
+                .connections
+                .iter()
+                .filter(|c| c.to == node.id)
+                .map(|c| c.messages.len())
+                .sum::<usize>() as u64,
+            (RuleMetric::InFlight, NodeType::Consumer { in_flight, .. }) => in_flight.len() as u64,
+            (RuleMetric::Delivered, _) => delivered.get(&node.id).copied().unwrap_or_default(),
+            (RuleMetric::Errors, _) => self.node_errors(node.id).count() as u64,
+            (RuleMetric::Produced, NodeType::Producer { messages_produced, .. }) => *messages_produced as u64,
+            (RuleMetric::ProcessingTime, NodeType::Consumer { processing_time, .. }) => *processing_time as u64,
+            _ => 0,
+        };
+        value as f64
+    }
+
+    /// Evaluates the formatting rules for every node they change.
+    pub(crate) fn node_formats(&self) -> HashMap<NodeId, NodeFormat> {
+        let rules: Vec<&FormatRule> = self.flowchart.format_rules.iter().filter(|r| r.enabled).collect();
+        if rules.is_empty() {
+            return HashMap::new();
+        }
+        let delivered = if rules.iter().any(|r| r.metric == RuleMetric::Delivered) {
+            self.delivered_per_node()
+        } else {
+            HashMap::new()
+        };
+        let mut formats = HashMap::new();
+        for node in self.flowchart.nodes.values() {
+            let mut format = NodeFormat::default();
+            for rule in &rules {
+                if !rule.applies(&node.node_type, self.node_metric(node, rule.metric, &delivered)) {
+                    continue;
+                }
+                format.fill = format.fill.or(rule.fill.map(rgb));
+                format.border = format.border.or(rule.border.map(rgb));
+            }
+            if format != NodeFormat::default() {
+                formats.insert(node.id, format);
+            }
+        }
+        formats
+    }
+
+    /// Replaces the formatting rules and records the change.
+    ///
+    /// With `coalesce` set, rapid consecutive calls (typing a name or
+    /// dragging a threshold) are merged into a single undo entry.
+    pub(crate) fn set_format_rules(&mut self, rules: Vec<FormatRule>, coalesce: bool) {
+        if rules == self.flowchart.format_rules {
+            return;
+        }
+        let old = std::mem::replace(&mut self.flowchart.format_rules, rules);
+        let action = UndoAction::FormatRulesChanged {
+            old,
+            new: self.flowchart.format_rules.clone(),
+        };
+        if coalesce {
+            self.undo_history
+                .push_action_coalesced(action, RULE_EDIT_KEY, self.frame_time);
+        } else {
+            self.undo_history.push_action(action);
+        }
+        self.file.mark_changed(ChangeKind::FormatRulesChanged);
+    }
+
+    /// Draws the rules panel. Opens when `show_format_rules_dialog` is true.
+    pub(crate) fn draw_format_rules_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let mut edited = self.flowchart.format_rules.clone();
+        let mut typed = false;
+        let delivered = self.delivered_per_node();
+
+        egui::Window::new("Formatting Rules")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Nodes matching a rule are drawn with its colors; earlier rules win.");
+                ui.separator();
+
+                let count = edited.len();
+                let mut move_up = None;
+                let mut move_down = None;
+                let mut remove = None;
+                if count == 0 {
+                    ui.label("No rules yet.");
+                }
+                for (idx, rule) in edited.iter_mut().enumerate() {
+                    ui.push_id(idx, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut rule.enabled, "");
+                            typed |= ui
+                                .add(egui::TextEdit::singleline(&mut rule.name).desired_width(160.0))
+                                .changed();
+                            if ui
+                                .add_enabled(idx > 0, egui::Button::new("⬆"))
+                                .on_hover_text("Move up")
+                                .clicked()
+                            {
+                                move_up = Some(idx);
+                            }
+                            if ui
+                                .add_enabled(idx + 1 < count, egui::Button::new("⬇"))
+                                .on_hover_text("Move down")
+                                .clicked()
+                            {
+                                move_down = Some(idx);
+                            }
+                            if ui.button("✖").on_hover_text("Remove rule").clicked() {
+                                remove = Some(idx);
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_salt("rule_target")
+                                .selected_text(rule.target.label())
+                                .show_ui(ui, |ui| {
+                                    for target in RuleTarget::ALL {
+                                        ui.selectable_value(&mut rule.target, target, target.label());
+                                    }
+                                });
+                            ui.label("with");
+                            egui::ComboBox::from_id_salt("rule_metric")
+                                .selected_text(rule.metric.label())
+                                .show_ui(ui, |ui| {
+                                    for metric in RuleMetric::ALL {
+                                        ui.selectable_value(&mut rule.metric, metric, metric.label());
+                                    }
+                                });
+                            egui::ComboBox::from_id_salt("rule_comparison")
+                                .width(40.0)
+                                .selected_text(rule.comparison.symbol())
+                                .show_ui(ui, |ui| {
+                                    for comparison in RuleComparison::ALL {
+                                        ui.selectable_value(&mut rule.comparison, comparison, comparison.symbol());
+                                    }
+                                });
+                            typed |= ui.add(egui::DragValue::new(&mut rule.threshold)).changed();
+                        });
+                        ui.horizontal(|ui| {
+                            color_option(ui, "Fill", &mut rule.fill, [230, 70, 70]);
+                            color_option(ui, "Border", &mut rule.border, [200, 0, 0]);
+                            let matching = self
+                                .flowchart
+                                .nodes
+                                .values()
+                                .filter(|n| rule.applies(&n.node_type, self.node_metric(n, rule.metric, &delivered)))
+                                .count();
+                            ui.weak(format!("{} node(s) match", matching));
+                        });
+                        ui.separator();
+                    });
+                }
+                if let Some(idx) = move_up {
+                    edited.swap(idx, idx - 1);
+                }
+                if let Some(idx) = move_down {
+                    edited.swap(idx, idx + 1);
+                }
+                if let Some(idx) = remove {
+                    edited.remove(idx);
+                }
+                if ui.button("Add Rule").clicked() {
+                    edited.push(FormatRule::default());
+                }
+            });
+
+        self.set_format_rules(edited, typed);
+        if !keep_open {
+            self.show_format_rules_dialog = false;
+        }
+    }
+}
+
+/// A checkbox turning a rule color on, with its color picker while on.
+fn color_option(ui: &mut egui::Ui, label: &str, color: &mut Option<[u8; 3]>, default: [u8; 3]) {
+    let mut on = color.is_some();
+    if ui.checkbox(&mut on, label).changed() {
+        *color = on.then_some(default);
+    }
+    if let Some(color) = color {
+        ui.color_edit_button_srgb(color);
+    }
+}
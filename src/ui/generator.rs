@@ -0,0 +1,147 @@
+//! Producer test-data generator dialog.
+//!
+//! Builds a producer's message template from a field spec (see
+//! [`crate::datagen`]) and previews a few samples before applying one.
+
+use super::state::{ChangeKind, DataGeneratorState, FlowchartApp};
+use crate::constants::{DEFAULT_GENERATOR_SPEC, GENERATOR_PREVIEW_SAMPLES};
+use crate::datagen::{generate_sample, parse_spec};
+use crate::types::*;
+use crate::ui::UndoAction;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Opens the generator dialog for a producer node.
+    pub(crate) fn open_data_generator(&mut self, node_id: NodeId) {
+        self.data_generator = Some(DataGeneratorState {
+            node_id,
+            spec: DEFAULT_GENERATOR_SPEC.to_string(),
+            seed: 1,
+        });
+    }
+
+    /// Draws the generator dialog while it is open.
+    pub(crate) fn draw_data_generator_dialog(&mut self, ctx: &egui::Context) {
+        let Some(state) = self.data_generator.as_mut() else {
+            return;
+        };
+        let Some(node_name) = self
+            .flowchart
+            .nodes
+            .get(&state.node_id)
+            .filter(|n| matches!(n.node_type, NodeType::Producer { .. }))
+            .map(|n| n.name.clone())
+        else {
+            // The producer was deleted or changed type while the dialog was open
+            self.data_generator = None;
+            return;
+        };
+
+        let mut keep_open = true;
+        let mut apply: Option<serde_json::Value> = None;
+        let mut cancel = false;
+        egui::Window::new("Generate Test Data")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(format!("Template for \"{}\"", node_name));
+                ui.add(
+                    egui::TextEdit::multiline(&mut state.spec)
+                        .desired_rows(8)
+                        .desired_width(f32::INFINITY)
+                        .font(egui::TextStyle::Monospace),
+                );
+
+                let parsed = parse_spec(&state.spec);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Preview:");
+                    if ui.button("Reroll").clicked() {
+                        state.seed = state.seed.wrapping_add(GENERATOR_PREVIEW_SAMPLES as u64);
+                    }
+                });
+                match &parsed {
+                    Ok(fields) => {
+                        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                            for i in 0..GENERATOR_PREVIEW_SAMPLES as u64 {
+                                let sample = generate_sample(fields, state.seed.wrapping_add(i));
+                                let text = serde_json::to_string_pretty(&sample).unwrap_or_default();
+                                ui.horizontal(|ui| {
+                                    ui.add(
+                                        egui::Label::new(egui::RichText::new(text).monospace())
+                                            .wrap(),
+                                    );
+                                    if ui
+                                        .small_button("Use")
+                                        .on_hover_text("Set this sample as the template")
+                                        .clicked()
+                                    {
+                                        apply = Some(sample.clone());
+                                    }
+                                });
+                                ui.separator();
+                            }
+                        });
+                    }
+                    Err(err) => {
+                        ui.colored_label(egui::Color32::RED, err);
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(parsed.is_ok(), egui::Button::new("Apply"))
+                        .on_hover_text("Set the first sample as the template")
+                        .clicked()
+                    {
+                        if let Ok(fields) = &parsed {
+                            apply = Some(generate_sample(fields, state.seed));
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if let Some(template) = apply {
+            let node_id = state.node_id;
+            self.data_generator = None;
+            self.apply_generated_template(node_id, template);
+        } else if cancel || !keep_open {
+            self.data_generator = None;
+        }
+    }
+
+    /// Replaces a producer's message template as one undo step.
+    pub(crate) fn apply_generated_template(&mut self, node_id: NodeId, template: serde_json::Value) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        if !matches!(&node.node_type, NodeType::Producer { message_template, .. } if *message_template != template)
+        {
+            return;
+        }
+        let old_node_type = node.node_type.clone();
+        if let NodeType::Producer {
+            message_template, ..
+        } = &mut node.node_type
+        {
+            *message_template = template;
+        }
+        let action = UndoAction::PropertyChanged {
+            node_id,
+            old_node_type,
+            new_node_type: node.node_type.clone(),
+        };
+        self.undo_history.push_action(action);
+        self.file.mark_changed(ChangeKind::NodeEdited(node_id));
+
+        // Refresh the template editor if it is showing this producer
+        if self.interaction.selected_node == Some(node_id) {
+            self.interaction.temp_producer_message_template.clear();
+        }
+    }
+}
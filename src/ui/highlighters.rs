@@ -2,9 +2,93 @@
 //!
 //! This module provides syntax highlighting functionality for code displayed
 //! in the properties panel, making it easier to read and edit scripts and templates.
+//! Token colors come from a [`SyntaxTheme`] holding one palette per light/dark
+//! mode, which users can adjust in Preferences.
 
 use eframe::egui::{self, Color32};
 use eframe::epaint::text::{LayoutJob, TextFormat};
+use serde::{Deserialize, Serialize};
+
+/// Colors for each kind of highlighted token, stored as RGB tuples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyntaxPalette {
+    /// Language keywords, and `true`/`false`/`null` in JSON
+    pub keyword: (u8, u8, u8),
+    /// String literals
+    pub string: (u8, u8, u8),
+    /// Comments
+    pub comment: (u8, u8, u8),
+    /// Number literals
+    pub number: (u8, u8, u8),
+    /// Names of called functions
+    pub function: (u8, u8, u8),
+    /// JSON object keys
+    pub key: (u8, u8, u8),
+    /// Everything else (punctuation, identifiers)
+    pub text: (u8, u8, u8),
+}
+
+impl SyntaxPalette {
+    /// Default palette for dark backgrounds.
+    pub const fn dark() -> Self {
+        Self {
+            keyword: (86, 156, 214),   // Blue
+            string: (206, 145, 120),   // Orange
+            comment: (106, 153, 85),   // Green
+            number: (181, 206, 168),   // Light green
+            function: (220, 220, 170), // Yellow
+            key: (156, 220, 254),      // Light blue
+            text: (212, 212, 212),     // Light gray
+        }
+    }
+
+    /// Default palette for light backgrounds, chosen for contrast on white.
+    pub const fn light() -> Self {
+        Self {
+            keyword: (0, 0, 170),     // Dark blue
+            string: (150, 20, 20),    // Dark red
+            comment: (0, 110, 0),     // Dark green
+            number: (100, 0, 150),    // Purple
+            function: (120, 70, 0),   // Brown
+            key: (0, 80, 170),        // Strong blue
+            text: (20, 20, 20),       // Near black
+        }
+    }
+
+    fn color(rgb: (u8, u8, u8)) -> Color32 {
+        Color32::from_rgb(rgb.0, rgb.1, rgb.2)
+    }
+}
+
+/// Highlighting palettes for dark and light mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyntaxTheme {
+    /// Palette used when dark mode is enabled
+    pub dark: SyntaxPalette,
+    /// Palette used in light mode
+    pub light: SyntaxPalette,
+}
+
+impl Default for SyntaxTheme {
+    fn default() -> Self {
+        Self {
+            dark: SyntaxPalette::dark(),
+            light: SyntaxPalette::light(),
+        }
+    }
+}
+
+impl SyntaxTheme {
+    /// Returns the palette matching the current visuals.
+    pub fn palette(&self, dark_mode: bool) -> &SyntaxPalette {
+        if dark_mode {
+            &self.dark
+        } else {
+            &self.light
+        }
+    }
+}
 
 /// Highlights JavaScript code with syntax coloring.
 ///
@@ -12,34 +96,20 @@ use eframe::epaint::text::{LayoutJob, TextFormat};
 ///
 /// * `text` - The JavaScript source code to highlight
 /// * `font_id` - The font to use for rendering
+/// * `palette` - Token colors to use
 ///
 /// # Returns
 ///
 /// A `LayoutJob` containing the highlighted text with appropriate colors
-pub fn highlight_javascript(text: &str, font_id: egui::FontId, dark_mode: bool) -> LayoutJob {
+pub fn highlight_javascript(text: &str, font_id: egui::FontId, palette: &SyntaxPalette) -> LayoutJob {
     let mut job = LayoutJob::default();
 
-    // Define colors for different token types, adjusting for dark/light mode
-    let (keyword_color, string_color, comment_color, number_color, function_color, default_color) =
-        if dark_mode {
-            (
-                Color32::from_rgb(86, 156, 214),  // Blue
-                Color32::from_rgb(206, 145, 120), // Orange
-                Color32::from_rgb(106, 153, 85),  // Green
-                Color32::from_rgb(181, 206, 168), // Light green
-                Color32::from_rgb(220, 220, 170), // Yellow
-                Color32::from_rgb(212, 212, 212), // Light gray (default)
-            )
-        } else {
-            (
-                Color32::from_rgb(0, 0, 170),   // Dark blue for keywords
-                Color32::from_rgb(163, 21, 21), // Dark red/brown for strings
-                Color32::from_rgb(0, 128, 0),   // Dark green for comments
-                Color32::from_rgb(100, 0, 150), // Purple for numbers
-                Color32::from_rgb(0, 102, 153), // Dark teal for function names
-                Color32::BLACK,                 // Black default text on light bg
-            )
-        };
+    let keyword_color = SyntaxPalette::color(palette.keyword);
+    let string_color = SyntaxPalette::color(palette.string);
+    let comment_color = SyntaxPalette::color(palette.comment);
+    let number_color = SyntaxPalette::color(palette.number);
+    let function_color = SyntaxPalette::color(palette.function);
+    let default_color = SyntaxPalette::color(palette.text);
 
     let keywords = [
         "function",
@@ -225,31 +295,19 @@ pub fn highlight_javascript(text: &str, font_id: egui::FontId, dark_mode: bool)
 ///
 /// * `text` - The JSON source code to highlight
 /// * `font_id` - The font to use for rendering
+/// * `palette` - Token colors to use
 ///
 /// # Returns
 ///
 /// A `LayoutJob` containing the highlighted text with appropriate colors
-pub fn highlight_json(text: &str, font_id: egui::FontId, dark_mode: bool) -> LayoutJob {
+pub fn highlight_json(text: &str, font_id: egui::FontId, palette: &SyntaxPalette) -> LayoutJob {
     let mut job = LayoutJob::default();
 
-    // Define colors for different token types, adjusting for dark/light mode
-    let (string_color, number_color, keyword_color, key_color, default_color) = if dark_mode {
-        (
-            Color32::from_rgb(206, 145, 120), // Orange
-            Color32::from_rgb(181, 206, 168), // Light green
-            Color32::from_rgb(86, 156, 214),  // Blue (for true/false/null)
-            Color32::from_rgb(156, 220, 254), // Light blue (for object keys)
-            Color32::from_rgb(212, 212, 212), // Light gray
-        )
-    } else {
-        (
-            Color32::from_rgb(163, 21, 21), // Dark red/brown for strings
-            Color32::from_rgb(100, 0, 150), // Purple for numbers
-            Color32::from_rgb(0, 0, 170),   // Dark blue for true/false/null
-            Color32::from_rgb(0, 102, 204), // Strong blue for object keys
-            Color32::BLACK,                 // Black default text
-        )
-    };
+    let string_color = SyntaxPalette::color(palette.string);
+    let number_color = SyntaxPalette::color(palette.number);
+    let keyword_color = SyntaxPalette::color(palette.keyword);
+    let key_color = SyntaxPalette::color(palette.key);
+    let default_color = SyntaxPalette::color(palette.text);
 
     let mut chars = text.char_indices().peekable();
     let mut in_key_position = false; // Track if we're expecting an object key
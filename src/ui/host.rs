@@ -0,0 +1,304 @@
+//! Driving the app from an embedding host.
+//!
+//! The toolbar's simulation controls and the per-frame run loop go through
+//! these methods, and the web build exposes them to the host page (see
+//! `FlowchartHandle` in the crate root), so a dashboard embedding the canvas
+//! can load a document, run it and follow its statistics step by step.
+//! [`AppState`] snapshots let hosts and tests read and restore the document,
+//! the view and the simulation separately, and test harnesses can inject
+//! messages into a run and take out the ones waiting at a node.
+
+use super::state::{AppState, DocumentState, FlowchartApp, LastRun, SimulationSnapshot, StepListener, ViewState};
+use crate::types::*;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Replaces the open document with the flowchart in `json`.
+    ///
+    /// The document has no path, like a new one, and any run is reset.
+    pub fn load_document_json(&mut self, json: &str) -> Result<(), String> {
+        let flowchart =
+            Flowchart::from_json(json).map_err(|e| format!("Failed to parse flowchart: {}", e))?;
+        crate::crash::record_event("Loaded document from host");
+        self.apply_loaded_flowchart_from_source(flowchart, None);
+        Ok(())
+    }
+
+    /// The open document, including its simulation state, as JSON.
+    pub fn document_json(&self) -> Result<String, String> {
+        self.flowchart
+            .to_json()
+            .map_err(|e| format!("Failed to serialize flowchart: {}", e))
+    }
+
+    /// Whether the simulation advances every frame.
+    pub fn is_simulation_running(&self) -> bool {
+        self.is_simulation_running
+    }
+
+    /// Starts (or resumes) running the simulation, advancing the run
+    /// configuration's steps per frame.
+    pub fn start_simulation(&mut self) {
+        crate::crash::record_event("Simulation started");
+        self.is_simulation_running = true;
+        self.flowchart.simulation_state = SimulationState::Running;
+    }
+
+    /// Pauses the simulation, keeping the messages in flight.
+    pub fn pause_simulation(&mut self) {
+        self.is_simulation_running = false;
+        self.run_to_step = None;
+        self.flowchart.simulation_state = SimulationState::Paused;
+    }
+
+    /// Runs the simulation until it reaches `step`, then pauses. Does nothing
+    /// if the run is already past it.
+    pub fn run_to_step(&mut self, step: u64) {
+        if step <= self.flowchart.current_step {
+            return;
+        }
+        self.run_to_step = Some(step);
+        self.start_simulation();
+    }
+
+    /// Returns true if a running simulation has reached its Run To target.
+    pub(crate) fn reached_run_to_step(&self) -> bool {
+        self.run_to_step.is_some_and(|target| self.flowchart.current_step >= target)
+    }
+
+    /// Stops the simulation and clears the run: messages, traces, history,
+    /// statistics, events, errors and the transformers' script globals. The
+    /// run's final messages and statistics stay viewable as the last run; edits
+    /// staged in the properties panel are left alone.
+    pub fn stop_simulation(&mut self) {
+        if self.flowchart.current_step > 0 {
+            self.last_run = Some(self.capture_last_run());
+        }
+        self.is_simulation_running = false;
+        self.run_to_step = None;
+        self.error_node = None;
+        self.simulation_engine.clear_traces();
+        self.simulation_engine.history_mut().clear();
+        self.simulation_engine.stats_mut().clear();
+        self.simulation_engine.events_mut().clear();
+        self.simulation_engine.clear_errors();
+        self.simulation_engine.clear_scripts();
+        self.traced_message = None;
+        self.flowchart.reset_simulation();
+    }
+
+    /// Advances the simulation by one step.
+    ///
+    /// Returns the errors raised by nodes, if any; the first failing node is
+    /// marked with the error border. The first step of a run reads the
+    /// nodes' linked source files first, and a step after an edit
+    /// reschedules the engine's work.
+    pub fn step_simulation(&mut self) -> Vec<(NodeId, String)> {
+        if self.flowchart.current_step == 0 {
+            self.last_run = None;
+            self.load_source_files();
+        }
+        if std::mem::take(&mut self.file.edited_since_step) {
+            self.simulation_engine.reschedule();
+        }
+        let delivered_messages = self.simulation_engine.step(&mut self.flowchart);
+        let errors = self
+            .simulation_engine
+            .deliver_messages(delivered_messages, &mut self.flowchart);
+        if let Some((node_id, _)) = errors.first() {
+            self.error_node = Some(*node_id);
+        }
+        if let (Some(listener), Some(sample)) =
+            (self.step_listener.as_mut(), self.simulation_engine.stats().last())
+        {
+            listener(sample);
+        }
+        errors
+    }
+
+    /// The final state of the current run, kept when it is stopped.
+    fn capture_last_run(&self) -> LastRun {
+        let name = |id: &NodeId| self.flowchart.nodes.get(id).map_or_else(|| "?".to_string(), |n| n.name.clone());
+        LastRun {
+            steps: self.flowchart.current_step,
+            summary: self.run_summary(),
+            in_flight: self
+                .flowchart
+                .connections
+                .iter()
+                .filter(|c| !c.messages.is_empty())
+                .map(|c| (name(&c.from), name(&c.to), c.messages.clone()))
+                .collect(),
+        }
+    }
+
+    /// Advances the simulation by up to `steps` steps, stopping early at the
+    /// first step whose nodes raise errors.
+    pub(crate) fn step_simulation_by(&mut self, steps: u32) {
+        for _ in 0..steps {
+            let errors = self.step_simulation();
+            for (node_id, error_msg) in &errors {
+                eprintln!("Error in node {}: {}", node_id, error_msg);
+            }
+            if !errors.is_empty() {
+                break;
+            }
+        }
+    }
+
+    /// Advances a running simulation by its steps for this frame, as many as
+    /// the run configuration's steps per frame. Errors stop the run unless it
+    /// continues on errors; reaching the Run To target, the pause-at step or
+    /// the maximum number of steps pauses it.
+    ///
+    /// Returns what halted the run, if its configuration did.
+    pub(crate) fn advance_running_simulation(&mut self) -> Option<RunHalt> {
+        let steps = self.flowchart.run_config.steps_per_frame.clamp(1, crate::constants::MAX_STEPS_PER_FRAME);
+        for _ in 0..steps {
+            let errors = self.step_simulation();
+            for (node_id, error_msg) in &errors {
+                eprintln!("Error in node {}: {}", node_id, error_msg);
+            }
+            let halt = self.flowchart.run_config.halt_after(self.flowchart.current_step, !errors.is_empty());
+            match halt {
+                Some(RunHalt::Error) => {
+                    self.is_simulation_running = false;
+                    self.run_to_step = None;
+                    self.flowchart.simulation_state = SimulationState::Stopped;
+                    eprintln!("Simulation stopped due to the error");
+                    return halt;
+                }
+                Some(RunHalt::MaxSteps | RunHalt::PauseStep) => {
+                    self.pause_simulation();
+                    return halt;
+                }
+                None if self.reached_run_to_step() => {
+                    self.pause_simulation();
+                    return None;
+                }
+                None => {}
+            }
+        }
+        None
+    }
+
+    /// The node with id or name `node`.
+    fn host_node(&self, node: &str) -> Result<NodeId, String> {
+        if let Ok(id) = NodeId::parse_str(node.trim()) {
+            if self.flowchart.nodes.contains_key(&id) {
+                return Ok(id);
+            }
+        }
+        let mut named = self.flowchart.nodes.values().filter(|n| n.name == node).map(|n| n.id);
+        match (named.next(), named.next()) {
+            (Some(id), None) => Ok(id),
+            (Some(_), Some(_)) => Err(format!("More than one node is named {}", node)),
+            (None, _) => Err(format!("Node {} not found", node)),
+        }
+    }
+
+    /// Hands a message with `data` straight to the consumer or transformer
+    /// with id or name `node`, in the current step.
+    pub fn inject_into_node(&mut self, node: &str, data: serde_json::Value) -> Result<(), String> {
+        let node_id = self.host_node(node)?;
+        self.simulation_engine
+            .inject_into_node(node_id, Message::new(data), &mut self.flowchart)
+    }
+
+    /// Puts a message with `data` on the connection at `index`, for the next
+    /// step to deliver.
+    pub fn inject_on_connection(&mut self, index: usize, data: serde_json::Value) -> Result<(), String> {
+        self.simulation_engine
+            .inject_on_connection(index, Message::new(data), &mut self.flowchart)
+    }
+
+    /// The messages waiting to be delivered to the node with id or name `node`.
+    pub fn peek_messages(&self, node: &str) -> Result<Vec<Message>, String> {
+        let node_id = self.host_node(node)?;
+        Ok(self
+            .simulation_engine
+            .peek_messages(node_id, &self.flowchart)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    /// Takes the messages waiting to be delivered to the node with id or name
+    /// `node` out of the run.
+    pub fn drain_messages(&mut self, node: &str) -> Result<Vec<Message>, String> {
+        let node_id = self.host_node(node)?;
+        Ok(self.simulation_engine.drain_messages(node_id, &mut self.flowchart))
+    }
+
+    /// Calls `listener` with the statistics of every following step, or stops
+    /// notifying if `None`.
+    pub fn set_step_listener(&mut self, listener: Option<StepListener>) {
+        self.step_listener = listener;
+    }
+
+    /// Snapshot of the document, the view and the simulation.
+    pub fn export_state(&self) -> AppState {
+        AppState {
+            document: DocumentState {
+                flowchart: self.flowchart.clone(),
+                path: self.file.current_path.clone(),
+            },
+            view: ViewState {
+                offset: (self.canvas.offset.x, self.canvas.offset.y),
+                zoom_factor: self.canvas.zoom_factor,
+                selected_node: self.interaction.selected_node,
+                selected_nodes: self.interaction.selected_nodes.clone(),
+                selected_group: self.interaction.selected_group,
+            },
+            simulation: SimulationSnapshot {
+                running: self.is_simulation_running,
+                state: self.flowchart.simulation_state.clone(),
+                current_step: self.flowchart.current_step,
+                error_node: self.error_node,
+                traced_message: self.traced_message,
+                stats: self.simulation_engine.stats().clone(),
+            },
+        }
+    }
+
+    /// Restores a snapshot from [`export_state`](Self::export_state).
+    pub fn import_state(&mut self, state: AppState) {
+        self.import_document(state.document);
+        self.import_simulation(state.simulation);
+        self.import_view(state.view);
+    }
+
+    /// Opens `document` as if it had been loaded from its path: the undo
+    /// history, selection and run are reset.
+    pub fn import_document(&mut self, document: DocumentState) {
+        self.apply_loaded_flowchart_from_source(document.flowchart, document.path);
+    }
+
+    /// Moves the canvas and selects the nodes of `view` that exist.
+    pub fn import_view(&mut self, view: ViewState) {
+        let nodes = &self.flowchart.nodes;
+        self.canvas.offset = egui::vec2(view.offset.0, view.offset.1);
+        self.canvas.zoom_factor = view.zoom_factor;
+        self.interaction.selected_node = view.selected_node.filter(|id| nodes.contains_key(id));
+        self.interaction.selected_nodes = view
+            .selected_nodes
+            .into_iter()
+            .filter(|id| nodes.contains_key(id))
+            .collect();
+        self.interaction.selected_group = view
+            .selected_group
+            .filter(|id| self.flowchart.groups.contains_key(id));
+    }
+
+    /// Restores the progress of a run on the open document.
+    ///
+    /// The messages in flight belong to the document, so restore that first.
+    pub fn import_simulation(&mut self, simulation: SimulationSnapshot) {
+        self.is_simulation_running = simulation.running;
+        self.flowchart.simulation_state = simulation.state;
+        self.flowchart.current_step = simulation.current_step;
+        self.error_node = simulation.error_node.filter(|id| self.flowchart.nodes.contains_key(id));
+        self.traced_message = simulation.traced_message;
+        *self.simulation_engine.stats_mut() = simulation.stats;
+    }
+}
@@ -0,0 +1,257 @@
+//! Message filters and the node history inspector.
+//!
+//! Both the connection inspector and the node history list can be narrowed
+//! down with a [`MessageFilter`]. The node history also exposes the sampling
+//! setting that bounds how many delivered messages are kept, and on desktop
+//! reads back older messages moved to a temporary file a page at a time.
+
+use super::json_tree::show_json_tree;
+use super::state::FlowchartApp;
+#[cfg(not(target_arch = "wasm32"))]
+use super::state::OlderHistory;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::constants::HISTORY_SPILL_PAGE;
+use super::trace::short_trace_id;
+use crate::inspect::MessageFilter;
+use crate::shapes::ValueShape;
+use crate::types::*;
+use eframe::egui;
+
+/// Largest "keep every k-th message" value offered in the history inspector.
+const MAX_HISTORY_SAMPLING: u32 = 1000;
+
+/// Lists the inferred shape of the messages on a connection: each field
+/// with its types, marked with `?` when some messages left it out.
+pub(crate) fn draw_message_shape(ui: &mut egui::Ui, shape: Option<&ValueShape>) {
+    let Some(shape) = shape else {
+        ui.weak("No messages have crossed this connection in this run.");
+        return;
+    };
+    ui.weak(format!("Inferred from {} message(s): {}", shape.seen(), shape.type_label()));
+    for line in shape.describe() {
+        ui.horizontal(|ui| {
+            ui.add_space(12.0 * line.depth as f32);
+            let name = if line.optional { format!("{}?", line.name) } else { line.name };
+            ui.monospace(name);
+            ui.weak(line.types);
+        });
+    }
+}
+
+/// Draws the filter controls for a message list.
+///
+/// # Arguments
+///
+/// * `ui` - The egui UI to draw into
+/// * `filter` - The filter being edited
+/// * `with_steps` - Whether to offer the step range (messages in transit have no step)
+/// * `id_salt` - Distinguishes this filter from others in the same panel
+pub(crate) fn draw_message_filter(
+    ui: &mut egui::Ui,
+    filter: &mut MessageFilter,
+    with_steps: bool,
+    id_salt: &str,
+) {
+    let title = if filter.is_empty() { "Filter" } else { "Filter (active)" };
+    egui::CollapsingHeader::new(title)
+        .id_salt(id_salt)
+        .show(ui, |ui| {
+            egui::Grid::new((id_salt, "grid")).num_columns(2).show(ui, |ui| {
+                ui.label("JSONPath:");
+                let mut json_path = filter.json_path().to_string();
+                let edited = ui.add(
+                    egui::TextEdit::singleline(&mut json_path)
+                        .hint_text("$.status == \"ok\"")
+                        .font(egui::TextStyle::Monospace),
+                );
+                if edited.changed() {
+                    filter.set_json_path(json_path);
+                }
+                ui.end_row();
+                ui.label("Text:");
+                ui.text_edit_singleline(&mut filter.text);
+                ui.end_row();
+                ui.label("Trace ID:");
+                ui.add(egui::TextEdit::singleline(&mut filter.trace_id).hint_text("prefix"));
+                ui.end_row();
+                if with_steps {
+                    ui.label("From step:");
+                    optional_step(ui, &mut filter.step_from);
+                    ui.end_row();
+                    ui.label("To step:");
+                    optional_step(ui, &mut filter.step_to);
+                    ui.end_row();
+                }
+            });
+            if let Err(err) = filter.validate() {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+            if ui
+                .add_enabled(!filter.is_empty(), egui::Button::new("Clear Filter"))
+                .clicked()
+            {
+                *filter = MessageFilter::default();
+            }
+        });
+}
+
+/// Checkbox plus step field for an optional bound of the step range.
+fn optional_step(ui: &mut egui::Ui, bound: &mut Option<u64>) {
+    ui.horizontal(|ui| {
+        let mut enabled = bound.is_some();
+        if ui.checkbox(&mut enabled, "").changed() {
+            *bound = enabled.then_some(0);
+        }
+        if let Some(step) = bound {
+            ui.add(egui::DragValue::new(step).range(0..=u64::MAX));
+        }
+    });
+}
+
+impl FlowchartApp {
+    /// Renders the messages delivered to a node, newest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The egui UI context
+    /// * `node_id` - The node whose history is shown
+    pub(crate) fn draw_node_history(&mut self, ui: &mut egui::Ui, node_id: NodeId) {
+        ui.label("Message History:");
+
+        let mut sample_every = self.simulation_engine.history().sample_every();
+        ui.horizontal(|ui| {
+            ui.label("Keep every");
+            ui.add(egui::DragValue::new(&mut sample_every).range(1..=MAX_HISTORY_SAMPLING));
+            ui.label("message(s)");
+        })
+        .response
+        .on_hover_text("Sampling keeps memory bounded on long simulations");
+        if sample_every != self.simulation_engine.history().sample_every() {
+            self.simulation_engine
+                .history_mut()
+                .set_sample_every(sample_every);
+        }
+
+        draw_message_filter(ui, &mut self.inspector.history_filter, true, "history_filter");
+        #[cfg(not(target_arch = "wasm32"))]
+        self.draw_older_history_controls(ui, node_id);
+
+        let history = self.simulation_engine.history();
+        let filter = &self.inspector.history_filter;
+        let older = self
+            .inspector
+            .older_history
+            .as_ref()
+            .filter(|older| older.node_id == node_id)
+            .map_or(&[][..], |older| &older.entries[..]);
+        let matching: Vec<_> = older
+            .iter()
+            .chain(history.entries(node_id))
+            .filter(|entry| filter.matches(&entry.message, Some(entry.step)))
+            .collect();
+        let kept = older.len() + history.entries(node_id).count();
+        ui.label(format!(
+            "Received: {} (kept {}, showing {})",
+            history.received(node_id),
+            kept,
+            matching.len()
+        ));
+        if matching.is_empty() {
+            return;
+        }
+
+        let mut trace_clicked = None;
+        egui::ScrollArea::vertical()
+            .id_salt("node_history")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for (idx, entry) in matching.iter().rev().enumerate() {
+                    ui.push_id(idx, |ui| {
+                        let from = self
+                            .flowchart
+                            .nodes
+                            .get(&entry.from)
+                            .map(|n| n.name.as_str())
+                            .unwrap_or("(deleted node)");
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Step {} from {}", entry.step, from));
+                            if let Some(trace_id) = entry.message.trace_id {
+                                if ui
+                                    .small_button("Trace")
+                                    .on_hover_text(format!(
+                                        "Highlight the path of trace {}",
+                                        short_trace_id(trace_id)
+                                    ))
+                                    .clicked()
+                                {
+                                    trace_clicked = Some(trace_id);
+                                }
+                            }
+                        });
+                        show_json_tree(
+                            ui,
+                            &entry.message.data,
+                            self.syntax_theme.palette(ui.visuals().dark_mode),
+                        );
+                        ui.separator();
+                    });
+                }
+            });
+
+        if let Some(trace_id) = trace_clicked {
+            self.start_trace(trace_id);
+        }
+    }
+
+    /// Offers to read back a node's messages moved to disk, a page at a time,
+    /// and keeps the ones shown up to date as more are moved out.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn draw_older_history_controls(&mut self, ui: &mut egui::Ui, node_id: NodeId) {
+        let spilled = self.simulation_engine.history().spilled(node_id);
+        if self.inspector.older_history.as_ref().is_some_and(|older| older.node_id != node_id) {
+            self.inspector.older_history = None;
+        }
+        if spilled == 0 {
+            self.inspector.older_history = None;
+            return;
+        }
+
+        if let Some(older) = self.inspector.older_history.as_mut() {
+            let count = older.requested.min(spilled);
+            if older.read_for != Some((spilled, count)) {
+                older.read_for = Some((spilled, count));
+                match self.simulation_engine.history_mut().spilled_entries(node_id, spilled - count..spilled) {
+                    Ok(entries) => {
+                        older.entries = entries;
+                        older.error = None;
+                    }
+                    Err(error) => {
+                        older.entries.clear();
+                        older.error = Some(error);
+                    }
+                }
+            }
+        }
+
+        let shown = self.inspector.older_history.as_ref().map_or(0, |older| older.entries.len());
+        ui.horizontal(|ui| {
+            ui.label(format!("On disk: {} (loaded {})", spilled, shown));
+            if shown < spilled && ui.button(format!("Load {} Older", HISTORY_SPILL_PAGE.min(spilled - shown))).clicked() {
+                let older = self.inspector.older_history.get_or_insert(OlderHistory {
+                    node_id,
+                    requested: 0,
+                    read_for: None,
+                    entries: Vec::new(),
+                    error: None,
+                });
+                older.requested = shown + HISTORY_SPILL_PAGE;
+            }
+            if self.inspector.older_history.is_some() && ui.button("Unload").clicked() {
+                self.inspector.older_history = None;
+            }
+        });
+        if let Some(error) = self.inspector.older_history.as_ref().and_then(|older| older.error.as_ref()) {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+    }
+}
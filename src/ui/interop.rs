@@ -0,0 +1,56 @@
+//! Import and export in the formats of other modeling tools.
+//!
+//! The conversions themselves live in [`crate::bpmn`], [`crate::drawio`] and
+//! [`crate::node_table`]; this module saves their output through a file
+//! dialog (native) or a download (wasm). Imports are picked through the pending load operations
+//! in `file_ops` and placed with the "Import & Merge" dialog.
+
+use super::state::FlowchartApp;
+
+impl FlowchartApp {
+    /// Exports the flowchart as BPMN 2.0 XML.
+    pub fn export_bpmn(&mut self) {
+        crate::crash::record_event("Exported BPMN");
+        Self::save_export("flowchart.bpmn", "BPMN", &["bpmn"], crate::bpmn::to_bpmn(&self.flowchart));
+    }
+
+    /// Exports the flowchart as a draw.io diagram.
+    pub fn export_drawio(&mut self) {
+        crate::crash::record_event("Exported draw.io diagram");
+        Self::save_export("flowchart.drawio", "draw.io", &["drawio", "xml"], crate::drawio::to_drawio(&self.flowchart));
+    }
+
+    /// Exports every node's type and parameters as a CSV table.
+    pub fn export_node_table(&mut self) {
+        crate::crash::record_event("Exported node table");
+        Self::save_export("nodes.csv", "CSV", &["csv"], crate::node_table::node_table_csv(&self.flowchart));
+    }
+
+    /// Saves exported text: opens a save dialog (native) or triggers a download (wasm).
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    fn save_export(file_name: &str, filter: &'static str, extensions: &'static [&'static str], content: String) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Err(e) = Self::trigger_download(file_name, &content) {
+                eprintln!("Failed to start {} download: {}", filter, e);
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let file_name = file_name.to_string();
+            tokio::spawn(async move {
+                if let Some(handle) = rfd::AsyncFileDialog::new()
+                    .add_filter(filter, extensions)
+                    .set_file_name(file_name)
+                    .save_file()
+                    .await
+                {
+                    if let Err(e) = std::fs::write(handle.path(), content) {
+                        eprintln!("Failed to save {} file: {}", filter, e);
+                    }
+                }
+            });
+        }
+    }
+}
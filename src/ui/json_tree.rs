@@ -0,0 +1,165 @@
+//! Collapsible JSON tree viewer for message payloads.
+//!
+//! Objects and arrays can be expanded and collapsed. Clicking a value copies
+//! it to the clipboard; right-clicking any entry offers to copy its value or
+//! its JSONPath (in the syntax accepted by the inspector filters).
+
+use super::highlighters::SyntaxPalette;
+use eframe::egui;
+use serde_json::Value;
+
+/// Containers up to this depth start expanded.
+const DEFAULT_OPEN_DEPTH: usize = 1;
+
+/// Draws `value` as a collapsible tree.
+///
+/// # Arguments
+///
+/// * `ui` - The egui UI to draw into; wrap calls in `push_id` to keep trees apart
+/// * `value` - The JSON value to show
+/// * `palette` - Token colors, matching the code editors
+pub(crate) fn show_json_tree(ui: &mut egui::Ui, value: &Value, palette: &SyntaxPalette) {
+    show_entry(ui, None, "$".to_string(), value, palette, 0);
+}
+
+/// Appends a child segment to a JSONPath, quoting keys that are not plain identifiers.
+pub(crate) fn child_path(parent: &str, key: &str) -> String {
+    let plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        && !key.starts_with(|c: char| c.is_ascii_digit());
+    if plain {
+        format!("{}.{}", parent, key)
+    } else {
+        format!("{}['{}']", parent, key.replace('\'', "\\'"))
+    }
+}
+
+fn show_entry(
+    ui: &mut egui::Ui,
+    key: Option<&str>,
+    path: String,
+    value: &Value,
+    palette: &SyntaxPalette,
+    depth: usize,
+) {
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let color = |rgb: (u8, u8, u8)| egui::Color32::from_rgb(rgb.0, rgb.1, rgb.2);
+    let append = |job: &mut egui::text::LayoutJob, text: &str, color: egui::Color32| {
+        job.append(text, 0.0, egui::TextFormat::simple(font.clone(), color));
+    };
+    let key_text = |job: &mut egui::text::LayoutJob| {
+        if let Some(key) = key {
+            append(job, &format!("{}: ", key), color(palette.key));
+        }
+    };
+
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            let mut job = egui::text::LayoutJob::default();
+            key_text(&mut job);
+            append(&mut job, &format!("{{…}} {} field(s)", map.len()), color(palette.text));
+            let response = egui::CollapsingHeader::new(job)
+                .id_salt(&path)
+                .default_open(depth < DEFAULT_OPEN_DEPTH)
+                .show(ui, |ui| {
+                    for (child_key, child) in map {
+                        show_entry(
+                            ui,
+                            Some(child_key),
+                            child_path(&path, child_key),
+                            child,
+                            palette,
+                            depth + 1,
+                        );
+                    }
+                });
+            copy_menu(&response.header_response, &path, value);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            let mut job = egui::text::LayoutJob::default();
+            key_text(&mut job);
+            append(&mut job, &format!("[…] {} item(s)", items.len()), color(palette.text));
+            let response = egui::CollapsingHeader::new(job)
+                .id_salt(&path)
+                .default_open(depth < DEFAULT_OPEN_DEPTH)
+                .show(ui, |ui| {
+                    for (index, child) in items.iter().enumerate() {
+                        show_entry(
+                            ui,
+                            Some(&index.to_string()),
+                            format!("{}[{}]", path, index),
+                            child,
+                            palette,
+                            depth + 1,
+                        );
+                    }
+                });
+            copy_menu(&response.header_response, &path, value);
+        }
+        leaf => {
+            let leaf_color = match leaf {
+                Value::String(_) => palette.string,
+                Value::Number(_) => palette.number,
+                Value::Bool(_) | Value::Null => palette.keyword,
+                // Empty objects and arrays
+                _ => palette.text,
+            };
+            let mut job = egui::text::LayoutJob::default();
+            key_text(&mut job);
+            append(&mut job, &leaf.to_string(), color(leaf_color));
+            let response = ui
+                .add(egui::Label::new(job).sense(egui::Sense::click()))
+                .on_hover_text(format!("{}\nClick to copy the value", path));
+            if response.clicked() {
+                ui.ctx().copy_text(copy_text(leaf));
+            }
+            copy_menu(&response, &path, leaf);
+        }
+    }
+}
+
+/// Text placed on the clipboard for a value; strings are copied without quotes.
+fn copy_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_else(|_| other.to_string()),
+    }
+}
+
+/// Context menu offering to copy an entry's value or path.
+fn copy_menu(response: &egui::Response, path: &str, value: &Value) {
+    response.context_menu(|ui| {
+        if ui.button("Copy Value").clicked() {
+            ui.ctx().copy_text(copy_text(value));
+            ui.close();
+        }
+        if ui.button("Copy Path").clicked() {
+            ui.ctx().copy_text(path.to_string());
+            ui.close();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_child_paths_round_trip_through_filters() {
+        let data = json!({"order": {"line items": [{"sku": "a"}]}, "2x": true});
+        let items = child_path(&child_path("$", "order"), "line items");
+        assert_eq!(items, "$.order['line items']");
+        let sku = child_path(&format!("{}[0]", items), "sku");
+        assert_eq!(sku, "$.order['line items'][0].sku");
+        assert_eq!(
+            crate::inspect::select_json_path(&data, &sku).unwrap(),
+            vec![&json!("a")]
+        );
+        assert_eq!(child_path("$", "2x"), "$['2x']");
+        assert_eq!(copy_text(&json!("plain")), "plain");
+        assert_eq!(copy_text(&json!(3)), "3");
+    }
+}
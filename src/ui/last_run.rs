@@ -0,0 +1,74 @@
+//! The last stopped run.
+//!
+//! Stopping a simulation clears the run so the next one starts fresh, but the
+//! final state of the stopped run is kept for reading until the next run
+//! starts: how long it ran, its statistics and the messages still on their
+//! way. Nothing here can be edited or resumed.
+
+use super::json_tree::show_json_tree;
+use super::state::FlowchartApp;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Draws the last run window. Opens when `show_last_run_dialog` is true.
+    pub(crate) fn draw_last_run_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let palette = self.syntax_theme.palette(ctx.style().visuals.dark_mode);
+        let limit = self.message_render_limit;
+
+        egui::Window::new("Last Run")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                let Some(last_run) = &self.last_run else {
+                    ui.label("No run has been stopped since the last one started.");
+                    return;
+                };
+                ui.label(format!("Stopped after {} steps. Kept until the next run starts.", last_run.steps));
+                ui.separator();
+
+                let summary = &last_run.summary;
+                egui::Grid::new("last_run_summary_grid").num_columns(2).striped(true).show(ui, |ui| {
+                    let rows = [
+                        ("Measured steps", summary.measured_steps.to_string()),
+                        ("Produced", summary.produced.to_string()),
+                        ("Delivered", summary.delivered.to_string()),
+                        ("Consumed", summary.consumed.to_string()),
+                        ("Throughput (msg/step)", format!("{:.2}", summary.throughput())),
+                        ("In transit (max)", summary.max_in_transit.to_string()),
+                        ("Script errors", summary.errors.to_string()),
+                    ];
+                    for (label, value) in rows {
+                        ui.label(label);
+                        ui.monospace(value);
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+
+                let in_flight: usize = last_run.in_flight.iter().map(|(_, _, messages)| messages.len()).sum();
+                ui.label(format!("{} message(s) were still in flight", in_flight));
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    for (index, (from, to, messages)) in last_run.in_flight.iter().enumerate() {
+                        egui::CollapsingHeader::new(format!("{} → {} ({})", from, to, messages.len()))
+                            .id_salt(("last_run_connection", index))
+                            .show(ui, |ui| {
+                                for message in messages.iter().take(limit) {
+                                    ui.push_id(message.id, |ui| show_json_tree(ui, &message.data, palette));
+                                    ui.separator();
+                                }
+                                if messages.len() > limit {
+                                    ui.weak(format!("and {} more", messages.len() - limit));
+                                }
+                            });
+                    }
+                });
+            });
+
+        if !keep_open {
+            self.show_last_run_dialog = false;
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Animated auto-layouts and the layout preview.
+//!
+//! Auto-layouts record their undo entry right away and then let the nodes
+//! glide to their new positions instead of jumping there. In preview mode the
+//! proposed positions are drawn as ghosts until the layout is applied or
+//! cancelled. Inserted nodes can also push their neighbours aside, and the
+//! force-directed layout can be tuned with live re-runs.
+
+use super::animation::ease_out;
+use super::state::{ForceLayoutSettings, FlowchartApp, LayoutAnimation, LayoutPreview, NodeMove};
+use super::undo::{UndoAction, UndoableFlowchart};
+use crate::analysis::plan_make_room;
+use crate::constants::LAYOUT_ANIMATION_SECS;
+use crate::types::*;
+use eframe::egui;
+use eframe::epaint::StrokeKind;
+use std::collections::HashMap;
+
+impl FlowchartApp {
+    /// Runs the toolbar's auto-layout: as a preview when `preview_layout` is
+    /// set, otherwise applied straight away.
+    pub(crate) fn run_auto_arrangement(&mut self) {
+        if self.preview_layout {
+            self.preview_auto_arrangement();
+        } else {
+            self.animate_layout(Self::apply_auto_arrangement);
+        }
+    }
+
+    /// Runs `layout` and animates the nodes it moved from their old positions.
+    ///
+    /// With reduced motion the nodes move at once.
+    pub(crate) fn animate_layout(&mut self, layout: impl FnOnce(&mut Self)) {
+        self.finish_layout_animation();
+        self.layout_preview = None;
+        let before = self.node_positions();
+        layout(self);
+        if self.reduce_motion {
+            return;
+        }
+
+        let moves: Vec<NodeMove> = self
+            .flowchart
+            .nodes
+            .values()
+            .filter_map(|n| {
+                let from = *before.get(&n.id)?;
+                (from != n.position).then_some(NodeMove { node_id: n.id, from, to: n.position })
+            })
+            .collect();
+        if moves.is_empty() {
+            return;
+        }
+        for m in &moves {
+            if let Some(node) = self.flowchart.nodes.get_mut(&m.node_id) {
+                node.position = m.from;
+            }
+        }
+        self.layout_animation = Some(LayoutAnimation {
+            started: self.frame_time,
+            moves,
+        });
+    }
+
+    /// Moves the nodes of a running layout animation for this frame.
+    ///
+    /// Any click or key press lands them first, so it acts on the final layout.
+    pub(crate) fn advance_layout_animation(&mut self, ctx: &egui::Context) {
+        let Some(animation) = &self.layout_animation else {
+            return;
+        };
+        let interrupted = ctx.input(|i| {
+            i.pointer.any_pressed()
+                || i.events.iter().any(|e| matches!(e, egui::Event::Key { pressed: true, .. }))
+        });
+        let t = ((self.frame_time - animation.started) / LAYOUT_ANIMATION_SECS) as f32;
+        if interrupted || t >= 1.0 {
+            self.finish_layout_animation();
+            return;
+        }
+
+        let eased = ease_out(t);
+        for m in &animation.moves {
+            if let Some(node) = self.flowchart.nodes.get_mut(&m.node_id) {
+                node.position = (
+                    m.from.0 + (m.to.0 - m.from.0) * eased,
+                    m.from.1 + (m.to.1 - m.from.1) * eased,
+                );
+            }
+        }
+        ctx.request_repaint();
+    }
+
+    /// Puts the nodes of a running layout animation at their final positions.
+    pub(crate) fn finish_layout_animation(&mut self) {
+        let Some(animation) = self.layout_animation.take() else {
+            return;
+        };
+        for m in animation.moves {
+            if let Some(node) = self.flowchart.nodes.get_mut(&m.node_id) {
+                node.position = m.to;
+            }
+        }
+    }
+
+    /// Computes the selected auto-layout without changing the document and
+    /// shows it as a preview. Does nothing if the layout would move nothing.
+    pub(crate) fn preview_auto_arrangement(&mut self) {
+        self.finish_layout_animation();
+        let history = self.undo_history.clone();
+        let (dirty, changes) = (self.file.has_unsaved_changes, self.file.changes.clone());
+        let before = self.node_positions();
+
+        self.apply_auto_arrangement();
+        let action = self.undo_history.pop_undo();
+        let positions: Vec<(NodeId, (f32, f32))> = self
+            .flowchart
+            .nodes
+            .values()
+            .filter(|n| before.get(&n.id) != Some(&n.position))
+            .map(|n| (n.id, n.position))
+            .collect();
+
+        // Put everything back the way it was
+        self.undo_history = history;
+        self.file.has_unsaved_changes = dirty;
+        self.file.changes = changes;
+        self.layout_preview = action.and_then(|action| {
+            let redo = self.flowchart.apply_undo(&action)?;
+            Some(LayoutPreview { action, redo, positions })
+        });
+    }
+
+    /// Applies the previewed layout as one undo entry, animating the nodes.
+    pub(crate) fn apply_layout_preview(&mut self) {
+        let Some(preview) = self.layout_preview.take() else {
+            return;
+        };
+        self.animate_layout(|app| {
+            if app.flowchart.apply_undo(&preview.redo).is_some() {
+                app.file.mark_action(&preview.action);
+                app.undo_history.push_action(preview.action);
+            }
+        });
+    }
+
+    /// Draws the Apply/Cancel bar while a layout preview is shown.
+    pub(crate) fn draw_layout_preview_bar(&mut self, ctx: &egui::Context) {
+        let Some(preview) = &self.layout_preview else {
+            return;
+        };
+        let mut apply = false;
+        let mut cancel = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        egui::Window::new("Layout Preview")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 48.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let count = preview.positions.len();
+                    ui.label(format!(
+                        "Layout preview: {} node{} would move",
+                        count,
+                        if count == 1 { "" } else { "s" }
+                    ));
+                    if ui.button("Apply").clicked() {
+                        apply = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+        if apply {
+            self.apply_layout_preview();
+        } else if cancel {
+            self.layout_preview = None;
+        }
+    }
+
+    /// Draws the previewed positions as ghost outlines joined to the nodes.
+    pub(crate) fn draw_layout_ghosts(&self, painter: &egui::Painter) {
+        let Some(preview) = &self.layout_preview else {
+            return;
+        };
+        let size = egui::vec2(crate::constants::NODE_WIDTH, crate::constants::NODE_HEIGHT)
+            * self.canvas.zoom_factor;
+        let color = egui::Color32::from_rgb(100, 150, 255);
+        for (id, position) in &preview.positions {
+            let Some(node) = self.flowchart.nodes.get(id) else {
+                continue;
+            };
+            let current = self.world_to_screen(egui::pos2(node.position.0, node.position.1));
+            let ghost = self.world_to_screen(egui::pos2(position.0, position.1));
+            painter.extend(egui::Shape::dashed_line(
+                &[current, ghost],
+                egui::Stroke::new(1.0, color.gamma_multiply(0.6)),
+                6.0,
+                4.0,
+            ));
+            let rect = egui::Rect::from_center_size(ghost, size);
+            painter.rect_filled(rect, 5.0, color.gamma_multiply(0.15));
+            painter.rect_stroke(rect, 5.0, egui::Stroke::new(1.5, color), StrokeKind::Outside);
+        }
+    }
+
+    /// Nudges nodes out of the way of `inserted` if `make_room_on_insert` is set.
+    ///
+    /// Returns the moves for the caller to record in the same undo entry as
+    /// the insertion; the nodes glide to their new positions.
+    pub(crate) fn make_room_for(&mut self, inserted: &[NodeId]) -> Option<UndoAction> {
+        if !self.make_room_on_insert {
+            return None;
+        }
+        let new_positions = plan_make_room(&self.flowchart, inserted);
+        if new_positions.is_empty() {
+            return None;
+        }
+        let old_positions = new_positions
+            .iter()
+            .filter_map(|(id, _)| self.flowchart.nodes.get(id).map(|n| (*id, n.position)))
+            .collect();
+        self.animate_layout(|app| {
+            for (id, position) in &new_positions {
+                if let Some(node) = app.flowchart.nodes.get_mut(id) {
+                    node.position = *position;
+                }
+            }
+        });
+        Some(UndoAction::MultipleNodesMoved { old_positions, new_positions })
+    }
+
+    /// Draws the tuning popover for the force-directed layout.
+    ///
+    /// With live tuning on, every change re-runs the layout in place of the
+    /// previous run from the popover, so the values can be compared without
+    /// piling up undo entries.
+    pub(crate) fn draw_force_layout_tuning(&mut self, ui: &mut egui::Ui) {
+        let response = ui.menu_button("⚙", |ui| {
+            let settings = &mut self.force_layout;
+            let mut changed = false;
+            egui::Grid::new("force_layout_tuning_grid").num_columns(2).show(ui, |ui| {
+                ui.label("Iterations:");
+                changed |= ui.add(egui::DragValue::new(&mut settings.iterations).range(1..=5000)).changed();
+                ui.end_row();
+                ui.label("Repulsion:");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut settings.repulsion).range(0.0..=500_000.0).speed(500.0))
+                    .changed();
+                ui.end_row();
+                ui.label("Attraction:");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut settings.attraction).range(0.0..=1.0).speed(0.005))
+                    .changed();
+                ui.end_row();
+                ui.label("Ideal edge length:");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut settings.ideal_edge_length).range(0.0..=2000.0).speed(2.0))
+                    .changed();
+                ui.end_row();
+            });
+            ui.checkbox(&mut self.force_layout_live, "Live")
+                .on_hover_text("Re-run the layout as the values change");
+            let mut rerun = changed && self.force_layout_live;
+            ui.horizontal(|ui| {
+                rerun |= ui.button("Re-run").clicked();
+                if ui.button("Reset to Defaults").clicked() {
+                    self.force_layout = ForceLayoutSettings::default();
+                    rerun |= self.force_layout_live;
+                }
+            });
+            if rerun {
+                self.rerun_force_layout();
+            }
+        });
+        if response.inner.is_none() {
+            // Popover closed: the next run starts a new undo entry
+            self.force_tuning_undo_len = None;
+        }
+    }
+
+    /// Runs the force-directed layout, replacing the previous run from the
+    /// tuning popover if nothing else was recorded since.
+    pub(crate) fn rerun_force_layout(&mut self) {
+        self.finish_layout_animation();
+        self.layout_preview = None;
+        if self.force_tuning_undo_len.take() == Some(self.undo_history.undo_len()) {
+            if let Some(inverse) = self
+                .undo_history
+                .pop_undo()
+                .and_then(|action| self.flowchart.apply_undo(&action))
+            {
+                self.file.mark_action(&inverse);
+            }
+        }
+        if self.auto_layout_graph() {
+            self.force_tuning_undo_len = Some(self.undo_history.undo_len());
+        }
+    }
+
+    /// Current position of every node.
+    fn node_positions(&self) -> HashMap<NodeId, (f32, f32)> {
+        self.flowchart
+            .nodes
+            .values()
+            .map(|n| (n.id, n.position))
+            .collect()
+    }
+}
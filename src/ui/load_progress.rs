@@ -0,0 +1,194 @@
+//! Background loading of large files.
+//!
+//! On native builds the picked file is read on its own thread in chunks, so
+//! the UI keeps drawing while a big document loads. Files from
+//! `LOAD_PROGRESS_MIN_BYTES` up show a progress dialog with a Cancel button;
+//! documents are parsed on the same thread before they are handed over.
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::state::LoadProgress;
+use super::state::{FileOperationResult, FlowchartApp};
+use eframe::egui;
+
+/// Reads `path` in chunks of `LOAD_CHUNK_BYTES`, calling `report` with the
+/// bytes read so far after each one.
+///
+/// Returns `Ok(None)` if `cancel` was set before the whole file was read.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn read_with_progress(
+    path: &std::path::Path,
+    cancel: &std::sync::atomic::AtomicBool,
+    mut report: impl FnMut(u64),
+) -> std::io::Result<Option<String>> {
+    use std::io::Read;
+    use std::sync::atomic::Ordering;
+
+    let mut file = std::fs::File::open(path)?;
+    let capacity = file.metadata().map(|m| m.len() as usize).unwrap_or(0);
+    let mut bytes = Vec::with_capacity(capacity);
+    let mut chunk = vec![0u8; crate::constants::LOAD_CHUNK_BYTES];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let read = match file.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        bytes.extend_from_slice(&chunk[..read]);
+        report(bytes.len() as u64);
+    }
+    String::from_utf8(bytes)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Loads `path` on a new thread and sends `completed` with its contents, or
+/// why it failed, on `sender`.
+///
+/// Large files report their progress first and can be cancelled from the
+/// progress dialog, in which case `LoadCancelled` is sent instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn spawn_load(
+    path: std::path::PathBuf,
+    completed: fn(String, String) -> FileOperationResult,
+    sender: Option<std::sync::mpsc::Sender<FileOperationResult>>,
+    ctx: egui::Context,
+) {
+    let Some(tx) = sender else {
+        return;
+    };
+    std::thread::spawn(move || {
+        let name = path.display().to_string();
+        let cancel = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let total_bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let show_progress = total_bytes >= crate::constants::LOAD_PROGRESS_MIN_BYTES;
+        if show_progress {
+            let _ = tx.send(FileOperationResult::LoadStarted(LoadProgress {
+                path: name.clone(),
+                bytes_read: 0,
+                total_bytes,
+                cancel: cancel.clone(),
+            }));
+            ctx.request_repaint();
+        }
+
+        let read = read_with_progress(&path, &cancel, |bytes_read| {
+            if show_progress {
+                let _ = tx.send(FileOperationResult::LoadProgressed(bytes_read));
+                ctx.request_repaint();
+            }
+        });
+        let result = match read {
+            Ok(Some(content)) => completed(name, content),
+            Ok(None) => FileOperationResult::LoadCancelled,
+            Err(e) => FileOperationResult::OperationFailed(format!("Failed to read file: {}", e)),
+        };
+        // Parsing cannot be interrupted, so a cancel during it drops the result
+        let result = if cancel.load(std::sync::atomic::Ordering::Relaxed) {
+            FileOperationResult::LoadCancelled
+        } else {
+            result
+        };
+        let _ = tx.send(result);
+        ctx.request_repaint();
+    });
+}
+
+impl FlowchartApp {
+    /// Clears the progress dialog when `result` ends a background load.
+    ///
+    /// Returns true if that load was cancelled, so its result must be dropped.
+    pub(crate) fn load_result_cancelled(&mut self, result: &FileOperationResult) -> bool {
+        match result {
+            FileOperationResult::LoadCompleted(..)
+            | FileOperationResult::DocumentParsed(..)
+            | FileOperationResult::MergeLoaded(..)
+            | FileOperationResult::PluginLoaded(..)
+            | FileOperationResult::RunBaselineLoaded(..)
+            | FileOperationResult::BpmnLoaded(..)
+            | FileOperationResult::TrafficTraceLoaded(..)
+            | FileOperationResult::NodeCsvLoaded(..)
+            | FileOperationResult::OperationFailed(_)
+            | FileOperationResult::LoadCancelled => self
+                .file
+                .load_progress
+                .take()
+                .is_some_and(|progress| progress.cancelled()),
+            _ => false,
+        }
+    }
+
+    /// Asks the running background load to stop and hides its dialog.
+    pub(crate) fn cancel_load(&mut self) {
+        if let Some(progress) = &self.file.load_progress {
+            progress.cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+            crate::crash::record_event("Cancelled loading document");
+        }
+    }
+
+    /// Draws the progress dialog while a large file loads.
+    pub(crate) fn draw_load_progress_dialog(&mut self, ctx: &egui::Context) {
+        let Some(progress) = self.file.load_progress.as_ref().filter(|p| !p.cancelled()) else {
+            return;
+        };
+        let mut cancel = false;
+        egui::Window::new("Opening Document")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(&progress.path);
+                if progress.parsing() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Parsing…");
+                    });
+                } else {
+                    let fraction = progress.bytes_read as f32 / progress.total_bytes.max(1) as f32;
+                    ui.add(
+                        egui::ProgressBar::new(fraction)
+                            .desired_width(280.0)
+                            .text(format!(
+                                "{:.1} of {:.1} MB",
+                                progress.bytes_read as f64 / 1_048_576.0,
+                                progress.total_bytes as f64 / 1_048_576.0
+                            )),
+                    );
+                }
+                if ui.button("Cancel").clicked() {
+                    cancel = true;
+                }
+            });
+        if cancel {
+            self.cancel_load();
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_read_with_progress_reports_chunks_and_stops_when_cancelled() {
+        let path = std::env::temp_dir().join(format!("flowchart_load_{}.json", uuid::Uuid::new_v4()));
+        let content = "x".repeat(crate::constants::LOAD_CHUNK_BYTES * 2 + 10);
+        std::fs::write(&path, &content).unwrap();
+
+        let cancel = AtomicBool::new(false);
+        let mut reports = Vec::new();
+        let read = read_with_progress(&path, &cancel, |bytes| reports.push(bytes)).unwrap();
+        assert_eq!(read.as_deref(), Some(content.as_str()));
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports.last(), Some(&(content.len() as u64)));
+
+        // Cancelling after the first chunk stops the read
+        let read = read_with_progress(&path, &cancel, |_| cancel.store(true, Ordering::Relaxed)).unwrap();
+        assert!(read.is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,610 @@
+//! The menu bar: File, Edit, View, Simulation and Help.
+//!
+//! Every command of the app is reachable from here; the toolbar below keeps
+//! quick access to the simulation controls and auto-layout. Each menu is
+//! drawn by a method of its own. The Help menu's keyboard shortcuts and
+//! about windows live here too. On macOS an application menu comes first,
+//! holding About, Preferences and Quit as a native menu bar would.
+
+#[cfg(target_arch = "wasm32")]
+use super::state::BrowserDocumentsMode;
+#[cfg(not(target_arch = "wasm32"))]
+use super::state::Modal;
+use super::state::{AutoArrangeMode, FlowchartApp, GridStyle, PendingConfirmAction};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::constants::APP_TITLE;
+use crate::constants::PIPELINE_NODE_SPACING;
+use crate::examples::all_examples;
+use eframe::egui;
+
+/// Shortcuts shown next to menu items; `handle_file_shortcuts`,
+/// `handle_undo_redo_keys` and the other key handlers act on them.
+const NEW_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::N);
+const OPEN_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O);
+const SAVE_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+const SAVE_AS_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT), egui::Key::S);
+#[cfg(not(target_arch = "wasm32"))]
+const QUIT_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Q);
+const UNDO_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+const REDO_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT), egui::Key::Z);
+const GROUP_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::G);
+const FRONT_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::CloseBracket);
+const BACK_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::OpenBracket);
+const PREFERENCES_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Comma);
+const VIEW_BACK_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT), egui::Key::ArrowLeft);
+const VIEW_FORWARD_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT), egui::Key::ArrowRight);
+
+/// Whether About, Preferences and Quit move to a macOS-style application menu.
+const APP_MENU: bool = cfg!(all(target_os = "macos", not(target_arch = "wasm32")));
+
+/// A menu button labelled with its keyboard shortcut.
+fn shortcut_button(ui: &mut egui::Ui, label: &str, shortcut: &egui::KeyboardShortcut) -> egui::Response {
+    let shortcut = ui.ctx().format_shortcut(shortcut);
+    ui.add(egui::Button::new(label).shortcut_text(shortcut))
+}
+
+impl FlowchartApp {
+    /// Draws the menu bar along the top of the window.
+    pub(crate) fn draw_menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("top_menu_bar").show(ctx, |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
+                #[cfg(not(target_arch = "wasm32"))]
+                if APP_MENU {
+                    self.app_menu(ui);
+                }
+                self.file_menu(ui);
+                self.edit_menu(ui);
+                self.view_menu(ui);
+                self.simulation_menu(ui);
+                self.help_menu(ui);
+            });
+        });
+    }
+
+    /// Documents: new, open, save, import and export.
+    fn file_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("File", |ui| {
+            if shortcut_button(ui, "New", &NEW_SHORTCUT).clicked() {
+                self.unless_unsaved(PendingConfirmAction::New, |app| app.new_flowchart());
+                ui.close();
+            }
+            if shortcut_button(ui, "Load…", &OPEN_SHORTCUT).clicked() {
+                self.unless_unsaved(PendingConfirmAction::Open, |app| app.load_flowchart());
+                ui.close();
+            }
+            #[cfg(target_arch = "wasm32")]
+            if ui.button("Open from Browser…").clicked() {
+                self.open_browser_documents(BrowserDocumentsMode::Open);
+                ui.close();
+            }
+            if ui.button("Import & Merge…").clicked() {
+                self.import_and_merge();
+                ui.close();
+            }
+            if ui.button("Import BPMN…").clicked() {
+                self.import_bpmn();
+                ui.close();
+            }
+            if ui
+                .button("Producers from Traffic…")
+                .on_hover_text("Propose producers that approximate a recorded JSON Lines trace")
+                .clicked()
+            {
+                self.import_traffic_trace();
+                ui.close();
+            }
+            if ui
+                .button("Nodes from CSV…")
+                .on_hover_text("Add nodes listed in a spreadsheet table, connected by a downstream column")
+                .clicked()
+            {
+                self.import_node_csv();
+                ui.close();
+            }
+            ui.add_enabled_ui(!self.file.is_example_readonly, |ui| {
+                if shortcut_button(ui, "Save", &SAVE_SHORTCUT).clicked() {
+                    self.save_flowchart();
+                    ui.close();
+                }
+            });
+            ui.add_enabled_ui(!self.file.is_example_readonly, |ui| {
+                if shortcut_button(ui, "Save As…", &SAVE_AS_SHORTCUT).clicked() {
+                    self.save_as_flowchart();
+                    ui.close();
+                }
+                #[cfg(target_arch = "wasm32")]
+                if ui.button("Save to Browser…").clicked() {
+                    self.open_browser_documents(BrowserDocumentsMode::Save);
+                    ui.close();
+                }
+            });
+            let has_selection = !self.selected_node_ids().is_empty();
+            ui.add_enabled_ui(has_selection, |ui| {
+                if ui.button("Export Selection…").clicked() {
+                    self.export_selection();
+                    ui.close();
+                }
+            });
+            ui.separator();
+            ui.menu_button("Examples", |ui| {
+                for ex in all_examples() {
+                    if ui.button(ex.name).clicked() {
+                        self.request_load_example(ex.kind);
+                        ui.close();
+                    }
+                }
+            });
+            ui.separator();
+            ui.menu_button("Export", |ui| {
+                if ui.button("SVG…").clicked() {
+                    // Open export dialog configured for SVG export
+                    self.pending_export_format = Some(crate::ui::state::ExportFormat::Svg);
+                    // Initialize defaults based on current app state
+                    self.export_options.include_grid = self.canvas.show_grid;
+                    self.export_options.background_color = if self.dark_mode {
+                        egui::Color32::from_gray(20)
+                    } else {
+                        egui::Color32::WHITE
+                    };
+                    // Default to whole graph; user can opt-in to selection only in dialog
+                    self.export_options.scope = crate::ui::state::ExportScope::WholeGraph;
+                    // Use the app's default connection color
+                    self.export_options.stroke_color = egui::Color32::DARK_GRAY;
+                    self.show_export_dialog = true;
+                    ui.close();
+                }
+                // PNG export is available on all targets
+                if ui.button("PNG…").clicked() {
+                    self.pending_export_format = Some(crate::ui::state::ExportFormat::Png);
+                    self.export_options.include_grid = self.canvas.show_grid;
+                    self.export_options.background_color = if self.dark_mode {
+                        egui::Color32::from_gray(20)
+                    } else {
+                        egui::Color32::WHITE
+                    };
+                    self.export_options.scope = crate::ui::state::ExportScope::WholeGraph;
+                    self.export_options.stroke_color = egui::Color32::DARK_GRAY;
+                    self.show_export_dialog = true;
+                    ui.close();
+                }
+                ui.separator();
+                if ui.button("BPMN…").clicked() {
+                    self.export_bpmn();
+                    ui.close();
+                }
+                if ui.button("draw.io…").clicked() {
+                    self.export_drawio();
+                    ui.close();
+                }
+                if ui
+                    .button("Node Table (CSV)…")
+                    .on_hover_text("Every node with its type and parameters, for spreadsheets")
+                    .clicked()
+                {
+                    self.export_node_table();
+                    ui.close();
+                }
+            });
+            #[cfg(not(target_arch = "wasm32"))]
+            if !APP_MENU {
+                ui.separator();
+                self.quit_item(ui, "Quit");
+            }
+        });
+    }
+
+    /// The macOS application menu: About, Preferences and Quit.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn app_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button(APP_TITLE, |ui| {
+            if ui.button(format!("About {}", APP_TITLE)).clicked() {
+                self.show_about_dialog = true;
+                ui.close();
+            }
+            ui.separator();
+            if shortcut_button(ui, "Preferences…", &PREFERENCES_SHORTCUT).clicked() {
+                self.show_preferences_dialog = true;
+                ui.close();
+            }
+            ui.separator();
+            self.quit_item(ui, &format!("Quit {}", APP_TITLE));
+        });
+    }
+
+    /// Quits, asking first if there are unsaved changes.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn quit_item(&mut self, ui: &mut egui::Ui, label: &str) {
+        if shortcut_button(ui, label, &QUIT_SHORTCUT).clicked() {
+            if self.file.has_unsaved_changes_effective() {
+                self.dialogs.open(Modal::UnsavedChanges(PendingConfirmAction::Quit));
+            } else {
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+            ui.close();
+        }
+    }
+
+    /// Undo, copying, layout and editing tools, plugins and preferences.
+    fn edit_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Edit", |ui| {
+            let can_undo = self.undo_history.can_undo();
+            let can_redo = self.undo_history.can_redo();
+            ui.add_enabled_ui(can_undo, |ui| {
+                if shortcut_button(ui, "⟲ Undo", &UNDO_SHORTCUT).clicked() {
+                    self.perform_undo();
+                    ui.close();
+                }
+            });
+            ui.add_enabled_ui(can_redo, |ui| {
+                if shortcut_button(ui, "⟳ Redo", &REDO_SHORTCUT).clicked() {
+                    self.perform_redo();
+                    ui.close();
+                }
+            });
+            if ui
+                .button("Edit History…")
+                .on_hover_text("Who changed what in this document, and when")
+                .clicked()
+            {
+                self.show_edit_history_dialog = true;
+                ui.close();
+            }
+            ui.separator();
+            if ui
+                .button("Copy Canvas as Image")
+                .on_hover_text("Copy the selection, or the visible canvas, as a PNG")
+                .clicked()
+            {
+                self.copy_canvas_as_image(ui.ctx());
+                ui.close();
+            }
+            ui.separator();
+            ui.add_enabled_ui(!self.selected_node_ids().is_empty(), |ui| {
+                if shortcut_button(ui, "Group Selection", &GROUP_SHORTCUT).clicked() {
+                    self.group_selection();
+                    ui.close();
+                }
+            });
+            let can_restack = !self.selected_node_ids().is_empty() || self.interaction.selected_group.is_some();
+            ui.add_enabled_ui(can_restack, |ui| {
+                if shortcut_button(ui, "Bring to Front", &FRONT_SHORTCUT).clicked() {
+                    self.bring_selection_to_front();
+                    ui.close();
+                }
+                if shortcut_button(ui, "Send to Back", &BACK_SHORTCUT).clicked() {
+                    self.send_selection_to_back();
+                    ui.close();
+                }
+            });
+            if ui.button("Add Pipeline…").clicked() {
+                // Center the chain in the window
+                let center = self.screen_to_world(ui.ctx().input(|i| i.content_rect().center()));
+                self.open_pipeline_wizard((center.x - PIPELINE_NODE_SPACING, center.y));
+                ui.close();
+            }
+            let selection = self.selected_node_ids();
+            ui.add_enabled_ui(selection.len() > 1, |ui| {
+                if ui
+                    .button("Connect Selection in Order")
+                    .on_hover_text("Connect each selected node to the next, in the order they were selected")
+                    .clicked()
+                {
+                    self.connect_selection_in_order();
+                    ui.close();
+                }
+            });
+            ui.add_enabled_ui(!selection.is_empty(), |ui| {
+                if ui
+                    .button("Pin Selected")
+                    .on_hover_text("Keep the selected nodes in place during force-directed layout")
+                    .clicked()
+                {
+                    self.set_nodes_pinned(&selection, true);
+                    ui.close();
+                }
+                if ui.button("Unpin Selected").clicked() {
+                    self.set_nodes_pinned(&selection, false);
+                    ui.close();
+                }
+            });
+            ui.add_enabled_ui(!selection.is_empty(), |ui| {
+                if ui
+                    .button("Replicate…")
+                    .on_hover_text("Make numbered copies of the selection, wired to the same nodes")
+                    .clicked()
+                {
+                    self.open_replicate_dialog(&selection);
+                    ui.close();
+                }
+            });
+            if ui
+                .button("Align Connected Nodes")
+                .on_hover_text("Nudge the selected nodes (or all) so nearly straight connections become horizontal or vertical")
+                .clicked()
+            {
+                self.animate_layout(Self::align_connected_nodes);
+                ui.close();
+            }
+            ui.menu_button("Auto Layout", |ui| {
+                for (mode, label) in [
+                    (AutoArrangeMode::ForceDirected, "Force-directed"),
+                    (AutoArrangeMode::Grid, "Grid"),
+                    (AutoArrangeMode::Line, "Line"),
+                    (AutoArrangeMode::Swimlanes, "Swimlanes"),
+                ] {
+                    if ui.button(label).clicked() {
+                        self.auto_arrange_mode = mode;
+                        self.run_auto_arrangement();
+                        ui.close();
+                    }
+                }
+                ui.separator();
+                ui.checkbox(&mut self.preview_layout, "Preview before applying");
+            });
+            if ui.button("Tidy…").clicked() {
+                self.show_tidy_dialog = true;
+                ui.close();
+            }
+            if ui.button("Auto-group…").clicked() {
+                self.auto_group_declined.clear();
+                self.show_auto_group_dialog = true;
+                ui.close();
+            }
+            ui.separator();
+            if ui.button("Plugins…").clicked() {
+                self.show_plugins_dialog = true;
+                ui.close();
+            }
+            if !APP_MENU && shortcut_button(ui, "Preferences…", &PREFERENCES_SHORTCUT).clicked() {
+                self.show_preferences_dialog = true;
+                ui.close();
+            }
+        });
+    }
+
+    /// Canvas appearance, swimlanes and the document summary.
+    fn view_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("View", |ui| {
+            ui.menu_button("Background", |ui| {
+                let mut style = self.canvas.show_grid.then_some(self.canvas.grid_style);
+                ui.radio_value(&mut style, None, "Plain");
+                ui.radio_value(&mut style, Some(GridStyle::Dots), "Dotted grid");
+                ui.radio_value(&mut style, Some(GridStyle::Lines), "Lined grid");
+                self.canvas.show_grid = style.is_some();
+                if let Some(style) = style {
+                    self.canvas.grid_style = style;
+                }
+                ui.separator();
+
+                let mut custom = self.canvas.background_color.is_some();
+                if ui.checkbox(&mut custom, "Custom color").changed() {
+                    self.canvas.background_color = if custom {
+                        let fill = ui.ctx().style().visuals.extreme_bg_color;
+                        Some((fill.r(), fill.g(), fill.b()))
+                    } else {
+                        None
+                    };
+                }
+                if let Some((r, g, b)) = self.canvas.background_color {
+                    let mut rgb = [r, g, b];
+                    ui.horizontal(|ui| {
+                        ui.label("Color:");
+                        ui.color_edit_button_srgb(&mut rgb);
+                    });
+                    self.canvas.background_color = Some((rgb[0], rgb[1], rgb[2]));
+                }
+            });
+            if ui
+                .checkbox(&mut self.canvas.show_origin_marker, "Show Origin Marker")
+                .changed()
+            {
+                ui.close();
+            }
+            if ui
+                .checkbox(&mut self.canvas.snap_connections, "Snap Straight Connections")
+                .on_hover_text("Draw nearly horizontal or vertical connections perfectly straight")
+                .changed()
+            {
+                ui.close();
+            }
+            if ui
+                .checkbox(&mut self.canvas.show_consumer_sparklines, "Consumer Sparklines")
+                .on_hover_text("Chart recent consumption inside consumer nodes while simulating")
+                .changed()
+            {
+                ui.close();
+            }
+            if ui.checkbox(&mut self.dark_mode, "Dark Mode").changed() {
+                ui.close();
+            }
+            if ui.button("Swimlanes…").clicked() {
+                self.show_swimlanes_dialog = true;
+                ui.close();
+            }
+            if ui.button("Formatting Rules…").clicked() {
+                self.show_format_rules_dialog = true;
+                ui.close();
+            }
+            if self.view_history_enabled {
+                ui.separator();
+                ui.add_enabled_ui(self.can_view_back(), |ui| {
+                    if shortcut_button(ui, "Previous View", &VIEW_BACK_SHORTCUT).clicked() {
+                        self.view_back();
+                        ui.close();
+                    }
+                });
+                ui.add_enabled_ui(self.can_view_forward(), |ui| {
+                    if shortcut_button(ui, "Next View", &VIEW_FORWARD_SHORTCUT).clicked() {
+                        self.view_forward();
+                        ui.close();
+                    }
+                });
+            }
+            ui.separator();
+            if ui.button("About this flowchart…").clicked() {
+                self.show_stats_dialog = true;
+                ui.close();
+            }
+        });
+    }
+
+    /// Running the simulation and looking at its results.
+    fn simulation_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Simulation", |ui| {
+            if self.is_simulation_running {
+                if ui.button("Pause").clicked() {
+                    self.pause_simulation();
+                    ui.close();
+                }
+            } else if ui.button("Start").clicked() {
+                self.start_simulation();
+                ui.close();
+            }
+            if ui.button("Stop").clicked() {
+                self.request_stop_simulation();
+                ui.close();
+            }
+            if ui.button("Step").clicked() {
+                self.step_simulation();
+                ui.close();
+            }
+            ui.separator();
+            if ui
+                .button("Parameters…")
+                .on_hover_text("Named values node properties, templates and scripts refer to")
+                .clicked()
+            {
+                self.show_parameters_dialog = true;
+                ui.close();
+            }
+            if ui.button("Run Statistics…").clicked() {
+                self.show_run_stats_dialog = true;
+                ui.close();
+            }
+            let has_steps = !self.simulation_engine.stats().is_empty();
+            if ui
+                .add_enabled(has_steps, egui::Button::new("Export Run Steps (CSV)…"))
+                .on_disabled_hover_text("Run the simulation first")
+                .clicked()
+            {
+                self.export_run_steps_csv();
+                ui.close();
+            }
+            if ui
+                .add_enabled(self.last_run.is_some(), egui::Button::new("Last Run…"))
+                .on_disabled_hover_text("Stop a run to keep its final state here")
+                .clicked()
+            {
+                self.show_last_run_dialog = true;
+                ui.close();
+            }
+            if ui.checkbox(&mut self.show_events_panel, "Events Log").changed() {
+                ui.close();
+            }
+            let errors = self.simulation_engine.errors().len();
+            let label = if errors > 0 { format!("Errors ({})…", errors) } else { "Errors…".to_string() };
+            if ui.button(label).clicked() {
+                self.show_errors_dialog = true;
+                ui.close();
+            }
+            if ui.button("Parameter Sweep…").clicked() {
+                self.show_sweep_dialog = true;
+                ui.close();
+            }
+            if ui.button("Record Run…").clicked() {
+                self.show_recording_dialog = true;
+                ui.close();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui
+                .button("Verify Determinism")
+                .on_hover_text("Run the document twice with the same seed and compare every step")
+                .clicked()
+            {
+                self.verify_determinism();
+                ui.close();
+            }
+        });
+    }
+
+    /// Keyboard shortcuts and the app version.
+    fn help_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Help", |ui| {
+            if ui.button("Keyboard Shortcuts…").clicked() {
+                self.show_shortcuts_dialog = true;
+                ui.close();
+            }
+            if !APP_MENU && ui.button("About Flowchart Tool…").clicked() {
+                self.show_about_dialog = true;
+                ui.close();
+            }
+        });
+    }
+
+    /// Draws the keyboard shortcuts window while it is open.
+    pub(crate) fn draw_shortcuts_dialog(&mut self, ctx: &egui::Context) {
+        let modifier = if super::is_macos_platform() { "Cmd" } else { "Ctrl" };
+        let alt = if super::is_macos_platform() { "Option" } else { "Alt" };
+        let rows = [
+            ("New, Open", ctx.format_shortcut(&NEW_SHORTCUT) + ", " + &ctx.format_shortcut(&OPEN_SHORTCUT)),
+            ("Save, Save As", ctx.format_shortcut(&SAVE_SHORTCUT) + ", " + &ctx.format_shortcut(&SAVE_AS_SHORTCUT)),
+            ("Preferences", ctx.format_shortcut(&PREFERENCES_SHORTCUT)),
+            ("Undo", ctx.format_shortcut(&UNDO_SHORTCUT)),
+            ("Redo", ctx.format_shortcut(&REDO_SHORTCUT) + " or " + &format!("{}+Y", modifier)),
+            ("Group the selection", ctx.format_shortcut(&GROUP_SHORTCUT)),
+            (
+                "Bring to front, send to back",
+                ctx.format_shortcut(&FRONT_SHORTCUT) + ", " + &ctx.format_shortcut(&BACK_SHORTCUT),
+            ),
+            ("Delete the selection", "Delete".to_string()),
+            ("Select, toggle selection", "Click, Shift+Click".to_string()),
+            ("Marquee select (add)", "Drag on empty canvas (Shift+Drag)".to_string()),
+            ("Move nodes (snap to grid)", "Drag (Shift+Drag)".to_string()),
+            ("Connect nodes", "Shift+Drag from a node".to_string()),
+            ("Duplicate and connect", format!("{}+Drag from a node", alt)),
+            ("Add a node", "Right-click the canvas".to_string()),
+            ("Pan", format!("Middle-drag or {}+Drag", modifier)),
+            ("Pan (sideways, keyboard)", "Shift+Scroll, arrow keys or W/A/S/D".to_string()),
+            ("Zoom", format!("Scroll wheel, {m}+= / {m}+-", m = modifier)),
+            ("Zoom to 100%", format!("{}+0", modifier)),
+            (
+                "Previous view, next view",
+                ctx.format_shortcut(&VIEW_BACK_SHORTCUT) + ", " + &ctx.format_shortcut(&VIEW_FORWARD_SHORTCUT),
+            ),
+            ("Step, step several", format!("{k}, Shift+{k}", k = self.step_key.name())),
+        ];
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut self.show_shortcuts_dialog)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts_grid").striped(true).show(ui, |ui| {
+                    for (action, keys) in rows {
+                        ui.label(action);
+                        ui.label(keys);
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+
+    /// Draws the about window while it is open.
+    pub(crate) fn draw_about_dialog(&mut self, ctx: &egui::Context) {
+        egui::Window::new("About Flowchart Tool")
+            .open(&mut self.show_about_dialog)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.heading("Flowchart Tool");
+                ui.label(format!("Version {}", env!("CARGO_PKG_VERSION")));
+                ui.label("A visual editor and simulator for message flows between producers, transformers and consumers.");
+            });
+    }
+}
@@ -0,0 +1,132 @@
+//! Import & merge of other flowchart files.
+//!
+//! A flowchart read from disk is staged in [`PendingMerge`] while the user
+//! picks an offset, then inserted into the current document with fresh IDs as
+//! a single undo entry.
+
+use super::state::{FlowchartApp, PendingMerge};
+use crate::constants::NODE_WIDTH;
+use crate::types::*;
+use crate::ui::UndoAction;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Stages a loaded flowchart for merging and opens the merge dialog.
+    ///
+    /// The suggested offset places the incoming nodes to the right of the
+    /// current contents, aligned with their top edge.
+    pub(crate) fn begin_merge(&mut self, source: String, flowchart: Flowchart) {
+        let offset = default_merge_offset(&self.flowchart, &flowchart);
+        self.pending_merge = Some(PendingMerge {
+            source,
+            flowchart,
+            offset,
+        });
+    }
+
+    /// Draws the "Import & Merge" dialog while a merge is pending.
+    pub(crate) fn draw_merge_dialog(&mut self, ctx: &egui::Context) {
+        let Some(pending) = self.pending_merge.as_mut() else {
+            return;
+        };
+
+        let mut keep_open = true;
+        let mut confirm = false;
+        let mut cancel = false;
+        egui::Window::new("Import & Merge")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Merge {} node(s), {} connection(s) and {} group(s) from \"{}\".",
+                    pending.flowchart.nodes.len(),
+                    pending.flowchart.connections.len(),
+                    pending.flowchart.groups.len(),
+                    pending.source
+                ));
+                ui.horizontal(|ui| {
+                    ui.label("Offset:");
+                    ui.add(egui::DragValue::new(&mut pending.offset.0).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut pending.offset.1).prefix("y: "));
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Merge").clicked() {
+                        confirm = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if confirm {
+            if let Some(pending) = self.pending_merge.take() {
+                self.apply_merge(&pending.flowchart, pending.offset);
+            }
+        } else if cancel || !keep_open {
+            self.pending_merge = None;
+        }
+    }
+
+    /// Merges `other` into the current flowchart at `offset` as one undo entry.
+    ///
+    /// The inserted nodes become the selection so they can be moved together.
+    pub(crate) fn apply_merge(&mut self, other: &Flowchart, offset: (f32, f32)) -> MergeResult {
+        let result = self.flowchart.merge(other, offset);
+        if result.node_ids.is_empty() && result.group_ids.is_empty() {
+            return result;
+        }
+
+        let mut actions: Vec<UndoAction> = result
+            .node_ids
+            .iter()
+            .map(|&node_id| UndoAction::NodeCreated { node_id })
+            .collect();
+        actions.extend(
+            result
+                .connections
+                .iter()
+                .map(|&(from, to)| UndoAction::ConnectionCreated { from, to }),
+        );
+        actions.extend(
+            result
+                .group_ids
+                .iter()
+                .map(|&group_id| UndoAction::GroupCreated { group_id }),
+        );
+        actions.extend(self.make_room_for(&result.node_ids));
+
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+
+        self.node_counter += result.node_ids.len() as u32;
+        self.interaction.selected_node = None;
+        self.interaction.selected_group = None;
+        self.interaction.selected_connection = None;
+        self.interaction.selected_nodes = result.node_ids.clone();
+        result
+    }
+}
+
+/// Offset that places `incoming` just to the right of `current`'s nodes.
+fn default_merge_offset(current: &Flowchart, incoming: &Flowchart) -> (f32, f32) {
+    let bounds = |fc: &Flowchart| {
+        fc.nodes.values().fold(None, |acc: Option<(f32, f32, f32)>, n| {
+            let (x, y) = n.position;
+            Some(match acc {
+                Some((min_x, max_x, min_y)) => (min_x.min(x), max_x.max(x), min_y.min(y)),
+                None => (x, x, y),
+            })
+        })
+    };
+    match (bounds(current), bounds(incoming)) {
+        (Some((_, cur_max_x, cur_min_y)), Some((inc_min_x, _, inc_min_y))) => (
+            cur_max_x - inc_min_x + NODE_WIDTH * 2.0,
+            cur_min_y - inc_min_y,
+        ),
+        _ => (0.0, 0.0),
+    }
+}
@@ -6,19 +6,119 @@
 //! # Module Organization
 //!
 //! - `highlighters` - Syntax highlighting for JavaScript and JSON
+//! - `animation` - Time-based animation helpers and reduced motion
 //! - `state` - Application state structures and the main FlowchartApp
+//! - `recovery` - Crash boundary, the recovery dialog for the next launch and safe mode
+//! - `file_watch` - Detects changes to the open file made by other programs
+//! - `menu_bar` - File, Edit, View, Simulation and Help menus
+//! - `dialogs` - Queue of modal dialogs (unsaved changes, confirmations, errors) shown one at a time
+//! - `confirm` - Confirmation of destructive actions, with "don't ask again"
 //! - `file_ops` - File save/load operations for native and WASM
+//! - `load_progress` - Background loading of large files with a progress dialog
+//! - `host` - Document and simulation control and state snapshots for embedding hosts
+//! - `browser_store` - IndexedDB document store for the web build
+//! - `documents` - Open/save dialog for documents kept in the browser
+//! - `file_handles` - File System Access API handles for saving back to opened files (web build)
 //! - `canvas` - Canvas navigation, zooming, panning, and interaction
 //! - `rendering` - Drawing nodes, connections, grid, and UI elements
-
+//! - `autoconnect` - Auto-connect suggestions for newly created nodes
+//! - `clipboard` - Copy nodes, connections and messages as JSON, and the canvas as an image
+//! - `tidy` - Preview and apply graph clean-ups
+//! - `autogroup` - Propose groups from densely connected nodes
+//! - `merge` - Import another flowchart file into the current one
+//! - `interop` - BPMN import and export, draw.io and node table export
+//! - `trace` - Highlight the path a traced message took through the flowchart
+//! - `traffic` - Review and add producers proposed from a recorded traffic trace
+//! - `csv_import` - Review and add nodes listed in a CSV table
+//! - `inspector` - Message filters and per-node message history
+//! - `events` - Events panel listing what happened during the simulation
+//! - `errors` - List of the script failures of a run and the error badges on nodes
+//! - `status_bar` - Mode, selection, cursor position, frame rate and gesture hints
+//! - `recording` - Record simulation runs as animated GIFs or PNG frame archives
+//! - `run_stats` - Run statistics dashboard with warm-up and measurement window
+//! - `last_run` - Read-only final state of the last stopped run
+//! - `edit_history` - The document's edit log and the Edit History window
+//! - `determinism` - Verify Determinism: two runs of the document compared step by step
+//! - `script_help` - Script API panel documenting what transformer scripts can use
+//! - `selections` - Named selections saved in the document and selected again from the toolbar
+//! - `snapshot` - Headless SVG and image snapshots for golden tests
+//! - `sources` - Transformer scripts and producer templates read from files next to the document
+//! - `sweep` - Parameter sweeps with a heatmap of the chosen metric
+//! - `json_tree` - Collapsible JSON viewer for message payloads
+//! - `formatting` - Conditional formatting rules that recolor nodes from their live values
+//! - `generator` - Build producer templates from a test-data spec
+//! - `pipeline` - Wizard creating a connected producer → transformer → consumer chain
+//! - `replicate` - Replicate window making numbered, wired-up copies of the selection
+//! - `preferences` - Preferences dialog (syntax highlighting colors)
+//! - `plugins` - Plugin manager and nodes created from plugins
+//! - `parameters` - Document parameters and the node properties bound to them
+//! - `swimlanes` - Lane bands, lane assignment and the lane-aware layout
+//! - `layout_motion` - Animated auto-layouts, the layout preview and making room for new nodes
+//! - `z_order` - Bring to front and send to back for overlapping nodes and groups
+//! - `view_history` - Optional history of pans and zooms, stepped through apart from undo
+//! - `desktop` - Run progress in the window title and the macOS application menu (desktop only)
+//! - `notifications` - Notifications in the corner of the window, such as a new web app version
+
+mod animation;
+mod autoconnect;
+mod autogroup;
 mod canvas;
+#[cfg(target_arch = "wasm32")]
+mod browser_store;
+mod clipboard;
+mod confirm;
+mod csv_import;
+#[cfg(not(target_arch = "wasm32"))]
+mod desktop;
+#[cfg(not(target_arch = "wasm32"))]
+mod determinism;
+mod dialogs;
+mod documents;
+mod edit_history;
 mod editor;
+mod errors;
+mod events;
+#[cfg(target_arch = "wasm32")]
+mod file_handles;
 mod file_ops;
+mod file_watch;
+mod formatting;
+mod generator;
 mod highlighters;
+mod host;
+mod inspector;
+mod interop;
+mod json_tree;
+mod last_run;
+mod layout_motion;
+mod load_progress;
+mod menu_bar;
+mod merge;
+mod notifications;
+mod parameters;
+mod pipeline;
+mod plugins;
+mod preferences;
 mod rendering;
 mod export;
+mod recording;
+mod recovery;
+mod replicate;
+mod run_stats;
+mod script_help;
+mod selections;
 mod state;
+mod snapshot;
+mod sources;
+mod status_bar;
+mod sweep;
+mod swimlanes;
+mod tidy;
+mod trace;
+mod traffic;
 mod undo;
+mod view_history;
+mod z_order;
 
 #[cfg(target_arch = "wasm32")]
 use web_sys;
@@ -48,12 +148,18 @@ fn is_macos_platform() -> bool {
     }
 }
 
-pub use state::FlowchartApp;
+#[cfg(not(target_arch = "wasm32"))]
+pub use snapshot::snapshot_image;
+pub use snapshot::{snapshot_svg, Snapshot};
+pub use state::{
+    AppState, ChangeKind, ChangeSummary, ConnectionStyle, DocumentState, ExportOptions, ExportScope, FlowchartApp,
+    SimulationSnapshot, StepListener, TextWrappingMode, UpdateListener, ViewState,
+};
 pub use undo::{UndoAction, UndoHistory, UndoableFlowchart};
 
 use self::editor::{handle_code_textedit_keys, simple_js_format, CodeEditOptions, LanguageKind};
-use self::state::PendingConfirmAction;
-use crate::examples::all_examples;
+use self::state::{PendingConfirmAction, PendingConfirmation};
+use crate::inspect::MessageFilter;
 use crate::types::*;
 use eframe::egui;
 #[cfg(target_arch = "wasm32")]
@@ -61,8 +167,18 @@ use eframe::wasm_bindgen::JsCast;
 
 impl eframe::App for FlowchartApp {
     /// Persist entire app state between restarts.
+    ///
+    /// On the web the document itself lives in the browser's document store,
+    /// so web storage only keeps the settings.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        match self.to_json() {
+        #[cfg(target_arch = "wasm32")]
+        let flowchart = std::mem::take(&mut self.flowchart);
+        let result = self.to_json();
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.flowchart = flowchart;
+        }
+        match result {
             Ok(json) => {
                 storage.set_string("app_state", json);
             }
@@ -74,6 +190,22 @@ impl eframe::App for FlowchartApp {
 
     /// Main update function called by egui for each frame.
     ///
+    /// Draws the frame inside the crash boundary, so a panic saves the
+    /// document for recovery before the app goes down (see `recovery`).
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.with_crash_boundary(|app| app.draw_frame(ctx, frame));
+    }
+
+    /// Lets the web build's host handle reach the app through the runner.
+    #[cfg(target_arch = "wasm32")]
+    fn as_any_mut(&mut self) -> Option<&mut dyn std::any::Any> {
+        Some(&mut *self)
+    }
+}
+
+impl FlowchartApp {
+    /// Draws one frame.
+    ///
     /// This method handles the overall UI layout, including the properties panel,
     /// toolbar, and main canvas area. It also processes simulation steps when running.
     ///
@@ -81,7 +213,7 @@ impl eframe::App for FlowchartApp {
     ///
     /// * `ctx` - The egui context
     /// * `frame` - The eframe frame
-    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+    fn draw_frame(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         // Apply theme visuals
         let visuals = if self.dark_mode {
             egui::Visuals::dark()
@@ -90,6 +222,21 @@ impl eframe::App for FlowchartApp {
         };
         ctx.set_visuals(visuals);
 
+        // Ctrl/Cmd+= / - / 0 zoom the canvas rather than scaling the whole UI
+        ctx.options_mut(|o| o.zoom_with_keyboard = false);
+
+        // Timestamp used to coalesce rapid edits into single undo entries
+        self.frame_time = ctx.input(|i| i.time);
+
+        // Edits of the last frame go into the document's edit log
+        self.log_edits();
+
+        // Glide nodes towards the positions of a just-applied auto-layout
+        self.advance_layout_animation(ctx);
+
+        // Capture the last frame of a run recording and step for the next
+        self.advance_recording(ctx);
+
         // Handle pending file operations
         self.handle_pending_operations(ctx);
 
@@ -105,6 +252,18 @@ impl eframe::App for FlowchartApp {
         // Handle group-related shortcuts (create/add to group)
         self.handle_group_shortcuts(ctx);
 
+        // Bring the selection to the front or send it to the back
+        self.handle_z_order_shortcuts(ctx);
+
+        // Step back and forward through earlier views
+        self.handle_view_history_keys(ctx);
+
+        // Step the simulation from the keyboard
+        self.handle_step_keys(ctx);
+
+        // Open the preferences with Cmd/Ctrl+,
+        self.handle_preferences_shortcut(ctx);
+
         // Intercept native window close requests (titlebar X)
         #[cfg(not(target_arch = "wasm32"))]
         {
@@ -112,10 +271,7 @@ impl eframe::App for FlowchartApp {
                 if self.file.has_unsaved_changes_effective() && !self.file.allow_close_on_next_request {
                     // Abort close and show confirmation dialog
                     ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
-                    if !self.file.show_unsaved_dialog {
-                        self.file.show_unsaved_dialog = true;
-                        self.file.pending_confirm_action = Some(PendingConfirmAction::Quit);
-                    }
+                    self.dialogs.open(state::Modal::UnsavedChanges(PendingConfirmAction::Quit));
                 } else {
                     // Either no unsaved changes or user confirmed close; allow it and reset the one-shot flag
                     self.file.allow_close_on_next_request = false;
@@ -145,115 +301,13 @@ impl eframe::App for FlowchartApp {
             self.window_inner_size = Some((size.x, size.y));
         }
 
-        // Top menu bar
-        egui::TopBottomPanel::top("top_menu_bar").show(ctx, |ui| {
-            egui::MenuBar::new().ui(ui, |ui| {
-                // File menu
-                ui.menu_button("File", |ui| {
-                    if ui.button("New").clicked() {
-                        if self.file.has_unsaved_changes_effective() {
-                            self.file.show_unsaved_dialog = true;
-                            self.file.pending_confirm_action = Some(PendingConfirmAction::New);
-                        } else {
-                            self.new_flowchart();
-                        }
-                        ui.close();
-                    }
-                    if ui.button("Load…").clicked() {
-                        if self.file.has_unsaved_changes_effective() {
-                            self.file.show_unsaved_dialog = true;
-                            self.file.pending_confirm_action = Some(PendingConfirmAction::Open);
-                        } else {
-                            self.load_flowchart();
-                        }
-                        ui.close();
-                    }
-                    ui.add_enabled_ui(!self.file.is_example_readonly, |ui| {
-                        if ui.button("Save").clicked() {
-                            self.save_flowchart();
-                            ui.close();
-                        }
-                    });
-                    ui.add_enabled_ui(!self.file.is_example_readonly, |ui| {
-                        if ui.button("Save As…").clicked() {
-                            self.save_as_flowchart();
-                            ui.close();
-                        }
-                    });
-                    ui.separator();
-                    ui.menu_button("Examples", |ui| {
-                        for ex in all_examples() {
-                            if ui.button(ex.name).clicked() {
-                                self.request_load_example(ex.kind);
-                                ui.close();
-                            }
-                        }
-                    });
-                    ui.separator();
-                    ui.menu_button("Export", |ui| {
-                        if ui.button("SVG…").clicked() {
-                            // Open export dialog configured for SVG export
-                            self.pending_export_format = Some(crate::ui::state::ExportFormat::Svg);
-                            // Initialize defaults based on current app state
-                            self.export_options.include_grid = self.canvas.show_grid;
-                            self.export_options.background_color = if self.dark_mode {
-                                egui::Color32::from_gray(20)
-                            } else {
-                                egui::Color32::WHITE
-                            };
-                            // Default to whole graph; user can opt-in to selection only in dialog
-                            self.export_options.scope = crate::ui::state::ExportScope::WholeGraph;
-                            // Use the app's default connection color
-                            self.export_options.stroke_color = egui::Color32::DARK_GRAY;
-                            self.show_export_dialog = true;
-                            ui.close();
-                        }
-                        // PNG export is available on all targets
-                        if ui.button("PNG…").clicked() {
-                            self.pending_export_format = Some(crate::ui::state::ExportFormat::Png);
-                            self.export_options.include_grid = self.canvas.show_grid;
-                            self.export_options.background_color = if self.dark_mode {
-                                egui::Color32::from_gray(20)
-                            } else {
-                                egui::Color32::WHITE
-                            };
-                            self.export_options.scope = crate::ui::state::ExportScope::WholeGraph;
-                            self.export_options.stroke_color = egui::Color32::DARK_GRAY;
-                            self.show_export_dialog = true;
-                            ui.close();
-                        }
-                    });
-                });
-
-                // Edit menu
-                ui.menu_button("Edit", |ui| {
-                    let can_undo = self.undo_history.can_undo();
-                    let can_redo = self.undo_history.can_redo();
-                    ui.add_enabled_ui(can_undo, |ui| {
-                        if ui.button("⟲ Undo").clicked() {
-                            self.perform_undo();
-                            ui.close();
-                        }
-                    });
-                    ui.add_enabled_ui(can_redo, |ui| {
-                        if ui.button("⟳ Redo").clicked() {
-                            self.perform_redo();
-                            ui.close();
-                        }
-                    });
-                });
+        // Menu bar
+        self.draw_menu_bar(ctx);
 
-                // View menu
-                ui.menu_button("View", |ui| {
-                    if ui.checkbox(&mut self.canvas.show_grid, "Show Grid").changed() {
-                        ui.close();
-                    }
-                    if ui.checkbox(&mut self.dark_mode, "Dark Mode").changed() {
-                        ui.close();
-                    }
-                });
-            });
-        });
+        // Explains why settings and plugins are missing after a safe-mode start
+        if self.safe_mode {
+            self.draw_safe_mode_banner(ctx);
+        }
 
         // Toolbar (simulation/layout) occupies full width below the menubar
         egui::TopBottomPanel::top("top_toolbar").show(ctx, |ui| {
@@ -265,6 +319,146 @@ impl eframe::App for FlowchartApp {
             self.draw_export_dialog(ctx);
         }
 
+        // Flowchart statistics overlay
+        if self.show_stats_dialog {
+            self.draw_stats_dialog(ctx);
+        }
+
+        // Tidy preview overlay
+        if self.show_tidy_dialog {
+            self.draw_tidy_dialog(ctx);
+        }
+
+        // Document parameters
+        if self.show_parameters_dialog {
+            self.draw_parameters_dialog(ctx);
+        }
+
+        // Swimlane editor overlay
+        if self.show_swimlanes_dialog {
+            self.draw_swimlanes_dialog(ctx);
+        }
+
+        // Conditional formatting rules overlay
+        if self.show_format_rules_dialog {
+            self.draw_format_rules_dialog(ctx);
+        }
+
+        // Auto-group proposals overlay
+        if self.show_auto_group_dialog {
+            self.draw_auto_group_dialog(ctx);
+        }
+
+        // Import & merge confirmation overlay
+        if self.pending_merge.is_some() {
+            self.draw_merge_dialog(ctx);
+        }
+
+        // Help windows
+        if self.show_shortcuts_dialog {
+            self.draw_shortcuts_dialog(ctx);
+        }
+        if self.show_about_dialog {
+            self.draw_about_dialog(ctx);
+        }
+
+        // Preferences overlay
+        if self.show_preferences_dialog {
+            self.draw_preferences_dialog(ctx);
+        }
+
+        // Run statistics dashboard
+        if self.show_run_stats_dialog {
+            self.draw_run_stats_dialog(ctx);
+        }
+
+        // Final state of the last stopped run
+        if self.show_last_run_dialog {
+            self.draw_last_run_dialog(ctx);
+        }
+
+        // Audit trail of document edits
+        if self.show_edit_history_dialog {
+            self.draw_edit_history_dialog(ctx);
+        }
+
+        // Script environment reference
+        if self.show_script_api_panel {
+            self.draw_script_api_panel(ctx);
+        }
+
+        // Script failures of the run
+        if self.show_errors_dialog {
+            self.draw_errors_dialog(ctx);
+        }
+
+        // Parameter sweep
+        if self.show_sweep_dialog {
+            self.draw_sweep_dialog(ctx);
+        }
+
+        // Outcome of Verify Determinism
+        #[cfg(not(target_arch = "wasm32"))]
+        self.draw_determinism_dialog(ctx);
+
+        // Run recording
+        if self.show_recording_dialog {
+            self.draw_recording_dialog(ctx);
+        }
+
+        // Plugin manager overlay
+        if self.show_plugins_dialog {
+            self.draw_plugins_dialog(ctx);
+        }
+
+        // Progress of a large file being opened
+        if self.file.load_progress.is_some() {
+            self.draw_load_progress_dialog(ctx);
+        }
+
+        // Browser documents (web build)
+        if self.browser_documents.is_some() {
+            self.draw_browser_documents_dialog(ctx);
+        }
+
+        // Producer test-data generator
+        if self.data_generator.is_some() {
+            self.draw_data_generator_dialog(ctx);
+        }
+
+        // "Add Pipeline" wizard
+        if self.pipeline_wizard.is_some() {
+            self.draw_pipeline_wizard(ctx);
+        }
+
+        // Replicate window
+        if self.replicate_dialog.is_some() {
+            self.draw_replicate_dialog(ctx);
+        }
+
+        // Producers proposed from a traffic trace
+        if self.traffic_import.is_some() {
+            self.draw_traffic_import_dialog(ctx);
+        }
+
+        // Nodes read from a CSV table
+        if self.node_csv_import.is_some() {
+            self.draw_node_csv_import_dialog(ctx);
+        }
+
+        // Apply/Cancel for a previewed auto-layout
+        if self.layout_preview.is_some() {
+            self.draw_layout_preview_bar(ctx);
+        }
+
+        // Message trace view
+        if self.traced_message.is_some() {
+            self.draw_trace_window(ctx);
+        }
+
+        // Status bar spans the full width along the bottom
+        self.draw_status_bar(ctx);
+
         // Properties panel should only take space from the canvas area below the toolbar
         let viewport_width = ctx.input(|i| i.content_rect().width());
         // Use remembered width when available, but clamp to viewport
@@ -285,102 +479,114 @@ impl eframe::App for FlowchartApp {
                 self.draw_properties_panel(ui);
             });
 
+        // Events log under the canvas
+        if self.show_events_panel {
+            self.draw_events_panel(ctx);
+        }
+
         // Central canvas area (below the toolbar)
         egui::CentralPanel::default().show(ctx, |ui| {
             // Canvas takes remaining space
             self.draw_canvas(ui);
         });
+        self.track_view_changes();
 
-        // Unsaved changes confirmation dialog
-        if self.file.show_unsaved_dialog {
-            let title = match self.file.pending_confirm_action {
-                Some(PendingConfirmAction::Quit) => "Unsaved changes — Quit?",
-                Some(PendingConfirmAction::New) => "Unsaved changes — Create New?",
-                Some(PendingConfirmAction::Open) => "Unsaved changes — Load File?",
-                Some(PendingConfirmAction::LoadExample) => "Unsaved changes — Load Example?",
-                None => "Unsaved changes",
-            };
-            egui::Window::new(title)
-                .collapsible(false)
-                .resizable(false)
-                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
-                .show(ctx, |ui| {
-                    ui.label("You have unsaved changes. Are you sure you want to continue?");
-                    ui.horizontal(|ui| {
-                        // Primary confirm button depends on action
-                        let confirm_label = match self.file.pending_confirm_action {
-                            Some(PendingConfirmAction::Quit) => "Discard and Quit",
-                            Some(PendingConfirmAction::New) => "Discard and Create New",
-                            Some(PendingConfirmAction::Open) => "Discard and Load",
-                            Some(PendingConfirmAction::LoadExample) => "Discard and Load Example",
-                            None => "Discard",
-                        };
-                        if ui.button(confirm_label).clicked() {
-                            match self.file.pending_confirm_action {
-                                Some(PendingConfirmAction::New) => {
-                                    self.new_flowchart();
-                                }
-                                Some(PendingConfirmAction::Open) => {
-                                    self.load_flowchart();
-                                }
-                                Some(PendingConfirmAction::LoadExample) => {
-                                    if let Some(kind) = self.file.pending_example.take() {
-                                        self.load_example(kind);
-                                    }
-                                }
-                                Some(PendingConfirmAction::Quit) => {
-                                    // Allow one close request to pass without interception
-                                    self.file.allow_close_on_next_request = true;
-                                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                                }
-                                None => {}
-                            }
-                            self.file.show_unsaved_dialog = false;
-                            self.file.pending_confirm_action = None;
-                        }
-                        if ui.button("Cancel").clicked() {
-                            self.file.show_unsaved_dialog = false;
-                            self.file.pending_confirm_action = None;
-                        }
-                    });
-                });
+        // Auto-connect offer for a freshly created node
+        self.draw_auto_connect_popup(ctx);
+
+        // Recovery offer after a crash
+        if self.pending_recovery.is_some() {
+            self.draw_recovery_dialog(ctx);
         }
 
-        // Process simulation if running
-        if self.is_simulation_running {
-            let delivered_messages = self.simulation_engine.step(&mut self.flowchart);
+        // Reload prompt for a file changed by another program
+        if self.file.external_change.is_some() {
+            self.draw_external_change_dialog(ctx);
+        }
 
-            // Handle delivered messages
-            for (node_id, message) in delivered_messages {
-                match self
-                    .simulation_engine
-                    .deliver_message(node_id, message, &mut self.flowchart)
-                {
-                    Ok(_) => {}
-                    Err(error_msg) => {
-                        // Stop simulation on error
-                        self.is_simulation_running = false;
-                        self.flowchart.simulation_state = SimulationState::Stopped;
-                        self.error_node = Some(node_id);
-                        eprintln!(
-                            "Simulation stopped due to error in node {}: {}",
-                            node_id, error_msg
-                        );
-                    }
-                }
-            }
+        // Unsaved changes, confirmations and errors, one at a time
+        self.draw_dialogs(ctx);
+        self.draw_notifications(ctx);
 
-            self.frame_counter += 1;
-            ctx.request_repaint(); // Keep animating
-        } else if self.error_node.is_some() {
-            // Keep repainting to show flashing error border
-            self.frame_counter += 1;
-            ctx.request_repaint();
+        // Process simulation if running
+        if self.is_simulation_running {
+            self.advance_running_simulation();
+            ctx.request_repaint(); // Keep simulating
+        } else if self.error_node.is_some() || self.traced_message.is_some() {
+            // Keep the error border flashing and the trace highlight pulsing
+            self.request_animation_frame(ctx);
         }
+
+        // Run progress in the title shown by the taskbar and the Dock
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_window_progress(ctx);
     }
 }
 
 impl FlowchartApp {
+    /// Draws the toolbar's Run To button and its target step.
+    fn draw_run_to_step(&mut self, ui: &mut egui::Ui) {
+        let target = self.run_to_step_input;
+        let enabled = target > self.flowchart.current_step && self.run_to_step.is_none();
+        if ui
+            .add_enabled(enabled, egui::Button::new("Run To"))
+            .on_hover_text("Run until the step is reached, then pause")
+            .clicked()
+        {
+            self.run_to_step(target);
+        }
+        ui.add(egui::DragValue::new(&mut self.run_to_step_input).range(1..=u64::MAX).prefix("step "));
+    }
+
+    /// Draws the "About this flowchart" statistics dialog. Opens when `show_stats_dialog` is true.
+    fn draw_stats_dialog(&mut self, ctx: &egui::Context) {
+        let stats = crate::analysis::analyze(&self.flowchart);
+        let mut keep_open = true;
+        egui::Window::new("About this flowchart")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("flowchart_stats_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let mut row = |label: &str, value: String| {
+                            ui.label(label);
+                            ui.label(value);
+                            ui.end_row();
+                        };
+                        row("Nodes", stats.total_nodes().to_string());
+                        row("  Producers", stats.producers.to_string());
+                        row("  Transformers", stats.transformers.to_string());
+                        row("  References", stats.references.to_string());
+                        row("  Consumers", stats.consumers.to_string());
+                        row("Connections", stats.connections.to_string());
+                        row("Groups", stats.groups.to_string());
+                        row("Components", stats.components.to_string());
+                        row(
+                            "Longest path",
+                            if stats.has_cycles {
+                                format!("{} (cycles collapsed)", stats.longest_path)
+                            } else {
+                                stats.longest_path.to_string()
+                            },
+                        );
+                        row("Cyclomatic complexity", stats.cyclomatic_complexity.to_string());
+                        row(
+                            "Script size",
+                            format!(
+                                "{} bytes, {} lines",
+                                stats.total_script_bytes, stats.total_script_lines
+                            ),
+                        );
+                    });
+            });
+        if !keep_open {
+            self.show_stats_dialog = false;
+        }
+    }
+
     /// Draws the export options dialog. Opens when `show_export_dialog` is true.
     fn draw_export_dialog(&mut self, ctx: &egui::Context) {
         let mut keep_open = true;
@@ -517,7 +723,7 @@ impl FlowchartApp {
                         None => {
                             // shouldn't happen; just show a disabled button
                             ui.add_enabled_ui(false, |ui| {
-                                ui.button("Export");
+                                let _ = ui.button("Export");
                             });
                         }
                     }
@@ -551,10 +757,14 @@ impl FlowchartApp {
                 _ => false,
             })
         });
-        if !pressed {
-            return;
+        if pressed {
+            self.group_selection();
         }
+    }
 
+    /// Puts the selected node(s) into the selected group, or into a new group
+    /// whose name is then edited.
+    pub(crate) fn group_selection(&mut self) {
         // Determine nodes to group: use multi-selection if any; otherwise use single selected node
         let mut nodes_to_group: Vec<NodeId> = if !self.interaction.selected_nodes.is_empty() {
             self.interaction.selected_nodes.clone()
@@ -577,7 +787,7 @@ impl FlowchartApp {
                         group.members.push(id);
                     }
                 }
-                self.file.has_unsaved_changes = true;
+                self.file.mark_changed(ChangeKind::GroupChanged(gid));
             }
         } else {
             let gid = uuid::Uuid::new_v4();
@@ -590,6 +800,7 @@ impl FlowchartApp {
                     name,
                     members: nodes_to_group,
                     drawing: crate::types::GroupDrawingMode::Rectangle,
+                    z_order: 0,
                 },
             );
             // Record undo action for group creation
@@ -608,7 +819,7 @@ impl FlowchartApp {
                 self.interaction.should_select_text = true;
                 self.interaction.focus_requested_for_edit = false;
             }
-            self.file.has_unsaved_changes = true;
+            self.file.mark_changed(ChangeKind::GroupChanged(gid));
         }
     }
 
@@ -712,22 +923,19 @@ impl FlowchartApp {
 
     /// Returns a group id if the world position is inside any group's background shape.
     fn find_group_at_position(&self, world_pos: egui::Pos2) -> Option<crate::types::GroupId> {
-        // If multiple hit, prefer the smallest area (innermost group)
-        let mut best: Option<(crate::types::GroupId, f32)> = None;
+        // If multiple hit, prefer the one on top, then the smallest area (innermost group)
+        let mut best: Option<(crate::types::GroupId, i32, f32)> = None;
+        let mut consider = |gid: crate::types::GroupId, z: i32, area: f32| {
+            if best.is_none_or(|(_, best_z, best_area)| z > best_z || (z == best_z && area < best_area)) {
+                best = Some((gid, z, area));
+            }
+        };
         for (gid, g) in &self.flowchart.groups {
             match g.drawing {
                 crate::types::GroupDrawingMode::Rectangle => {
                     if let Some(r) = self.group_world_rect(*gid) {
                         if r.contains(world_pos) {
-                            let area = r.area();
-                            match best {
-                                None => best = Some((*gid, area)),
-                                Some((_, best_area)) => {
-                                    if area < best_area {
-                                        best = Some((*gid, area));
-                                    }
-                                }
-                            }
+                            consider(*gid, g.z_order, r.area());
                         }
                     }
                 }
@@ -752,22 +960,14 @@ impl FlowchartApp {
                                 j = i;
                             }
                             if inside {
-                                let area = bbox.area();
-                                match best {
-                                    None => best = Some((*gid, area)),
-                                    Some((_, best_area)) => {
-                                        if area < best_area {
-                                            best = Some((*gid, area));
-                                        }
-                                    }
-                                }
+                                consider(*gid, g.z_order, bbox.area());
                             }
                         }
                     }
                 }
             }
         }
-        best.map(|(gid, _)| gid)
+        best.map(|(gid, _, _)| gid)
     }
     #[cfg(target_arch = "wasm32")]
     fn update_beforeunload(has_unsaved_changes: bool) {
@@ -817,28 +1017,17 @@ impl FlowchartApp {
             }
             // Open: Cmd/Ctrl+O
             if i.key_pressed(egui::Key::O) && cmd {
-                if self.file.has_unsaved_changes_effective() {
-                    self.file.show_unsaved_dialog = true;
-                    self.file.pending_confirm_action = Some(PendingConfirmAction::Open);
-                } else {
-                    self.load_flowchart();
-                }
+                self.unless_unsaved(PendingConfirmAction::Open, |app| app.load_flowchart());
             }
             // New: Cmd/Ctrl+N
             if i.key_pressed(egui::Key::N) && cmd {
-                if self.file.has_unsaved_changes_effective() {
-                    self.file.show_unsaved_dialog = true;
-                    self.file.pending_confirm_action = Some(PendingConfirmAction::New);
-                } else {
-                    self.new_flowchart();
-                }
+                self.unless_unsaved(PendingConfirmAction::New, |app| app.new_flowchart());
             }
             // Quit: Cmd/Ctrl+Q (native only)
             #[cfg(not(target_arch = "wasm32"))]
             if i.key_pressed(egui::Key::Q) && cmd {
                 if self.file.has_unsaved_changes_effective() {
-                    self.file.show_unsaved_dialog = true;
-                    self.file.pending_confirm_action = Some(PendingConfirmAction::Quit);
+                    self.dialogs.open(state::Modal::UnsavedChanges(PendingConfirmAction::Quit));
                 } else {
                     request_quit = true;
                 }
@@ -849,6 +1038,24 @@ impl FlowchartApp {
         }
     }
 
+    /// Handles the step key: it steps the simulation once, or
+    /// `multi_step_count` times with Shift.
+    fn handle_step_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let steps = ctx.input(|i| {
+            if i.modifiers.command || i.modifiers.alt || !i.key_pressed(self.step_key) {
+                0
+            } else if i.modifiers.shift {
+                self.multi_step_count
+            } else {
+                1
+            }
+        });
+        self.step_simulation_by(steps);
+    }
+
     /// Handles undo/redo keyboard shortcuts.
     ///
     /// # Arguments
@@ -884,7 +1091,10 @@ impl FlowchartApp {
         // Check if any text edit widget wants keyboard focus - if so, don't handle delete
         let is_editing_text = ctx.wants_keyboard_input();
 
-        if ctx.input(|i| i.key_pressed(egui::Key::Delete)) && !is_editing_text {
+        if ctx.input(|i| i.key_pressed(egui::Key::Delete))
+            && !is_editing_text
+            && !self.dialogs.is_open()
+        {
             // If a group is selected, delete the group (but keep its nodes and connections)
             if let Some(gid) = self.interaction.selected_group {
                 // If we are currently editing this group's name, ignore Delete to avoid accidental removal
@@ -906,52 +1116,15 @@ impl FlowchartApp {
                         self.interaction.temp_group_name.clear();
                     }
 
-                    self.file.has_unsaved_changes = true;
+                    self.file.mark_changed(ChangeKind::GroupChanged(gid));
                     return; // handled delete key
                 }
             }
 
             // If multiple nodes are selected, delete them together
             if self.interaction.selected_nodes.len() > 1 {
-                // Capture nodes and connections for undo
-                let mut nodes: Vec<FlowchartNode> = Vec::new();
-                let mut connections: Vec<Connection> = Vec::new();
-
-                let selected_set: std::collections::HashSet<NodeId> =
-                    self.interaction.selected_nodes.iter().copied().collect();
-
-                // Collect node data
-                for id in &self.interaction.selected_nodes {
-                    if let Some(node) = self.flowchart.nodes.get(id).cloned() {
-                        nodes.push(node);
-                    }
-                }
-                // Collect all connections involving any selected node
-                for c in &self.flowchart.connections {
-                    if selected_set.contains(&c.from) || selected_set.contains(&c.to) {
-                        connections.push(c.clone());
-                    }
-                }
-
-                // Record combined undo action
-                self.undo_history
-                    .push_action(UndoAction::MultipleNodesDeleted {
-                        nodes: nodes.clone(),
-                        connections: connections.clone(),
-                    });
-
-                // Perform deletion
-                for id in &self.interaction.selected_nodes {
-                    let _ = self.flowchart.remove_node(id);
-                }
-
-                // Clear selection
-                self.interaction.selected_nodes.clear();
-                self.interaction.selected_node = None;
-                self.interaction.selected_connection = None;
-                self.interaction.selected_group = None;
-                self.interaction.editing_node_name = None;
-                self.file.has_unsaved_changes = true;
+                let nodes = self.interaction.selected_nodes.clone();
+                self.confirm_then(PendingConfirmation::DeleteNodes(nodes));
             } else if let Some(selected_node) = self.interaction.selected_node {
                 // Store node and its connections for undo
                 if let Some(node) = self.flowchart.nodes.get(&selected_node).cloned() {
@@ -964,8 +1137,9 @@ impl FlowchartApp {
                         .collect();
 
                     // Record undo action before deletion
-                    self.undo_history
-                        .push_action(UndoAction::NodeDeleted { node, connections });
+                    let action = UndoAction::NodeDeleted { node, connections };
+                    self.file.mark_action(&action);
+                    self.undo_history.push_action(action);
                 }
 
                 // Remove the selected node (also updates groups and connections)
@@ -976,28 +1150,60 @@ impl FlowchartApp {
                 self.interaction.selected_nodes.clear();
                 self.interaction.selected_group = None;
                 self.interaction.editing_node_name = None;
-                self.file.has_unsaved_changes = true;
             } else if let Some(conn_idx) = self.interaction.selected_connection {
                 // Remove the selected connection
                 if conn_idx < self.flowchart.connections.len() {
                     let connection = self.flowchart.connections[conn_idx].clone();
 
                     // Record undo action before deletion
-                    self.undo_history
-                        .push_action(UndoAction::ConnectionDeleted {
-                            connection,
-                            index: conn_idx,
-                        });
+                    let action = UndoAction::ConnectionDeleted {
+                        connection,
+                        index: conn_idx,
+                    };
+                    self.file.mark_action(&action);
+                    self.undo_history.push_action(action);
 
                     self.flowchart.connections.remove(conn_idx);
                     self.interaction.selected_connection = None;
-                    self.file.has_unsaved_changes = true;
                 }
             }
         }
     }
 
-    /// Renders the toolbar with simulation controls and layout tools.
+    /// Deletes `ids` and their connections as one undoable action, and clears
+    /// the selection.
+    pub(crate) fn delete_nodes(&mut self, ids: &[NodeId]) {
+        // Capture nodes and connections for undo
+        let selected_set: std::collections::HashSet<NodeId> = ids.iter().copied().collect();
+        let nodes: Vec<FlowchartNode> = ids.iter().filter_map(|id| self.flowchart.nodes.get(id).cloned()).collect();
+        let connections: Vec<Connection> = self
+            .flowchart
+            .connections
+            .iter()
+            .filter(|c| selected_set.contains(&c.from) || selected_set.contains(&c.to))
+            .cloned()
+            .collect();
+
+        // Record combined undo action
+        let action = UndoAction::MultipleNodesDeleted { nodes, connections };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+
+        // Perform deletion
+        for id in ids {
+            let _ = self.flowchart.remove_node(id);
+        }
+
+        // Clear selection
+        self.interaction.selected_nodes.clear();
+        self.interaction.selected_node = None;
+        self.interaction.selected_connection = None;
+        self.interaction.selected_group = None;
+        self.interaction.editing_node_name = None;
+    }
+
+    /// Renders the quick-access toolbar with simulation controls and layout
+    /// tools; every command is also in the menu bar.
     ///
     /// # Arguments
     ///
@@ -1008,65 +1214,47 @@ impl FlowchartApp {
             // Simulation controls
             if self.is_simulation_running {
                 if ui.button("Pause").clicked() {
-                    self.is_simulation_running = false;
-                    self.flowchart.simulation_state = SimulationState::Paused;
+                    self.pause_simulation();
                 }
             } else if ui.button("Start").clicked() {
-                self.is_simulation_running = true;
-                self.flowchart.simulation_state = SimulationState::Running;
+                self.start_simulation();
             }
             if ui.button("Stop").clicked() {
-                self.is_simulation_running = false;
-                self.flowchart.simulation_state = SimulationState::Stopped;
-                self.flowchart.current_step = 0;
-                self.error_node = None;
-                // Clear all messages from connections
-                for connection in &mut self.flowchart.connections {
-                    connection.messages.clear();
-                }
-                // Reset producer counters and node states
-                for node in self.flowchart.nodes.values_mut() {
-                    node.state = NodeState::Idle;
-                    match &mut node.node_type {
-                        NodeType::Producer { messages_produced, .. } => {
-                            *messages_produced = 0;
-                        }
-                        NodeType::Transformer { globals, initial_globals, .. } => {
-                            // Reset transformer globals to their initial values
-                            *globals = initial_globals.clone();
-                        }
-                        _ => {}
-                    }
-                }
+                self.request_stop_simulation();
             }
-            if ui.button("Step").clicked() {
-                let delivered_messages = self.simulation_engine.step(&mut self.flowchart);
-                for (node_id, message) in delivered_messages {
-                    match self.simulation_engine.deliver_message(
-                        node_id,
-                        message,
-                        &mut self.flowchart,
-                    ) {
-                        Ok(_) => {}
-                        Err(error_msg) => {
-                            self.error_node = Some(node_id);
-                            eprintln!("Error in node {}: {}", node_id, error_msg);
-                        }
-                    }
+            let step_hint = format!(
+                "Advance one step ({key}); Shift+{key} advances {count}",
+                key = self.step_key.name(),
+                count = self.multi_step_count
+            );
+            if ui.button("Step").on_hover_text(step_hint).clicked() {
+                for (node_id, error_msg) in self.step_simulation() {
+                    self.error_node = Some(node_id);
+                    eprintln!("Error in node {}: {}", node_id, error_msg);
                 }
             }
+            self.draw_run_to_step(ui);
+            if ui
+                .add_enabled(!self.simulation_engine.stats().is_empty(), egui::Button::new("Export CSV"))
+                .on_hover_text("Save what each step produced, consumed and left on each connection")
+                .on_disabled_hover_text("Run the simulation first")
+                .clicked()
+            {
+                self.export_run_steps_csv();
+            }
 
             ui.separator();
 
             // Auto-arrange apply button + combo box to choose mode
             if ui.button("Auto Layout").clicked() {
-                self.apply_auto_arrangement();
+                self.run_auto_arrangement();
             }
             egui::ComboBox::from_id_salt("auto_arrange_mode_combo")
                 .selected_text(match self.auto_arrange_mode {
                     crate::ui::state::AutoArrangeMode::ForceDirected => "Force-directed",
                     crate::ui::state::AutoArrangeMode::Grid => "Grid",
                     crate::ui::state::AutoArrangeMode::Line => "Line",
+                    crate::ui::state::AutoArrangeMode::Swimlanes => "Swimlanes",
                 })
                 .show_ui(ui, |ui| {
                     ui.selectable_value(
@@ -1084,12 +1272,20 @@ impl FlowchartApp {
                         crate::ui::state::AutoArrangeMode::Line,
                         "Line",
                     );
+                    ui.selectable_value(
+                        &mut self.auto_arrange_mode,
+                        crate::ui::state::AutoArrangeMode::Swimlanes,
+                        "Swimlanes",
+                    );
                 });
+            if self.auto_arrange_mode == crate::ui::state::AutoArrangeMode::ForceDirected {
+                self.draw_force_layout_tuning(ui);
+            }
+            ui.checkbox(&mut self.preview_layout, "Preview")
+                .on_hover_text("Show the proposed layout with Apply/Cancel instead of applying it");
 
             ui.separator();
-
-            // Show current simulation step
-            ui.label(format!("Step: {}", self.flowchart.current_step));
+            self.draw_saved_selections_menu(ui);
 
             // Show current file and unsaved changes indicator
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -1101,17 +1297,36 @@ impl FlowchartApp {
                     if self.file.has_unsaved_changes_effective() {
                         label.push('*');
                     }
-                    ui.label(label);
+                    let response = ui.label(label);
+                    if let Some(details) = self.file.unsaved_changes_description() {
+                        response.on_hover_text(details);
+                    }
                 } else {
                     let status = if self.file.has_unsaved_changes_effective() {
                         "Untitled*"
                     } else {
                         "Untitled"
                     };
-                    ui.label(status);
+                    let response = ui.label(status);
+                    if let Some(details) = self.file.unsaved_changes_description() {
+                        response.on_hover_text(details);
+                    }
                 }
 
                 ui.label(format!("Zoom: {:.0}%", self.canvas.zoom_factor * 100.0));
+                let (min, max) = self.zoom_limits;
+                let mut zoom = self.canvas.zoom_factor;
+                if ui
+                    .add(
+                        egui::Slider::new(&mut zoom, min..=max.max(min))
+                            .logarithmic(true)
+                            .show_value(false),
+                    )
+                    .on_hover_text("Zoom around the middle of the canvas (Ctrl/Cmd+0 resets to 100%)")
+                    .changed()
+                {
+                    self.zoom_around(zoom, self.canvas.screen_rect.center());
+                }
             });
         });
     }
@@ -1165,7 +1380,7 @@ impl FlowchartApp {
                                 if !new_name.is_empty() && new_name != old_name {
                                     g.name = new_name;
                                     // We could add a dedicated undo action in future
-                                    self.file.has_unsaved_changes = true;
+                                    self.file.mark_changed(ChangeKind::GroupChanged(gid));
                                 }
                             }
                             self.interaction.editing_group_name = None;
@@ -1179,7 +1394,7 @@ impl FlowchartApp {
                                 let new_name = self.interaction.temp_group_name.trim().to_string();
                                 if !new_name.is_empty() && new_name != old_name {
                                     g.name = new_name;
-                                    self.file.has_unsaved_changes = true;
+                                    self.file.mark_changed(ChangeKind::GroupChanged(gid));
                                 }
                             }
                             self.interaction.editing_group_name = None;
@@ -1216,7 +1431,7 @@ impl FlowchartApp {
                     if drawing != group.drawing {
                         if let Some(g) = self.flowchart.groups.get_mut(&gid) {
                             g.drawing = drawing;
-                            self.file.has_unsaved_changes = true;
+                            self.file.mark_changed(ChangeKind::GroupChanged(gid));
                         }
                     }
                     ui.separator();
@@ -1234,16 +1449,18 @@ impl FlowchartApp {
                 if let Some(node) = self.flowchart.nodes.get(&selected_id).cloned() {
                     // If the currently selected node is NOT a Transformer, clear any
                     // Transformer globals staging so edits don't linger across types.
-                    if !matches!(node.node_type, NodeType::Transformer { .. }) {
-                        if self.interaction.temp_globals_node_id.is_some()
-                            || !self.interaction.temp_transformer_globals_edits.is_empty()
-                        {
-                            self.interaction.temp_transformer_globals_edits.clear();
-                            self.interaction.temp_globals_node_id = None;
-                        }
+                    if !matches!(node.node_type, NodeType::Transformer { .. })
+                        && (self.interaction.temp_globals_node_id.is_some()
+                            || !self.interaction.temp_transformer_globals_edits.is_empty())
+                    {
+                        self.interaction.temp_transformer_globals_edits.clear();
+                        self.interaction.temp_globals_node_id = None;
                     }
 
-                    ui.label("Type: Node");
+                    ui.horizontal(|ui| {
+                        ui.label("Type: Node");
+                        clipboard::copy_as_json_button(ui, &node);
+                    });
                     ui.separator();
 
                     // Node name editing
@@ -1253,6 +1470,15 @@ impl FlowchartApp {
                     } else if ui.button(&node.name).clicked() {
                         self.start_editing_node_name(selected_id, &node.name);
                     }
+                    self.draw_lane_picker(ui, &node);
+                    let mut pinned = node.pinned;
+                    if ui
+                        .checkbox(&mut pinned, "Pinned")
+                        .on_hover_text("Keep this node in place when auto-arranging with force-directed layout")
+                        .changed()
+                    {
+                        self.set_nodes_pinned(&[selected_id], pinned);
+                    }
 
                     ui.separator();
 
@@ -1263,6 +1489,14 @@ impl FlowchartApp {
 
                     // Node state and position
                     self.draw_node_status_info(ui, &node);
+
+                    if matches!(
+                        node.node_type,
+                        NodeType::Consumer { .. } | NodeType::Transformer { .. }
+                    ) {
+                        ui.separator();
+                        self.draw_node_history(ui, selected_id);
+                    }
                 } else {
                     ui.label("Node not found");
                 }
@@ -1275,7 +1509,24 @@ impl FlowchartApp {
                     self.interaction.temp_globals_node_id = None;
                 }
                 if let Some(connection) = self.flowchart.connections.get(conn_idx) {
-                    self.draw_connection_properties(ui, connection);
+                    if self.inspector.connection_messages_of != Some(conn_idx) {
+                        self.inspector.connection_messages_of = Some(conn_idx);
+                        self.inspector.connection_messages_shown = self.message_render_limit;
+                    }
+                    let mut filter = self.inspector.connection_filter.clone();
+                    let mut shown = self.inspector.connection_messages_shown;
+                    let old_style = connection.style;
+                    let mut style = old_style;
+                    let trace_clicked =
+                        self.draw_connection_properties(ui, connection, &mut filter, &mut shown, &mut style);
+                    self.inspector.connection_filter = filter;
+                    self.inspector.connection_messages_shown = shown;
+                    if style != old_style {
+                        self.update_connection_style(conn_idx, style);
+                    }
+                    if let Some(trace_id) = trace_clicked {
+                        self.start_trace(trace_id);
+                    }
                 } else {
                     ui.label("Connection not found");
                 }
@@ -1299,8 +1550,27 @@ impl FlowchartApp {
     ///
     /// * `ui` - The egui UI context
     /// * `connection` - The connection to display properties for
-    fn draw_connection_properties(&self, ui: &mut egui::Ui, connection: &Connection) {
-        ui.label("Type: Connection");
+    /// * `filter` - Filter applied to the listed messages
+    /// * `shown` - How many matching messages to list; "Show more" raises it
+    ///   by `message_render_limit`
+    /// * `style` - The connection's line style, edited in place
+    ///
+    /// # Returns
+    ///
+    /// The trace ID of a message whose "Trace" button was clicked, if any.
+    fn draw_connection_properties(
+        &self,
+        ui: &mut egui::Ui,
+        connection: &Connection,
+        filter: &mut MessageFilter,
+        shown: &mut usize,
+        style: &mut LineStyle,
+    ) -> Option<TraceId> {
+        let mut trace_clicked = None;
+        ui.horizontal(|ui| {
+            ui.label("Type: Connection");
+            clipboard::copy_as_json_button(ui, connection);
+        });
         ui.separator();
 
         // Show from and to node names
@@ -1316,6 +1586,23 @@ impl FlowchartApp {
             ui.label("To: (node not found)");
         }
 
+        ui.separator();
+        egui::CollapsingHeader::new("Line Style")
+            .id_salt("connection_line_style")
+            .default_open(true)
+            .show(ui, |ui| Self::draw_line_style_editor(ui, style));
+
+        ui.separator();
+        egui::CollapsingHeader::new("Message Shape")
+            .id_salt("connection_message_shape")
+            .default_open(true)
+            .show(ui, |ui| {
+                inspector::draw_message_shape(
+                    ui,
+                    self.simulation_engine.connection_shape(connection.from, connection.to),
+                );
+            });
+
         ui.separator();
         ui.label(format!(
             "Messages in transit: {}",
@@ -1326,31 +1613,68 @@ impl FlowchartApp {
         if !connection.messages.is_empty() {
             ui.separator();
             ui.label("Message Contents:");
+            inspector::draw_message_filter(ui, filter, false, "connection_filter");
+            let matching = connection
+                .messages
+                .iter()
+                .filter(|m| filter.matches(m, None))
+                .count();
+            if matching < connection.messages.len() {
+                ui.label(format!("Showing {} of {}", matching, connection.messages.len()));
+            }
 
+            let listed = (*shown).min(matching);
             egui::ScrollArea::vertical()
                 .max_height(300.0)
                 .show(ui, |ui| {
-                    for (idx, message) in connection.messages.iter().enumerate() {
+                    let messages = connection
+                        .messages
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, m)| filter.matches(m, None))
+                        .take(listed);
+                    for (idx, message) in messages {
                         ui.push_id(idx, |ui| {
-                            // Pretty-print JSON for display
-                            let json_str = serde_json::to_string_pretty(&message.data)
-                                .unwrap_or_else(|_| format!("{:?}", message.data));
-
-                            // Header label for the message
-                            ui.label(format!("Message {}", idx + 1));
-
-                            // Create a JSON layouter for syntax highlighting
-                            let layouter_ref = json_str.clone();
-                            let mut layouter = rendering::create_json_layouter(&layouter_ref);
+                            // Header label for the message, noting failed attempts
+                            let mut header = format!("Message {}", idx + 1);
+                            if message.attempts > 0 {
+                                header.push_str(&format!(" — {} failed attempt(s)", message.attempts));
+                            }
+                            if let Some(step) = message.retry_at {
+                                header.push_str(&format!(", retry at step {}", step));
+                            }
+                            if message.priority != 0 {
+                                header.push_str(&format!(" [priority {}]", message.priority));
+                            }
+                            ui.horizontal(|ui| {
+                                ui.label(header);
+                                if let Some(trace_id) = message.trace_id {
+                                    let traced = self.traced_message == Some(trace_id);
+                                    if ui
+                                        .selectable_label(traced, "Trace")
+                                        .on_hover_text(format!(
+                                            "Highlight the path of trace {}",
+                                            trace::short_trace_id(trace_id)
+                                        ))
+                                        .clicked()
+                                    {
+                                        trace_clicked = Some(trace_id);
+                                    }
+                                }
+                                if ui
+                                    .small_button("Copy as JSON")
+                                    .on_hover_text("Copy the message, including its ID and trace")
+                                    .clicked()
+                                {
+                                    clipboard::copy_as_json(ui.ctx(), message);
+                                }
+                            });
 
-                            // Non-interactive code viewer with highlighting
-                            ui.add(
-                                egui::TextEdit::multiline(&mut json_str.as_str())
-                                    .desired_rows(5)
-                                    .desired_width(f32::INFINITY)
-                                    .font(egui::TextStyle::Monospace)
-                                    .interactive(false)
-                                    .layouter(&mut layouter),
+                            // Collapsible payload viewer; click a value to copy it
+                            json_tree::show_json_tree(
+                                ui,
+                                &message.data,
+                                self.syntax_theme.palette(ui.visuals().dark_mode),
                             );
 
                             // Small separator between messages
@@ -1358,11 +1682,92 @@ impl FlowchartApp {
                             ui.separator();
                         });
                     }
+
+                    if listed < matching {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Listing {} of {}", listed, matching));
+                            if ui.button("Show more").clicked() {
+                                *shown = listed + self.message_render_limit;
+                            }
+                            if ui.button("Show all").clicked() {
+                                *shown = matching;
+                            }
+                        });
+                    }
                 });
         }
 
         ui.separator();
         ui.colored_label(egui::Color32::GRAY, "Press Delete to remove");
+        trace_clicked
+    }
+
+    /// Renders the arrowhead, thickness, dash pattern and color of a
+    /// connection, editing `style` in place.
+    fn draw_line_style_editor(ui: &mut egui::Ui, style: &mut LineStyle) {
+        egui::Grid::new("connection_style_grid").num_columns(2).show(ui, |ui| {
+            ui.label("Arrowhead:");
+            egui::ComboBox::from_id_salt("connection_arrowhead")
+                .selected_text(style.arrow.label())
+                .show_ui(ui, |ui| {
+                    for head in ArrowHead::ALL {
+                        ui.selectable_value(&mut style.arrow, head, head.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Thickness:");
+            ui.add(
+                egui::DragValue::new(&mut style.width)
+                    .range(crate::constants::CONNECTION_WIDTH_RANGE)
+                    .speed(0.1)
+                    .max_decimals(1),
+            );
+            ui.end_row();
+
+            ui.label("Line:");
+            egui::ComboBox::from_id_salt("connection_dash")
+                .selected_text(style.dash.label())
+                .show_ui(ui, |ui| {
+                    for dash in LineDash::ALL {
+                        ui.selectable_value(&mut style.dash, dash, dash.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Color:");
+            ui.horizontal(|ui| {
+                let mut custom = style.color.is_some();
+                if ui.checkbox(&mut custom, "Custom").changed() {
+                    style.color = custom.then_some([90, 90, 90]);
+                }
+                if let Some(color) = &mut style.color {
+                    ui.color_edit_button_srgb(color);
+                }
+            });
+            ui.end_row();
+        });
+        if !style.is_default() && ui.button("Reset Style").clicked() {
+            *style = LineStyle::default();
+        }
+    }
+
+    /// Changes the line style of the connection at `index`.
+    ///
+    /// Quick successive edits, such as dragging the thickness, are merged
+    /// into one undo step.
+    fn update_connection_style(&mut self, index: usize, style: LineStyle) {
+        let Some(connection) = self.flowchart.connections.get_mut(index) else {
+            return;
+        };
+        let old = std::mem::replace(&mut connection.style, style);
+        let (from, to) = (connection.from, connection.to);
+        self.undo_history.push_action_coalesced(
+            UndoAction::ConnectionStyleChanged { from, to, old, new: style },
+            &format!("{}:{}:style", from, to),
+            self.frame_time,
+        );
+        self.file.mark_changed(ChangeKind::ConnectionEdited(from, to));
     }
 
     /// Renders the name editing field for a node.
@@ -1440,131 +1845,337 @@ impl FlowchartApp {
         });
     }
 
-    /// Starts editing the name of the specified node.
+    /// Starts editing the name of the specified node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - ID of the node to edit
+    /// * `current_name` - Current name of the node
+    fn start_editing_node_name(&mut self, node_id: NodeId, current_name: &str) {
+        self.interaction.editing_node_name = Some(node_id);
+        self.interaction.temp_node_name = current_name.to_string();
+        self.interaction.should_select_text = true;
+        self.interaction.focus_requested_for_edit = false;
+    }
+
+    /// Saves the current name edit to the selected node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - ID of the node to update
+    fn save_node_name_change(&mut self, node_id: NodeId) {
+        if let Some(node) = self.flowchart.nodes.get_mut(&node_id) {
+            let old_name = node.name.clone();
+            let new_name = self.interaction.temp_node_name.clone();
+
+            // Only record undo if name actually changed
+            if old_name != new_name {
+                self.undo_history.push_action(UndoAction::NodeRenamed {
+                    node_id,
+                    old_name,
+                    new_name: new_name.clone(),
+                });
+                node.name = new_name;
+                self.file.mark_changed(ChangeKind::NodeEdited(node_id));
+            }
+        }
+        self.interaction.editing_node_name = None;
+    }
+
+    /// Updates a producer's message template from the temporary editing
+    /// value. Leaves the template unchanged while the text isn't valid JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - ID of the producer node to update
+    fn update_producer_template(&mut self, node_id: NodeId) {
+        let Ok(template) = serde_json::from_str::<serde_json::Value>(&self.interaction.temp_producer_message_template)
+        else {
+            return;
+        };
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        let old_node_type = node.node_type.clone();
+        let NodeType::Producer { message_template, .. } = &mut node.node_type else {
+            return;
+        };
+        if *message_template == template {
+            return;
+        }
+        *message_template = template;
+
+        // Record undo action, merging rapid edits of the same field
+        self.undo_history.push_action_coalesced(
+            UndoAction::PropertyChanged {
+                node_id,
+                old_node_type,
+                new_node_type: node.node_type.clone(),
+            },
+            &format!("{}:message_template", node_id),
+            self.frame_time,
+        );
+        self.file.mark_changed(ChangeKind::NodeEdited(node_id));
+    }
+
+    /// Updates the schedule of a producer node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - ID of the producer node to update
+    /// * `start_step` - First step on which the producer emits
+    /// * `messages_per_cycle` - Total number of messages it emits
+    /// * `steps_between_cycles` - Gap between emissions in steps
+    fn update_producer_schedule(
+        &mut self,
+        node_id: NodeId,
+        start_step: u64,
+        messages_per_cycle: u32,
+        steps_between_cycles: u32,
+    ) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        let old_node_type = node.node_type.clone();
+        let NodeType::Producer {
+            start_step: start,
+            messages_per_cycle: total,
+            steps_between_cycles: between,
+            ..
+        } = &mut node.node_type
+        else {
+            return;
+        };
+        *start = start_step;
+        *total = messages_per_cycle;
+        *between = steps_between_cycles;
+        self.undo_history.push_action_coalesced(
+            UndoAction::PropertyChanged {
+                node_id,
+                old_node_type,
+                new_node_type: node.node_type.clone(),
+            },
+            &format!("{}:schedule", node_id),
+            self.frame_time,
+        );
+        self.file.mark_changed(ChangeKind::NodeEdited(node_id));
+    }
+
+    /// Updates the phase and jitter of a producer node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - ID of the producer node to update
+    /// * `timing` - The new phase and jitter
+    fn update_producer_timing(&mut self, node_id: NodeId, timing: ProducerTiming) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        let old = std::mem::replace(&mut node.timing, timing);
+        self.undo_history.push_action_coalesced(
+            UndoAction::TimingChanged { node_id, old, new: timing },
+            &format!("{}:timing", node_id),
+            self.frame_time,
+        );
+        self.file.mark_changed(ChangeKind::NodeEdited(node_id));
+    }
+
+    /// Updates the retry policy of a transformer node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - ID of the transformer node to update
+    /// * `policy` - The new retry policy
+    fn update_transformer_retry(&mut self, node_id: NodeId, policy: RetryPolicy) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        let old_node_type = node.node_type.clone();
+        let NodeType::Transformer { retry, .. } = &mut node.node_type else {
+            return;
+        };
+        *retry = policy;
+        self.undo_history.push_action_coalesced(
+            UndoAction::PropertyChanged {
+                node_id,
+                old_node_type,
+                new_node_type: node.node_type.clone(),
+            },
+            &format!("{}:retry", node_id),
+            self.frame_time,
+        );
+        self.file.mark_changed(ChangeKind::NodeEdited(node_id));
+    }
+
+    /// Updates the random failure settings of a node.
     ///
     /// # Arguments
     ///
-    /// * `node_id` - ID of the node to edit
-    /// * `current_name` - Current name of the node
-    fn start_editing_node_name(&mut self, node_id: NodeId, current_name: &str) {
-        self.interaction.editing_node_name = Some(node_id);
-        self.interaction.temp_node_name = current_name.to_string();
-        self.interaction.should_select_text = true;
-        self.interaction.focus_requested_for_edit = false;
+    /// * `node_id` - ID of the transformer or consumer node to update
+    /// * `failure` - The new failure settings
+    fn update_node_failure(&mut self, node_id: NodeId, failure: FailureSettings) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        let old = std::mem::replace(&mut node.failure, failure);
+        self.undo_history.push_action_coalesced(
+            UndoAction::FailureChanged { node_id, old, new: failure },
+            &format!("{}:failure", node_id),
+            self.frame_time,
+        );
+        self.file.mark_changed(ChangeKind::NodeEdited(node_id));
     }
 
-    /// Saves the current name edit to the selected node.
-    ///
-    /// # Arguments
+    /// Turns the tap of a consumer on or off as one undo step.
     ///
-    /// * `node_id` - ID of the node to update
-    fn save_node_name_change(&mut self, node_id: NodeId) {
-        if let Some(node) = self.flowchart.nodes.get_mut(&node_id) {
-            let old_name = node.name.clone();
-            let new_name = self.interaction.temp_node_name.clone();
+    /// Turning it off removes the consumer's outgoing connections, since
+    /// consumers without a tap can't be a source.
+    pub(crate) fn set_consumer_tap(&mut self, node_id: NodeId, tap: bool) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        if node.tap == tap {
+            return;
+        }
+        node.tap = tap;
+        let mut actions = Vec::new();
+        if !tap {
+            for index in (0..self.flowchart.connections.len()).rev() {
+                if self.flowchart.connections[index].from == node_id {
+                    let connection = self.flowchart.connections.remove(index);
+                    actions.push(UndoAction::ConnectionDeleted { connection, index });
+                }
+            }
+        }
+        actions.push(UndoAction::TapChanged { node_id, tap });
+        let action = if actions.len() == 1 {
+            actions.remove(0)
+        } else {
+            UndoAction::Batch { actions }
+        };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+    }
 
-            // Only record undo if name actually changed
-            if old_name != new_name {
-                self.undo_history.push_action(UndoAction::NodeRenamed {
-                    node_id,
-                    old_name,
-                    new_name: new_name.clone(),
-                });
-                node.name = new_name;
-                self.file.has_unsaved_changes = true;
+    /// Draws the failure probability and mode of a transformer or consumer.
+    fn draw_failure_settings(&mut self, ui: &mut egui::Ui, node_id: NodeId, failure: FailureSettings) {
+        ui.label(egui::RichText::new("Random Failures").strong());
+        let mut new_failure = failure;
+        let mut percent = new_failure.probability * 100.0;
+        ui.horizontal(|ui| {
+            ui.label("Probability:");
+            let response = ui
+                .add(egui::DragValue::new(&mut percent).range(0.0..=100.0).speed(0.1).suffix("%"))
+                .on_hover_text("Chance that each delivered message fails, drawn from the run's seed");
+            if response.changed() {
+                new_failure.probability = percent / 100.0;
             }
+        });
+        ui.add_enabled_ui(!new_failure.is_off(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Failed Messages:");
+                egui::ComboBox::from_id_salt("failure_mode")
+                    .selected_text(new_failure.mode.label())
+                    .show_ui(ui, |ui| {
+                        for mode in FailureMode::ALL {
+                            ui.selectable_value(&mut new_failure.mode, mode, mode.label());
+                        }
+                    });
+            });
+        });
+        if new_failure != failure {
+            self.update_node_failure(node_id, new_failure);
         }
-        self.interaction.editing_node_name = None;
     }
 
-    /// Updates a producer node property from the temporary editing values.
+    /// Updates the processing time and concurrency limit of a consumer node.
     ///
     /// # Arguments
     ///
-    /// * `node_id` - ID of the node to update
-    /// * `property` - Name of the property to update
-    fn update_producer_property(&mut self, node_id: NodeId, property: &str) {
-        if let Some(node) = self.flowchart.nodes.get(&node_id) {
-            let old_node_type = node.node_type.clone();
-            let mut changed = false;
-
-            if let NodeType::Producer {
-                mut message_template,
-                mut start_step,
-                mut messages_per_cycle,
-                mut steps_between_cycles,
-                messages_produced,
-            } = node.node_type.clone()
-            {
-                match property {
-                    "start_step" => {
-                        if let Ok(value) = self.interaction.temp_producer_start_step.parse::<u64>()
-                        {
-                            if start_step != value {
-                                start_step = value;
-                                changed = true;
-                            }
-                        }
-                    }
-                    "messages_per_cycle" => {
-                        if let Ok(value) = self
-                            .interaction
-                            .temp_producer_messages_per_cycle
-                            .parse::<u32>()
-                        {
-                            if messages_per_cycle != value {
-                                messages_per_cycle = value;
-                                changed = true;
-                            }
-                        }
-                    }
-                    "steps_between_cycles" => {
-                        if let Ok(value) =
-                            self.interaction.temp_producer_steps_between.parse::<u32>()
-                        {
-                            if steps_between_cycles != value {
-                                steps_between_cycles = value;
-                                changed = true;
-                            }
-                        }
-                    }
-                    "message_template" => {
-                        if let Ok(value) = serde_json::from_str::<serde_json::Value>(
-                            &self.interaction.temp_producer_message_template,
-                        ) {
-                            if message_template != value {
-                                message_template = value;
-                                changed = true;
-                            }
-                        }
-                    }
-                    _ => {}
-                }
-
-                if changed {
-                    let new_node_type = NodeType::Producer {
-                        message_template,
-                        start_step,
-                        messages_per_cycle,
-                        steps_between_cycles,
-                        messages_produced,
-                    };
+    /// * `node_id` - ID of the consumer node to update
+    /// * `processing_time` - Steps each message occupies the consumer
+    /// * `max_concurrency` - Maximum messages processed at once (0 = unlimited)
+    fn update_consumer_processing(&mut self, node_id: NodeId, processing_time: u32, max_concurrency: u32) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        let old_node_type = node.node_type.clone();
+        let NodeType::Consumer {
+            processing_time: time,
+            max_concurrency: limit,
+            ..
+        } = &mut node.node_type
+        else {
+            return;
+        };
+        *time = processing_time;
+        *limit = max_concurrency;
+        self.undo_history.push_action_coalesced(
+            UndoAction::PropertyChanged {
+                node_id,
+                old_node_type,
+                new_node_type: node.node_type.clone(),
+            },
+            &format!("{}:processing", node_id),
+            self.frame_time,
+        );
+        self.file.mark_changed(ChangeKind::NodeEdited(node_id));
+    }
 
-                    // Record undo action
-                    self.undo_history.push_action(UndoAction::PropertyChanged {
-                        node_id,
-                        old_node_type,
-                        new_node_type: new_node_type.clone(),
-                    });
+    /// Updates the accept filter of a consumer node and checks it for errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - ID of the consumer node to update
+    /// * `filter` - The new filter; empty accepts every message
+    fn update_consumer_accept_filter(&mut self, node_id: NodeId, filter: String) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        let old_node_type = node.node_type.clone();
+        let NodeType::Consumer { accept_filter, .. } = &mut node.node_type else {
+            return;
+        };
+        self.interaction.accept_filter_error = crate::simulation::validate_accept_filter(&filter)
+            .err()
+            .map(|error| (node_id, error));
+        *accept_filter = filter;
+        self.undo_history.push_action_coalesced(
+            UndoAction::PropertyChanged {
+                node_id,
+                old_node_type,
+                new_node_type: node.node_type.clone(),
+            },
+            &format!("{}:accept_filter", node_id),
+            self.frame_time,
+        );
+        self.file.mark_changed(ChangeKind::NodeEdited(node_id));
+    }
 
-                    // Apply the change
-                    if let Some(node) = self.flowchart.nodes.get_mut(&node_id) {
-                        node.node_type = new_node_type;
-                    }
-                    self.file.has_unsaved_changes = true;
-                }
-            }
+    /// Updates the linked file path of a reference node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - ID of the reference node to update
+    /// * `path` - The new file path
+    fn update_reference_path(&mut self, node_id: NodeId, path: String) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        if !matches!(&node.node_type, NodeType::Reference { path: old } if *old != path) {
+            return;
         }
+        let old_node_type = std::mem::replace(&mut node.node_type, NodeType::Reference { path });
+        self.undo_history.push_action_coalesced(
+            UndoAction::PropertyChanged {
+                node_id,
+                old_node_type,
+                new_node_type: node.node_type.clone(),
+            },
+            &format!("{}:path", node_id),
+            self.frame_time,
+        );
+        self.file.mark_changed(ChangeKind::NodeEdited(node_id));
     }
 
     /// Updates a transformer node property from the temporary editing values.
@@ -1596,19 +2207,25 @@ impl FlowchartApp {
                             };
                             let current_globals = if let NodeType::Transformer { globals, .. } = &node.node_type { globals.clone() } else { Default::default() };
                             let current_initial_globals = if let NodeType::Transformer { initial_globals, .. } = &node.node_type { initial_globals.clone() } else { Default::default() };
+                            let current_retry = if let NodeType::Transformer { retry, .. } = &node.node_type { *retry } else { Default::default() };
                             let new_node_type = NodeType::Transformer {
                                 script: new_script,
                                 selected_outputs,
                                 globals: current_globals,
                                 initial_globals: current_initial_globals,
+                                retry: current_retry,
                             };
 
-                            // Record undo action
-                            self.undo_history.push_action(UndoAction::PropertyChanged {
-                                node_id,
-                                old_node_type,
-                                new_node_type: new_node_type.clone(),
-                            });
+                            // Record undo action, merging consecutive keystrokes
+                            self.undo_history.push_action_coalesced(
+                                UndoAction::PropertyChanged {
+                                    node_id,
+                                    old_node_type,
+                                    new_node_type: new_node_type.clone(),
+                                },
+                                &format!("{}:script", node_id),
+                                self.frame_time,
+                            );
 
                             // Apply the change
                             if let Some(node) = self.flowchart.nodes.get_mut(&node_id) {
@@ -1620,7 +2237,7 @@ impl FlowchartApp {
                             }
                             // Clear global error highlight when script changes
                             self.error_node = None;
-                            self.file.has_unsaved_changes = true;
+                            self.file.mark_changed(ChangeKind::NodeEdited(node_id));
                         }
                     }
                     "globals" => {
@@ -1640,16 +2257,17 @@ impl FlowchartApp {
                         }
                         if !parse_failed {
                             let old_node_type = node.node_type.clone();
-                            let (cur_script, selected_outputs) = if let NodeType::Transformer { script, selected_outputs, .. } = &node.node_type {
-                                (script.clone(), selected_outputs.clone())
+                            let (cur_script, selected_outputs, cur_retry) = if let NodeType::Transformer { script, selected_outputs, retry, .. } = &node.node_type {
+                                (script.clone(), selected_outputs.clone(), *retry)
                             } else {
-                                (String::new(), None)
+                                (String::new(), None, Default::default())
                             };
                             let new_node_type = NodeType::Transformer {
                                 script: cur_script,
                                 selected_outputs,
                                 globals: new_map.clone(),
                                 initial_globals: new_map,
+                                retry: cur_retry,
                             };
                             // Record undo action
                             self.undo_history.push_action(UndoAction::PropertyChanged {
@@ -1661,7 +2279,7 @@ impl FlowchartApp {
                             if let Some(node) = self.flowchart.nodes.get_mut(&node_id) {
                                 node.node_type = new_node_type;
                             }
-                            self.file.has_unsaved_changes = true;
+                            self.file.mark_changed(ChangeKind::NodeEdited(node_id));
                         }
                     }
                     _ => {}
@@ -1683,8 +2301,12 @@ impl FlowchartApp {
                 NodeType::Producer { .. } => "Producer",
                 NodeType::Consumer { .. } => "Consumer",
                 NodeType::Transformer { .. } => "Transformer",
+                NodeType::Reference { .. } => "Reference",
             }
         ));
+        if let Some(plugin) = &node.plugin {
+            self.draw_plugin_info(ui, plugin);
+        }
 
         // Type-specific properties
         match &node.node_type {
@@ -1696,36 +2318,70 @@ impl FlowchartApp {
                 messages_produced,
             } => {
                 // Initialize temp values if empty
-                if self.interaction.temp_producer_start_step.is_empty() {
-                    self.interaction.temp_producer_start_step = start_step.to_string();
-                }
-                if self.interaction.temp_producer_messages_per_cycle.is_empty() {
-                    self.interaction.temp_producer_messages_per_cycle =
-                        messages_per_cycle.to_string();
-                }
-                if self.interaction.temp_producer_steps_between.is_empty() {
-                    self.interaction.temp_producer_steps_between = steps_between_cycles.to_string();
-                }
                 if self.interaction.temp_producer_message_template.is_empty() {
                     self.interaction.temp_producer_message_template =
                         serde_json::to_string_pretty(message_template)
                             .unwrap_or_else(|_| "{}".to_string());
                 }
 
-                ui.label("Start Step:");
-                if ui
-                    .text_edit_singleline(&mut self.interaction.temp_producer_start_step)
-                    .changed()
+                let mut new_start_step = *start_step;
+                let mut new_messages_per_cycle = *messages_per_cycle;
+                let mut new_steps_between = *steps_between_cycles;
+                let mut new_timing = node.timing;
+                egui::Grid::new("producer_schedule_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Start Step:");
+                    ui.add(
+                        egui::DragValue::new(&mut new_start_step)
+                            .range(0..=crate::constants::PRODUCER_MAX_START_STEP)
+                            .prefix("step "),
+                    )
+                    .on_hover_text("First step on which the producer emits");
+                    ui.end_row();
+
+                    ui.label("Total Messages:");
+                    ui.add(
+                        egui::DragValue::new(&mut new_messages_per_cycle)
+                            .range(0..=crate::constants::PRODUCER_MAX_MESSAGES)
+                            .suffix(" msgs"),
+                    )
+                    .on_hover_text("Messages emitted over the whole run");
+                    ui.end_row();
+
+                    ui.label("Steps Between Cycles:");
+                    ui.add(
+                        egui::DragValue::new(&mut new_steps_between)
+                            .range(0..=crate::constants::PRODUCER_MAX_STEPS_BETWEEN)
+                            .suffix(" steps"),
+                    )
+                    .on_hover_text("0 emits on every step");
+                    ui.end_row();
+
+                    ui.label("Phase:");
+                    ui.add(
+                        egui::DragValue::new(&mut new_timing.phase)
+                            .range(0..=crate::constants::PRODUCER_MAX_STEPS_BETWEEN)
+                            .suffix(" steps"),
+                    )
+                    .on_hover_text("Shifts every emission later, to offset producers on the same cycle");
+                    ui.end_row();
+
+                    ui.label("Jitter:");
+                    ui.add(
+                        egui::DragValue::new(&mut new_timing.jitter)
+                            .range(0..=crate::constants::PRODUCER_MAX_STEPS_BETWEEN)
+                            .suffix(" steps"),
+                    )
+                    .on_hover_text("Delays each emission by a random 0 to this many steps, drawn from the run's seed");
+                    ui.end_row();
+                });
+                if new_start_step != *start_step
+                    || new_messages_per_cycle != *messages_per_cycle
+                    || new_steps_between != *steps_between_cycles
                 {
-                    self.update_producer_property(node.id, "start_step");
+                    self.update_producer_schedule(node.id, new_start_step, new_messages_per_cycle, new_steps_between);
                 }
-
-                ui.label("Total Messages:");
-                if ui
-                    .text_edit_singleline(&mut self.interaction.temp_producer_messages_per_cycle)
-                    .changed()
-                {
-                    self.update_producer_property(node.id, "messages_per_cycle");
+                if new_timing != node.timing {
+                    self.update_producer_timing(node.id, new_timing);
                 }
 
                 ui.label(format!(
@@ -1733,20 +2389,26 @@ impl FlowchartApp {
                     messages_produced, messages_per_cycle
                 ));
 
-                ui.label("Steps Between Cycles:");
-                if ui
-                    .text_edit_singleline(&mut self.interaction.temp_producer_steps_between)
-                    .changed()
-                {
-                    self.update_producer_property(node.id, "steps_between_cycles");
-                }
+                ui.label("Schedule:");
+                self.draw_producer_timeline(
+                    ui,
+                    *start_step,
+                    *messages_per_cycle,
+                    *steps_between_cycles,
+                    *messages_produced,
+                    node,
+                );
 
                 ui.separator();
                 ui.label("Message Template (JSON):");
+                #[cfg(not(target_arch = "wasm32"))]
+                self.draw_source_file_row(ui, node, "template");
+                let from_file = cfg!(not(target_arch = "wasm32")) && node.source_file.is_some();
 
                 // Store a reference for the layouter and a mutable copy for editing
                 let layouter_ref = self.interaction.temp_producer_message_template.clone();
-                let mut layouter = rendering::create_json_layouter(&layouter_ref);
+                let syntax_theme = self.syntax_theme;
+                let mut layouter = rendering::create_json_layouter(&layouter_ref, &syntax_theme);
 
                 let text_edit_response = ui.add(
                     egui::TextEdit::multiline(&mut self.interaction.temp_producer_message_template)
@@ -1754,6 +2416,7 @@ impl FlowchartApp {
                         .desired_width(f32::INFINITY)
                         .font(egui::TextStyle::Monospace)
                         .lock_focus(true)
+                        .interactive(!from_file)
                         .layouter(&mut layouter),
                 );
 
@@ -1791,7 +2454,7 @@ impl FlowchartApp {
                 }
 
                 if edited || text_edit_response.changed() {
-                    self.update_producer_property(node.id, "message_template");
+                    self.update_producer_template(node.id);
                 }
 
                 // Hint: formatting shortcut
@@ -1801,9 +2464,122 @@ impl FlowchartApp {
                     "Tip: Press Ctrl+Shift+F to format JSON."
                 };
                 ui.add(egui::Label::new(egui::RichText::new(hint).small().italics()).wrap());
+                ui.add(
+                    egui::Label::new(
+                        egui::RichText::new(
+                            "Add a numeric \"__priority\" field to deliver these messages ahead of lower-priority ones.",
+                        )
+                        .small()
+                        .italics(),
+                    )
+                    .wrap(),
+                );
+                if ui
+                    .button("Generate Template…")
+                    .on_hover_text("Build a template from a field spec with sample values")
+                    .clicked()
+                {
+                    self.open_data_generator(node.id);
+                }
             }
-            NodeType::Consumer { consumption_rate } => {
+            NodeType::Consumer {
+                consumption_rate,
+                processing_time,
+                max_concurrency,
+                in_flight,
+                accept_filter,
+            } => {
                 ui.label(format!("Consumption Rate: {} msg/step", consumption_rate));
+
+                let mut new_processing_time = *processing_time;
+                let mut new_max_concurrency = *max_concurrency;
+                ui.horizontal(|ui| {
+                    ui.label("Processing Time:");
+                    ui.add(
+                        egui::DragValue::new(&mut new_processing_time)
+                            .range(0..=10_000)
+                            .suffix(" steps"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Max Concurrency:");
+                    ui.add(egui::DragValue::new(&mut new_max_concurrency).range(0..=10_000))
+                        .on_hover_text("0 means unlimited");
+                });
+                if new_processing_time != *processing_time || new_max_concurrency != *max_concurrency {
+                    self.update_consumer_processing(node.id, new_processing_time, new_max_concurrency);
+                }
+
+                if *processing_time > 0 {
+                    let capacity = if *max_concurrency == 0 {
+                        "∞".to_string()
+                    } else {
+                        max_concurrency.to_string()
+                    };
+                    ui.label(format!("Busy: {}/{}", in_flight.len(), capacity));
+                } else {
+                    ui.weak("Messages are consumed instantly.");
+                }
+
+                ui.label("Accept Filter:");
+                let mut edited_filter = accept_filter.clone();
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut edited_filter)
+                        .hint_text("$.status == \"ok\"  or  msg.priority > 2")
+                        .font(egui::TextStyle::Monospace),
+                );
+                response.on_hover_text(
+                    "Only matching messages are consumed; the rest wait on the connection.\n\
+                     Use a JSONPath condition starting with $, or a JavaScript expression over msg.",
+                );
+                if edited_filter != *accept_filter {
+                    self.update_consumer_accept_filter(node.id, edited_filter);
+                }
+                if let Some((_, error)) = self
+                    .interaction
+                    .accept_filter_error
+                    .as_ref()
+                    .filter(|(id, _)| *id == node.id)
+                {
+                    ui.colored_label(egui::Color32::from_rgb(200, 80, 80), error);
+                }
+
+                let mut tap = node.tap;
+                let outputs = self.flowchart.connections.iter().filter(|c| c.from == node.id).count();
+                let response = ui.checkbox(&mut tap, "Tap: send a receipt for each message taken");
+                let hint = if outputs > 0 && node.tap {
+                    format!("Turning the tap off removes its {} outgoing connections", outputs)
+                } else {
+                    "Lets the consumer connect to accounting nodes, which get the consumer, step, message id \
+                     and latency of each message it takes instead of the message itself"
+                        .to_string()
+                };
+                if response.on_hover_text(hint).changed() {
+                    self.set_consumer_tap(node.id, tap);
+                }
+
+                ui.separator();
+                self.draw_failure_settings(ui, node.id, node.failure);
+            }
+            NodeType::Reference { path } => {
+                ui.label("Linked File:");
+                let mut edited_path = path.clone();
+                if ui.text_edit_singleline(&mut edited_path).changed() {
+                    self.update_reference_path(node.id, edited_path);
+                }
+                ui.add_enabled_ui(!path.trim().is_empty(), |ui| {
+                    if ui.button("Open Linked File").clicked() {
+                        self.open_reference(node.id);
+                    }
+                });
+                ui.add(
+                    egui::Label::new(
+                        egui::RichText::new("Relative paths are resolved from this file's folder.")
+                            .small()
+                            .italics(),
+                    )
+                    .wrap(),
+                );
             }
             NodeType::Transformer { script, .. } => {
                 // Initialize temp value if empty or if out of sync with selected node
@@ -1811,7 +2587,14 @@ impl FlowchartApp {
                     self.interaction.temp_transformer_script = script.clone();
                 }
 
-                ui.label("JavaScript Script:");
+                ui.horizontal(|ui| {
+                    ui.label("JavaScript Script:");
+                    ui.toggle_value(&mut self.show_script_api_panel, "Script API")
+                        .on_hover_text("What scripts can use: transform, context, state, __targets and more");
+                });
+                #[cfg(not(target_arch = "wasm32"))]
+                self.draw_source_file_row(ui, node, "script");
+                let from_file = cfg!(not(target_arch = "wasm32")) && node.source_file.is_some();
 
                 // Determine a max height of ~50 lines based on monospace row height
                 let row_height = ui.text_style_height(&egui::TextStyle::Monospace).max(12.0);
@@ -1819,7 +2602,8 @@ impl FlowchartApp {
 
                 // Store a reference for the layouter and a mutable copy for editing
                 let layouter_ref = self.interaction.temp_transformer_script.clone();
-                let mut layouter = rendering::create_js_layouter(&layouter_ref);
+                let syntax_theme = self.syntax_theme;
+                let mut layouter = rendering::create_js_layouter(&layouter_ref, &syntax_theme);
 
                 egui::ScrollArea::vertical()
                     .max_height(max_height)
@@ -1832,9 +2616,15 @@ impl FlowchartApp {
                             .desired_width(f32::INFINITY)
                             .font(egui::TextStyle::Monospace)
                             .lock_focus(true)
+                            .interactive(!from_file)
                             .layouter(&mut layouter),
                         );
 
+                        if std::mem::take(&mut self.interaction.focus_script_editor) {
+                            text_edit_response.request_focus();
+                            text_edit_response.scroll_to_me(Some(egui::Align::TOP));
+                        }
+
                         let mut edited = false;
                         let opts = CodeEditOptions {
                             language: LanguageKind::JavaScript,
@@ -1887,6 +2677,31 @@ impl FlowchartApp {
                     ui.colored_label(egui::Color32::RED, format!("Script error: {}", msg));
                 }
 
+                ui.separator();
+                ui.label(egui::RichText::new("Retry on Error").strong());
+                if let NodeType::Transformer { retry, .. } = &node.node_type {
+                    let mut new_retry = *retry;
+                    ui.horizontal(|ui| {
+                        ui.label("Max Attempts:");
+                        ui.add(egui::DragValue::new(&mut new_retry.max_attempts).range(1..=100));
+                    });
+                    ui.add_enabled_ui(new_retry.max_attempts > 1, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Backoff:");
+                            ui.add(
+                                egui::DragValue::new(&mut new_retry.backoff_steps)
+                                    .range(0..=10_000)
+                                    .suffix(" steps"),
+                            );
+                        });
+                    });
+                    if new_retry != *retry {
+                        self.update_transformer_retry(node.id, new_retry);
+                    }
+                }
+                ui.separator();
+                self.draw_failure_settings(ui, node.id, node.failure);
+
                 ui.separator();
                 ui.heading("Global State");
                 ui.label("These values are available in scripts as globalThis.state");
@@ -1919,16 +2734,17 @@ impl FlowchartApp {
                                     // Only save if all values are valid JSON
                                     if all_valid {
                                         let old_node_type = prev_node.node_type.clone();
-                                        let (cur_script, selected_outputs, cur_globals) = if let NodeType::Transformer { script, selected_outputs, globals, .. } = &prev_node.node_type {
-                                            (script.clone(), selected_outputs.clone(), globals.clone())
+                                        let (cur_script, selected_outputs, cur_globals, cur_retry) = if let NodeType::Transformer { script, selected_outputs, globals, retry, .. } = &prev_node.node_type {
+                                            (script.clone(), selected_outputs.clone(), globals.clone(), *retry)
                                         } else {
-                                            (String::new(), None, Default::default())
+                                            (String::new(), None, Default::default(), Default::default())
                                         };
                                         let new_node_type = NodeType::Transformer {
                                             script: cur_script,
                                             selected_outputs,
                                             globals: cur_globals,
                                             initial_globals: new_map,
+                                            retry: cur_retry,
                                         };
                                         // Record undo action
                                         self.undo_history.push_action(UndoAction::PropertyChanged {
@@ -1940,7 +2756,7 @@ impl FlowchartApp {
                                         if let Some(node_mut) = self.flowchart.nodes.get_mut(&prev_node_id) {
                                             node_mut.node_type = new_node_type;
                                         }
-                                        self.file.has_unsaved_changes = true;
+                                        self.file.mark_changed(ChangeKind::NodeEdited(prev_node_id));
                                     }
                                 }
                             }
@@ -2078,10 +2894,10 @@ impl FlowchartApp {
                                     );
 
                                     // Validate JSON on change
-                                    if saved_response.changed() {
-                                        if serde_json::from_str::<serde_json::Value>(saved_val_str.trim()).is_err() {
-                                            saved_response.on_hover_text_at_pointer("⚠ Invalid JSON");
-                                        }
+                                    if saved_response.changed()
+                                        && serde_json::from_str::<serde_json::Value>(saved_val_str.trim()).is_err()
+                                    {
+                                        saved_response.on_hover_text_at_pointer("⚠ Invalid JSON");
                                     }
 
                                     // Current value column (read-only, shows runtime value)
@@ -2189,40 +3005,14 @@ impl FlowchartApp {
     /// * `ui` - The egui UI context
     fn draw_no_selection_info(&self, ui: &mut egui::Ui) {
         ui.label("No node selected");
-        ui.separator();
-
-        // Selection basics
-        ui.label("Selection:");
-        ui.label(" - Left-click a node to select it");
-        ui.label(" - Shift+Click a node to toggle its selection");
-        ui.label(" - Drag on empty canvas to marquee-select (hold Shift to add)");
-        ui.label(" - Click empty space to clear selection");
-
-        ui.separator();
-        // Node movement and connections
-        ui.label("Nodes & connections:");
-        ui.label(" - Drag a node to move it (hold Shift to snap to grid)");
-        ui.label(" - Shift+Drag from a node to create a connection");
-
-        ui.separator();
-        // Canvas navigation and context menu
-        ui.label("Canvas:");
-        ui.label(" - Right-click to open the menu and create nodes");
-        ui.label(" - Middle-drag or Cmd (macOS) / Ctrl (Win/Linux) + Left-drag to pan");
-        ui.label(" - Scroll wheel to zoom");
-
-        ui.separator();
-        // Grouping and edits
-        ui.label("Editing:");
-        ui.label(" - Cmd/Ctrl+G to group selected node(s)");
-        ui.label(" - Delete removes the selected node(s), connection, or group");
-        ui.label(" - Undo/Redo: Cmd/Ctrl+Z, Cmd/Ctrl+Shift+Z or Cmd/Ctrl+Y");
-
-        ui.separator();
-        // File shortcuts
-        ui.label("File:");
-        ui.label(" - Cmd/Ctrl+S Save, Cmd/Ctrl+Shift+S Save As");
-        ui.label(" - Cmd/Ctrl+O Open, Cmd/Ctrl+N New");
+        ui.label(
+            egui::RichText::new(
+                "Hints for the mouse gestures at hand are shown in the status bar; \
+                 Help → Keyboard Shortcuts lists them all.",
+            )
+            .small()
+            .italics(),
+        );
     }
 
     /// Renders the right-click context menu for creating nodes.
@@ -2242,6 +3032,52 @@ impl FlowchartApp {
             .show(ui.ctx(), |ui| {
                 egui::Frame::popup(ui.style()).show(ui, |ui| {
                     ui.vertical(|ui| {
+                        if let Some(node_id) = self.context_menu.target_node {
+                            ui.label("Node:");
+                            ui.separator();
+                            if ui.button("Copy as JSON").clicked() {
+                                if let Some(json) = self.node_as_json(node_id) {
+                                    ui.ctx().copy_text(json);
+                                }
+                                self.context_menu.show = false;
+                            }
+                            let selection = self.selected_node_ids();
+                            if selection.len() > 1
+                                && selection.contains(&node_id)
+                                && ui.button("Connect Selection in Order").clicked()
+                            {
+                                self.connect_selection_in_order();
+                                self.context_menu.show = false;
+                            }
+                            // Restack the whole selection when the node is part of it
+                            let targets = if selection.contains(&node_id) { selection } else { vec![node_id] };
+                            if ui.button("Bring to Front").clicked() {
+                                self.restack(&targets, &[], true);
+                                self.context_menu.show = false;
+                            }
+                            if ui.button("Send to Back").clicked() {
+                                self.restack(&targets, &[], false);
+                                self.context_menu.show = false;
+                            }
+                            ui.separator();
+                        }
+
+                        if let Some((from, to)) = self.context_menu.target_connection {
+                            ui.label("Connection:");
+                            ui.separator();
+                            if ui.button("Insert Transformer here").clicked() {
+                                self.insert_transformer_into_connection(from, to);
+                                self.context_menu.show = false;
+                            }
+                            if ui.button("Copy as JSON").clicked() {
+                                if let Some(json) = self.connection_as_json(from, to) {
+                                    ui.ctx().copy_text(json);
+                                }
+                                self.context_menu.show = false;
+                            }
+                            ui.separator();
+                        }
+
                         ui.label("Create Node:");
                         ui.separator();
 
@@ -2257,20 +3093,66 @@ impl FlowchartApp {
                         }
 
                         if ui.button("Consumer").clicked() {
-                            self.create_node_at_pos(NodeType::Consumer { consumption_rate: 1 });
+                            self.create_node_at_pos(NodeType::Consumer {
+                                consumption_rate: 1,
+                                processing_time: 0,
+                                max_concurrency: 1,
+                                in_flight: Vec::new(),
+                                accept_filter: String::new(),
+                            });
                             self.context_menu.show = false;
                         }
 
                         if ui.button("Transformer").clicked() {
                             self.create_node_at_pos(NodeType::Transformer {
-                                script: "// Transform the input message with optional routing via __targets\nfunction transform(input) {\n    // To target specific outputs by node name, include __targets as an array.\n    // For example, send only to node named \"NextNode\":\n    // return { value: input.value, __targets: [\"NextNode\"] };\n    // If __targets is omitted or null, the message is broadcast to all outputs.\n    return input;\n}".to_string(),
+                                script: crate::constants::DEFAULT_TRANSFORMER_SCRIPT.to_string(),
                                 selected_outputs: None,
                                 globals: Default::default(),
                                 initial_globals: Default::default(),
+                                retry: Default::default(),
+                            });
+                            self.context_menu.show = false;
+                        }
+
+                        if ui.button("Reference").clicked() {
+                            self.create_node_at_pos(NodeType::Reference {
+                                path: String::new(),
                             });
                             self.context_menu.show = false;
                         }
 
+                        if ui
+                            .button("Pipeline…")
+                            .on_hover_text("A connected producer → transformer → consumer chain")
+                            .clicked()
+                        {
+                            self.open_pipeline_wizard(self.context_menu.world_pos);
+                            self.context_menu.show = false;
+                        }
+
+                        if !self.plugins.is_empty() {
+                            ui.separator();
+                            ui.label("Plugins");
+                            let mut chosen = None;
+                            for plugin in self.plugins.iter() {
+                                let manifest = &plugin.manifest;
+                                let label = format!("{} {}", manifest.icon, manifest.name);
+                                let response = ui.button(label.trim());
+                                let response = if manifest.description.is_empty() {
+                                    response
+                                } else {
+                                    response.on_hover_text(&manifest.description)
+                                };
+                                if response.clicked() {
+                                    chosen = Some(manifest.id.clone());
+                                }
+                            }
+                            if let Some(id) = chosen {
+                                self.create_plugin_node(&id);
+                                self.context_menu.show = false;
+                            }
+                        }
+
                         ui.separator();
                         if ui.button("Cancel").clicked() {
                             self.context_menu.show = false;
@@ -2308,16 +3190,26 @@ impl FlowchartApp {
         let node_id = new_node.id;
         self.flowchart.add_node(new_node);
 
-        // Record undo action for node creation
-        self.undo_history
-            .push_action(UndoAction::NodeCreated { node_id });
+        // Record undo action for node creation, with any nodes nudged aside
+        let created = UndoAction::NodeCreated { node_id };
+        let action = match self.make_room_for(&[node_id]) {
+            Some(moved) => {
+                self.file.mark_action(&moved);
+                UndoAction::Batch { actions: vec![created, moved] }
+            }
+            None => created,
+        };
+        self.undo_history.push_action(action);
 
         // Select the new node and start editing its name immediately
         self.interaction.selected_node = Some(node_id);
         self.start_editing_node_name(node_id, &format!("node{}", self.node_counter));
 
         // Mark as having unsaved changes
-        self.file.has_unsaved_changes = true;
+        self.file.mark_changed(ChangeKind::NodeAdded(node_id));
+
+        // Offer to wire the node into nearby nodes or the connection it was dropped on
+        self.interaction.auto_connect_suggestion = self.suggest_auto_connect(node_id);
     }
 
     /// Renders the main canvas area with nodes, connections, and handles user interactions.
@@ -2335,11 +3227,13 @@ impl FlowchartApp {
             self.canvas.offset = canvas_center.to_vec2();
         }
 
-        // Handle canvas panning with middle mouse button or Ctrl+drag
+        // Handle canvas panning with middle mouse button, Ctrl+drag, sideways scrolling or the keyboard
         self.handle_canvas_panning(ui, &response);
+        self.handle_keyboard_panning(ui);
 
-        // Handle scroll wheel zooming
+        // Handle scroll wheel and keyboard zooming
         self.handle_canvas_zoom(ui, &response);
+        self.handle_keyboard_zoom(ui, &response);
 
         // Handle other interactions (selection, context menu, marquee start/update)
         // Run this before node dragging so marquee gets priority over node drag
@@ -2348,8 +3242,12 @@ impl FlowchartApp {
         // Handle node dragging with left mouse button (respects marquee priority)
         self.handle_node_dragging(ui, &response);
 
+        // Error badges show their errors on hover and open the script editor on click
+        self.handle_error_badges(ui, &response);
+
         // Render all flowchart elements (including marquee rectangle if active)
         let canvas_rect = response.rect;
+        self.canvas.screen_rect = canvas_rect;
         self.render_flowchart_elements(&painter, canvas_rect);
 
         // Show context menu if active
@@ -2371,6 +3269,7 @@ impl FlowchartApp {
             && self.interaction.dragging_node.is_none()
             && self.interaction.drawing_connection_from.is_none()
             && self.interaction.pending_shift_connection_from.is_none()
+            && self.interaction.duplicating_from.is_none()
         {
             if let Some(pos) = response.interact_pointer_pos() {
                 // If a marquee is already active, always update its end point regardless of what's under the cursor
@@ -2411,14 +3310,23 @@ impl FlowchartApp {
                 if !self.interaction.marquee_additive {
                     self.interaction.selected_nodes.clear();
                 }
-                for (id, node) in &self.flowchart.nodes {
-                    let center = egui::pos2(node.position.0, node.position.1);
-                    if world_rect.contains(center) {
-                        if !self.interaction.selected_nodes.contains(id) {
-                            self.interaction.selected_nodes.push(*id);
-                        }
-                    }
-                }
+                // Newly enclosed nodes join left to right, then top to bottom, so the
+                // selection has an order that follows the layout (see "Connect Selection in Order")
+                let mut enclosed: Vec<&FlowchartNode> = self
+                    .flowchart
+                    .nodes
+                    .values()
+                    .filter(|node| world_rect.contains(egui::pos2(node.position.0, node.position.1)))
+                    .filter(|node| !self.interaction.selected_nodes.contains(&node.id))
+                    .collect();
+                enclosed.sort_by(|a, b| {
+                    a.position
+                        .0
+                        .total_cmp(&b.position.0)
+                        .then(a.position.1.total_cmp(&b.position.1))
+                });
+                let enclosed: Vec<NodeId> = enclosed.iter().map(|node| node.id).collect();
+                self.interaction.selected_nodes.extend(enclosed);
                 // Sync single selection convenience field
                 if self.interaction.selected_nodes.len() == 1 {
                     self.interaction.selected_node = Some(self.interaction.selected_nodes[0]);
@@ -2455,7 +3363,7 @@ impl FlowchartApp {
                 // cursor location, cycle selection among all entities under cursor. If there
                 // are no alternative entities, keep the existing connection selected.
                 if let Some(current_conn) = self.interaction.selected_connection {
-                    if conn_hits.iter().any(|&i| i == current_conn) {
+                    if conn_hits.contains(&current_conn) {
                         // Build ordered hit list (node first, then connections in draw order)
                         enum Hit { Node(NodeId), Connection(usize) }
                         let mut hits: Vec<Hit> = Vec::new();
@@ -2571,6 +3479,14 @@ impl FlowchartApp {
                 let world_pos = self.screen_to_world(screen_pos);
                 self.context_menu.screen_pos = (screen_pos.x, screen_pos.y);
                 self.context_menu.world_pos = (world_pos.x, world_pos.y);
+                // Right-clicking a node or connection offers actions on it too
+                self.context_menu.target_node = self.find_node_at_position(world_pos);
+                self.context_menu.target_connection = if self.context_menu.target_node.is_none() {
+                    self.find_connection_at_position(world_pos)
+                        .map(|idx| (self.flowchart.connections[idx].from, self.flowchart.connections[idx].to))
+                } else {
+                    None
+                };
                 self.context_menu.show = true;
                 self.context_menu.just_opened = true;
             }
@@ -2581,19 +3497,23 @@ impl FlowchartApp {
     /// Note: This does NOT clear transformer globals - those are managed separately
     /// based on node selection to preserve unsaved edits when reselecting the same node.
     fn clear_temp_editing_values(&mut self) {
-        self.interaction.temp_producer_start_step.clear();
-        self.interaction.temp_producer_messages_per_cycle.clear();
-        self.interaction.temp_producer_steps_between.clear();
         self.interaction.temp_producer_message_template.clear();
         self.interaction.temp_transformer_script.clear();
+        self.interaction.temp_source_file = None;
     }
 
     /// Performs an undo operation.
     fn perform_undo(&mut self) {
         if let Some(action) = self.undo_history.pop_undo() {
+            // Described before it removes what it names
+            self.log_edits();
+            let description = edit_history::describe_action(&action, &self.flowchart);
             if let Some(redo_action) = self.flowchart.apply_undo(&action) {
+                crate::crash::record_event("Undo");
+                self.append_edit_record(format!("Undid: {}", description));
+                // The inverse action describes the edit undo just made
+                self.file.mark_action(&redo_action);
                 self.undo_history.push_redo(redo_action);
-                self.file.has_unsaved_changes = true;
 
                 // Clear selection and temp values to refresh UI
                 self.interaction.selected_node = None;
@@ -2609,9 +3529,13 @@ impl FlowchartApp {
     fn perform_redo(&mut self) {
         if let Some(action) = self.undo_history.pop_redo() {
             if let Some(undo_action) = self.flowchart.apply_undo(&action) {
-                self.undo_history.push_undo(undo_action);
+                crate::crash::record_event("Redo");
+                self.log_edits();
+                let description = edit_history::describe_action(&undo_action, &self.flowchart);
+                self.append_edit_record(format!("Redid: {}", description));
+                self.file.mark_action(&undo_action);
                 // Don't call push_action here as it would clear the redo stack
-                self.file.has_unsaved_changes = true;
+                self.undo_history.push_undo(undo_action);
 
                 // Clear selection and temp values to refresh UI
                 self.interaction.selected_node = None;
@@ -2626,11 +3550,15 @@ impl FlowchartApp {
     /// Apply the currently selected auto-arrangement mode to nodes.
     /// If a group is selected, only that group's nodes are rearranged.
     /// Otherwise, if any nodes are selected, only those nodes are rearranged.
+    /// The swimlane layout always arranges every node.
     fn apply_auto_arrangement(&mut self) {
         match self.auto_arrange_mode {
-            crate::ui::state::AutoArrangeMode::ForceDirected => self.auto_layout_graph(),
+            crate::ui::state::AutoArrangeMode::ForceDirected => {
+                self.auto_layout_graph();
+            }
             crate::ui::state::AutoArrangeMode::Grid => self.grid_layout_selected_or_all(),
             crate::ui::state::AutoArrangeMode::Line => self.line_layout_selected_or_all(),
+            crate::ui::state::AutoArrangeMode::Swimlanes => self.apply_swimlane_layout(),
         }
     }
 
@@ -2803,6 +3731,24 @@ impl FlowchartApp {
         result
     }
 
+    /// Pins or unpins `node_ids` as one undo step. Nodes already in that
+    /// state are left out of the undo entry.
+    pub(crate) fn set_nodes_pinned(&mut self, node_ids: &[NodeId], pinned: bool) {
+        let mut changed = Vec::new();
+        for id in node_ids {
+            if let Some(node) = self.flowchart.nodes.get_mut(id).filter(|n| n.pinned != pinned) {
+                node.pinned = pinned;
+                changed.push(*id);
+            }
+        }
+        if changed.is_empty() {
+            return;
+        }
+        let action = UndoAction::NodesPinned { node_ids: changed, pinned };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+    }
+
     /// Automatically organizes nodes using a force-directed layout algorithm.
     ///
     /// This method applies forces to nodes to create an aesthetically pleasing layout:
@@ -2812,27 +3758,43 @@ impl FlowchartApp {
     ///
     /// The algorithm accounts for node size (100x70) and adds extra spacing
     /// to ensure connections are visible between nodes.
-    fn auto_layout_graph(&mut self) {
+    ///
+    /// Pinned nodes still push and pull on the others but keep their
+    /// positions. When any are involved the layout is not re-centered, so
+    /// the rest settles around them.
+    ///
+    /// Strengths, iteration count and ideal edge length come from `force_layout`.
+    /// Returns whether an undo entry was recorded.
+    pub(crate) fn auto_layout_graph(&mut self) -> bool {
         if self.flowchart.nodes.is_empty() {
-            return;
+            return false;
         }
 
         // Determine target nodes: selected group > multi-selection > all
         let target_ids: Vec<NodeId> = self.target_ids_for_layout();
         if target_ids.is_empty() {
-            return;
+            return false;
+        }
+
+        // Pinned nodes act as fixed anchors; only the others move
+        let movable_ids: Vec<NodeId> = target_ids
+            .iter()
+            .copied()
+            .filter(|id| self.flowchart.nodes.get(id).is_some_and(|n| !n.pinned))
+            .collect();
+        if movable_ids.is_empty() {
+            return false;
         }
+        let has_anchors = movable_ids.len() < target_ids.len();
 
-        // Store original positions for undo (only for affected nodes)
-        let old_positions: Vec<(NodeId, (f32, f32))> = target_ids
+        // Store original positions for undo (only for nodes that can move)
+        let old_positions: Vec<(NodeId, (f32, f32))> = movable_ids
             .iter()
             .filter_map(|id| self.flowchart.nodes.get(id).map(|n| (*id, n.position)))
             .collect();
 
-        // Constants for the force-directed algorithm
-        const ITERATIONS: usize = 500;
-        const REPULSION_STRENGTH: f32 = 50000.0;
-        const ATTRACTION_STRENGTH: f32 = 0.08;
+        // Tunable strengths, see the popover next to the Auto Layout button
+        let settings = self.force_layout;
         const DAMPING: f32 = 0.85;
 
         // Node dimensions and spacing
@@ -2853,7 +3815,7 @@ impl FlowchartApp {
         }
 
         // Run simulation iterations
-        for _ in 0..ITERATIONS {
+        for _ in 0..settings.iterations {
             // Calculate forces for each node
             let mut forces: std::collections::HashMap<NodeId, (f32, f32)> =
                 std::collections::HashMap::new();
@@ -2881,9 +3843,9 @@ impl FlowchartApp {
                         // Stronger repulsion force when nodes are closer than minimum distance
                         let force_magnitude = if distance < min_distance {
                             // Extra strong repulsion to prevent overlaps
-                            REPULSION_STRENGTH / (distance * distance) * 2.0
+                            settings.repulsion / (distance * distance) * 2.0
                         } else {
-                            REPULSION_STRENGTH / (distance * distance)
+                            settings.repulsion / (distance * distance)
                         };
 
                         let fx = (dx / distance) * force_magnitude;
@@ -2912,11 +3874,10 @@ impl FlowchartApp {
                     let dy = to_node.position.1 - from_node.position.1;
                     let distance = (dx * dx + dy * dy).sqrt().max(1.0);
 
-                    // Spring force proportional to distance, but weaker for very close nodes
-                    let ideal_distance = min_distance * 1.5; // Prefer nodes to be a bit farther than minimum
-                    let displacement = distance - ideal_distance;
-                    let fx = (dx / distance) * displacement * ATTRACTION_STRENGTH;
-                    let fy = (dy / distance) * displacement * ATTRACTION_STRENGTH;
+                    // Spring force proportional to the distance from the ideal edge length
+                    let displacement = distance - settings.ideal_edge_length;
+                    let fx = (dx / distance) * displacement * settings.attraction;
+                    let fy = (dy / distance) * displacement * settings.attraction;
 
                     // Apply forces
                     let force_from = forces.get(&connection.from).unwrap();
@@ -2929,7 +3890,7 @@ impl FlowchartApp {
 
             // Update velocities and positions
             for (node_id, force) in &forces {
-                if let Some(node) = self.flowchart.nodes.get_mut(node_id) {
+                if let Some(node) = self.flowchart.nodes.get_mut(node_id).filter(|n| !n.pinned) {
                     let velocity = velocities.get_mut(node_id).unwrap();
 
                     // Update velocity with damping
@@ -2944,7 +3905,7 @@ impl FlowchartApp {
         }
 
         // Center the affected nodes around their centroid to avoid shifting unrelated nodes
-        if !target_ids.is_empty() {
+        if !has_anchors {
             // Calculate center of mass
             let mut center_x = 0.0;
             let mut center_y = 0.0;
@@ -2970,19 +3931,41 @@ impl FlowchartApp {
         }
 
         // Collect new positions after layout
-        let new_positions: Vec<(NodeId, (f32, f32))> = target_ids
+        let new_positions: Vec<(NodeId, (f32, f32))> = movable_ids
             .iter()
             .filter_map(|id| self.flowchart.nodes.get(id).map(|n| (*id, n.position)))
             .collect();
 
         // Record undo action for the layout operation
-        self.undo_history
-            .push_action(UndoAction::MultipleNodesMoved {
-                old_positions,
-                new_positions,
-            });
+        let action = UndoAction::MultipleNodesMoved {
+            old_positions,
+            new_positions,
+        };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+        true
+    }
 
-        self.file.has_unsaved_changes = true;
+    /// Nudges the layout targets so nearly horizontal or vertical connections
+    /// between them become exactly so, as one undo entry.
+    pub(crate) fn align_connected_nodes(&mut self) {
+        let new_positions = crate::analysis::plan_axis_alignment(&self.flowchart, &self.target_ids_for_layout());
+        if new_positions.is_empty() {
+            return;
+        }
+        let mut old_positions = Vec::with_capacity(new_positions.len());
+        for (id, position) in &new_positions {
+            if let Some(node) = self.flowchart.nodes.get_mut(id) {
+                old_positions.push((*id, node.position));
+                node.position = *position;
+            }
+        }
+        let action = UndoAction::MultipleNodesMoved {
+            old_positions,
+            new_positions,
+        };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
     }
 
     /// Arrange nodes in a grid. Applies to selected nodes if any, otherwise all.
@@ -3026,7 +4009,7 @@ impl FlowchartApp {
         let n = ids.len();
         let cols = (n as f32).sqrt().ceil() as usize;
         let cols = cols.max(1);
-        let rows = ((n + cols - 1) / cols).max(1);
+        let rows = n.div_ceil(cols).max(1);
 
         // grid physical dimensions
         let cell_w = NODE_WIDTH + H_SPACING;
@@ -3083,9 +4066,9 @@ impl FlowchartApp {
             .filter_map(|id| self.flowchart.nodes.get(id).map(|n| (*id, n.position)))
             .collect();
 
-        self.undo_history
-            .push_action(UndoAction::MultipleNodesMoved { old_positions, new_positions });
-        self.file.has_unsaved_changes = true;
+        let action = UndoAction::MultipleNodesMoved { old_positions, new_positions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
     }
 
     /// Arrange nodes in a horizontal line. Applies to selected nodes if any, otherwise all.
@@ -3136,9 +4119,9 @@ impl FlowchartApp {
             .filter_map(|id| self.flowchart.nodes.get(id).map(|n| (*id, n.position)))
             .collect();
 
-        self.undo_history
-            .push_action(UndoAction::MultipleNodesMoved { old_positions, new_positions });
-        self.file.has_unsaved_changes = true;
+        let action = UndoAction::MultipleNodesMoved { old_positions, new_positions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
     }
 }
 
@@ -0,0 +1,112 @@
+//! Notifications in the corner of the window.
+//!
+//! Notifications tell the user about something that happened outside their
+//! current task without interrupting it, unlike the modal dialogs. They are
+//! stacked in the bottom-right corner, newest at the bottom. One with a
+//! button, such as "A new version is available — Reload", stays until it is
+//! used or dismissed; the others fade after `NOTIFICATION_TIMEOUT_SECS`.
+
+use super::state::{FlowchartApp, Notification, NotificationAction, PendingConfirmAction, UpdateListener};
+use crate::constants::NOTIFICATION_TIMEOUT_SECS;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Adds a notification, replacing one with the same text.
+    pub(crate) fn post_notification(&mut self, message: String, action: Option<NotificationAction>) {
+        self.notifications.retain(|notification| notification.message != message);
+        self.notifications.push(Notification {
+            message,
+            action,
+            posted_at: self.frame_time,
+        });
+    }
+
+    /// Offers to reload the web app into `version` if it is newer than the
+    /// running one, returning whether the offer is shown.
+    pub fn notify_update_available(&mut self, version: &str) -> bool {
+        if !crate::pwa::is_newer_version(version) {
+            return false;
+        }
+        self.notifications
+            .retain(|notification| notification.action != Some(NotificationAction::ReloadForUpdate));
+        let message = format!("Version {} is available.", version.trim().trim_start_matches('v'));
+        self.post_notification(message, Some(NotificationAction::ReloadForUpdate));
+        true
+    }
+
+    /// Calls `listener` when the user accepts an update, instead of reloading
+    /// the page directly.
+    pub fn set_update_listener(&mut self, listener: Option<UpdateListener>) {
+        self.update_listener = listener;
+    }
+
+    /// Reloads into the new version, asking first if there are unsaved changes.
+    pub(crate) fn accept_update(&mut self) {
+        self.unless_unsaved(PendingConfirmAction::ReloadForUpdate, |app| app.reload_for_update());
+    }
+
+    /// Hands the update over to the host page, or reloads the page.
+    pub(crate) fn reload_for_update(&mut self) {
+        self.notifications
+            .retain(|notification| notification.action != Some(NotificationAction::ReloadForUpdate));
+        crate::crash::record_event("Reloading for an update");
+        if let Some(listener) = self.update_listener.as_mut() {
+            listener();
+        } else {
+            #[cfg(target_arch = "wasm32")]
+            if let Some(window) = web_sys::window() {
+                let _ = window.location().reload();
+            }
+        }
+    }
+
+    /// Removes notifications that have timed out and draws the others.
+    pub(crate) fn draw_notifications(&mut self, ctx: &egui::Context) {
+        let now = self.frame_time;
+        self.notifications.retain(|notification| {
+            notification.action.is_some() || now - notification.posted_at < NOTIFICATION_TIMEOUT_SECS
+        });
+        if self.notifications.is_empty() {
+            return;
+        }
+        if let Some(expires) = self
+            .notifications
+            .iter()
+            .filter(|notification| notification.action.is_none())
+            .map(|notification| notification.posted_at + NOTIFICATION_TIMEOUT_SECS - now)
+            .reduce(f64::min)
+        {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(expires.max(0.0)));
+        }
+
+        let mut dismissed = None;
+        let mut acted = None;
+        egui::Area::new(egui::Id::new("notifications"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -36.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (index, notification) in self.notifications.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(&notification.message);
+                            if let Some(action) = notification.action {
+                                if ui.button(action.label()).clicked() {
+                                    acted = Some(action);
+                                }
+                            }
+                            if ui.small_button("✕").on_hover_text("Dismiss").clicked() {
+                                dismissed = Some(index);
+                            }
+                        });
+                    });
+                }
+            });
+
+        if let Some(index) = dismissed {
+            self.notifications.remove(index);
+        }
+        if let Some(NotificationAction::ReloadForUpdate) = acted {
+            self.accept_update();
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Parameters window.
+//!
+//! Lists the document's [`Parameters`] for editing in one place, and the node
+//! properties bound to them. Every change goes through
+//! [`FlowchartApp::set_parameters`], which writes the new values into the
+//! bound properties and records both as one undo step.
+
+use super::state::{FlowchartApp, ParameterDraft};
+use super::undo::UndoAction;
+use crate::parameters::{apply_parameters, is_valid_name, BoundProperty, Parameters};
+use crate::types::*;
+use eframe::egui;
+use serde_json::Value;
+
+/// Parses a value typed in the parameters window: JSON if it is, text otherwise.
+fn parse_value(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.trim().to_string()))
+}
+
+/// One draft per parameter of `parameters`.
+fn drafts_of(parameters: &Parameters) -> Vec<ParameterDraft> {
+    parameters
+        .values
+        .iter()
+        .map(|(name, value)| ParameterDraft {
+            original: name.clone(),
+            name: name.clone(),
+            value: value.to_string(),
+        })
+        .collect()
+}
+
+impl FlowchartApp {
+    /// Replaces the document's parameters, writes their values into the
+    /// bound node properties and records the change as one undo step.
+    ///
+    /// Bindings that couldn't be applied are listed in the parameters window.
+    pub fn set_parameters(&mut self, parameters: Parameters) {
+        if parameters == self.flowchart.parameters {
+            return;
+        }
+        let old = std::mem::replace(&mut self.flowchart.parameters, parameters);
+        let before: Vec<(NodeId, NodeType)> = self
+            .flowchart
+            .nodes
+            .values()
+            .map(|node| (node.id, node.node_type.clone()))
+            .collect();
+        self.parameters_dialog.problems = apply_parameters(&mut self.flowchart);
+
+        let mut actions = vec![UndoAction::ParametersChanged {
+            old,
+            new: self.flowchart.parameters.clone(),
+        }];
+        for (node_id, old_node_type) in before {
+            let new_node_type = &self.flowchart.nodes[&node_id].node_type;
+            if *new_node_type != old_node_type {
+                actions.push(UndoAction::PropertyChanged {
+                    node_id,
+                    old_node_type,
+                    new_node_type: new_node_type.clone(),
+                });
+            }
+        }
+        let action = if actions.len() == 1 {
+            actions.remove(0)
+        } else {
+            UndoAction::Batch { actions }
+        };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+    }
+
+    /// Commits the draft of one parameter: a rename, a new value, or both.
+    fn commit_parameter_draft(&mut self, draft: &ParameterDraft) {
+        let name = draft.name.trim();
+        let mut parameters = self.flowchart.parameters.clone();
+        if name != draft.original {
+            if !is_valid_name(name) {
+                self.parameters_dialog.error = Some(format!("{} isn't a valid parameter name", name));
+                return;
+            }
+            if parameters.values.contains_key(name) {
+                self.parameters_dialog.error = Some(format!("There is already a parameter named {}", name));
+                return;
+            }
+            parameters.rename(&draft.original, name);
+        }
+        parameters.values.insert(name.to_string(), parse_value(&draft.value));
+        self.parameters_dialog.error = None;
+        self.set_parameters(parameters);
+    }
+
+    /// Draws the parameters window. Opens when `show_parameters_dialog` is true.
+    pub(crate) fn draw_parameters_dialog(&mut self, ctx: &egui::Context) {
+        if self.parameters_dialog.drafted_from.as_ref() != Some(&self.flowchart.parameters) {
+            self.parameters_dialog.drafts = drafts_of(&self.flowchart.parameters);
+            self.parameters_dialog.drafted_from = Some(self.flowchart.parameters.clone());
+        }
+        let mut keep_open = true;
+        let mut commit: Option<ParameterDraft> = None;
+        let mut changed: Option<Parameters> = None;
+        let parameters = &self.flowchart.parameters;
+        let nodes = &self.flowchart.nodes;
+        let state = &mut self.parameters_dialog;
+        let node_name = |id: &NodeId| nodes.get(id).map_or_else(|| "(deleted node)".to_string(), |n| n.name.clone());
+
+        egui::Window::new("Parameters")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(
+                    "Values are JSON; anything else is taken as text. Templates use \"{{NAME}}\" and scripts \
+                     context.params.NAME.",
+                );
+                ui.separator();
+
+                egui::Grid::new("parameters_grid").num_columns(4).striped(true).show(ui, |ui| {
+                    for draft in &mut state.drafts {
+                        let name = ui.add(egui::TextEdit::singleline(&mut draft.name).desired_width(120.0));
+                        let value = ui.add(egui::TextEdit::singleline(&mut draft.value).desired_width(140.0));
+                        if (name.lost_focus() || value.lost_focus())
+                            && (draft.name != draft.original
+                                || parameters.values.get(&draft.original) != Some(&parse_value(&draft.value)))
+                        {
+                            commit = Some(draft.clone());
+                        }
+                        let used = parameters
+                            .bindings
+                            .iter()
+                            .filter(|binding| binding.parameter == draft.original)
+                            .count();
+                        ui.weak(format!("used by {}", used));
+                        if ui.small_button("✕").on_hover_text("Delete the parameter and its bindings").clicked() {
+                            let mut next = parameters.clone();
+                            next.values.remove(&draft.original);
+                            next.bindings.retain(|binding| binding.parameter != draft.original);
+                            changed = Some(next);
+                        }
+                        ui.end_row();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut state.new_name)
+                            .hint_text("NAME")
+                            .desired_width(120.0),
+                    );
+                    ui.add(
+                        egui::TextEdit::singleline(&mut state.new_value)
+                            .hint_text("value")
+                            .desired_width(140.0),
+                    );
+                    let name = state.new_name.trim();
+                    let addable = is_valid_name(name) && !parameters.values.contains_key(name);
+                    if ui.add_enabled(addable, egui::Button::new("Add")).clicked() {
+                        let mut next = parameters.clone();
+                        next.values.insert(name.to_string(), parse_value(&state.new_value));
+                        changed = Some(next);
+                        state.new_name.clear();
+                        state.new_value.clear();
+                    }
+                });
+
+                ui.separator();
+                ui.strong("Bound properties");
+                for binding in &parameters.bindings {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{} · {} ← {}",
+                            node_name(&binding.node),
+                            binding.property.label(),
+                            binding.parameter
+                        ));
+                        if ui.small_button("✕").on_hover_text("Unbind; the property keeps its value").clicked() {
+                            let mut next = parameters.clone();
+                            next.unbind(binding.node, binding.property);
+                            changed = Some(next);
+                        }
+                    });
+                }
+                ui.horizontal(|ui| {
+                    let mut bindable: Vec<&FlowchartNode> = nodes
+                        .values()
+                        .filter(|node| BoundProperty::ALL.iter().any(|p| p.applies_to(&node.node_type)))
+                        .collect();
+                    bindable.sort_by(|a, b| a.name.cmp(&b.name));
+                    let selected = state.bind_node.and_then(|id| nodes.get(&id));
+                    egui::ComboBox::from_id_salt("parameter_bind_node")
+                        .selected_text(selected.map_or("Node", |node| node.name.as_str()))
+                        .show_ui(ui, |ui| {
+                            for node in &bindable {
+                                ui.selectable_value(&mut state.bind_node, Some(node.id), &node.name);
+                            }
+                        });
+                    if let Some(node) = selected {
+                        if !state.bind_property.applies_to(&node.node_type) {
+                            if let Some(first) = BoundProperty::ALL.into_iter().find(|p| p.applies_to(&node.node_type)) {
+                                state.bind_property = first;
+                            }
+                        }
+                        egui::ComboBox::from_id_salt("parameter_bind_property")
+                            .selected_text(state.bind_property.label())
+                            .show_ui(ui, |ui| {
+                                for property in BoundProperty::ALL.into_iter().filter(|p| p.applies_to(&node.node_type)) {
+                                    ui.selectable_value(&mut state.bind_property, property, property.label());
+                                }
+                            });
+                    }
+                    egui::ComboBox::from_id_salt("parameter_bind_parameter")
+                        .selected_text(if state.bind_parameter.is_empty() { "Parameter" } else { &state.bind_parameter })
+                        .show_ui(ui, |ui| {
+                            for name in parameters.values.keys() {
+                                ui.selectable_value(&mut state.bind_parameter, name.clone(), name);
+                            }
+                        });
+                    let ready = selected.is_some() && parameters.values.contains_key(&state.bind_parameter);
+                    if ui.add_enabled(ready, egui::Button::new("Bind")).clicked() {
+                        if let Some(node) = selected {
+                            let mut next = parameters.clone();
+                            next.bind(node.id, state.bind_property, &state.bind_parameter);
+                            changed = Some(next);
+                        }
+                    }
+                });
+
+                if let Some(error) = &state.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                for problem in &state.problems {
+                    ui.colored_label(ui.visuals().warn_fg_color, problem);
+                }
+            });
+
+        if let Some(draft) = commit {
+            self.commit_parameter_draft(&draft);
+        }
+        if let Some(parameters) = changed {
+            self.parameters_dialog.error = None;
+            self.set_parameters(parameters);
+        }
+        if !keep_open {
+            self.show_parameters_dialog = false;
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! "Add Pipeline" wizard.
+//!
+//! Asks for the names and rates of a producer → transformer → consumer chain
+//! and creates the three nodes side by side, already connected, as one undo
+//! step.
+
+use super::state::{FlowchartApp, PipelineWizardState};
+use crate::constants::{DEFAULT_TRANSFORMER_SCRIPT, PIPELINE_NODE_SPACING};
+use crate::types::*;
+use crate::ui::UndoAction;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Opens the wizard for a chain starting at `position`.
+    pub(crate) fn open_pipeline_wizard(&mut self, position: (f32, f32)) {
+        let n = self.node_counter;
+        self.pipeline_wizard = Some(PipelineWizardState {
+            position,
+            producer_name: format!("Producer {}", n + 1),
+            transformer_name: format!("Transformer {}", n + 2),
+            consumer_name: format!("Consumer {}", n + 3),
+            total_messages: 10,
+            steps_between: 1,
+            processing_time: 0,
+            max_concurrency: 1,
+        });
+    }
+
+    /// Draws the wizard while it is open.
+    pub(crate) fn draw_pipeline_wizard(&mut self, ctx: &egui::Context) {
+        let Some(state) = self.pipeline_wizard.as_mut() else {
+            return;
+        };
+
+        let mut keep_open = true;
+        let mut create = false;
+        let mut cancel = false;
+        egui::Window::new("Add Pipeline")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("pipeline_wizard_grid").num_columns(2).show(ui, |ui| {
+                    ui.strong("Producer");
+                    ui.text_edit_singleline(&mut state.producer_name);
+                    ui.end_row();
+                    ui.label("Total messages:");
+                    ui.add(egui::DragValue::new(&mut state.total_messages).range(1..=1_000_000));
+                    ui.end_row();
+                    ui.label("One message every:");
+                    ui.add(egui::DragValue::new(&mut state.steps_between).range(1..=10_000).suffix(" steps"));
+                    ui.end_row();
+
+                    ui.strong("Transformer");
+                    ui.text_edit_singleline(&mut state.transformer_name);
+                    ui.end_row();
+
+                    ui.strong("Consumer");
+                    ui.text_edit_singleline(&mut state.consumer_name);
+                    ui.end_row();
+                    ui.label("Processing time:");
+                    ui.add(egui::DragValue::new(&mut state.processing_time).range(0..=10_000).suffix(" steps"))
+                        .on_hover_text("0 consumes messages instantly");
+                    ui.end_row();
+                    ui.label("Max concurrency:");
+                    ui.add(egui::DragValue::new(&mut state.max_concurrency).range(0..=10_000))
+                        .on_hover_text("0 means unlimited");
+                    ui.end_row();
+                });
+
+                let names = [&state.producer_name, &state.transformer_name, &state.consumer_name];
+                let valid = names.iter().all(|name| !name.trim().is_empty());
+                if !valid {
+                    ui.colored_label(egui::Color32::from_rgb(200, 80, 80), "Every node needs a name.");
+                }
+                let taken: Vec<&str> = names
+                    .iter()
+                    .map(|name| name.trim())
+                    .filter(|name| self.flowchart.nodes.values().any(|n| n.name == *name))
+                    .collect();
+                if !taken.is_empty() {
+                    ui.weak(format!(
+                        "Already in use: {}. Scripts that route by name may reach the wrong node.",
+                        taken.join(", ")
+                    ));
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(valid, egui::Button::new("Create")).clicked() {
+                        create = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if create {
+            if let Some(state) = self.pipeline_wizard.take() {
+                self.create_pipeline(&state);
+            }
+        } else if cancel || !keep_open {
+            self.pipeline_wizard = None;
+        }
+    }
+
+    /// Creates and connects the chain described by `state` as one undo step.
+    ///
+    /// The new nodes become the selection so they can be moved together.
+    pub(crate) fn create_pipeline(&mut self, state: &PipelineWizardState) -> [NodeId; 3] {
+        let (x, y) = state.position;
+        let nodes = [
+            (
+                &state.producer_name,
+                NodeType::Producer {
+                    message_template: serde_json::json!({"value": 0}),
+                    start_step: 0,
+                    messages_per_cycle: state.total_messages,
+                    steps_between_cycles: state.steps_between,
+                    messages_produced: 0,
+                },
+            ),
+            (
+                &state.transformer_name,
+                NodeType::Transformer {
+                    script: DEFAULT_TRANSFORMER_SCRIPT.to_string(),
+                    selected_outputs: None,
+                    globals: Default::default(),
+                    initial_globals: Default::default(),
+                    retry: Default::default(),
+                },
+            ),
+            (
+                &state.consumer_name,
+                NodeType::Consumer {
+                    consumption_rate: 1,
+                    processing_time: state.processing_time,
+                    max_concurrency: state.max_concurrency,
+                    in_flight: Vec::new(),
+                    accept_filter: String::new(),
+                },
+            ),
+        ];
+        let mut column = 0.0;
+        let ids = nodes.map(|(name, node_type)| {
+            let position = (x + PIPELINE_NODE_SPACING * column, y);
+            column += 1.0;
+            self.node_counter += 1;
+            self.flowchart
+                .add_node(FlowchartNode::new(name.trim().to_string(), position, node_type))
+        });
+
+        let mut actions: Vec<UndoAction> = ids.iter().map(|&node_id| UndoAction::NodeCreated { node_id }).collect();
+        for pair in ids.windows(2) {
+            if self.flowchart.add_connection(pair[0], pair[1]).is_ok() {
+                actions.push(UndoAction::ConnectionCreated { from: pair[0], to: pair[1] });
+            }
+        }
+        actions.extend(self.make_room_for(&ids));
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+        crate::crash::record_event("Added pipeline");
+
+        self.interaction.selected_node = None;
+        self.interaction.selected_group = None;
+        self.interaction.selected_connection = None;
+        self.interaction.selected_nodes = ids.to_vec();
+        ids
+    }
+}
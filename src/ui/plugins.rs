@@ -0,0 +1,190 @@
+//! Plugin manager and plugin node creation.
+//!
+//! Plugins come from [`crate::plugins`]. This module reloads and installs
+//! them, creates nodes from them from the context menu and draws the plugin
+//! manager window.
+
+use super::state::{FlowchartApp, PendingLoadOperation};
+use crate::plugins::{Plugin, PluginRegistry};
+use crate::types::PluginRef;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Reloads the installed plugins from the plugins folder (desktop only).
+    pub fn reload_plugins(&mut self) {
+        let Some(dir) = crate::plugins::plugins_dir() else {
+            return;
+        };
+        let (registry, errors) = PluginRegistry::load_dir(&dir);
+        self.plugins = registry;
+        self.plugin_errors = errors;
+    }
+
+    /// Installs a single-file plugin, replacing any installed one with the same id.
+    ///
+    /// On the desktop the plugin is also copied into the plugins folder so it
+    /// is there on the next launch.
+    pub(crate) fn install_plugin(&mut self, manifest_json: &str) {
+        let plugin = match Plugin::from_manifest(manifest_json, None) {
+            Ok(plugin) => plugin,
+            Err(e) => {
+                self.plugin_errors.push(e);
+                return;
+            }
+        };
+        if let Some(dir) = crate::plugins::plugins_dir() {
+            let written = plugin.to_bundle_json().and_then(|json| {
+                std::fs::create_dir_all(&dir)
+                    .and_then(|_| std::fs::write(dir.join(format!("{}.json", plugin.manifest.id)), json))
+                    .map_err(|e| format!("Failed to copy plugin to {}: {}", dir.display(), e))
+            });
+            if let Err(e) = written {
+                self.plugin_errors.push(e);
+            }
+        }
+        crate::crash::record_event("Installed plugin");
+        self.plugins.insert(plugin);
+    }
+
+    /// Uninstalls a plugin. Existing nodes created from it keep working.
+    pub(crate) fn remove_plugin(&mut self, id: &str) {
+        if self.plugins.remove(id).is_none() {
+            return;
+        }
+        if let Some(dir) = crate::plugins::plugins_dir() {
+            let bundle = dir.join(format!("{}.json", id));
+            if bundle.is_file() {
+                let _ = std::fs::remove_file(bundle);
+            } else {
+                self.plugin_errors.push(format!(
+                    "Plugin \"{}\" was installed as a folder; delete it from {} to remove it permanently",
+                    id,
+                    dir.display()
+                ));
+            }
+        }
+    }
+
+    /// Creates a node from the plugin `id` at the context menu position.
+    pub(crate) fn create_plugin_node(&mut self, id: &str) {
+        let Some(plugin) = self.plugins.get(id).cloned() else {
+            return;
+        };
+        self.create_node_at_pos(plugin.node_type());
+        let Some(node_id) = self.interaction.selected_node else {
+            return;
+        };
+        if let Some(node) = self.flowchart.nodes.get_mut(&node_id) {
+            node.plugin = Some(plugin.node_ref());
+            node.name = format!("{} {}", plugin.manifest.name, self.node_counter);
+            self.interaction.temp_node_name = node.name.clone();
+        }
+    }
+
+    /// Shows which plugin a node came from and the properties it declares.
+    pub(crate) fn draw_plugin_info(&self, ui: &mut egui::Ui, plugin_ref: &PluginRef) {
+        let Some(plugin) = self.plugins.get(&plugin_ref.id) else {
+            ui.weak(format!("Plugin: {} (not installed)", plugin_ref.id));
+            return;
+        };
+        let manifest = &plugin.manifest;
+        ui.label(format!("Plugin: {}", format!("{} {}", manifest.icon, manifest.name).trim()));
+        if !manifest.description.is_empty() {
+            ui.weak(&manifest.description);
+        }
+        if !manifest.properties.is_empty() {
+            ui.weak("Properties (edit under Global State):");
+            for property in &manifest.properties {
+                let label = if property.label.is_empty() { &property.name } else { &property.label };
+                let response = ui.weak(format!("• {} ({})", label, property.name));
+                if !property.description.is_empty() {
+                    response.on_hover_text(&property.description);
+                }
+            }
+        }
+        if !plugin_ref.outputs {
+            ui.weak("This node has no outputs.");
+        }
+    }
+
+    /// Draws the plugin manager. Opens when `show_plugins_dialog` is true.
+    pub(crate) fn draw_plugins_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let mut remove = None;
+        let mut install = false;
+        let mut reload = false;
+
+        egui::Window::new("Plugins")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if self.plugins.is_empty() {
+                    ui.label("No plugins installed.");
+                } else {
+                    egui::Grid::new("plugins_grid").num_columns(3).show(ui, |ui| {
+                        for plugin in self.plugins.iter() {
+                            let manifest = &plugin.manifest;
+                            ui.label(&manifest.icon);
+                            let label = if manifest.version.is_empty() {
+                                manifest.name.clone()
+                            } else {
+                                format!("{} {}", manifest.name, manifest.version)
+                            };
+                            let response = ui.label(label);
+                            if !manifest.description.is_empty() {
+                                response.on_hover_text(&manifest.description);
+                            }
+                            if ui
+                                .button("✖")
+                                .on_hover_text("Uninstall; existing nodes keep their script")
+                                .clicked()
+                            {
+                                remove = Some(manifest.id.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+                }
+                ui.separator();
+                let plugins_dir = crate::plugins::plugins_dir();
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Install Plugin…")
+                        .on_hover_text("Pick a single-file plugin manifest with its script inline in \"source\"")
+                        .clicked()
+                    {
+                        install = true;
+                    }
+                    if plugins_dir.is_some() && ui.button("Reload").clicked() {
+                        reload = true;
+                    }
+                });
+                if let Some(dir) = &plugins_dir {
+                    ui.weak(format!("Plugins folder: {}", dir.display()));
+                }
+                if !self.plugin_errors.is_empty() {
+                    ui.separator();
+                    for error in &self.plugin_errors {
+                        ui.colored_label(egui::Color32::from_rgb(200, 80, 80), error);
+                    }
+                    if ui.button("Clear Messages").clicked() {
+                        self.plugin_errors.clear();
+                    }
+                }
+            });
+
+        if let Some(id) = remove {
+            self.remove_plugin(&id);
+        }
+        if install {
+            self.file.pending_load_operation = Some(PendingLoadOperation::Plugin);
+        }
+        if reload {
+            self.reload_plugins();
+        }
+        if !keep_open {
+            self.show_plugins_dialog = false;
+        }
+    }
+}
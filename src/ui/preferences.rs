@@ -0,0 +1,260 @@
+//! Preferences dialog.
+//!
+//! Holds the syntax highlighting palettes used by the script and JSON editors,
+//! the reduced-motion setting, the size of the events log and of the node
+//! message history, and which destructive actions ask for confirmation. Changes apply immediately and
+//! persist with the app state.
+
+use super::highlighters::{highlight_javascript, highlight_json, SyntaxPalette};
+use super::state::{ConfirmKind, FlowchartApp};
+use crate::constants::{
+    EVENT_LOG_MAX_CAPACITY, KEYBOARD_PAN_MAX_SPEED, MESSAGE_RENDER_MAX_LIMIT, MULTI_STEP_MAX_COUNT, NODE_HISTORY_MAX_LIMIT,
+    STEP_KEY_CHOICES, ZOOM_LIMIT_RANGE,
+};
+use eframe::egui;
+
+/// Snippet highlighted in the preferences preview.
+const PREVIEW_SCRIPT: &str = "// Double the value\nfunction transform(input) {\n    return { value: input.value * 2, ok: true };\n}";
+/// JSON snippet highlighted in the preferences preview.
+const PREVIEW_JSON: &str = "{\n    \"id\": 42,\n    \"status\": \"shipped\",\n    \"express\": false\n}";
+
+impl FlowchartApp {
+    /// Draws the preferences dialog. Opens when `show_preferences_dialog` is true.
+    ///
+    /// The palette for the current light/dark mode is shown for editing, so the
+    /// preview always reflects what the editors will look like.
+    pub(crate) fn draw_preferences_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let dark_mode = self.dark_mode;
+        egui::Window::new("Preferences")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.heading("Syntax Highlighting");
+                ui.label(if dark_mode {
+                    "Editing colors for dark mode"
+                } else {
+                    "Editing colors for light mode"
+                });
+
+                let palette = if dark_mode {
+                    &mut self.syntax_theme.dark
+                } else {
+                    &mut self.syntax_theme.light
+                };
+                egui::Grid::new("syntax_palette_grid").num_columns(2).show(ui, |ui| {
+                    for (label, rgb) in [
+                        ("Keywords", &mut palette.keyword),
+                        ("Strings", &mut palette.string),
+                        ("Comments", &mut palette.comment),
+                        ("Numbers", &mut palette.number),
+                        ("Function names", &mut palette.function),
+                        ("JSON keys", &mut palette.key),
+                        ("Other text", &mut palette.text),
+                    ] {
+                        ui.label(label);
+                        let mut color = [rgb.0, rgb.1, rgb.2];
+                        if ui.color_edit_button_srgb(&mut color).changed() {
+                            *rgb = (color[0], color[1], color[2]);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if ui.button("Reset to Defaults").clicked() {
+                    *palette = if dark_mode {
+                        SyntaxPalette::dark()
+                    } else {
+                        SyntaxPalette::light()
+                    };
+                }
+
+                ui.separator();
+                ui.label("Preview:");
+                let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+                egui::Frame::group(ui.style())
+                    .fill(ui.visuals().extreme_bg_color)
+                    .show(ui, |ui| {
+                        ui.label(highlight_javascript(PREVIEW_SCRIPT, font_id.clone(), palette));
+                        ui.separator();
+                        ui.label(highlight_json(PREVIEW_JSON, font_id, palette));
+                    });
+
+                ui.separator();
+                ui.heading("Motion");
+                ui.checkbox(&mut self.reduce_motion, "Reduce motion")
+                    .on_hover_text("Show error borders and trace highlights without flashing or pulsing");
+
+                ui.separator();
+                ui.heading("Layout");
+                ui.checkbox(&mut self.make_room_on_insert, "Make room for new nodes")
+                    .on_hover_text("Nudge nearby nodes apart when a node is created or a file is imported; pinned nodes stay put");
+
+                ui.separator();
+                ui.heading("Navigation");
+                ui.horizontal(|ui| {
+                    ui.label("Keyboard panning speed");
+                    ui.add(
+                        egui::DragValue::new(&mut self.keyboard_pan_speed)
+                            .range(50.0..=KEYBOARD_PAN_MAX_SPEED)
+                            .speed(10.0)
+                            .suffix(" pt/s"),
+                    )
+                    .on_hover_text("How fast the arrow keys and W/A/S/D pan the canvas");
+                });
+                let limits_changed = ui
+                    .horizontal(|ui| {
+                        ui.label("Zoom from");
+                        let (min, max) = &mut self.zoom_limits;
+                        let min_changed = ui
+                            .add(
+                                egui::DragValue::new(min)
+                                    .range(*ZOOM_LIMIT_RANGE.start()..=1.0)
+                                    .speed(0.01)
+                                    .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+                            )
+                            .changed();
+                        ui.label("to");
+                        let max_changed = ui
+                            .add(
+                                egui::DragValue::new(max)
+                                    .range(1.0..=*ZOOM_LIMIT_RANGE.end())
+                                    .speed(0.05)
+                                    .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+                            )
+                            .changed();
+                        min_changed || max_changed
+                    })
+                    .inner;
+                if limits_changed {
+                    // Bring the current zoom within the new limits
+                    self.zoom_around(self.canvas.zoom_factor, self.canvas.screen_rect.center());
+                }
+                ui.checkbox(&mut self.view_history_enabled, "Remember view history").on_hover_text(
+                    "Step back through pans and zooms with Cmd/Ctrl+Shift+Left, apart from undo",
+                );
+
+                ui.separator();
+                ui.heading("Stepping");
+                ui.horizontal(|ui| {
+                    ui.label("Step key");
+                    egui::ComboBox::from_id_salt("step_key_combo")
+                        .selected_text(self.step_key.name())
+                        .show_ui(ui, |ui| {
+                            for key in STEP_KEY_CHOICES {
+                                ui.selectable_value(&mut self.step_key, key, key.name());
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label(format!("Shift+{} advances", self.step_key.name()));
+                    ui.add(
+                        egui::DragValue::new(&mut self.multi_step_count)
+                            .range(2..=MULTI_STEP_MAX_COUNT)
+                            .suffix(" steps"),
+                    );
+                });
+
+                ui.separator();
+                ui.heading("Messages");
+                ui.horizontal(|ui| {
+                    ui.label("Show up to");
+                    ui.add(
+                        egui::DragValue::new(&mut self.message_render_limit)
+                            .range(10..=MESSAGE_RENDER_MAX_LIMIT)
+                            .speed(10.0)
+                            .suffix(" messages"),
+                    )
+                    .on_hover_text("Per connection on the canvas, and per page in its properties");
+                });
+
+                ui.separator();
+                ui.heading("Events Log");
+                ui.horizontal(|ui| {
+                    ui.label("Keep the last");
+                    let response = ui.add(
+                        egui::DragValue::new(&mut self.event_log_capacity)
+                            .range(100..=EVENT_LOG_MAX_CAPACITY)
+                            .speed(100.0)
+                            .suffix(" events"),
+                    );
+                    if response.changed() {
+                        self.simulation_engine.events_mut().set_capacity(self.event_log_capacity);
+                    }
+                });
+
+                ui.separator();
+                ui.heading("Message History");
+                ui.horizontal(|ui| {
+                    ui.label("Keep the last");
+                    let response = ui.add(
+                        egui::DragValue::new(&mut self.history_memory_limit)
+                            .range(10..=NODE_HISTORY_MAX_LIMIT)
+                            .speed(10.0)
+                            .suffix(" messages"),
+                    )
+                    .on_hover_text("Per node in memory, for the node history inspector");
+                    if response.changed() {
+                        self.apply_history_retention();
+                    }
+                });
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui
+                    .checkbox(&mut self.history_spill_to_disk, "Move older messages to a temporary file")
+                    .on_hover_text("Keeps them out of memory on long runs; the file is deleted on exit")
+                    .changed()
+                {
+                    self.apply_history_retention();
+                }
+
+                ui.separator();
+                ui.heading("Edit History");
+                ui.checkbox(&mut self.record_edit_log, "Record edits in the document")
+                    .on_hover_text("Saved with the document and listed in Edit → Edit History");
+                ui.horizontal(|ui| {
+                    ui.label("Record them as");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.edit_log_author)
+                            .hint_text("your name")
+                            .desired_width(160.0),
+                    );
+                });
+
+                ui.separator();
+                ui.heading("Confirmations");
+                ui.label("Ask before:");
+                for kind in ConfirmKind::ALL {
+                    let mut asks = self.confirmations.asks(kind);
+                    if ui.checkbox(&mut asks, kind.label()).changed() {
+                        self.confirmations.set_asks(kind, asks);
+                    }
+                }
+            });
+        if !keep_open {
+            self.show_preferences_dialog = false;
+        }
+    }
+
+    /// Handles Cmd/Ctrl+, (open the preferences).
+    pub(crate) fn handle_preferences_shortcut(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::Comma)) {
+            self.show_preferences_dialog = true;
+        }
+    }
+
+    /// Applies the message history preferences to the simulation engine.
+    ///
+    /// If the temporary file can't be created, spilling is turned off again
+    /// and an error is shown.
+    pub(crate) fn apply_history_retention(&mut self) {
+        self.simulation_engine.history_mut().set_limit(self.history_memory_limit);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(error) = self.simulation_engine.history_mut().set_spill_to_disk(self.history_spill_to_disk) {
+            self.history_spill_to_disk = false;
+            self.dialogs.error("Message History", error);
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Run recordings.
+//!
+//! Recording captures the canvas, steps the simulation, and repeats for the
+//! chosen number of steps, so the frames show messages moving through the
+//! model exactly as they look on screen. Each frame is a screenshot of the
+//! canvas area; the frames are then saved as an animated GIF or as a ZIP of
+//! numbered PNGs for video tools. Both encoders are pure Rust and work in the
+//! browser too.
+
+use super::snapshot::Snapshot;
+use super::state::{FlowchartApp, RecordingFormat, RecordingOptions, RunRecording};
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Pauses the simulation and starts recording with `recording_options`.
+    pub(crate) fn start_recording(&mut self) {
+        if self.is_simulation_running {
+            self.pause_simulation();
+        }
+        crate::crash::record_event("Run recording started");
+        self.recording = Some(RunRecording {
+            options: self.recording_options,
+            frames: Vec::new(),
+            awaiting_frame: false,
+        });
+    }
+
+    /// Collects the screenshot of the last frame and steps the simulation
+    /// for the next one; called at the start of every frame.
+    pub(crate) fn advance_recording(&mut self, ctx: &egui::Context) {
+        let Some(recording) = self.recording.as_mut() else {
+            return;
+        };
+        if recording.awaiting_frame {
+            let screenshot = ctx.input(|i| {
+                i.raw.events.iter().find_map(|event| match event {
+                    egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                    _ => None,
+                })
+            });
+            let Some(image) = screenshot else {
+                ctx.request_repaint();
+                return;
+            };
+            recording.frames.push(crop_to_canvas(&image, self.canvas.screen_rect, ctx.pixels_per_point()));
+            recording.awaiting_frame = false;
+            if recording.frames.len() > recording.options.steps as usize {
+                self.finish_recording();
+                return;
+            }
+        }
+
+        // The first frame shows the model before the recorded steps
+        if !recording.frames.is_empty() {
+            self.step_simulation();
+        }
+        if let Some(recording) = self.recording.as_mut() {
+            recording.awaiting_frame = true;
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(egui::UserData::default()));
+        ctx.request_repaint();
+    }
+
+    /// Ends the recording and saves the frames captured so far.
+    pub(crate) fn finish_recording(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+        if recording.frames.is_empty() {
+            return;
+        }
+        crate::crash::record_event("Run recording saved");
+        Self::save_recording(recording.frames, recording.options);
+    }
+
+    /// Encodes `frames` and saves them through a save dialog (native) or as
+    /// a download (web).
+    fn save_recording(frames: Vec<Snapshot>, options: RecordingOptions) {
+        let file_name = format!("flowchart-run.{}", options.format.extension());
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let result = encode_recording(&frames, options)
+                .and_then(|bytes| Self::trigger_download_bytes(&file_name, &bytes));
+            if let Err(e) = result {
+                eprintln!("Failed to save recording: {}", e);
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            tokio::spawn(async move {
+                if let Some(handle) = rfd::AsyncFileDialog::new()
+                    .add_filter(options.format.label(), &[options.format.extension()])
+                    .set_file_name(&file_name)
+                    .save_file()
+                    .await
+                {
+                    let result = encode_recording(&frames, options)
+                        .and_then(|bytes| std::fs::write(handle.path(), bytes).map_err(|e| e.to_string()));
+                    if let Err(e) = result {
+                        eprintln!("Failed to save recording: {}", e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Draws the recording dialog. Opens when `show_recording_dialog` is true.
+    pub(crate) fn draw_recording_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let mut start = false;
+        let mut stop = false;
+        let mut discard = false;
+
+        egui::Window::new("Record Run")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if let Some(recording) = &self.recording {
+                    let captured = recording.frames.len().saturating_sub(1);
+                    ui.label(format!("Recording step {} of {}…", captured, recording.options.steps));
+                    ui.add(egui::ProgressBar::new(captured as f32 / recording.options.steps.max(1) as f32));
+                    ui.horizontal(|ui| {
+                        if ui.button("Stop and Save").clicked() {
+                            stop = true;
+                        }
+                        if ui.button("Discard").clicked() {
+                            discard = true;
+                        }
+                    });
+                    return;
+                }
+
+                let options = &mut self.recording_options;
+                ui.label("Captures the canvas once per step, starting from the current step.");
+                egui::Grid::new("recording_options").num_columns(2).show(ui, |ui| {
+                    ui.label("Steps:");
+                    ui.add(egui::DragValue::new(&mut options.steps).range(1..=1000));
+                    ui.end_row();
+                    ui.label("Frame delay:");
+                    ui.add(egui::DragValue::new(&mut options.frame_delay_ms).range(20..=5000).suffix(" ms"))
+                        .on_hover_text("How long each frame is shown in the GIF");
+                    ui.end_row();
+                    ui.label("Format:");
+                    egui::ComboBox::from_id_salt("recording_format")
+                        .selected_text(options.format.label())
+                        .show_ui(ui, |ui| {
+                            for format in [RecordingFormat::Gif, RecordingFormat::PngFrames] {
+                                ui.selectable_value(&mut options.format, format, format.label());
+                            }
+                        });
+                    ui.end_row();
+                });
+                ui.weak("Keep the window still while recording; the frames are the canvas as drawn.");
+                if ui.button("Start Recording").clicked() {
+                    start = true;
+                }
+            });
+
+        if start {
+            self.start_recording();
+        } else if stop {
+            self.finish_recording();
+        } else if discard {
+            self.recording = None;
+        }
+        if !keep_open {
+            self.show_recording_dialog = false;
+            self.recording = None;
+        }
+    }
+}
+
+/// The part of the screenshot `image` covered by `canvas` (in points).
+fn crop_to_canvas(image: &egui::ColorImage, canvas: egui::Rect, pixels_per_point: f32) -> Snapshot {
+    let [image_width, image_height] = image.size;
+    let full = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(image_width as f32, image_height as f32));
+    let mut area = if canvas.is_positive() {
+        egui::Rect::from_min_max(
+            (canvas.min.to_vec2() * pixels_per_point).round().to_pos2(),
+            (canvas.max.to_vec2() * pixels_per_point).round().to_pos2(),
+        )
+        .intersect(full)
+    } else {
+        full
+    };
+    if !area.is_positive() {
+        area = full;
+    }
+
+    let (x0, y0) = (area.min.x as usize, area.min.y as usize);
+    let (width, height) = (area.width() as usize, area.height() as usize);
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for y in y0..y0 + height {
+        for color in &image.pixels[y * image_width + x0..y * image_width + x0 + width] {
+            pixels.extend_from_slice(&color.to_srgba_unmultiplied());
+        }
+    }
+    Snapshot {
+        width: width as u32,
+        height: height as u32,
+        pixels,
+    }
+}
+
+/// Encodes `frames` in the recording's format.
+fn encode_recording(frames: &[Snapshot], options: RecordingOptions) -> Result<Vec<u8>, String> {
+    match options.format {
+        RecordingFormat::Gif => encode_gif(frames, options.frame_delay_ms),
+        RecordingFormat::PngFrames => {
+            let files = frames
+                .iter()
+                .enumerate()
+                .map(|(index, frame)| Ok((format!("frame_{:04}.png", index + 1), encode_png(frame)?)))
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(stored_zip(&files))
+        }
+    }
+}
+
+/// Encodes `frames` as a looping GIF showing each for `frame_delay_ms`.
+///
+/// Frames of a different size than the first (the window was resized while
+/// recording) are left out.
+fn encode_gif(frames: &[Snapshot], frame_delay_ms: u32) -> Result<Vec<u8>, String> {
+    let first = frames.first().ok_or("Nothing was recorded")?;
+    let width = u16::try_from(first.width).map_err(|_| "Recording is too wide for a GIF".to_string())?;
+    let height = u16::try_from(first.height).map_err(|_| "Recording is too tall for a GIF".to_string())?;
+    let delay = (frame_delay_ms / 10).clamp(1, u16::MAX as u32) as u16;
+
+    let mut out = Vec::new();
+    {
+        let mut encoder =
+            gif::Encoder::new(&mut out, width, height, &[]).map_err(|e| format!("Failed to encode GIF: {}", e))?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|e| format!("Failed to encode GIF: {}", e))?;
+        for snapshot in frames.iter().filter(|f| f.width == first.width && f.height == first.height) {
+            let mut pixels = snapshot.pixels.clone();
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+            frame.delay = delay;
+            encoder
+                .write_frame(&frame)
+                .map_err(|e| format!("Failed to encode GIF: {}", e))?;
+        }
+    }
+    Ok(out)
+}
+
+/// Encodes one frame as a PNG file.
+fn encode_png(frame: &Snapshot) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, frame.width, frame.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    writer
+        .write_image_data(&frame.pixels)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    writer.finish().map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    Ok(out)
+}
+
+/// A ZIP archive holding `files` without compression (PNGs are compressed
+/// already), dated 1980-01-01.
+fn stored_zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    const DOS_DATE: u16 = 0x21;
+    let mut out = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in files {
+        let offset = out.len() as u32;
+        let crc = crc32(data);
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        common.extend_from_slice(&0u16.to_le_bytes()); // flags
+        common.extend_from_slice(&0u16.to_le_bytes()); // stored
+        common.extend_from_slice(&0u16.to_le_bytes()); // time
+        common.extend_from_slice(&DOS_DATE.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        out.extend_from_slice(&common);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        directory.extend_from_slice(&common);
+        directory.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        directory.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        directory.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        directory.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let directory_offset = out.len() as u32;
+    out.extend_from_slice(&directory);
+    out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // this disk
+    out.extend_from_slice(&0u16.to_le_bytes()); // directory disk
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    out.extend_from_slice(&directory_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    out
+}
+
+/// CRC-32 (IEEE) of `data`, as ZIP archives store it.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(shade: u8) -> Snapshot {
+        Snapshot {
+            width: 4,
+            height: 3,
+            pixels: [shade, 0, 255 - shade, 255].repeat(12),
+        }
+    }
+
+    #[test]
+    fn test_gif_holds_every_frame() {
+        let frames = vec![frame(0), frame(128), frame(255)];
+        let bytes = encode_gif(&frames, 200).unwrap();
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(bytes.as_slice()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (4, 3));
+        let mut count = 0;
+        while let Some(decoded) = decoder.read_next_frame().unwrap() {
+            assert_eq!(decoded.delay, 20);
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        assert!(encode_gif(&[], 200).is_err());
+    }
+
+    #[test]
+    fn test_png_frames_zip() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        let options = RecordingOptions {
+            format: RecordingFormat::PngFrames,
+            ..Default::default()
+        };
+        let bytes = encode_recording(&[frame(0), frame(255)], options).unwrap();
+        assert_eq!(&bytes[..4], &[0x50, 0x4b, 0x03, 0x04]);
+        assert_eq!(&bytes[30..44], b"frame_0001.png");
+        // PNG signature right after the first local header
+        assert_eq!(&bytes[44..48], &[0x89, b'P', b'N', b'G']);
+        let end = &bytes[bytes.len() - 22..];
+        assert_eq!(&end[..4], &[0x50, 0x4b, 0x05, 0x06]);
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 2);
+    }
+}
@@ -0,0 +1,192 @@
+//! Crash boundary and recovery dialog.
+//!
+//! When a frame panics the app writes a [`CrashBundle`] (see [`crate::crash`])
+//! before the panic continues. The next launch shows the recovery dialog,
+//! which restores the emergency copy of the document and offers the bug
+//! report for copying.
+//!
+//! If the stored app state itself keeps the app from starting, a safe-mode
+//! launch (`--safe-mode`, or `?safe_mode` in the page URL on the web) starts
+//! from defaults without plugins, crash recovery or reopening the last
+//! document. Its settings replace the stored ones when the app closes.
+
+use super::state::FlowchartApp;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::analysis::analyze;
+use crate::crash;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::crash::CrashBundle;
+use crate::types::Flowchart;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// A fresh app for a safe-mode launch, showing the safe-mode banner.
+    ///
+    /// The caller skips restoring the stored state, loading plugins and
+    /// reopening documents.
+    pub fn safe_mode() -> Self {
+        crash::record_event("Started in safe mode");
+        Self {
+            safe_mode: true,
+            ..Default::default()
+        }
+    }
+
+    /// Draws the banner explaining safe mode, until it is dismissed.
+    pub(crate) fn draw_safe_mode_banner(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("safe_mode_banner").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.colored_label(egui::Color32::from_rgb(230, 150, 60), "⚠ Safe mode");
+                ui.label(
+                    "Saved settings, plugins and the last document were not loaded. \
+                     The settings of this session replace the saved ones when the app closes; \
+                     restart normally to load plugins again.",
+                );
+                if ui.button("Dismiss").clicked() {
+                    self.safe_mode = false;
+                }
+            });
+        });
+    }
+
+    /// Describes the app state for a bug report without any document content.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn anonymized_state_summary(&self) -> String {
+        let stats = analyze(&self.flowchart);
+        let document = match self.file.current_path.as_deref() {
+            None => "untitled",
+            Some(p) if p.starts_with("Example: ") => "example",
+            Some(p) if p.starts_with(crate::constants::BROWSER_DOCUMENT_PREFIX) => "browser document",
+            Some(_) => "file",
+        };
+        format!(
+            "Nodes: {} producer(s), {} consumer(s), {} transformer(s), {} reference(s)\n\
+             Connections: {}, groups: {}, lanes: {}\n\
+             Messages in flight: {}\n\
+             Simulation: {:?} at step {}\n\
+             Undo entries: {}\n\
+             Selected nodes: {}\n\
+             Document: {}, unsaved changes: {}",
+            stats.producers,
+            stats.consumers,
+            stats.transformers,
+            stats.references,
+            stats.connections,
+            stats.groups,
+            self.flowchart.swimlanes.lanes.len(),
+            self.flowchart.connections.iter().map(|c| c.messages.len()).sum::<usize>(),
+            self.flowchart.simulation_state,
+            self.flowchart.current_step,
+            self.undo_history.undo_len(),
+            self.selected_node_ids().len(),
+            document,
+            self.file.has_unsaved_changes_effective(),
+        )
+    }
+
+    /// Runs `draw` and, if it panics, saves a crash bundle before the panic continues.
+    pub(crate) fn with_crash_boundary(&mut self, draw: impl FnOnce(&mut Self)) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| draw(self)));
+            if let Err(payload) = result {
+                self.write_crash_bundle();
+                std::panic::resume_unwind(payload);
+            }
+        }
+        // Panics abort on the web, so there is nothing to catch
+        #[cfg(target_arch = "wasm32")]
+        draw(self);
+    }
+
+    /// Writes the emergency document copy and bug report to the recovery directory.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_crash_bundle(&self) {
+        let Some(dir) = crash::recovery_dir() else {
+            return;
+        };
+        let bundle = CrashBundle {
+            document: self.flowchart.to_json().unwrap_or_default(),
+            document_path: self.file.current_path.clone(),
+            report: crash::build_report(&self.anonymized_state_summary()),
+        };
+        match crash::write_bundle(&dir, &bundle) {
+            Ok(()) => eprintln!("Saved crash recovery data to {}", dir.display()),
+            Err(e) => eprintln!("Failed to save crash recovery data: {}", e),
+        }
+    }
+
+    /// Picks up the bundle left by a crashed session, if any.
+    pub fn check_for_crash_recovery(&mut self) {
+        self.pending_recovery = crash::recovery_dir().and_then(|dir| crash::read_bundle(&dir));
+    }
+
+    /// Restores the document from the pending crash bundle as unsaved changes.
+    pub(crate) fn restore_crashed_document(&mut self) {
+        let Some(bundle) = self.pending_recovery.take() else {
+            return;
+        };
+        match Flowchart::from_json(&bundle.document) {
+            Ok(flowchart) => {
+                self.apply_loaded_flowchart_from_source(flowchart, bundle.document_path);
+                self.file.has_unsaved_changes = true;
+                crash::record_event("Restored document after crash");
+            }
+            Err(e) => {
+                eprintln!("Failed to parse recovered flowchart: {}", e);
+            }
+        }
+        Self::clear_crash_bundle();
+    }
+
+    /// Deletes the bundle from disk.
+    fn clear_crash_bundle() {
+        if let Some(dir) = crash::recovery_dir() {
+            crash::clear_bundle(&dir);
+        }
+    }
+
+    /// Draws the recovery dialog while a crash bundle is pending.
+    pub(crate) fn draw_recovery_dialog(&mut self, ctx: &egui::Context) {
+        let Some(bundle) = &self.pending_recovery else {
+            return;
+        };
+        let mut restore = false;
+        let mut discard = false;
+        egui::Window::new("Recover Unsaved Work")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label("Flowchart Tool closed unexpectedly last time. A copy of the open document was saved.");
+                if let Some(path) = &bundle.document_path {
+                    ui.weak(format!("Document: {}", path));
+                }
+                ui.collapsing("Bug report", |ui| {
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        ui.monospace(&bundle.report);
+                    });
+                    ui.weak("The report describes the app state but contains none of your document.");
+                    if ui.button("Copy Bug Report").clicked() {
+                        ctx.copy_text(bundle.report.clone());
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Restore Document").clicked() {
+                        restore = true;
+                    }
+                    if ui.button("Discard").clicked() {
+                        discard = true;
+                    }
+                });
+            });
+
+        if restore {
+            self.restore_crashed_document();
+        } else if discard {
+            self.pending_recovery = None;
+            Self::clear_crash_bundle();
+        }
+    }
+}
@@ -3,13 +3,101 @@
 //! This module handles all drawing operations including grid background,
 //! connection lines with arrows and messages, and node visualization.
 
+use super::formatting::NodeFormat;
 use super::highlighters;
-use super::state::FlowchartApp;
+use super::state::{FlowchartApp, GridStyle};
 use crate::types::*;
 use eframe::egui;
 use eframe::epaint::StrokeKind;
 
+/// Straightens a connection from `start` to `end` when it is within
+/// `tolerance` of horizontal or vertical, by moving both ends to their
+/// shared middle line.
+pub(crate) fn snap_orthogonal(start: egui::Pos2, end: egui::Pos2, tolerance: f32) -> (egui::Pos2, egui::Pos2) {
+    let delta = end - start;
+    if delta.y.abs() <= tolerance && delta.x.abs() > delta.y.abs() {
+        let y = (start.y + end.y) / 2.0;
+        (egui::pos2(start.x, y), egui::pos2(end.x, y))
+    } else if delta.x.abs() <= tolerance && delta.y.abs() > delta.x.abs() {
+        let x = (start.x + end.x) / 2.0;
+        (egui::pos2(x, start.y), egui::pos2(x, end.y))
+    } else {
+        (start, end)
+    }
+}
+
+/// Where the line from `inside` towards `toward` crosses the border of
+/// `rect`. `inside` itself when it lies outside `rect` or `toward` lies
+/// inside it, as there is no crossing to find.
+pub(crate) fn border_point(rect: egui::Rect, inside: egui::Pos2, toward: egui::Pos2) -> egui::Pos2 {
+    if !rect.contains(inside) || rect.contains(toward) {
+        return inside;
+    }
+    let delta = toward - inside;
+    let exit = |from: f32, d: f32, min: f32, max: f32| {
+        if d > 0.0 {
+            (max - from) / d
+        } else if d < 0.0 {
+            (min - from) / d
+        } else {
+            f32::INFINITY
+        }
+    };
+    let t = exit(inside.x, delta.x, rect.min.x, rect.max.x)
+        .min(exit(inside.y, delta.y, rect.min.y, rect.max.y))
+        .min(1.0);
+    inside + delta * t
+}
+
+/// World-space rectangle of a node at `position`.
+fn node_world_rect(position: (f32, f32)) -> egui::Rect {
+    let size = egui::vec2(crate::constants::NODE_WIDTH, crate::constants::NODE_HEIGHT);
+    egui::Rect::from_center_size(egui::pos2(position.0, position.1), size)
+}
+
+/// Moves the ends of the line from `start` to `end` out to the borders of
+/// the nodes at `from` and `to`. Overlapping nodes have no line between
+/// their borders and keep the ends as given.
+fn border_anchors(from: (f32, f32), to: (f32, f32), start: egui::Pos2, end: egui::Pos2) -> (egui::Pos2, egui::Pos2) {
+    let (from_rect, to_rect) = (node_world_rect(from), node_world_rect(to));
+    if from_rect.intersects(to_rect) {
+        return (start, end);
+    }
+    (border_point(from_rect, start, end), border_point(to_rect, end, start))
+}
+
+/// Color and width of a connection line drawn with `style`. Selected
+/// connections are highlighted in blue and a little thicker.
+fn connection_stroke(style: &LineStyle, is_selected: bool) -> (egui::Color32, f32) {
+    if is_selected {
+        (egui::Color32::from_rgb(100, 150, 255), style.width + 1.0)
+    } else {
+        let color = style
+            .color
+            .map_or(egui::Color32::DARK_GRAY, |[r, g, b]| egui::Color32::from_rgb(r, g, b));
+        (color, style.width)
+    }
+}
+
 impl FlowchartApp {
+    /// World-space ends of the connection from `from` to `to`, straightened
+    /// when `snap_connections` is on. None if either node is missing.
+    ///
+    /// The ends sit where the line between the nodes crosses their borders,
+    /// so arrows meet the node they point at instead of disappearing under
+    /// it. Overlapping nodes connect their centres.
+    pub(crate) fn connection_endpoints(&self, from: NodeId, to: NodeId) -> Option<(egui::Pos2, egui::Pos2)> {
+        let (a, b) = (self.flowchart.nodes.get(&from)?, self.flowchart.nodes.get(&to)?);
+        let start = egui::pos2(a.position.0, a.position.1);
+        let end = egui::pos2(b.position.0, b.position.1);
+        let (start, end) = if self.canvas.snap_connections {
+            snap_orthogonal(start, end, crate::constants::ORTHOGONAL_SNAP_TOLERANCE)
+        } else {
+            (start, end)
+        };
+        Some(border_anchors(a.position, b.position, start, end))
+    }
+
     /// Renders all flowchart elements (grid, connections, and nodes) on the canvas.
     ///
     /// Elements are drawn in layers: grid first (background), then connections,
@@ -20,13 +108,28 @@ impl FlowchartApp {
     /// * `painter` - The egui painter for drawing operations
     /// * `canvas_rect` - The screen-space rectangle of the canvas area
     pub fn render_flowchart_elements(&self, painter: &egui::Painter, canvas_rect: egui::Rect) {
+        // Fill a custom background color before anything else
+        if let Some((r, g, b)) = self.canvas.background_color {
+            painter.rect_filled(canvas_rect, 0.0, egui::Color32::from_rgb(r, g, b));
+        }
+
         // Draw grid first (behind everything) if enabled
         if self.canvas.show_grid {
             self.draw_grid(painter, canvas_rect);
         }
 
+        if self.canvas.show_origin_marker {
+            self.draw_origin_marker(painter, canvas_rect);
+        }
+
+        // Swimlane bands sit behind groups, connections and nodes
+        if self.flowchart.swimlanes.enabled {
+            self.draw_swimlane_bands(painter, canvas_rect);
+        }
+
         // Draw group background shapes behind connections and nodes and render the group name
-        for (gid, group) in &self.flowchart.groups {
+        for group in self.flowchart.groups_by_z_order() {
+            let gid = &group.id;
             let is_selected = self.interaction.selected_group == Some(*gid);
             let fill = if is_selected {
                 egui::Color32::from_rgba_unmultiplied(100, 150, 255, 32)
@@ -108,6 +211,10 @@ impl FlowchartApp {
         // Draw connection arrowheads at the same layer as the connection lines (below nodes)
         self.draw_connection_arrows_overlay(painter);
 
+        // Highlight the traced message's path between connections and nodes
+        let trace_hops = self.traced_hops();
+        self.draw_trace_edges(painter, &trace_hops);
+
         // Draw connection preview if currently drawing
         if let Some(from_node_id) = self.interaction.drawing_connection_from {
             if let Some(draw_pos) = self.interaction.connection_draw_pos {
@@ -115,10 +222,22 @@ impl FlowchartApp {
             }
         }
 
+        // Draw the copy being Alt-dragged out of a node
+        if let (Some(from_node_id), Some(drop_pos)) =
+            (self.interaction.duplicating_from, self.interaction.duplicate_drop_pos)
+        {
+            self.draw_duplicate_preview(painter, from_node_id, drop_pos);
+        }
+
         // Draw nodes on top (above connections and their arrowheads)
-        for node in self.flowchart.nodes.values() {
-            self.draw_node(painter, node);
+        let formats = self.node_formats();
+        for node in self.flowchart.nodes_by_z_order() {
+            self.draw_node(painter, node, formats.get(&node.id));
         }
+        self.draw_trace_nodes(painter, &trace_hops);
+
+        // Proposed positions of a layout preview
+        self.draw_layout_ghosts(painter);
 
         // Draw marquee selection rectangle if active
         if let (Some(start), Some(end)) =
@@ -180,8 +299,9 @@ impl FlowchartApp {
     }
 
     /// Draws a zoom-aware grid on the canvas for visual reference.
-
-    /// Draws a zoom-aware grid on the canvas for visual reference.
+    ///
+    /// The grid is drawn as lines or as dots at each intersection depending on
+    /// `CanvasState::grid_style`.
     ///
     /// Grid lines are drawn every 20 world units. The grid automatically adjusts
     /// for zoom level and only draws when the grid spacing is visible.
@@ -213,6 +333,24 @@ impl FlowchartApp {
             return;
         }
 
+        if self.canvas.grid_style == GridStyle::Dots {
+            let dot_color = egui::Color32::from_rgba_unmultiplied(128, 128, 128, 90);
+            let radius = (self.canvas.zoom_factor * 1.2).clamp(0.75, 2.0);
+            let mut x = start_x;
+            while x <= end_x {
+                let mut y = start_y;
+                while y <= end_y {
+                    let screen = self.world_to_screen(egui::pos2(x, y));
+                    if canvas_rect.contains(screen) {
+                        painter.circle_filled(screen, radius, dot_color);
+                    }
+                    y += grid_size;
+                }
+                x += grid_size;
+            }
+            return;
+        }
+
         // Draw vertical grid lines
         let mut x = start_x;
         while x <= end_x {
@@ -280,6 +418,31 @@ impl FlowchartApp {
         }
     }
 
+    /// Draws a crosshair marking the world origin (0, 0), if it is on screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `painter` - The egui painter for drawing operations
+    /// * `canvas_rect` - The screen-space rectangle defining visible area
+    pub fn draw_origin_marker(&self, painter: &egui::Painter, canvas_rect: egui::Rect) {
+        let origin = self.world_to_screen(egui::pos2(0.0, 0.0));
+        let size = crate::constants::ORIGIN_MARKER_SIZE;
+        if !canvas_rect.expand(size).contains(origin) {
+            return;
+        }
+
+        let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(220, 80, 80));
+        painter.line_segment(
+            [origin - egui::vec2(size, 0.0), origin + egui::vec2(size, 0.0)],
+            stroke,
+        );
+        painter.line_segment(
+            [origin - egui::vec2(0.0, size), origin + egui::vec2(0.0, size)],
+            stroke,
+        );
+        painter.circle_stroke(origin, size * 0.35, stroke);
+    }
+
     /// Renders a connection between two nodes with animated messages and directional arrow.
     ///
     /// Connections are drawn as lines with arrows indicating direction. Messages in
@@ -297,34 +460,28 @@ impl FlowchartApp {
         is_selected: bool,
     ) {
         // Get node positions with zoom and canvas offset applied
-        let start_world = self
-            .flowchart
-            .nodes
-            .get(&connection.from)
-            .map(|n| egui::pos2(n.position.0, n.position.1))
-            .unwrap_or_else(|| egui::pos2(0.0, 0.0));
+        let (start_world, end_world) = self
+            .connection_endpoints(connection.from, connection.to)
+            .unwrap_or((egui::pos2(0.0, 0.0), egui::pos2(100.0, 100.0)));
         let start_pos = self.world_to_screen(start_world);
-
-        let end_world = self
-            .flowchart
-            .nodes
-            .get(&connection.to)
-            .map(|n| egui::pos2(n.position.0, n.position.1))
-            .unwrap_or_else(|| egui::pos2(100.0, 100.0));
         let end_pos = self.world_to_screen(end_world);
-
-        // Choose color and width based on selection
-        let (line_color, line_width) = if is_selected {
-            (egui::Color32::from_rgb(100, 150, 255), 3.0)
-        } else {
-            (egui::Color32::DARK_GRAY, 2.0)
-        };
-
-        // Draw the connection line
-        painter.line_segment(
-            [start_pos, end_pos],
-            egui::Stroke::new(line_width, line_color),
-        );
+        let (line_color, line_width) = connection_stroke(&connection.style, is_selected);
+
+        // Draw the connection line in its dash pattern
+        let path = [start_pos, end_pos];
+        let stroke = egui::Stroke::new(line_width, line_color);
+        match connection.style.dash {
+            LineDash::Solid => {
+                painter.line_segment(path, stroke);
+            }
+            LineDash::Dashed => {
+                let dash = (4.0 * line_width).max(6.0);
+                painter.extend(egui::Shape::dashed_line(&path, stroke, dash, dash * 0.6));
+            }
+            LineDash::Dotted => {
+                painter.extend(egui::Shape::dotted_line(&path, line_color, (2.5 * line_width).max(4.0), line_width * 0.6));
+            }
+        }
 
         // Draw messages as a grid next to the arrow
         if !connection.messages.is_empty() {
@@ -332,9 +489,10 @@ impl FlowchartApp {
         }
     }
 
-    /// Draws a directional arrow at the center of a connection line.
+    /// Draws a directional arrow whose tip touches the end of a connection line.
     ///
-    /// The arrow is rendered as a filled triangle pointing from source to destination.
+    /// The arrow is rendered as a filled triangle or an open chevron pointing
+    /// from source to destination, or not at all for [`ArrowHead::None`].
     /// Arrow size scales with the current zoom level.
     ///
     /// # Arguments
@@ -343,16 +501,17 @@ impl FlowchartApp {
     /// * `start` - Start position of the connection in screen space
     /// * `end` - End position of the connection in screen space
     /// * `color` - Color for the arrow
-    fn draw_arrow_at_center(
+    /// * `head` - Shape of the arrow
+    /// * `line_width` - Width of the connection line, used for open arrows
+    fn draw_arrowhead(
         &self,
         painter: &egui::Painter,
         start: egui::Pos2,
         end: egui::Pos2,
         color: egui::Color32,
+        head: ArrowHead,
+        line_width: f32,
     ) {
-        // Calculate center point
-        let center = start + (end - start) * 0.5;
-
         // Calculate direction vector
         let direction = (end - start).normalized();
 
@@ -360,6 +519,9 @@ impl FlowchartApp {
         let arrow_size = 8.0 * self.canvas.zoom_factor;
         let arrow_width = 6.0 * self.canvas.zoom_factor;
 
+        // Center the triangle one arrow length back from the end
+        let center = end - direction * arrow_size;
+
         // Calculate perpendicular vector for arrow wings
         let perpendicular = egui::vec2(-direction.y, direction.x);
 
@@ -368,12 +530,22 @@ impl FlowchartApp {
         let arrow_left = center - direction * arrow_size + perpendicular * arrow_width;
         let arrow_right = center - direction * arrow_size - perpendicular * arrow_width;
 
-        // Draw filled triangle
-        painter.add(egui::Shape::convex_polygon(
-            vec![arrow_tip, arrow_left, arrow_right],
-            color,
-            egui::Stroke::NONE,
-        ));
+        match head {
+            ArrowHead::Filled => {
+                painter.add(egui::Shape::convex_polygon(
+                    vec![arrow_tip, arrow_left, arrow_right],
+                    color,
+                    egui::Stroke::NONE,
+                ));
+            }
+            ArrowHead::Open => {
+                painter.add(egui::Shape::line(
+                    vec![arrow_left, arrow_tip, arrow_right],
+                    egui::Stroke::new(line_width, color),
+                ));
+            }
+            ArrowHead::None => {}
+        }
     }
 
     /// Draws all connection arrowheads in an overlay pass so they are not occluded by nodes.
@@ -382,39 +554,27 @@ impl FlowchartApp {
     pub fn draw_connection_arrows_overlay(&self, painter: &egui::Painter) {
         for (idx, connection) in self.flowchart.connections.iter().enumerate() {
             // Compute start/end positions in screen space
-            let start_world = self
-                .flowchart
-                .nodes
-                .get(&connection.from)
-                .map(|n| egui::pos2(n.position.0, n.position.1))
-                .unwrap_or_else(|| egui::pos2(0.0, 0.0));
+            let (start_world, end_world) = self
+                .connection_endpoints(connection.from, connection.to)
+                .unwrap_or((egui::pos2(0.0, 0.0), egui::pos2(100.0, 100.0)));
             let start_pos = self.world_to_screen(start_world);
-
-            let end_world = self
-                .flowchart
-                .nodes
-                .get(&connection.to)
-                .map(|n| egui::pos2(n.position.0, n.position.1))
-                .unwrap_or_else(|| egui::pos2(100.0, 100.0));
             let end_pos = self.world_to_screen(end_world);
 
             // Match connection color/width (selected vs normal)
-            let (line_color, _line_width) = if self.interaction.selected_connection == Some(idx) {
-                (egui::Color32::from_rgb(100, 150, 255), 3.0)
-            } else {
-                (egui::Color32::DARK_GRAY, 2.0)
-            };
+            let is_selected = self.interaction.selected_connection == Some(idx);
+            let (line_color, line_width) = connection_stroke(&connection.style, is_selected);
 
-            // Draw arrow at the center (overlay, above nodes)
-            self.draw_arrow_at_center(painter, start_pos, end_pos, line_color);
+            // Draw arrow at the target's border (overlay, above nodes)
+            self.draw_arrowhead(painter, start_pos, end_pos, line_color, connection.style.arrow, line_width);
         }
     }
 
     /// Draws a grid of dots representing messages in transit next to the connection arrow.
     ///
-    /// Messages are displayed in a 5-column grid with unlimited rows. Each message
-    /// is represented by a yellow dot with a gray outline. The grid is positioned
-    /// perpendicular to the connection line for visibility.
+    /// Messages are displayed in a 5-column grid. Each message is represented by
+    /// a yellow dot with a gray outline. The grid is positioned perpendicular to
+    /// the connection line for visibility. At most `message_render_limit` dots
+    /// are drawn; a "+N" label counts the rest.
     ///
     /// # Arguments
     ///
@@ -455,8 +615,9 @@ impl FlowchartApp {
             - perpendicular * grid_width_pixels * 0.5
             - direction * grid_height_pixels * 0.5;
 
-        // Draw each dot in the grid
-        for i in 0..message_count {
+        // Draw each dot in the grid, up to the render limit
+        let drawn = message_count.min(self.message_render_limit);
+        for i in 0..drawn {
             let row = i / grid_width;
             let col = i % grid_width;
 
@@ -472,6 +633,41 @@ impl FlowchartApp {
                 egui::Stroke::new(1.0, egui::Color32::DARK_GRAY),
             );
         }
+
+        if drawn < message_count {
+            let row = drawn.div_ceil(grid_width);
+            let label_pos = grid_start
+                + perpendicular * (row as f32 * dot_spacing * self.canvas.zoom_factor)
+                + direction * (grid_height_pixels * 0.5);
+            painter.text(
+                label_pos,
+                egui::Align2::CENTER_CENTER,
+                format!("+{}", message_count - drawn),
+                egui::FontId::proportional(11.0 * self.canvas.zoom_factor),
+                egui::Color32::YELLOW,
+            );
+        }
+    }
+
+    /// Draws an outline where an Alt-dragged copy of `from_node_id` would be
+    /// placed, with the connection it would get from the original.
+    fn draw_duplicate_preview(&self, painter: &egui::Painter, from_node_id: NodeId, drop_pos: egui::Pos2) {
+        let Some(from_node) = self.flowchart.nodes.get(&from_node_id) else {
+            return;
+        };
+        let shift_held = painter.ctx().input(|i| i.modifiers.shift);
+        let ghost_world = self.duplicate_drop_world_pos(drop_pos, shift_held);
+        let ghost = self.world_to_screen(ghost_world);
+        let color = egui::Color32::from_rgb(100, 150, 255);
+        if Self::is_valid_node_connection(from_node, from_node) {
+            let from_world = egui::pos2(from_node.position.0, from_node.position.1);
+            let (start, end) = border_anchors(from_node.position, (ghost_world.x, ghost_world.y), from_world, ghost_world);
+            painter.line_segment([self.world_to_screen(start), self.world_to_screen(end)], egui::Stroke::new(2.0, color));
+        }
+        let size = egui::vec2(crate::constants::NODE_WIDTH, crate::constants::NODE_HEIGHT) * self.canvas.zoom_factor;
+        let rect = egui::Rect::from_center_size(ghost, size);
+        painter.rect_filled(rect, 5.0, color.gamma_multiply(0.15));
+        painter.rect_stroke(rect, 5.0, egui::Stroke::new(1.5, color), StrokeKind::Outside);
     }
 
     /// Renders a preview of the connection being drawn during shift-click drag.
@@ -492,25 +688,19 @@ impl FlowchartApp {
         to_screen_pos: egui::Pos2,
     ) {
         if let Some(from_node) = self.flowchart.nodes.get(&from_node_id) {
+            // Start at the source's border, on the way to the mouse
+            let to_world_pos = self.screen_to_world(to_screen_pos);
             let from_world = egui::pos2(from_node.position.0, from_node.position.1);
-            let from_screen = self.world_to_screen(from_world);
+            let from_screen = self.world_to_screen(border_point(node_world_rect(from_node.position), from_world, to_world_pos));
 
             // Check if hovering over a valid target node
-            let to_world_pos = self.screen_to_world(to_screen_pos);
             let is_valid = if let Some(to_node_id) = self.find_node_at_position(to_world_pos) {
                 if to_node_id == from_node_id {
                     // Self-connection is invalid
                     false
                 } else if let Some(to_node) = self.flowchart.nodes.get(&to_node_id) {
                     // Check if connection is allowed based on node types
-                    match (&from_node.node_type, &to_node.node_type) {
-                        // Consumer cannot send (cannot be source)
-                        (NodeType::Consumer { .. }, _) => false,
-                        // Producer cannot receive (cannot be target)
-                        (_, NodeType::Producer { .. }) => false,
-                        // All other combinations are valid
-                        _ => true,
-                    }
+                    Self::is_valid_node_connection(from_node, to_node)
                 } else {
                     true // Unknown node, assume valid
                 }
@@ -538,13 +728,16 @@ impl FlowchartApp {
     ///
     /// Nodes are color-coded by type (green=Producer, red=Consumer, blue=Transformer).
     /// Selected nodes have a yellow border, dragged nodes have an orange border,
-    /// and error nodes have a flashing red border.
+    /// and error nodes have a flashing red border. Nodes with errors in the
+    /// run also carry an error badge, and consumers chart their recent
+    /// consumption when sparklines are on.
     ///
     /// # Arguments
     ///
     /// * `painter` - The egui painter for drawing operations
     /// * `node` - The node to render
-    pub fn draw_node(&self, painter: &egui::Painter, node: &FlowchartNode) {
+    /// * `format` - Colors from the formatting rules matching the node, if any
+    pub(crate) fn draw_node(&self, painter: &egui::Painter, node: &FlowchartNode, format: Option<&NodeFormat>) {
         let node_size = egui::vec2(crate::constants::NODE_WIDTH, crate::constants::NODE_HEIGHT);
 
         // Apply zoom and canvas offset for proper positioning
@@ -553,12 +746,13 @@ impl FlowchartApp {
         let scaled_size = node_size * self.canvas.zoom_factor;
         let rect = egui::Rect::from_center_size(screen_pos, scaled_size);
 
-        // Determine node color based on type
-        let mut color = match node.node_type {
+        // Determine node color based on type, unless a formatting rule sets it
+        let mut color = format.and_then(|f| f.fill).unwrap_or(match node.node_type {
             NodeType::Producer { .. } => egui::Color32::LIGHT_GREEN,
             NodeType::Consumer { .. } => egui::Color32::LIGHT_RED,
             NodeType::Transformer { .. } => egui::Color32::LIGHT_BLUE,
-        };
+            NodeType::Reference { .. } => egui::Color32::from_rgb(230, 210, 140),
+        });
 
         // Darken color if being dragged
         if Some(node.id) == self.interaction.dragging_node {
@@ -575,8 +769,8 @@ impl FlowchartApp {
 
         // Draw border with appropriate highlighting
         let (stroke_color, stroke_width) = if Some(node.id) == self.error_node {
-            // Flashing red border for error nodes (flash every 15 frames)
-            let flash_on = (self.frame_counter / 15).is_multiple_of(2);
+            // Flashing red border for error nodes
+            let flash_on = self.blink(crate::constants::ERROR_FLASH_HALF_PERIOD_SECS);
             if flash_on {
                 (egui::Color32::from_rgb(255, 0, 0), 5.0) // Bright red for error
             } else {
@@ -588,6 +782,8 @@ impl FlowchartApp {
             || self.interaction.selected_nodes.contains(&node.id)
         {
             (egui::Color32::YELLOW, 3.0) // Yellow for selected
+        } else if let Some(border) = format.and_then(|f| f.border) {
+            (border, 3.0) // Formatting rule border
         } else {
             (egui::Color32::BLACK, 2.0) // Black for normal
         };
@@ -601,6 +797,231 @@ impl FlowchartApp {
 
         // Render wrapped node name text
         self.draw_node_text(painter, node, screen_pos, scaled_size);
+
+        // Reference nodes show which file they link to along the bottom edge
+        if let NodeType::Reference { path } = &node.node_type {
+            let file_name = std::path::Path::new(path)
+                .file_name()
+                .map(|f| f.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "(no file)".to_string());
+            let font_size = (9.0 * self.canvas.zoom_factor).clamp(6.0, 36.0);
+            painter.text(
+                egui::pos2(screen_pos.x, rect.bottom() - 4.0 * self.canvas.zoom_factor),
+                egui::Align2::CENTER_BOTTOM,
+                format!("↗ {}", file_name),
+                egui::FontId::proportional(font_size),
+                egui::Color32::from_gray(60),
+            );
+        }
+
+        // Plugin nodes carry their plugin's icon in the top-left corner
+        if let Some(plugin) = node.plugin.as_ref().filter(|p| !p.icon.is_empty()) {
+            let font_size = (12.0 * self.canvas.zoom_factor).clamp(6.0, 48.0);
+            painter.text(
+                rect.left_top() + egui::vec2(4.0, 3.0) * self.canvas.zoom_factor,
+                egui::Align2::LEFT_TOP,
+                &plugin.icon,
+                egui::FontId::proportional(font_size),
+                egui::Color32::from_gray(40),
+            );
+        }
+
+        // Pinned nodes show a pin in the top-right corner
+        if node.pinned {
+            let font_size = (11.0 * self.canvas.zoom_factor).clamp(6.0, 44.0);
+            painter.text(
+                rect.right_top() + egui::vec2(-4.0, 3.0) * self.canvas.zoom_factor,
+                egui::Align2::RIGHT_TOP,
+                "📌",
+                egui::FontId::proportional(font_size),
+                egui::Color32::from_gray(40),
+            );
+        }
+
+        // Consumers chart their recent consumption while simulating
+        if self.canvas.show_consumer_sparklines && matches!(node.node_type, NodeType::Consumer { .. }) {
+            self.draw_consumer_sparkline(painter, node.id, rect);
+        }
+
+        // Nodes that failed during the run keep an error badge
+        self.draw_error_badge(painter, node);
+    }
+
+    /// Messages delivered to `node_id` in each of the last
+    /// `CONSUMER_SPARKLINE_STEPS` steps of the run, oldest first.
+    pub(crate) fn consumer_sparkline_values(&self, node_id: NodeId) -> Vec<u64> {
+        self.simulation_engine
+            .stats()
+            .recent(crate::constants::CONSUMER_SPARKLINE_STEPS)
+            .map(|sample| sample.deliveries.get(&node_id).copied().unwrap_or_default())
+            .collect()
+    }
+
+    /// Draws a sparkline of a consumer's recent consumption along the bottom
+    /// of its body, newest step at the right edge. Nothing is drawn before
+    /// the first step or when the node is too small on screen to read it.
+    fn draw_consumer_sparkline(&self, painter: &egui::Painter, node_id: NodeId, rect: egui::Rect) {
+        let values = self.consumer_sparkline_values(node_id);
+        let margin = 6.0 * self.canvas.zoom_factor;
+        let band = egui::Rect::from_min_max(
+            egui::pos2(rect.left() + margin, rect.bottom() - rect.height() * 0.3),
+            egui::pos2(rect.right() - margin, rect.bottom() - margin * 0.5),
+        );
+        if values.is_empty() || band.height() < 4.0 || band.width() < 10.0 {
+            return;
+        }
+
+        let max = values.iter().copied().max().unwrap_or_default().max(1) as f32;
+        let slot = band.width() / (crate::constants::CONSUMER_SPARKLINE_STEPS - 1).max(1) as f32;
+        let first_slot = crate::constants::CONSUMER_SPARKLINE_STEPS - values.len();
+        let points: Vec<egui::Pos2> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                egui::pos2(
+                    band.left() + slot * (first_slot + i) as f32,
+                    band.bottom() - band.height() * (*value as f32 / max),
+                )
+            })
+            .collect();
+        let color = egui::Color32::from_rgb(110, 20, 20);
+        painter.line_segment(
+            [band.left_bottom(), band.right_bottom()],
+            egui::Stroke::new(1.0, color.gamma_multiply(0.4)),
+        );
+        if let [point] = points.as_slice() {
+            painter.circle_filled(*point, 1.5, color);
+        } else {
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+        }
+    }
+
+    /// Draws a small timeline of the steps on which a producer emits messages.
+    ///
+    /// Already emitted messages are drawn dimmed and the current simulation step
+    /// is marked, so schedules can be checked without running the simulation.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The egui UI to draw into
+    /// * `start_step` - First step on which the producer may emit
+    /// * `messages_per_cycle` - Total number of messages the producer emits
+    /// * `steps_between_cycles` - Gap between emissions in steps
+    /// * `messages_produced` - How many messages have been emitted so far
+    /// * `node` - The producer, whose phase and jitter shift the emissions
+    pub(crate) fn draw_producer_timeline(
+        &self,
+        ui: &mut egui::Ui,
+        start_step: u64,
+        messages_per_cycle: u32,
+        steps_between_cycles: u32,
+        messages_produced: u32,
+        node: &FlowchartNode,
+    ) {
+        use crate::constants::{PRODUCER_TIMELINE_HEIGHT, PRODUCER_TIMELINE_MAX_STEPS};
+
+        let steps = crate::simulation::producer_emission_steps(
+            start_step,
+            messages_per_cycle,
+            steps_between_cycles,
+            &node.timing,
+            self.flowchart.run_config.seed,
+            node.id,
+            PRODUCER_TIMELINE_MAX_STEPS as usize + 1,
+        );
+        let current_step = self.flowchart.current_step;
+        let last = steps.last().copied().unwrap_or(start_step);
+        let horizon = last.max(current_step).saturating_add(1).clamp(10, PRODUCER_TIMELINE_MAX_STEPS);
+
+        let width = ui.available_width().max(60.0);
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(width, PRODUCER_TIMELINE_HEIGHT),
+            egui::Sense::hover(),
+        );
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        let axis_y = rect.center().y + 4.0;
+        let step_x = |step: u64| rect.left() + rect.width() * step as f32 / horizon as f32;
+
+        painter.line_segment(
+            [egui::pos2(rect.left(), axis_y), egui::pos2(rect.right(), axis_y)],
+            visuals.widgets.noninteractive.fg_stroke,
+        );
+
+        for (i, &step) in steps.iter().enumerate() {
+            if step > horizon {
+                break;
+            }
+            let color = if (i as u32) < messages_produced {
+                visuals.weak_text_color()
+            } else {
+                egui::Color32::LIGHT_GREEN
+            };
+            let x = step_x(step);
+            painter.line_segment(
+                [egui::pos2(x, axis_y - 8.0), egui::pos2(x, axis_y)],
+                egui::Stroke::new(2.0, color),
+            );
+        }
+
+        if current_step <= horizon {
+            let x = step_x(current_step);
+            painter.line_segment(
+                [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 165, 0)),
+            );
+        }
+
+        let font = egui::FontId::proportional(9.0);
+        let text_color = visuals.weak_text_color();
+        painter.text(
+            egui::pos2(rect.left(), rect.bottom()),
+            egui::Align2::LEFT_BOTTOM,
+            "0",
+            font.clone(),
+            text_color,
+        );
+        painter.text(
+            egui::pos2(rect.right(), rect.bottom()),
+            egui::Align2::RIGHT_BOTTOM,
+            horizon.to_string(),
+            font,
+            text_color,
+        );
+
+        if let Some(pos) = response.hover_pos() {
+            let hovered = ((pos.x - rect.left()) / rect.width() * horizon as f32).round() as u64;
+            let emits = steps.contains(&hovered);
+            response.on_hover_text(format!(
+                "Step {}{}",
+                hovered,
+                if emits { ": emits a message" } else { "" }
+            ));
+        }
+
+        let summary = match (steps.first(), messages_per_cycle) {
+            (_, 0) | (None, _) => "No messages scheduled".to_string(),
+            (Some(first), total) => {
+                let final_step = match steps.last() {
+                    Some(last) if steps.len() == total as usize => *last,
+                    // Past the timeline, so leave the jitter out
+                    _ => {
+                        let interval = u64::from(steps_between_cycles.max(1));
+                        start_step
+                            .saturating_add(u64::from(node.timing.phase))
+                            .saturating_add(u64::from(total - 1) * interval)
+                    }
+                };
+                format!(
+                    "{} message(s) between step {} and step {}{}",
+                    total,
+                    first,
+                    final_step,
+                    if final_step > horizon { " (timeline truncated)" } else { "" }
+                )
+            }
+        };
+        ui.label(egui::RichText::new(summary).small());
     }
 
     /// Renders the node's name text with proper wrapping and positioning.
@@ -803,13 +1224,6 @@ fn round_polygon_points(
         Some(egui::pos2(a.x + d1.x * t, a.y + d1.y * t))
     }
 
-    // Normalize angle to [0, 2pi)
-    fn norm_angle(mut a: f32) -> f32 {
-        while a < 0.0 { a += std::f32::consts::TAU; }
-        while a >= std::f32::consts::TAU { a -= std::f32::consts::TAU; }
-        a
-    }
-
     let mut out: Vec<egui::Pos2> = Vec::with_capacity(n * (segs + 1));
 
     for i in 0..n {
@@ -919,17 +1333,19 @@ fn round_polygon_points(
 /// # Arguments
 ///
 /// * `temp_script` - Reference to the script string being edited
+/// * `theme` - Highlighting palettes; the one matching the current visuals is used
 ///
 /// # Returns
 ///
 /// A closure that can be used as a layouter for egui::TextEdit
-pub fn create_js_layouter(
-    temp_script: &str,
-) -> impl FnMut(&egui::Ui, &dyn egui::TextBuffer, f32) -> std::sync::Arc<egui::Galley> + '_ {
+pub fn create_js_layouter<'a>(
+    temp_script: &'a str,
+    theme: &'a highlighters::SyntaxTheme,
+) -> impl FnMut(&egui::Ui, &dyn egui::TextBuffer, f32) -> std::sync::Arc<egui::Galley> + 'a {
     move |ui: &egui::Ui, _text: &dyn egui::TextBuffer, wrap_width: f32| {
         let font_id = egui::TextStyle::Monospace.resolve(ui.style());
         let mut layout_job =
-            highlighters::highlight_javascript(temp_script, font_id, ui.visuals().dark_mode);
+            highlighters::highlight_javascript(temp_script, font_id, theme.palette(ui.visuals().dark_mode));
         layout_job.wrap.max_width = wrap_width;
         ui.fonts_mut(|f| f.layout_job(layout_job))
     }
@@ -940,17 +1356,19 @@ pub fn create_js_layouter(
 /// # Arguments
 ///
 /// * `temp_json` - Reference to the JSON string being edited
+/// * `theme` - Highlighting palettes; the one matching the current visuals is used
 ///
 /// # Returns
 ///
 /// A closure that can be used as a layouter for egui::TextEdit
-pub fn create_json_layouter(
-    temp_json: &str,
-) -> impl FnMut(&egui::Ui, &dyn egui::TextBuffer, f32) -> std::sync::Arc<egui::Galley> + '_ {
+pub fn create_json_layouter<'a>(
+    temp_json: &'a str,
+    theme: &'a highlighters::SyntaxTheme,
+) -> impl FnMut(&egui::Ui, &dyn egui::TextBuffer, f32) -> std::sync::Arc<egui::Galley> + 'a {
     move |ui: &egui::Ui, _text: &dyn egui::TextBuffer, wrap_width: f32| {
         let font_id = egui::TextStyle::Monospace.resolve(ui.style());
         let mut layout_job =
-            highlighters::highlight_json(temp_json, font_id, ui.visuals().dark_mode);
+            highlighters::highlight_json(temp_json, font_id, theme.palette(ui.visuals().dark_mode));
         layout_job.wrap.max_width = wrap_width;
         ui.fonts_mut(|f| f.layout_job(layout_job))
     }
@@ -0,0 +1,158 @@
+//! Replicate window.
+//!
+//! Asks how many copies of the selected nodes to make and whether to put a
+//! fan-out and fan-in transformer around them, then builds the pool with
+//! [`replicate`] as one undo step.
+
+use super::state::{FlowchartApp, ReplicateDialogState};
+use crate::constants::REPLICATE_MAX_COPIES;
+use crate::replicate::{replicate, ReplicateOptions, Replication};
+use crate::types::*;
+use crate::ui::UndoAction;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Opens the Replicate window for `nodes`.
+    pub(crate) fn open_replicate_dialog(&mut self, nodes: &[NodeId]) {
+        self.replicate_dialog = Some(ReplicateDialogState {
+            nodes: nodes.to_vec(),
+            count: 3,
+            fan_out: true,
+            fan_in: true,
+        });
+    }
+
+    /// Draws the Replicate window while it is open.
+    pub(crate) fn draw_replicate_dialog(&mut self, ctx: &egui::Context) {
+        let Some(state) = self.replicate_dialog.as_mut() else {
+            return;
+        };
+        state.nodes.retain(|id| self.flowchart.nodes.contains_key(id));
+
+        let mut keep_open = true;
+        let mut create = false;
+        let mut cancel = false;
+        egui::Window::new("Replicate")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let selected = state.nodes.len();
+                ui.label(format!(
+                    "{} selected {} become copy 1 of the pool.",
+                    selected,
+                    if selected == 1 { "node" } else { "nodes" }
+                ));
+                ui.horizontal(|ui| {
+                    ui.label("Copies:");
+                    ui.add(egui::DragValue::new(&mut state.count).range(2..=REPLICATE_MAX_COPIES));
+                });
+                ui.checkbox(&mut state.fan_out, "Insert a fan-out node")
+                    .on_hover_text("Hands each incoming message to the next copy in turn");
+                ui.checkbox(&mut state.fan_in, "Insert a fan-in node")
+                    .on_hover_text("Merges what the copies send on");
+                let routes_by_name = state.nodes.iter().any(|id| {
+                    matches!(&self.flowchart.nodes[id].node_type,
+                        NodeType::Transformer { script, .. } if script.contains("__targets"))
+                });
+                if routes_by_name {
+                    ui.weak("Scripts routing with __targets keep the names they use; update them after replicating.");
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(selected > 0, egui::Button::new("Replicate")).clicked() {
+                        create = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if create {
+            if let Some(state) = self.replicate_dialog.take() {
+                // Can't fail: the button needs a selection and the count starts at two
+                let _ = self.replicate_selection(&state);
+            }
+        } else if cancel || !keep_open {
+            self.replicate_dialog = None;
+        }
+    }
+
+    /// Replicates the nodes of `state` as one undo step.
+    ///
+    /// The whole pool, fan nodes included, becomes the selection.
+    pub(crate) fn replicate_selection(&mut self, state: &ReplicateDialogState) -> Result<Replication, String> {
+        let before: Vec<(NodeId, NodeType)> = self
+            .flowchart
+            .nodes
+            .values()
+            .map(|node| (node.id, node.node_type.clone()))
+            .collect();
+        let old_parameters = self.flowchart.parameters.clone();
+        let options = ReplicateOptions {
+            count: state.count as usize,
+            fan_out: state.fan_out,
+            fan_in: state.fan_in,
+        };
+        let result = replicate(&mut self.flowchart, &state.nodes, options)?;
+
+        let mut actions: Vec<UndoAction> = result
+            .removed
+            .iter()
+            .map(|(connection, index)| UndoAction::ConnectionDeleted {
+                connection: connection.clone(),
+                index: *index,
+            })
+            .collect();
+        actions.extend(result.renamed.iter().map(|(node_id, old_name, new_name)| UndoAction::NodeRenamed {
+            node_id: *node_id,
+            old_name: old_name.clone(),
+            new_name: new_name.clone(),
+        }));
+        for (node_id, old_node_type) in before {
+            let new_node_type = &self.flowchart.nodes[&node_id].node_type;
+            if *new_node_type != old_node_type {
+                actions.push(UndoAction::PropertyChanged {
+                    node_id,
+                    old_node_type,
+                    new_node_type: new_node_type.clone(),
+                });
+            }
+        }
+        let created = result.created();
+        actions.extend(created.iter().map(|&node_id| UndoAction::NodeCreated { node_id }));
+        actions.extend(
+            result
+                .connections
+                .iter()
+                .map(|&(from, to)| UndoAction::ConnectionCreated { from, to }),
+        );
+        if self.flowchart.parameters != old_parameters {
+            actions.push(UndoAction::ParametersChanged {
+                old: old_parameters,
+                new: self.flowchart.parameters.clone(),
+            });
+        }
+        actions.extend(self.make_room_for(&created));
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+        crate::crash::record_event("Replicated nodes");
+
+        self.node_counter += created.len() as u32;
+        self.interaction.selected_node = None;
+        self.interaction.selected_group = None;
+        self.interaction.selected_connection = None;
+        self.interaction.selected_nodes = result
+            .copies
+            .iter()
+            .flatten()
+            .copied()
+            .chain(result.fan_out)
+            .chain(result.fan_in)
+            .collect();
+        Ok(result)
+    }
+}
@@ -0,0 +1,566 @@
+//! Run statistics dashboard.
+//!
+//! Shows the totals and rates of the current run over the measurement window
+//! configured in [`RunConfig`], a chart of the per-step samples with the
+//! warm-up shaded, per-consumer latency histograms, and exports the samples
+//! as CSV and the summary as JSON. An exported summary can be loaded back as
+//! a baseline: its steps are drawn as ghost lines and its figures shown next
+//! to the current run's with the difference. A memory section reports how
+//! much sharing identical message payloads saves.
+
+use super::state::{ChangeKind, FlowchartApp, PendingLoadOperation};
+use super::UndoAction;
+use crate::metrics::{latency_histogram, BaselineRun, LatencyStats, RunSummary, StepSample};
+use crate::types::*;
+use eframe::egui;
+
+/// Undo coalescing key for dragging the window values.
+const RUN_CONFIG_KEY: &str = "run_config";
+
+/// Height of the per-step chart in the dashboard.
+const CHART_HEIGHT: f32 = 90.0;
+
+/// Height of a consumer's latency histogram.
+const HISTOGRAM_HEIGHT: f32 = 70.0;
+
+/// Most bars drawn in a latency histogram.
+const HISTOGRAM_MAX_BINS: usize = 30;
+
+/// A line on the per-step chart: label, color, the value it plots and the
+/// column of [`BaselineRun::steps`] holding the same value.
+type ChartSeries = (&'static str, egui::Color32, fn(&StepSample) -> u64, usize);
+
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`.
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Formats `current - baseline` with its relative change, e.g. `+1.50 (+25%)`.
+fn format_delta(current: f64, baseline: f64, precision: usize) -> String {
+    let delta = current - baseline;
+    if delta.abs() < 0.5 * 10f64.powi(-(precision as i32)) {
+        return "=".to_string();
+    }
+    if baseline == 0.0 {
+        return format!("{:+.*}", precision, delta);
+    }
+    format!("{:+.*} ({:+.0}%)", precision, delta, 100.0 * delta / baseline)
+}
+
+impl FlowchartApp {
+    /// Replaces the run settings, e.g. the warm-up and measurement window, and
+    /// records the change.
+    pub(crate) fn set_run_config(&mut self, settings: RunConfig) {
+        if settings == self.flowchart.run_config {
+            return;
+        }
+        let old = std::mem::replace(&mut self.flowchart.run_config, settings);
+        self.undo_history.push_action_coalesced(
+            UndoAction::RunConfigChanged { old, new: settings },
+            RUN_CONFIG_KEY,
+            self.frame_time,
+        );
+        self.file.mark_changed(ChangeKind::RunConfigChanged);
+    }
+
+    /// Loads an exported run summary as the baseline to compare against.
+    pub(crate) fn set_run_baseline(&mut self, path: &str, content: &str) {
+        let name = std::path::Path::new(path)
+            .file_name()
+            .map_or_else(|| path.to_string(), |n| n.to_string_lossy().into_owned());
+        match BaselineRun::from_json(&name, content) {
+            Ok(baseline) => {
+                self.run_baseline = Some(baseline);
+                self.run_baseline_error = None;
+            }
+            Err(e) => self.run_baseline_error = Some(e),
+        }
+    }
+
+    /// Summary of the current run over the configured measurement window.
+    pub(crate) fn run_summary(&self) -> RunSummary {
+        self.simulation_engine.stats().summarize(&self.flowchart.run_config)
+    }
+
+    /// Describes where the run is relative to the measurement window.
+    fn measurement_status(&self, summary: &RunSummary) -> String {
+        let settings = &self.flowchart.run_config;
+        if self.simulation_engine.stats().is_empty() {
+            return "No steps run yet.".to_string();
+        }
+        if summary.measured_steps == 0 {
+            return format!(
+                "Warming up: {} of {} steps done.",
+                self.flowchart.current_step.min(settings.warm_up_steps),
+                settings.warm_up_steps
+            );
+        }
+        let first = summary.first_step.unwrap_or_default();
+        let last = summary.last_step.unwrap_or_default();
+        match settings.last_measured_step() {
+            Some(end) if summary.is_complete() => {
+                format!("Measurement complete: steps {}–{}.", first, end)
+            }
+            Some(end) => format!("Measuring steps {}–{}, at step {}.", first, end, last),
+            None => format!("Measuring from step {}, at step {}.", first, last),
+        }
+    }
+
+    /// Draws the dashboard. Opens when `show_run_stats_dialog` is true.
+    pub(crate) fn draw_run_stats_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let mut settings = self.flowchart.run_config;
+        let mut export_csv = false;
+        let mut export_json = false;
+        let mut export_latencies = false;
+        let mut pick_baseline = false;
+        let mut clear_baseline = false;
+        let summary = self.run_summary();
+        let baseline = self.run_baseline.as_ref();
+
+        egui::Window::new("Run Statistics")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                egui::Grid::new("run_config_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Warm-up:");
+                    ui.add(egui::DragValue::new(&mut settings.warm_up_steps).suffix(" steps"))
+                        .on_hover_text("Statistics of the first steps are discarded");
+                    ui.end_row();
+                    ui.label("Measure for:");
+                    ui.add(egui::DragValue::new(&mut settings.measurement_steps).suffix(" steps"))
+                        .on_hover_text("0 measures until the run stops");
+                    ui.end_row();
+                    ui.label("Pause at:");
+                    ui.horizontal(|ui| {
+                        let mut pause = settings.pause_at_step.is_some();
+                        let mut step = settings.pause_at_step.unwrap_or(self.flowchart.current_step + 1);
+                        ui.checkbox(&mut pause, "");
+                        ui.add_enabled(pause, egui::DragValue::new(&mut step).range(1..=u64::MAX).prefix("step "))
+                            .on_hover_text("A running simulation pauses by itself on this step");
+                        settings.pause_at_step = pause.then_some(step);
+                    });
+                    ui.end_row();
+                    ui.label("End after:");
+                    ui.horizontal(|ui| {
+                        let mut end = settings.max_steps.is_some();
+                        let mut steps = settings.max_steps.unwrap_or(self.flowchart.current_step.max(100));
+                        ui.checkbox(&mut end, "");
+                        ui.add_enabled(end, egui::DragValue::new(&mut steps).range(1..=u64::MAX).suffix(" steps"))
+                            .on_hover_text("A running simulation pauses here, and command-line runs end here");
+                        settings.max_steps = end.then_some(steps);
+                    });
+                    ui.end_row();
+                    ui.label("Speed:");
+                    ui.add(
+                        egui::DragValue::new(&mut settings.steps_per_frame)
+                            .range(1..=crate::constants::MAX_STEPS_PER_FRAME)
+                            .suffix(" steps/frame"),
+                    )
+                    .on_hover_text("Steps a running simulation advances each frame");
+                    ui.end_row();
+                    ui.label("On errors:");
+                    ui.checkbox(&mut settings.continue_on_error, "Keep running")
+                        .on_hover_text("Script errors are counted but don't stop the run");
+                    ui.end_row();
+                    ui.label("Random seed:");
+                    ui.add(egui::DragValue::new(&mut settings.seed))
+                        .on_hover_text("Seeds random node failures and producer jitter; the same seed repeats them exactly");
+                    ui.end_row();
+                });
+                ui.weak(self.measurement_status(&summary));
+                if let Some(baseline) = baseline {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Comparing with {}", baseline.source));
+                        if ui.small_button("Clear").clicked() {
+                            clear_baseline = true;
+                        }
+                    });
+                    if (baseline.warm_up_steps, baseline.measurement_steps)
+                        != (settings.warm_up_steps, settings.measurement_steps)
+                    {
+                        ui.weak(format!(
+                            "The baseline was measured with a {}-step warm-up and a {} window.",
+                            baseline.warm_up_steps,
+                            match baseline.measurement_steps {
+                                0 => "open-ended".to_string(),
+                                steps => format!("{}-step", steps),
+                            }
+                        ));
+                    }
+                }
+                if let Some(error) = &self.run_baseline_error {
+                    ui.colored_label(egui::Color32::from_rgb(200, 80, 80), error);
+                }
+                ui.separator();
+
+                let columns = if baseline.is_some() { 4 } else { 2 };
+                egui::Grid::new("run_summary_grid").num_columns(columns).striped(true).show(ui, |ui| {
+                    // Label, current value, baseline value and decimals shown
+                    let rows = [
+                        ("Measured steps", summary.measured_steps as f64, baseline.map(|b| b.measured_steps as f64), 0),
+                        ("Produced", summary.produced as f64, baseline.map(|b| b.produced as f64), 0),
+                        ("Delivered", summary.delivered as f64, baseline.map(|b| b.delivered as f64), 0),
+                        ("Consumed", summary.consumed as f64, baseline.map(|b| b.consumed as f64), 0),
+                        ("Throughput (msg/step)", summary.throughput(), baseline.map(|b| b.throughput), 2),
+                        ("In transit (mean)", summary.mean_in_transit, baseline.map(|b| b.mean_in_transit), 1),
+                        ("In transit (max)", summary.max_in_transit as f64, baseline.map(|b| b.max_in_transit as f64), 0),
+                        ("Script errors", summary.errors as f64, baseline.map(|b| b.errors as f64), 0),
+                    ];
+                    if baseline.is_some() {
+                        ui.label("");
+                        ui.strong("Current");
+                        ui.strong("Baseline");
+                        ui.strong("Δ");
+                        ui.end_row();
+                    }
+                    for (label, value, baseline_value, precision) in rows {
+                        ui.label(label);
+                        ui.monospace(format!("{:.*}", precision, value));
+                        if let Some(baseline_value) = baseline_value {
+                            ui.monospace(format!("{:.*}", precision, baseline_value));
+                            ui.monospace(format_delta(value, baseline_value, precision));
+                        }
+                        ui.end_row();
+                    }
+                });
+
+                ui.separator();
+                self.draw_run_chart(ui, &settings);
+
+                if !summary.node_deliveries.is_empty() {
+                    ui.separator();
+                    let mut nodes: Vec<&FlowchartNode> = summary
+                        .node_deliveries
+                        .keys()
+                        .filter_map(|id| self.flowchart.nodes.get(id))
+                        .collect();
+                    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+                    let columns = if baseline.is_some() { 5 } else { 3 };
+                    egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                        egui::Grid::new("run_nodes_grid").num_columns(columns).striped(true).show(ui, |ui| {
+                            ui.strong("Node");
+                            ui.strong("Received");
+                            ui.strong("Per step");
+                            if baseline.is_some() {
+                                ui.strong("Baseline");
+                                ui.strong("Δ");
+                            }
+                            ui.end_row();
+                            for node in nodes {
+                                let rate = summary.node_rate(node.id);
+                                ui.label(&node.name);
+                                ui.monospace(summary.node_deliveries[&node.id].to_string());
+                                ui.monospace(format!("{:.2}", rate));
+                                if let Some(baseline) = baseline {
+                                    match baseline.nodes.get(&node.name) {
+                                        Some(then) => {
+                                            ui.monospace(format!("{:.2}", then.per_step));
+                                            ui.monospace(format_delta(rate, then.per_step, 2));
+                                        }
+                                        None => {
+                                            ui.weak("—");
+                                            ui.weak("");
+                                        }
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+
+                if !summary.latencies.is_empty() {
+                    ui.separator();
+                    ui.strong("End-to-end latency");
+                    let mut consumers: Vec<(&FlowchartNode, &Vec<u64>)> = summary
+                        .latencies
+                        .iter()
+                        .filter_map(|(id, latencies)| self.flowchart.nodes.get(id).map(|n| (n, latencies)))
+                        .collect();
+                    consumers.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+                    for (node, latencies) in consumers {
+                        let Some(stats) = LatencyStats::from_sorted(latencies) else {
+                            continue;
+                        };
+                        let mut title = format!(
+                            "{}: p50 {} · p95 {} · p99 {} steps",
+                            node.name, stats.p50, stats.p95, stats.p99
+                        );
+                        let then = baseline.and_then(|b| b.nodes.get(&node.name)).and_then(|n| n.latency);
+                        if let Some(then) = then {
+                            title.push_str(&format!(" (baseline p95 {})", then.p95));
+                        }
+                        egui::CollapsingHeader::new(title)
+                            .id_salt(("latency", node.id))
+                            .show(ui, |ui| {
+                                Self::draw_latency_histogram(ui, latencies, &stats);
+                                ui.weak(format!(
+                                    "{} messages, min {}, mean {:.1}, max {}",
+                                    stats.count, stats.min, stats.mean, stats.max
+                                ));
+                                if let Some(then) = then {
+                                    ui.weak(format!(
+                                        "Baseline: p50 {}, p99 {}, mean {:.1} ({})",
+                                        then.p50,
+                                        then.p99,
+                                        then.mean,
+                                        format_delta(stats.mean, then.mean, 1)
+                                    ));
+                                }
+                            });
+                    }
+                }
+
+                ui.separator();
+                let payloads = self.simulation_engine.payloads().stats();
+                ui.collapsing("Memory", |ui| {
+                    egui::Grid::new("run_memory_grid").num_columns(2).striped(true).show(ui, |ui| {
+                        ui.label("Messages held");
+                        ui.monospace(payloads.references.to_string());
+                        ui.end_row();
+                        ui.label("Distinct payloads");
+                        ui.monospace(payloads.distinct.to_string());
+                        ui.end_row();
+                        ui.label("Saved by sharing payloads");
+                        ui.monospace(format_bytes(payloads.bytes_saved));
+                        ui.end_row();
+                    });
+                    ui.weak("Messages in transit and in node histories with equal payloads share one copy.");
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Compare With…")
+                        .on_hover_text("Load a summary exported with \"Export Summary (JSON)\" as the baseline")
+                        .clicked()
+                    {
+                        pick_baseline = true;
+                    }
+                });
+                ui.add_enabled_ui(!self.simulation_engine.stats().is_empty(), |ui| {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button("Export Steps (CSV)…")
+                            .on_hover_text("Every recorded step, with a column marking the measured ones")
+                            .clicked()
+                        {
+                            export_csv = true;
+                        }
+                        if ui.button("Export Summary (JSON)…").clicked() {
+                            export_json = true;
+                        }
+                        if ui
+                            .button("Export Latencies (CSV)…")
+                            .on_hover_text("One row per consumed message")
+                            .clicked()
+                        {
+                            export_latencies = true;
+                        }
+                    });
+                });
+            });
+
+        self.set_run_config(settings);
+        if export_csv {
+            self.export_run_steps_csv();
+        }
+        if export_latencies {
+            let csv = self.simulation_engine.stats().latency_csv(&self.flowchart);
+            Self::save_text_file("run_latencies.csv", "CSV", "csv", csv);
+        }
+        if export_json {
+            match summary.to_json(self.simulation_engine.stats(), &self.flowchart) {
+                Ok(json) => Self::save_text_file("run_summary.json", "JSON", "json", json),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        if pick_baseline {
+            self.file.pending_load_operation = Some(PendingLoadOperation::RunBaseline);
+        }
+        if clear_baseline {
+            self.run_baseline = None;
+        }
+        if !keep_open {
+            self.show_run_stats_dialog = false;
+        }
+    }
+
+    /// Draws consumed messages and messages in transit per step, with the
+    /// steps outside the measurement window shaded and the baseline's steps
+    /// as fainter ghost lines.
+    fn draw_run_chart(&self, ui: &mut egui::Ui, settings: &RunConfig) {
+        let samples: Vec<_> = self.simulation_engine.stats().samples().collect();
+        let width = ui.available_width().max(120.0);
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(width, CHART_HEIGHT), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+
+        let ghost: &[[u64; 3]] = self.run_baseline.as_ref().map_or(&[], |b| &b.steps);
+        let steps = samples.iter().map(|s| s.step).chain(ghost.iter().map(|g| g[0]));
+        let (Some(first_step), Some(last_step)) = (steps.clone().min(), steps.max()) else {
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Run the simulation to collect statistics",
+                egui::FontId::proportional(12.0),
+                visuals.weak_text_color(),
+            );
+            return;
+        };
+        let last_step = last_step.max(first_step + 1);
+        let x = |step: u64| {
+            rect.left() + rect.width() * (step.saturating_sub(first_step)) as f32 / (last_step - first_step) as f32
+        };
+
+        // Shade the steps that don't count
+        let shade = egui::Color32::from_rgba_unmultiplied(128, 128, 128, 40);
+        if settings.warm_up_steps > first_step {
+            let end = x(settings.warm_up_steps.min(last_step));
+            painter.rect_filled(egui::Rect::from_x_y_ranges(rect.left()..=end, rect.y_range()), 0.0, shade);
+        }
+        if let Some(end) = settings.last_measured_step().filter(|end| *end < last_step) {
+            let start = x(end + 1);
+            painter.rect_filled(egui::Rect::from_x_y_ranges(start..=rect.right(), rect.y_range()), 0.0, shade);
+        }
+
+        let series: [ChartSeries; 2] = [
+            ("consumed", egui::Color32::from_rgb(90, 160, 230), |s| s.consumed, 1),
+            ("in transit", egui::Color32::from_rgb(230, 150, 60), |s| s.in_transit, 2),
+        ];
+        let max = samples
+            .iter()
+            .flat_map(|s| series.iter().map(move |(_, _, value, _)| value(s)))
+            .chain(ghost.iter().flat_map(|g| series.iter().map(move |(_, _, _, column)| g[*column])))
+            .max()
+            .unwrap_or(0)
+            .max(1) as f32;
+        let y = |value: u64| rect.bottom() - rect.height() * value as f32 / max;
+        // One point per horizontal pixel is plenty
+        let ghost_stride = (ghost.len() as f32 / rect.width()).ceil().max(1.0) as usize;
+        for (_, color, _, column) in &series {
+            let points: Vec<egui::Pos2> =
+                ghost.iter().step_by(ghost_stride).map(|g| egui::pos2(x(g[0]), y(g[*column]))).collect();
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color.gamma_multiply(0.4))));
+        }
+        let stride = (samples.len() as f32 / rect.width()).ceil().max(1.0) as usize;
+        for (_, color, value, _) in &series {
+            let points: Vec<egui::Pos2> = samples
+                .iter()
+                .step_by(stride)
+                .map(|s| egui::pos2(x(s.step), y(value(s))))
+                .collect();
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, *color)));
+        }
+
+        ui.horizontal(|ui| {
+            for (label, color, _, _) in &series {
+                ui.colored_label(*color, format!("— {}", label));
+            }
+            if !ghost.is_empty() {
+                ui.weak("faint: baseline");
+            }
+            ui.weak(format!("peak {}", max as u64));
+        });
+    }
+
+    /// Draws a histogram of `sorted` latencies with the p50/p95/p99 marked.
+    fn draw_latency_histogram(ui: &mut egui::Ui, sorted: &[u64], stats: &LatencyStats) {
+        let bins = latency_histogram(sorted, HISTOGRAM_MAX_BINS);
+        let width = ui.available_width().max(120.0);
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(width, HISTOGRAM_HEIGHT), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+        let (Some(first), Some(last)) = (bins.first(), bins.last()) else {
+            return;
+        };
+        let (low, high) = (first.0, last.1 + 1);
+        let x = |latency: u64| rect.left() + rect.width() * (latency - low) as f32 / (high - low) as f32;
+        let tallest = bins.iter().map(|b| b.2).max().unwrap_or(1).max(1) as f32;
+
+        let bar_color = egui::Color32::from_rgb(90, 160, 230);
+        for &(start, end, count) in &bins {
+            let top = rect.bottom() - (rect.height() - 12.0) * count as f32 / tallest;
+            let bar = egui::Rect::from_x_y_ranges(x(start) + 0.5..=x(end + 1) - 0.5, top..=rect.bottom());
+            painter.rect_filled(bar, 0.0, bar_color);
+        }
+        for (label, value) in [("p50", stats.p50), ("p95", stats.p95), ("p99", stats.p99)] {
+            // Mark the middle of the percentile's step
+            let mx = (x(value) + x(value + 1)) / 2.0;
+            painter.line_segment(
+                [egui::pos2(mx, rect.top()), egui::pos2(mx, rect.bottom())],
+                egui::Stroke::new(1.0, egui::Color32::from_rgb(230, 150, 60)),
+            );
+            painter.text(
+                egui::pos2(mx + 2.0, rect.top() + 1.0),
+                egui::Align2::LEFT_TOP,
+                label,
+                egui::FontId::proportional(10.0),
+                visuals.text_color(),
+            );
+        }
+
+        if let Some(pos) = response.hover_pos() {
+            let hovered = bins.iter().find(|(start, end, _)| pos.x >= x(*start) && pos.x < x(end + 1));
+            if let Some(&(start, end, count)) = hovered {
+                let range = if start == end {
+                    format!("{} steps", start)
+                } else {
+                    format!("{}–{} steps", start, end)
+                };
+                response.on_hover_text(format!("{}: {} messages", range, count));
+            }
+        }
+    }
+
+    /// Saves the per-step metrics of the current run as `run_steps.csv`.
+    pub(crate) fn export_run_steps_csv(&self) {
+        let csv = self.simulation_engine.steps_csv(&self.flowchart);
+        Self::save_text_file("run_steps.csv", "CSV", "csv", csv);
+    }
+
+    /// Saves text through a save dialog (native) or as a download (web).
+    pub(crate) fn save_text_file(file_name: &str, filter_name: &str, extension: &str, content: String) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = (filter_name, extension);
+            if let Err(e) = Self::trigger_download(file_name, &content) {
+                eprintln!("Failed to start download: {}", e);
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let (file_name, filter_name, extension) =
+                (file_name.to_string(), filter_name.to_string(), extension.to_string());
+            tokio::spawn(async move {
+                if let Some(handle) = rfd::AsyncFileDialog::new()
+                    .add_filter(&filter_name, &[extension.as_str()])
+                    .set_file_name(&file_name)
+                    .save_file()
+                    .await
+                {
+                    if let Err(e) = std::fs::write(handle.path(), content) {
+                        eprintln!("Failed to save {}: {}", file_name, e);
+                    }
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Script API panel.
+//!
+//! Opened from the Transformer editor, it lists what a transformer script can
+//! use, each with an example and the output the example gives. It is drawn
+//! from [`crate::script_api::SCRIPT_API`], whose examples the tests run.
+
+use super::highlighters::highlight_javascript;
+use super::state::FlowchartApp;
+use crate::script_api::SCRIPT_API;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Draws the Script API window. Opens when `show_script_api_panel` is true.
+    pub(crate) fn draw_script_api_panel(&mut self, ctx: &egui::Context) {
+        let mut keep_open = self.show_script_api_panel;
+        let palette = self.syntax_theme.palette(ctx.style().visuals.dark_mode);
+
+        egui::Window::new("Script API")
+            .open(&mut keep_open)
+            .collapsible(true)
+            .resizable(true)
+            .default_width(420.0)
+            .default_height(480.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for item in SCRIPT_API {
+                        ui.label(egui::RichText::new(item.name).monospace().strong());
+                        ui.add(egui::Label::new(item.summary).wrap());
+                        egui::CollapsingHeader::new("Example")
+                            .id_salt(("script_api_example", item.name))
+                            .show(ui, |ui| {
+                                let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+                                ui.label(highlight_javascript(item.example, font_id, palette));
+                                ui.horizontal(|ui| {
+                                    ui.weak("Input:");
+                                    ui.monospace(item.input);
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.weak("Returns:");
+                                    ui.monospace(item.output);
+                                });
+                            });
+                        ui.separator();
+                    }
+                });
+            });
+
+        self.show_script_api_panel = keep_open;
+    }
+}
@@ -0,0 +1,139 @@
+//! Saved selections.
+//!
+//! A multi-selection can be saved under a name and selected again later from
+//! the toolbar's Selections menu, for bulk edits that keep coming back to the
+//! same nodes. Saved selections are kept in the document but, unlike groups,
+//! aren't drawn and don't affect layouts; nodes deleted since are skipped
+//! when a selection is activated.
+
+use super::state::{ChangeKind, FlowchartApp};
+use super::UndoAction;
+use crate::types::*;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Replaces the saved selections and records the change as one undo step.
+    fn set_saved_selections(&mut self, selections: Vec<SavedSelection>) {
+        if selections == self.flowchart.saved_selections {
+            return;
+        }
+        let old = std::mem::replace(&mut self.flowchart.saved_selections, selections);
+        let action = UndoAction::SavedSelectionsChanged {
+            old,
+            new: self.flowchart.saved_selections.clone(),
+        };
+        self.undo_history.push_action(action);
+        self.file.mark_changed(ChangeKind::SavedSelectionsChanged);
+    }
+
+    /// Saves the current selection as `name`, replacing a saved selection of
+    /// the same name. Returns false if nothing is selected or the name is blank.
+    pub(crate) fn save_selection(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        let nodes = self.selected_node_ids();
+        if name.is_empty() || nodes.is_empty() {
+            return false;
+        }
+        let mut selections = self.flowchart.saved_selections.clone();
+        let saved = SavedSelection {
+            name: name.to_string(),
+            nodes,
+        };
+        match selections.iter_mut().find(|s| s.name == name) {
+            Some(existing) => *existing = saved,
+            None => selections.push(saved),
+        }
+        self.set_saved_selections(selections);
+        true
+    }
+
+    /// Selects the nodes of the saved selection at `index` that still exist.
+    pub(crate) fn activate_saved_selection(&mut self, index: usize) {
+        let Some(saved) = self.flowchart.saved_selections.get(index) else {
+            return;
+        };
+        let nodes: Vec<NodeId> = saved
+            .nodes
+            .iter()
+            .copied()
+            .filter(|id| self.flowchart.nodes.contains_key(id))
+            .collect();
+        self.interaction.selected_node = match nodes.as_slice() {
+            [only] => Some(*only),
+            _ => None,
+        };
+        self.interaction.selected_nodes = nodes;
+        self.interaction.selected_group = None;
+        self.interaction.selected_connection = None;
+        self.interaction.editing_node_name = None;
+        self.clear_temp_editing_values();
+    }
+
+    /// Deletes the saved selection at `index`.
+    pub(crate) fn delete_saved_selection(&mut self, index: usize) {
+        if index >= self.flowchart.saved_selections.len() {
+            return;
+        }
+        let mut selections = self.flowchart.saved_selections.clone();
+        selections.remove(index);
+        self.set_saved_selections(selections);
+    }
+
+    /// Draws the toolbar's Selections menu: the saved selections to activate
+    /// or delete, and a field to save the current one.
+    pub(crate) fn draw_saved_selections_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Selections", |ui| {
+            let mut activate = None;
+            let mut delete = None;
+            if self.flowchart.saved_selections.is_empty() {
+                ui.weak("No saved selections yet.");
+            }
+            for (index, saved) in self.flowchart.saved_selections.iter().enumerate() {
+                let present = saved.nodes.iter().filter(|id| self.flowchart.nodes.contains_key(id)).count();
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!("{} ({})", saved.name, present))
+                        .on_hover_text("Select these nodes")
+                        .clicked()
+                    {
+                        activate = Some(index);
+                    }
+                    if ui.small_button("✖").on_hover_text("Delete saved selection").clicked() {
+                        delete = Some(index);
+                    }
+                });
+            }
+            ui.separator();
+
+            let selected = self.selected_node_ids().len();
+            ui.add_enabled_ui(selected > 0, |ui| {
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.saved_selection_name)
+                            .hint_text("Name")
+                            .desired_width(120.0),
+                    );
+                    let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let label = format!("Save {} Node(s)", selected);
+                    if ui.button(label).clicked() || enter {
+                        let name = std::mem::take(&mut self.saved_selection_name);
+                        if self.save_selection(&name) {
+                            ui.close();
+                        } else {
+                            self.saved_selection_name = name;
+                        }
+                    }
+                })
+                .response
+                .on_disabled_hover_text("Select nodes to save them under a name");
+            });
+
+            if let Some(index) = activate {
+                self.activate_saved_selection(index);
+                ui.close();
+            } else if let Some(index) = delete {
+                self.delete_saved_selection(index);
+            }
+        });
+    }
+}
@@ -0,0 +1,155 @@
+//! Headless snapshots of a flowchart for golden-image tests.
+//!
+//! Renders a document the way SVG/PNG export does, but without a window: the
+//! text is measured with egui's built-in fonts in a throwaway context, and
+//! images are rasterized with those same fonts instead of the system ones,
+//! so a snapshot only depends on the document and the options. Compare a
+//! [`Snapshot`] with a stored PNG using [`Snapshot::differing_pixels`].
+
+use super::state::{ExportOptions, FlowchartApp};
+use crate::types::Flowchart;
+use eframe::egui;
+
+/// Renders `flowchart` to an SVG document.
+pub fn snapshot_svg(flowchart: &Flowchart, options: &ExportOptions) -> String {
+    render_svg(flowchart, options).0
+}
+
+/// Renders `flowchart` to an image at `options.png_scale`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn snapshot_image(flowchart: &Flowchart, options: &ExportOptions) -> Result<Snapshot, String> {
+    let (svg, width, height) = render_svg(flowchart, options);
+    let pixmap = super::export::rasterize_svg(&svg, width, height, options, builtin_fonts())?;
+    Ok(Snapshot::from_pixmap(&pixmap))
+}
+
+/// Builds the export SVG of `flowchart` inside a headless egui frame.
+fn render_svg(flowchart: &Flowchart, options: &ExportOptions) -> (String, u32, u32) {
+    let app = FlowchartApp {
+        flowchart: flowchart.clone(),
+        ..Default::default()
+    };
+    let mut rendered = None;
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        rendered = Some(app.build_svg_with_options(ctx, options));
+    });
+    rendered.expect("egui runs the frame once")
+}
+
+/// egui's default proportional font, set as the SVG renderer's sans-serif.
+#[cfg(not(target_arch = "wasm32"))]
+fn builtin_fonts() -> fontdb::Database {
+    let definitions = egui::FontDefinitions::default();
+    let mut db = fontdb::Database::new();
+    let primary = definitions
+        .families
+        .get(&egui::FontFamily::Proportional)
+        .and_then(|names| names.first())
+        .and_then(|name| definitions.font_data.get(name));
+    if let Some(font) = primary {
+        db.load_font_data(font.font.to_vec());
+    }
+    let family = db.faces().next().and_then(|face| face.families.first()).map(|f| f.0.clone());
+    if let Some(family) = family {
+        db.set_sans_serif_family(family);
+    }
+    db
+}
+
+/// A rendered flowchart as straight (not premultiplied) RGBA pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Row-major RGBA bytes, four per pixel
+    pub pixels: Vec<u8>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Snapshot {
+    /// Encodes the snapshot as a PNG file.
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let size = tiny_skia::IntSize::from_wh(self.width, self.height)
+            .ok_or_else(|| format!("Invalid snapshot size {}x{}", self.width, self.height))?;
+        let pixmap = tiny_skia::Pixmap::from_vec(premultiplied(&self.pixels), size)
+            .ok_or("Snapshot pixels do not match its size")?;
+        pixmap.encode_png().map_err(|e| format!("Failed to encode PNG: {}", e))
+    }
+
+    /// Decodes a PNG file, such as a stored golden image.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, String> {
+        let pixmap = tiny_skia::Pixmap::decode_png(bytes).map_err(|e| format!("Failed to decode PNG: {}", e))?;
+        Ok(Self::from_pixmap(&pixmap))
+    }
+
+    pub(crate) fn from_pixmap(pixmap: &tiny_skia::Pixmap) -> Self {
+        let pixels = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|p| {
+                let c = p.demultiply();
+                [c.red(), c.green(), c.blue(), c.alpha()]
+            })
+            .collect();
+        Self { width: pixmap.width(), height: pixmap.height(), pixels }
+    }
+}
+
+impl Snapshot {
+    /// Number of pixels where some channel differs from `other` by more
+    /// than `tolerance`, or `None` if the sizes differ.
+    pub fn differing_pixels(&self, other: &Snapshot, tolerance: u8) -> Option<usize> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return None;
+        }
+        let differing = self
+            .pixels
+            .chunks_exact(4)
+            .zip(other.pixels.chunks_exact(4))
+            .filter(|(a, b)| a.iter().zip(b.iter()).any(|(x, y)| x.abs_diff(*y) > tolerance))
+            .count();
+        Some(differing)
+    }
+}
+
+/// Premultiplies straight RGBA bytes by their alpha.
+#[cfg(not(target_arch = "wasm32"))]
+fn premultiplied(pixels: &[u8]) -> Vec<u8> {
+    pixels
+        .chunks_exact(4)
+        .flat_map(|p| {
+            let c = tiny_skia::ColorU8::from_rgba(p[0], p[1], p[2], p[3]).premultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect()
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::examples::{build_example, ExampleKind};
+
+    #[test]
+    fn test_snapshot_matches_golden_image() {
+        let options = ExportOptions {
+            include_background: true,
+            ..Default::default()
+        };
+        let flowchart = build_example(ExampleKind::DecisionBranch);
+        let snapshot = snapshot_image(&flowchart, &options).unwrap();
+        assert_eq!(snapshot_image(&flowchart, &options).unwrap(), snapshot);
+        assert_eq!(Snapshot::from_png(&snapshot.to_png().unwrap()).unwrap(), snapshot);
+
+        // Set UPDATE_GOLDEN=1 to refresh the stored image after a deliberate change
+        let golden = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/decision_branch.png");
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(golden.parent().unwrap()).unwrap();
+            std::fs::write(&golden, snapshot.to_png().unwrap()).unwrap();
+        }
+        let expected = Snapshot::from_png(&std::fs::read(&golden).unwrap()).unwrap();
+        assert_eq!(snapshot.differing_pixels(&expected, 2), Some(0));
+    }
+}
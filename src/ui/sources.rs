@@ -0,0 +1,217 @@
+//! Scripts and templates kept in files next to the document.
+//!
+//! A transformer's script or a producer's message template can be linked to
+//! a file (see [`FlowchartNode::source_file`]), resolved relative to the
+//! document's folder like reference nodes are. The files are read again
+//! whenever a run starts; the node is updated if the file changed, and keeps
+//! its copy in the document if the file can't be read, so the flowchart still
+//! opens and runs without them. Linked files are only read on native builds.
+
+use super::state::{FlowchartApp, SourceFileStatus};
+use super::undo::UndoAction;
+use crate::types::*;
+#[cfg(not(target_arch = "wasm32"))]
+use eframe::egui;
+
+/// The node type with its script or template replaced by `content`, or
+/// `None` if it already matches.
+fn updated_from_source(node_type: &NodeType, content: &str) -> Result<Option<NodeType>, String> {
+    let mut updated = node_type.clone();
+    match &mut updated {
+        NodeType::Transformer { script, .. } => {
+            if script == content {
+                return Ok(None);
+            }
+            *script = content.to_string();
+        }
+        NodeType::Producer { message_template, .. } => {
+            let template: serde_json::Value =
+                serde_json::from_str(content).map_err(|e| format!("Invalid JSON template: {}", e))?;
+            if *message_template == template {
+                return Ok(None);
+            }
+            *message_template = template;
+        }
+        NodeType::Consumer { .. } | NodeType::Reference { .. } => {
+            return Err("Only transformer scripts and producer templates can be read from a file".to_string());
+        }
+    }
+    Ok(Some(updated))
+}
+
+impl FlowchartApp {
+    /// Links the script or template of `node_id` to `file`, or keeps it in
+    /// the document again if `None`, as one undo step.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_source_file(&mut self, node_id: NodeId, file: Option<String>) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        if node.source_file == file {
+            return;
+        }
+        let action = UndoAction::SourceFileChanged {
+            node_id,
+            old_file: node.source_file.clone(),
+            new_file: file.clone(),
+        };
+        node.source_file = file;
+        self.source_file_status.remove(&node_id);
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+    }
+
+    /// Reads the source files of all linked nodes; called when a run starts.
+    ///
+    /// Returns the number of nodes updated from files that changed.
+    pub fn load_source_files(&mut self) -> usize {
+        let linked: Vec<NodeId> = self
+            .flowchart
+            .nodes
+            .values()
+            .filter(|node| node.source_file.is_some())
+            .map(|node| node.id)
+            .collect();
+        linked
+            .into_iter()
+            .filter(|id| self.load_source_file(*id) == Some(SourceFileStatus::Updated))
+            .count()
+    }
+
+    /// Reads the source file of `node_id` and updates the node if it changed.
+    ///
+    /// Returns the outcome, also kept for the properties panel, or `None` if
+    /// the node isn't linked to a file.
+    pub(crate) fn load_source_file(&mut self, node_id: NodeId) -> Option<SourceFileStatus> {
+        let node = self.flowchart.nodes.get(&node_id)?;
+        let path = self.resolve_reference_path(node.source_file.as_deref()?);
+        let status = match read_source_file(&path).and_then(|content| updated_from_source(&node.node_type, &content)) {
+            Ok(None) => SourceFileStatus::Unchanged,
+            Ok(Some(new_node_type)) => {
+                let action = UndoAction::PropertyChanged {
+                    node_id,
+                    old_node_type: node.node_type.clone(),
+                    new_node_type: new_node_type.clone(),
+                };
+                if let Some(node) = self.flowchart.nodes.get_mut(&node_id) {
+                    node.node_type = new_node_type;
+                }
+                self.file.mark_action(&action);
+                self.undo_history.push_action(action);
+                if self.interaction.selected_node == Some(node_id) {
+                    self.clear_temp_editing_values();
+                }
+                SourceFileStatus::Updated
+            }
+            Err(error) => SourceFileStatus::Failed(error),
+        };
+        self.source_file_status.insert(node_id, status.clone());
+        Some(status)
+    }
+
+    /// Draws the source file field of a transformer or producer in the
+    /// properties panel, with the outcome of the last read.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn draw_source_file_row(&mut self, ui: &mut egui::Ui, node: &FlowchartNode, what: &str) {
+        let mut text = self
+            .interaction
+            .temp_source_file
+            .clone()
+            .unwrap_or_else(|| node.source_file.clone().unwrap_or_default());
+        let mut reload = false;
+        ui.horizontal(|ui| {
+            ui.label("From file:");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut text)
+                    .hint_text("kept in the document")
+                    .desired_width(ui.available_width() - 50.0),
+            );
+            if response.changed() {
+                self.interaction.temp_source_file = Some(text.clone());
+            }
+            if response.lost_focus() {
+                if let Some(typed) = self.interaction.temp_source_file.take() {
+                    let typed = typed.trim();
+                    self.set_source_file(node.id, (!typed.is_empty()).then(|| typed.to_string()));
+                }
+            }
+            if ui
+                .add_enabled(node.source_file.is_some(), egui::Button::new("Load"))
+                .on_hover_text("Read the file now instead of when the next run starts")
+                .clicked()
+            {
+                reload = true;
+            }
+        });
+        if reload {
+            self.load_source_file(node.id);
+        }
+
+        let Some(file) = &node.source_file else {
+            return;
+        };
+        if self.file.current_path.is_none() && std::path::Path::new(file).is_relative() {
+            ui.weak("Save the document first so the path is found from its folder.");
+        }
+        match self.source_file_status.get(&node.id) {
+            Some(SourceFileStatus::Failed(error)) => {
+                ui.colored_label(egui::Color32::RED, format!("Using the saved copy: {}", error));
+            }
+            Some(SourceFileStatus::Updated) => {
+                ui.weak(format!("Updated the {} from the changed file.", what));
+            }
+            Some(SourceFileStatus::Unchanged) | None => {
+                ui.weak(format!("Edit the file to change the {}; it is read again when a run starts.", what));
+            }
+        }
+    }
+}
+
+/// Reads a linked source file.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_source_file(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))
+}
+
+/// Reads a linked source file; the browser can't, so the node keeps its copy.
+#[cfg(target_arch = "wasm32")]
+fn read_source_file(_path: &str) -> Result<String, String> {
+    Err("Linked files are only read by the desktop app".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_updated_from_source_detects_changes() {
+        let transformer = NodeType::Transformer {
+            script: "function transform(input) { return input; }".into(),
+            selected_outputs: None,
+            globals: Default::default(),
+            initial_globals: Default::default(),
+            retry: Default::default(),
+        };
+        assert_eq!(updated_from_source(&transformer, "function transform(input) { return input; }"), Ok(None));
+        let Ok(Some(NodeType::Transformer { script, .. })) = updated_from_source(&transformer, "// new") else {
+            panic!("script should be replaced");
+        };
+        assert_eq!(script, "// new");
+
+        let producer = NodeType::Producer {
+            message_template: json!({"id": 1}),
+            start_step: 0,
+            messages_per_cycle: 1,
+            steps_between_cycles: 1,
+            messages_produced: 0,
+        };
+        // Formatting differences alone are no change
+        assert_eq!(updated_from_source(&producer, "{\n  \"id\": 1\n}"), Ok(None));
+        assert!(matches!(
+            updated_from_source(&producer, "{\"id\": 2}"),
+            Ok(Some(NodeType::Producer { message_template, .. })) if message_template == json!({"id": 2})
+        ));
+        assert!(updated_from_source(&producer, "{not json").unwrap_err().contains("Invalid JSON"));
+    }
+}
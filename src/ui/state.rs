@@ -4,13 +4,21 @@
 //! current UI state, including canvas navigation, user interactions, context menus,
 //! and file operations.
 
-use super::undo::UndoHistory;
+use super::file_watch::FileWatcher;
+use super::highlighters::SyntaxTheme;
+use super::undo::{UndoAction, UndoHistory};
+use crate::analysis::{AutoGroupOptions, TidyOptions};
 use crate::examples::ExampleKind;
+use crate::inspect::MessageFilter;
 use crate::simulation::SimulationEngine;
+use crate::traffic::{ProducerProposal, TrafficRecord};
 use crate::types::*;
 use eframe::egui;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
 
 // ===== Export dialog and options types =====
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -19,36 +27,57 @@ pub enum ExportFormat {
     Png,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Which part of the flowchart an export covers.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ExportScope {
+    /// Every node and group
     WholeGraph,
+    /// The selected nodes, their connections and groups
     SelectionOnly,
+    /// Everything inside this world-space area, such as the visible canvas
+    Area(egui::Rect),
 }
 
+/// How exported connections are drawn.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionStyle {
+    /// Straight lines
     Straight,
+    /// Bezier curves bowed to one side
     Curved,
 }
 
+/// How node names are laid out in exports.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextWrappingMode {
+    /// One centered line
     Simple,
+    /// Wrapped to the node width using egui's text metrics, like the canvas
     CanvasLike,
 }
 
+/// Options for SVG/PNG export and snapshots.
 #[derive(Debug, Clone, Copy)]
 pub struct ExportOptions {
+    /// Which part of the flowchart to draw
     pub scope: ExportScope,
+    /// Draw the grid behind the flowchart
     pub include_grid: bool,
+    /// Fill the background with `background_color`
     pub include_background: bool,
+    /// Background fill color
     pub background_color: egui::Color32,
+    /// Space around the drawing, in pixels
     pub margin_px: f32,
+    /// How connections are drawn
     pub connection_style: ConnectionStyle,
+    /// Color of connections and their arrows
     pub stroke_color: egui::Color32,
+    /// Width of connection lines
     pub stroke_width: f32,
+    /// How node names are laid out
     pub text_wrapping: TextWrappingMode,
-    // PNG-only (native):
+    /// Scale of raster output (PNG and image snapshots)
     pub png_scale: f32,
 }
 
@@ -83,6 +112,43 @@ pub enum AutoArrangeMode {
     Grid,
     /// Place nodes in a single horizontal line
     Line,
+    /// Keep nodes in their swimlanes, ordered by dependency
+    Swimlanes,
+}
+
+/// Tuning of the force-directed layout
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ForceLayoutSettings {
+    /// Number of simulation steps the layout runs
+    pub iterations: usize,
+    /// How strongly every pair of nodes pushes apart
+    pub repulsion: f32,
+    /// How strongly connections pull their nodes towards the ideal length
+    pub attraction: f32,
+    /// Distance between the centers of connected nodes the layout aims for
+    pub ideal_edge_length: f32,
+}
+
+impl Default for ForceLayoutSettings {
+    fn default() -> Self {
+        Self {
+            iterations: 500,
+            repulsion: 50000.0,
+            attraction: 0.08,
+            ideal_edge_length: 210.0,
+        }
+    }
+}
+
+/// Pattern drawn on the canvas background when the grid is shown
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum GridStyle {
+    /// Continuous horizontal and vertical lines
+    #[default]
+    Lines,
+    /// Small dots at each grid intersection
+    Dots,
 }
 
 /// State related to canvas navigation and display.
@@ -98,6 +164,19 @@ pub struct CanvasState {
     pub zoom_factor: f32,
     /// Whether the grid should be displayed on the canvas
     pub show_grid: bool,
+    /// How the grid is drawn when `show_grid` is enabled
+    pub grid_style: GridStyle,
+    /// Custom canvas background color (RGB); `None` uses the theme default
+    pub background_color: Option<(u8, u8, u8)>,
+    /// Whether to draw a crosshair at the world origin
+    pub show_origin_marker: bool,
+    /// Draw nearly horizontal or vertical connections perfectly straight
+    pub snap_connections: bool,
+    /// Draw a sparkline of recent consumption inside consumer nodes while simulating
+    pub show_consumer_sparklines: bool,
+    /// Screen-space rect the canvas was last drawn in
+    #[serde(skip)]
+    pub screen_rect: egui::Rect,
 }
 
 impl Default for CanvasState {
@@ -106,6 +185,12 @@ impl Default for CanvasState {
             offset: egui::Vec2::ZERO,
             zoom_factor: 1.0,
             show_grid: true,
+            grid_style: GridStyle::Lines,
+            background_color: None,
+            show_origin_marker: false,
+            snap_connections: true,
+            show_consumer_sparklines: false,
+            screen_rect: egui::Rect::NOTHING,
         }
     }
 }
@@ -184,21 +269,27 @@ pub struct InteractionState {
     /// Start screen position for pending shift-connection gesture
     #[serde(skip)]
     pub pending_shift_start_screen_pos: Option<egui::Pos2>,
-    /// Currently selected connection index, if any
-    #[serde(skip)]
-    pub selected_connection: Option<usize>,
-    /// Temporary storage for producer properties while editing
+    /// Node being copied by an Alt+drag, connected to the copy on release
     #[serde(skip)]
-    pub temp_producer_start_step: String,
+    pub duplicating_from: Option<NodeId>,
+    /// Current mouse position while Alt+dragging a copy
     #[serde(skip)]
-    pub temp_producer_messages_per_cycle: String,
+    pub duplicate_drop_pos: Option<egui::Pos2>,
+    /// Currently selected connection index, if any
     #[serde(skip)]
-    pub temp_producer_steps_between: String,
+    pub selected_connection: Option<usize>,
+    /// Temporary storage for the producer message template while editing
     #[serde(skip)]
     pub temp_producer_message_template: String,
     /// Temporary storage for transformer script while editing
     #[serde(skip)]
     pub temp_transformer_script: String,
+    /// Source file path being typed, until the field loses focus
+    #[serde(skip)]
+    pub temp_source_file: Option<String>,
+    /// Focus the transformer script editor the next time the properties panel draws
+    #[serde(skip)]
+    pub focus_script_editor: bool,
     /// Temporary storage for transformer globals editing: per-key JSON strings
     #[serde(skip)]
     pub temp_transformer_globals_edits: std::collections::HashMap<String, String>,
@@ -210,6 +301,34 @@ pub struct InteractionState {
     /// Track which node's globals are currently loaded in temp_transformer_globals_edits
     #[serde(skip)]
     pub temp_globals_node_id: Option<NodeId>,
+    /// Pending auto-connect offer for a freshly created node, shown as a popup
+    #[serde(skip)]
+    pub auto_connect_suggestion: Option<AutoConnectSuggestion>,
+    /// Problem with the accept filter last entered for a consumer node
+    #[serde(skip)]
+    pub accept_filter_error: Option<(NodeId, String)>,
+}
+
+/// An offer to wire a newly created node into the surrounding graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoConnectSuggestion {
+    /// Insert the node into an existing connection, splitting `from -> to`
+    /// into `from -> node -> to`
+    SplitConnection {
+        /// The newly created node
+        node_id: NodeId,
+        /// Source of the connection to split
+        from: NodeId,
+        /// Destination of the connection to split
+        to: NodeId,
+    },
+    /// Add a single connection between the new node and a nearby node
+    Connect {
+        /// Source node of the proposed connection
+        from: NodeId,
+        /// Destination node of the proposed connection
+        to: NodeId,
+    },
 }
 
 impl Default for InteractionState {
@@ -238,16 +357,153 @@ impl Default for InteractionState {
             connection_draw_pos: None,
             pending_shift_connection_from: None,
             pending_shift_start_screen_pos: None,
+            duplicating_from: None,
+            duplicate_drop_pos: None,
             selected_connection: None,
-            temp_producer_start_step: String::new(),
-            temp_producer_messages_per_cycle: String::new(),
-            temp_producer_steps_between: String::new(),
             temp_producer_message_template: String::new(),
             temp_transformer_script: String::new(),
+            temp_source_file: None,
+            focus_script_editor: false,
             temp_transformer_globals_edits: Default::default(),
             temp_new_global_key: String::new(),
             temp_new_global_value: String::new(),
             temp_globals_node_id: None,
+            auto_connect_suggestion: None,
+            accept_filter_error: None,
+        }
+    }
+}
+
+/// Filters applied in the connection and node history inspectors.
+#[derive(Debug, Clone, Default)]
+pub struct InspectorState {
+    /// Filter for messages in transit on the selected connection
+    pub connection_filter: MessageFilter,
+    /// Filter for the selected node's message history
+    pub history_filter: MessageFilter,
+    /// Number of the selected connection's messages listed so far
+    pub connection_messages_shown: usize,
+    /// Connection that `connection_messages_shown` belongs to
+    pub connection_messages_of: Option<usize>,
+    /// Older messages of the selected node read back from disk
+    pub older_history: Option<OlderHistory>,
+}
+
+/// Spilled node history read back for the history inspector.
+#[derive(Debug, Clone)]
+pub struct OlderHistory {
+    /// Node the entries belong to
+    pub node_id: NodeId,
+    /// How many of the newest spilled entries to show
+    pub requested: usize,
+    /// The node's spilled entry count and `requested` when last read
+    pub read_for: Option<(usize, usize)>,
+    /// Entries read, oldest first
+    pub entries: Vec<crate::inspect::HistoryEntry>,
+    /// Why the entries couldn't be read
+    pub error: Option<String>,
+}
+
+/// Filters and scrolling of the events panel.
+#[derive(Debug, Clone)]
+pub struct EventsPanelState {
+    /// Which events are listed
+    pub filter: crate::events::EventFilter,
+    /// Keep the newest events in view as they arrive
+    pub follow: bool,
+}
+
+impl Default for EventsPanelState {
+    fn default() -> Self {
+        Self {
+            filter: Default::default(),
+            follow: true,
+        }
+    }
+}
+
+/// A parameter as it is being edited in the parameters window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterDraft {
+    /// Name of the parameter in the document
+    pub original: String,
+    /// Name as typed
+    pub name: String,
+    /// Value as typed
+    pub value: String,
+}
+
+/// Edits in progress in the parameters window.
+#[derive(Debug, Clone)]
+pub struct ParametersDialogState {
+    /// The parameters the drafts were made from
+    pub drafted_from: Option<crate::parameters::Parameters>,
+    /// One draft per parameter, in name order
+    pub drafts: Vec<ParameterDraft>,
+    /// Name of the parameter to add
+    pub new_name: String,
+    /// Value of the parameter to add
+    pub new_value: String,
+    /// Node of the binding to add
+    pub bind_node: Option<NodeId>,
+    /// Property of the binding to add
+    pub bind_property: crate::parameters::BoundProperty,
+    /// Parameter of the binding to add
+    pub bind_parameter: String,
+    /// Why the last edit was refused
+    pub error: Option<String>,
+    /// Bindings the last change couldn't apply
+    pub problems: Vec<String>,
+}
+
+impl Default for ParametersDialogState {
+    fn default() -> Self {
+        Self {
+            drafted_from: None,
+            drafts: Vec::new(),
+            new_name: String::new(),
+            new_value: String::new(),
+            bind_node: None,
+            bind_property: crate::parameters::BoundProperty::ConsumptionRate,
+            bind_parameter: String::new(),
+            error: None,
+            problems: Vec::new(),
+        }
+    }
+}
+
+/// Parameter sweep dialog settings and the sweep they started.
+#[derive(Debug, Clone)]
+pub struct SweepState {
+    /// First swept parameter (heatmap columns)
+    pub x: crate::sweep::SweepAxis,
+    /// Second swept parameter (heatmap rows), used when `two_parameters` is set
+    pub y: crate::sweep::SweepAxis,
+    /// Whether a second parameter is swept
+    pub two_parameters: bool,
+    /// Steps per run when the measurement window is open-ended
+    pub steps: u64,
+    /// Metric the heatmap shows
+    pub metric: crate::sweep::SweepMetric,
+    /// The current or last sweep
+    pub sweep: Option<crate::sweep::Sweep>,
+    /// Whether the sweep is still running combinations
+    pub running: bool,
+    /// Why the sweep could not start
+    pub error: Option<String>,
+}
+
+impl Default for SweepState {
+    fn default() -> Self {
+        Self {
+            x: Default::default(),
+            y: Default::default(),
+            two_parameters: false,
+            steps: crate::constants::SWEEP_DEFAULT_STEPS,
+            metric: Default::default(),
+            sweep: None,
+            running: false,
+            error: None,
         }
     }
 }
@@ -270,6 +526,12 @@ pub struct ContextMenuState {
     /// Flag to prevent context menu from closing immediately after opening
     #[serde(skip)]
     pub just_opened: bool,
+    /// Connection (source, destination) under the cursor when the menu was opened, if any
+    #[serde(skip)]
+    pub target_connection: Option<(NodeId, NodeId)>,
+    /// Node under the cursor when the menu was opened, if any
+    #[serde(skip)]
+    pub target_node: Option<NodeId>,
 }
 
 impl Default for ContextMenuState {
@@ -279,6 +541,8 @@ impl Default for ContextMenuState {
             screen_pos: (0.0, 0.0),
             world_pos: (0.0, 0.0),
             just_opened: false,
+            target_connection: None,
+            target_node: None,
         }
     }
 }
@@ -295,6 +559,13 @@ pub struct FileState {
     /// Flag indicating if the flowchart has unsaved changes
     #[serde(skip)]
     pub has_unsaved_changes: bool,
+    /// Per-subsystem breakdown of what changed since the last save or load
+    #[serde(skip)]
+    pub changes: ChangeSummary,
+    /// Set by every edit; the next simulation step reschedules the engine's
+    /// work from the whole flowchart
+    #[serde(skip)]
+    pub edited_since_step: bool,
     /// When true, saving is disabled because the current flowchart was loaded from a built-in example
     #[serde(skip)]
     pub is_example_readonly: bool,
@@ -303,23 +574,38 @@ pub struct FileState {
     pub pending_save_operation: Option<PendingSaveOperation>,
     #[serde(skip)]
     pub pending_load_operation: Option<PendingLoadOperation>,
+    /// Pending list/delete request for the browser's document store
+    #[serde(skip)]
+    pub pending_store_operation: Option<StoreOperation>,
     /// Channel for receiving file operation results from async contexts
     #[serde(skip)]
     pub file_operation_sender: Option<Sender<FileOperationResult>>,
     #[serde(skip)]
     pub file_operation_receiver: Option<Receiver<FileOperationResult>>,
-    /// Whether to show an unsaved-changes confirmation dialog
-    #[serde(skip)]
-    pub show_unsaved_dialog: bool,
-    /// The action the user attempted that requires confirmation (e.g., New or Quit)
-    #[serde(skip)]
-    pub pending_confirm_action: Option<PendingConfirmAction>,
     /// If the pending action is to load an example, store which example here
     #[serde(skip)]
     pub pending_example: Option<ExampleKind>,
+    /// If the pending action is to follow a reference node or open a handed-off file, the path to open
+    #[serde(skip)]
+    pub pending_reference_path: Option<String>,
     /// One-shot flag to allow the next close request to proceed after user confirmation (native only)
     #[serde(skip)]
     pub allow_close_on_next_request: bool,
+    /// Watches the open file for changes made by other programs (native only)
+    #[serde(skip)]
+    pub watcher: Option<FileWatcher>,
+    /// Contents of the open file as last loaded or saved, to tell our own writes apart
+    #[serde(skip)]
+    pub disk_snapshot: Option<String>,
+    /// Time of the latest change event on the open file not yet re-read
+    #[serde(skip)]
+    pub disk_change_pending_since: Option<f64>,
+    /// External change shown in the reload prompt
+    #[serde(skip)]
+    pub external_change: Option<ExternalChange>,
+    /// Large file being read in the background, shown in the progress dialog
+    #[serde(skip)]
+    pub load_progress: Option<LoadProgress>,
 }
 
 impl Default for FileState {
@@ -328,15 +614,22 @@ impl Default for FileState {
         Self {
             current_path: None,
             has_unsaved_changes: false,
+            changes: ChangeSummary::default(),
+            edited_since_step: false,
             is_example_readonly: false,
             pending_save_operation: None,
             pending_load_operation: None,
+            pending_store_operation: None,
             file_operation_sender: Some(sender),
             file_operation_receiver: Some(receiver),
-            show_unsaved_dialog: false,
-            pending_confirm_action: None,
             pending_example: None,
+            pending_reference_path: None,
             allow_close_on_next_request: false,
+            watcher: None,
+            disk_snapshot: None,
+            disk_change_pending_since: None,
+            external_change: None,
+            load_progress: None,
         }
     }
 }
@@ -350,6 +643,435 @@ impl FileState {
     pub fn has_unsaved_changes_effective(&self) -> bool {
         self.has_unsaved_changes && !self.is_example_readonly
     }
+
+    /// Records a single change and marks the flowchart as dirty.
+    pub fn mark_changed(&mut self, kind: ChangeKind) {
+        self.changes.record(kind);
+        self.has_unsaved_changes = true;
+        self.edited_since_step = true;
+    }
+
+    /// Records the change described by an undo action and marks the flowchart as dirty.
+    ///
+    /// The action is interpreted as the edit that just happened, so when undoing
+    /// or redoing, pass the inverse action returned by `apply_undo`.
+    pub fn mark_action(&mut self, action: &UndoAction) {
+        self.changes.record_action(action);
+        self.has_unsaved_changes = true;
+        self.edited_since_step = true;
+    }
+
+    /// Clears the dirty flag and the change breakdown (after save, load or new).
+    pub fn mark_saved(&mut self) {
+        self.has_unsaved_changes = false;
+        self.changes = ChangeSummary::default();
+    }
+
+    /// Human-readable summary of unsaved changes, e.g. "3 nodes edited, 1 connection added".
+    ///
+    /// Returns `None` when there is nothing to report.
+    pub fn unsaved_changes_description(&self) -> Option<String> {
+        if !self.has_unsaved_changes_effective() {
+            return None;
+        }
+        Some(self.changes.describe())
+    }
+}
+
+/// A single kind of edit, used to build a [`ChangeSummary`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// A node was created
+    NodeAdded(NodeId),
+    /// A node was deleted
+    NodeRemoved(NodeId),
+    /// A node's name or properties changed
+    NodeEdited(NodeId),
+    /// A node was repositioned
+    NodeMoved(NodeId),
+    /// A connection was created
+    ConnectionAdded,
+    /// A connection was deleted
+    ConnectionRemoved,
+    /// The connection between two nodes changed in place
+    ConnectionEdited(NodeId, NodeId),
+    /// A group was created, deleted, renamed or had its members changed
+    GroupChanged(GroupId),
+    /// Document parameters or their bindings changed
+    ParametersChanged,
+    /// Swimlane mode or the lane definitions changed
+    SwimlanesChanged,
+    /// The warm-up or measurement window changed
+    RunConfigChanged,
+    /// Conditional formatting rules changed
+    FormatRulesChanged,
+    /// A named selection was saved, replaced or deleted
+    SavedSelectionsChanged,
+}
+
+/// Accumulated changes since the last save, tracked per subsystem.
+///
+/// Node sets are kept net of each other: a node created and deleted again
+/// before saving does not show up at all, and edits or moves of a node that
+/// is later removed are folded into the removal.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeSummary {
+    /// Nodes created since the last save
+    pub nodes_added: HashSet<NodeId>,
+    /// Nodes deleted since the last save (that existed at save time)
+    pub nodes_removed: HashSet<NodeId>,
+    /// Nodes whose name or properties changed
+    pub nodes_edited: HashSet<NodeId>,
+    /// Nodes whose position changed
+    pub nodes_moved: HashSet<NodeId>,
+    /// Number of connections created
+    pub connections_added: usize,
+    /// Number of connections deleted
+    pub connections_removed: usize,
+    /// Connections, by their ends, that changed in place
+    pub connections_edited: HashSet<(NodeId, NodeId)>,
+    /// Groups that were created, deleted or modified
+    pub groups_changed: HashSet<GroupId>,
+    /// Whether document parameters or their bindings changed
+    pub parameters_changed: bool,
+    /// Whether swimlane settings changed
+    pub swimlanes_changed: bool,
+    /// Whether the run statistics window changed
+    pub run_config_changed: bool,
+    /// Whether the conditional formatting rules changed
+    pub format_rules_changed: bool,
+    /// Whether the saved selections changed
+    pub saved_selections_changed: bool,
+}
+
+impl ChangeSummary {
+    /// Returns true if no changes have been recorded.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Adds one change to the summary.
+    pub fn record(&mut self, kind: ChangeKind) {
+        match kind {
+            ChangeKind::NodeAdded(id) => {
+                if !self.nodes_removed.remove(&id) {
+                    self.nodes_added.insert(id);
+                }
+            }
+            ChangeKind::NodeRemoved(id) => {
+                self.nodes_edited.remove(&id);
+                self.nodes_moved.remove(&id);
+                if !self.nodes_added.remove(&id) {
+                    self.nodes_removed.insert(id);
+                }
+            }
+            ChangeKind::NodeEdited(id) => {
+                if !self.nodes_added.contains(&id) {
+                    self.nodes_edited.insert(id);
+                }
+            }
+            ChangeKind::NodeMoved(id) => {
+                if !self.nodes_added.contains(&id) {
+                    self.nodes_moved.insert(id);
+                }
+            }
+            ChangeKind::ConnectionAdded => self.connections_added += 1,
+            ChangeKind::ConnectionRemoved => self.connections_removed += 1,
+            ChangeKind::ConnectionEdited(from, to) => {
+                self.connections_edited.insert((from, to));
+            }
+            ChangeKind::GroupChanged(id) => {
+                self.groups_changed.insert(id);
+            }
+            ChangeKind::ParametersChanged => self.parameters_changed = true,
+            ChangeKind::SwimlanesChanged => self.swimlanes_changed = true,
+            ChangeKind::RunConfigChanged => self.run_config_changed = true,
+            ChangeKind::FormatRulesChanged => self.format_rules_changed = true,
+            ChangeKind::SavedSelectionsChanged => self.saved_selections_changed = true,
+        }
+    }
+
+    /// Adds the changes described by an undo action.
+    pub fn record_action(&mut self, action: &UndoAction) {
+        match action {
+            UndoAction::NodeMoved { node_id, .. } => self.record(ChangeKind::NodeMoved(*node_id)),
+            UndoAction::MultipleNodesMoved { new_positions, .. } => {
+                for (id, _) in new_positions {
+                    self.record(ChangeKind::NodeMoved(*id));
+                }
+            }
+            UndoAction::PropertyChanged { node_id, .. }
+            | UndoAction::NodeRenamed { node_id, .. }
+            | UndoAction::LaneAssigned { node_id, .. }
+            | UndoAction::SourceFileChanged { node_id, .. }
+            | UndoAction::FailureChanged { node_id, .. }
+            | UndoAction::TapChanged { node_id, .. }
+            | UndoAction::TimingChanged { node_id, .. } => {
+                self.record(ChangeKind::NodeEdited(*node_id))
+            }
+            UndoAction::NodeDeleted { node, connections } => {
+                self.record(ChangeKind::NodeRemoved(node.id));
+                for _ in connections {
+                    self.record(ChangeKind::ConnectionRemoved);
+                }
+            }
+            UndoAction::MultipleNodesDeleted { nodes, connections } => {
+                for node in nodes {
+                    self.record(ChangeKind::NodeRemoved(node.id));
+                }
+                for _ in connections {
+                    self.record(ChangeKind::ConnectionRemoved);
+                }
+            }
+            UndoAction::ConnectionDeleted { .. } => self.record(ChangeKind::ConnectionRemoved),
+            UndoAction::NodeCreated { node_id } => self.record(ChangeKind::NodeAdded(*node_id)),
+            UndoAction::ConnectionCreated { .. } | UndoAction::ConnectionRestored { .. } => {
+                self.record(ChangeKind::ConnectionAdded)
+            }
+            UndoAction::ConnectionMessagesChanged { from, to, .. }
+            | UndoAction::ConnectionStyleChanged { from, to, .. } => {
+                self.record(ChangeKind::ConnectionEdited(*from, *to))
+            }
+            UndoAction::GroupDeleted { group } => self.record(ChangeKind::GroupChanged(group.id)),
+            UndoAction::GroupCreated { group_id } => self.record(ChangeKind::GroupChanged(*group_id)),
+            UndoAction::NodesPinned { node_ids, .. } => {
+                for id in node_ids {
+                    self.record(ChangeKind::NodeEdited(*id));
+                }
+            }
+            UndoAction::ZOrderChanged { nodes, groups } => {
+                for (id, _, _) in nodes {
+                    self.record(ChangeKind::NodeEdited(*id));
+                }
+                for (id, _, _) in groups {
+                    self.record(ChangeKind::GroupChanged(*id));
+                }
+            }
+            UndoAction::ParametersChanged { .. } => self.record(ChangeKind::ParametersChanged),
+            UndoAction::SwimlanesChanged { .. } => self.record(ChangeKind::SwimlanesChanged),
+            UndoAction::RunConfigChanged { .. } => self.record(ChangeKind::RunConfigChanged),
+            UndoAction::FormatRulesChanged { .. } => self.record(ChangeKind::FormatRulesChanged),
+            UndoAction::SavedSelectionsChanged { .. } => self.record(ChangeKind::SavedSelectionsChanged),
+            UndoAction::Batch { actions } => {
+                for a in actions {
+                    self.record_action(a);
+                }
+            }
+        }
+    }
+
+    /// Formats the summary as a comma-separated list, e.g. "3 nodes edited, 1 connection added".
+    ///
+    /// Returns "No tracked changes" when the dirty flag was set without details.
+    pub fn describe(&self) -> String {
+        fn part(parts: &mut Vec<String>, count: usize, singular: &str, plural: &str, verb: &str) {
+            if count > 0 {
+                let noun = if count == 1 { singular } else { plural };
+                parts.push(format!("{} {} {}", count, noun, verb));
+            }
+        }
+
+        let mut parts = Vec::new();
+        part(&mut parts, self.nodes_added.len(), "node", "nodes", "added");
+        part(&mut parts, self.nodes_removed.len(), "node", "nodes", "removed");
+        part(&mut parts, self.nodes_edited.len(), "node", "nodes", "edited");
+        part(&mut parts, self.nodes_moved.len(), "node", "nodes", "moved");
+        part(&mut parts, self.connections_added, "connection", "connections", "added");
+        part(&mut parts, self.connections_removed, "connection", "connections", "removed");
+        part(&mut parts, self.connections_edited.len(), "connection", "connections", "edited");
+        part(&mut parts, self.groups_changed.len(), "group", "groups", "changed");
+        if self.parameters_changed {
+            parts.push("parameters changed".to_string());
+        }
+        if self.swimlanes_changed {
+            parts.push("swimlanes changed".to_string());
+        }
+        if self.run_config_changed {
+            parts.push("run settings changed".to_string());
+        }
+        if self.format_rules_changed {
+            parts.push("formatting rules changed".to_string());
+        }
+        if self.saved_selections_changed {
+            parts.push("saved selections changed".to_string());
+        }
+
+        if parts.is_empty() {
+            "No tracked changes".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Destructive actions that can ask for confirmation first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmKind {
+    /// Deleting more than one node at once
+    DeleteNodes,
+    /// Stopping a run that has made progress, which clears it
+    ResetSimulation,
+    /// Saving over a file that changed since it was opened or saved
+    OverwriteFile,
+}
+
+impl ConfirmKind {
+    /// Every kind, in the order Preferences lists them.
+    pub const ALL: [ConfirmKind; 3] = [
+        ConfirmKind::DeleteNodes,
+        ConfirmKind::ResetSimulation,
+        ConfirmKind::OverwriteFile,
+    ];
+
+    /// Name shown in Preferences, after "Ask before".
+    pub fn label(self) -> &'static str {
+        match self {
+            ConfirmKind::DeleteNodes => "Deleting several nodes",
+            ConfirmKind::ResetSimulation => "Resetting the simulation",
+            ConfirmKind::OverwriteFile => "Overwriting a file",
+        }
+    }
+}
+
+/// Which destructive actions ask for confirmation first; all do by default.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ConfirmationSettings {
+    /// Ask before deleting more than one node
+    pub delete_nodes: bool,
+    /// Ask before stopping a run that has made progress
+    pub reset_simulation: bool,
+    /// Ask before saving over a file that changed elsewhere
+    pub overwrite_file: bool,
+}
+
+impl Default for ConfirmationSettings {
+    fn default() -> Self {
+        Self {
+            delete_nodes: true,
+            reset_simulation: true,
+            overwrite_file: true,
+        }
+    }
+}
+
+impl ConfirmationSettings {
+    /// Returns true if actions of `kind` ask for confirmation.
+    pub fn asks(&self, kind: ConfirmKind) -> bool {
+        match kind {
+            ConfirmKind::DeleteNodes => self.delete_nodes,
+            ConfirmKind::ResetSimulation => self.reset_simulation,
+            ConfirmKind::OverwriteFile => self.overwrite_file,
+        }
+    }
+
+    /// Turns confirmation of `kind` on or off.
+    pub fn set_asks(&mut self, kind: ConfirmKind, asks: bool) {
+        let flag = match kind {
+            ConfirmKind::DeleteNodes => &mut self.delete_nodes,
+            ConfirmKind::ResetSimulation => &mut self.reset_simulation,
+            ConfirmKind::OverwriteFile => &mut self.overwrite_file,
+        };
+        *flag = asks;
+    }
+}
+
+/// A destructive action waiting for the user to confirm it.
+#[derive(Debug)]
+pub enum PendingConfirmation {
+    /// Delete these nodes and their connections
+    DeleteNodes(Vec<NodeId>),
+    /// Stop the simulation and clear the run
+    ResetSimulation,
+    /// Run this save, which replaces an existing file or stored document
+    Overwrite(PendingSaveOperation),
+}
+
+impl PendingConfirmation {
+    /// The setting that decides whether this action asks first.
+    pub fn kind(&self) -> ConfirmKind {
+        match self {
+            PendingConfirmation::DeleteNodes(_) => ConfirmKind::DeleteNodes,
+            PendingConfirmation::ResetSimulation => ConfirmKind::ResetSimulation,
+            PendingConfirmation::Overwrite(_) => ConfirmKind::OverwriteFile,
+        }
+    }
+}
+
+/// A dialog that needs an answer before the app carries on with something.
+#[derive(Debug)]
+pub enum Modal {
+    /// Discarding unsaved changes for this action
+    UnsavedChanges(PendingConfirmAction),
+    /// A destructive action
+    Confirm(PendingConfirmation),
+    /// Something that failed, with a title and the reason
+    Error {
+        /// Dialog title
+        title: String,
+        /// What went wrong
+        message: String,
+    },
+}
+
+impl Modal {
+    /// Whether `other` asks the same question, so opening it again adds nothing.
+    fn same_as(&self, other: &Modal) -> bool {
+        match (self, other) {
+            (Modal::UnsavedChanges(a), Modal::UnsavedChanges(b)) => a == b,
+            (Modal::Confirm(a), Modal::Confirm(b)) => a.kind() == b.kind(),
+            (
+                Modal::Error { title, message },
+                Modal::Error {
+                    title: other_title,
+                    message: other_message,
+                },
+            ) => title == other_title && message == other_message,
+            _ => false,
+        }
+    }
+}
+
+/// Queue of modal dialogs. Only the oldest is shown; the next appears once
+/// it is answered.
+#[derive(Debug, Default)]
+pub struct DialogManager {
+    queue: VecDeque<Modal>,
+    /// "Don't ask again" checkbox of the confirmation dialog being shown
+    pub dont_ask_again: bool,
+}
+
+impl DialogManager {
+    /// Queues `modal` unless the same question is already waiting.
+    pub fn open(&mut self, modal: Modal) {
+        if !self.queue.iter().any(|queued| queued.same_as(&modal)) {
+            self.queue.push_back(modal);
+        }
+    }
+
+    /// Queues an error dialog.
+    pub fn error(&mut self, title: impl Into<String>, message: impl Into<String>) {
+        self.open(Modal::Error {
+            title: title.into(),
+            message: message.into(),
+        });
+    }
+
+    /// The dialog being shown.
+    pub fn current(&self) -> Option<&Modal> {
+        self.queue.front()
+    }
+
+    /// Removes the dialog being shown and returns it, so the next one shows.
+    pub fn close(&mut self) -> Option<Modal> {
+        self.dont_ask_again = false;
+        self.queue.pop_front()
+    }
+
+    /// Whether any dialog is waiting.
+    pub fn is_open(&self) -> bool {
+        !self.queue.is_empty()
+    }
 }
 
 /// Represents a pending save operation type.
@@ -359,6 +1081,10 @@ pub enum PendingSaveOperation {
     SaveAs,
     /// Save to the existing file path
     Save,
+    /// Write the selected nodes to a new file without changing the current path
+    ExportSelection,
+    /// Store the document in the browser's document store under this name (web only)
+    BrowserDocument(String),
 }
 
 /// Represents a pending load operation type.
@@ -366,6 +1092,284 @@ pub enum PendingSaveOperation {
 pub enum PendingLoadOperation {
     /// Load from a file (show file picker)
     Load,
+    /// Pick a file whose contents are merged into the current flowchart
+    Merge,
+    /// Load a known file path without showing a picker (e.g. a reference node's target)
+    OpenPath(String),
+    /// Load a document from the browser's document store by name (web only)
+    BrowserDocument(String),
+    /// Pick a single-file plugin to install
+    Plugin,
+    /// Pick an exported run summary to compare the current run against
+    RunBaseline,
+    /// Pick a BPMN 2.0 file whose processes are merged into the current flowchart
+    ImportBpmn,
+    /// Pick a JSON Lines traffic trace to propose producers from
+    TrafficTrace,
+    /// Pick a CSV table of nodes to add
+    NodeCsv,
+}
+
+/// A request to the browser's document store that neither saves nor loads the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreOperation {
+    /// List the stored documents
+    List,
+    /// Delete the stored document with this name
+    Delete(String),
+}
+
+/// Summary of a flowchart kept in the browser's document store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredDocumentInfo {
+    /// Name the document was saved under
+    pub name: String,
+    /// When it was last saved, in milliseconds since the Unix epoch
+    pub saved_at: f64,
+    /// Size of the stored JSON in bytes
+    pub size: usize,
+}
+
+/// Whether the browser documents dialog is choosing a document to open or a name to save under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserDocumentsMode {
+    /// Pick a stored document to open
+    Open,
+    /// Pick a name to store the current document under
+    Save,
+}
+
+/// State of the browser documents dialog (web build).
+#[derive(Debug, Clone)]
+pub struct BrowserDocumentsState {
+    /// What the dialog is for
+    pub mode: BrowserDocumentsMode,
+    /// Stored documents, newest first; `None` while the list is loading
+    pub documents: Option<Vec<StoredDocumentInfo>>,
+    /// Last error reported by the document store
+    pub error: Option<String>,
+    /// Name being typed in save mode
+    pub name: String,
+    /// Document waiting for the user to confirm its deletion
+    pub confirm_delete: Option<String>,
+}
+
+/// Staged input of the producer test-data generator dialog.
+#[derive(Debug, Clone)]
+pub struct DataGeneratorState {
+    /// Producer whose template is being generated
+    pub node_id: NodeId,
+    /// Field spec text, one `name: type` per line
+    pub spec: String,
+    /// Seed for the preview samples; rerolling picks a new one
+    pub seed: u64,
+}
+
+/// File formats a recorded run can be saved in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordingFormat {
+    /// An animated GIF that loops forever
+    #[default]
+    Gif,
+    /// A ZIP archive with one PNG per frame, for video editors and encoders
+    PngFrames,
+}
+
+impl RecordingFormat {
+    /// Name shown in the recording dialog.
+    pub fn label(self) -> &'static str {
+        match self {
+            RecordingFormat::Gif => "Animated GIF",
+            RecordingFormat::PngFrames => "PNG frames (ZIP)",
+        }
+    }
+
+    /// File extension of the format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordingFormat::Gif => "gif",
+            RecordingFormat::PngFrames => "zip",
+        }
+    }
+}
+
+/// Settings of the next run recording (remembered within the session).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordingOptions {
+    /// Number of simulation steps to record, one frame each
+    pub steps: u32,
+    /// How long each frame is shown in the GIF, in milliseconds
+    pub frame_delay_ms: u32,
+    /// File format to save the frames in
+    pub format: RecordingFormat,
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        Self {
+            steps: 50,
+            frame_delay_ms: 200,
+            format: RecordingFormat::Gif,
+        }
+    }
+}
+
+/// A run being recorded: the canvas is captured, then the simulation is
+/// stepped, until `options.steps` steps are captured after the first frame.
+#[derive(Debug, Clone)]
+pub struct RunRecording {
+    /// Settings the recording was started with
+    pub options: RecordingOptions,
+    /// Captured canvas frames, oldest first
+    pub frames: Vec<super::snapshot::Snapshot>,
+    /// Whether a screenshot was requested and hasn't arrived yet
+    pub awaiting_frame: bool,
+}
+
+/// Final state of the last stopped run, kept read-only until the next run
+/// starts.
+#[derive(Debug, Clone)]
+pub struct LastRun {
+    /// Steps the run took before it was stopped
+    pub steps: u64,
+    /// Statistics of the run over its measurement window
+    pub summary: crate::metrics::RunSummary,
+    /// Messages left on connections, with the names their ends had at the
+    /// time; connections without messages are left out
+    pub in_flight: Vec<(String, String, Vec<Message>)>,
+}
+
+/// A recorded traffic trace being turned into producers.
+#[derive(Debug, Clone)]
+pub struct TrafficImportState {
+    /// File name of the trace
+    pub source: String,
+    /// Parsed records, empty if the trace couldn't be read
+    pub records: Vec<TrafficRecord>,
+    /// Why the trace couldn't be read, if it couldn't
+    pub error: Option<String>,
+    /// Recorded seconds per simulation step
+    pub seconds_per_step: f64,
+    /// Producers proposed for `seconds_per_step`
+    pub proposals: Vec<ProducerProposal>,
+    /// Whether each proposal is added
+    pub selected: Vec<bool>,
+}
+
+/// A CSV table of nodes being reviewed before the nodes are added.
+#[derive(Debug, Clone)]
+pub struct NodeCsvImportState {
+    /// File name of the table
+    pub source: String,
+    /// The nodes read, empty if the table couldn't be read
+    pub table: crate::csv_import::NodeCsv,
+    /// Why the table couldn't be read, if it couldn't
+    pub error: Option<String>,
+    /// Whether the `downstream` column is turned into connections
+    pub connect: bool,
+}
+
+/// A pan offset and zoom factor of the canvas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewPoint {
+    /// Pan offset (in screen space)
+    pub offset: egui::Vec2,
+    /// Zoom factor
+    pub zoom: f32,
+}
+
+/// Earlier and later views of the canvas, kept apart from the document's
+/// undo history.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ViewHistory {
+    /// Views to step back to, the most recent last
+    pub back: Vec<ViewPoint>,
+    /// Views stepped back from, the most recent last
+    pub forward: Vec<ViewPoint>,
+    /// View seen on the last frame
+    pub last: Option<ViewPoint>,
+    /// Frame time of the last change of view, `None` once the view was
+    /// stepped to or hasn't moved yet
+    pub last_change: Option<f64>,
+}
+
+/// Nodes gliding from their old positions to those chosen by an auto-layout.
+///
+/// The undo entry and the document already hold the final positions as
+/// far as history is concerned; the animation only moves nodes per frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutAnimation {
+    /// Frame time the animation started at
+    pub started: f64,
+    /// The nodes that move
+    pub moves: Vec<NodeMove>,
+}
+
+/// One node moving in a [`LayoutAnimation`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NodeMove {
+    /// The moving node
+    pub node_id: NodeId,
+    /// Position before the layout
+    pub from: (f32, f32),
+    /// Position chosen by the layout
+    pub to: (f32, f32),
+}
+
+/// An auto-layout computed but not applied, drawn as ghosts on the canvas.
+#[derive(Debug, Clone)]
+pub struct LayoutPreview {
+    /// The layout's undo entry, recorded when the preview is applied
+    pub action: UndoAction,
+    /// Applying this with `apply_undo` performs the layout
+    pub redo: UndoAction,
+    /// Proposed position of every node the layout moves
+    pub positions: Vec<(NodeId, (f32, f32))>,
+}
+
+/// Staged input of the Replicate window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicateDialogState {
+    /// The nodes to replicate, in selection order
+    pub nodes: Vec<NodeId>,
+    /// Number of copies, including the selection itself
+    pub count: u32,
+    /// Insert a fan-out transformer in front of the copies
+    pub fan_out: bool,
+    /// Insert a fan-in transformer behind the copies
+    pub fan_in: bool,
+}
+
+/// Staged input of the "Add Pipeline" wizard.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineWizardState {
+    /// World position of the producer; the other nodes follow to its right
+    pub position: (f32, f32),
+    /// Name of the producer
+    pub producer_name: String,
+    /// Name of the transformer
+    pub transformer_name: String,
+    /// Name of the consumer
+    pub consumer_name: String,
+    /// Messages the producer sends in total
+    pub total_messages: u32,
+    /// Steps between the producer's messages
+    pub steps_between: u32,
+    /// Steps the consumer takes per message
+    pub processing_time: u32,
+    /// Messages the consumer processes at once
+    pub max_concurrency: u32,
+}
+
+/// A flowchart loaded for "Import & Merge" that is waiting for the user to confirm.
+#[derive(Debug)]
+pub struct PendingMerge {
+    /// Name of the file the flowchart was loaded from
+    pub source: String,
+    /// The loaded flowchart
+    pub flowchart: Flowchart,
+    /// World-space offset at which the contents will be inserted
+    pub offset: (f32, f32),
 }
 
 /// Messages sent from async file operations back to the main app.
@@ -375,10 +1379,85 @@ pub enum FileOperationResult {
     SaveCompleted(String),
     /// Load operation completed successfully with path and content
     LoadCompleted(String, String),
+    /// A document was read and parsed in the background, with path, flowchart and content
+    DocumentParsed(String, Box<Flowchart>, String),
+    /// A background load of a large file started
+    LoadStarted(LoadProgress),
+    /// A background load has read this many bytes
+    LoadProgressed(u64),
+    /// A background load stopped because it was cancelled
+    LoadCancelled,
+    /// Selection export completed successfully with the given path
+    ExportCompleted(String),
+    /// File picked for merging was read successfully with path and content
+    MergeLoaded(String, String),
+    /// The browser's document store was listed
+    BrowserDocumentsListed(Vec<StoredDocumentInfo>),
+    /// A document was deleted from the browser's document store
+    BrowserDocumentDeleted(String),
+    /// The open file was changed, replaced or removed on disk
+    FileChangedOnDisk(String),
+    /// Another launch of the app asked for this file to be opened
+    OpenRequested(String),
+    /// File picked for plugin installation was read successfully with path and content
+    PluginLoaded(String, String),
+    /// File picked as the run comparison baseline was read successfully with path and content
+    RunBaselineLoaded(String, String),
+    /// File picked for BPMN import was read successfully with path and content
+    BpmnLoaded(String, String),
+    /// Traffic trace picked for producer proposals was read successfully with path and content
+    TrafficTraceLoaded(String, String),
+    /// CSV table of nodes picked for import was read successfully with path and content
+    NodeCsvLoaded(String, String),
     /// Operation failed with an error message
     OperationFailed(String),
 }
 
+/// Progress of a large file being read in the background (native only).
+#[derive(Debug, Clone)]
+pub struct LoadProgress {
+    /// Path of the file being loaded
+    pub path: String,
+    /// Bytes read so far
+    pub bytes_read: u64,
+    /// Size of the file in bytes
+    pub total_bytes: u64,
+    /// Set to ask the loading thread to stop; its result is then dropped
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl LoadProgress {
+    /// Whether the whole file has been read and is being parsed.
+    pub fn parsing(&self) -> bool {
+        self.bytes_read >= self.total_bytes
+    }
+
+    /// Whether the load was cancelled.
+    pub fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// Outcome of reading a node's source file (see [`FlowchartNode::source_file`]).
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceFileStatus {
+    /// The file matched the node's copy
+    Unchanged,
+    /// The file had changed and the node was updated from it
+    Updated,
+    /// The file could not be read or parsed; the node kept its copy
+    Failed(String),
+}
+
+/// A change to the open file made outside the app, awaiting the user's decision.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExternalChange {
+    /// The file now holds different contents
+    Modified(String),
+    /// The file was deleted or moved away
+    Removed,
+}
+
 /// Pending confirmation actions that may require user approval due to unsaved changes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PendingConfirmAction {
@@ -388,8 +1467,42 @@ pub enum PendingConfirmAction {
     Open,
     /// User is attempting to load a built-in example
     LoadExample,
+    /// User is attempting to follow a reference node to another file
+    OpenReference,
+    /// User is opening a file passed to another launch of the app
+    OpenFile,
     /// User is attempting to quit the application
     Quit,
+    /// User accepted a new version of the web app, which reloads the page
+    ReloadForUpdate,
+}
+
+/// What the button of a notification does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationAction {
+    /// Reload the web app to start the new version waiting to take over
+    ReloadForUpdate,
+}
+
+impl NotificationAction {
+    /// Text of the button.
+    pub fn label(self) -> &'static str {
+        match self {
+            NotificationAction::ReloadForUpdate => "Reload",
+        }
+    }
+}
+
+/// A message shown in the corner of the window. One with a button stays until
+/// it is used or dismissed; others go after `NOTIFICATION_TIMEOUT_SECS`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// Text shown
+    pub message: String,
+    /// Button offered next to the text, if any
+    pub action: Option<NotificationAction>,
+    /// Frame time the notification was posted at
+    pub posted_at: f64,
 }
 
 /// The main application structure containing UI state and the flowchart data.
@@ -407,8 +1520,12 @@ pub struct FlowchartApp {
     /// Whether the simulation is currently running
     #[serde(skip)]
     pub is_simulation_running: bool,
-    /// Speed multiplier for simulation (currently unused)
-    pub simulation_speed: f32,
+    /// Step a running simulation pauses on, set from the toolbar's Run To
+    #[serde(skip)]
+    pub run_to_step: Option<u64>,
+    /// Target step entered in the toolbar's Run To field
+    #[serde(skip)]
+    pub run_to_step_input: u64,
     /// Counter for generating unique default node names
     pub node_counter: u32,
     /// Canvas navigation and display state
@@ -422,13 +1539,28 @@ pub struct FlowchartApp {
     /// Node that encountered a script error, if any
     #[serde(skip)]
     pub error_node: Option<NodeId>,
-    /// Frame counter for animation effects (e.g., flashing error borders)
+    /// Time (seconds) of the current frame, used to coalesce rapid undo actions
+    /// and to drive animations
     #[serde(skip)]
-    pub frame_counter: u64,
+    pub frame_time: f64,
     /// Undo/redo history for tracking and reversing actions
     pub undo_history: UndoHistory,
     /// Whether dark mode visuals are enabled
     pub dark_mode: bool,
+    /// Syntax highlighting colors for code editors, per light/dark mode
+    pub syntax_theme: SyntaxTheme,
+    /// Hold animated effects (flashing, pulsing) still
+    pub reduce_motion: bool,
+    /// Number of simulation events the events log keeps
+    pub event_log_capacity: usize,
+    /// Delivered messages each node keeps in memory for its history
+    pub history_memory_limit: usize,
+    /// Move older node history to a temporary file instead of dropping it
+    /// (desktop only)
+    pub history_spill_to_disk: bool,
+    /// Most in-transit messages drawn as dots on a connection, and listed per
+    /// page in the connection's properties
+    pub message_render_limit: usize,
     /// Remembered width of the properties panel across sessions
     pub properties_panel_width: f32,
     /// Persisted last known window inner size in logical points (desktop only)
@@ -437,11 +1569,65 @@ pub struct FlowchartApp {
     /// Last known window position (desktop only)
     #[serde(skip)]
     pub last_window_pos: Option<egui::Pos2>,
+    /// Browser-stored document that was open last, reopened on the next visit (web only)
+    pub last_browser_document: Option<String>,
+    /// Browser documents dialog, while open
+    #[serde(skip)]
+    pub browser_documents: Option<BrowserDocumentsState>,
+    /// Bundle left by a crashed session, shown in the recovery dialog
+    #[serde(skip)]
+    pub pending_recovery: Option<crate::crash::CrashBundle>,
+    /// Result of reading each linked source file when the last run started
+    #[serde(skip)]
+    pub source_file_status: std::collections::HashMap<NodeId, SourceFileStatus>,
+    /// Started without the stored state and plugins; shows the safe-mode banner
+    #[serde(skip)]
+    pub safe_mode: bool,
     /// Whether we've already applied the stored window geometry this session
     #[serde(skip)]
     pub applied_viewport_restore: bool,
     /// Selected auto-arrangement mode for the toolbar button
     pub auto_arrange_mode: AutoArrangeMode,
+    /// Show auto-layouts as a preview to apply or cancel instead of applying them
+    pub preview_layout: bool,
+    /// Tuning of the force-directed layout
+    pub force_layout: ForceLayoutSettings,
+    /// Re-run the force-directed layout as its tuning changes
+    pub force_layout_live: bool,
+    /// Undo history length right after the last run from the tuning popover,
+    /// so the next live run can replace it
+    #[serde(skip)]
+    pub force_tuning_undo_len: Option<usize>,
+    /// Nudge nearby nodes apart when nodes are created or imported
+    pub make_room_on_insert: bool,
+    /// Speed of panning with the arrow keys or WASD, in screen points per second
+    pub keyboard_pan_speed: f32,
+    /// Smallest and largest canvas zoom factors
+    pub zoom_limits: (f32, f32),
+    /// Record pans and zooms so they can be stepped back through
+    pub view_history_enabled: bool,
+    /// Earlier and later views when `view_history_enabled` is on
+    #[serde(skip)]
+    pub view_history: ViewHistory,
+    /// Append every undoable edit to the document's edit log
+    pub record_edit_log: bool,
+    /// Name edits are recorded under in the edit log
+    pub edit_log_author: String,
+    /// Key that steps the simulation once, or `multi_step_count` times with Shift
+    pub step_key: egui::Key,
+    /// Steps Shift plus the step key advances the simulation by
+    pub multi_step_count: u32,
+    /// Which destructive actions ask for confirmation first
+    pub confirmations: ConfirmationSettings,
+    /// Modal dialogs waiting to be answered, shown one at a time
+    #[serde(skip)]
+    pub dialogs: DialogManager,
+    /// Layout currently being animated
+    #[serde(skip)]
+    pub layout_animation: Option<LayoutAnimation>,
+    /// Layout shown as a preview
+    #[serde(skip)]
+    pub layout_preview: Option<LayoutPreview>,
     /// Counter for generating default group names
     pub group_counter: u32,
     /// Export dialog visibility flag (not persisted)
@@ -453,6 +1639,210 @@ pub struct FlowchartApp {
     /// Staged export options (remembered within the session)
     #[serde(skip)]
     pub export_options: ExportOptions,
+    /// "About this flowchart" statistics dialog visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_stats_dialog: bool,
+    /// Tidy preview dialog visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_tidy_dialog: bool,
+    /// Staged tidy options (remembered within the session)
+    #[serde(skip)]
+    pub tidy_options: TidyOptions,
+    /// Swimlane editor visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_swimlanes_dialog: bool,
+    /// Parameters window visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_parameters_dialog: bool,
+    /// Edits in progress in the parameters window (not persisted)
+    #[serde(skip)]
+    pub parameters_dialog: ParametersDialogState,
+    /// Formatting rules panel visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_format_rules_dialog: bool,
+    /// Plugin manager visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_plugins_dialog: bool,
+    /// Run statistics dashboard visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_run_stats_dialog: bool,
+    /// Final state of the last stopped run, until the next run starts
+    #[serde(skip)]
+    pub last_run: Option<LastRun>,
+    /// Last run window visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_last_run_dialog: bool,
+    /// Edit history window visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_edit_history_dialog: bool,
+    /// Text the edit history is filtered by (not persisted)
+    #[serde(skip)]
+    pub edit_history_filter: String,
+    /// Script API panel visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_script_api_panel: bool,
+    /// Earlier run the dashboard compares the current run against
+    #[serde(skip)]
+    pub run_baseline: Option<crate::metrics::BaselineRun>,
+    /// Why the last baseline failed to load
+    #[serde(skip)]
+    pub run_baseline_error: Option<String>,
+    /// Events panel visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_events_panel: bool,
+    /// Events panel filters (not persisted)
+    #[serde(skip)]
+    pub events_panel: EventsPanelState,
+    /// Simulation errors list visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_errors_dialog: bool,
+    /// Parameter sweep dialog visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_sweep_dialog: bool,
+    /// Outcome of the last Verify Determinism: what matched, or where the runs differed
+    #[serde(skip)]
+    pub determinism_check: Option<Result<String, String>>,
+    /// Parameter sweep settings and results
+    #[serde(skip)]
+    pub sweep: SweepState,
+    /// Installed plugins. The desktop reloads them from the plugins folder on
+    /// start; the web build keeps uploaded plugins with the app state.
+    #[cfg_attr(not(target_arch = "wasm32"), serde(skip))]
+    pub plugins: crate::plugins::PluginRegistry,
+    /// Problems found while loading or installing plugins
+    #[serde(skip)]
+    pub plugin_errors: Vec<String>,
+    /// Auto-group proposal dialog visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_auto_group_dialog: bool,
+    /// Staged auto-group options (remembered within the session)
+    #[serde(skip)]
+    pub auto_group_options: AutoGroupOptions,
+    /// Proposed communities the user has unticked in the auto-group dialog
+    #[serde(skip)]
+    pub auto_group_declined: Vec<Vec<NodeId>>,
+    /// Flowchart waiting to be merged from the "Import & Merge" dialog
+    #[serde(skip)]
+    pub pending_merge: Option<PendingMerge>,
+    /// Trace highlighted on the canvas by the trace view, if any
+    #[serde(skip)]
+    pub traced_message: Option<TraceId>,
+    /// Inspector filter settings (not persisted)
+    #[serde(skip)]
+    pub inspector: InspectorState,
+    /// Producer test-data generator dialog, while open
+    #[serde(skip)]
+    pub data_generator: Option<DataGeneratorState>,
+    /// "Add Pipeline" wizard, while open
+    #[serde(skip)]
+    pub pipeline_wizard: Option<PipelineWizardState>,
+    /// Replicate window, while open
+    #[serde(skip)]
+    pub replicate_dialog: Option<ReplicateDialogState>,
+    /// Producers proposed from a recorded traffic trace, while reviewed
+    #[serde(skip)]
+    pub traffic_import: Option<TrafficImportState>,
+    /// Nodes read from a CSV table, while reviewed
+    #[serde(skip)]
+    pub node_csv_import: Option<NodeCsvImportState>,
+    /// Run recording dialog visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_recording_dialog: bool,
+    /// Staged recording settings (remembered within the session)
+    #[serde(skip)]
+    pub recording_options: RecordingOptions,
+    /// The run being recorded, if any
+    #[serde(skip)]
+    pub recording: Option<RunRecording>,
+    /// Name typed for the next saved selection
+    #[serde(skip)]
+    pub saved_selection_name: String,
+    /// Preferences dialog visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_preferences_dialog: bool,
+    /// Keyboard shortcuts window visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_shortcuts_dialog: bool,
+    /// About window visibility flag (not persisted)
+    #[serde(skip)]
+    pub show_about_dialog: bool,
+    /// Called with the statistics of every simulation step, for embedding hosts
+    #[serde(skip)]
+    pub step_listener: Option<StepListener>,
+    /// Window title last sent to the OS (desktop only)
+    #[serde(skip)]
+    pub window_title_shown: String,
+    /// Whether the window title showed the progress of a run last frame
+    #[serde(skip)]
+    pub window_tracks_run: bool,
+    /// Called when the user accepts a new version of the web app, for the host page
+    #[serde(skip)]
+    pub update_listener: Option<UpdateListener>,
+    /// Notifications shown in the corner of the window, oldest first
+    #[serde(skip)]
+    pub notifications: Vec<Notification>,
+}
+
+/// Callback receiving the statistics of each simulation step.
+pub type StepListener = Box<dyn FnMut(&crate::metrics::StepSample)>;
+
+/// Callback run when the user accepts an update of the web app.
+pub type UpdateListener = Box<dyn FnMut()>;
+
+/// Snapshot of the app for embedding hosts and tests, taken with
+/// `FlowchartApp::export_state` and restored with `import_state`.
+///
+/// Each part can also be restored on its own with `import_document`,
+/// `import_view` and `import_simulation`.
+#[derive(Debug, Clone)]
+pub struct AppState {
+    /// The open document
+    pub document: DocumentState,
+    /// Where the canvas looks and what is selected
+    pub view: ViewState,
+    /// Progress of the simulation run
+    pub simulation: SimulationSnapshot,
+}
+
+/// The open document in an [`AppState`].
+#[derive(Debug, Clone)]
+pub struct DocumentState {
+    /// The flowchart, including the messages in flight
+    pub flowchart: Flowchart,
+    /// Path the document was opened from or saved to, if any
+    pub path: Option<String>,
+}
+
+/// The canvas view in an [`AppState`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewState {
+    /// Canvas pan offset in screen space
+    pub offset: (f32, f32),
+    /// Zoom level (1.0 = normal)
+    pub zoom_factor: f32,
+    /// Primary selected node
+    pub selected_node: Option<NodeId>,
+    /// All selected nodes
+    pub selected_nodes: Vec<NodeId>,
+    /// Selected group
+    pub selected_group: Option<GroupId>,
+}
+
+/// The simulation run in an [`AppState`].
+#[derive(Debug, Clone)]
+pub struct SimulationSnapshot {
+    /// Whether the simulation advances every frame
+    pub running: bool,
+    /// Running, paused or stopped
+    pub state: SimulationState,
+    /// Steps taken so far
+    pub current_step: u64,
+    /// Node whose last delivery failed
+    pub error_node: Option<NodeId>,
+    /// Message whose path is highlighted
+    pub traced_message: Option<TraceId>,
+    /// Per-step statistics of the run
+    pub stats: crate::metrics::RunStats,
 }
 
 impl Default for FlowchartApp {
@@ -461,25 +1851,101 @@ impl Default for FlowchartApp {
             flowchart: Flowchart::default(),
             simulation_engine: SimulationEngine::new(),
             is_simulation_running: false,
-            simulation_speed: 1.0,
+            run_to_step: None,
+            run_to_step_input: 100,
             node_counter: 0,
             canvas: CanvasState::default(),
             interaction: InteractionState::default(),
             context_menu: ContextMenuState::default(),
             file: FileState::default(),
             error_node: None,
-            frame_counter: 0,
+            frame_time: 0.0,
             undo_history: UndoHistory::new(),
             dark_mode: true,
+            syntax_theme: SyntaxTheme::default(),
+            reduce_motion: super::animation::prefers_reduced_motion(),
+            event_log_capacity: crate::constants::EVENT_LOG_DEFAULT_CAPACITY,
+            history_memory_limit: crate::constants::NODE_HISTORY_LIMIT,
+            history_spill_to_disk: false,
+            message_render_limit: crate::constants::MESSAGE_RENDER_DEFAULT_LIMIT,
             properties_panel_width: 300.0,
             window_inner_size: None,
             last_window_pos: None,
+            last_browser_document: None,
+            browser_documents: None,
+            pending_recovery: None,
+            source_file_status: Default::default(),
+            safe_mode: false,
             applied_viewport_restore: false,
             auto_arrange_mode: AutoArrangeMode::ForceDirected,
+            preview_layout: false,
+            force_layout: ForceLayoutSettings::default(),
+            force_layout_live: true,
+            force_tuning_undo_len: None,
+            make_room_on_insert: false,
+            keyboard_pan_speed: crate::constants::KEYBOARD_PAN_DEFAULT_SPEED,
+            zoom_limits: (crate::constants::ZOOM_DEFAULT_MIN, crate::constants::ZOOM_DEFAULT_MAX),
+            view_history_enabled: false,
+            view_history: ViewHistory::default(),
+            record_edit_log: true,
+            edit_log_author: crate::audit::default_author(),
+            step_key: egui::Key::Space,
+            multi_step_count: crate::constants::MULTI_STEP_DEFAULT_COUNT,
+            confirmations: ConfirmationSettings::default(),
+            dialogs: DialogManager::default(),
+            layout_animation: None,
+            layout_preview: None,
             group_counter: 0,
             show_export_dialog: false,
             pending_export_format: None,
             export_options: ExportOptions::default(),
+            show_stats_dialog: false,
+            show_tidy_dialog: false,
+            tidy_options: TidyOptions::default(),
+            show_swimlanes_dialog: false,
+            show_parameters_dialog: false,
+            parameters_dialog: ParametersDialogState::default(),
+            show_format_rules_dialog: false,
+            show_plugins_dialog: false,
+            show_run_stats_dialog: false,
+            last_run: None,
+            show_last_run_dialog: false,
+            show_edit_history_dialog: false,
+            edit_history_filter: String::new(),
+            show_script_api_panel: false,
+            run_baseline: None,
+            run_baseline_error: None,
+            show_events_panel: false,
+            show_errors_dialog: false,
+            events_panel: EventsPanelState::default(),
+            show_sweep_dialog: false,
+            determinism_check: None,
+            sweep: SweepState::default(),
+            plugins: Default::default(),
+            plugin_errors: Vec::new(),
+            show_auto_group_dialog: false,
+            auto_group_options: AutoGroupOptions::default(),
+            auto_group_declined: Vec::new(),
+            pending_merge: None,
+            traced_message: None,
+            inspector: InspectorState::default(),
+            data_generator: None,
+            pipeline_wizard: None,
+            replicate_dialog: None,
+            traffic_import: None,
+            node_csv_import: None,
+            show_recording_dialog: false,
+            recording_options: RecordingOptions::default(),
+            recording: None,
+            saved_selection_name: String::new(),
+            show_preferences_dialog: false,
+            show_shortcuts_dialog: false,
+            show_about_dialog: false,
+            step_listener: None,
+            window_title_shown: String::new(),
+            window_tracks_run: false,
+            update_listener: None,
+            notifications: Vec::new(),
         }
     }
 }
@@ -515,9 +1981,31 @@ impl FlowchartApp {
             window_inner_size: self.window_inner_size,
             applied_viewport_restore: self.applied_viewport_restore,
             last_window_pos: self.last_window_pos,
+            last_browser_document: self.last_browser_document.clone(),
             dark_mode: self.dark_mode,
+            syntax_theme: self.syntax_theme,
+            reduce_motion: self.reduce_motion,
+            event_log_capacity: self.event_log_capacity,
+            history_memory_limit: self.history_memory_limit,
+            history_spill_to_disk: self.history_spill_to_disk,
+            message_render_limit: self.message_render_limit,
             auto_arrange_mode: self.auto_arrange_mode,
+            preview_layout: self.preview_layout,
+            force_layout: self.force_layout,
+            force_layout_live: self.force_layout_live,
+            make_room_on_insert: self.make_room_on_insert,
+            keyboard_pan_speed: self.keyboard_pan_speed,
+            zoom_limits: self.zoom_limits,
+            view_history_enabled: self.view_history_enabled,
+            record_edit_log: self.record_edit_log,
+            edit_log_author: std::mem::take(&mut self.edit_log_author),
+            step_key: self.step_key,
+            multi_step_count: self.multi_step_count,
+            confirmations: self.confirmations,
+            plugins: std::mem::take(&mut self.plugins),
             ..Default::default()
         };
+        self.simulation_engine.events_mut().set_capacity(self.event_log_capacity);
+        self.apply_history_retention();
     }
 }
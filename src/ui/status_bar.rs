@@ -0,0 +1,112 @@
+//! Status bar along the bottom of the window.
+//!
+//! Shows whether the document is being edited or simulated, what is
+//! selected, the world coordinates under the cursor and the frame rate, with
+//! a hint for the gesture at hand (e.g. how to connect while hovering a node).
+
+use super::is_macos_platform;
+use super::state::FlowchartApp;
+use crate::types::{NodeId, SimulationState};
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Draws the status bar. Call before the side and bottom panels so it
+    /// spans the whole width below them.
+    pub(crate) fn draw_status_bar(&self, ctx: &egui::Context) {
+        let cursor = ctx
+            .pointer_hover_pos()
+            .filter(|pos| self.canvas.screen_rect.contains(*pos))
+            .map(|pos| self.screen_to_world(pos));
+        let hovered_node = cursor.and_then(|pos| self.find_node_at_position(pos));
+        let dt = ctx.input(|i| i.stable_dt);
+
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(self.status_mode());
+                ui.separator();
+                ui.label(self.selection_summary());
+                ui.separator();
+                ui.label(egui::RichText::new(self.gesture_hint(hovered_node)).weak());
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if dt > 0.0 {
+                        ui.label(format!("{:.0} fps", 1.0 / dt));
+                        ui.separator();
+                    }
+                    match cursor {
+                        Some(pos) => ui.label(format!("x {:.0}, y {:.0}", pos.x, pos.y)),
+                        None => ui.label("x –, y –"),
+                    };
+                });
+            });
+        });
+    }
+
+    /// Editing, or the state of the simulation and its step.
+    pub(crate) fn status_mode(&self) -> String {
+        let step = self.flowchart.current_step;
+        if let Some(target) = self.run_to_step.filter(|_| self.is_simulation_running) {
+            format!("Running to step {} (step {})", target, step)
+        } else if self.is_simulation_running {
+            format!("Simulating (step {})", step)
+        } else if self.flowchart.simulation_state == SimulationState::Paused {
+            format!("Paused (step {})", step)
+        } else {
+            "Editing".to_string()
+        }
+    }
+
+    /// What is selected, e.g. `Node: Orders` or `3 nodes selected`.
+    pub(crate) fn selection_summary(&self) -> String {
+        let name = |id: &NodeId| self.flowchart.nodes.get(id).map_or("?", |n| n.name.as_str());
+        let nodes = self.selected_node_ids();
+        match nodes.as_slice() {
+            [id] => return format!("Node: {}", name(id)),
+            [] => {}
+            many => return format!("{} nodes selected", many.len()),
+        }
+        if let Some(group) = self
+            .interaction
+            .selected_group
+            .and_then(|id| self.flowchart.groups.get(&id))
+        {
+            return format!("Group: {}", group.name);
+        }
+        if let Some(conn) = self
+            .interaction
+            .selected_connection
+            .and_then(|index| self.flowchart.connections.get(index))
+        {
+            return format!("Connection: {} → {}", name(&conn.from), name(&conn.to));
+        }
+        "Nothing selected".to_string()
+    }
+
+    /// A hint for the gesture in progress, or for what can be done next.
+    pub(crate) fn gesture_hint(&self, hovered_node: Option<NodeId>) -> String {
+        let modifier = if is_macos_platform() { "Cmd" } else { "Ctrl" };
+        let interaction = &self.interaction;
+        if interaction.drawing_connection_from.is_some() {
+            "Release over a node to connect, or elsewhere to cancel".to_string()
+        } else if interaction.duplicating_from.is_some() {
+            "Release to place a connected copy · Hold Shift to snap to the grid".to_string()
+        } else if interaction.dragging_node.is_some() {
+            "Hold Shift to snap to the grid".to_string()
+        } else if interaction.marquee_start.is_some() {
+            "Hold Shift to add to the selection".to_string()
+        } else if interaction.is_panning {
+            "Release to stop panning".to_string()
+        } else if hovered_node.is_some() {
+            "Drag to move · Shift+drag to connect · Alt+drag to duplicate · Right-click for more".to_string()
+        } else if !self.selected_node_ids().is_empty() {
+            format!("Delete removes the selection · {}+G groups it", modifier)
+        } else if interaction.selected_group.is_some() || interaction.selected_connection.is_some() {
+            "Delete removes it".to_string()
+        } else {
+            format!(
+                "Right-click to add a node · Drag to select · Scroll to zoom · {}+drag to pan",
+                modifier
+            )
+        }
+    }
+}
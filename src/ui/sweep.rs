@@ -0,0 +1,301 @@
+//! Parameter sweep dialog.
+//!
+//! Edits the swept parameters, runs the combinations of a [`Sweep`] one per
+//! frame so the window stays responsive, and draws the chosen metric as a
+//! heatmap with one cell per combination.
+
+use super::state::FlowchartApp;
+use crate::constants::SWEEP_MAX_RUNS;
+use crate::sweep::{Sweep, SweepAxis, SweepMetric, SweepParameter};
+use crate::types::*;
+use eframe::egui;
+
+/// Most height the heatmap grows to.
+const HEATMAP_MAX_HEIGHT: f32 = 300.0;
+
+/// Height of a heatmap row when there is room.
+const HEATMAP_ROW_HEIGHT: f32 = 26.0;
+
+/// Width kept left of the heatmap for the row values.
+const HEATMAP_LABEL_WIDTH: f32 = 36.0;
+
+/// Colors of the lowest and highest value on the heatmap.
+const HEAT_LOW: (u8, u8, u8) = (40, 60, 130);
+const HEAT_HIGH: (u8, u8, u8) = (250, 210, 60);
+
+/// Color for a value `t` of the way from the lowest to the highest.
+fn heat_color(t: f32) -> egui::Color32 {
+    let mix = |low: u8, high: u8| (low as f32 + (high as f32 - low as f32) * t.clamp(0.0, 1.0)).round() as u8;
+    egui::Color32::from_rgb(mix(HEAT_LOW.0, HEAT_HIGH.0), mix(HEAT_LOW.1, HEAT_HIGH.1), mix(HEAT_LOW.2, HEAT_HIGH.2))
+}
+
+/// Formats a metric value without decimals when it is a whole number.
+fn format_metric(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+impl FlowchartApp {
+    /// Steps each sweep run lasts: to the end of a bounded measurement
+    /// window, otherwise the number set in the dialog.
+    pub(crate) fn sweep_steps(&self) -> u64 {
+        self.flowchart
+            .run_config
+            .last_measured_step()
+            .map_or(self.sweep.steps, |last| last + 1)
+    }
+
+    /// Plans a sweep of the current document from the dialog settings and
+    /// starts running it.
+    pub(crate) fn start_sweep(&mut self) {
+        let y = self.sweep.two_parameters.then_some(self.sweep.y);
+        match Sweep::new(&self.flowchart, self.sweep.x, y, self.sweep_steps(), SWEEP_MAX_RUNS) {
+            Ok(sweep) => {
+                crate::crash::record_event(format!("Started parameter sweep of {} runs", sweep.total()));
+                self.sweep.sweep = Some(sweep);
+                self.sweep.running = true;
+                self.sweep.error = None;
+            }
+            Err(e) => self.sweep.error = Some(e),
+        }
+    }
+
+    /// Runs the next combination of the running sweep.
+    pub(crate) fn advance_sweep(&mut self) {
+        if !self.sweep.running {
+            return;
+        }
+        let more = self.sweep.sweep.as_mut().is_some_and(|sweep| sweep.run_next() && !sweep.is_finished());
+        self.sweep.running = more;
+    }
+
+    /// Draws the sweep dialog. Opens when `show_sweep_dialog` is true; the
+    /// sweep only runs while it is open.
+    pub(crate) fn draw_sweep_dialog(&mut self, ctx: &egui::Context) {
+        self.advance_sweep();
+        if self.sweep.running {
+            ctx.request_repaint();
+        }
+
+        let mut keep_open = true;
+        let mut start = false;
+        let steps = self.sweep_steps();
+        let window_bounded = self.flowchart.run_config.last_measured_step().is_some();
+
+        egui::Window::new("Parameter Sweep")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                let flowchart = &self.flowchart;
+                let state = &mut self.sweep;
+                ui.add_enabled_ui(!state.running, |ui| {
+                    egui::Grid::new("sweep_axes_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Columns:");
+                        Self::draw_sweep_axis(ui, "sweep_x", &mut state.x, flowchart);
+                        ui.end_row();
+                        ui.checkbox(&mut state.two_parameters, "Rows:");
+                        ui.add_enabled_ui(state.two_parameters, |ui| {
+                            Self::draw_sweep_axis(ui, "sweep_y", &mut state.y, flowchart);
+                        });
+                        ui.end_row();
+                        ui.label("Each run:");
+                        if window_bounded {
+                            ui.label(format!("{} steps (warm-up and measurement window)", steps));
+                        } else {
+                            ui.add(egui::DragValue::new(&mut state.steps).range(1..=100_000).suffix(" steps"))
+                                .on_hover_text("Set a measurement window in Run Statistics to run exactly to its end");
+                        }
+                        ui.end_row();
+                    });
+                });
+
+                ui.horizontal(|ui| {
+                    if state.running {
+                        if ui.button("Stop").clicked() {
+                            state.running = false;
+                        }
+                    } else if ui.button("Run Sweep").clicked() {
+                        start = true;
+                    }
+                    if let Some(sweep) = &state.sweep {
+                        ui.weak(format!("{} of {} runs", sweep.completed(), sweep.total()));
+                    }
+                });
+                if let Some(error) = &state.error {
+                    ui.colored_label(egui::Color32::from_rgb(200, 80, 80), error);
+                }
+
+                let Some(sweep) = &state.sweep else {
+                    ui.weak("Runs a copy of the document for every combination; the document is not changed.");
+                    return;
+                };
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Show:");
+                    egui::ComboBox::from_id_salt("sweep_metric_combo")
+                        .selected_text(state.metric.label())
+                        .show_ui(ui, |ui| {
+                            for metric in SweepMetric::ALL {
+                                ui.selectable_value(&mut state.metric, metric, metric.label());
+                            }
+                        });
+                });
+                Self::draw_sweep_heatmap(ui, sweep, state.metric, flowchart);
+            });
+
+        if start {
+            self.start_sweep();
+        }
+        if !keep_open {
+            self.show_sweep_dialog = false;
+        }
+    }
+
+    /// Draws the parameter, node and value range of one sweep axis.
+    fn draw_sweep_axis(ui: &mut egui::Ui, id: &str, axis: &mut SweepAxis, flowchart: &Flowchart) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt((id, "parameter"))
+                .selected_text(axis.parameter.label())
+                .show_ui(ui, |ui| {
+                    for parameter in SweepParameter::ALL {
+                        ui.selectable_value(&mut axis.parameter, parameter, parameter.label());
+                    }
+                });
+
+            let mut nodes: Vec<&FlowchartNode> = flowchart
+                .nodes
+                .values()
+                .filter(|n| axis.parameter.applies_to(&n.node_type))
+                .collect();
+            nodes.sort_by(|a, b| a.name.cmp(&b.name));
+            if !nodes.iter().any(|n| n.id == axis.node) {
+                // Keep the axis usable after changing the parameter
+                if let Some(node) = nodes.first() {
+                    axis.node = node.id;
+                }
+            }
+            let selected = flowchart
+                .nodes
+                .get(&axis.node)
+                .filter(|n| axis.parameter.applies_to(&n.node_type))
+                .map_or_else(|| "(no matching node)".to_string(), |n| n.name.clone());
+            egui::ComboBox::from_id_salt((id, "node"))
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for node in nodes {
+                        ui.selectable_value(&mut axis.node, node.id, &node.name);
+                    }
+                });
+
+            ui.label("from");
+            ui.add(egui::DragValue::new(&mut axis.start).range(0..=100_000));
+            ui.label("to");
+            ui.add(egui::DragValue::new(&mut axis.end).range(0..=100_000));
+            ui.label("by");
+            ui.add(egui::DragValue::new(&mut axis.increment).range(1..=100_000));
+        });
+    }
+
+    /// Draws one cell per combination colored by `metric`, with the values
+    /// of the swept parameters along the edges.
+    fn draw_sweep_heatmap(ui: &mut egui::Ui, sweep: &Sweep, metric: SweepMetric, flowchart: &Flowchart) {
+        let (columns, rows) = (sweep.x_values().len(), sweep.y_values().len());
+        let row_height = (HEATMAP_MAX_HEIGHT / rows as f32).min(HEATMAP_ROW_HEIGHT);
+        let width = ui.available_width().max(160.0);
+        let label_width = if sweep.y.is_some() { HEATMAP_LABEL_WIDTH } else { 0.0 };
+        let size = egui::vec2(width, row_height * rows as f32 + 16.0);
+        if let Some(y) = &sweep.y {
+            ui.weak(format!("Rows: {}", y.label(flowchart)));
+        }
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        let grid = egui::Rect::from_min_max(
+            rect.min + egui::vec2(label_width, 0.0),
+            egui::pos2(rect.right(), rect.bottom() - 16.0),
+        );
+        let cell_width = grid.width() / columns as f32;
+        let cell = |column: usize, row: usize| {
+            egui::Rect::from_min_size(
+                grid.min + egui::vec2(column as f32 * cell_width, row as f32 * row_height),
+                egui::vec2(cell_width, row_height),
+            )
+        };
+
+        let range = sweep.metric_range(metric);
+        let font = egui::FontId::proportional(10.0);
+        for row in 0..rows {
+            for column in 0..columns {
+                let rect = cell(column, row).shrink(0.5);
+                let value = sweep.result(column, row).map(|summary| metric.value(summary));
+                let color = match (value, range) {
+                    (Some(Some(v)), Some((low, high))) if high > low => heat_color(((v - low) / (high - low)) as f32),
+                    (Some(Some(_)), _) => heat_color(0.5),
+                    _ => visuals.extreme_bg_color,
+                };
+                painter.rect_filled(rect, 0.0, color);
+                if let Some(Some(v)) = value {
+                    if rect.width() >= 34.0 && rect.height() >= 14.0 {
+                        let luminance = 0.3 * color.r() as f32 + 0.6 * color.g() as f32 + 0.1 * color.b() as f32;
+                        let text_color = if luminance > 140.0 { egui::Color32::BLACK } else { egui::Color32::WHITE };
+                        painter.text(rect.center(), egui::Align2::CENTER_CENTER, format_metric(v), font.clone(), text_color);
+                    }
+                }
+            }
+        }
+
+        // Values along the edges, thinned out so they don't overlap
+        let every = (40.0 / cell_width).ceil().max(1.0) as usize;
+        for (column, value) in sweep.x_values().iter().enumerate().step_by(every) {
+            painter.text(
+                egui::pos2(cell(column, 0).center().x, grid.bottom() + 2.0),
+                egui::Align2::CENTER_TOP,
+                value.to_string(),
+                font.clone(),
+                visuals.text_color(),
+            );
+        }
+        if sweep.y.is_some() {
+            let every = (14.0 / row_height).ceil().max(1.0) as usize;
+            for (row, value) in sweep.y_values().iter().enumerate().step_by(every) {
+                painter.text(
+                    egui::pos2(grid.left() - 4.0, cell(0, row).center().y),
+                    egui::Align2::RIGHT_CENTER,
+                    value.to_string(),
+                    font.clone(),
+                    visuals.text_color(),
+                );
+            }
+        }
+
+        if let Some(pos) = response.hover_pos().filter(|pos| grid.contains(*pos)) {
+            let column = (((pos.x - grid.left()) / cell_width) as usize).min(columns - 1);
+            let row = (((pos.y - grid.top()) / row_height) as usize).min(rows - 1);
+            let mut text = format!("{} = {}", sweep.x.label(flowchart), sweep.x_values()[column]);
+            if let Some(y) = &sweep.y {
+                text.push_str(&format!("\n{} = {}", y.label(flowchart), sweep.y_values()[row]));
+            }
+            let value = match sweep.result(column, row) {
+                None => "not run yet".to_string(),
+                Some(summary) => metric.value(summary).map_or_else(|| "no data".to_string(), format_metric),
+            };
+            text.push_str(&format!("\n{}: {}", metric.label(), value));
+            response.on_hover_text(text);
+        }
+
+        ui.horizontal(|ui| {
+            ui.weak(format!("Columns: {}", sweep.x.label(flowchart)));
+            if let Some((low, high)) = range {
+                ui.colored_label(heat_color(0.0), "■");
+                ui.weak(format_metric(low));
+                ui.colored_label(heat_color(1.0), "■");
+                ui.weak(format_metric(high));
+            }
+        });
+    }
+}
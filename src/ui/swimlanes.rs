@@ -0,0 +1,275 @@
+//! Swimlane mode.
+//!
+//! Lanes are horizontal bands, stacked from world y = 0, that nodes can be
+//! assigned to (for example one lane per team or service). This module draws
+//! the bands, edits the lane list, assigns nodes and applies the lane-aware
+//! layout from [`crate::analysis::plan_swimlane_layout`].
+
+use super::state::{ChangeKind, FlowchartApp};
+use crate::analysis::plan_swimlane_layout;
+use crate::types::*;
+use crate::ui::UndoAction;
+use eframe::egui;
+
+/// Undo coalescing key for typing in lane names.
+const LANE_RENAME_KEY: &str = "swimlanes:rename";
+
+impl FlowchartApp {
+    /// Draws the lane bands and their names behind everything else on the canvas.
+    pub(crate) fn draw_swimlane_bands(&self, painter: &egui::Painter, canvas_rect: egui::Rect) {
+        let text_color = if self.dark_mode {
+            egui::Color32::from_gray(200)
+        } else {
+            egui::Color32::from_gray(60)
+        };
+        let border = egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(128, 128, 128, 90));
+        for (idx, (lane, top, bottom)) in self.flowchart.swimlanes.bands().enumerate() {
+            let top = self.world_to_screen(egui::pos2(0.0, top)).y;
+            let bottom = self.world_to_screen(egui::pos2(0.0, bottom)).y;
+            let band = egui::Rect::from_x_y_ranges(canvas_rect.x_range(), top..=bottom);
+            if !band.intersects(canvas_rect) {
+                continue;
+            }
+            let alpha = if idx % 2 == 0 { 14 } else { 30 };
+            painter.rect_filled(band, 0.0, egui::Color32::from_rgba_unmultiplied(128, 128, 128, alpha));
+            painter.line_segment([band.left_bottom(), band.right_bottom()], border);
+            if idx == 0 {
+                painter.line_segment([band.left_top(), band.right_top()], border);
+            }
+
+            // Keep the name visible while the band is on screen
+            let label_y = top.max(canvas_rect.top()) + 6.0;
+            if label_y < bottom - 6.0 {
+                let name = if lane.name.is_empty() { "Unnamed Lane" } else { &lane.name };
+                painter.text(
+                    egui::pos2(canvas_rect.left() + 8.0, label_y),
+                    egui::Align2::LEFT_TOP,
+                    name,
+                    egui::FontId::proportional(14.0),
+                    text_color,
+                );
+            }
+        }
+    }
+
+    /// Draws the lane picker for a node. Shown only when lanes exist.
+    pub(crate) fn draw_lane_picker(&mut self, ui: &mut egui::Ui, node: &FlowchartNode) {
+        if self.flowchart.swimlanes.lanes.is_empty() {
+            return;
+        }
+        let current = node
+            .lane
+            .filter(|id| self.flowchart.swimlanes.lane(*id).is_some());
+        let mut selected = current;
+        ui.horizontal(|ui| {
+            ui.label("Lane:");
+            let text = current
+                .and_then(|id| self.flowchart.swimlanes.lane(id))
+                .map(|l| l.name.clone())
+                .unwrap_or_else(|| "(none)".to_string());
+            egui::ComboBox::from_id_salt("node_lane_combo")
+                .selected_text(text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, "(none)");
+                    for lane in &self.flowchart.swimlanes.lanes {
+                        ui.selectable_value(&mut selected, Some(lane.id), &lane.name);
+                    }
+                });
+        });
+        if selected != current {
+            self.assign_lane(node.id, selected);
+        }
+    }
+
+    /// Moves a node into `lane` (or out of all lanes) as one undo step.
+    pub(crate) fn assign_lane(&mut self, node_id: NodeId, lane: Option<LaneId>) {
+        let Some(node) = self.flowchart.nodes.get_mut(&node_id) else {
+            return;
+        };
+        if node.lane == lane {
+            return;
+        }
+        let action = UndoAction::LaneAssigned {
+            node_id,
+            old_lane: node.lane,
+            new_lane: lane,
+        };
+        node.lane = lane;
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+    }
+
+    /// Replaces the swimlane settings and records the change.
+    ///
+    /// With `coalesce` set, rapid consecutive calls (typing a lane name) are
+    /// merged into a single undo entry.
+    pub(crate) fn set_swimlanes(&mut self, swimlanes: Swimlanes, coalesce: bool) {
+        if swimlanes == self.flowchart.swimlanes {
+            return;
+        }
+        let old = std::mem::replace(&mut self.flowchart.swimlanes, swimlanes);
+        let action = UndoAction::SwimlanesChanged {
+            old,
+            new: self.flowchart.swimlanes.clone(),
+        };
+        if coalesce {
+            self.undo_history
+                .push_action_coalesced(action, LANE_RENAME_KEY, self.frame_time);
+        } else {
+            self.undo_history.push_action(action);
+        }
+        self.file.mark_changed(ChangeKind::SwimlanesChanged);
+    }
+
+    /// Draws the lane editor. Opens when `show_swimlanes_dialog` is true.
+    pub(crate) fn draw_swimlanes_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let mut edited = self.flowchart.swimlanes.clone();
+        let mut renamed = false;
+        let mut layout = false;
+
+        egui::Window::new("Swimlanes")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut edited.enabled, "Show swimlanes");
+                ui.separator();
+
+                let count = edited.lanes.len();
+                let mut move_up = None;
+                let mut move_down = None;
+                let mut remove = None;
+                if count == 0 {
+                    ui.label("No lanes yet.");
+                } else {
+                    egui::Grid::new("swimlanes_grid").num_columns(5).show(ui, |ui| {
+                        for (idx, lane) in edited.lanes.iter_mut().enumerate() {
+                            let members = self
+                                .flowchart
+                                .nodes
+                                .values()
+                                .filter(|n| n.lane == Some(lane.id))
+                                .count();
+                            renamed |= ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut lane.name)
+                                        .id_salt(lane.id)
+                                        .desired_width(160.0),
+                                )
+                                .changed();
+                            ui.label(format!("{} node(s)", members));
+                            if ui
+                                .add_enabled(idx > 0, egui::Button::new("⬆"))
+                                .on_hover_text("Move up")
+                                .clicked()
+                            {
+                                move_up = Some(idx);
+                            }
+                            if ui
+                                .add_enabled(idx + 1 < count, egui::Button::new("⬇"))
+                                .on_hover_text("Move down")
+                                .clicked()
+                            {
+                                move_down = Some(idx);
+                            }
+                            if ui
+                                .button("✖")
+                                .on_hover_text("Remove lane; its nodes become unassigned")
+                                .clicked()
+                            {
+                                remove = Some(idx);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                }
+                if let Some(idx) = move_up {
+                    edited.lanes.swap(idx, idx - 1);
+                }
+                if let Some(idx) = move_down {
+                    edited.lanes.swap(idx, idx + 1);
+                }
+                if let Some(idx) = remove {
+                    edited.lanes.remove(idx);
+                }
+                if ui.button("Add Lane").clicked() {
+                    edited.lanes.push(Lane::new(format!("Lane {}", count + 1)));
+                    edited.enabled = true;
+                }
+
+                ui.separator();
+                if ui
+                    .add_enabled(!edited.lanes.is_empty(), egui::Button::new("Lay Out in Lanes"))
+                    .on_hover_text("Keep nodes in their lanes, ordered left to right by dependency")
+                    .clicked()
+                {
+                    layout = true;
+                }
+            });
+
+        if edited != self.flowchart.swimlanes {
+            // Only pure renames coalesce; structural edits are separate steps
+            let only_names = renamed
+                && edited.enabled == self.flowchart.swimlanes.enabled
+                && edited.lanes.len() == self.flowchart.swimlanes.lanes.len()
+                && edited
+                    .lanes
+                    .iter()
+                    .zip(&self.flowchart.swimlanes.lanes)
+                    .all(|(a, b)| a.id == b.id);
+            if !only_names {
+                self.undo_history.break_coalescing();
+            }
+            self.set_swimlanes(edited, only_names);
+        }
+        if layout {
+            self.animate_layout(Self::apply_swimlane_layout);
+        }
+        if !keep_open {
+            self.show_swimlanes_dialog = false;
+        }
+    }
+
+    /// Lays out all nodes in their lanes, as one undo entry.
+    pub(crate) fn apply_swimlane_layout(&mut self) {
+        let layout = plan_swimlane_layout(&self.flowchart);
+        let mut actions = Vec::new();
+
+        let old_positions: Vec<(NodeId, (f32, f32))> = layout
+            .positions
+            .iter()
+            .filter_map(|(id, _)| self.flowchart.nodes.get(id).map(|n| (*id, n.position)))
+            .collect();
+        if old_positions != layout.positions {
+            for (id, position) in &layout.positions {
+                if let Some(node) = self.flowchart.nodes.get_mut(id) {
+                    node.position = *position;
+                }
+            }
+            actions.push(UndoAction::MultipleNodesMoved {
+                old_positions,
+                new_positions: layout.positions,
+            });
+        }
+
+        let mut resized = self.flowchart.swimlanes.clone();
+        for (lane, height) in resized.lanes.iter_mut().zip(layout.lane_heights) {
+            lane.height = height;
+        }
+        if resized != self.flowchart.swimlanes {
+            let old = std::mem::replace(&mut self.flowchart.swimlanes, resized);
+            actions.push(UndoAction::SwimlanesChanged {
+                old,
+                new: self.flowchart.swimlanes.clone(),
+            });
+        }
+
+        if actions.is_empty() {
+            return;
+        }
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+    }
+}
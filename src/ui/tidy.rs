@@ -0,0 +1,203 @@
+//! Graph tidy command.
+//!
+//! Shows a preview of the clean-ups computed by [`crate::analysis::plan_tidy`]
+//! and applies them to the flowchart as a single undo entry.
+
+use super::state::FlowchartApp;
+use crate::analysis::{plan_tidy, TidyPlan};
+use crate::types::*;
+use crate::ui::UndoAction;
+use eframe::egui;
+
+impl FlowchartApp {
+    /// Draws the tidy preview dialog. Opens when `show_tidy_dialog` is true.
+    pub(crate) fn draw_tidy_dialog(&mut self, ctx: &egui::Context) {
+        let mut keep_open = true;
+        let mut apply = false;
+        let plan = plan_tidy(&self.flowchart, &self.tidy_options);
+
+        egui::Window::new("Tidy")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut self.tidy_options.remove_orphans, "Remove orphan nodes");
+                ui.checkbox(
+                    &mut self.tidy_options.merge_parallel_edges,
+                    "Merge duplicate parallel connections",
+                );
+                ui.checkbox(&mut self.tidy_options.strip_empty_groups, "Remove empty groups");
+                ui.separator();
+
+                if plan.is_empty() {
+                    ui.label("Nothing to tidy.");
+                } else {
+                    let names = |ids: &[NodeId]| -> String {
+                        ids.iter()
+                            .filter_map(|id| self.flowchart.nodes.get(id).map(|n| n.name.clone()))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+                    if !plan.orphan_nodes.is_empty() {
+                        ui.label(format!(
+                            "Remove {} orphan node(s): {}",
+                            plan.orphan_nodes.len(),
+                            names(&plan.orphan_nodes)
+                        ));
+                    }
+                    if !plan.duplicate_connections.is_empty() {
+                        ui.label(format!(
+                            "Merge {} duplicate connection(s)",
+                            plan.duplicate_connections.len()
+                        ));
+                    }
+                    if !plan.empty_groups.is_empty() {
+                        let group_names = plan
+                            .empty_groups
+                            .iter()
+                            .filter_map(|id| self.flowchart.groups.get(id).map(|g| g.name.clone()))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        ui.label(format!(
+                            "Remove {} empty group(s): {}",
+                            plan.empty_groups.len(),
+                            group_names
+                        ));
+                    }
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(!plan.is_empty(), |ui| {
+                        if ui.button("Apply").clicked() {
+                            apply = true;
+                        }
+                    });
+                    if ui.button("Cancel").clicked() {
+                        self.show_tidy_dialog = false;
+                    }
+                });
+            });
+
+        if apply {
+            self.apply_tidy_plan(&plan);
+            self.show_tidy_dialog = false;
+        }
+        if !keep_open {
+            self.show_tidy_dialog = false;
+        }
+    }
+
+    /// Applies a tidy plan and records all of its changes as one undo entry.
+    ///
+    /// Duplicate connections are removed and their in-flight messages appended
+    /// to the first connection between the same nodes, so nothing is lost.
+    pub(crate) fn apply_tidy_plan(&mut self, plan: &TidyPlan) {
+        let mut actions: Vec<UndoAction> = Vec::new();
+
+        // Gather messages carried by duplicates, keeping connection order
+        let mut carried: Vec<((NodeId, NodeId), Vec<Message>)> = Vec::new();
+        for &idx in &plan.duplicate_connections {
+            let Some(conn) = self.flowchart.connections.get(idx) else {
+                continue;
+            };
+            if conn.messages.is_empty() {
+                continue;
+            }
+            let key = (conn.from, conn.to);
+            match carried.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, msgs)) => msgs.extend(conn.messages.iter().cloned()),
+                None => carried.push((key, conn.messages.clone())),
+            }
+        }
+
+        // Remove duplicates from the back so earlier indices stay valid
+        for &idx in plan.duplicate_connections.iter().rev() {
+            if idx < self.flowchart.connections.len() {
+                let connection = self.flowchart.connections.remove(idx);
+                actions.push(UndoAction::ConnectionDeleted { connection, index: idx });
+            }
+        }
+
+        // Append the carried messages to each surviving connection
+        for ((from, to), extra) in carried {
+            if let Some(index) = self
+                .flowchart
+                .connections
+                .iter()
+                .position(|c| c.from == from && c.to == to)
+            {
+                let connection = &mut self.flowchart.connections[index];
+                let old = connection.messages.clone();
+                connection.messages.extend(extra);
+                actions.push(UndoAction::ConnectionMessagesChanged {
+                    index,
+                    from,
+                    to,
+                    old,
+                    new: connection.messages.clone(),
+                });
+            }
+        }
+
+        // Groups that lose members or disappear are recorded as delete + re-create
+        let mut affected_groups: Vec<GroupId> = self
+            .flowchart
+            .groups
+            .values()
+            .filter(|g| {
+                plan.empty_groups.contains(&g.id)
+                    || g.members.iter().any(|m| plan.orphan_nodes.contains(m))
+            })
+            .map(|g| g.id)
+            .collect();
+        affected_groups.sort();
+        for gid in affected_groups {
+            let Some(original) = self.flowchart.groups.remove(&gid) else {
+                continue;
+            };
+            let mut updated = original.clone();
+            actions.push(UndoAction::GroupDeleted { group: original });
+            if !plan.empty_groups.contains(&gid) {
+                updated.members.retain(|m| !plan.orphan_nodes.contains(m));
+                self.flowchart.groups.insert(gid, updated);
+                actions.push(UndoAction::GroupCreated { group_id: gid });
+            }
+        }
+
+        for id in &plan.orphan_nodes {
+            if let Some(node) = self.flowchart.nodes.remove(id) {
+                actions.push(UndoAction::NodeDeleted {
+                    node,
+                    connections: Vec::new(),
+                });
+            }
+        }
+
+        if actions.is_empty() {
+            return;
+        }
+
+        // Drop selection that may point at removed items
+        self.interaction.selected_nodes.retain(|id| !plan.orphan_nodes.contains(id));
+        if self
+            .interaction
+            .selected_node
+            .is_some_and(|id| plan.orphan_nodes.contains(&id))
+        {
+            self.interaction.selected_node = None;
+        }
+        if self
+            .interaction
+            .selected_group
+            .is_some_and(|id| !self.flowchart.groups.contains_key(&id))
+        {
+            self.interaction.selected_group = None;
+        }
+        self.interaction.selected_connection = None;
+
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+    }
+}
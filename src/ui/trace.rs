@@ -0,0 +1,152 @@
+//! Message trace view.
+//!
+//! Every produced message carries a trace ID that its transformed and fanned
+//! out copies keep. Tracing a message from the connection inspector highlights
+//! the nodes and connections it passed through, and lists each hop by step.
+
+use super::state::FlowchartApp;
+use crate::simulation::TraceHop;
+use crate::types::*;
+use eframe::egui;
+
+/// Highlight color for traced nodes and connections.
+const TRACE_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 80, 200);
+
+impl FlowchartApp {
+    /// Highlights the path of `trace_id` on the canvas and opens the trace window.
+    pub(crate) fn start_trace(&mut self, trace_id: TraceId) {
+        self.traced_message = Some(trace_id);
+    }
+
+    /// Hops of the currently traced message, or an empty list if nothing is traced.
+    pub(crate) fn traced_hops(&self) -> Vec<TraceHop> {
+        self.traced_message
+            .map(|trace_id| self.simulation_engine.trace_hops(trace_id))
+            .unwrap_or_default()
+    }
+
+    /// Draws the trace window listing each hop of the traced message.
+    ///
+    /// Clicking a hop selects its node. Closing the window ends the trace view.
+    pub(crate) fn draw_trace_window(&mut self, ctx: &egui::Context) {
+        let Some(trace_id) = self.traced_message else {
+            return;
+        };
+        let hops = self.traced_hops();
+
+        let mut keep_open = true;
+        let mut select: Option<NodeId> = None;
+        egui::Window::new("Message Trace")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(260.0)
+            .show(ctx, |ui| {
+                ui.label(format!("Trace {}", short_trace_id(trace_id)));
+                ui.separator();
+                if hops.is_empty() {
+                    ui.label("No recorded hops. Run the simulation to follow this message.");
+                    return;
+                }
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for hop in &hops {
+                        let name = |id: &NodeId| {
+                            self.flowchart
+                                .nodes
+                                .get(id)
+                                .map(|n| n.name.clone())
+                                .unwrap_or_else(|| "(deleted node)".to_string())
+                        };
+                        let text = match &hop.from {
+                            Some(from) => format!(
+                                "Step {}: {} → {}",
+                                hop.step,
+                                name(from),
+                                name(&hop.node_id)
+                            ),
+                            None => format!("Step {}: produced by {}", hop.step, name(&hop.node_id)),
+                        };
+                        let selected = self.interaction.selected_node == Some(hop.node_id);
+                        if ui.selectable_label(selected, text).clicked() {
+                            select = Some(hop.node_id);
+                        }
+                    }
+                });
+            });
+
+        if let Some(node_id) = select {
+            if self.flowchart.nodes.contains_key(&node_id) {
+                self.interaction.selected_nodes.clear();
+                self.interaction.selected_connection = None;
+                self.interaction.selected_group = None;
+                self.interaction.selected_node = Some(node_id);
+            }
+        }
+        if !keep_open {
+            self.traced_message = None;
+        }
+    }
+
+    /// Draws highlighted lines along the connections the traced message used.
+    ///
+    /// Called between drawing connections and nodes so the lines stay behind nodes.
+    pub(crate) fn draw_trace_edges(&self, painter: &egui::Painter, hops: &[TraceHop]) {
+        let stroke = egui::Stroke::new(5.0 * self.canvas.zoom_factor.max(0.5), TRACE_COLOR);
+        for hop in hops {
+            let Some((start, end)) = hop.from.and_then(|from| self.connection_endpoints(from, hop.node_id)) else {
+                continue;
+            };
+            painter.line_segment([self.world_to_screen(start), self.world_to_screen(end)], stroke);
+        }
+    }
+
+    /// Outlines traced nodes and labels them with the steps they handled the message on.
+    pub(crate) fn draw_trace_nodes(&self, painter: &egui::Painter, hops: &[TraceHop]) {
+        let mut steps_by_node: Vec<(NodeId, Vec<u64>)> = Vec::new();
+        for hop in hops {
+            match steps_by_node.iter_mut().find(|(id, _)| *id == hop.node_id) {
+                Some((_, steps)) => {
+                    if !steps.contains(&hop.step) {
+                        steps.push(hop.step);
+                    }
+                }
+                None => steps_by_node.push((hop.node_id, vec![hop.step])),
+            }
+        }
+
+        let zoom = self.canvas.zoom_factor;
+        let size = egui::vec2(crate::constants::NODE_WIDTH, crate::constants::NODE_HEIGHT) * zoom;
+        let font = egui::FontId::proportional((11.0 * zoom).clamp(8.0, 24.0));
+        let width = 2.0 + 2.0 * self.pulse(crate::constants::TRACE_PULSE_PERIOD_SECS);
+        for (node_id, steps) in steps_by_node {
+            let Some(node) = self.flowchart.nodes.get(&node_id) else {
+                continue;
+            };
+            let center = self.world_to_screen(egui::pos2(node.position.0, node.position.1));
+            let rect = egui::Rect::from_center_size(center, size).expand(4.0 * zoom);
+            painter.rect_stroke(
+                rect,
+                7.0,
+                egui::Stroke::new(width, TRACE_COLOR),
+                egui::StrokeKind::Outside,
+            );
+            let label = steps
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            painter.text(
+                egui::pos2(center.x, rect.top() - 2.0),
+                egui::Align2::CENTER_BOTTOM,
+                format!("step {}", label),
+                font.clone(),
+                TRACE_COLOR,
+            );
+        }
+    }
+}
+
+/// First eight characters of a trace ID, enough to tell traces apart at a glance.
+pub(crate) fn short_trace_id(trace_id: TraceId) -> String {
+    trace_id.simple().to_string()[..8].to_string()
+}
@@ -0,0 +1,176 @@
+//! Producers from recorded traffic.
+//!
+//! Reviews the producers proposed for a JSON Lines trace (see
+//! [`crate::traffic`]): how much recorded time a simulation step stands for,
+//! which streams to add, and the template inferred for each. The chosen
+//! producers are added as one undo step, stacked in a column at the centre
+//! of the canvas, ready to be connected to the rest of the model.
+
+use super::json_tree::show_json_tree;
+use super::state::{FlowchartApp, TrafficImportState};
+use super::UndoAction;
+use crate::constants::NODE_HEIGHT;
+use crate::traffic::{parse_trace, propose_producers};
+use crate::types::*;
+use eframe::egui;
+
+/// Recorded time a step stands for when a trace is opened, in seconds.
+const DEFAULT_SECONDS_PER_STEP: f64 = 1.0;
+
+/// Distance between the centres of the added producers.
+const PRODUCER_SPACING: f32 = NODE_HEIGHT * 1.5;
+
+impl FlowchartApp {
+    /// Opens the review dialog for the trace `content` read from `path`.
+    pub(crate) fn open_traffic_import(&mut self, path: &str, content: &str) {
+        let source = std::path::Path::new(path)
+            .file_name()
+            .map_or_else(|| path.to_string(), |n| n.to_string_lossy().into_owned());
+        let (records, error) = match parse_trace(content) {
+            Ok(records) => (records, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        let proposals = propose_producers(&records, DEFAULT_SECONDS_PER_STEP);
+        self.traffic_import = Some(TrafficImportState {
+            source,
+            records,
+            error,
+            seconds_per_step: DEFAULT_SECONDS_PER_STEP,
+            selected: vec![true; proposals.len()],
+            proposals,
+        });
+    }
+
+    /// Draws the review dialog while a trace is open.
+    pub(crate) fn draw_traffic_import_dialog(&mut self, ctx: &egui::Context) {
+        let palette = self.syntax_theme.palette(ctx.style().visuals.dark_mode);
+        let Some(state) = self.traffic_import.as_mut() else {
+            return;
+        };
+        let mut keep_open = true;
+        let mut add = false;
+        let mut cancel = false;
+
+        egui::Window::new("Producers from Traffic")
+            .open(&mut keep_open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(440.0)
+            .show(ctx, |ui| {
+                if let Some(error) = &state.error {
+                    ui.colored_label(egui::Color32::RED, format!("Couldn't read {}: {}", state.source, error));
+                    return;
+                }
+                ui.label(format!(
+                    "{} messages in {} stream(s) from {}",
+                    state.records.len(),
+                    state.proposals.len(),
+                    state.source
+                ));
+                ui.horizontal(|ui| {
+                    ui.label("One step is");
+                    let changed = ui
+                        .add(
+                            egui::DragValue::new(&mut state.seconds_per_step)
+                                .range(0.001..=86_400.0)
+                                .speed(0.1)
+                                .suffix(" s"),
+                        )
+                        .on_hover_text("Recorded time each simulation step stands for")
+                        .changed();
+                    ui.label("of recorded time");
+                    if changed {
+                        state.proposals = propose_producers(&state.records, state.seconds_per_step);
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    for (index, (proposal, selected)) in state.proposals.iter().zip(state.selected.iter_mut()).enumerate() {
+                        ui.push_id(index, |ui| {
+                            ui.checkbox(selected, egui::RichText::new(&proposal.name).strong());
+                            let rate = match proposal.rate_per_second {
+                                Some(rate) => format!("{:.2} per second, ", rate),
+                                None => String::new(),
+                            };
+                            ui.weak(format!(
+                                "{} messages, {}one every {} step(s) from step {}",
+                                proposal.messages, rate, proposal.steps_between_cycles, proposal.start_step
+                            ));
+                            if proposal.rate_capped {
+                                ui.colored_label(
+                                    egui::Color32::from_rgb(220, 140, 0),
+                                    "Recorded faster than one message per step; a shorter step matches the rate.",
+                                );
+                            }
+                            egui::CollapsingHeader::new("Template")
+                                .id_salt("traffic_template")
+                                .show(ui, |ui| show_json_tree(ui, &proposal.message_template, palette));
+                            ui.separator();
+                        });
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let chosen = state.selected.iter().filter(|s| **s).count();
+                    if ui
+                        .add_enabled(chosen > 0, egui::Button::new(format!("Add {} Producer(s)", chosen)))
+                        .clicked()
+                    {
+                        add = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if add {
+            if let Some(state) = self.traffic_import.take() {
+                let center = self.screen_to_world(self.canvas.screen_rect.center());
+                self.add_traffic_producers(&state, (center.x, center.y));
+            }
+        } else if cancel || !keep_open {
+            self.traffic_import = None;
+        }
+    }
+
+    /// Adds the selected proposals of `state` as producers stacked around
+    /// `center`, as one undo step, and selects them.
+    pub(crate) fn add_traffic_producers(&mut self, state: &TrafficImportState, center: (f32, f32)) -> Vec<NodeId> {
+        let chosen: Vec<_> = state
+            .proposals
+            .iter()
+            .zip(&state.selected)
+            .filter(|(_, selected)| **selected)
+            .map(|(proposal, _)| proposal)
+            .collect();
+        let top = center.1 - PRODUCER_SPACING * (chosen.len().saturating_sub(1)) as f32 / 2.0;
+        let ids: Vec<NodeId> = chosen
+            .iter()
+            .enumerate()
+            .map(|(row, proposal)| {
+                let position = (center.0, top + PRODUCER_SPACING * row as f32);
+                self.node_counter += 1;
+                self.flowchart
+                    .add_node(FlowchartNode::new(proposal.name.clone(), position, proposal.node_type()))
+            })
+            .collect();
+        if ids.is_empty() {
+            return ids;
+        }
+
+        let mut actions: Vec<UndoAction> = ids.iter().map(|&node_id| UndoAction::NodeCreated { node_id }).collect();
+        actions.extend(self.make_room_for(&ids));
+        let action = UndoAction::Batch { actions };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+        crate::crash::record_event("Added producers from traffic");
+
+        self.interaction.selected_node = None;
+        self.interaction.selected_group = None;
+        self.interaction.selected_connection = None;
+        self.interaction.selected_nodes = ids.clone();
+        ids
+    }
+}
@@ -4,11 +4,11 @@
 //! types of operations including node movements, property changes, and deletions.
 
 use crate::types::*;
-use crate::constants::MAX_UNDO_HISTORY;
+use crate::constants::{MAX_UNDO_HISTORY, UNDO_COALESCE_WINDOW_SECS};
 use serde::{Deserialize, Serialize};
 
-/// Maximum number of undo actions to keep in history
-/// See crate::constants::MAX_UNDO_HISTORY
+// Maximum number of undo actions to keep in history:
+// see crate::constants::MAX_UNDO_HISTORY
 
 /// Represents different types of actions that can be undone.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +71,30 @@ pub enum UndoAction {
         /// Destination node id
         to: NodeId,
     },
+    /// The messages waiting on a connection changed, e.g. when tidying
+    /// merged those of its duplicates into it
+    ConnectionMessagesChanged {
+        /// Index of the connection in the list
+        index: usize,
+        /// Source node of the connection
+        from: NodeId,
+        /// Target node of the connection
+        to: NodeId,
+        /// Messages before the change
+        old: Vec<Message>,
+        /// Messages after the change
+        new: Vec<Message>,
+    },
+    /// A deleted connection was put back at its index; undoing it deletes
+    /// that connection again rather than the first one between the same nodes
+    ConnectionRestored {
+        /// Index the connection was put back at
+        index: usize,
+        /// Source node of the connection
+        from: NodeId,
+        /// Target node of the connection
+        to: NodeId,
+    },
     /// A node's name was changed
     NodeRenamed {
         /// The node whose name changed
@@ -90,6 +114,115 @@ pub enum UndoAction {
         /// The unique identifier of the new group
         group_id: GroupId,
     },
+    /// A node was moved to another swimlane, or out of all lanes
+    LaneAssigned {
+        /// The node whose lane changed
+        node_id: NodeId,
+        /// The previous lane
+        old_lane: Option<LaneId>,
+        /// The new lane
+        new_lane: Option<LaneId>,
+    },
+    /// A node's script or template was linked to a file, or unlinked
+    SourceFileChanged {
+        /// The node whose source file changed
+        node_id: NodeId,
+        /// The previous source file
+        old_file: Option<String>,
+        /// The new source file
+        new_file: Option<String>,
+    },
+    /// A node's random failure probability or mode changed
+    FailureChanged {
+        /// The node whose failure settings changed
+        node_id: NodeId,
+        /// Failure settings before the change
+        old: FailureSettings,
+        /// Failure settings after the change
+        new: FailureSettings,
+    },
+    /// A consumer's tap was turned on or off
+    TapChanged {
+        /// The consumer
+        node_id: NodeId,
+        /// Whether the tap is on after the change
+        tap: bool,
+    },
+    /// A connection's arrowhead, thickness, dash pattern or color changed
+    ConnectionStyleChanged {
+        /// Source node of the connection
+        from: NodeId,
+        /// Target node of the connection
+        to: NodeId,
+        /// Style before the change
+        old: LineStyle,
+        /// Style after the change
+        new: LineStyle,
+    },
+    /// A producer's phase or jitter changed
+    TimingChanged {
+        /// The producer whose timing changed
+        node_id: NodeId,
+        /// Timing before the change
+        old: ProducerTiming,
+        /// Timing after the change
+        new: ProducerTiming,
+    },
+    /// Nodes or groups were brought to the front or sent to the back
+    ZOrderChanged {
+        /// Nodes with their stacking order before and after the change
+        nodes: Vec<(NodeId, i32, i32)>,
+        /// Groups with their stacking order before and after the change
+        groups: Vec<(GroupId, i32, i32)>,
+    },
+    /// Nodes were pinned or unpinned for the force-directed layout
+    NodesPinned {
+        /// The nodes whose pin changed
+        node_ids: Vec<NodeId>,
+        /// Whether the nodes are pinned after the change
+        pinned: bool,
+    },
+    /// Document parameters or their bindings changed; the node properties
+    /// this set are recorded next to it in a batch
+    ParametersChanged {
+        /// Parameters before the change
+        old: crate::parameters::Parameters,
+        /// Parameters after the change
+        new: crate::parameters::Parameters,
+    },
+    /// Swimlane mode was toggled or lanes were added, removed, renamed or resized
+    SwimlanesChanged {
+        /// Swimlane settings before the change
+        old: Swimlanes,
+        /// Swimlane settings after the change
+        new: Swimlanes,
+    },
+    /// The run settings changed, e.g. the warm-up or measurement window
+    RunConfigChanged {
+        /// Settings before the change
+        old: RunConfig,
+        /// Settings after the change
+        new: RunConfig,
+    },
+    /// Conditional formatting rules were added, removed, reordered or edited
+    FormatRulesChanged {
+        /// Rules before the change
+        old: Vec<FormatRule>,
+        /// Rules after the change
+        new: Vec<FormatRule>,
+    },
+    /// A named selection was saved, replaced or deleted
+    SavedSelectionsChanged {
+        /// Saved selections before the change
+        old: Vec<SavedSelection>,
+        /// Saved selections after the change
+        new: Vec<SavedSelection>,
+    },
+    /// Several actions performed together as one user operation, in the order they happened
+    Batch {
+        /// The individual actions, oldest first
+        actions: Vec<UndoAction>,
+    },
 }
 
 /// Manages undo/redo history for the application.
@@ -101,6 +234,13 @@ pub struct UndoHistory {
     /// Stack of actions that can be redone
     #[serde(skip)]
     redo_stack: Vec<UndoAction>,
+    /// Gesture key and timestamp of the most recent coalescible push, if the
+    /// top of the undo stack may still absorb follow-up edits
+    #[serde(skip)]
+    last_coalesce: Option<(String, f64)>,
+    /// Actions pushed since the edit log last took them
+    #[serde(skip)]
+    unlogged: Vec<UndoAction>,
 }
 
 impl UndoHistory {
@@ -109,6 +249,8 @@ impl UndoHistory {
         Self {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            last_coalesce: None,
+            unlogged: Vec::new(),
         }
     }
 
@@ -120,6 +262,8 @@ impl UndoHistory {
     ///
     /// * `action` - The action to record
     pub fn push_action(&mut self, action: UndoAction) {
+        self.last_coalesce = None;
+        self.unlogged.push(action.clone());
         self.undo_stack.push(action);
         self.redo_stack.clear();
 
@@ -129,6 +273,149 @@ impl UndoHistory {
         }
     }
 
+    /// Adds an action that belongs to a continuous gesture (typing in an editor,
+    /// dragging a value) and may be merged into the previous entry.
+    ///
+    /// If the previous push used the same `key`, happened no more than
+    /// `UNDO_COALESCE_WINDOW_SECS` before `now`, and targets the same node, the
+    /// top entry keeps its original "old" state and takes the new "new" state.
+    /// Otherwise this behaves like [`UndoHistory::push_action`].
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action to record
+    /// * `key` - Identifies the gesture, e.g. the node and property being edited
+    /// * `now` - Current time in seconds (e.g. egui's `input.time`)
+    pub fn push_action_coalesced(&mut self, action: UndoAction, key: &str, now: f64) {
+        let within_window = matches!(
+            &self.last_coalesce,
+            Some((last_key, last_time))
+                if last_key == key && now - *last_time <= UNDO_COALESCE_WINDOW_SECS
+        );
+        if within_window {
+            if let Some(top) = self.undo_stack.last_mut() {
+                if Self::merge_into(top, &action) {
+                    self.redo_stack.clear();
+                    self.last_coalesce = Some((key.to_string(), now));
+                    return;
+                }
+            }
+        }
+        self.push_action(action);
+        self.last_coalesce = Some((key.to_string(), now));
+    }
+
+    /// Ends the current gesture so the next coalescible push starts a new entry.
+    pub fn break_coalescing(&mut self) {
+        self.last_coalesce = None;
+    }
+
+    /// Folds `next` into `top` when both describe the same kind of change on the
+    /// same target. Returns false (leaving `top` untouched) otherwise.
+    fn merge_into(top: &mut UndoAction, next: &UndoAction) -> bool {
+        match (top, next) {
+            (
+                UndoAction::PropertyChanged {
+                    node_id,
+                    new_node_type,
+                    ..
+                },
+                UndoAction::PropertyChanged {
+                    node_id: next_id,
+                    new_node_type: next_type,
+                    ..
+                },
+            ) if node_id == next_id => {
+                *new_node_type = next_type.clone();
+                true
+            }
+            (
+                UndoAction::NodeMoved {
+                    node_id,
+                    new_position,
+                    ..
+                },
+                UndoAction::NodeMoved {
+                    node_id: next_id,
+                    new_position: next_position,
+                    ..
+                },
+            ) if node_id == next_id => {
+                *new_position = *next_position;
+                true
+            }
+            (
+                UndoAction::NodeRenamed {
+                    node_id, new_name, ..
+                },
+                UndoAction::NodeRenamed {
+                    node_id: next_id,
+                    new_name: next_name,
+                    ..
+                },
+            ) if node_id == next_id => {
+                *new_name = next_name.clone();
+                true
+            }
+            (
+                UndoAction::FailureChanged { node_id, new, .. },
+                UndoAction::FailureChanged {
+                    node_id: next_id,
+                    new: next,
+                    ..
+                },
+            ) if node_id == next_id => {
+                *new = *next;
+                true
+            }
+            (
+                UndoAction::ConnectionStyleChanged { from, to, new, .. },
+                UndoAction::ConnectionStyleChanged {
+                    from: next_from,
+                    to: next_to,
+                    new: next,
+                    ..
+                },
+            ) if from == next_from && to == next_to => {
+                *new = *next;
+                true
+            }
+            (
+                UndoAction::TimingChanged { node_id, new, .. },
+                UndoAction::TimingChanged {
+                    node_id: next_id,
+                    new: next,
+                    ..
+                },
+            ) if node_id == next_id => {
+                *new = *next;
+                true
+            }
+            (
+                UndoAction::SwimlanesChanged { new, .. },
+                UndoAction::SwimlanesChanged { new: next, .. },
+            ) => {
+                *new = next.clone();
+                true
+            }
+            (
+                UndoAction::RunConfigChanged { new, .. },
+                UndoAction::RunConfigChanged { new: next, .. },
+            ) => {
+                *new = *next;
+                true
+            }
+            (
+                UndoAction::FormatRulesChanged { new, .. },
+                UndoAction::FormatRulesChanged { new: next, .. },
+            ) => {
+                *new = next.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Returns true if there are actions that can be undone.
     pub fn can_undo(&self) -> bool {
         !self.undo_stack.is_empty()
@@ -139,12 +426,18 @@ impl UndoHistory {
         !self.redo_stack.is_empty()
     }
 
+    /// Returns the number of actions that can be undone.
+    pub fn undo_len(&self) -> usize {
+        self.undo_stack.len()
+    }
+
     /// Pops the most recent action from the undo stack.
     ///
     /// # Returns
     ///
     /// The action to undo, or None if the undo stack is empty
     pub fn pop_undo(&mut self) -> Option<UndoAction> {
+        self.last_coalesce = None;
         self.undo_stack.pop()
     }
 
@@ -167,6 +460,7 @@ impl UndoHistory {
     /// });
     /// ```
     pub fn push_undo(&mut self, action: UndoAction) {
+        self.last_coalesce = None;
         self.undo_stack.push(action);
     }
 
@@ -209,8 +503,17 @@ impl UndoHistory {
         self.redo_stack.pop()
     }
 
+    /// Removes and returns the actions recorded since the last call, oldest
+    /// first. Gestures merged by [`UndoHistory::push_action_coalesced`] are
+    /// listed once.
+    pub fn take_unlogged(&mut self) -> Vec<UndoAction> {
+        std::mem::take(&mut self.unlogged)
+    }
+
     /// Clears all undo and redo history.
     pub fn clear(&mut self) {
+        self.last_coalesce = None;
+        self.unlogged.clear();
         self.undo_stack.clear();
         self.redo_stack.clear();
     }
@@ -298,16 +601,38 @@ impl UndoableFlowchart for Flowchart {
             }
             UndoAction::ConnectionDeleted { connection, index } => {
                 // Restore the connection at its original index
-                if *index <= self.connections.len() {
-                    self.connections.insert(*index, connection.clone());
-                } else {
-                    self.connections.push(connection.clone());
-                }
-                Some(UndoAction::ConnectionCreated {
+                let index = (*index).min(self.connections.len());
+                self.connections.insert(index, connection.clone());
+                Some(UndoAction::ConnectionRestored {
+                    index,
                     from: connection.from,
                     to: connection.to,
                 })
             }
+            UndoAction::ConnectionRestored { index, from, to } => {
+                // Remove the restored connection, falling back to the first
+                // one between the same nodes if the list has changed since
+                let index = match self.connections.get(*index) {
+                    Some(c) if c.from == *from && c.to == *to => *index,
+                    _ => self.connections.iter().position(|c| c.from == *from && c.to == *to)?,
+                };
+                let connection = self.connections.remove(index);
+                Some(UndoAction::ConnectionDeleted { connection, index })
+            }
+            UndoAction::ConnectionMessagesChanged { index, from, to, old, new } => {
+                let connection = self
+                    .connections
+                    .get_mut(*index)
+                    .filter(|c| c.from == *from && c.to == *to)?;
+                connection.messages = old.clone();
+                Some(UndoAction::ConnectionMessagesChanged {
+                    index: *index,
+                    from: *from,
+                    to: *to,
+                    old: new.clone(),
+                    new: old.clone(),
+                })
+            }
             UndoAction::NodeCreated { node_id } => {
                 // Remove the created node and its connections
                 if let Some(node) = self.nodes.remove(node_id) {
@@ -361,12 +686,155 @@ impl UndoableFlowchart for Flowchart {
             }
             UndoAction::GroupCreated { group_id } => {
                 // Remove the created group
-                if let Some(group) = self.groups.remove(group_id) {
-                    Some(UndoAction::GroupDeleted { group })
+                self.groups
+                    .remove(group_id)
+                    .map(|group| UndoAction::GroupDeleted { group })
+            }
+            UndoAction::LaneAssigned {
+                node_id,
+                old_lane,
+                new_lane,
+            } => {
+                if let Some(node) = self.nodes.get_mut(node_id) {
+                    node.lane = *old_lane;
+                    Some(UndoAction::LaneAssigned {
+                        node_id: *node_id,
+                        old_lane: *new_lane,
+                        new_lane: *old_lane,
+                    })
+                } else {
+                    None
+                }
+            }
+            UndoAction::SourceFileChanged {
+                node_id,
+                old_file,
+                new_file,
+            } => {
+                if let Some(node) = self.nodes.get_mut(node_id) {
+                    node.source_file = old_file.clone();
+                    Some(UndoAction::SourceFileChanged {
+                        node_id: *node_id,
+                        old_file: new_file.clone(),
+                        new_file: old_file.clone(),
+                    })
+                } else {
+                    None
+                }
+            }
+            UndoAction::TapChanged { node_id, tap } => {
+                if let Some(node) = self.nodes.get_mut(node_id) {
+                    node.tap = !*tap;
+                    Some(UndoAction::TapChanged {
+                        node_id: *node_id,
+                        tap: !*tap,
+                    })
+                } else {
+                    None
+                }
+            }
+            UndoAction::FailureChanged { node_id, old, new } => {
+                if let Some(node) = self.nodes.get_mut(node_id) {
+                    node.failure = *old;
+                    Some(UndoAction::FailureChanged {
+                        node_id: *node_id,
+                        old: *new,
+                        new: *old,
+                    })
+                } else {
+                    None
+                }
+            }
+            UndoAction::ConnectionStyleChanged { from, to, old, new } => {
+                let connection = self.connections.iter_mut().find(|c| c.from == *from && c.to == *to)?;
+                connection.style = *old;
+                Some(UndoAction::ConnectionStyleChanged {
+                    from: *from,
+                    to: *to,
+                    old: *new,
+                    new: *old,
+                })
+            }
+            UndoAction::TimingChanged { node_id, old, new } => {
+                if let Some(node) = self.nodes.get_mut(node_id) {
+                    node.timing = *old;
+                    Some(UndoAction::TimingChanged {
+                        node_id: *node_id,
+                        old: *new,
+                        new: *old,
+                    })
                 } else {
                     None
                 }
             }
+            UndoAction::ZOrderChanged { nodes, groups } => {
+                for (node_id, before, _) in nodes {
+                    if let Some(node) = self.nodes.get_mut(node_id) {
+                        node.z_order = *before;
+                    }
+                }
+                for (group_id, before, _) in groups {
+                    if let Some(group) = self.groups.get_mut(group_id) {
+                        group.z_order = *before;
+                    }
+                }
+                Some(UndoAction::ZOrderChanged {
+                    nodes: nodes.iter().map(|&(id, before, after)| (id, after, before)).collect(),
+                    groups: groups.iter().map(|&(id, before, after)| (id, after, before)).collect(),
+                })
+            }
+            UndoAction::NodesPinned { node_ids, pinned } => {
+                for node_id in node_ids {
+                    if let Some(node) = self.nodes.get_mut(node_id) {
+                        node.pinned = !*pinned;
+                    }
+                }
+                Some(UndoAction::NodesPinned {
+                    node_ids: node_ids.clone(),
+                    pinned: !*pinned,
+                })
+            }
+            UndoAction::ParametersChanged { old, new } => {
+                self.parameters = old.clone();
+                Some(UndoAction::ParametersChanged {
+                    old: new.clone(),
+                    new: old.clone(),
+                })
+            }
+            UndoAction::SwimlanesChanged { old, new } => {
+                self.swimlanes = old.clone();
+                Some(UndoAction::SwimlanesChanged {
+                    old: new.clone(),
+                    new: old.clone(),
+                })
+            }
+            UndoAction::RunConfigChanged { old, new } => {
+                self.run_config = *old;
+                Some(UndoAction::RunConfigChanged { old: *new, new: *old })
+            }
+            UndoAction::FormatRulesChanged { old, new } => {
+                self.format_rules = old.clone();
+                Some(UndoAction::FormatRulesChanged {
+                    old: new.clone(),
+                    new: old.clone(),
+                })
+            }
+            UndoAction::SavedSelectionsChanged { old, new } => {
+                self.saved_selections = old.clone();
+                Some(UndoAction::SavedSelectionsChanged {
+                    old: new.clone(),
+                    new: old.clone(),
+                })
+            }
+            UndoAction::Batch { actions } => {
+                // Undo newest first; the inverses then read oldest-first for redo
+                let inverses: Vec<UndoAction> = actions
+                    .iter()
+                    .rev()
+                    .filter_map(|a| self.apply_undo(a))
+                    .collect();
+                Some(UndoAction::Batch { actions: inverses })
+            }
         }
     }
 }
@@ -567,6 +1035,10 @@ mod tests {
             (100.0, 200.0),
             NodeType::Consumer {
                 consumption_rate: 5,
+                processing_time: 0,
+                max_concurrency: 1,
+                in_flight: Vec::new(),
+                accept_filter: String::new(),
             },
         );
         let node_id = node.id;
@@ -659,4 +1131,147 @@ mod tests {
             assert_eq!(node_id, id2);
         }
     }
+
+    fn consumer(rate: u32) -> NodeType {
+        NodeType::Consumer {
+            consumption_rate: rate,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_coalesced_property_changes_merge_within_window() {
+        let mut history = UndoHistory::new();
+        let node_id = Uuid::new_v4();
+
+        for (i, t) in [0.0, 0.3, 0.6, 0.9].iter().enumerate() {
+            history.push_action_coalesced(
+                UndoAction::PropertyChanged {
+                    node_id,
+                    old_node_type: consumer(i as u32),
+                    new_node_type: consumer(i as u32 + 1),
+                },
+                "rate",
+                *t,
+            );
+        }
+
+        let action = history.pop_undo().unwrap();
+        assert!(!history.can_undo(), "edits should collapse into one entry");
+        if let UndoAction::PropertyChanged {
+            old_node_type: NodeType::Consumer { consumption_rate: old, .. },
+            new_node_type: NodeType::Consumer { consumption_rate: new, .. },
+            ..
+        } = action
+        {
+            assert_eq!(old, 0);
+            assert_eq!(new, 4);
+        } else {
+            panic!("Expected merged PropertyChanged action");
+        }
+    }
+
+    #[test]
+    fn test_coalescing_splits_on_timeout_key_and_break() {
+        let mut history = UndoHistory::new();
+        let node_id = Uuid::new_v4();
+        let change = |from: u32| UndoAction::PropertyChanged {
+            node_id,
+            old_node_type: consumer(from),
+            new_node_type: consumer(from + 1),
+        };
+
+        history.push_action_coalesced(change(0), "rate", 0.0);
+        // Too late to merge
+        history.push_action_coalesced(change(1), "rate", 5.0);
+        // Different gesture key
+        history.push_action_coalesced(change(2), "other", 5.1);
+        // Explicit gesture boundary
+        history.break_coalescing();
+        history.push_action_coalesced(change(3), "other", 5.2);
+
+        let mut count = 0;
+        while history.pop_undo().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn test_coalescing_does_not_merge_after_undo_or_other_actions() {
+        let mut history = UndoHistory::new();
+        let node_id = Uuid::new_v4();
+        let moved = |x: f32| UndoAction::NodeMoved {
+            node_id,
+            old_position: (x, 0.0),
+            new_position: (x + 1.0, 0.0),
+        };
+
+        history.push_action_coalesced(moved(0.0), "drag", 0.0);
+        history.push_action_coalesced(moved(1.0), "drag", 0.1);
+        history.push_action(UndoAction::NodeCreated {
+            node_id: Uuid::new_v4(),
+        });
+        history.push_action_coalesced(moved(2.0), "drag", 0.2);
+        let undone = history.pop_undo().unwrap();
+        history.push_redo(undone);
+        history.push_action_coalesced(moved(3.0), "drag", 0.3);
+
+        let mut count = 0;
+        while let Some(action) = history.pop_undo() {
+            if count == 2 {
+                if let UndoAction::NodeMoved {
+                    old_position,
+                    new_position,
+                    ..
+                } = action
+                {
+                    assert_eq!(old_position, (0.0, 0.0));
+                    assert_eq!(new_position, (2.0, 0.0));
+                }
+            }
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_batch_undo_and_redo_restore_split_connection() {
+        let mut flowchart = Flowchart::new();
+        let consumer = NodeType::Consumer {
+            consumption_rate: 1,
+            processing_time: 0,
+            max_concurrency: 1,
+            in_flight: Vec::new(),
+            accept_filter: String::new(),
+        };
+        let a = flowchart.add_node(FlowchartNode::new("A".into(), (0.0, 0.0), consumer.clone()));
+        let b = flowchart.add_node(FlowchartNode::new("B".into(), (0.0, 0.0), consumer.clone()));
+        let mid = flowchart.add_node(FlowchartNode::new("M".into(), (0.0, 0.0), consumer));
+        flowchart.add_connection(a, b).unwrap();
+
+        // Split A->B into A->M->B by hand, recording a batch
+        let original = flowchart.connections.remove(0);
+        flowchart.add_connection(a, mid).unwrap();
+        flowchart.add_connection(mid, b).unwrap();
+        let batch = UndoAction::Batch {
+            actions: vec![
+                UndoAction::ConnectionDeleted { connection: original, index: 0 },
+                UndoAction::ConnectionCreated { from: a, to: mid },
+                UndoAction::ConnectionCreated { from: mid, to: b },
+            ],
+        };
+
+        let redo = flowchart.apply_undo(&batch).expect("batch should invert");
+        let pairs: Vec<(NodeId, NodeId)> = flowchart.connections.iter().map(|c| (c.from, c.to)).collect();
+        assert_eq!(pairs, vec![(a, b)]);
+
+        let undo_again = flowchart.apply_undo(&redo).expect("redo should invert");
+        let pairs: Vec<(NodeId, NodeId)> = flowchart.connections.iter().map(|c| (c.from, c.to)).collect();
+        assert_eq!(pairs, vec![(a, mid), (mid, b)]);
+        assert!(matches!(undo_again, UndoAction::Batch { ref actions } if actions.len() == 3));
+    }
 }
@@ -0,0 +1,107 @@
+//! Stepping back through earlier views of the canvas.
+//!
+//! When "Remember view history" is on in Preferences, each pan or zoom is
+//! recorded once the view has been still for a moment, so a whole scroll or
+//! drag becomes one step. Cmd/Ctrl+Shift+Left returns to the view before it
+//! and Cmd/Ctrl+Shift+Right goes forward again. The history is separate from
+//! undo: it never touches the document and isn't saved.
+
+use super::state::{FlowchartApp, ViewPoint};
+use crate::constants::{VIEW_HISTORY_LIMIT, VIEW_HISTORY_SETTLE_SECS};
+use eframe::egui;
+
+impl FlowchartApp {
+    /// The canvas's current pan offset and zoom.
+    fn current_view(&self) -> ViewPoint {
+        ViewPoint {
+            offset: self.canvas.offset,
+            zoom: self.canvas.zoom_factor,
+        }
+    }
+
+    /// Moves the canvas to `view` without recording it.
+    fn show_view(&mut self, view: ViewPoint) {
+        self.canvas.offset = view.offset;
+        self.canvas.zoom_factor = view.zoom;
+        self.view_history.last = Some(view);
+        self.view_history.last_change = None;
+    }
+
+    /// Records the view before a pan or zoom that starts after the view has
+    /// settled. Called once per frame, after the canvas has handled input.
+    pub(crate) fn track_view_changes(&mut self) {
+        if !self.view_history_enabled {
+            if self.view_history != Default::default() {
+                self.view_history = Default::default();
+            }
+            return;
+        }
+        let view = self.current_view();
+        let history = &mut self.view_history;
+        match history.last {
+            Some(last) if last != view => {
+                let settled = history
+                    .last_change
+                    .is_none_or(|time| self.frame_time - time >= VIEW_HISTORY_SETTLE_SECS);
+                if settled {
+                    history.back.push(last);
+                    if history.back.len() > VIEW_HISTORY_LIMIT {
+                        history.back.remove(0);
+                    }
+                    history.forward.clear();
+                }
+                history.last_change = Some(self.frame_time);
+            }
+            _ => {}
+        }
+        history.last = Some(view);
+    }
+
+    /// Whether there is an earlier view to go back to.
+    pub(crate) fn can_view_back(&self) -> bool {
+        !self.view_history.back.is_empty()
+    }
+
+    /// Whether there is a later view to go forward to.
+    pub(crate) fn can_view_forward(&self) -> bool {
+        !self.view_history.forward.is_empty()
+    }
+
+    /// Returns to the view before the last pan or zoom.
+    pub(crate) fn view_back(&mut self) {
+        if let Some(view) = self.view_history.back.pop() {
+            let current = self.current_view();
+            self.view_history.forward.push(current);
+            self.show_view(view);
+        }
+    }
+
+    /// Goes forward to the view last stepped back from.
+    pub(crate) fn view_forward(&mut self) {
+        if let Some(view) = self.view_history.forward.pop() {
+            let current = self.current_view();
+            self.view_history.back.push(current);
+            self.show_view(view);
+        }
+    }
+
+    /// Handles Cmd/Ctrl+Shift+Left (previous view) and Cmd/Ctrl+Shift+Right
+    /// (next view) while the view history is on.
+    pub(crate) fn handle_view_history_keys(&mut self, ctx: &egui::Context) {
+        if !self.view_history_enabled || ctx.wants_keyboard_input() {
+            return;
+        }
+        let (back, forward) = ctx.input(|i| {
+            let held = i.modifiers.command && i.modifiers.shift;
+            (
+                held && i.key_pressed(egui::Key::ArrowLeft),
+                held && i.key_pressed(egui::Key::ArrowRight),
+            )
+        });
+        if back {
+            self.view_back();
+        } else if forward {
+            self.view_forward();
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Stacking order of overlapping nodes and groups.
+//!
+//! Nodes and groups each carry a `z_order`. The canvas and the exports draw
+//! them from the lowest to the highest, and hit testing picks the highest
+//! one under the pointer. "Bring to Front" and "Send to Back" move the
+//! selection above or below everything else in its stack, keeping its own
+//! order, as one undo step. Groups stay behind connections and nodes
+//! whatever their order.
+
+use super::state::FlowchartApp;
+use super::UndoAction;
+use crate::types::*;
+use eframe::egui;
+
+/// New stacking orders that move the `selected` entries of `stack`, listed
+/// from bottom to top, above or below all the others, as (id, before,
+/// after). Empty when they are there already.
+fn restacked<Id: Copy + PartialEq>(stack: &[(Id, i32)], selected: &[Id], to_front: bool) -> Vec<(Id, i32, i32)> {
+    let (moving, staying): (Vec<_>, Vec<_>) = stack.iter().copied().partition(|(id, _)| selected.contains(id));
+    let (Some(&(_, lowest)), Some(&(_, highest))) = (moving.first(), moving.last()) else {
+        return Vec::new();
+    };
+    let (Some(&(_, floor)), Some(&(_, top))) = (staying.first(), staying.last()) else {
+        return Vec::new();
+    };
+    let first = if to_front {
+        if lowest > top {
+            return Vec::new();
+        }
+        top.saturating_add(1)
+    } else {
+        if highest < floor {
+            return Vec::new();
+        }
+        floor.saturating_sub(moving.len() as i32)
+    };
+    moving
+        .iter()
+        .enumerate()
+        .map(|(i, &(id, before))| (id, before, first.saturating_add(i as i32)))
+        .collect()
+}
+
+impl FlowchartApp {
+    /// Moves the selected nodes and group above the others.
+    pub(crate) fn bring_selection_to_front(&mut self) {
+        let groups: Vec<GroupId> = self.interaction.selected_group.into_iter().collect();
+        self.restack(&self.selected_node_ids(), &groups, true);
+    }
+
+    /// Moves the selected nodes and group below the others.
+    pub(crate) fn send_selection_to_back(&mut self) {
+        let groups: Vec<GroupId> = self.interaction.selected_group.into_iter().collect();
+        self.restack(&self.selected_node_ids(), &groups, false);
+    }
+
+    /// Moves `node_ids` and `group_ids` to the front or the back of their
+    /// stacks as one undo step.
+    pub(crate) fn restack(&mut self, node_ids: &[NodeId], group_ids: &[GroupId], to_front: bool) {
+        let node_stack: Vec<(NodeId, i32)> =
+            self.flowchart.nodes_by_z_order().iter().map(|node| (node.id, node.z_order)).collect();
+        let group_stack: Vec<(GroupId, i32)> =
+            self.flowchart.groups_by_z_order().iter().map(|group| (group.id, group.z_order)).collect();
+        let nodes = restacked(&node_stack, node_ids, to_front);
+        let groups = restacked(&group_stack, group_ids, to_front);
+        if nodes.is_empty() && groups.is_empty() {
+            return;
+        }
+
+        for &(id, _, after) in &nodes {
+            if let Some(node) = self.flowchart.nodes.get_mut(&id) {
+                node.z_order = after;
+            }
+        }
+        for &(id, _, after) in &groups {
+            if let Some(group) = self.flowchart.groups.get_mut(&id) {
+                group.z_order = after;
+            }
+        }
+        let action = UndoAction::ZOrderChanged { nodes, groups };
+        self.file.mark_action(&action);
+        self.undo_history.push_action(action);
+    }
+
+    /// Handles Cmd/Ctrl+] (bring to front) and Cmd/Ctrl+[ (send to back).
+    pub(crate) fn handle_z_order_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (front, back) = ctx.input(|i| {
+            (
+                i.modifiers.command && i.key_pressed(egui::Key::CloseBracket),
+                i.modifiers.command && i.key_pressed(egui::Key::OpenBracket),
+            )
+        });
+        if front {
+            self.bring_selection_to_front();
+        } else if back {
+            self.send_selection_to_back();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restacking_keeps_the_moved_entries_in_order() {
+        let stack = [('a', 0), ('b', 0), ('c', 1), ('d', 4)];
+        assert_eq!(restacked(&stack, &['a', 'c'], true), vec![('a', 0, 5), ('c', 1, 6)]);
+        assert_eq!(restacked(&stack, &['d', 'b'], false), vec![('b', 0, -2), ('d', 4, -1)]);
+        // Already on top, or nothing to move past
+        assert!(restacked(&stack, &['d'], true).is_empty());
+        assert!(restacked(&stack, &['a', 'b', 'c', 'd'], false).is_empty());
+        assert!(restacked(&stack, &[], true).is_empty());
+    }
+}